@@ -19,14 +19,18 @@
 use crate::agent::intent::{route_intent, DeterministicIntent};
 use crate::agent::offline_shell::plan_offline_shell_commands;
 use crate::agent::prompt::PromptBuilder;
-use crate::core::cache::{ApiCacheKey, ApiResponseCache};
+use crate::core::cache::{ApiCacheKey, ResponseCache};
+use crate::core::context::ContextBudget;
 use crate::core::error::{HarperError, HarperResult};
+use crate::core::mcp::McpRegistry;
 use crate::core::plan::AuthoringPhase;
 use crate::core::{ApiConfig, Message};
-use crate::memory::storage::CommandLogEntry;
+use crate::memory::storage::{CommandLogEntry, SUMMARY_ROLE};
 use crate::parsing;
 use crate::runtime::config::{ExecPolicyConfig, ExecutionStrategy};
 use crate::runtime::scheduler::{TaskPriority, TaskScheduler};
+use crate::tools::firmware::FirmwareDevices;
+use crate::tools::registry::ToolRegistry;
 use crate::tools::shell::CommandAuditContext;
 use crate::tools::ToolService;
 
@@ -46,11 +50,12 @@ use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::time::{Duration, Instant};
-use turul_mcp_client::{McpClient, ResourceContent};
+use turul_mcp_client::ResourceContent;
 
 #[derive(Debug)]
 enum CommandAction {
     Clear,
+    Compact,
     Exit,
     Audit(AuditParams),
     Strategy(Option<ExecutionStrategy>),
@@ -190,9 +195,11 @@ use std::sync::Arc;
 pub struct ChatService<'a> {
     conn: &'a Connection,
     config: &'a ApiConfig,
-    api_cache: Option<&'a mut ApiResponseCache>,
+    tools: ToolRegistry,
+    api_cache: Option<&'a mut ResponseCache>,
+    cache_bypass: bool,
     #[allow(dead_code)]
-    mcp_client: Option<&'a McpClient>,
+    mcp: Option<&'a McpRegistry>,
     #[allow(dead_code)]
     todos: Vec<String>,
     prompt_id: Option<String>,
@@ -200,10 +207,14 @@ pub struct ChatService<'a> {
     exec_policy: ExecPolicyConfig,
     approver: Option<Arc<dyn UserApproval>>,
     runtime_events: Option<Arc<dyn RuntimeEventSink>>,
+    firmware: Option<Arc<FirmwareDevices>>,
     background_tasks: TaskScheduler<ChatBackgroundTask>,
     todo_reminder_armed: bool,
     last_audit_refresh: Option<Instant>,
     execution_strategy: ExecutionStrategy,
+    pinned_context: HashSet<String>,
+    /// Turns left before auto-compaction is retried after a failure
+    compaction_backoff: u32,
 }
 
 #[derive(Debug, Clone, Serialize, PartialEq, Eq)]
@@ -255,8 +266,8 @@ impl<'a> ChatService<'a> {
     pub fn new(
         conn: &'a Connection,
         config: &'a ApiConfig,
-        mcp_client: Option<&'a McpClient>,
-        api_cache: Option<&'a mut ApiResponseCache>,
+        mcp: Option<&'a McpRegistry>,
+        api_cache: Option<&'a mut ResponseCache>,
         prompt_id: Option<String>,
         custom_commands: HashMap<String, String>,
         exec_policy: ExecPolicyConfig,
@@ -265,18 +276,23 @@ impl<'a> ChatService<'a> {
         Self {
             conn,
             config,
-            mcp_client,
+            tools: ToolRegistry::default(),
+            mcp,
             api_cache,
+            cache_bypass: false,
             todos: Vec::new(),
             prompt_id,
             custom_commands,
             exec_policy,
             approver: None,
             runtime_events: None,
+            firmware: None,
             background_tasks: TaskScheduler::new(),
             todo_reminder_armed: false,
             last_audit_refresh: None,
             execution_strategy,
+            pinned_context: HashSet::new(),
+            compaction_backoff: 0,
         }
     }
 
@@ -291,6 +307,31 @@ impl<'a> ChatService<'a> {
         self
     }
 
+    /// Offer the model the tools built from `[tools]`
+    pub fn with_tools(mut self, tools: ToolRegistry) -> Self {
+        self.tools = tools;
+        self
+    }
+
+    /// Share the firmware devices built from `[firmware]` with tool calls
+    pub fn with_firmware(mut self, firmware: Arc<FirmwareDevices>) -> Self {
+        self.firmware = Some(firmware);
+        self
+    }
+
+    /// Release what the session still holds, such as firmware connections
+    pub async fn end_session(&self, session_id: &str) {
+        if let Some(firmware) = &self.firmware {
+            firmware.release_session(Some(session_id)).await;
+        }
+    }
+
+    /// Skip the response cache for requests made by this service
+    pub fn with_cache_bypass(mut self, bypass: bool) -> Self {
+        self.cache_bypass = bypass;
+        self
+    }
+
     pub fn debug_turn_summary(&self, history: &[Message], user_msg: &str) -> ChatTurnDebugSummary {
         let deterministic_intent = route_intent(user_msg).or_else(|| {
             Self::infer_followup_write_file_intent(history, user_msg)
@@ -338,8 +379,10 @@ impl<'a> ChatService<'a> {
         Self {
             conn,
             config,
-            mcp_client: None,
+            tools: ToolRegistry::default(),
+            mcp: None,
             api_cache: None,
+            cache_bypass: false,
             todos: Vec::new(),
             prompt_id: None,
             custom_commands: HashMap::new(),
@@ -347,6 +390,8 @@ impl<'a> ChatService<'a> {
                 approval_profile: None,
                 allowed_commands: None,
                 blocked_commands: None,
+                allow_command_chains: None,
+                allow_redirects_and_substitutions: None,
                 sandbox_profile: None,
                 sandbox: None,
                 retry_max_attempts: None,
@@ -356,10 +401,13 @@ impl<'a> ChatService<'a> {
             },
             approver: None,
             runtime_events: None,
+            firmware: None,
             background_tasks: TaskScheduler::new(),
             todo_reminder_armed: false,
             last_audit_refresh: None,
             execution_strategy: ExecutionStrategy::Auto,
+            pinned_context: HashSet::new(),
+            compaction_backoff: 0,
         }
     }
 
@@ -373,10 +421,7 @@ impl<'a> ChatService<'a> {
 
         let system_prompt = self.build_system_prompt(web_search_enabled).await;
 
-        let history = vec![Message {
-            role: "system".to_string(),
-            content: system_prompt,
-        }];
+        let history = vec![Message::text("system", system_prompt)];
 
         Ok((history, session_id))
     }
@@ -401,6 +446,7 @@ impl<'a> ChatService<'a> {
                         history.clear();
                         "Chat history cleared.".to_string()
                     }
+                    CommandAction::Compact => self.compact_history(history, session_id).await?,
                     CommandAction::Audit(params) => {
                         self.format_command_audit(session_id, &params)?
                     }
@@ -423,7 +469,7 @@ impl<'a> ChatService<'a> {
                             .process_message(history, web_search_enabled, session_id)
                             .await?;
                         self.add_assistant_message(history, session_id, &response)?;
-                        self.trim_history(history);
+                        self.trim_history(history, session_id).await;
                         return Ok(());
                     }
                     CommandAction::Unknown(s) => s,
@@ -447,21 +493,21 @@ impl<'a> ChatService<'a> {
             .await?
         {
             self.add_assistant_message(history, session_id, &local_response)?;
-            self.trim_history(history);
+            self.trim_history(history, session_id).await;
             return Ok(());
         }
         let response = self
             .process_message(history, web_search_enabled, session_id)
             .await?;
         self.add_assistant_message(history, session_id, &response)?;
-        self.trim_history(history);
+        self.trim_history(history, session_id).await;
         self.poll_background_tasks(session_id);
         Ok(())
     }
 
     /// Preprocess @mcp_resource references into content
     pub(crate) async fn preprocess_mcp_resource_references(&self, user_msg: &str) -> String {
-        if self.mcp_client.is_none() {
+        if self.mcp.is_none() {
             return user_msg.to_string();
         }
 
@@ -510,11 +556,11 @@ impl<'a> ChatService<'a> {
 
     /// Read an MCP resource by URI
     async fn read_mcp_resource(&self, uri: &str) -> Result<String, HarperError> {
-        let Some(client) = self.mcp_client else {
+        let Some(registry) = self.mcp else {
             return Err(HarperError::Config("MCP client not available".to_string()));
         };
 
-        match client.read_resource(uri).await {
+        match registry.read_resource(uri).await {
             Ok(contents) => {
                 let mut content_parts = Vec::new();
                 for item in &contents {
@@ -591,6 +637,8 @@ impl<'a> ChatService<'a> {
         help_text.push_str("  Exit the session.\n\n");
         help_text.push_str("/clear\n");
         help_text.push_str("  Clear chat history.\n\n");
+        help_text.push_str("/compact\n");
+        help_text.push_str("  Summarize older messages to free up context.\n\n");
         help_text.push_str("/audit [limit]\n");
         help_text.push_str("  Show recent command executions.\n\n");
         help_text.push_str("/strategy [auto|grounded|deterministic|model]\n");
@@ -629,6 +677,7 @@ impl<'a> ChatService<'a> {
         match name {
             "exit" => CommandAction::Exit,
             "clear" => CommandAction::Clear,
+            "compact" => CommandAction::Compact,
             "strategy" => match args {
                 Some(value) => match Self::parse_execution_strategy(value) {
                     Some(strategy) => CommandAction::Strategy(Some(strategy)),
@@ -659,7 +708,7 @@ impl<'a> ChatService<'a> {
     /// Build system prompt
     pub async fn build_system_prompt(&self, web_search_enabled: bool) -> String {
         let prompt_builder =
-            PromptBuilder::new(self.config, self.prompt_id.clone(), self.mcp_client);
+            PromptBuilder::new(self.config, &self.tools, self.prompt_id.clone(), self.mcp);
         prompt_builder.build_system_prompt(web_search_enabled).await
     }
 
@@ -773,12 +822,26 @@ impl<'a> ChatService<'a> {
         }
     }
 
-    /// Run the chat loop
+    /// Run the chat loop, releasing the session however it ends
     async fn run_chat_loop(
         &mut self,
         session_id: &str,
         history: &mut Vec<Message>,
         web_search_enabled: bool,
+    ) -> Result<(), HarperError> {
+        let result = self
+            .chat_turns(session_id, history, web_search_enabled)
+            .await;
+        self.end_session(session_id).await;
+        result
+    }
+
+    /// Read and answer user input until the user leaves
+    async fn chat_turns(
+        &mut self,
+        session_id: &str,
+        history: &mut Vec<Message>,
+        web_search_enabled: bool,
     ) -> Result<(), HarperError> {
         loop {
             self.poll_background_tasks(session_id);
@@ -835,6 +898,11 @@ impl<'a> ChatService<'a> {
                             println!("{}", "Chat history cleared.".bold().green());
                             continue;
                         }
+                        CommandAction::Compact => {
+                            let report = self.compact_history(history, session_id).await?;
+                            println!("{}", report.bold().green());
+                            continue;
+                        }
                         CommandAction::Audit(params) => {
                             let report = self.format_command_audit(session_id, &params)?;
                             for line in report.lines() {
@@ -871,7 +939,7 @@ impl<'a> ChatService<'a> {
                                 })?;
                             self.display_response(&response);
                             self.add_assistant_message(history, session_id, &response)?;
-                            self.trim_history(history);
+                            self.trim_history(history, session_id).await;
                             continue;
                         }
                         CommandAction::Unknown(s) => {
@@ -894,7 +962,7 @@ impl<'a> ChatService<'a> {
                 })?;
             self.display_response(&response);
             self.add_assistant_message(history, session_id, &response)?;
-            self.trim_history(history);
+            self.trim_history(history, session_id).await;
         }
         Ok(())
     }
@@ -938,11 +1006,10 @@ impl<'a> ChatService<'a> {
             .timeout(std::time::Duration::from_secs(90))
             .build()?;
         let mut history_for_llm = history.clone();
+        self.pinned_context.clear();
         if let Some(plan_prompt) = self.plan_prompt_for_request(history, session_id)? {
-            history_for_llm.push(Message {
-                role: "system".to_string(),
-                content: plan_prompt,
-            });
+            self.pinned_context.insert(plan_prompt.clone());
+            history_for_llm.push(Message::text("system", plan_prompt));
         }
         let last_user_msg = history
             .iter()
@@ -996,10 +1063,10 @@ impl<'a> ChatService<'a> {
         if let Some(authoring_request_context) =
             self.authoring_prompt_for_request(&last_user_msg).await?
         {
-            history_for_llm.push(Message {
-                role: "system".to_string(),
-                content: authoring_request_context.prompt.clone(),
-            });
+            history_for_llm.push(Message::text(
+                "system",
+                authoring_request_context.prompt.clone(),
+            ));
             authoring_context = Some(authoring_request_context);
         }
 
@@ -1063,9 +1130,17 @@ impl<'a> ChatService<'a> {
             },
             Some(task_mode.model_activity_label().to_string()),
         );
-        let mut response = match self.call_llm(&client, &history_for_llm).await {
+        let mut response = match self.call_llm(&client, &history_for_llm, session_id).await {
             Ok(response) => response,
-            Err(HarperError::Api(_)) | Err(HarperError::Command(_)) => {
+            Err(
+                err @ (HarperError::Api(_) | HarperError::Command(_) | HarperError::Unavailable(_)),
+            ) => {
+                if let Some(output) = self
+                    .try_offline_shell_fallback(&err, &last_user_msg, session_id)
+                    .await?
+                {
+                    return Ok(output);
+                }
                 if matches!(task_mode, TaskMode::RespondOnly) {
                     return Ok(Self::model_backend_unavailable_reply());
                 }
@@ -1183,20 +1258,27 @@ impl<'a> ChatService<'a> {
                 Self::forced_tool_retry_target(&last_user_msg, &clean_response, forced_tool_retry)
             {
                 forced_tool_retry = true;
-                history_for_llm.push(Message {
-                    role: "system".to_string(),
-                    content: format!(
+                history_for_llm.push(Message::text("system", format!(
                         "The user request requires an actual tool call. Do not answer with prose. Respond now with exactly one JSON tool call using `{}`.",
                         required_tool
-                    ),
-                });
+                    )));
                 self.emit_activity_update(
                     session_id,
                     Some(task_mode.model_activity_label().to_string()),
                 );
-                response = match self.call_llm(&client, &history_for_llm).await {
+                response = match self.call_llm(&client, &history_for_llm, session_id).await {
                     Ok(response) => response,
-                    Err(HarperError::Api(_)) | Err(HarperError::Command(_)) => {
+                    Err(
+                        err @ (HarperError::Api(_)
+                        | HarperError::Command(_)
+                        | HarperError::Unavailable(_)),
+                    ) => {
+                        if let Some(output) = self
+                            .try_offline_shell_fallback(&err, &last_user_msg, session_id)
+                            .await?
+                        {
+                            return Ok(output);
+                        }
                         if matches!(task_mode, TaskMode::RespondOnly) {
                             return Ok(Self::model_backend_unavailable_reply());
                         }
@@ -1241,15 +1323,12 @@ impl<'a> ChatService<'a> {
                 has_structured_authoring_plan,
                 &inspected_paths,
             ) {
-                history_for_llm.push(Message {
-                    role: "system".to_string(),
-                    content: authoring_retry_prompt,
-                });
+                history_for_llm.push(Message::text("system", authoring_retry_prompt));
                 self.emit_activity_update(
                     session_id,
                     Some(task_mode.model_activity_label().to_string()),
                 );
-                response = self.call_llm(&client, &history_for_llm).await?;
+                response = self.call_llm(&client, &history_for_llm, session_id).await?;
                 continue;
             }
             if let Some(agents_prompt) = self.agents_guidance_for_tool_call(
@@ -1258,15 +1337,13 @@ impl<'a> ChatService<'a> {
                 &injected_agents_guidance,
             )? {
                 injected_agents_guidance.insert(dedupe_key.clone());
-                history_for_llm.push(Message {
-                    role: "system".to_string(),
-                    content: agents_prompt,
-                });
+                self.pinned_context.insert(agents_prompt.clone());
+                history_for_llm.push(Message::text("system", agents_prompt));
                 self.emit_activity_update(
                     session_id,
                     Some(task_mode.model_activity_label().to_string()),
                 );
-                response = self.call_llm(&client, &history_for_llm).await?;
+                response = self.call_llm(&client, &history_for_llm, session_id).await?;
                 continue;
             }
             if executed_tool_calls.contains(&dedupe_key) {
@@ -1301,8 +1378,9 @@ impl<'a> ChatService<'a> {
                 let mut tool_service = ToolService::new(
                     self.conn,
                     self.config,
+                    &self.tools,
                     &self.exec_policy,
-                    self.mcp_client,
+                    self.mcp,
                     Some(session_id),
                 );
                 if let Some(approver) = &self.approver {
@@ -1311,6 +1389,9 @@ impl<'a> ChatService<'a> {
                 if let Some(runtime_events) = &self.runtime_events {
                     tool_service = tool_service.with_runtime_events(runtime_events.clone());
                 }
+                if let Some(firmware) = &self.firmware {
+                    tool_service = tool_service.with_firmware(firmware.clone());
+                }
                 tool_service
                     .handle_tool_use(
                         &client,
@@ -1395,10 +1476,7 @@ impl<'a> ChatService<'a> {
                 }
                 executed_tool_calls.insert(dedupe_key);
                 last_tool_content = Some(tool_content.clone());
-                let tool_message = Message {
-                    role: "system".to_string(),
-                    content: tool_content,
-                };
+                let tool_message = Message::text("system", tool_content);
                 history.push(tool_message.clone());
                 history_for_llm.push(tool_message);
                 response = tool_result;
@@ -2726,20 +2804,19 @@ impl<'a> ChatService<'a> {
             ));
         }
 
-        let tool_message = Message {
-            role: "system".to_string(),
-            content: tool_content.to_string(),
-        };
+        let tool_message = Message::text("system", tool_content);
         history.push(tool_message.clone());
         history_for_llm.push(tool_message);
-        history_for_llm.push(Message {
-            role: "system".to_string(),
-            content: Self::deterministic_summary_instruction(tool_name).to_string(),
-        });
+        history_for_llm.push(Message::text(
+            "system",
+            Self::deterministic_summary_instruction(tool_name).to_string(),
+        ));
         self.emit_activity_update(session_id, Some("summarizing result".to_string()));
-        let response = match self.call_llm(client, history_for_llm).await {
+        let response = match self.call_llm(client, history_for_llm, session_id).await {
             Ok(response) => response,
-            Err(HarperError::Api(_)) | Err(HarperError::Command(_)) => {
+            Err(HarperError::Api(_))
+            | Err(HarperError::Command(_))
+            | Err(HarperError::Unavailable(_)) => {
                 return Ok(Self::compact_deterministic_fallback(
                     tool_name,
                     tool_content,
@@ -3150,6 +3227,44 @@ impl<'a> ChatService<'a> {
         if self.execution_strategy != ExecutionStrategy::Deterministic {
             return Ok(None);
         }
+        self.run_offline_shell_commands(user_msg, session_id, "offline_shell_nlu")
+            .await
+    }
+
+    /// Last link of the provider fallback chain: answer the request with
+    /// `agent::offline_shell` once every configured provider has failed.
+    /// Fatal errors such as a rejected API key are left to the caller
+    async fn try_offline_shell_fallback(
+        &mut self,
+        error: &HarperError,
+        user_msg: &str,
+        session_id: &str,
+    ) -> Result<Option<String>, HarperError> {
+        if !self.config.fallback.offline_shell || !matches!(error, HarperError::Unavailable(_)) {
+            return Ok(None);
+        }
+        self.emit_activity_update(
+            session_id,
+            Some("falling back to offline shell".to_string()),
+        );
+        let Some(output) = self
+            .run_offline_shell_commands(user_msg, session_id, "offline_shell_fallback")
+            .await?
+        else {
+            return Ok(None);
+        };
+        Ok(Some(format!(
+            "Model providers are unavailable, so this was answered offline.\n\n{}",
+            output
+        )))
+    }
+
+    async fn run_offline_shell_commands(
+        &mut self,
+        user_msg: &str,
+        session_id: &str,
+        source: &'static str,
+    ) -> Result<Option<String>, HarperError> {
         let commands = plan_offline_shell_commands(user_msg)
             .into_iter()
             .map(|command| Self::normalize_run_command_candidate(&command))
@@ -3167,7 +3282,7 @@ impl<'a> ChatService<'a> {
         let audit_ctx = CommandAuditContext {
             conn: self.conn,
             session_id: Some(session_id),
-            source: "offline_shell_nlu",
+            source,
         };
         let mut sections = Vec::new();
         for command in commands {
@@ -3371,36 +3486,58 @@ impl<'a> ChatService<'a> {
         crate::memory::storage::load_command_logs_for_session(self.conn, session_id, limit)
     }
 
-    /// Call LLM
+    /// Call LLM, streaming the reply to runtime listeners when attached
     async fn call_llm(
         &mut self,
         client: &Client,
         history: &[Message],
+        session_id: &str,
     ) -> Result<String, HarperError> {
-        // Check cache
-        if let Some(cache) = &self.api_cache {
-            let cache_key = ApiCacheKey::new(
-                &format!("{}", self.config.provider),
-                &self.config.model_name,
-                history,
-            );
+        let fitted: Vec<Message> = self
+            .fit_context(history)
+            .into_iter()
+            .map(|message| {
+                if message.role == SUMMARY_ROLE {
+                    Message::text(
+                        "system",
+                        format!("Summary of the earlier conversation:\n{}", message.content),
+                    )
+                } else {
+                    message
+                }
+            })
+            .collect();
+        let history = fitted.as_slice();
+
+        let cache_key = ApiCacheKey::new(self.config, &self.tools.effective_tools(), history);
 
-            if let Some(cached_response) = cache.get(&cache_key) {
-                return Ok(cached_response.clone());
+        // Check cache
+        if !self.cache_bypass {
+            if let Some(cache) = self.api_cache.as_deref_mut() {
+                if let Ok(Some(cached_response)) = cache.get(self.conn, &cache_key) {
+                    return Ok(cached_response);
+                }
             }
         }
 
         // Make API call
-        let response = crate::core::llm_client::call_llm(client, self.config, history).await?;
+        let reply = crate::core::llm_client::call_llm_with_events(
+            client,
+            self.config,
+            &self.tools,
+            history,
+            self.runtime_events.clone(),
+            Some(session_id),
+        )
+        .await?;
+        let _ = crate::memory::storage::insert_token_usage(self.conn, session_id, &reply);
+        let response = reply.content;
 
         // Cache response
-        if let Some(cache) = &mut self.api_cache {
-            let cache_key = ApiCacheKey::new(
-                &format!("{}", self.config.provider),
-                &self.config.model_name,
-                history,
-            );
-            cache.insert(cache_key, response.clone());
+        if !self.cache_bypass {
+            if let Some(cache) = self.api_cache.as_deref_mut() {
+                let _ = cache.insert(self.conn, cache_key, &response);
+            }
         }
 
         Ok(response)
@@ -3425,10 +3562,7 @@ impl<'a> ChatService<'a> {
     ) -> Result<(), HarperError> {
         // Ensure session exists
         self.save_session(session_id)?;
-        history.push(Message {
-            role: "user".to_string(),
-            content: content.to_string(),
-        });
+        history.push(Message::text("user", content));
         let user_message_count = history
             .iter()
             .filter(|message| message.role == "user")
@@ -3447,20 +3581,122 @@ impl<'a> ChatService<'a> {
         session_id: &str,
         content: &str,
     ) -> Result<(), HarperError> {
-        history.push(Message {
-            role: "assistant".to_string(),
-            content: content.to_string(),
-        });
+        history.push(Message::text("assistant", content));
         crate::memory::storage::save_message(self.conn, session_id, "assistant", content)
     }
 
-    /// Trim history
-    fn trim_history(&self, history: &mut Vec<Message>) {
-        const MAX_HISTORY: usize = 50;
-        if history.len() > MAX_HISTORY {
-            let to_remove_count = history.len() - MAX_HISTORY;
-            history.drain(1..1 + to_remove_count);
+    /// Keep history within the model's context budget
+    ///
+    /// Older messages are compacted into a summary first; whatever still
+    /// does not fit is trimmed. After a failed compaction the service only
+    /// trims for a few turns, so a broken summarizer is not retried on every
+    /// turn but a transient error does not turn compaction off for good.
+    async fn trim_history(&mut self, history: &mut Vec<Message>, session_id: &str) {
+        const COMPACTION_RETRY_TURNS: u32 = 3;
+
+        let budget = ContextBudget::for_config(self.config);
+        if self.compaction_backoff > 0 {
+            self.compaction_backoff -= 1;
+        } else if budget.estimator.estimate_messages(history) > budget.max_tokens {
+            if let Err(err) = self.compact_history(history, session_id).await {
+                log::warn!("conversation compaction failed, trimming instead: {}", err);
+                self.compaction_backoff = COMPACTION_RETRY_TURNS;
+            }
         }
+        *history = self.fit_context(history);
+    }
+
+    /// Fit messages to the context budget, keeping the system prompt, the
+    /// latest user message, AGENTS.md guidance and plan prompts pinned
+    fn fit_context(&self, messages: &[Message]) -> Vec<Message> {
+        let latest_user = messages.iter().rposition(|message| message.role == "user");
+        ContextBudget::for_config(self.config).fit(messages, |index, message| {
+            Some(index) == latest_user
+                || message.role == SUMMARY_ROLE
+                || self.pinned_context.contains(&message.content)
+        })
+    }
+
+    /// Summarize all but the most recent messages of a session
+    ///
+    /// The summary is persisted with the summary role and replaces the
+    /// compacted span in `history`; the originals stay in the database.
+    /// Tool output in the span is summarized with it, while tool output
+    /// among the recent messages and pinned guidance stay in `history`.
+    async fn compact_history(
+        &mut self,
+        history: &mut Vec<Message>,
+        session_id: &str,
+    ) -> Result<String, HarperError> {
+        const KEEP_RECENT: usize = 4;
+        const MAX_MESSAGE_CHARS: usize = 4000;
+        let is_persisted = |message: &Message| {
+            matches!(message.role.as_str(), "user" | "assistant" | SUMMARY_ROLE)
+        };
+
+        let active = crate::memory::storage::load_context_history(self.conn, session_id)?;
+        if active.len() <= KEEP_RECENT + 1 {
+            return Ok("Nothing to compact yet.".to_string());
+        }
+        let span_len = active.len() - KEEP_RECENT;
+
+        // The in-memory span ends where the most recent persisted messages begin
+        let leading_system = history
+            .iter()
+            .take_while(|message| message.role == "system")
+            .count();
+        let mut recent = 0;
+        let mut cut = history.len();
+        while cut > leading_system && recent < KEEP_RECENT {
+            cut -= 1;
+            if is_persisted(&history[cut]) {
+                recent += 1;
+            }
+        }
+        if recent < KEEP_RECENT {
+            cut = leading_system;
+        }
+        let (pinned, in_memory): (Vec<&Message>, Vec<&Message>) = history[leading_system..cut]
+            .iter()
+            .partition(|message| self.pinned_context.contains(&message.content));
+        // Messages trimmed from memory earlier are still part of the stored span
+        let trimmed = span_len.saturating_sub(in_memory.iter().filter(|m| is_persisted(m)).count());
+
+        let transcript = active[..trimmed]
+            .iter()
+            .chain(in_memory)
+            .map(|message| {
+                let content: String = message.content.chars().take(MAX_MESSAGE_CHARS).collect();
+                format!("{}: {}", message.role, content)
+            })
+            .collect::<Vec<_>>()
+            .join("\n\n");
+        let request = vec![
+            Message::text("system", "Summarize this conversation so it can replace the original messages. Keep the user's goals, decisions, file paths, commands and their outcomes, and any open questions. Reply with the summary only."),
+            Message::text("user", transcript),
+        ];
+
+        self.emit_activity_update(session_id, Some("compacting conversation".to_string()));
+        let client = Client::builder()
+            .timeout(std::time::Duration::from_secs(90))
+            .build()?;
+        let summary =
+            crate::core::llm_client::call_llm(&client, self.config, &self.tools, &request).await;
+        self.emit_activity_update(session_id, None);
+        let summary = summary?;
+        let summary = summary.trim();
+        crate::memory::storage::compact_messages(self.conn, session_id, span_len, summary)?;
+
+        let pinned: Vec<Message> = pinned.into_iter().cloned().collect();
+        let recent = history.split_off(cut);
+        history.truncate(leading_system);
+        history.push(Message::text(SUMMARY_ROLE, summary.to_string()));
+        history.extend(pinned);
+        history.extend(recent);
+        Ok(format!(
+            "Compacted {} earlier messages into a summary. The originals remain in the session history.",
+            span_len
+        ))
     }
 
     /// Save session
@@ -3833,7 +4069,253 @@ mod tests {
             api_key: "test-key".to_string(),
             base_url: "https://api.openai.com/v1/chat/completions".to_string(),
             model_name: "gpt-5.5".to_string(),
+            headers: Default::default(),
+            parameters: Default::default(),
+            fallback: Default::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn offline_shell_fallback_answers_when_enabled() {
+        let conn = Connection::open_in_memory().expect("in-memory db");
+        crate::memory::storage::init_db(&conn).expect("init db");
+        let mut config = test_config();
+        let mut chat = ChatService::new(
+            &conn,
+            &config,
+            None,
+            None,
+            None,
+            HashMap::new(),
+            ExecPolicyConfig::default(),
+        );
+        let unavailable = HarperError::Unavailable("HTTP 503: overloaded".to_string());
+        assert!(chat
+            .try_offline_shell_fallback(&unavailable, "what directory am I in?", "offline-session")
+            .await
+            .expect("disabled fallback")
+            .is_none());
+
+        config.fallback.offline_shell = true;
+        let mut chat = ChatService::new(
+            &conn,
+            &config,
+            None,
+            None,
+            None,
+            HashMap::new(),
+            ExecPolicyConfig {
+                approval_profile: Some(crate::runtime::config::ApprovalProfile::AllowAll),
+                ..ExecPolicyConfig::default()
+            },
+        );
+        assert!(chat
+            .try_offline_shell_fallback(
+                &HarperError::Api("HTTP 401: bad key".to_string()),
+                "what directory am I in?",
+                "offline-session"
+            )
+            .await
+            .expect("fatal error")
+            .is_none());
+        let output = chat
+            .try_offline_shell_fallback(&unavailable, "what directory am I in?", "offline-session")
+            .await
+            .expect("offline fallback")
+            .expect("offline answer");
+        assert!(output.starts_with("Model providers are unavailable"));
+        assert!(output.contains("$ pwd"));
+
+        assert!(chat
+            .try_offline_shell_fallback(&unavailable, "write a poem about rust", "offline-session")
+            .await
+            .expect("unplannable request")
+            .is_none());
+    }
+
+    async fn spawn_summary_server() -> String {
+        let app = axum::Router::new().fallback(|| async {
+            axum::Json(serde_json::json!({
+                "choices": [{"message": {"content": "User set up the repo and fixed the build."}}]
+            }))
+        });
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("bind mock server");
+        let addr = listener.local_addr().expect("mock server addr");
+        tokio::spawn(async move {
+            let _ = axum::serve(listener, app).await;
+        });
+        format!("http://{}/v1/chat/completions", addr)
+    }
+
+    fn seed_turns(chat: &ChatService<'_>, session_id: &str, turns: usize) -> Vec<Message> {
+        let mut history = vec![Message::text("system", "system prompt")];
+        for turn in 0..turns {
+            let question = format!("question {} {}", turn, "detail ".repeat(40));
+            chat.add_user_message(&mut history, session_id, &question)
+                .expect("user message");
+            chat.add_assistant_message(&mut history, session_id, &format!("answer {}", turn))
+                .expect("assistant message");
         }
+        history
+    }
+
+    #[tokio::test]
+    async fn compact_command_persists_summary_and_keeps_originals() {
+        let conn = Connection::open_in_memory().expect("in-memory db");
+        crate::memory::storage::init_db(&conn).expect("init db");
+        let mut config = test_config();
+        config.base_url = spawn_summary_server().await;
+        let mut chat = ChatService::new_test(&conn, &config);
+        let mut history = seed_turns(&chat, "compact-session", 4);
+
+        let report = chat
+            .compact_history(&mut history, "compact-session")
+            .await
+            .expect("compact");
+
+        assert!(report.starts_with("Compacted 4 earlier messages"));
+        let roles: Vec<&str> = history.iter().map(|m| m.role.as_str()).collect();
+        assert_eq!(
+            roles,
+            vec![
+                "system",
+                SUMMARY_ROLE,
+                "user",
+                "assistant",
+                "user",
+                "assistant"
+            ]
+        );
+        assert_eq!(
+            history[1].content,
+            "User set up the repo and fixed the build."
+        );
+        let originals = crate::memory::session_service::SessionService::new(&conn)
+            .view_session_data("compact-session")
+            .expect("view session");
+        assert_eq!(originals.len(), 9);
+        assert!(originals[0].content.starts_with("question 0"));
+    }
+
+    #[tokio::test]
+    async fn compaction_summarizes_old_tool_output_and_keeps_recent_tool_output() {
+        let conn = Connection::open_in_memory().expect("in-memory db");
+        crate::memory::storage::init_db(&conn).expect("init db");
+        let requests = Arc::new(std::sync::Mutex::new(Vec::<String>::new()));
+        let recorded = requests.clone();
+        let app = axum::Router::new().fallback(move |body: String| {
+            let recorded = recorded.clone();
+            async move {
+                recorded.lock().unwrap().push(body);
+                axum::Json(serde_json::json!({
+                    "choices": [{"message": {"content": "User set up the repo and fixed the build."}}]
+                }))
+            }
+        });
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("bind mock server");
+        let addr = listener.local_addr().expect("mock server addr");
+        tokio::spawn(async move {
+            let _ = axum::serve(listener, app).await;
+        });
+        let mut config = test_config();
+        config.base_url = format!("http://{}/v1/chat/completions", addr);
+        let mut chat = ChatService::new_test(&conn, &config);
+        let mut history = seed_turns(&chat, "tool-compact", 4);
+        history.insert(2, Message::text("system", "old tool output"));
+        let last = history.len() - 1;
+        history.insert(last, Message::text("system", "recent tool output"));
+
+        chat.compact_history(&mut history, "tool-compact")
+            .await
+            .expect("compact");
+
+        let contents: Vec<&str> = history.iter().map(|m| m.content.as_str()).collect();
+        assert_eq!(contents[1], "User set up the repo and fixed the build.");
+        assert!(contents.contains(&"recent tool output"));
+        assert!(!contents.contains(&"old tool output"));
+        assert_eq!(contents.last(), Some(&"answer 3"));
+        assert!(requests.lock().unwrap()[0].contains("old tool output"));
+    }
+
+    #[tokio::test]
+    async fn trim_history_compacts_when_budget_is_exceeded() {
+        let conn = Connection::open_in_memory().expect("in-memory db");
+        crate::memory::storage::init_db(&conn).expect("init db");
+        let mut config = test_config();
+        config.base_url = spawn_summary_server().await;
+        config.parameters.context_window = Some(600);
+        config.parameters.max_tokens = Some(100);
+        let mut chat = ChatService::new_test(&conn, &config);
+        let mut history = seed_turns(&chat, "auto-compact", 6);
+
+        chat.trim_history(&mut history, "auto-compact").await;
+
+        assert_eq!(history[1].role, SUMMARY_ROLE);
+        assert!(history
+            .last()
+            .is_some_and(|message| message.content == "answer 5"));
+    }
+
+    #[tokio::test]
+    async fn trim_history_backs_off_compacting_after_a_failure() {
+        let conn = Connection::open_in_memory().expect("in-memory db");
+        crate::memory::storage::init_db(&conn).expect("init db");
+        let mut config = test_config();
+        config.base_url = "http://127.0.0.1:9/v1/chat/completions".to_string();
+        config.parameters.context_window = Some(600);
+        config.parameters.max_tokens = Some(100);
+        let mut chat = ChatService::new_test(&conn, &config);
+        let mut history = seed_turns(&chat, "failed-compact", 6);
+
+        chat.trim_history(&mut history, "failed-compact").await;
+
+        assert_eq!(chat.compaction_backoff, 3);
+        assert!(history.iter().all(|message| message.role != SUMMARY_ROLE));
+        assert!(history
+            .last()
+            .is_some_and(|message| message.content == "answer 5"));
+
+        // A few turns later compaction is tried again
+        for _ in 0..3 {
+            chat.trim_history(&mut history, "failed-compact").await;
+        }
+        assert_eq!(chat.compaction_backoff, 0);
+        chat.add_user_message(&mut history, "failed-compact", &"more ".repeat(200))
+            .expect("user message");
+        chat.trim_history(&mut history, "failed-compact").await;
+        assert_eq!(chat.compaction_backoff, 3);
+    }
+
+    #[test]
+    fn fit_context_keeps_pinned_guidance_and_latest_request() {
+        let conn = Connection::open_in_memory().expect("in-memory db");
+        let mut config = test_config();
+        config.parameters.context_window = Some(400);
+        config.parameters.max_tokens = Some(100);
+        let mut chat = ChatService::new_test(&conn, &config);
+        let guidance = "Before executing this path-targeting tool, apply these scoped AGENTS.md instructions for the affected files:\n- keep diffs small".to_string();
+        chat.pinned_context.insert(guidance.clone());
+        let message = |role: &str, content: String| Message::text(role, content);
+        let history = vec![
+            message("system", "system prompt".to_string()),
+            message("user", "old question ".repeat(100)),
+            message("system", "old tool output ".repeat(200)),
+            message("assistant", "old answer ".repeat(100)),
+            message("user", "latest question".to_string()),
+            message("system", guidance.clone()),
+        ];
+
+        let fitted = chat.fit_context(&history);
+        let contents: Vec<&str> = fitted.iter().map(|m| m.content.as_str()).collect();
+
+        assert_eq!(contents.first(), Some(&"system prompt"));
+        assert!(contents.contains(&"latest question"));
+        assert!(contents.contains(&guidance.as_str()));
+        assert!(!contents.iter().any(|c| c.starts_with("old tool output")));
     }
 
     #[test]
@@ -3939,10 +4421,10 @@ mod tests {
             HashMap::new(),
             exec_policy,
         );
-        let history = vec![Message {
-            role: "user".to_string(),
-            content: "fix the migration and then update the docs".to_string(),
-        }];
+        let history = vec![Message::text(
+            "user",
+            "fix the migration and then update the docs",
+        )];
 
         let prompt = chat
             .plan_prompt_for_request(&history, "blocked-plan-session")
@@ -3991,10 +4473,10 @@ mod tests {
             HashMap::new(),
             exec_policy,
         );
-        let history = vec![Message {
-            role: "user".to_string(),
-            content: "fix the handler and then rerun the tests".to_string(),
-        }];
+        let history = vec![Message::text(
+            "user",
+            "fix the handler and then rerun the tests",
+        )];
 
         let prompt = chat
             .plan_prompt_for_request(&history, "retry-plan-session")
@@ -4170,14 +4652,8 @@ mod tests {
     #[test]
     fn infer_followup_write_file_intent_uses_previous_assistant_filename_and_code() {
         let history = vec![
-            Message {
-                role: "user".to_string(),
-                content: "hey can you create a python hello joy file".to_string(),
-            },
-            Message {
-                role: "assistant".to_string(),
-                content: "Sure. Save this content into a file named `hello_joy.py`: `print(\"Hello Joy!\")` and run it with `python3 hello_joy.py`.".to_string(),
-            },
+            Message::text("user", "hey can you create a python hello joy file"),
+            Message::text("assistant", "Sure. Save this content into a file named `hello_joy.py`: `print(\"Hello Joy!\")` and run it with `python3 hello_joy.py`."),
         ];
 
         let intent =
@@ -4191,14 +4667,8 @@ mod tests {
     #[test]
     fn infer_followup_write_file_intent_sanitizes_absolute_filename() {
         let history = vec![
-            Message {
-                role: "user".to_string(),
-                content: "create a new file explaining ai in markdown".to_string(),
-            },
-            Message {
-                role: "assistant".to_string(),
-                content: "Save this as `/home/user/ai.md`\n\n# Introduction to Artificial Intelligence\n\nAI is a field of computer science.".to_string(),
-            },
+            Message::text("user", "create a new file explaining ai in markdown"),
+            Message::text("assistant", "Save this as `/home/user/ai.md`\n\n# Introduction to Artificial Intelligence\n\nAI is a field of computer science."),
         ];
 
         let intent =
@@ -4214,14 +4684,8 @@ mod tests {
     #[test]
     fn infer_followup_write_file_intent_extracts_markdown_body() {
         let history = vec![
-            Message {
-                role: "user".to_string(),
-                content: "create a new file explaining ai in markdown".to_string(),
-            },
-            Message {
-                role: "assistant".to_string(),
-                content: "Sure, use `ai.md`\n\n# Introduction to Artificial Intelligence\n\n## What Is AI?\n\nArtificial intelligence is ...".to_string(),
-            },
+            Message::text("user", "create a new file explaining ai in markdown"),
+            Message::text("assistant", "Sure, use `ai.md`\n\n# Introduction to Artificial Intelligence\n\n## What Is AI?\n\nArtificial intelligence is ..."),
         ];
 
         let intent =
@@ -4238,15 +4702,8 @@ mod tests {
     #[test]
     fn infer_followup_run_command_intent_uses_previous_assistant_command() {
         let history = vec![
-            Message {
-                role: "user".to_string(),
-                content: "can you create a sample file name hello and put 1 to 10 in numbers"
-                    .to_string(),
-            },
-            Message {
-                role: "assistant".to_string(),
-                content: "Save this script as `create_hello.sh`, make it executable with `chmod +x create_hello.sh`, and run it with `./create_hello.sh`.".to_string(),
-            },
+            Message::text("user", "can you create a sample file name hello and put 1 to 10 in numbers"),
+            Message::text("assistant", "Save this script as `create_hello.sh`, make it executable with `chmod +x create_hello.sh`, and run it with `./create_hello.sh`."),
         ];
 
         let intent = ChatService::infer_followup_run_command_intent(&history, "run that command")
@@ -4258,16 +4715,11 @@ mod tests {
     #[test]
     fn infer_followup_run_command_intent_prefers_previous_user_git_intent() {
         let history = vec![
-            Message {
-                role: "user".to_string(),
-                content: "run the git status".to_string(),
-            },
-            Message {
-                role: "assistant".to_string(),
-                content:
-                    "Git working directory has 11 modified, 1 untracked. Notable files: app.rs."
-                        .to_string(),
-            },
+            Message::text("user", "run the git status"),
+            Message::text(
+                "assistant",
+                "Git working directory has 11 modified, 1 untracked. Notable files: app.rs.",
+            ),
         ];
 
         let intent = ChatService::infer_followup_run_command_intent(&history, "run that command")
@@ -4278,10 +4730,7 @@ mod tests {
 
     #[test]
     fn infer_followup_run_command_intent_returns_none_without_previous_command() {
-        let history = vec![Message {
-            role: "assistant".to_string(),
-            content: "I created the file directly.".to_string(),
-        }];
+        let history = vec![Message::text("assistant", "I created the file directly.")];
 
         let intent = ChatService::infer_followup_run_command_intent(&history, "run that command");
         assert!(intent.is_none());
@@ -4320,11 +4769,10 @@ mod tests {
             HashMap::new(),
             exec_policy,
         );
-        let history = vec![Message {
-            role: "assistant".to_string(),
-            content: "Git working directory has 11 modified, 1 untracked. Notable files: app.rs."
-                .to_string(),
-        }];
+        let history = vec![Message::text(
+            "assistant",
+            "Git working directory has 11 modified, 1 untracked. Notable files: app.rs.",
+        )];
 
         let intent = chat
             .infer_followup_run_command_intent_for_session(&history, "run that", "followup-session")
@@ -4571,10 +5019,10 @@ mod tests {
 
     #[test]
     fn headless_turn_debug_resolves_followup_run_command() {
-        let history = vec![Message {
-            role: "assistant".to_string(),
-            content: "Ran `git status`.\n```\nOn branch main\n```".to_string(),
-        }];
+        let history = vec![Message::text(
+            "assistant",
+            "Ran `git status`.\n```\nOn branch main\n```",
+        )];
 
         let debug = debug_turn(ExecutionStrategy::Deterministic, &history, "run that");
 
@@ -15,30 +15,111 @@
 //! Prompt building and management
 
 use crate::core::error::HarperError;
+use crate::core::mcp::McpRegistry;
 use crate::core::ApiConfig;
+use crate::tools::registry::ToolRegistry;
 use chrono::Datelike;
-use turul_mcp_client::McpClient;
 
 /// Prompt building functionality
 pub struct PromptBuilder<'a> {
     pub config: &'a ApiConfig,
+    pub tools: &'a ToolRegistry,
     pub prompt_id: Option<String>,
-    pub mcp_client: Option<&'a McpClient>,
+    pub mcp: Option<&'a McpRegistry>,
 }
 
 impl<'a> PromptBuilder<'a> {
     const IGNORED_CONTEXT_FILES: [&'static str; 2] = ["README.md", "README"];
     const IGNORED_CONTEXT_DIRS: [&'static str; 3] = ["target", "node_modules", "website"];
 
+    /// Usage lines for the built-in tools, listed while the tool is enabled
+    const CORE_TOOL_USAGE: [(&'static str, &'static str); 22] = [
+        ("read_file", r#"read_file(args: {"path": "src/main.rs"})"#),
+        (
+            "write_file",
+            r#"write_file(args: {"path": "src/main.rs", "content": "..."})"#,
+        ),
+        (
+            "search_replace",
+            r#"search_replace(args: {"path": "src/main.rs", "old_string": "old", "new_string": "new"})"#,
+        ),
+        (
+            "run_command",
+            r#"run_command(args: {"command": "git status"})"#,
+        ),
+        (
+            "run_command",
+            r#"run_command(args: {"command": "cp ./src.txt ./build/out.txt", "declared_read_paths": ["./src.txt"], "declared_write_paths": ["./build/out.txt"]})"#,
+        ),
+        (
+            "run_command",
+            r#"run_command(args: {"command": "curl -fsSL http://127.0.0.1:8081/health", "requires_network": true, "retry_policy": "safe"})"#,
+        ),
+        (
+            "adx_query",
+            r#"adx_query(args: {"query": "StormEvents | take 10", "database": "Samples", "cluster_url": "https://help.kusto.windows.net"})"#,
+        ),
+        (
+            "todo",
+            r#"todo(args: {"action": "add|list|remove|clear", "description": "...", "index": 1})"#,
+        ),
+        (
+            "update_plan",
+            r#"update_plan(args: {"explanation": "optional context", "items": [{"step": "Inspect files", "status": "in_progress"}]})"#,
+        ),
+        (
+            "list_changed_files",
+            r#"list_changed_files(args: {"ext": "rs", "tracked_only": true, "since": "HEAD~1"})"#,
+        ),
+        ("git_status", r#"git_status(args: {})"#),
+        ("git_diff", r#"git_diff(args: {})"#),
+        ("git_add", r#"git_add(args: {"files": "src/main.rs"})"#),
+        (
+            "git_commit",
+            r#"git_commit(args: {"message": "feat: update prompt contract"})"#,
+        ),
+        (
+            "codebase_investigator",
+            r#"codebase_investigator(args: {"action": "find_calls", "symbol": "ToolService"})"#,
+        ),
+        (
+            "codebase_investigator",
+            r#"codebase_investigator(args: {"action": "trace_relationship", "x": "PromptBuilder", "y": "ToolService"})"#,
+        ),
+        ("firmware_list", r#"firmware_list(args: {})"#),
+        (
+            "firmware_info",
+            r#"firmware_info(args: {"device": "esp32"})"#,
+        ),
+        (
+            "firmware_connect",
+            r#"firmware_connect(args: {"device": "esp32"})"#,
+        ),
+        (
+            "firmware_gpio",
+            r#"firmware_gpio(args: {"device": "esp32", "pin": 2, "state": true})"#,
+        ),
+        (
+            "firmware_flash",
+            r#"firmware_flash(args: {"device": "esp32"})"#,
+        ),
+        (
+            "firmware_monitor",
+            r#"firmware_monitor(args: {"device": "esp32", "seconds": 10})"#,
+        ),
+    ];
+
     pub fn new(
         config: &'a ApiConfig,
+        tools: &'a ToolRegistry,
         prompt_id: Option<String>,
-        mcp_client: Option<&'a McpClient>,
+        mcp: Option<&'a McpRegistry>,
     ) -> Self {
         Self {
             config,
+            tools,
             prompt_id,
-            mcp_client,
+            mcp,
         }
     }
 
@@ -88,7 +169,7 @@ User Intent Recognition:
 - tell me about a specific file -> use read_file
 - query Azure Data Explorer or Kusto -> use adx_query
 
-Use this JSON shape for built-in tools:
+When native function calling is available, call the provided functions directly. Otherwise use this JSON shape for built-in tools:
 {\"tool\":\"tool_name\",\"args\":{...}}
 
 If the user asks for a file read, file edit, search, diff, git inspection, or command execution, do not answer with an apology or a capability disclaimer. Emit the correct tool JSON immediately.
@@ -105,29 +186,24 @@ Codebase discovery rules:
 - For ambiguous repository questions, use codebase_investigator or a search command first to locate the relevant file(s).
 - Use read_file directly only when the target file is explicit and unambiguous.
 
-Core Tools:
-- read_file(args: {\"path\": \"src/main.rs\"})
-- write_file(args: {\"path\": \"src/main.rs\", \"content\": \"...\"})
-- search_replace(args: {\"path\": \"src/main.rs\", \"old_string\": \"old\", \"new_string\": \"new\"})
-- run_command(args: {\"command\": \"git status\"})
-- run_command(args: {\"command\": \"cp ./src.txt ./build/out.txt\", \"declared_read_paths\": [\"./src.txt\"], \"declared_write_paths\": [\"./build/out.txt\"]})
-- run_command(args: {\"command\": \"curl -fsSL http://127.0.0.1:8081/health\", \"requires_network\": true, \"retry_policy\": \"safe\"})
-- adx_query(args: {\"query\": \"StormEvents | take 10\", \"database\": \"Samples\", \"cluster_url\": \"https://help.kusto.windows.net\"})
-- todo(args: {\"action\": \"add|list|remove|clear\", \"description\": \"...\", \"index\": 1})
-- update_plan(args: {\"explanation\": \"optional context\", \"items\": [{\"step\": \"Inspect files\", \"status\": \"in_progress\"}]})
-- list_changed_files(args: {\"ext\": \"rs\", \"tracked_only\": true, \"since\": \"HEAD~1\"})
-- git_status(args: {})
-- git_diff(args: {})
-- git_add(args: {\"files\": \"src/main.rs\"})
-- git_commit(args: {\"message\": \"feat: update prompt contract\"})
-- codebase_investigator(args: {\"action\": \"find_calls\", \"symbol\": \"ToolService\"})
-- codebase_investigator(args: {\"action\": \"trace_relationship\", \"x\": \"PromptBuilder\", \"y\": \"ToolService\"})
-- firmware_list(args: {})
-- firmware_info(args: {\"device\": \"esp32\"})
-- firmware_gpio(args: {\"pin\": 2, \"state\": true})
-
-Example: {\"tool\":\"read_file\",\"args\":{\"path\":\"src/main.rs\"}}",
+Core Tools:",
         );
+        for (name, usage) in Self::CORE_TOOL_USAGE {
+            if self.tools.is_enabled(name) {
+                prompt.push_str("\n- ");
+                prompt.push_str(usage);
+            }
+        }
+        for tool in self.tools.tools() {
+            if !Self::CORE_TOOL_USAGE
+                .iter()
+                .any(|(name, _)| *name == tool.name())
+            {
+                prompt.push_str(&format!("\n- {}: {}", tool.name(), tool.description()));
+            }
+        }
+        prompt
+            .push_str("\n\nExample: {\"tool\":\"read_file\",\"args\":{\"path\":\"src/main.rs\"}}");
 
         if let Some(mcp_tools) = self.get_mcp_tools_text().await {
             prompt.push_str(&mcp_tools);
@@ -227,35 +303,30 @@ Example: {\"tool\":\"read_file\",\"args\":{\"path\":\"src/main.rs\"}}",
         crate::core::agents::resolve_agents_for_dir(&current_dir)
     }
 
-    /// Get MCP tools text
+    /// Get MCP tools text for every connected server
     async fn get_mcp_tools_text(&self) -> Option<String> {
-        if let Some(client) = self.mcp_client {
-            match client.list_tools().await {
-                Ok(tools) => {
-                    if tools.is_empty() {
-                        return None;
-                    }
+        let registry = self.mcp?;
+        let tools: Vec<_> = registry
+            .list_tools()
+            .await
+            .into_iter()
+            .filter(|entry| self.tools.is_enabled(&entry.name))
+            .collect();
+        if tools.is_empty() {
+            return None;
+        }
 
-                    let mut tools_text = String::from("\n\nMCP Tools:\n");
-                    for tool in &tools {
-                        tools_text.push_str(&format!(
-                            "- {}: {}\n",
-                            tool.name,
-                            tool.description.as_deref().unwrap_or("...")
-                        ));
-                    }
-                    tools_text
-                        .push_str("\nRespond: {\"mcp_tool\": \"name\", \"arguments\": {...}}");
-                    Some(tools_text)
-                }
-                Err(e) => {
-                    eprintln!("Warning: Failed to list MCP tools: {}", e);
-                    None
-                }
-            }
-        } else {
-            None
+        let mut tools_text = String::from("\n\nMCP Tools:\n");
+        for entry in &tools {
+            tools_text.push_str(&format!(
+                "- {}: {}\n",
+                entry.name,
+                entry.tool.description.as_deref().unwrap_or("...")
+            ));
         }
+        tools_text
+            .push_str("\nRespond: {\"mcp_tool\": \"mcp__<server>__<tool>\", \"arguments\": {...}}");
+        Some(tools_text)
     }
 }
 
@@ -263,6 +334,7 @@ Example: {\"tool\":\"read_file\",\"args\":{\"path\":\"src/main.rs\"}}",
 mod tests {
     use super::PromptBuilder;
     use crate::core::{ApiConfig, ApiProvider};
+    use crate::tools::registry::ToolRegistry;
 
     fn test_config() -> ApiConfig {
         ApiConfig {
@@ -270,13 +342,17 @@ mod tests {
             api_key: "test-key".to_string(),
             base_url: "https://api.openai.com/v1/chat/completions".to_string(),
             model_name: "gpt-5.5".to_string(),
+            headers: Default::default(),
+            parameters: Default::default(),
+            fallback: Default::default(),
         }
     }
 
     #[tokio::test]
     async fn build_system_prompt_includes_workspace_file_rules() {
         let config = test_config();
-        let builder = PromptBuilder::new(&config, None, None);
+        let tools = ToolRegistry::default();
+        let builder = PromptBuilder::new(&config, &tools, None, None);
         let prompt = builder.build_system_prompt(false).await;
 
         assert!(prompt.contains("File path rules:"));
@@ -287,4 +363,20 @@ mod tests {
         assert!(prompt.contains("do not guess a file path first"));
         assert!(prompt.contains("use codebase_investigator or a search command first"));
     }
+
+    #[tokio::test]
+    async fn build_system_prompt_lists_only_enabled_tools() {
+        let config = test_config();
+        let tools = ToolRegistry::new(None, vec!["git_*".to_string(), "firmware_*".to_string()]);
+        let prompt = PromptBuilder::new(&config, &tools, None, None)
+            .build_system_prompt(false)
+            .await;
+
+        assert!(prompt.contains("- read_file(args: {\"path\": \"src/main.rs\"})"));
+        assert!(prompt.contains("- run_command(args: {\"command\": \"git status\"})"));
+        assert!(!prompt.contains("- git_status(args:"));
+        assert!(!prompt.contains("- firmware_gpio(args:"));
+        assert!(prompt.contains("\n- db_query: Run a read-only SELECT query"));
+        assert!(prompt.contains("Example: {\"tool\":\"read_file\""));
+    }
 }
@@ -15,10 +15,16 @@
 //! Simple in-memory cache for API responses
 //!
 //! This module provides basic caching functionality to reduce API calls
-//! and improve performance for repeated requests.
+//! and improve performance for repeated requests. [`ResponseCache`] adds
+//! optional SQLite persistence and hit/miss accounting on top.
 
+use crate::core::error::HarperResult;
+use crate::core::{ApiConfig, Message};
+use ring::digest::{Context, SHA256};
+use rusqlite::Connection;
+use serde::Deserialize;
 use std::collections::HashMap;
-use std::hash::{Hash, Hasher};
+use std::hash::Hash;
 use std::time::{Duration, Instant};
 
 /// Cache entry with expiration time
@@ -112,24 +118,57 @@ where
 pub struct ApiCacheKey {
     pub provider: String,
     pub model: String,
-    pub messages_hash: u64,
+    pub request_hash: u64,
 }
 
 impl ApiCacheKey {
     /// Create a new API cache key
-    pub fn new(provider: &str, model: &str, messages: &[super::Message]) -> Self {
-        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    ///
+    /// Besides the provider and model, the request hash covers the endpoint,
+    /// the model parameters, the names of the tools on offer and the messages,
+    /// including any images attached to them.
+    /// The hash is derived from SHA-256 so it stays stable across
+    /// builds and can be persisted.
+    pub fn new(config: &ApiConfig, tools: &[String], messages: &[Message]) -> Self {
+        let mut context = Context::new(&SHA256);
+        let mut update = |part: &str| {
+            context.update(&(part.len() as u64).to_le_bytes());
+            context.update(part.as_bytes());
+        };
+        update(&config.base_url);
+        update(&format!("{:?}", config.parameters));
+        update(&tools.len().to_string());
+        for tool in tools {
+            update(tool);
+        }
         for msg in messages {
-            msg.role.hash(&mut hasher);
-            msg.content.hash(&mut hasher);
+            update(&msg.role);
+            update(&msg.content);
+            update(msg.tool_call_id.as_deref().unwrap_or_default());
+            update(&msg.images.len().to_string());
+            for image in &msg.images {
+                update(&image.mime_type);
+                update(&image.data);
+            }
         }
+        let digest = context.finish();
+        let mut prefix = [0u8; 8];
+        prefix.copy_from_slice(&digest.as_ref()[..8]);
 
         Self {
-            provider: provider.to_string(),
-            model: model.to_string(),
-            messages_hash: hasher.finish(),
+            provider: config.provider.to_string(),
+            model: config.model_name.clone(),
+            request_hash: u64::from_le_bytes(prefix),
         }
     }
+
+    /// Key used for the persisted cache entry
+    pub fn storage_key(&self) -> String {
+        format!(
+            "{}:{}:{:016x}",
+            self.provider, self.model, self.request_hash
+        )
+    }
 }
 
 /// Global API response cache
@@ -139,3 +178,195 @@ pub type ApiResponseCache = Cache<ApiCacheKey, String>;
 pub fn new_api_cache() -> ApiResponseCache {
     Cache::new(crate::core::constants::cache::API_RESPONSE_TTL)
 }
+
+/// Response cache settings (`[api.cache]`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct ResponseCacheConfig {
+    /// Serve repeated requests from the cache
+    pub enabled: bool,
+    /// Keep entries in SQLite so they survive restarts
+    pub persist: bool,
+    /// Seconds an entry stays valid
+    pub ttl_secs: u64,
+}
+
+impl Default for ResponseCacheConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            persist: false,
+            ttl_secs: crate::core::constants::cache::API_RESPONSE_TTL.as_secs(),
+        }
+    }
+}
+
+/// LLM response cache with optional SQLite persistence
+///
+/// Every lookup is counted in the database so hit rates show up in
+/// `SessionService::get_global_stats`.
+#[derive(Debug)]
+pub struct ResponseCache {
+    memory: ApiResponseCache,
+    persist: bool,
+    ttl: Duration,
+}
+
+impl ResponseCache {
+    /// Create a cache from its settings
+    pub fn new(config: &ResponseCacheConfig) -> Self {
+        let ttl = Duration::from_secs(config.ttl_secs);
+        Self {
+            memory: Cache::new(ttl),
+            persist: config.persist,
+            ttl,
+        }
+    }
+
+    /// Look up a response, falling back to SQLite when persistence is on
+    pub fn get(&mut self, conn: &Connection, key: &ApiCacheKey) -> HarperResult<Option<String>> {
+        let mut response = self.memory.get(key).cloned();
+        if response.is_none() && self.persist {
+            response = crate::memory::storage::load_cached_response(conn, &key.storage_key())?;
+            if let Some(response) = &response {
+                self.memory.insert(key.clone(), response.clone());
+            }
+        }
+        crate::memory::storage::record_cache_lookup(conn, response.is_some())?;
+        Ok(response)
+    }
+
+    /// Store a response
+    pub fn insert(
+        &mut self,
+        conn: &Connection,
+        key: ApiCacheKey,
+        response: &str,
+    ) -> HarperResult<()> {
+        if self.persist {
+            crate::memory::storage::save_cached_response(
+                conn,
+                &key.storage_key(),
+                response,
+                self.ttl,
+            )?;
+        }
+        self.memory.insert(key, response.to_string());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{ApiProvider, ImageAttachment};
+
+    fn config() -> ApiConfig {
+        ApiConfig {
+            provider: ApiProvider::OpenAI,
+            api_key: "test-key".to_string(),
+            base_url: "https://api.openai.com/v1/chat/completions".to_string(),
+            model_name: "gpt-5.5".to_string(),
+            headers: Default::default(),
+            parameters: Default::default(),
+            fallback: Default::default(),
+        }
+    }
+
+    fn cache_key(content: &str) -> ApiCacheKey {
+        ApiCacheKey::new(&config(), &[], &messages(content))
+    }
+
+    fn messages(content: &str) -> Vec<Message> {
+        vec![Message::text("user", content)]
+    }
+
+    fn persistent_config() -> ResponseCacheConfig {
+        ResponseCacheConfig {
+            enabled: true,
+            persist: true,
+            ttl_secs: 60,
+        }
+    }
+
+    #[test]
+    fn cache_key_is_stable_and_content_sensitive() {
+        let first = cache_key("hello");
+        let again = cache_key("hello");
+        let other = cache_key("hello!");
+
+        assert_eq!(first, again);
+        assert_ne!(first.request_hash, other.request_hash);
+        assert!(first.storage_key().starts_with("OpenAI:gpt-5.5:"));
+    }
+
+    #[test]
+    fn cache_key_covers_tools_endpoint_and_parameters() {
+        let base = cache_key("hello");
+        let with_tools =
+            ApiCacheKey::new(&config(), &["read_file".to_string()], &messages("hello"));
+        let other_endpoint = ApiCacheKey::new(
+            &ApiConfig {
+                base_url: "http://127.0.0.1:8080/v1/chat/completions".to_string(),
+                ..config()
+            },
+            &[],
+            &messages("hello"),
+        );
+        let mut warmer = config();
+        warmer.parameters.temperature = Some(1.2);
+        let other_parameters = ApiCacheKey::new(&warmer, &[], &messages("hello"));
+        let image = |data: &str| {
+            let mut messages = messages("hello");
+            messages[0].images.push(ImageAttachment {
+                mime_type: "image/png".to_string(),
+                data: data.to_string(),
+            });
+            ApiCacheKey::new(&config(), &[], &messages)
+        };
+        let with_image = image("aGVsbG8=");
+        assert_ne!(with_image.request_hash, image("d29ybGQ=").request_hash);
+
+        for changed in [with_tools, other_endpoint, other_parameters, with_image] {
+            assert_ne!(base.request_hash, changed.request_hash);
+        }
+    }
+
+    #[test]
+    fn persisted_responses_survive_a_new_cache_and_count_lookups() {
+        let conn = Connection::open_in_memory().expect("in-memory db");
+        crate::memory::storage::init_db(&conn).expect("init db");
+        let key = cache_key("hello");
+
+        let mut cache = ResponseCache::new(&persistent_config());
+        assert_eq!(cache.get(&conn, &key).expect("lookup"), None);
+        cache
+            .insert(&conn, key.clone(), "hi there")
+            .expect("insert");
+
+        let mut restarted = ResponseCache::new(&persistent_config());
+        assert_eq!(
+            restarted.get(&conn, &key).expect("lookup").as_deref(),
+            Some("hi there")
+        );
+        assert_eq!(
+            crate::memory::storage::load_cache_stats(&conn).expect("stats"),
+            (1, 1)
+        );
+    }
+
+    #[test]
+    fn expired_persisted_responses_are_ignored() {
+        let conn = Connection::open_in_memory().expect("in-memory db");
+        crate::memory::storage::init_db(&conn).expect("init db");
+        let key = cache_key("hello");
+        let mut cache = ResponseCache::new(&ResponseCacheConfig {
+            ttl_secs: 0,
+            ..persistent_config()
+        });
+        cache.insert(&conn, key.clone(), "stale").expect("insert");
+
+        let mut restarted = ResponseCache::new(&persistent_config());
+        assert_eq!(restarted.get(&conn, &key).expect("lookup"), None);
+    }
+}
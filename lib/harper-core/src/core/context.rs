@@ -0,0 +1,312 @@
+// Copyright 2026 harpertoken
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Token-aware context management
+//!
+//! Conversation history is fitted to a per-model token budget before it is
+//! sent to a provider. Old tool outputs are truncated and then dropped first,
+//! followed by the oldest conversation turns. Pinned messages are never
+//! touched.
+
+use crate::core::{ApiConfig, ApiProvider, Message};
+
+/// Tokens reserved for the model response when `max_tokens` is not configured
+const DEFAULT_RESPONSE_RESERVE: u32 = 4096;
+/// Size an old tool output is cut down to before it is dropped outright
+const TRUNCATED_TOOL_OUTPUT_TOKENS: usize = 256;
+/// Approximate framing cost of a single chat message
+const MESSAGE_OVERHEAD_TOKENS: usize = 4;
+
+/// Character-based token estimate for a provider's tokenizer
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TokenEstimator {
+    chars_per_token: f64,
+}
+
+impl TokenEstimator {
+    /// Pick an estimate matching the tokenizer family of `provider` and `model`
+    pub fn for_model(provider: &ApiProvider, model: &str) -> Self {
+        let model = model.to_ascii_lowercase();
+        let chars_per_token = match provider {
+            ApiProvider::OpenAI | ApiProvider::Gemini => 4.0,
+            ApiProvider::Anthropic => 3.5,
+            ApiProvider::Sambanova | ApiProvider::Ollama | ApiProvider::OpenAiCompatible => {
+                if model.starts_with("gpt") {
+                    4.0
+                } else {
+                    3.3
+                }
+            }
+        };
+        Self { chars_per_token }
+    }
+
+    /// Estimated tokens for a piece of text
+    pub fn estimate_text(&self, text: &str) -> usize {
+        (text.chars().count() as f64 / self.chars_per_token).ceil() as usize
+    }
+
+    /// Estimated tokens for a message including its framing
+    pub fn estimate_message(&self, message: &Message) -> usize {
+        self.estimate_text(&message.content) + MESSAGE_OVERHEAD_TOKENS
+    }
+
+    /// Estimated tokens for a whole conversation
+    pub fn estimate_messages(&self, messages: &[Message]) -> usize {
+        messages
+            .iter()
+            .map(|message| self.estimate_message(message))
+            .sum()
+    }
+
+    fn truncate(&self, content: &str, max_tokens: usize) -> String {
+        let keep_chars = (max_tokens as f64 * self.chars_per_token) as usize;
+        let kept: String = content.chars().take(keep_chars).collect();
+        let dropped = self.estimate_text(content).saturating_sub(max_tokens);
+        format!(
+            "{}\n[... ~{} tokens of tool output truncated to fit the context budget]",
+            kept.trim_end(),
+            dropped
+        )
+    }
+}
+
+/// Token budget for the history sent with a single request
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ContextBudget {
+    pub max_tokens: usize,
+    pub estimator: TokenEstimator,
+}
+
+impl ContextBudget {
+    /// Budget for the configured provider and model
+    ///
+    /// Uses `parameters.context_window` when set, otherwise a default window
+    /// for the provider, and reserves room for the response.
+    pub fn for_config(config: &ApiConfig) -> Self {
+        let window = config
+            .parameters
+            .context_window
+            .unwrap_or_else(|| default_context_window(&config.provider, &config.model_name));
+        let reserve = config
+            .parameters
+            .max_tokens
+            .unwrap_or(DEFAULT_RESPONSE_RESERVE)
+            .min(window / 2);
+        Self {
+            max_tokens: (window - reserve) as usize,
+            estimator: TokenEstimator::for_model(&config.provider, &config.model_name),
+        }
+    }
+
+    /// Fit `messages` into the budget
+    ///
+    /// Leading system messages and anything `is_pinned` accepts are kept
+    /// verbatim. Unpinned tool outputs (system and tool messages after the
+    /// prompt) are truncated oldest first, then dropped, and finally the
+    /// oldest unpinned turns are removed. The result can still exceed the
+    /// budget when the pinned messages alone do.
+    pub fn fit<F>(&self, messages: &[Message], is_pinned: F) -> Vec<Message>
+    where
+        F: Fn(usize, &Message) -> bool,
+    {
+        let leading_system = messages
+            .iter()
+            .take_while(|message| message.role == "system")
+            .count();
+        let mut entries: Vec<(Message, bool)> = messages
+            .iter()
+            .enumerate()
+            .map(|(index, message)| {
+                let pinned = index < leading_system || is_pinned(index, message);
+                (message.clone(), pinned)
+            })
+            .collect();
+        let mut total: usize = entries
+            .iter()
+            .map(|(message, _)| self.estimator.estimate_message(message))
+            .sum();
+        if total <= self.max_tokens {
+            return messages.to_vec();
+        }
+
+        for (message, pinned) in entries.iter_mut() {
+            if total <= self.max_tokens {
+                break;
+            }
+            if *pinned || !is_tool_output(message) {
+                continue;
+            }
+            let before = self.estimator.estimate_message(message);
+            if before <= TRUNCATED_TOOL_OUTPUT_TOKENS + MESSAGE_OVERHEAD_TOKENS {
+                continue;
+            }
+            message.content = self
+                .estimator
+                .truncate(&message.content, TRUNCATED_TOOL_OUTPUT_TOKENS);
+            total = total - before + self.estimator.estimate_message(message);
+        }
+
+        self.drop_oldest(&mut entries, &mut total, is_tool_output);
+        self.drop_oldest(&mut entries, &mut total, |_| true);
+
+        entries.into_iter().map(|(message, _)| message).collect()
+    }
+
+    fn drop_oldest<F>(&self, entries: &mut Vec<(Message, bool)>, total: &mut usize, matches: F)
+    where
+        F: Fn(&Message) -> bool,
+    {
+        let mut index = 0;
+        while *total > self.max_tokens && index < entries.len() {
+            let (message, pinned) = &entries[index];
+            if *pinned || !matches(message) {
+                index += 1;
+                continue;
+            }
+            *total -= self.estimator.estimate_message(message);
+            entries.remove(index);
+        }
+    }
+}
+
+fn is_tool_output(message: &Message) -> bool {
+    matches!(message.role.as_str(), "system" | "tool")
+}
+
+/// Context window assumed when none is configured for the model
+pub fn default_context_window(provider: &ApiProvider, model: &str) -> u32 {
+    let model = model.to_ascii_lowercase();
+    match provider {
+        ApiProvider::OpenAI if model.starts_with("gpt-4.1") => 1_000_000,
+        ApiProvider::OpenAI => 128_000,
+        ApiProvider::Anthropic => 200_000,
+        ApiProvider::Gemini => 1_000_000,
+        ApiProvider::Sambanova => 32_768,
+        ApiProvider::Ollama => 8_192,
+        ApiProvider::OpenAiCompatible => 32_768,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{FallbackChain, ModelParameters};
+    use std::collections::HashMap;
+
+    fn message(role: &str, content: &str) -> Message {
+        Message::text(role, content)
+    }
+
+    fn budget(max_tokens: usize) -> ContextBudget {
+        ContextBudget {
+            max_tokens,
+            estimator: TokenEstimator::for_model(&ApiProvider::OpenAI, "gpt-4o"),
+        }
+    }
+
+    #[test]
+    fn estimator_follows_provider_tokenizer() {
+        let openai = TokenEstimator::for_model(&ApiProvider::OpenAI, "gpt-4o");
+        let anthropic = TokenEstimator::for_model(&ApiProvider::Anthropic, "claude-sonnet-4-5");
+        let text = "a".repeat(700);
+
+        assert_eq!(openai.estimate_text(&text), 175);
+        assert_eq!(anthropic.estimate_text(&text), 200);
+        assert_eq!(openai.estimate_message(&message("user", &text)), 179);
+    }
+
+    #[test]
+    fn budget_reserves_response_tokens_from_configured_window() {
+        let mut config = ApiConfig {
+            provider: ApiProvider::Ollama,
+            api_key: String::new(),
+            base_url: "http://localhost:11434/api/chat".to_string(),
+            model_name: "llama3".to_string(),
+            headers: HashMap::new(),
+            parameters: ModelParameters::default(),
+            fallback: FallbackChain::default(),
+        };
+        assert_eq!(ContextBudget::for_config(&config).max_tokens, 8_192 - 4_096);
+
+        config.parameters.context_window = Some(32_000);
+        config.parameters.max_tokens = Some(2_000);
+        assert_eq!(ContextBudget::for_config(&config).max_tokens, 30_000);
+    }
+
+    #[test]
+    fn history_within_budget_is_unchanged() {
+        let history = vec![message("system", "prompt"), message("user", "hi")];
+        let fitted = budget(1_000).fit(&history, |_, _| false);
+        assert_eq!(fitted.len(), 2);
+        assert_eq!(fitted[1].content, "hi");
+    }
+
+    #[test]
+    fn old_tool_outputs_are_truncated_before_turns_are_dropped() {
+        let history = vec![
+            message("system", "prompt"),
+            message("user", "read the file"),
+            message("system", &"x".repeat(8_000)),
+            message("assistant", "done"),
+            message("user", "next"),
+        ];
+        let fitted = budget(400).fit(&history, |_, _| false);
+
+        assert_eq!(fitted.len(), 5);
+        assert!(fitted[2]
+            .content
+            .contains("truncated to fit the context budget"));
+        assert!(budget(400).estimator.estimate_messages(&fitted) <= 400);
+    }
+
+    #[test]
+    fn tool_outputs_are_dropped_then_oldest_turns() {
+        let history = vec![
+            message("system", "prompt"),
+            message("user", &"u".repeat(400)),
+            message("system", &"x".repeat(8_000)),
+            message("assistant", &"a".repeat(400)),
+            message("user", "latest question"),
+        ];
+
+        let fitted = budget(240).fit(&history, |_, _| false);
+        let roles: Vec<&str> = fitted.iter().map(|m| m.role.as_str()).collect();
+        assert_eq!(roles, vec!["system", "user", "assistant", "user"]);
+
+        let fitted = budget(140).fit(&history, |_, _| false);
+        let contents: Vec<&str> = fitted.iter().map(|m| m.content.as_str()).collect();
+        assert_eq!(
+            contents,
+            vec!["prompt", &"a".repeat(400), "latest question"]
+        );
+    }
+
+    #[test]
+    fn pinned_messages_survive_trimming() {
+        let guidance =
+            "Before executing this path-targeting tool, apply these scoped AGENTS.md instructions";
+        let history = vec![
+            message("system", "prompt"),
+            message("user", &"u".repeat(4_000)),
+            message("system", guidance),
+            message("system", &"x".repeat(4_000)),
+            message("user", "latest"),
+        ];
+
+        let fitted = budget(60).fit(&history, |index, m| index == 4 || m.content == guidance);
+        let contents: Vec<&str> = fitted.iter().map(|m| m.content.as_str()).collect();
+        assert_eq!(contents, vec!["prompt", guidance, "latest"]);
+    }
+}
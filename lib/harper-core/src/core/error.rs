@@ -29,6 +29,9 @@ pub enum HarperError {
     /// API related errors
     #[error("API error: {0}")]
     Api(String),
+    /// Every model provider was unreachable, rate limited or failing
+    #[error("Model providers unavailable: {0}")]
+    Unavailable(String),
     /// MCP related errors
     #[allow(dead_code)]
     #[error("MCP error: {0}")]
@@ -50,6 +50,13 @@ pub trait RuntimeEventSink: Send + Sync {
         is_error: bool,
         done: bool,
     ) -> HarperResult<()>;
+    /// Streamed assistant text; `done` marks the end of one model reply
+    async fn assistant_output_updated(
+        &self,
+        session_id: &str,
+        chunk: String,
+        done: bool,
+    ) -> HarperResult<()>;
 }
 
 pub struct NoopRuntimeEventSink;
@@ -86,6 +93,15 @@ impl RuntimeEventSink for NoopRuntimeEventSink {
     ) -> HarperResult<()> {
         Ok(())
     }
+
+    async fn assistant_output_updated(
+        &self,
+        _session_id: &str,
+        _chunk: String,
+        _done: bool,
+    ) -> HarperResult<()> {
+        Ok(())
+    }
 }
 
 /// A default implementation that uses standard I/O (blocking)
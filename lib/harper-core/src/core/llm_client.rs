@@ -14,330 +14,945 @@
 
 //! AI provider integrations and cryptographic utilities
 //!
-//! This module handles communication with different AI providers (OpenAI, Sambanova, Gemini, Ollama)
+//! This module handles communication with different AI providers (OpenAI, Sambanova, Gemini, Anthropic,
+//! Ollama and any OpenAI-compatible server)
 //! and provides cryptographic functions for secure data handling.
 
 use crate::core::constants::crypto::*;
 use crate::core::error::{HarperError, HarperResult};
-use crate::core::{ApiConfig, ApiProvider, Message};
-use reqwest::header::{AUTHORIZATION, CONTENT_TYPE};
+use crate::core::io_traits::RuntimeEventSink;
+use crate::core::usage::TokenUsage;
+use crate::core::{ApiConfig, ApiProvider, ImageAttachment, Message, RetryPolicy};
+use crate::tools::registry::ToolRegistry;
+use reqwest::header::{AUTHORIZATION, CONTENT_TYPE, RETRY_AFTER};
 use reqwest::StatusCode;
 use ring::{
     aead::{self},
     rand::{SecureRandom, SystemRandom},
 };
 use serde_json::{json, Value};
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
 
-fn built_in_tool_functions() -> Vec<Value> {
-    vec![
+fn build_function_tools(tools: &ToolRegistry) -> Vec<Value> {
+    tools
+        .tool_functions()
+        .into_iter()
+        .map(|function| json!({"type": "function", "function": function}))
+        .collect()
+}
+
+/// Id of a tool call, falling back to its position for providers such as
+/// Ollama that do not assign one
+pub(crate) fn tool_call_id(call: &Value, index: usize) -> String {
+    call.get("id")
+        .and_then(|v| v.as_str())
+        .filter(|id| !id.is_empty())
+        .map(|id| id.to_string())
+        .unwrap_or_else(|| format!("call_{}", index))
+}
+
+/// Tool calls recorded in an assistant message, if every one of them is
+/// answered by a `tool` message in the same history
+fn answered_tool_calls(message: &Message, answered_ids: &HashSet<&str>) -> Option<Vec<Value>> {
+    if message.role != "assistant" {
+        return None;
+    }
+    let parsed = serde_json::from_str::<Value>(message.content.trim()).ok()?;
+    let calls = parsed.as_array()?;
+    if calls.is_empty() || calls.iter().any(|call| call.get("function").is_none()) {
+        return None;
+    }
+    calls
+        .iter()
+        .enumerate()
+        .all(|(index, call)| answered_ids.contains(tool_call_id(call, index).as_str()))
+        .then(|| calls.clone())
+}
+
+fn native_tool_call(call: &Value, index: usize, ollama: bool) -> Value {
+    let name = call
+        .pointer("/function/name")
+        .cloned()
+        .unwrap_or_else(|| json!(""));
+    let arguments = call
+        .pointer("/function/arguments")
+        .cloned()
+        .unwrap_or_else(|| json!({}));
+    if ollama {
+        let arguments = match arguments {
+            Value::String(raw) => serde_json::from_str(&raw).unwrap_or_else(|_| json!({})),
+            other => other,
+        };
+        json!({"function": {"name": name, "arguments": arguments}})
+    } else {
+        let arguments = match arguments {
+            Value::String(raw) => raw,
+            other => other.to_string(),
+        };
         json!({
-            "name": "read_file",
-            "description": "Read the contents of a file",
-            "parameters": {
-                "type": "object",
-                "properties": {
-                    "path": {
-                        "type": "string",
-                        "description": "The path to the file to read"
-                    }
-                },
-                "required": ["path"]
+            "id": tool_call_id(call, index),
+            "type": "function",
+            "function": {"name": name, "arguments": arguments}
+        })
+    }
+}
+
+/// Convert history into chat-completions messages, using the native tool
+/// protocol for assistant tool calls that have matching `tool` results
+///
+/// With `vision`, attached images go along as `image_url` parts (or Ollama's
+/// `images` field); only user messages may carry them, so images of other
+/// messages follow in a user message of their own.
+fn build_chat_messages(history: &[Message], provider: &ApiProvider, vision: bool) -> Vec<Value> {
+    let ollama = matches!(provider, ApiProvider::Ollama);
+    let answered_ids: HashSet<&str> = history
+        .iter()
+        .filter(|m| m.role == "tool")
+        .filter_map(|m| m.tool_call_id.as_deref())
+        .collect();
+    let mut native_ids = HashSet::new();
+    let mut messages = Vec::new();
+
+    for m in history {
+        let mut message = chat_message(m, ollama, &answered_ids, &mut native_ids);
+        if !vision || m.images.is_empty() {
+            messages.push(message);
+        } else if ollama {
+            message["images"] = json!(m.images.iter().map(|image| &image.data).collect::<Vec<_>>());
+            messages.push(message);
+        } else if m.role == "user" {
+            let mut parts = vec![json!({"type": "text", "text": m.content})];
+            parts.extend(m.images.iter().map(image_url_part));
+            message["content"] = json!(parts);
+            messages.push(message);
+        } else {
+            let mut parts = vec![json!({"type": "text", "text": "Images from the message above:"})];
+            parts.extend(m.images.iter().map(image_url_part));
+            messages.push(message);
+            messages.push(json!({"role": "user", "content": parts}));
+        }
+    }
+    messages
+}
+
+fn image_url_part(image: &ImageAttachment) -> Value {
+    json!({"type": "image_url", "image_url": {"url": image.data_url()}})
+}
+
+fn chat_message(
+    m: &Message,
+    ollama: bool,
+    answered_ids: &HashSet<&str>,
+    native_ids: &mut HashSet<String>,
+) -> Value {
+    if let Some(calls) = answered_tool_calls(m, answered_ids) {
+        let calls: Vec<Value> = calls
+            .iter()
+            .enumerate()
+            .map(|(index, call)| {
+                native_ids.insert(tool_call_id(call, index));
+                native_tool_call(call, index, ollama)
+            })
+            .collect();
+        let content = if ollama { json!("") } else { Value::Null };
+        return json!({"role": "assistant", "content": content, "tool_calls": calls});
+    }
+    if m.role == "tool" {
+        return match m.tool_call_id.as_deref() {
+            Some(id) if native_ids.contains(id) && ollama => {
+                json!({"role": "tool", "content": m.content})
             }
-        }),
-        json!({
-            "name": "write_file",
-            "description": "Write content to a file",
-            "parameters": {
-                "type": "object",
-                "properties": {
-                    "path": {
-                        "type": "string",
-                        "description": "The path to the file to write"
-                    },
-                    "content": {
-                        "type": "string",
-                        "description": "The content to write to the file"
-                    }
-                },
-                "required": ["path", "content"]
+            Some(id) if native_ids.contains(id) => {
+                json!({"role": "tool", "tool_call_id": id, "content": m.content})
             }
-        }),
-        json!({
-            "name": "search_replace",
-            "description": "Search and replace text in a file",
-            "parameters": {
-                "type": "object",
-                "properties": {
-                    "path": {
-                        "type": "string",
-                        "description": "The path to the file"
-                    },
-                    "old_string": {
-                        "type": "string",
-                        "description": "The text to replace"
-                    },
-                    "new_string": {
-                        "type": "string",
-                        "description": "The replacement text"
-                    }
-                },
-                "required": ["path", "old_string", "new_string"]
+            _ => {
+                json!({"role": "system", "content": format!("Tool result:\n{}", m.content)})
             }
-        }),
-        json!({
-            "name": "run_command",
-            "description": "Run a shell command",
-            "parameters": {
-                "type": "object",
-                "properties": {
-                    "command": {
-                        "type": "string",
-                        "description": "The command to run"
-                    },
-                    "declared_read_paths": {
-                        "type": "array",
-                        "items": {"type": "string"},
-                        "description": "Optional explicit paths the command will read"
-                    },
-                    "declared_write_paths": {
-                        "type": "array",
-                        "items": {"type": "string"},
-                        "description": "Optional explicit paths the command will write"
-                    },
-                    "requires_network": {
-                        "type": "boolean",
-                        "description": "Set true if the command needs network access"
-                    },
-                    "retry_policy": {
-                        "type": "string",
-                        "enum": ["never", "safe"],
-                        "description": "Optional explicit retry policy for transient-safe commands"
-                    }
-                },
-                "required": ["command"]
+        };
+    }
+    json!({"role": m.role, "content": m.content})
+}
+
+/// Sampling defaults for OpenAI and Sambanova when `[api.parameters]` leaves them unset
+const DEFAULT_TEMPERATURE: f64 = 0.1;
+const DEFAULT_TOP_P: f64 = 0.1;
+
+/// Output budget sent with Anthropic requests, which require `max_tokens`
+const ANTHROPIC_MAX_TOKENS: u32 = 4096;
+const ANTHROPIC_VERSION: &str = "2023-06-01";
+
+fn build_anthropic_tools(tools: &ToolRegistry) -> Vec<Value> {
+    tools
+        .tool_functions()
+        .into_iter()
+        .map(|function| {
+            json!({
+                "name": function["name"],
+                "description": function["description"],
+                "input_schema": function["parameters"],
+            })
+        })
+        .collect()
+}
+
+fn anthropic_tool_use(call: &Value, index: usize) -> Value {
+    let input = match call.pointer("/function/arguments") {
+        Some(Value::String(raw)) => serde_json::from_str(raw).unwrap_or_else(|_| json!({})),
+        Some(other) => other.clone(),
+        None => json!({}),
+    };
+    json!({
+        "type": "tool_use",
+        "id": tool_call_id(call, index),
+        "name": call.pointer("/function/name").cloned().unwrap_or_else(|| json!("")),
+        "input": input,
+    })
+}
+
+fn anthropic_image_blocks(m: &Message, vision: bool) -> Vec<Value> {
+    if !vision {
+        return Vec::new();
+    }
+    m.images
+        .iter()
+        .map(|image| {
+            json!({
+                "type": "image",
+                "source": {"type": "base64", "media_type": image.mime_type, "data": image.data}
+            })
+        })
+        .collect()
+}
+
+/// Build a Messages API body: system prompts move to the top-level `system`
+/// field and answered tool calls become `tool_use`/`tool_result` blocks
+fn build_anthropic_request_body(
+    config: &ApiConfig,
+    tools: &ToolRegistry,
+    history: &[Message],
+    stream: bool,
+) -> Value {
+    let answered_ids: HashSet<&str> = history
+        .iter()
+        .filter(|m| m.role == "tool")
+        .filter_map(|m| m.tool_call_id.as_deref())
+        .collect();
+    let mut native_ids = HashSet::new();
+    let mut system = Vec::new();
+    let mut messages: Vec<Value> = Vec::new();
+    let vision = config.supports_vision();
+
+    for m in history {
+        if m.role == "system" {
+            system.push(m.content.as_str());
+            continue;
+        }
+        let (role, blocks) = if let Some(calls) = answered_tool_calls(m, &answered_ids) {
+            let blocks = calls
+                .iter()
+                .enumerate()
+                .map(|(index, call)| {
+                    native_ids.insert(tool_call_id(call, index));
+                    anthropic_tool_use(call, index)
+                })
+                .collect();
+            ("assistant", blocks)
+        } else if m.role == "tool" {
+            let images = anthropic_image_blocks(m, vision);
+            match m.tool_call_id.as_deref() {
+                Some(id) if native_ids.contains(id) => {
+                    let content = if images.is_empty() {
+                        json!(m.content)
+                    } else {
+                        let mut content = vec![json!({"type": "text", "text": m.content})];
+                        content.extend(images);
+                        json!(content)
+                    };
+                    let block =
+                        json!({"type": "tool_result", "tool_use_id": id, "content": content});
+                    ("user", vec![block])
+                }
+                _ => {
+                    let text = format!("Tool result:\n{}", m.content);
+                    let mut blocks = vec![json!({"type": "text", "text": text})];
+                    blocks.extend(images);
+                    ("user", blocks)
+                }
             }
-        }),
-        json!({
-            "name": "search",
-            "description": "Search the web for current external information",
-            "parameters": {
-                "type": "object",
-                "properties": {
-                    "query": {
-                        "type": "string",
-                        "description": "The web search query"
-                    }
-                },
-                "required": ["query"]
+        } else if m.role == "assistant" {
+            (
+                "assistant",
+                vec![json!({"type": "text", "text": m.content})],
+            )
+        } else {
+            let mut blocks = vec![json!({"type": "text", "text": m.content})];
+            blocks.extend(anthropic_image_blocks(m, vision));
+            ("user", blocks)
+        };
+
+        // The Messages API expects alternating turns, so merge consecutive
+        // messages from the same side into one turn
+        match messages.last_mut() {
+            Some(last) if last["role"] == role => {
+                if let Some(content) = last["content"].as_array_mut() {
+                    content.extend(blocks);
+                }
             }
-        }),
-        json!({
-            "name": "adx_query",
-            "description": "Run a read-only KQL query against Azure Data Explorer. Credentials come from HARPER_ADX_* environment variables unless explicitly provided.",
-            "parameters": {
-                "type": "object",
-                "properties": {
-                    "cluster_url": {
-                        "type": "string",
-                        "description": "Azure Data Explorer cluster URL, for example https://help.kusto.windows.net"
-                    },
-                    "database": {
-                        "type": "string",
-                        "description": "ADX database name"
-                    },
-                    "query": {
-                        "type": "string",
-                        "description": "Read-only KQL query. Management commands that start with a dot are not allowed."
-                    }
-                },
-                "required": ["query"]
+            _ => messages.push(json!({"role": role, "content": blocks})),
+        }
+    }
+
+    let mut body = json!({
+        "model": config.model_name,
+        "max_tokens": config.parameters.max_tokens.unwrap_or(ANTHROPIC_MAX_TOKENS),
+        "messages": messages,
+        "tools": build_anthropic_tools(tools),
+    });
+    if let Some(temperature) = config.parameters.temperature {
+        body["temperature"] = json!(temperature);
+    }
+    if let Some(top_p) = config.parameters.top_p {
+        body["top_p"] = json!(top_p);
+    }
+    if !system.is_empty() {
+        body["system"] = json!(system.join("\n\n"));
+    }
+    if stream {
+        body["stream"] = json!(true);
+    }
+    body
+}
+
+fn build_ollama_request_body(
+    config: &ApiConfig,
+    tools: &ToolRegistry,
+    history: &[Message],
+    stream: bool,
+) -> Value {
+    let mut body = json!({
+        "model": config.model_name,
+        "messages": build_chat_messages(history, &config.provider, config.supports_vision()),
+        "tools": build_function_tools(tools),
+        "stream": stream,
+    });
+    let mut options = serde_json::Map::new();
+    if let Some(temperature) = config.parameters.temperature {
+        options.insert("temperature".to_string(), json!(temperature));
+    }
+    if let Some(top_p) = config.parameters.top_p {
+        options.insert("top_p".to_string(), json!(top_p));
+    }
+    if let Some(max_tokens) = config.parameters.max_tokens {
+        options.insert("num_predict".to_string(), json!(max_tokens));
+    }
+    if !options.is_empty() {
+        body["options"] = Value::Object(options);
+    }
+    body
+}
+
+fn gemini_stream_url(base_url: &str) -> String {
+    let url = base_url.replacen(":generateContent", ":streamGenerateContent", 1);
+    if url.contains("alt=sse") {
+        url
+    } else if url.contains('?') {
+        format!("{}&alt=sse", url)
+    } else {
+        format!("{}?alt=sse", url)
+    }
+}
+
+/// Build the request for one provider, declaring the tools of the session
+///
+/// Fallback providers are given the same `tools` as the primary one.
+fn build_chat_request(
+    client: &reqwest::Client,
+    config: &ApiConfig,
+    tools: &ToolRegistry,
+    history: &[Message],
+    stream: bool,
+) -> reqwest::RequestBuilder {
+    config.headers.iter().fold(
+        build_provider_request(client, config, tools, history, stream),
+        |request, (name, value)| request.header(name.as_str(), value.as_str()),
+    )
+}
+
+fn build_provider_request(
+    client: &reqwest::Client,
+    config: &ApiConfig,
+    tools: &ToolRegistry,
+    history: &[Message],
+    stream: bool,
+) -> reqwest::RequestBuilder {
+    match config.provider {
+        ApiProvider::OpenAI | ApiProvider::Sambanova => {
+            let extra_query = String::new();
+
+            let mut body = json!({
+                "model": config.model_name,
+                "messages": build_chat_messages(history, &config.provider, config.supports_vision()),
+                "tools": build_function_tools(tools),
+                "tool_choice": "auto",
+                "temperature": config.parameters.temperature.unwrap_or(DEFAULT_TEMPERATURE),
+                "top_p": config.parameters.top_p.unwrap_or(DEFAULT_TOP_P),
+                "extra_query": extra_query,
+            });
+            if let Some(max_tokens) = config.parameters.max_tokens {
+                body["max_tokens"] = json!(max_tokens);
             }
-        }),
-        json!({
-            "name": "todo",
-            "description": "Manage todo list. Supported actions: add, list, remove, clear",
-            "parameters": {
-                "type": "object",
-                "properties": {
-                    "action": {
-                        "type": "string",
-                        "enum": ["add", "list", "remove", "clear"],
-                        "description": "The action to perform"
-                    },
-                    "description": {
-                        "type": "string",
-                        "description": "Description for 'add' action"
-                    },
-                    "index": {
-                        "type": "integer",
-                        "description": "1-based index for 'remove' action"
-                    }
-                },
-                "required": ["action"]
+            if stream {
+                body["stream"] = json!(true);
+                if matches!(config.provider, ApiProvider::OpenAI) {
+                    // Usage is only reported in a final chunk when asked for
+                    body["stream_options"] = json!({"include_usage": true});
+                }
             }
-        }),
-        json!({
-            "name": "update_plan",
-            "description": "Update the current execution plan for the active session",
-            "parameters": {
-                "type": "object",
-                "properties": {
-                    "explanation": {
-                        "type": "string",
-                        "description": "Optional note explaining the current plan or why it changed"
-                    },
-                    "authoring_plan": {
-                        "type": "object",
-                        "description": "Optional structured authoring plan for open-ended code changes",
-                        "properties": {
-                            "primary_files": {"type": "array", "items": {"type": "string"}},
-                            "supporting_files": {"type": "array", "items": {"type": "string"}},
-                            "validation_files": {"type": "array", "items": {"type": "string"}},
-                            "planned_edits": {
-                                "type": "array",
-                                "items": {
-                                    "type": "object",
-                                    "properties": {
-                                        "path": {"type": "string"},
-                                        "change": {"type": "string"},
-                                        "why": {"type": "string"}
-                                    },
-                                    "required": ["path", "change"]
-                                }
-                            },
-                            "validation_plan": {
-                                "type": "array",
-                                "items": {
-                                    "type": "object",
-                                    "properties": {
-                                        "command": {"type": "string"},
-                                        "scope": {"type": "string"}
-                                    },
-                                    "required": ["command"]
-                                }
-                            }
-                        }
-                    },
-                    "items": {
-                        "type": "array",
-                        "description": "Ordered plan steps",
-                        "items": {
-                            "type": "object",
-                            "properties": {
-                                "step": {
-                                    "type": "string",
-                                    "description": "Short step description"
-                                },
-                                "status": {
-                                    "type": "string",
-                                    "enum": ["pending", "in_progress", "completed", "blocked"],
-                                    "description": "Current status of the step"
-                                }
-                            },
-                            "required": ["step", "status"]
-                        }
+            client
+                .post(&config.base_url)
+                .header(AUTHORIZATION, format!("Bearer {}", config.api_key))
+                .header(CONTENT_TYPE, "application/json")
+                .json(&body)
+        }
+        ApiProvider::OpenAiCompatible => {
+            let mut body = json!({
+                "model": config.model_name,
+                "messages": build_chat_messages(history, &config.provider, config.supports_vision()),
+                "tools": build_function_tools(tools),
+                "tool_choice": "auto",
+            });
+            // Leave unset parameters to the server, whose defaults are tuned
+            // for the model it serves
+            if let Some(temperature) = config.parameters.temperature {
+                body["temperature"] = json!(temperature);
+            }
+            if let Some(top_p) = config.parameters.top_p {
+                body["top_p"] = json!(top_p);
+            }
+            if let Some(max_tokens) = config.parameters.max_tokens {
+                body["max_tokens"] = json!(max_tokens);
+            }
+            if stream {
+                body["stream"] = json!(true);
+            }
+            let request = client
+                .post(&config.base_url)
+                .header(CONTENT_TYPE, "application/json");
+            let request = if config.api_key.trim().is_empty() {
+                request
+            } else {
+                request.header(AUTHORIZATION, format!("Bearer {}", config.api_key))
+            };
+            request.json(&body)
+        }
+        ApiProvider::Gemini => {
+            let mut system_instructions = Vec::new();
+            let mut gemini_contents = Vec::new();
+            let vision = config.supports_vision();
+
+            for msg in history {
+                if msg.role == "system" {
+                    system_instructions.push(msg.content.as_str());
+                } else {
+                    let role = if msg.role == "assistant" {
+                        "model"
+                    } else {
+                        "user"
+                    };
+                    let text = if msg.role == "tool" {
+                        format!("Tool result:\n{}", msg.content)
+                    } else {
+                        msg.content.clone()
+                    };
+                    let mut parts = vec![json!({"text": text})];
+                    if vision && role == "user" {
+                        parts.extend(msg.images.iter().map(|image| {
+                            json!({"inline_data": {"mime_type": image.mime_type, "data": image.data}})
+                        }));
                     }
-                },
-                "required": ["items"]
+                    gemini_contents.push(json!({
+                        "role": role,
+                        "parts": parts
+                    }));
+                }
             }
-        }),
-        json!({
-            "name": "git_status",
-            "description": "Get the current git status",
-            "parameters": {
-                "type": "object",
-                "properties": {},
-                "required": []
-            }
-        }),
-        json!({
-            "name": "git_diff",
-            "description": "Get the git diff",
-            "parameters": {
-                "type": "object",
-                "properties": {},
-                "required": []
-            }
-        }),
-        json!({
-            "name": "git_commit",
-            "description": "Commit changes with a message",
-            "parameters": {
-                "type": "object",
-                "properties": {
-                    "message": {
-                        "type": "string",
-                        "description": "The commit message"
+
+            let tools = json!({
+                "function_declarations": tools.tool_functions()
+            });
+
+            let mut body = json!({
+                "contents": gemini_contents,
+                "tools": [tools]
+            });
+
+            let mut generation_config = serde_json::Map::new();
+            if let Some(temperature) = config.parameters.temperature {
+                generation_config.insert("temperature".to_string(), json!(temperature));
+            }
+            if let Some(top_p) = config.parameters.top_p {
+                generation_config.insert("topP".to_string(), json!(top_p));
+            }
+            if let Some(max_tokens) = config.parameters.max_tokens {
+                generation_config.insert("maxOutputTokens".to_string(), json!(max_tokens));
+            }
+            if !generation_config.is_empty() {
+                body["generationConfig"] = Value::Object(generation_config);
+            }
+
+            if !system_instructions.is_empty() {
+                body["systemInstruction"] = json!({
+                    "parts": [{"text": system_instructions.join("\n\n")}]
+                });
+            }
+            let url = if stream {
+                gemini_stream_url(&config.base_url)
+            } else {
+                config.base_url.clone()
+            };
+            client
+                .post(&url)
+                .header(CONTENT_TYPE, "application/json")
+                .header("x-goog-api-key", &config.api_key)
+                .json(&body)
+        }
+        ApiProvider::Anthropic => client
+            .post(&config.base_url)
+            .header(CONTENT_TYPE, "application/json")
+            .header("x-api-key", &config.api_key)
+            .header("anthropic-version", ANTHROPIC_VERSION)
+            .json(&build_anthropic_request_body(
+                config, tools, history, stream,
+            )),
+        ApiProvider::Ollama => client
+            .post(&config.base_url)
+            .header(CONTENT_TYPE, "application/json")
+            .json(&build_ollama_request_body(config, tools, history, stream)),
+    }
+}
+
+/// How the fallback chain should react to a failed request
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum FailureAction {
+    /// Surface the error; another provider would fail the same way
+    Fatal,
+    /// Try the next provider straight away
+    Failover,
+    /// Back off and retry, then move down the chain
+    Retry(Option<Duration>),
+}
+
+#[derive(Debug)]
+struct RequestFailure {
+    error: HarperError,
+    action: FailureAction,
+}
+
+impl RequestFailure {
+    /// Failures the chain may retry or fail over become
+    /// [`HarperError::Unavailable`], so callers can tell them from fatal ones
+    fn new(message: String, action: FailureAction) -> Self {
+        let error = match action {
+            FailureAction::Fatal => HarperError::Api(message),
+            FailureAction::Failover | FailureAction::Retry(_) => HarperError::Unavailable(message),
+        };
+        Self { error, action }
+    }
+}
+
+async fn send_chat_request(
+    request: reqwest::RequestBuilder,
+) -> Result<reqwest::Response, RequestFailure> {
+    let res = match request.send().await {
+        Ok(res) => res,
+        Err(err) => {
+            let action = if err.is_connect() || err.is_timeout() {
+                FailureAction::Failover
+            } else {
+                FailureAction::Fatal
+            };
+            return Err(RequestFailure::new(err.to_string(), action));
+        }
+    };
+
+    if !res.status().is_success() {
+        let status = res.status();
+        let action = if status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error() {
+            FailureAction::Retry(
+                res.headers()
+                    .get(RETRY_AFTER)
+                    .and_then(|value| value.to_str().ok())
+                    .and_then(parse_retry_after),
+            )
+        } else {
+            FailureAction::Fatal
+        };
+        let error_text = res
+            .text()
+            .await
+            .unwrap_or_else(|_| "Could not read error body".to_string());
+        return Err(RequestFailure::new(
+            format_api_error(status, &error_text),
+            action,
+        ));
+    }
+
+    Ok(res)
+}
+
+/// Parse a `Retry-After` header given either as seconds or as an HTTP date
+fn parse_retry_after(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<f64>() {
+        return Duration::try_from_secs_f64(seconds).ok();
+    }
+    let at = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    Some(
+        (at.with_timezone(&chrono::Utc) - chrono::Utc::now())
+            .to_std()
+            .unwrap_or(Duration::ZERO),
+    )
+}
+
+/// Delay before retry number `attempt + 1`, or `None` when the provider asks
+/// for a longer wait than the policy allows
+fn backoff_delay(
+    policy: &RetryPolicy,
+    attempt: u32,
+    retry_after: Option<Duration>,
+) -> Option<Duration> {
+    let max = Duration::from_millis(policy.max_backoff_ms);
+    match retry_after {
+        Some(delay) if delay > max => None,
+        Some(delay) => Some(delay),
+        None => Some(
+            Duration::from_millis(policy.initial_backoff_ms)
+                .saturating_mul(2u32.saturating_pow(attempt))
+                .min(max),
+        ),
+    }
+}
+
+async fn send_with_retry(
+    request: reqwest::RequestBuilder,
+    policy: &RetryPolicy,
+) -> Result<reqwest::Response, RequestFailure> {
+    let mut attempt = 0;
+    loop {
+        let Some(next) = request.try_clone() else {
+            return send_chat_request(request).await;
+        };
+        let failure = match send_chat_request(next).await {
+            Ok(res) => return Ok(res),
+            Err(failure) => failure,
+        };
+        let FailureAction::Retry(retry_after) = failure.action else {
+            return Err(failure);
+        };
+        if attempt >= policy.max_retries {
+            return Err(failure);
+        }
+        let Some(delay) = backoff_delay(policy, attempt, retry_after) else {
+            return Err(failure);
+        };
+        tokio::time::sleep(delay).await;
+        attempt += 1;
+    }
+}
+
+/// Send the request to the primary provider, then to each fallback in order
+/// until one answers, returning the response with the config that served it
+///
+/// Rate limits, server errors and unreachable endpoints move down the chain;
+/// any other failure is returned as-is. `on_fallback` is told about every
+/// fallback provider before it is tried.
+async fn send_with_fallback<'c>(
+    client: &reqwest::Client,
+    config: &'c ApiConfig,
+    tools: &ToolRegistry,
+    history: &[Message],
+    stream: bool,
+    on_fallback: &mut (dyn FnMut(&ApiConfig) + Send),
+) -> HarperResult<(reqwest::Response, &'c ApiConfig)> {
+    let mut last_error = None;
+    for (index, candidate) in std::iter::once(config)
+        .chain(config.fallback.providers.iter())
+        .enumerate()
+    {
+        if index > 0 {
+            on_fallback(candidate);
+        }
+        let request = build_chat_request(client, candidate, tools, history, stream);
+        match send_with_retry(request, &config.fallback.retry).await {
+            Ok(res) => return Ok((res, candidate)),
+            Err(failure) if failure.action == FailureAction::Fatal => return Err(failure.error),
+            Err(failure) => last_error = Some(failure.error),
+        }
+    }
+    Err(last_error.unwrap_or_else(|| HarperError::Api("No LLM provider configured".to_string())))
+}
+
+/// Splits a streamed response body into JSON payloads
+///
+/// OpenAI-style providers, Gemini and Anthropic frame their output as
+/// server-sent events, while Ollama writes one JSON object per line.
+#[derive(Debug)]
+struct StreamDecoder {
+    sse: bool,
+    buffer: Vec<u8>,
+    event_data: Vec<String>,
+}
+
+impl StreamDecoder {
+    fn new(provider: &ApiProvider) -> Self {
+        Self {
+            sse: !matches!(provider, ApiProvider::Ollama),
+            buffer: Vec::new(),
+            event_data: Vec::new(),
+        }
+    }
+
+    /// Feed a raw body chunk, returning every payload it completed
+    fn push(&mut self, chunk: &[u8]) -> Vec<String> {
+        self.buffer.extend_from_slice(chunk);
+        let mut payloads = Vec::new();
+        while let Some(pos) = self.buffer.iter().position(|byte| *byte == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=pos).collect();
+            let line = String::from_utf8_lossy(&line);
+            self.decode_line(line.trim_end_matches(['\r', '\n']), &mut payloads);
+        }
+        payloads
+    }
+
+    /// Flush whatever is left once the body has ended
+    fn finish(&mut self) -> Vec<String> {
+        let mut payloads = Vec::new();
+        if !self.buffer.is_empty() {
+            let line = String::from_utf8_lossy(&self.buffer).to_string();
+            self.buffer.clear();
+            self.decode_line(line.trim_end_matches('\r'), &mut payloads);
+        }
+        if self.sse {
+            self.decode_line("", &mut payloads);
+        }
+        payloads
+    }
+
+    fn decode_line(&mut self, line: &str, payloads: &mut Vec<String>) {
+        if !self.sse {
+            if !line.trim().is_empty() {
+                payloads.push(line.trim().to_string());
+            }
+            return;
+        }
+
+        if line.is_empty() {
+            if !self.event_data.is_empty() {
+                payloads.push(self.event_data.join("\n"));
+                self.event_data.clear();
+            }
+        } else if let Some(data) = line.strip_prefix("data:") {
+            self.event_data
+                .push(data.strip_prefix(' ').unwrap_or(data).to_string());
+        }
+    }
+}
+
+/// Collects streamed deltas into the same reply `call_llm` would return
+#[derive(Debug)]
+struct StreamAccumulator {
+    provider: ApiProvider,
+    content: String,
+    tool_calls: Vec<Value>,
+    function_call: Option<Value>,
+    usage: Option<TokenUsage>,
+}
+
+impl StreamAccumulator {
+    fn new(provider: ApiProvider) -> Self {
+        Self {
+            provider,
+            content: String::new(),
+            tool_calls: Vec::new(),
+            function_call: None,
+            usage: None,
+        }
+    }
+
+    /// Apply one decoded payload, returning the text delta it carried
+    fn apply(&mut self, payload: &str) -> HarperResult<Option<String>> {
+        if payload.trim() == "[DONE]" {
+            return Ok(None);
+        }
+        let Ok(event) = serde_json::from_str::<Value>(payload) else {
+            return Ok(None);
+        };
+        if let Some(error) = event.get("error") {
+            let message = error
+                .get("message")
+                .and_then(|v| v.as_str())
+                .map(|s| s.to_string())
+                .unwrap_or_else(|| error.to_string());
+            return Err(HarperError::Api(message));
+        }
+        if let Some(usage) = TokenUsage::from_response(&self.provider, &event) {
+            self.usage
+                .get_or_insert_with(TokenUsage::default)
+                .merge(usage);
+        }
+
+        let mut delta = String::new();
+        match self.provider {
+            ApiProvider::OpenAI | ApiProvider::Sambanova | ApiProvider::OpenAiCompatible => {
+                let choice_delta = &event["choices"][0]["delta"];
+                if let Some(text) = choice_delta.get("content").and_then(|v| v.as_str()) {
+                    delta.push_str(text);
+                }
+                if let Some(calls) = choice_delta.get("tool_calls").and_then(|v| v.as_array()) {
+                    for (position, call) in calls.iter().enumerate() {
+                        let index = call
+                            .get("index")
+                            .and_then(|v| v.as_u64())
+                            .map(|v| v as usize)
+                            .unwrap_or(position);
+                        self.merge_tool_call_delta(index, call);
                     }
-                },
-                "required": ["message"]
+                }
             }
-        }),
-        json!({
-            "name": "git_add",
-            "description": "Add files to git staging",
-            "parameters": {
-                "type": "object",
-                "properties": {
-                    "files": {
-                        "type": "string",
-                        "description": "Files to add (space-separated)"
+            ApiProvider::Gemini => {
+                if let Some(parts) = event["candidates"][0]["content"]["parts"].as_array() {
+                    for part in parts {
+                        if let Some(function_call) = part.get("functionCall") {
+                            self.function_call = Some(function_call.clone());
+                        } else if let Some(text) = part.get("text").and_then(|v| v.as_str()) {
+                            delta.push_str(text);
+                        }
                     }
-                },
-                "required": ["files"]
+                }
             }
-        }),
-        json!({
-            "name": "list_changed_files",
-            "description": "List changed files with optional filters",
-            "parameters": {
-                "type": "object",
-                "properties": {
-                    "ext": {
-                        "type": "string",
-                        "description": "Optional file extension filter, for example 'rs'"
-                    },
-                    "tracked_only": {
-                        "type": "boolean",
-                        "description": "When true, exclude untracked files"
-                    },
-                    "since": {
-                        "type": "string",
-                        "description": "Optional git --since expression, for example 'today'"
+            ApiProvider::Anthropic => match event["type"].as_str() {
+                Some("content_block_start") => {
+                    let block = &event["content_block"];
+                    if block["type"] == "tool_use" {
+                        let index = self.tool_calls.len();
+                        self.merge_tool_call_delta(
+                            index,
+                            &json!({
+                                "id": block["id"],
+                                "type": "function",
+                                "function": {"name": block["name"]}
+                            }),
+                        );
+                    } else if let Some(text) = block.get("text").and_then(|v| v.as_str()) {
+                        delta.push_str(text);
+                    }
+                }
+                Some("content_block_delta") => {
+                    let block_delta = &event["delta"];
+                    match block_delta["type"].as_str() {
+                        Some("text_delta") => {
+                            if let Some(text) = block_delta["text"].as_str() {
+                                delta.push_str(text);
+                            }
+                        }
+                        Some("input_json_delta") if !self.tool_calls.is_empty() => {
+                            let index = self.tool_calls.len() - 1;
+                            self.merge_tool_call_delta(
+                                index,
+                                &json!({"function": {"arguments": block_delta["partial_json"]}}),
+                            );
+                        }
+                        _ => {}
                     }
-                },
-                "required": []
+                }
+                _ => {}
+            },
+            ApiProvider::Ollama => {
+                let message = event.get("message");
+                if let Some(calls) = message
+                    .and_then(|msg| msg.get("tool_calls"))
+                    .and_then(|v| v.as_array())
+                {
+                    self.tool_calls.extend(calls.iter().cloned());
+                }
+                if let Some(text) = message
+                    .and_then(|msg| msg.get("content"))
+                    .or_else(|| event.get("response"))
+                    .and_then(|v| v.as_str())
+                {
+                    delta.push_str(text);
+                }
             }
-        }),
-    ]
-}
+        }
 
-fn build_ollama_tools() -> Vec<Value> {
-    built_in_tool_functions()
-        .into_iter()
-        .map(|function| json!({"type": "function", "function": function}))
-        .collect()
-}
+        if delta.is_empty() {
+            return Ok(None);
+        }
+        self.content.push_str(&delta);
+        Ok(Some(delta))
+    }
 
-fn build_ollama_request_body(config: &ApiConfig, history: &[Message]) -> Value {
-    let messages_json: Vec<_> = history
-        .iter()
-        .map(|m| json!({"role": m.role, "content": m.content}))
-        .collect();
+    fn merge_tool_call_delta(&mut self, index: usize, call: &Value) {
+        while self.tool_calls.len() <= index {
+            self.tool_calls.push(json!({
+                "type": "function",
+                "function": {"name": "", "arguments": ""}
+            }));
+        }
+        let entry = &mut self.tool_calls[index];
+        if let Some(id) = call.get("id") {
+            entry["id"] = id.clone();
+        }
+        if let Some(kind) = call.get("type") {
+            entry["type"] = kind.clone();
+        }
+        if let Some(name) = call.pointer("/function/name").and_then(|v| v.as_str()) {
+            entry["function"]["name"] = json!(name);
+        }
+        match call.pointer("/function/arguments") {
+            Some(Value::String(fragment)) => {
+                let mut arguments = entry["function"]["arguments"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .to_string();
+                arguments.push_str(fragment);
+                entry["function"]["arguments"] = json!(arguments);
+            }
+            Some(other) => entry["function"]["arguments"] = other.clone(),
+            None => {}
+        }
+    }
 
-    json!({
-        "model": config.model_name,
-        "messages": messages_json,
-        "tools": build_ollama_tools(),
-        "stream": false,
-    })
+    fn finish(mut self) -> (String, Option<TokenUsage>) {
+        // Anthropic streams no input fragments for tools called without arguments
+        for call in &mut self.tool_calls {
+            if call["function"]["arguments"] == "" {
+                call["function"]["arguments"] = json!("{}");
+            }
+        }
+        let content = if !self.tool_calls.is_empty() {
+            serde_json::to_string(&self.tool_calls).unwrap_or_else(|_| "[No response]".to_string())
+        } else if let Some(function_call) = self.function_call {
+            serde_json::to_string(&function_call).unwrap_or_else(|_| "[No response]".to_string())
+        } else if self.content.is_empty() {
+            "[No response]".to_string()
+        } else {
+            self.content
+        };
+        (content, self.usage)
+    }
 }
 
 fn extract_assistant_reply(provider: &ApiProvider, resp_json: &Value) -> String {
     match provider {
-        ApiProvider::OpenAI | ApiProvider::Sambanova => {
+        ApiProvider::OpenAI | ApiProvider::Sambanova | ApiProvider::OpenAiCompatible => {
             let message = &resp_json["choices"][0]["message"];
-            if let Some(tool_calls) = message.get("tool_calls") {
+            if let Some(tool_calls) = message
+                .get("tool_calls")
+                .filter(|calls| calls.as_array().is_some_and(|calls| !calls.is_empty()))
+            {
                 serde_json::to_string(tool_calls).unwrap_or_else(|_| "[No response]".to_string())
             } else {
                 message["content"]
@@ -357,6 +972,37 @@ fn extract_assistant_reply(provider: &ApiProvider, resp_json: &Value) -> String
                     .to_string()
             }
         }
+        ApiProvider::Anthropic => {
+            let blocks = resp_json["content"].as_array().cloned().unwrap_or_default();
+            let tool_calls: Vec<Value> = blocks
+                .iter()
+                .filter(|block| block["type"] == "tool_use")
+                .map(|block| {
+                    json!({
+                        "id": block["id"],
+                        "type": "function",
+                        "function": {
+                            "name": block["name"],
+                            "arguments": block.get("input").unwrap_or(&json!({})).to_string(),
+                        }
+                    })
+                })
+                .collect();
+            if !tool_calls.is_empty() {
+                return serde_json::to_string(&tool_calls)
+                    .unwrap_or_else(|_| "[No response]".to_string());
+            }
+            let text: String = blocks
+                .iter()
+                .filter(|block| block["type"] == "text")
+                .filter_map(|block| block["text"].as_str())
+                .collect();
+            if text.is_empty() {
+                "[No response]".to_string()
+            } else {
+                text
+            }
+        }
         ApiProvider::Ollama => {
             if let Some(tool_calls) = resp_json
                 .get("message")
@@ -385,11 +1031,13 @@ fn extract_assistant_reply(provider: &ApiProvider, resp_json: &Value) -> String
 /// Call the configured LLM API with conversation history
 ///
 /// Sends a request to the configured AI provider with the conversation history
-/// and returns the AI's response.
+/// and returns the AI's response. Rate-limited and failing providers are retried
+/// with backoff before the request moves on to `config.fallback.providers`.
 ///
 /// # Arguments
 /// * `client` - HTTP client for making API requests
 /// * `config` - API configuration including provider, key, and model
+/// * `tools` - Tools declared to the model
 /// * `history` - Conversation history as a slice of messages
 ///
 /// # Returns
@@ -400,104 +1048,199 @@ fn extract_assistant_reply(provider: &ApiProvider, resp_json: &Value) -> String
 pub async fn call_llm(
     client: &reqwest::Client,
     config: &ApiConfig,
+    tools: &ToolRegistry,
     history: &[Message],
 ) -> HarperResult<String> {
-    let res = match config.provider {
-        ApiProvider::OpenAI | ApiProvider::Sambanova => {
-            let messages_json: Vec<_> = history
-                .iter()
-                .map(|m| json!({"role": m.role, "content": m.content}))
-                .collect();
+    call_llm_reply(client, config, tools, history)
+        .await
+        .map(|reply| reply.content)
+}
 
-            let extra_query = String::new();
+/// A completed LLM call with the usage the provider reported for it
+#[derive(Debug, Clone)]
+pub struct LlmReply {
+    /// The reply in the shape `call_llm` returns
+    pub content: String,
+    /// Provider that answered, which differs from the configured one after a fallback
+    pub provider: ApiProvider,
+    pub model: String,
+    pub usage: Option<TokenUsage>,
+    /// Cost in USD when the answering model has prices configured
+    pub estimated_cost: Option<f64>,
+}
 
-            let body = json!({
-                "model": config.model_name,
-                "messages": messages_json,
-                "temperature": 0.1,
-                "top_p": 0.1,
-                "extra_query": extra_query,
-            });
-            client
-                .post(&config.base_url)
-                .header(AUTHORIZATION, format!("Bearer {}", config.api_key))
-                .header(CONTENT_TYPE, "application/json")
-                .json(&body)
-                .send()
-                .await?
+impl LlmReply {
+    fn new(content: String, served_by: &ApiConfig, usage: Option<TokenUsage>) -> Self {
+        Self {
+            content,
+            provider: served_by.provider,
+            model: served_by.model_name.clone(),
+            estimated_cost: usage.and_then(|usage| usage.estimate_cost(&served_by.parameters)),
+            usage,
         }
-        ApiProvider::Gemini => {
-            let mut system_instructions = Vec::new();
-            let mut gemini_contents = Vec::new();
+    }
+}
 
-            for msg in history {
-                if msg.role == "system" {
-                    system_instructions.push(msg.content.as_str());
-                } else {
-                    let role = if msg.role == "assistant" {
-                        "model"
-                    } else {
-                        "user"
-                    };
-                    gemini_contents.push(json!({
-                        "role": role,
-                        "parts": [{"text": msg.content}]
-                    }));
-                }
-            }
+/// Works like [`call_llm`] but also returns the token usage of the call
+pub async fn call_llm_reply(
+    client: &reqwest::Client,
+    config: &ApiConfig,
+    tools: &ToolRegistry,
+    history: &[Message],
+) -> HarperResult<LlmReply> {
+    let (res, served_by) =
+        send_with_fallback(client, config, tools, history, false, &mut |_| {}).await?;
 
-            let tools = json!({
-                "function_declarations": built_in_tool_functions()
-            });
+    let resp_json: serde_json::Value = res
+        .json()
+        .await
+        .map_err(|e| HarperError::Api(e.to_string()))?;
 
-            let mut body = json!({
-                "contents": gemini_contents,
-                "tools": [tools]
-            });
+    let assistant_reply = extract_assistant_reply(&served_by.provider, &resp_json);
+    let usage = TokenUsage::from_response(&served_by.provider, &resp_json);
 
-            if !system_instructions.is_empty() {
-                body["systemInstruction"] = json!({
-                    "parts": [{"text": system_instructions.join("
+    Ok(LlmReply::new(assistant_reply, served_by, usage))
+}
 
-")}]
-                });
-            }
-            let url = config.base_url.clone();
-            client
-                .post(&url)
-                .header(CONTENT_TYPE, "application/json")
-                .header("x-goog-api-key", &config.api_key)
-                .json(&body)
-                .send()
-                .await?
+/// Call the configured LLM API and stream the reply as it is generated
+///
+/// Works like [`call_llm`] but requests a streamed response: server-sent events
+/// for OpenAI-style providers, Gemini and Anthropic, newline-delimited JSON for
+/// Ollama. Each text delta is passed to `on_delta` as soon as it arrives.
+///
+/// # Returns
+/// The complete reply, including serialized tool calls, in the same shape
+/// `call_llm` returns
+///
+/// # Errors
+/// Returns `HarperError` if the request fails or the provider reports an error mid-stream
+pub async fn call_llm_stream<F>(
+    client: &reqwest::Client,
+    config: &ApiConfig,
+    tools: &ToolRegistry,
+    history: &[Message],
+    on_delta: F,
+) -> HarperResult<String>
+where
+    F: FnMut(&str) + Send,
+{
+    call_llm_stream_reply(client, config, tools, history, on_delta)
+        .await
+        .map(|reply| reply.content)
+}
+
+/// Works like [`call_llm_stream`] but also returns the token usage of the call
+pub async fn call_llm_stream_reply<F>(
+    client: &reqwest::Client,
+    config: &ApiConfig,
+    tools: &ToolRegistry,
+    history: &[Message],
+    on_delta: F,
+) -> HarperResult<LlmReply>
+where
+    F: FnMut(&str) + Send,
+{
+    stream_with_fallback(client, config, tools, history, on_delta, &mut |_| {}).await
+}
+
+async fn stream_with_fallback<F>(
+    client: &reqwest::Client,
+    config: &ApiConfig,
+    tools: &ToolRegistry,
+    history: &[Message],
+    mut on_delta: F,
+    on_fallback: &mut (dyn FnMut(&ApiConfig) + Send),
+) -> HarperResult<LlmReply>
+where
+    F: FnMut(&str) + Send,
+{
+    let (mut res, served_by) =
+        send_with_fallback(client, config, tools, history, true, on_fallback).await?;
+    let mut decoder = StreamDecoder::new(&served_by.provider);
+    let mut accumulator = StreamAccumulator::new(served_by.provider);
+
+    while let Some(chunk) = res.chunk().await? {
+        for payload in decoder.push(&chunk) {
+            if let Some(delta) = accumulator.apply(&payload)? {
+                on_delta(&delta);
+            }
         }
-        ApiProvider::Ollama => {
-            client
-                .post(&config.base_url)
-                .header(CONTENT_TYPE, "application/json")
-                .json(&build_ollama_request_body(config, history))
-                .send()
-                .await?
+    }
+    for payload in decoder.finish() {
+        if let Some(delta) = accumulator.apply(&payload)? {
+            on_delta(&delta);
         }
-    };
-
-    if !res.status().is_success() {
-        let status = res.status();
-        let error_text = res
-            .text()
-            .await
-            .unwrap_or_else(|_| "Could not read error body".to_string());
-        return Err(HarperError::Api(format_api_error(status, &error_text)));
     }
 
-    let resp_json: serde_json::Value = res
-        .json()
-        .await
-        .map_err(|e| HarperError::Api(e.to_string()))?;
+    let (content, usage) = accumulator.finish();
+    Ok(LlmReply::new(content, served_by, usage))
+}
 
-    let assistant_reply = extract_assistant_reply(&config.provider, &resp_json);
+enum StreamEvent {
+    Delta(String),
+    Fallback(String),
+}
+
+/// Call the LLM, streaming text to `runtime_events` when a sink is attached
+///
+/// Deltas are forwarded in order through `assistant_output_updated`, followed by
+/// a final `done` event, and a switch to a fallback provider is reported through
+/// `activity_updated`. Without a sink this is a plain [`call_llm_reply`].
+pub async fn call_llm_with_events(
+    client: &reqwest::Client,
+    config: &ApiConfig,
+    tools: &ToolRegistry,
+    history: &[Message],
+    runtime_events: Option<Arc<dyn RuntimeEventSink>>,
+    session_id: Option<&str>,
+) -> HarperResult<LlmReply> {
+    let (Some(runtime_events), Some(session_id)) = (runtime_events, session_id) else {
+        return call_llm_reply(client, config, tools, history).await;
+    };
 
-    Ok(assistant_reply)
+    let (event_tx, mut event_rx) = tokio::sync::mpsc::unbounded_channel::<StreamEvent>();
+    let session_id = session_id.to_string();
+    let forwarder = tokio::spawn(async move {
+        while let Some(event) = event_rx.recv().await {
+            let _ = match event {
+                StreamEvent::Delta(chunk) => {
+                    runtime_events
+                        .assistant_output_updated(&session_id, chunk, false)
+                        .await
+                }
+                StreamEvent::Fallback(status) => {
+                    runtime_events
+                        .activity_updated(&session_id, Some(status))
+                        .await
+                }
+            };
+        }
+        let _ = runtime_events
+            .assistant_output_updated(&session_id, String::new(), true)
+            .await;
+    });
+
+    let fallback_tx = event_tx.clone();
+    let result = stream_with_fallback(
+        client,
+        config,
+        tools,
+        history,
+        |delta| {
+            let _ = event_tx.send(StreamEvent::Delta(delta.to_string()));
+        },
+        &mut |provider| {
+            let _ = fallback_tx.send(StreamEvent::Fallback(format!(
+                "falling back to {} ({})",
+                provider.provider, provider.model_name
+            )));
+        },
+    )
+    .await;
+    drop(event_tx);
+    drop(fallback_tx);
+    let _ = forwarder.await;
+    result
 }
 
 fn format_api_error(status: StatusCode, error_text: &str) -> String {
@@ -639,7 +1382,7 @@ pub fn decrypt_data(encrypted_data: &[u8], key: &[u8]) -> HarperResult<Vec<u8>>
 mod tests {
     use super::*;
     use crate::core::constants::test_data;
-    use crate::core::{ApiConfig, ApiProvider, Message};
+    use crate::core::{ApiConfig, ApiProvider, Message, ModelParameters, RetryPolicy};
     use hex_literal::hex;
     use serde_json::json;
 
@@ -649,17 +1392,18 @@ mod tests {
             api_key: String::new(),
             base_url: "http://127.0.0.1:11434/api/chat".to_string(),
             model_name: "qwen2.5:1.5b".to_string(),
+            headers: Default::default(),
+            parameters: Default::default(),
+            fallback: Default::default(),
         }
     }
 
     #[test]
     fn build_ollama_request_body_includes_tools() {
-        let history = vec![Message {
-            role: "user".to_string(),
-            content: "read Cargo.toml".to_string(),
-        }];
+        let history = vec![Message::text("user", "read Cargo.toml")];
 
-        let body = build_ollama_request_body(&test_ollama_config(), &history);
+        let body =
+            build_ollama_request_body(&test_ollama_config(), &Default::default(), &history, false);
         let tools = body
             .get("tools")
             .and_then(|value| value.as_array())
@@ -696,6 +1440,994 @@ mod tests {
         assert!(reply.contains("Cargo.toml"));
     }
 
+    fn tool_call_history() -> Vec<Message> {
+        vec![
+            Message::text("user", "read Cargo.toml"),
+            Message::text("assistant", r#"[{"id":"call_a","type":"function","function":{"name":"read_file","arguments":"{\"path\":\"Cargo.toml\"}"}}]"#.to_string()),
+            Message::tool_result("call_a", "[package]"),
+        ]
+    }
+
+    #[test]
+    fn openai_request_body_declares_native_tools() {
+        let config = stream_config(
+            ApiProvider::OpenAI,
+            "http://127.0.0.1:9/v1/chat/completions".to_string(),
+        );
+        let request = build_chat_request(
+            &reqwest::Client::new(),
+            &config,
+            &ToolRegistry::default(),
+            &stream_history(),
+            false,
+        )
+        .build()
+        .expect("request");
+        let body: Value = serde_json::from_slice(
+            request
+                .body()
+                .and_then(|body| body.as_bytes())
+                .expect("json body"),
+        )
+        .expect("body json");
+
+        assert_eq!(body["tool_choice"], json!("auto"));
+        assert_eq!(body["tools"][0]["type"], json!("function"));
+        assert_eq!(body["tools"][0]["function"]["name"], json!("read_file"));
+    }
+
+    #[test]
+    fn request_bodies_leave_out_disabled_tools() {
+        let tools = ToolRegistry::new(
+            Some(vec!["read_file".to_string(), "git_*".to_string()]),
+            vec!["git_commit".to_string()],
+        );
+        let names = |declared: &Value, key: &str| -> Vec<String> {
+            declared
+                .as_array()
+                .expect("tools array")
+                .iter()
+                .map(|tool| {
+                    tool.pointer(key)
+                        .and_then(|v| v.as_str())
+                        .unwrap()
+                        .to_string()
+                })
+                .collect()
+        };
+        let expected = vec!["read_file", "git_status", "git_diff", "git_add"];
+
+        let ollama = build_ollama_request_body(&test_ollama_config(), &tools, &[], false);
+        assert_eq!(names(&ollama["tools"], "/function/name"), expected);
+
+        let config = stream_config(
+            ApiProvider::Anthropic,
+            "http://127.0.0.1:9/v1/messages".to_string(),
+        );
+        let anthropic = build_anthropic_request_body(&config, &tools, &[], false);
+        assert_eq!(names(&anthropic["tools"], "/name"), expected);
+    }
+
+    #[test]
+    fn build_chat_messages_uses_native_tool_protocol_for_answered_calls() {
+        let messages = build_chat_messages(&tool_call_history(), &ApiProvider::OpenAI, false);
+
+        assert_eq!(messages[1]["role"], json!("assistant"));
+        assert_eq!(messages[1]["content"], Value::Null);
+        assert_eq!(messages[1]["tool_calls"][0]["id"], json!("call_a"));
+        assert_eq!(
+            messages[1]["tool_calls"][0]["function"]["arguments"],
+            json!("{\"path\":\"Cargo.toml\"}")
+        );
+        assert_eq!(messages[2]["role"], json!("tool"));
+        assert_eq!(messages[2]["tool_call_id"], json!("call_a"));
+        assert_eq!(messages[2]["content"], json!("[package]"));
+    }
+
+    #[test]
+    fn build_chat_messages_keeps_unanswered_tool_calls_as_text() {
+        let mut history = tool_call_history();
+        history.pop();
+
+        let messages = build_chat_messages(&history, &ApiProvider::OpenAI, false);
+
+        assert!(messages[1].get("tool_calls").is_none());
+        assert!(messages[1]["content"]
+            .as_str()
+            .expect("text content")
+            .contains("read_file"));
+    }
+
+    #[test]
+    fn build_chat_messages_sends_ollama_arguments_as_objects() {
+        let messages = build_chat_messages(&tool_call_history(), &ApiProvider::Ollama, false);
+
+        assert_eq!(
+            messages[1]["tool_calls"][0]["function"]["arguments"],
+            json!({"path": "Cargo.toml"})
+        );
+        assert_eq!(messages[2]["role"], json!("tool"));
+        assert!(messages[2].get("tool_call_id").is_none());
+    }
+
+    fn screenshot() -> ImageAttachment {
+        ImageAttachment {
+            mime_type: "image/png".to_string(),
+            data: "aGVsbG8=".to_string(),
+        }
+    }
+
+    fn image_tool_history() -> Vec<Message> {
+        let mut history = tool_call_history();
+        let result = history.pop().expect("tool result");
+        history.push(result.with_images(vec![screenshot()]));
+        history
+    }
+
+    #[test]
+    fn build_chat_messages_sends_images_only_with_vision() {
+        let messages = build_chat_messages(&image_tool_history(), &ApiProvider::OpenAI, true);
+        assert_eq!(messages.len(), 4);
+        assert_eq!(messages[2]["role"], json!("tool"));
+        assert_eq!(messages[3]["role"], json!("user"));
+        assert_eq!(
+            messages[3]["content"][1]["image_url"]["url"],
+            json!("data:image/png;base64,aGVsbG8=")
+        );
+
+        let ollama = build_chat_messages(&image_tool_history(), &ApiProvider::Ollama, true);
+        assert_eq!(ollama.len(), 3);
+        assert_eq!(ollama[2]["images"], json!(["aGVsbG8="]));
+
+        let text_only = build_chat_messages(&image_tool_history(), &ApiProvider::OpenAI, false);
+        assert_eq!(text_only.len(), 3);
+        assert!(text_only[2].get("images").is_none());
+    }
+
+    #[test]
+    fn vision_support_follows_provider_model_and_override() {
+        let mut config = stream_config(ApiProvider::OpenAI, String::new());
+        config.model_name = "gpt-4o-mini".to_string();
+        assert!(config.supports_vision());
+        config.model_name = "gpt-3.5-turbo".to_string();
+        assert!(!config.supports_vision());
+
+        let mut config = test_ollama_config();
+        assert!(!config.supports_vision());
+        config.parameters.vision = Some(true);
+        assert!(config.supports_vision());
+        assert!(stream_config(ApiProvider::Anthropic, String::new()).supports_vision());
+    }
+
+    #[test]
+    fn extract_assistant_reply_ignores_null_openai_tool_calls() {
+        let resp_json = json!({
+            "choices": [{"message": {"role": "assistant", "content": "Done.", "tool_calls": null}}]
+        });
+
+        assert_eq!(
+            extract_assistant_reply(&ApiProvider::OpenAI, &resp_json),
+            "Done."
+        );
+    }
+
+    #[test]
+    fn anthropic_request_body_uses_top_level_system_and_tool_blocks() {
+        let mut history = vec![Message::text("system", "You are Harper.")];
+        history.extend(tool_call_history());
+        let config = stream_config(
+            ApiProvider::Anthropic,
+            "https://api.anthropic.com/v1/messages".to_string(),
+        );
+
+        let body = build_anthropic_request_body(&config, &ToolRegistry::default(), &history, false);
+
+        assert_eq!(body["system"], json!("You are Harper."));
+        assert_eq!(body["max_tokens"], json!(ANTHROPIC_MAX_TOKENS));
+        assert!(body.get("stream").is_none());
+        let tools = body["tools"].as_array().expect("tools array");
+        assert!(tools
+            .iter()
+            .any(|tool| tool["name"] == "read_file" && tool["input_schema"]["type"] == "object"));
+
+        let messages = body["messages"].as_array().expect("messages array");
+        assert_eq!(messages.len(), 3);
+        assert_eq!(messages[0]["role"], json!("user"));
+        assert_eq!(messages[1]["role"], json!("assistant"));
+        assert_eq!(
+            messages[1]["content"][0],
+            json!({
+                "type": "tool_use",
+                "id": "call_a",
+                "name": "read_file",
+                "input": {"path": "Cargo.toml"}
+            })
+        );
+        assert_eq!(messages[2]["role"], json!("user"));
+        assert_eq!(messages[2]["content"][0]["type"], json!("tool_result"));
+        assert_eq!(messages[2]["content"][0]["tool_use_id"], json!("call_a"));
+    }
+
+    #[test]
+    fn anthropic_tool_results_carry_image_blocks() {
+        let config = stream_config(
+            ApiProvider::Anthropic,
+            "https://api.anthropic.com/v1/messages".to_string(),
+        );
+
+        let body = build_anthropic_request_body(
+            &config,
+            &ToolRegistry::default(),
+            &image_tool_history(),
+            false,
+        );
+
+        let content = &body["messages"][2]["content"][0]["content"];
+        assert_eq!(content[0], json!({"type": "text", "text": "[package]"}));
+        assert_eq!(
+            content[1],
+            json!({
+                "type": "image",
+                "source": {"type": "base64", "media_type": "image/png", "data": "aGVsbG8="}
+            })
+        );
+    }
+
+    #[test]
+    fn extract_assistant_reply_maps_anthropic_tool_use_blocks() {
+        let text_reply = json!({
+            "content": [
+                {"type": "text", "text": "Hello"},
+                {"type": "text", "text": " there"}
+            ]
+        });
+        assert_eq!(
+            extract_assistant_reply(&ApiProvider::Anthropic, &text_reply),
+            "Hello there"
+        );
+
+        let tool_reply = json!({
+            "content": [
+                {"type": "text", "text": "Let me look."},
+                {"type": "tool_use", "id": "toolu_1", "name": "read_file", "input": {"path": "README.md"}}
+            ],
+            "stop_reason": "tool_use"
+        });
+        let calls: Value = serde_json::from_str(&extract_assistant_reply(
+            &ApiProvider::Anthropic,
+            &tool_reply,
+        ))
+        .expect("tool calls json");
+        assert_eq!(calls[0]["id"], json!("toolu_1"));
+        assert_eq!(calls[0]["function"]["name"], json!("read_file"));
+        assert_eq!(
+            calls[0]["function"]["arguments"],
+            json!("{\"path\":\"README.md\"}")
+        );
+    }
+
+    fn built_request_body(request: &reqwest::Request) -> Value {
+        let bytes = request
+            .body()
+            .and_then(|body| body.as_bytes())
+            .expect("request body");
+        serde_json::from_slice(bytes).expect("request body json")
+    }
+
+    #[test]
+    fn openai_compatible_request_sends_extra_headers_and_optional_key() {
+        let mut config = stream_config(
+            ApiProvider::OpenAiCompatible,
+            "http://localhost:8000/v1/chat/completions".to_string(),
+        );
+        config.api_key = String::new();
+        config
+            .headers
+            .insert("X-Team".to_string(), "infra".to_string());
+        config.parameters = ModelParameters {
+            temperature: Some(0.7),
+            top_p: None,
+            max_tokens: Some(512),
+            context_window: None,
+            ..ModelParameters::default()
+        };
+
+        let request = build_chat_request(
+            &reqwest::Client::new(),
+            &config,
+            &ToolRegistry::default(),
+            &stream_history(),
+            false,
+        )
+        .build()
+        .expect("request builds");
+
+        assert_eq!(request.headers()["x-team"], "infra");
+        assert!(request.headers().get(AUTHORIZATION).is_none());
+        let body = built_request_body(&request);
+        assert_eq!(body["temperature"], json!(0.7));
+        assert!(body.get("top_p").is_none());
+        assert_eq!(body["max_tokens"], json!(512));
+        assert!(body.get("extra_query").is_none());
+
+        config.api_key = "local-key".to_string();
+        let request = build_chat_request(
+            &reqwest::Client::new(),
+            &config,
+            &ToolRegistry::default(),
+            &stream_history(),
+            false,
+        )
+        .build()
+        .expect("request builds");
+        assert_eq!(request.headers()[AUTHORIZATION], "Bearer local-key");
+    }
+
+    #[test]
+    fn model_parameters_replace_openai_sampling_defaults() {
+        let mut config = stream_config(
+            ApiProvider::OpenAI,
+            "https://api.openai.com/v1/chat/completions".to_string(),
+        );
+        let request = build_chat_request(
+            &reqwest::Client::new(),
+            &config,
+            &ToolRegistry::default(),
+            &stream_history(),
+            false,
+        )
+        .build()
+        .expect("request builds");
+        let body = built_request_body(&request);
+        assert_eq!(body["temperature"], json!(DEFAULT_TEMPERATURE));
+        assert_eq!(body["top_p"], json!(DEFAULT_TOP_P));
+        assert!(body.get("max_tokens").is_none());
+
+        config.parameters.top_p = Some(0.95);
+        config.parameters.max_tokens = Some(2048);
+        let request = build_chat_request(
+            &reqwest::Client::new(),
+            &config,
+            &ToolRegistry::default(),
+            &stream_history(),
+            false,
+        )
+        .build()
+        .expect("request builds");
+        let body = built_request_body(&request);
+        assert_eq!(body["temperature"], json!(DEFAULT_TEMPERATURE));
+        assert_eq!(body["top_p"], json!(0.95));
+        assert_eq!(body["max_tokens"], json!(2048));
+    }
+
+    #[tokio::test]
+    async fn call_llm_stream_reads_openai_compatible_server() {
+        let (base_url, recorded) = spawn_chunk_replay_server(vec![
+            "data: {\"choices\":[{\"delta\":{\"content\":\"local \"}}]}\n\n",
+            "data: {\"choices\":[{\"delta\":{\"content\":\"reply\"}}]}\n\ndata: [DONE]\n\n",
+        ])
+        .await;
+        let config = stream_config(
+            ApiProvider::OpenAiCompatible,
+            format!("{}/v1/chat/completions", base_url),
+        );
+
+        let reply = call_llm_stream(
+            &reqwest::Client::new(),
+            &config,
+            &ToolRegistry::default(),
+            &stream_history(),
+            |_| {},
+        )
+        .await
+        .expect("stream should succeed");
+
+        assert_eq!(reply, "local reply");
+        let (uri, body) = recorded.lock().unwrap().clone().expect("request recorded");
+        assert_eq!(uri, "/v1/chat/completions");
+        assert_eq!(body["model"], json!("test-model"));
+    }
+
+    type RecordedRequest = std::sync::Arc<std::sync::Mutex<Option<(String, Value)>>>;
+
+    /// Serves `chunks` verbatim as the response body of any POST, recording the request
+    async fn spawn_chunk_replay_server(chunks: Vec<&'static str>) -> (String, RecordedRequest) {
+        let recorded: RecordedRequest = Default::default();
+        let recorded_clone = recorded.clone();
+        let app = axum::Router::new().fallback(move |uri: axum::http::Uri, body: String| {
+            let chunks = chunks.clone();
+            let recorded = recorded_clone.clone();
+            async move {
+                let body_json = serde_json::from_str(&body).unwrap_or(Value::Null);
+                *recorded.lock().expect("recorded request lock") =
+                    Some((uri.to_string(), body_json));
+                axum::body::Body::from_stream(futures_util::stream::iter(
+                    chunks
+                        .into_iter()
+                        .map(|chunk| Ok::<_, std::convert::Infallible>(chunk.to_string())),
+                ))
+            }
+        });
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("bind mock server");
+        let addr = listener.local_addr().expect("mock server addr");
+        tokio::spawn(async move {
+            let _ = axum::serve(listener, app).await;
+        });
+        (format!("http://{}", addr), recorded)
+    }
+
+    fn stream_config(provider: ApiProvider, base_url: String) -> ApiConfig {
+        ApiConfig {
+            provider,
+            api_key: "test-key".to_string(),
+            base_url,
+            model_name: "test-model".to_string(),
+            headers: Default::default(),
+            parameters: Default::default(),
+            fallback: Default::default(),
+        }
+    }
+
+    fn stream_history() -> Vec<Message> {
+        vec![Message::text("user", "say hello")]
+    }
+
+    #[tokio::test]
+    async fn call_llm_stream_replays_openai_sse_deltas_in_order() {
+        let (base_url, recorded) = spawn_chunk_replay_server(vec![
+            "data: {\"choices\":[{\"delta\":{\"role\":\"assistant\"}}]}\n\n",
+            "data: {\"choices\":[{\"delta\":{\"content\":\"Hel\"}}]}\n\ndata: {\"choi",
+            "ces\":[{\"delta\":{\"content\":\"lo\"}}]}\r\n\r\n",
+            ": keep-alive\n\ndata: [DONE]\n\n",
+        ])
+        .await;
+        let config = stream_config(
+            ApiProvider::OpenAI,
+            format!("{}/v1/chat/completions", base_url),
+        );
+
+        let mut deltas = Vec::new();
+        let reply = call_llm_stream(
+            &reqwest::Client::new(),
+            &config,
+            &ToolRegistry::default(),
+            &stream_history(),
+            |delta| deltas.push(delta.to_string()),
+        )
+        .await
+        .expect("stream should succeed");
+
+        assert_eq!(deltas, vec!["Hel".to_string(), "lo".to_string()]);
+        assert_eq!(reply, "Hello");
+        let (uri, body) = recorded.lock().unwrap().clone().expect("request recorded");
+        assert_eq!(uri, "/v1/chat/completions");
+        assert_eq!(body["stream"], json!(true));
+    }
+
+    #[tokio::test]
+    async fn call_llm_stream_reply_prices_openai_usage_chunk() {
+        let (base_url, recorded) = spawn_chunk_replay_server(vec![
+            "data: {\"choices\":[{\"delta\":{\"content\":\"Hi\"}}]}\n\n",
+            "data: {\"choices\":[],\"usage\":{\"prompt_tokens\":1000,\"completion_tokens\":200}}\n\n",
+            "data: [DONE]\n\n",
+        ])
+        .await;
+        let mut config = stream_config(
+            ApiProvider::OpenAI,
+            format!("{}/v1/chat/completions", base_url),
+        );
+        config.parameters.input_cost_per_mtok = Some(2.0);
+        config.parameters.output_cost_per_mtok = Some(10.0);
+
+        let reply = call_llm_stream_reply(
+            &reqwest::Client::new(),
+            &config,
+            &ToolRegistry::default(),
+            &stream_history(),
+            |_| {},
+        )
+        .await
+        .expect("stream should succeed");
+
+        assert_eq!(reply.content, "Hi");
+        assert_eq!(reply.model, "test-model");
+        assert_eq!(
+            reply.usage,
+            Some(TokenUsage {
+                prompt_tokens: 1000,
+                completion_tokens: 200,
+            })
+        );
+        let cost = reply.estimated_cost.expect("priced model");
+        assert!((cost - 0.004).abs() < 1e-9);
+        let (_, body) = recorded.lock().unwrap().clone().expect("request recorded");
+        assert_eq!(body["stream_options"], json!({"include_usage": true}));
+    }
+
+    #[tokio::test]
+    async fn call_llm_stream_reply_merges_anthropic_usage_events() {
+        let (base_url, _) = spawn_chunk_replay_server(vec![
+            "event: message_start\ndata: {\"type\":\"message_start\",\"message\":{\"usage\":{\"input_tokens\":42,\"output_tokens\":1}}}\n\n",
+            "event: content_block_start\ndata: {\"type\":\"content_block_start\",\"index\":0,\"content_block\":{\"type\":\"text\",\"text\":\"\"}}\n\n",
+            "event: content_block_delta\ndata: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"text_delta\",\"text\":\"Hello\"}}\n\n",
+            "event: message_delta\ndata: {\"type\":\"message_delta\",\"usage\":{\"output_tokens\":7}}\n\n",
+        ])
+        .await;
+        let config = stream_config(ApiProvider::Anthropic, format!("{}/v1/messages", base_url));
+
+        let reply = call_llm_stream_reply(
+            &reqwest::Client::new(),
+            &config,
+            &ToolRegistry::default(),
+            &stream_history(),
+            |_| {},
+        )
+        .await
+        .expect("stream should succeed");
+
+        assert_eq!(reply.content, "Hello");
+        assert_eq!(
+            reply.usage,
+            Some(TokenUsage {
+                prompt_tokens: 42,
+                completion_tokens: 7,
+            })
+        );
+        assert_eq!(reply.estimated_cost, None);
+    }
+
+    #[tokio::test]
+    async fn call_llm_stream_assembles_openai_tool_call_fragments() {
+        let (base_url, _) = spawn_chunk_replay_server(vec![
+            "data: {\"choices\":[{\"delta\":{\"tool_calls\":[{\"index\":0,\"id\":\"call_1\",\"type\":\"function\",\"function\":{\"name\":\"read_file\",\"arguments\":\"\"}}]}}]}\n\n",
+            "data: {\"choices\":[{\"delta\":{\"tool_calls\":[{\"index\":0,\"function\":{\"arguments\":\"{\\\"path\\\":\"}}]}}]}\n\n",
+            "data: {\"choices\":[{\"delta\":{\"tool_calls\":[{\"index\":0,\"function\":{\"arguments\":\"\\\"Cargo.toml\\\"}\"}}]}}]}\n\n",
+            "data: [DONE]\n\n",
+        ])
+        .await;
+        let config = stream_config(ApiProvider::Sambanova, base_url);
+
+        let mut deltas = Vec::new();
+        let reply = call_llm_stream(
+            &reqwest::Client::new(),
+            &config,
+            &ToolRegistry::default(),
+            &stream_history(),
+            |delta| deltas.push(delta.to_string()),
+        )
+        .await
+        .expect("stream should succeed");
+
+        assert!(deltas.is_empty());
+        let calls: Value = serde_json::from_str(&reply).expect("tool calls json");
+        assert_eq!(calls[0]["id"], json!("call_1"));
+        assert_eq!(calls[0]["function"]["name"], json!("read_file"));
+        assert_eq!(
+            calls[0]["function"]["arguments"],
+            json!("{\"path\":\"Cargo.toml\"}")
+        );
+    }
+
+    #[tokio::test]
+    async fn call_llm_stream_uses_gemini_sse_endpoint() {
+        let (base_url, recorded) = spawn_chunk_replay_server(vec![
+            "data: {\"candidates\":[{\"content\":{\"parts\":[{\"text\":\"Hi \"}]}}]}\r\n\r\n",
+            "data: {\"candidates\":[{\"content\":{\"parts\":[{\"text\":\"there\"}]}}]}\r\n\r\n",
+        ])
+        .await;
+        let config = stream_config(
+            ApiProvider::Gemini,
+            format!("{}/v1beta/models/test-model:generateContent", base_url),
+        );
+
+        let mut deltas = Vec::new();
+        let reply = call_llm_stream(
+            &reqwest::Client::new(),
+            &config,
+            &ToolRegistry::default(),
+            &stream_history(),
+            |delta| deltas.push(delta.to_string()),
+        )
+        .await
+        .expect("stream should succeed");
+
+        assert_eq!(deltas, vec!["Hi ".to_string(), "there".to_string()]);
+        assert_eq!(reply, "Hi there");
+        let (uri, _) = recorded.lock().unwrap().clone().expect("request recorded");
+        assert_eq!(
+            uri,
+            "/v1beta/models/test-model:streamGenerateContent?alt=sse"
+        );
+    }
+
+    #[tokio::test]
+    async fn call_llm_stream_reads_ollama_ndjson() {
+        let (base_url, recorded) = spawn_chunk_replay_server(vec![
+            "{\"message\":{\"role\":\"assistant\",\"content\":\"Hello\"},\"done\":false}\n{\"mess",
+            "age\":{\"role\":\"assistant\",\"content\":\" world\"},\"done\":false}\n",
+            "{\"message\":{\"role\":\"assistant\",\"content\":\"\"},\"done\":true}",
+        ])
+        .await;
+        let config = stream_config(ApiProvider::Ollama, format!("{}/api/chat", base_url));
+
+        let mut deltas = Vec::new();
+        let reply = call_llm_stream(
+            &reqwest::Client::new(),
+            &config,
+            &ToolRegistry::default(),
+            &stream_history(),
+            |delta| deltas.push(delta.to_string()),
+        )
+        .await
+        .expect("stream should succeed");
+
+        assert_eq!(deltas, vec!["Hello".to_string(), " world".to_string()]);
+        assert_eq!(reply, "Hello world");
+        let (_, body) = recorded.lock().unwrap().clone().expect("request recorded");
+        assert_eq!(body["stream"], json!(true));
+    }
+
+    #[tokio::test]
+    async fn call_llm_stream_surfaces_mid_stream_errors() {
+        let (base_url, _) = spawn_chunk_replay_server(vec![
+            "data: {\"choices\":[{\"delta\":{\"content\":\"partial\"}}]}\n\n",
+            "data: {\"error\":{\"message\":\"rate limited\"}}\n\n",
+        ])
+        .await;
+        let config = stream_config(ApiProvider::OpenAI, base_url);
+
+        let err = call_llm_stream(
+            &reqwest::Client::new(),
+            &config,
+            &ToolRegistry::default(),
+            &stream_history(),
+            |_| {},
+        )
+        .await
+        .expect_err("stream error should surface");
+        assert!(err.to_string().contains("rate limited"));
+    }
+
+    #[tokio::test]
+    async fn call_llm_reads_anthropic_messages_response() {
+        let (base_url, recorded) = spawn_chunk_replay_server(vec![
+            "{\"id\":\"msg_1\",\"type\":\"message\",\"role\":\"assistant\",\"content\":[{\"type\":\"text\",\"text\":\"Hello from the stub\"}],\"stop_reason\":\"end_turn\"}",
+        ])
+        .await;
+        let config = stream_config(ApiProvider::Anthropic, format!("{}/v1/messages", base_url));
+
+        let reply = call_llm(
+            &reqwest::Client::new(),
+            &config,
+            &ToolRegistry::default(),
+            &stream_history(),
+        )
+        .await
+        .expect("call should succeed");
+
+        assert_eq!(reply, "Hello from the stub");
+        let (uri, body) = recorded.lock().unwrap().clone().expect("request recorded");
+        assert_eq!(uri, "/v1/messages");
+        assert_eq!(body["model"], json!("test-model"));
+        assert_eq!(
+            body["messages"][0]["content"][0]["text"],
+            json!("say hello")
+        );
+    }
+
+    #[tokio::test]
+    async fn call_llm_stream_reads_anthropic_events() {
+        let (base_url, recorded) = spawn_chunk_replay_server(vec![
+            "event: message_start\ndata: {\"type\":\"message_start\",\"message\":{\"id\":\"msg_1\",\"content\":[]}}\n\n",
+            "event: content_block_start\ndata: {\"type\":\"content_block_start\",\"index\":0,\"content_block\":{\"type\":\"text\",\"text\":\"\"}}\n\n",
+            "event: content_block_delta\ndata: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"text_delta\",\"text\":\"Reading\"}}\n\n",
+            "event: content_block_start\ndata: {\"type\":\"content_block_start\",\"index\":1,\"content_block\":{\"type\":\"tool_use\",\"id\":\"toolu_1\",\"name\":\"read_file\",\"input\":{}}}\n\n",
+            "event: content_block_delta\ndata: {\"type\":\"content_block_delta\",\"index\":1,\"delta\":{\"type\":\"input_json_delta\",\"partial_json\":\"{\\\"path\\\":\"}}\n\n",
+            "event: content_block_delta\ndata: {\"type\":\"content_block_delta\",\"index\":1,\"delta\":{\"type\":\"input_json_delta\",\"partial_json\":\"\\\"Cargo.toml\\\"}\"}}\n\n",
+            "event: message_stop\ndata: {\"type\":\"message_stop\"}\n\n",
+        ])
+        .await;
+        let config = stream_config(ApiProvider::Anthropic, format!("{}/v1/messages", base_url));
+
+        let mut deltas = Vec::new();
+        let reply = call_llm_stream(
+            &reqwest::Client::new(),
+            &config,
+            &ToolRegistry::default(),
+            &stream_history(),
+            |delta| deltas.push(delta.to_string()),
+        )
+        .await
+        .expect("stream should succeed");
+
+        assert_eq!(deltas, vec!["Reading".to_string()]);
+        let calls: Value = serde_json::from_str(&reply).expect("tool calls json");
+        assert_eq!(calls[0]["id"], json!("toolu_1"));
+        assert_eq!(calls[0]["function"]["name"], json!("read_file"));
+        assert_eq!(
+            calls[0]["function"]["arguments"],
+            json!("{\"path\":\"Cargo.toml\"}")
+        );
+        let (_, body) = recorded.lock().unwrap().clone().expect("request recorded");
+        assert_eq!(body["stream"], json!(true));
+    }
+
+    type HitCounter = std::sync::Arc<std::sync::atomic::AtomicUsize>;
+
+    /// Answers the n-th request with the n-th `(status, retry_after, body)`,
+    /// repeating the last one once the list runs out
+    async fn spawn_status_server(
+        responses: Vec<(u16, Option<&'static str>, &'static str)>,
+    ) -> (String, HitCounter) {
+        let hits: HitCounter = Default::default();
+        let hits_clone = hits.clone();
+        let app = axum::Router::new().fallback(move || {
+            let responses = responses.clone();
+            let hits = hits_clone.clone();
+            async move {
+                let index = hits.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                let (status, retry_after, body) = responses[index.min(responses.len() - 1)];
+                let mut builder = axum::http::Response::builder().status(status);
+                if let Some(retry_after) = retry_after {
+                    builder = builder.header("retry-after", retry_after);
+                }
+                builder
+                    .body(axum::body::Body::from(body))
+                    .expect("stub response")
+            }
+        });
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("bind mock server");
+        let addr = listener.local_addr().expect("mock server addr");
+        tokio::spawn(async move {
+            let _ = axum::serve(listener, app).await;
+        });
+        (format!("http://{}", addr), hits)
+    }
+
+    const OPENAI_OK: &str = "{\"choices\":[{\"message\":{\"content\":\"recovered\"}}]}";
+    const OPENAI_SSE_OK: &str =
+        "data: {\"choices\":[{\"delta\":{\"content\":\"recovered\"}}]}\n\ndata: [DONE]\n\n";
+
+    fn fast_retry() -> RetryPolicy {
+        RetryPolicy {
+            max_retries: 2,
+            initial_backoff_ms: 1,
+            max_backoff_ms: 50,
+        }
+    }
+
+    #[test]
+    fn parse_retry_after_reads_seconds_and_http_dates() {
+        assert_eq!(parse_retry_after("3"), Some(Duration::from_secs(3)));
+        assert_eq!(parse_retry_after(" 0.5 "), Some(Duration::from_millis(500)));
+        assert_eq!(parse_retry_after("-1"), None);
+        assert_eq!(parse_retry_after("1e20"), None);
+        assert_eq!(parse_retry_after("NaN"), None);
+        assert_eq!(
+            parse_retry_after("Sun, 06 Nov 1994 08:49:37 GMT"),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(parse_retry_after("soon"), None);
+    }
+
+    #[test]
+    fn backoff_delay_doubles_and_respects_retry_after() {
+        let policy = RetryPolicy {
+            max_retries: 5,
+            initial_backoff_ms: 100,
+            max_backoff_ms: 1_000,
+        };
+        assert_eq!(
+            backoff_delay(&policy, 0, None),
+            Some(Duration::from_millis(100))
+        );
+        assert_eq!(
+            backoff_delay(&policy, 2, None),
+            Some(Duration::from_millis(400))
+        );
+        assert_eq!(
+            backoff_delay(&policy, 8, None),
+            Some(Duration::from_millis(1_000))
+        );
+        assert_eq!(
+            backoff_delay(&policy, 0, Some(Duration::from_millis(700))),
+            Some(Duration::from_millis(700))
+        );
+        assert_eq!(
+            backoff_delay(&policy, 0, Some(Duration::from_secs(60))),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn call_llm_retries_rate_limited_requests() {
+        let (base_url, hits) = spawn_status_server(vec![
+            (429, Some("0"), "{\"error\":{\"message\":\"slow down\"}}"),
+            (200, None, OPENAI_OK),
+        ])
+        .await;
+        let mut config = stream_config(ApiProvider::OpenAI, base_url);
+        config.fallback.retry = fast_retry();
+
+        let reply = call_llm(
+            &reqwest::Client::new(),
+            &config,
+            &ToolRegistry::default(),
+            &stream_history(),
+        )
+        .await
+        .expect("retry should recover");
+
+        assert_eq!(reply, "recovered");
+        assert_eq!(hits.load(std::sync::atomic::Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn call_llm_walks_fallback_chain_on_server_errors() {
+        let (primary_url, primary_hits) = spawn_status_server(vec![(
+            503,
+            None,
+            "{\"error\":{\"message\":\"overloaded\"}}",
+        )])
+        .await;
+        let (fallback_url, fallback_hits) =
+            spawn_status_server(vec![(200, None, OPENAI_SSE_OK)]).await;
+        let mut config = stream_config(ApiProvider::OpenAI, primary_url);
+        config.fallback.retry = fast_retry();
+        config.fallback.providers = vec![
+            stream_config(
+                ApiProvider::OpenAI,
+                "http://127.0.0.1:9/unreachable".to_string(),
+            ),
+            stream_config(ApiProvider::OpenAiCompatible, fallback_url),
+        ];
+
+        let (sink, activity) = RecordingEvents::sink();
+        let reply = call_llm_with_events(
+            &reqwest::Client::new(),
+            &config,
+            &ToolRegistry::default(),
+            &stream_history(),
+            Some(sink),
+            Some("fallback-session"),
+        )
+        .await
+        .expect("fallback provider should answer");
+
+        assert_eq!(reply.content, "recovered");
+        assert_eq!(primary_hits.load(std::sync::atomic::Ordering::SeqCst), 3);
+        assert_eq!(fallback_hits.load(std::sync::atomic::Ordering::SeqCst), 1);
+        assert_eq!(
+            activity.lock().unwrap().clone(),
+            vec![
+                "falling back to OpenAI (test-model)".to_string(),
+                "falling back to OpenAiCompatible (test-model)".to_string(),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn call_llm_does_not_fall_back_on_client_errors() {
+        let (primary_url, _) =
+            spawn_status_server(vec![(401, None, "{\"error\":{\"message\":\"bad key\"}}")]).await;
+        let (fallback_url, fallback_hits) = spawn_status_server(vec![(200, None, OPENAI_OK)]).await;
+        let mut config = stream_config(ApiProvider::OpenAI, primary_url);
+        config.fallback.retry = fast_retry();
+        config.fallback.providers = vec![stream_config(ApiProvider::OpenAI, fallback_url)];
+
+        let err = call_llm(
+            &reqwest::Client::new(),
+            &config,
+            &ToolRegistry::default(),
+            &stream_history(),
+        )
+        .await
+        .expect_err("client errors surface");
+
+        assert!(matches!(err, HarperError::Api(_)));
+        assert!(err.to_string().contains("bad key"));
+        assert_eq!(fallback_hits.load(std::sync::atomic::Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn call_llm_reports_an_exhausted_chain_as_unavailable() {
+        let (primary_url, _) = spawn_status_server(vec![(
+            503,
+            None,
+            "{\"error\":{\"message\":\"overloaded\"}}",
+        )])
+        .await;
+        let mut config = stream_config(ApiProvider::OpenAI, primary_url);
+        config.fallback.retry = fast_retry();
+        config.fallback.providers = vec![stream_config(
+            ApiProvider::OpenAI,
+            "http://127.0.0.1:9/unreachable".to_string(),
+        )];
+
+        let err = call_llm(
+            &reqwest::Client::new(),
+            &config,
+            &ToolRegistry::default(),
+            &stream_history(),
+        )
+        .await
+        .expect_err("every provider fails");
+
+        assert!(matches!(err, HarperError::Unavailable(_)));
+    }
+
+    type ActivityLog = std::sync::Arc<std::sync::Mutex<Vec<String>>>;
+
+    struct RecordingEvents {
+        activity: ActivityLog,
+    }
+
+    impl RecordingEvents {
+        fn sink() -> (Arc<dyn RuntimeEventSink>, ActivityLog) {
+            let activity = ActivityLog::default();
+            (
+                Arc::new(Self {
+                    activity: activity.clone(),
+                }),
+                activity,
+            )
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl RuntimeEventSink for RecordingEvents {
+        async fn plan_updated(
+            &self,
+            _session_id: &str,
+            _plan: Option<crate::core::plan::PlanState>,
+        ) -> HarperResult<()> {
+            Ok(())
+        }
+
+        async fn agents_updated(
+            &self,
+            _session_id: &str,
+            _agents: Option<crate::core::agents::ResolvedAgents>,
+        ) -> HarperResult<()> {
+            Ok(())
+        }
+
+        async fn command_output_updated(
+            &self,
+            _session_id: &str,
+            _command: String,
+            _chunk: String,
+            _is_error: bool,
+            _done: bool,
+        ) -> HarperResult<()> {
+            Ok(())
+        }
+
+        async fn activity_updated(
+            &self,
+            _session_id: &str,
+            status: Option<String>,
+        ) -> HarperResult<()> {
+            if let Some(status) = status {
+                self.activity.lock().unwrap().push(status);
+            }
+            Ok(())
+        }
+
+        async fn assistant_output_updated(
+            &self,
+            _session_id: &str,
+            _chunk: String,
+            _done: bool,
+        ) -> HarperResult<()> {
+            Ok(())
+        }
+    }
+
     #[test]
     fn test_encrypt_decrypt_roundtrip() {
         let message = b"Hello, World! This is a test message.";
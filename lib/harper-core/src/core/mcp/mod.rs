@@ -0,0 +1,658 @@
+// Copyright 2026 harpertoken
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Connections to the configured MCP servers
+//!
+//! Every server's tools are exposed to the model as `mcp__<server>__<tool>`,
+//! so tools with the same name on different servers never collide.
+//!
+//! The registry subscribes to resource updates on servers that support it
+//! and reports `resources/list_changed` and `resources/updated` through
+//! [`McpRegistry::resource_changes`], so resource listings can be refreshed.
+
+pub mod stdio;
+
+use crate::core::error::{HarperError, HarperResult};
+use crate::core::ImageAttachment;
+use crate::runtime::config::{McpConfig, McpServerConfig, McpTransportKind};
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use stdio::StdioTransport;
+use tokio::sync::broadcast;
+use turul_mcp_client::transport::{
+    ConnectionInfo, EventReceiver, HttpTransport, Transport, TransportCapabilities,
+    TransportResponse, TransportStatistics, TransportType,
+};
+use turul_mcp_client::{
+    CallToolResult, ContentBlock, GetPromptResult, McpClient, McpClientBuilder, McpClientResult,
+    Prompt, Resource, ResourceContent, ResourceContents, Tool,
+};
+
+/// Prefix of every MCP tool name shown to the model
+pub const TOOL_PREFIX: &str = "mcp__";
+
+/// Name a server's tool is exposed under
+pub fn qualified_tool_name(server: &str, tool: &str) -> String {
+    format!("{}{}__{}", TOOL_PREFIX, server, tool)
+}
+
+/// Split `mcp__<server>__<tool>` into its server and tool names
+pub fn split_tool_name(name: &str) -> Option<(&str, &str)> {
+    let (server, tool) = name.strip_prefix(TOOL_PREFIX)?.split_once("__")?;
+    (!server.is_empty() && !tool.is_empty()).then_some((server, tool))
+}
+
+/// Render content blocks as text for the model
+///
+/// Images keep a text placeholder and are also returned as attachments, so
+/// models that accept images can see them.
+pub fn render_content(blocks: Vec<ContentBlock>) -> (String, Vec<ImageAttachment>) {
+    let mut parts = Vec::new();
+    let mut images = Vec::new();
+    for block in blocks {
+        match block {
+            ContentBlock::Text { text, .. } => parts.push(text),
+            ContentBlock::Image {
+                data, mime_type, ..
+            } => {
+                parts.push(format!(
+                    "[Image: {} bytes, type: {}]",
+                    data.len(),
+                    mime_type
+                ));
+                images.push(ImageAttachment { mime_type, data });
+            }
+            ContentBlock::Audio {
+                data, mime_type, ..
+            } => parts.push(format!(
+                "[Audio: {} bytes, type: {}]",
+                data.len(),
+                mime_type
+            )),
+            ContentBlock::ResourceLink { resource, .. } => {
+                parts.push(format!("[Resource link: @mcp:{}]", resource.uri))
+            }
+            ContentBlock::Resource {
+                resource: ResourceContents::Text(contents),
+                ..
+            } => parts.push(contents.text),
+            ContentBlock::Resource { .. } => parts.push("[Resource content]".to_string()),
+            ContentBlock::ToolUse { .. } => parts.push("[Tool use]".to_string()),
+            ContentBlock::ToolResult { .. } => parts.push("[Tool result]".to_string()),
+        }
+    }
+    (parts.join("\n"), images)
+}
+
+/// A connected MCP server
+pub struct McpServer {
+    pub name: String,
+    pub client: McpClient,
+    /// Handle on the client's transport for requests the client library has
+    /// no method for; `None` for clients added with [`McpRegistry::with_server`]
+    transport: Option<Arc<dyn Transport>>,
+    /// Resource URIs `resources/subscribe` was sent for
+    subscribed: Mutex<HashSet<String>>,
+}
+
+impl McpServer {
+    fn new(name: String, client: McpClient, transport: Option<Arc<dyn Transport>>) -> Self {
+        Self {
+            name,
+            client,
+            transport,
+            subscribed: Mutex::new(HashSet::new()),
+        }
+    }
+
+    async fn supports_resource_subscriptions(&self) -> bool {
+        self.client
+            .session_info()
+            .await
+            .server_capabilities
+            .and_then(|capabilities| capabilities.resources)
+            .and_then(|resources| resources.subscribe)
+            .unwrap_or(false)
+    }
+}
+
+/// A tool offered by one of the connected servers
+#[derive(Debug, Clone)]
+pub struct McpToolEntry {
+    pub server: String,
+    /// Namespaced name the model calls the tool by
+    pub name: String,
+    pub tool: Tool,
+}
+
+/// A resource offered by one of the connected servers
+#[derive(Debug, Clone)]
+pub struct McpResourceEntry {
+    pub server: String,
+    pub resource: Resource,
+}
+
+/// A prompt template offered by one of the connected servers
+#[derive(Debug, Clone)]
+pub struct McpPromptEntry {
+    pub server: String,
+    pub prompt: Prompt,
+}
+
+impl McpPromptEntry {
+    /// `<server>/<prompt>`, the name `/mcp prompt` accepts to pick a server
+    pub fn qualified_name(&self) -> String {
+        format!("{}/{}", self.server, self.prompt.name)
+    }
+}
+
+/// All MCP servers Harper is connected to
+pub struct McpRegistry {
+    servers: Vec<McpServer>,
+    resources_changed: broadcast::Sender<()>,
+}
+
+impl Default for McpRegistry {
+    fn default() -> Self {
+        Self {
+            servers: Vec::new(),
+            resources_changed: broadcast::channel(8).0,
+        }
+    }
+}
+
+impl McpRegistry {
+    /// Connect to every enabled server in `config`
+    ///
+    /// Servers that cannot be reached are logged and skipped so one broken
+    /// entry does not take the others down.
+    pub async fn connect(config: &McpConfig) -> Self {
+        let mut registry = Self::default();
+        for server in config.active_servers() {
+            let connected = match server_transport(&server) {
+                Ok(transport) => registry.connect_transport(&server.name, transport).await,
+                Err(e) => Err(e),
+            };
+            if let Err(e) = connected {
+                log::warn!("Failed to connect to MCP server {}: {}", server.name, e);
+            }
+        }
+        registry
+    }
+
+    /// Connect a client over `transport` and add it under `name`
+    ///
+    /// Resource change notifications from the server are forwarded to
+    /// [`Self::resource_changes`], and its resources are subscribed to when
+    /// the server supports `resources/subscribe`.
+    pub async fn connect_transport(
+        &mut self,
+        name: impl Into<String>,
+        transport: Box<dyn Transport>,
+    ) -> HarperResult<()> {
+        let transport: Arc<dyn Transport> = Arc::from(transport);
+        let resources_changed = self.resources_changed.clone();
+        let client = McpClientBuilder::new()
+            .with_transport(Box::new(SharedTransport(Arc::clone(&transport))))
+            .on_notification(move |method, _| {
+                if matches!(
+                    method,
+                    "notifications/resources/list_changed" | "notifications/resources/updated"
+                ) {
+                    let _ = resources_changed.send(());
+                }
+            })
+            .build();
+        client
+            .connect()
+            .await
+            .map_err(|e| HarperError::Mcp(e.to_string()))?;
+        let server = McpServer::new(name.into(), client, Some(transport));
+        subscribe_server_resources(&server).await;
+        self.servers.push(server);
+        Ok(())
+    }
+
+    /// Add an already connected client under `name`
+    pub fn with_server(mut self, name: impl Into<String>, client: McpClient) -> Self {
+        self.servers.push(McpServer::new(name.into(), client, None));
+        self
+    }
+
+    /// Notified whenever a server reports its resource list or one of its
+    /// subscribed resources changed
+    pub fn resource_changes(&self) -> broadcast::Receiver<()> {
+        self.resources_changed.subscribe()
+    }
+
+    /// Subscribe to resources listed since the last call
+    pub async fn subscribe_resources(&self) {
+        for server in &self.servers {
+            subscribe_server_resources(server).await;
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.servers.is_empty()
+    }
+
+    pub fn servers(&self) -> &[McpServer] {
+        &self.servers
+    }
+
+    pub fn server(&self, name: &str) -> Option<&McpServer> {
+        self.servers.iter().find(|server| server.name == name)
+    }
+
+    /// Tools of every server, namespaced by server
+    pub async fn list_tools(&self) -> Vec<McpToolEntry> {
+        let mut entries = Vec::new();
+        for server in &self.servers {
+            match server.client.list_tools().await {
+                Ok(tools) => entries.extend(tools.into_iter().map(|tool| McpToolEntry {
+                    server: server.name.clone(),
+                    name: qualified_tool_name(&server.name, &tool.name),
+                    tool,
+                })),
+                Err(e) => {
+                    log::warn!("Failed to list MCP tools of {}: {}", server.name, e)
+                }
+            }
+        }
+        entries
+    }
+
+    /// Call a tool by its namespaced name
+    ///
+    /// A bare tool name is looked up across all servers, which keeps
+    /// single-server setups working with un-prefixed calls.
+    pub async fn call_tool(&self, name: &str, arguments: Value) -> HarperResult<CallToolResult> {
+        let (server, tool) = self.resolve_tool(name).await?;
+        server
+            .client
+            .call_tool(&tool, arguments)
+            .await
+            .map_err(|e| HarperError::Mcp(e.to_string()))
+    }
+
+    async fn resolve_tool(&self, name: &str) -> HarperResult<(&McpServer, String)> {
+        if let Some((server_name, tool)) = split_tool_name(name) {
+            if let Some(server) = self.server(server_name) {
+                return Ok((server, tool.to_string()));
+            }
+        }
+        for server in &self.servers {
+            if let Ok(tools) = server.client.list_tools().await {
+                if tools.iter().any(|tool| tool.name == name) {
+                    return Ok((server, name.to_string()));
+                }
+            }
+        }
+        Err(HarperError::Mcp(format!("Unknown MCP tool: {}", name)))
+    }
+
+    /// Resources of every server
+    ///
+    /// Lists are cached per server until it sends `resources/list_changed`.
+    pub async fn list_resources(&self) -> Vec<McpResourceEntry> {
+        let mut entries = Vec::new();
+        for server in &self.servers {
+            match server.client.list_resources().await {
+                Ok(resources) => {
+                    entries.extend(resources.into_iter().map(|resource| McpResourceEntry {
+                        server: server.name.clone(),
+                        resource,
+                    }))
+                }
+                Err(e) => {
+                    log::warn!("Failed to list MCP resources of {}: {}", server.name, e)
+                }
+            }
+        }
+        entries
+    }
+
+    /// Prompt templates of every server
+    pub async fn list_prompts(&self) -> Vec<McpPromptEntry> {
+        let mut entries = Vec::new();
+        for server in &self.servers {
+            match server.client.list_prompts().await {
+                Ok(prompts) => entries.extend(prompts.into_iter().map(|prompt| McpPromptEntry {
+                    server: server.name.clone(),
+                    prompt,
+                })),
+                Err(e) => {
+                    log::warn!("Failed to list MCP prompts of {}: {}", server.name, e)
+                }
+            }
+        }
+        entries
+    }
+
+    /// Expand a prompt template
+    ///
+    /// `name` is either `<server>/<prompt>` or a bare prompt name, which is
+    /// looked up across all servers.
+    pub async fn get_prompt(
+        &self,
+        name: &str,
+        arguments: &[(String, String)],
+    ) -> HarperResult<GetPromptResult> {
+        let (server, prompt) = self.resolve_prompt(name).await?;
+        let arguments = (!arguments.is_empty()).then(|| {
+            Value::Object(
+                arguments
+                    .iter()
+                    .map(|(key, value)| (key.clone(), Value::String(value.clone())))
+                    .collect(),
+            )
+        });
+        server
+            .client
+            .get_prompt(&prompt, arguments)
+            .await
+            .map_err(|e| HarperError::Mcp(e.to_string()))
+    }
+
+    async fn resolve_prompt(&self, name: &str) -> HarperResult<(&McpServer, String)> {
+        if let Some((server_name, prompt)) = name.split_once('/') {
+            if let Some(server) = self.server(server_name) {
+                return Ok((server, prompt.to_string()));
+            }
+        }
+        for server in &self.servers {
+            if let Ok(prompts) = server.client.list_prompts().await {
+                if prompts.iter().any(|prompt| prompt.name == name) {
+                    return Ok((server, name.to_string()));
+                }
+            }
+        }
+        Err(HarperError::Mcp(format!("Unknown MCP prompt: {}", name)))
+    }
+
+    /// Read a resource from the first server that serves `uri`
+    pub async fn read_resource(&self, uri: &str) -> HarperResult<Vec<ResourceContent>> {
+        let mut last_error = None;
+        for server in &self.servers {
+            match server.client.read_resource(uri).await {
+                Ok(contents) => return Ok(contents),
+                Err(e) => last_error = Some(e.to_string()),
+            }
+        }
+        Err(HarperError::Mcp(
+            last_error.unwrap_or_else(|| "no MCP servers connected".to_string()),
+        ))
+    }
+}
+
+/// Open and initialize a client for one `[[mcp.servers]]` entry
+pub async fn connect_server(server: &McpServerConfig) -> HarperResult<McpClient> {
+    let client = McpClientBuilder::new()
+        .with_transport(server_transport(server)?)
+        .build();
+    client
+        .connect()
+        .await
+        .map_err(|e| HarperError::Mcp(e.to_string()))?;
+    Ok(client)
+}
+
+fn server_transport(server: &McpServerConfig) -> HarperResult<Box<dyn Transport>> {
+    Ok(match server.transport {
+        McpTransportKind::Http => {
+            let url = server.url.as_deref().unwrap_or_default();
+            Box::new(HttpTransport::new(url).map_err(|e| HarperError::Mcp(e.to_string()))?)
+        }
+        McpTransportKind::Stdio => Box::new(StdioTransport::new(
+            server.command.as_deref().unwrap_or_default(),
+            &server.args,
+            &server.env,
+        )),
+    })
+}
+
+/// Send `resources/subscribe` for every listed resource not subscribed yet
+///
+/// Failures are logged; the resource list is still refreshed on
+/// `resources/list_changed` without a subscription.
+async fn subscribe_server_resources(server: &McpServer) {
+    static NEXT_REQUEST: AtomicU64 = AtomicU64::new(0);
+
+    let Some(transport) = &server.transport else {
+        return;
+    };
+    if !server.supports_resource_subscriptions().await {
+        return;
+    }
+    let resources = match server.client.list_resources().await {
+        Ok(resources) => resources,
+        Err(e) => {
+            log::warn!("Failed to list MCP resources of {}: {}", server.name, e);
+            return;
+        }
+    };
+    for resource in resources {
+        let is_new = server
+            .subscribed
+            .lock()
+            .expect("mcp subscriptions lock")
+            .insert(resource.uri.clone());
+        if !is_new {
+            continue;
+        }
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": format!("harper_subscribe_{}", NEXT_REQUEST.fetch_add(1, Ordering::Relaxed)),
+            "method": "resources/subscribe",
+            "params": {"uri": resource.uri},
+        });
+        let error = match transport.send_request(request).await {
+            Ok(response) => response.get("error").map(Value::to_string),
+            Err(e) => Some(e.to_string()),
+        };
+        if let Some(error) = error {
+            log::warn!(
+                "Failed to subscribe to MCP resource {} of {}: {}",
+                resource.uri,
+                server.name,
+                error
+            );
+        }
+    }
+}
+
+/// Transport shared between a client and the registry
+struct SharedTransport(Arc<dyn Transport>);
+
+#[async_trait]
+impl Transport for SharedTransport {
+    fn transport_type(&self) -> TransportType {
+        self.0.transport_type()
+    }
+
+    fn capabilities(&self) -> TransportCapabilities {
+        self.0.capabilities()
+    }
+
+    async fn connect(&self) -> McpClientResult<()> {
+        self.0.connect().await
+    }
+
+    async fn disconnect(&self) -> McpClientResult<()> {
+        self.0.disconnect().await
+    }
+
+    fn is_connected(&self) -> bool {
+        self.0.is_connected()
+    }
+
+    async fn send_request(&self, request: Value) -> McpClientResult<Value> {
+        self.0.send_request(request).await
+    }
+
+    async fn send_request_with_headers(
+        &self,
+        request: Value,
+    ) -> McpClientResult<TransportResponse> {
+        self.0.send_request_with_headers(request).await
+    }
+
+    async fn send_notification(&self, notification: Value) -> McpClientResult<()> {
+        self.0.send_notification(notification).await
+    }
+
+    async fn send_delete(&self, session_id: &str) -> McpClientResult<()> {
+        self.0.send_delete(session_id).await
+    }
+
+    fn set_session_id(&self, session_id: String) {
+        self.0.set_session_id(session_id)
+    }
+
+    fn clear_session_id(&self) {
+        self.0.clear_session_id()
+    }
+
+    async fn update_auth_header(&self, value: Option<String>) {
+        self.0.update_auth_header(value).await
+    }
+
+    async fn start_event_listener(&self) -> McpClientResult<EventReceiver> {
+        self.0.start_event_listener().await
+    }
+
+    fn connection_info(&self) -> ConnectionInfo {
+        self.0.connection_info()
+    }
+
+    async fn health_check(&self) -> McpClientResult<bool> {
+        self.0.health_check().await
+    }
+
+    fn statistics(&self) -> TransportStatistics {
+        self.0.statistics()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+    #[test]
+    fn tool_names_round_trip_through_namespace() {
+        let name = qualified_tool_name("docs", "search__pages");
+        assert_eq!(name, "mcp__docs__search__pages");
+        assert_eq!(split_tool_name(&name), Some(("docs", "search__pages")));
+        assert_eq!(split_tool_name("mcp__docs"), None);
+        assert_eq!(split_tool_name("read_file"), None);
+    }
+
+    #[test]
+    fn rendered_content_keeps_images_as_attachments() {
+        let blocks = vec![
+            ContentBlock::text("Screenshot of the login page"),
+            ContentBlock::image("aGVsbG8=", "image/png"),
+        ];
+
+        let (text, images) = render_content(blocks);
+        assert_eq!(
+            text,
+            "Screenshot of the login page\n[Image: 8 bytes, type: image/png]"
+        );
+        assert_eq!(
+            images,
+            vec![ImageAttachment {
+                mime_type: "image/png".to_string(),
+                data: "aGVsbG8=".to_string(),
+            }]
+        );
+    }
+
+    /// Peer serving one resource that announces a list change once it has
+    /// been subscribed to, returning the subscribed URIs
+    fn spawn_resource_peer(
+        reader: tokio::io::DuplexStream,
+        mut writer: tokio::io::DuplexStream,
+    ) -> tokio::task::JoinHandle<Vec<String>> {
+        tokio::spawn(async move {
+            let mut subscribed = Vec::new();
+            let mut lines = BufReader::new(reader).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                let message: Value = serde_json::from_str(&line).expect("peer json");
+                let Some(id) = message.get("id").cloned() else {
+                    continue;
+                };
+                let result = match message["method"].as_str().unwrap_or_default() {
+                    "initialize" => json!({
+                        "protocolVersion": message["params"]["protocolVersion"],
+                        "capabilities": {"resources": {"subscribe": true, "listChanged": true}},
+                        "serverInfo": {"name": "peer", "version": "1.0.0"}
+                    }),
+                    "resources/list" => json!({
+                        "resources": [{"uri": "docs://readme", "name": "readme"}]
+                    }),
+                    "resources/subscribe" => {
+                        subscribed.push(message["params"]["uri"].as_str().unwrap().to_string());
+                        json!({})
+                    }
+                    _ => json!({}),
+                };
+                let mut reply = format!(
+                    "{}\n",
+                    json!({"jsonrpc": "2.0", "id": id, "result": result})
+                );
+                if message["method"] == "resources/subscribe" {
+                    reply.push_str(&format!(
+                        "{}\n",
+                        json!({"jsonrpc": "2.0", "method": "notifications/resources/list_changed"})
+                    ));
+                }
+                if writer.write_all(reply.as_bytes()).await.is_err() {
+                    break;
+                }
+            }
+            subscribed
+        })
+    }
+
+    #[tokio::test]
+    async fn registry_subscribes_to_resources_and_reports_changes() {
+        let (client_out, peer_in) = tokio::io::duplex(4096);
+        let (peer_out, client_in) = tokio::io::duplex(4096);
+        let peer = spawn_resource_peer(peer_in, peer_out);
+        let mut registry = McpRegistry::default();
+        let mut changes = registry.resource_changes();
+
+        registry
+            .connect_transport(
+                "docs",
+                Box::new(StdioTransport::from_streams(client_in, client_out)),
+            )
+            .await
+            .expect("connect");
+        tokio::time::timeout(std::time::Duration::from_secs(5), changes.recv())
+            .await
+            .expect("list_changed forwarded")
+            .expect("change");
+
+        // Already subscribed resources are not subscribed again
+        registry.subscribe_resources().await;
+        drop(registry);
+        assert_eq!(peer.await.expect("peer task"), vec!["docs://readme"]);
+    }
+}
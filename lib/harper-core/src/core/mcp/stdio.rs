@@ -0,0 +1,421 @@
+// Copyright 2026 harpertoken
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! stdio transport for MCP servers
+//!
+//! The server runs as a child process and exchanges newline-delimited
+//! JSON-RPC messages over its stdin and stdout. Responses are matched to
+//! requests by id; everything carrying a `method` is forwarded to the
+//! client as a server event.
+
+use async_trait::async_trait;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::process::Stdio;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::process::{Child, Command};
+use tokio::sync::{mpsc, oneshot};
+use turul_mcp_client::error::TransportError;
+use turul_mcp_client::transport::{
+    ConnectionInfo, EventReceiver, ServerEvent, Transport, TransportCapabilities,
+    TransportResponse, TransportStatistics, TransportType,
+};
+use turul_mcp_client::McpClientResult;
+
+type BoxedReader = Box<dyn AsyncRead + Send + Unpin>;
+type BoxedWriter = Box<dyn AsyncWrite + Send + Unpin>;
+type PendingRequests = Arc<Mutex<HashMap<String, oneshot::Sender<Value>>>>;
+type EventSender = Arc<Mutex<Option<mpsc::UnboundedSender<ServerEvent>>>>;
+
+enum Peer {
+    Command {
+        command: String,
+        args: Vec<String>,
+        env: HashMap<String, String>,
+    },
+    Streams(Mutex<Option<(BoxedReader, BoxedWriter)>>),
+}
+
+/// MCP transport over a child process's stdin and stdout
+pub struct StdioTransport {
+    peer: Peer,
+    endpoint: String,
+    writer: tokio::sync::Mutex<Option<BoxedWriter>>,
+    child: Mutex<Option<Child>>,
+    pending: PendingRequests,
+    events: EventSender,
+    connected: Arc<AtomicBool>,
+}
+
+impl StdioTransport {
+    /// Launch `command` with `args` and `env` on connect
+    pub fn new(command: &str, args: &[String], env: &HashMap<String, String>) -> Self {
+        Self::with_peer(
+            Peer::Command {
+                command: command.to_string(),
+                args: args.to_vec(),
+                env: env.clone(),
+            },
+            format!("stdio:{}", command),
+        )
+    }
+
+    /// Talk to a peer that is already running, such as an in-process server
+    pub fn from_streams<R, W>(reader: R, writer: W) -> Self
+    where
+        R: AsyncRead + Send + Unpin + 'static,
+        W: AsyncWrite + Send + Unpin + 'static,
+    {
+        Self::with_peer(
+            Peer::Streams(Mutex::new(Some((Box::new(reader), Box::new(writer))))),
+            "stdio:streams".to_string(),
+        )
+    }
+
+    fn with_peer(peer: Peer, endpoint: String) -> Self {
+        Self {
+            peer,
+            endpoint,
+            writer: tokio::sync::Mutex::new(None),
+            child: Mutex::new(None),
+            pending: Arc::new(Mutex::new(HashMap::new())),
+            events: Arc::new(Mutex::new(None)),
+            connected: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    fn open(&self) -> McpClientResult<(BoxedReader, BoxedWriter)> {
+        match &self.peer {
+            Peer::Command { command, args, env } => {
+                let mut child = Command::new(command)
+                    .args(args)
+                    .envs(env)
+                    .stdin(Stdio::piped())
+                    .stdout(Stdio::piped())
+                    .stderr(Stdio::piped())
+                    .kill_on_drop(true)
+                    .spawn()
+                    .map_err(|e| {
+                        TransportError::ConnectionFailed(format!(
+                            "failed to start {}: {}",
+                            command, e
+                        ))
+                    })?;
+                let stdin = child.stdin.take().ok_or(TransportError::Closed)?;
+                let stdout = child.stdout.take().ok_or(TransportError::Closed)?;
+                if let Some(stderr) = child.stderr.take() {
+                    let command = command.clone();
+                    tokio::spawn(async move {
+                        let mut lines = BufReader::new(stderr).lines();
+                        while let Ok(Some(line)) = lines.next_line().await {
+                            log::debug!("{}: {}", command, line);
+                        }
+                    });
+                }
+                *self.child.lock().expect("stdio child lock") = Some(child);
+                Ok((Box::new(stdout), Box::new(stdin)))
+            }
+            Peer::Streams(streams) => streams
+                .lock()
+                .expect("stdio streams lock")
+                .take()
+                .ok_or_else(|| {
+                    TransportError::ConnectionFailed("stdio streams already used".to_string())
+                        .into()
+                }),
+        }
+    }
+
+    fn spawn_reader(&self, reader: BoxedReader) {
+        let pending = Arc::clone(&self.pending);
+        let events = Arc::clone(&self.events);
+        let connected = Arc::clone(&self.connected);
+        tokio::spawn(async move {
+            let mut lines = BufReader::new(reader).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                if line.trim().is_empty() {
+                    continue;
+                }
+                match serde_json::from_str::<Value>(&line) {
+                    Ok(message) => dispatch(&pending, &events, message),
+                    Err(e) => log::debug!("Ignoring malformed MCP message: {}", e),
+                }
+            }
+            connected.store(false, Ordering::SeqCst);
+            // Dropping the senders wakes every waiting request with an error
+            pending.lock().expect("pending requests lock").clear();
+            if let Some(events) = events.lock().expect("event sender lock").as_ref() {
+                let _ = events.send(ServerEvent::ConnectionLost);
+            }
+        });
+    }
+
+    async fn write_message(&self, message: &Value) -> McpClientResult<()> {
+        let mut line = serde_json::to_string(message)?;
+        line.push('\n');
+        let mut writer = self.writer.lock().await;
+        let writer = writer.as_mut().ok_or(TransportError::Closed)?;
+        writer
+            .write_all(line.as_bytes())
+            .await
+            .map_err(|e| TransportError::Stdio(e.to_string()))?;
+        writer
+            .flush()
+            .await
+            .map_err(|e| TransportError::Stdio(e.to_string()))?;
+        Ok(())
+    }
+}
+
+fn request_key(id: &Value) -> String {
+    id.to_string()
+}
+
+fn dispatch(pending: &PendingRequests, events: &EventSender, message: Value) {
+    if message.get("method").is_some() {
+        let event = if message.get("id").is_some() {
+            ServerEvent::Request(message)
+        } else {
+            ServerEvent::Notification(message)
+        };
+        if let Some(events) = events.lock().expect("event sender lock").as_ref() {
+            let _ = events.send(event);
+        }
+        return;
+    }
+
+    let Some(id) = message.get("id") else {
+        return;
+    };
+    let waiter = pending
+        .lock()
+        .expect("pending requests lock")
+        .remove(&request_key(id));
+    if let Some(waiter) = waiter {
+        let _ = waiter.send(message);
+    }
+}
+
+#[async_trait]
+impl Transport for StdioTransport {
+    fn transport_type(&self) -> TransportType {
+        // The client library has no stdio variant; the exchange is the same
+        // request/response JSON-RPC it uses over HTTP.
+        TransportType::Http
+    }
+
+    fn capabilities(&self) -> TransportCapabilities {
+        TransportCapabilities {
+            streaming: false,
+            bidirectional: true,
+            server_events: true,
+            max_message_size: None,
+            persistent: true,
+        }
+    }
+
+    async fn connect(&self) -> McpClientResult<()> {
+        if self.is_connected() {
+            return Ok(());
+        }
+        let (reader, writer) = self.open()?;
+        *self.writer.lock().await = Some(writer);
+        self.connected.store(true, Ordering::SeqCst);
+        self.spawn_reader(reader);
+        Ok(())
+    }
+
+    async fn disconnect(&self) -> McpClientResult<()> {
+        self.connected.store(false, Ordering::SeqCst);
+        // Closing stdin asks the server to exit; the kill covers servers that don't
+        self.writer.lock().await.take();
+        if let Some(mut child) = self.child.lock().expect("stdio child lock").take() {
+            let _ = child.start_kill();
+        }
+        Ok(())
+    }
+
+    fn is_connected(&self) -> bool {
+        self.connected.load(Ordering::SeqCst)
+    }
+
+    async fn send_request(&self, request: Value) -> McpClientResult<Value> {
+        let id = request
+            .get("id")
+            .map(request_key)
+            .ok_or_else(|| TransportError::Stdio("request has no id".to_string()))?;
+        let (sender, receiver) = oneshot::channel();
+        self.pending
+            .lock()
+            .expect("pending requests lock")
+            .insert(id.clone(), sender);
+        if !self.is_connected() {
+            self.pending
+                .lock()
+                .expect("pending requests lock")
+                .remove(&id);
+            return Err(TransportError::Closed.into());
+        }
+        if let Err(e) = self.write_message(&request).await {
+            self.pending
+                .lock()
+                .expect("pending requests lock")
+                .remove(&id);
+            return Err(e);
+        }
+        receiver.await.map_err(|_| TransportError::Closed.into())
+    }
+
+    async fn send_request_with_headers(
+        &self,
+        request: Value,
+    ) -> McpClientResult<TransportResponse> {
+        let body = self.send_request(request).await?;
+        Ok(TransportResponse::body_only(body))
+    }
+
+    async fn send_notification(&self, notification: Value) -> McpClientResult<()> {
+        self.write_message(&notification).await
+    }
+
+    async fn send_delete(&self, _session_id: &str) -> McpClientResult<()> {
+        Ok(())
+    }
+
+    fn set_session_id(&self, _session_id: String) {}
+
+    fn clear_session_id(&self) {}
+
+    async fn start_event_listener(&self) -> McpClientResult<EventReceiver> {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        *self.events.lock().expect("event sender lock") = Some(sender);
+        Ok(receiver)
+    }
+
+    fn connection_info(&self) -> ConnectionInfo {
+        ConnectionInfo {
+            transport_type: self.transport_type(),
+            endpoint: self.endpoint.clone(),
+            connected: self.is_connected(),
+            capabilities: self.capabilities(),
+            metadata: serde_json::json!({ "transport": "stdio" }),
+        }
+    }
+
+    fn statistics(&self) -> TransportStatistics {
+        TransportStatistics::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use turul_mcp_client::McpClientBuilder;
+
+    /// Minimal line-delimited MCP peer answering initialize, tools/list and
+    /// tools/call, recording every notification it receives
+    fn spawn_peer(
+        reader: tokio::io::DuplexStream,
+        mut writer: tokio::io::DuplexStream,
+    ) -> tokio::task::JoinHandle<Vec<String>> {
+        tokio::spawn(async move {
+            let mut notifications = Vec::new();
+            let mut lines = BufReader::new(reader).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                let message: Value = serde_json::from_str(&line).expect("peer json");
+                let method = message["method"].as_str().unwrap_or_default().to_string();
+                let Some(id) = message.get("id").cloned() else {
+                    notifications.push(method);
+                    continue;
+                };
+                let result = match method.as_str() {
+                    "initialize" => json!({
+                        "protocolVersion": message["params"]["protocolVersion"],
+                        "capabilities": {"tools": {"listChanged": false}},
+                        "serverInfo": {"name": "peer", "version": "1.0.0"}
+                    }),
+                    "tools/list" => json!({
+                        "tools": [{
+                            "name": "echo",
+                            "inputSchema": {"type": "object", "properties": {}}
+                        }]
+                    }),
+                    "tools/call" => json!({
+                        "content": [{
+                            "type": "text",
+                            "text": message["params"]["arguments"]["message"]
+                        }]
+                    }),
+                    _ => json!({}),
+                };
+                let response = json!({"jsonrpc": "2.0", "id": id, "result": result});
+                let line = format!("{}\n", response);
+                if writer.write_all(line.as_bytes()).await.is_err() {
+                    break;
+                }
+            }
+            notifications
+        })
+    }
+
+    #[tokio::test]
+    async fn client_talks_to_line_delimited_peer() {
+        let (client_out, peer_in) = tokio::io::duplex(4096);
+        let (peer_out, client_in) = tokio::io::duplex(4096);
+        let peer = spawn_peer(peer_in, peer_out);
+
+        let client = McpClientBuilder::new()
+            .with_transport(Box::new(StdioTransport::from_streams(
+                client_in, client_out,
+            )))
+            .build();
+        client.connect().await.expect("initialize over stdio");
+
+        let tools = client.list_tools().await.expect("tools/list");
+        assert_eq!(tools.len(), 1);
+        assert_eq!(tools[0].name, "echo");
+
+        let result = client
+            .call_tool("echo", json!({"message": "over stdio"}))
+            .await
+            .expect("tools/call");
+        assert!(
+            matches!(&result.content[0], turul_mcp_client::ContentBlock::Text { text, .. } if text == "over stdio")
+        );
+
+        client.disconnect().await.expect("disconnect");
+        drop(client);
+        let notifications = peer.await.expect("peer task");
+        assert_eq!(notifications, vec!["notifications/initialized"]);
+    }
+
+    #[tokio::test]
+    async fn pending_requests_fail_when_peer_exits() {
+        let (client_out, _peer_in) = tokio::io::duplex(4096);
+        let (peer_out, client_in) = tokio::io::duplex(4096);
+        let transport = StdioTransport::from_streams(client_in, client_out);
+        transport.connect().await.expect("connect");
+
+        drop(peer_out);
+        let err = transport
+            .send_request(json!({"jsonrpc": "2.0", "id": 1, "method": "ping"}))
+            .await
+            .expect_err("closed peer");
+        assert!(err.to_string().contains("closed"), "{}", err);
+        assert!(!transport.is_connected());
+    }
+}
@@ -20,13 +20,16 @@ pub mod agents;
 pub mod auth;
 pub mod cache;
 pub mod constants;
+pub mod context;
 pub mod error;
 pub mod io_traits;
 pub mod llm_client;
+pub mod mcp;
 pub mod models;
 pub mod native_shell;
 pub mod plan;
 pub mod plan_events;
+pub mod usage;
 
 /// Supported AI API providers
 #[derive(Debug, Clone, Copy)]
@@ -35,6 +38,10 @@ pub enum ApiProvider {
     Sambanova,
     Gemini,
     Ollama,
+    Anthropic,
+    /// Any server speaking the OpenAI chat-completions protocol, such as vLLM,
+    /// llama.cpp server or LM Studio
+    OpenAiCompatible,
 }
 
 impl std::fmt::Display for ApiProvider {
@@ -44,6 +51,8 @@ impl std::fmt::Display for ApiProvider {
             ApiProvider::Sambanova => write!(f, "Sambanova"),
             ApiProvider::Gemini => write!(f, "Gemini"),
             ApiProvider::Ollama => write!(f, "Ollama"),
+            ApiProvider::Anthropic => write!(f, "Anthropic"),
+            ApiProvider::OpenAiCompatible => write!(f, "OpenAiCompatible"),
         }
     }
 }
@@ -59,15 +68,169 @@ pub struct ApiConfig {
     pub base_url: String,
     /// Name of the model to use
     pub model_name: String,
+    /// Extra HTTP headers sent with every request
+    pub headers: HashMap<String, String>,
+    /// Sampling parameters for the configured model
+    pub parameters: ModelParameters,
+    /// Where to turn when this provider fails with a retriable error
+    pub fallback: FallbackChain,
+}
+
+impl ApiConfig {
+    /// Whether images attached to messages are sent to the model
+    ///
+    /// `vision` in the model parameters wins; otherwise Anthropic and Gemini
+    /// models are assumed to accept images, as are OpenAI's multimodal
+    /// families. Local and compatible servers need `vision = true`.
+    pub fn supports_vision(&self) -> bool {
+        if let Some(vision) = self.parameters.vision {
+            return vision;
+        }
+        match self.provider {
+            ApiProvider::Anthropic | ApiProvider::Gemini => true,
+            ApiProvider::OpenAI => {
+                let model = self.model_name.to_ascii_lowercase();
+                [
+                    "gpt-4o",
+                    "gpt-4.1",
+                    "gpt-4-turbo",
+                    "gpt-5",
+                    "o1",
+                    "o3",
+                    "o4",
+                ]
+                .iter()
+                .any(|family| model.starts_with(family))
+            }
+            ApiProvider::Sambanova | ApiProvider::Ollama | ApiProvider::OpenAiCompatible => false,
+        }
+    }
+}
+
+/// Providers and retry behaviour used when a request fails with a retriable error
+#[derive(Debug, Clone, Default)]
+pub struct FallbackChain {
+    /// Providers tried in order once the primary one gives up
+    pub providers: Vec<ApiConfig>,
+    /// Backoff applied to rate-limited and server-error responses
+    pub retry: RetryPolicy,
+    /// Answer from `agent::offline_shell` once every provider has failed
+    pub offline_shell: bool,
+}
+
+/// Exponential backoff for rate-limited (429) and server-error (5xx) responses
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct RetryPolicy {
+    /// Retries per provider before moving down the fallback chain
+    pub max_retries: u32,
+    pub initial_backoff_ms: u64,
+    /// Upper bound on a single wait; a longer `Retry-After` skips to the next provider
+    pub max_backoff_ms: u64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 2,
+            initial_backoff_ms: 500,
+            max_backoff_ms: 8_000,
+        }
+    }
+}
+
+/// Sampling and context parameters for chat requests
+///
+/// Unset values fall back to the provider defaults.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+pub struct ModelParameters {
+    pub temperature: Option<f64>,
+    pub top_p: Option<f64>,
+    pub max_tokens: Option<u32>,
+    /// Context window used to budget conversation history, in tokens
+    pub context_window: Option<u32>,
+    /// Price of a million prompt tokens in USD, used to estimate spend
+    pub input_cost_per_mtok: Option<f64>,
+    /// Price of a million completion tokens in USD
+    pub output_cost_per_mtok: Option<f64>,
+    /// Whether the model accepts images; guessed from the provider and model when unset
+    pub vision: Option<bool>,
+}
+
+impl ModelParameters {
+    /// Fill unset values from `defaults`
+    pub fn or(self, defaults: ModelParameters) -> ModelParameters {
+        ModelParameters {
+            temperature: self.temperature.or(defaults.temperature),
+            top_p: self.top_p.or(defaults.top_p),
+            max_tokens: self.max_tokens.or(defaults.max_tokens),
+            context_window: self.context_window.or(defaults.context_window),
+            input_cost_per_mtok: self.input_cost_per_mtok.or(defaults.input_cost_per_mtok),
+            output_cost_per_mtok: self.output_cost_per_mtok.or(defaults.output_cost_per_mtok),
+            vision: self.vision.or(defaults.vision),
+        }
+    }
 }
 
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
 /// A message in a conversation with an AI model
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Message {
-    /// The role of the message sender (user, assistant, system)
+    /// The role of the message sender (user, assistant, system, tool)
     pub role: String,
     /// The content of the message
     pub content: String,
+    /// For `tool` messages, the id of the tool call this result answers
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_call_id: Option<String>,
+    /// Images sent along with the content to models that accept them
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub images: Vec<ImageAttachment>,
+}
+
+impl Message {
+    /// Build a plain text message with the given role
+    pub fn text(role: impl Into<String>, content: impl Into<String>) -> Self {
+        Self {
+            role: role.into(),
+            content: content.into(),
+            tool_call_id: None,
+            images: Vec::new(),
+        }
+    }
+
+    /// Build a `tool` role message carrying the result of one tool call
+    pub fn tool_result(tool_call_id: impl Into<String>, content: impl Into<String>) -> Self {
+        Self {
+            role: "tool".to_string(),
+            content: content.into(),
+            tool_call_id: Some(tool_call_id.into()),
+            images: Vec::new(),
+        }
+    }
+
+    /// Attach images, such as those returned by an MCP tool
+    pub fn with_images(mut self, images: Vec<ImageAttachment>) -> Self {
+        self.images = images;
+        self
+    }
+}
+
+/// A base64-encoded image attached to a message
+///
+/// The message content always carries a text placeholder as well, so models
+/// without image input and stored history still see that an image was there.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImageAttachment {
+    pub mime_type: String,
+    pub data: String,
+}
+
+impl ImageAttachment {
+    /// `data:` URL used by the chat-completions `image_url` part
+    pub fn data_url(&self) -> String {
+        format!("data:{};base64,{}", self.mime_type, self.data)
+    }
 }
@@ -40,6 +40,11 @@ impl ProviderModels {
         default_model: "llama3",
     };
 
+    pub const ANTHROPIC: ProviderModels = ProviderModels {
+        base_url: "https://api.anthropic.com/v1/messages",
+        default_model: "claude-sonnet-4-5",
+    };
+
     pub const CEREBRAS: ProviderModels = ProviderModels {
         base_url: "https://api.cerebras.ai/v1/chat/completions",
         default_model: "qwen-3-32b-a4b",
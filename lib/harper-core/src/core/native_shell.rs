@@ -13,6 +13,7 @@
 // limitations under the License.
 
 use crate::core::error::{HarperError, HarperResult};
+use crate::core::mcp::{render_content, McpRegistry};
 use crate::core::plan::{PlanItem, PlanState, PlanStepStatus};
 use rusqlite::Connection;
 
@@ -20,9 +21,11 @@ use rusqlite::Connection;
 pub enum NativeShellCommand {
     Ask(String),
     Auth(AuthShellCommand),
+    Compact,
     Config(ConfigShellCommand),
     Help,
     History(HistoryShellCommand),
+    Mcp(McpShellCommand),
     Session(SessionShellCommand),
     Status,
     Update(UpdateShellCommand),
@@ -50,6 +53,10 @@ pub struct ConfigShellContext {
     pub approval: String,
     pub strategy: String,
     pub sandbox: String,
+    /// Built-in tools left after `[tools]`
+    pub tools: Vec<String>,
+    /// The `[tools]` patterns, or `none`
+    pub tool_filters: String,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -70,6 +77,16 @@ pub enum HistoryShellCommand {
     Show(Option<String>),
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum McpShellCommand {
+    Resources,
+    Prompts,
+    Prompt {
+        name: String,
+        arguments: Vec<(String, String)>,
+    },
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum SessionShellCommand {
     List,
@@ -105,10 +122,21 @@ pub enum NativeShellOutcome {
     UpdateCheck,
     UpdateApply,
     UpdateStatus,
-    ConfigSet { key: String, value: String },
-    AuthLogin { provider: String },
+    ConfigSet {
+        key: String,
+        value: String,
+    },
+    AuthLogin {
+        provider: String,
+    },
     AuthLogout,
-    OpenSession { target: String, preview: bool },
+    Compact,
+    OpenSession {
+        target: String,
+        preview: bool,
+    },
+    /// Needs the connected MCP servers; see [`execute_mcp_shell_command`]
+    Mcp(McpShellCommand),
 }
 
 pub fn parse_native_shell_command(input: &str) -> HarperResult<Option<NativeShellCommand>> {
@@ -127,12 +155,15 @@ pub fn parse_native_shell_command(input: &str) -> HarperResult<Option<NativeShel
     match command {
         "help" if has_slash || tokens.len() == 1 => Ok(Some(NativeShellCommand::Help)),
         "status" if has_slash || tokens.len() == 1 => Ok(Some(NativeShellCommand::Status)),
+        "compact" if has_slash || tokens.len() == 1 => Ok(Some(NativeShellCommand::Compact)),
         "auth" => parse_auth_command(&tokens, has_slash)
             .map(|command| command.map(NativeShellCommand::Auth)),
         "config" => parse_config_command(&tokens, has_slash)
             .map(|command| command.map(NativeShellCommand::Config)),
         "history" => parse_history_command(&tokens, has_slash)
             .map(|command| command.map(NativeShellCommand::History)),
+        "mcp" => parse_mcp_command(&tokens, has_slash)
+            .map(|command| command.map(NativeShellCommand::Mcp)),
         "session" | "sessions" => parse_session_command(&tokens, has_slash)
             .map(|command| command.map(NativeShellCommand::Session)),
         "update" => parse_update_command(&tokens, has_slash)
@@ -183,6 +214,8 @@ pub fn execute_native_shell_command_with_context(
     match command {
         NativeShellCommand::Ask(prompt) => Ok(NativeShellOutcome::Ask(prompt)),
         NativeShellCommand::Run(command) => Ok(NativeShellOutcome::Run(command)),
+        NativeShellCommand::Compact => Ok(NativeShellOutcome::Compact),
+        NativeShellCommand::Mcp(command) => Ok(NativeShellOutcome::Mcp(command)),
         NativeShellCommand::Config(ConfigShellCommand::Set { key, value }) => {
             Ok(NativeShellOutcome::ConfigSet { key, value })
         }
@@ -307,6 +340,134 @@ fn parse_history_command(
     }
 }
 
+fn parse_mcp_command(tokens: &[String], strict: bool) -> HarperResult<Option<McpShellCommand>> {
+    match tokens
+        .get(1)
+        .map(|token| token.as_str())
+        .unwrap_or("resources")
+    {
+        "resources" => Ok(Some(McpShellCommand::Resources)),
+        "prompts" => Ok(Some(McpShellCommand::Prompts)),
+        "prompt" => {
+            let name = tokens.get(2).cloned().ok_or_else(|| {
+                HarperError::Validation("mcp prompt requires a prompt name".to_string())
+            })?;
+            let arguments = tokens[3..]
+                .iter()
+                .map(|argument| {
+                    argument
+                        .split_once('=')
+                        .filter(|(key, _)| !key.is_empty())
+                        .map(|(key, value)| (key.to_string(), value.to_string()))
+                        .ok_or_else(|| {
+                            HarperError::Validation(format!(
+                                "mcp prompt arguments must be key=value, got '{}'",
+                                argument
+                            ))
+                        })
+                })
+                .collect::<HarperResult<Vec<_>>>()?;
+            Ok(Some(McpShellCommand::Prompt { name, arguments }))
+        }
+        _ if !strict => Ok(None),
+        subcommand => Err(HarperError::Validation(format!(
+            "unknown mcp command '{}'",
+            subcommand
+        ))),
+    }
+}
+
+/// What an `mcp` command produced
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum McpShellReply {
+    /// Text to show the user
+    Listing(String),
+    /// An expanded prompt template, sent as the next user message
+    Prompt(String),
+}
+
+/// Run an `mcp` command against the connected servers
+pub async fn execute_mcp_shell_command(
+    mcp: Option<&McpRegistry>,
+    command: &McpShellCommand,
+) -> HarperResult<McpShellReply> {
+    let Some(mcp) = mcp.filter(|mcp| !mcp.is_empty()) else {
+        return Ok(McpShellReply::Listing(
+            "No MCP servers connected. Configure [[mcp.servers]] to use them.".to_string(),
+        ));
+    };
+
+    match command {
+        McpShellCommand::Resources => {
+            let resources = mcp.list_resources().await;
+            if resources.is_empty() {
+                return Ok(McpShellReply::Listing(
+                    "No MCP resources available.".to_string(),
+                ));
+            }
+            let mut lines = vec!["MCP resources:".to_string()];
+            for entry in resources {
+                let mut line = format!(
+                    "  @mcp:{}  {} [{}]",
+                    entry.resource.uri, entry.resource.name, entry.server
+                );
+                if let Some(description) = entry.resource.description.as_deref() {
+                    line.push_str(&format!(" - {}", description));
+                }
+                lines.push(line);
+            }
+            Ok(McpShellReply::Listing(lines.join("\n")))
+        }
+        McpShellCommand::Prompts => {
+            let prompts = mcp.list_prompts().await;
+            if prompts.is_empty() {
+                return Ok(McpShellReply::Listing(
+                    "No MCP prompts available.".to_string(),
+                ));
+            }
+            let mut lines = vec!["MCP prompts:".to_string()];
+            for entry in prompts {
+                let arguments = entry
+                    .prompt
+                    .arguments
+                    .iter()
+                    .flatten()
+                    .map(|argument| {
+                        if argument.required.unwrap_or(false) {
+                            format!(" {}=<value>", argument.name)
+                        } else {
+                            format!(" [{}=<value>]", argument.name)
+                        }
+                    })
+                    .collect::<String>();
+                let mut line = format!("  {}{}", entry.qualified_name(), arguments);
+                if let Some(description) = entry.prompt.description.as_deref() {
+                    line.push_str(&format!(" - {}", description));
+                }
+                lines.push(line);
+            }
+            Ok(McpShellReply::Listing(lines.join("\n")))
+        }
+        McpShellCommand::Prompt { name, arguments } => {
+            let result = mcp.get_prompt(name, arguments).await?;
+            let text = result
+                .messages
+                .into_iter()
+                .map(|message| render_content(vec![message.content]).0)
+                .filter(|text| !text.is_empty())
+                .collect::<Vec<_>>()
+                .join("\n\n");
+            if text.is_empty() {
+                return Err(HarperError::Mcp(format!(
+                    "MCP prompt {} returned no text",
+                    name
+                )));
+            }
+            Ok(McpShellReply::Prompt(text))
+        }
+    }
+}
+
 fn parse_session_command(
     tokens: &[String],
     strict: bool,
@@ -574,6 +735,8 @@ fn format_config_context(config: Option<&ConfigShellContext>) -> String {
         format!("approval: {}", config.approval),
         format!("strategy: {}", config.strategy),
         format!("sandbox: {}", config.sandbox),
+        format!("tools: {}", config.tools.join(", ")),
+        format!("tool filters: {}", config.tool_filters),
     ]
     .join("\n")
 }
@@ -604,6 +767,10 @@ fn help_text() -> String {
         "  session open <number|id>",
         "  history show [number|id]",
         "  history list [number|id]",
+        "  mcp resources",
+        "  mcp prompts",
+        "  mcp prompt <name> [key=value ...]",
+        "  compact",
         "  auth status",
         "  auth login [provider]",
         "  auth logout",
@@ -778,6 +945,47 @@ mod tests {
         );
     }
 
+    #[test]
+    fn parses_mcp_commands_with_prompt_arguments() {
+        assert_eq!(
+            parse_native_shell_command("/mcp resources")
+                .expect("parse")
+                .expect("command"),
+            NativeShellCommand::Mcp(McpShellCommand::Resources)
+        );
+        assert_eq!(
+            parse_native_shell_command("mcp prompts")
+                .expect("parse")
+                .expect("command"),
+            NativeShellCommand::Mcp(McpShellCommand::Prompts)
+        );
+        assert_eq!(
+            parse_native_shell_command(
+                r#"/mcp prompt docs/review file=src/main.rs focus="error handling""#
+            )
+            .expect("parse")
+            .expect("command"),
+            NativeShellCommand::Mcp(McpShellCommand::Prompt {
+                name: "docs/review".to_string(),
+                arguments: vec![
+                    ("file".to_string(), "src/main.rs".to_string()),
+                    ("focus".to_string(), "error handling".to_string()),
+                ],
+            })
+        );
+        assert!(parse_native_shell_command("/mcp prompt review src/main.rs").is_err());
+        assert!(parse_native_shell_command("/mcp prompt").is_err());
+        assert!(parse_native_shell_command("/mcp tools").is_err());
+    }
+
+    #[tokio::test]
+    async fn mcp_commands_explain_missing_servers() {
+        let reply = execute_mcp_shell_command(None, &McpShellCommand::Prompts)
+            .await
+            .expect("reply");
+        assert!(matches!(reply, McpShellReply::Listing(text) if text.contains("No MCP servers")));
+    }
+
     #[test]
     fn parses_read_only_service_commands() {
         assert_eq!(
@@ -886,6 +1094,12 @@ mod tests {
                 .expect("command"),
             NativeShellCommand::Update(UpdateShellCommand::Apply)
         );
+        assert_eq!(
+            parse_native_shell_command("/compact")
+                .expect("parse")
+                .expect("command"),
+            NativeShellCommand::Compact
+        );
     }
 
     #[test]
@@ -895,7 +1109,7 @@ mod tests {
                 .expect("help");
 
         assert!(
-            matches!(output, NativeShellOutcome::Handled(text) if text.contains("auth login [provider]") && text.contains("/strategy auto|grounded|deterministic|model"))
+            matches!(output, NativeShellOutcome::Handled(text) if text.contains("auth login [provider]") && text.contains("mcp prompt <name>") && text.contains("/strategy auto|grounded|deterministic|model"))
         );
     }
 
@@ -940,6 +1154,11 @@ mod tests {
                 value: "deterministic".to_string()
             }
         );
+        assert_eq!(
+            execute_native_shell_command(&conn, "session-a", NativeShellCommand::Compact)
+                .expect("compact"),
+            NativeShellOutcome::Compact
+        );
     }
 
     #[test]
@@ -991,6 +1210,42 @@ mod tests {
         assert_eq!(output, NativeShellOutcome::UpdateStatus);
     }
 
+    #[test]
+    fn config_show_lists_effective_tools() {
+        let conn = setup_conn();
+        let tools = crate::tools::registry::ToolRegistry::new(
+            Some(vec!["read_file".to_string(), "git_*".to_string()]),
+            vec!["git_commit".to_string()],
+        );
+        let context = NativeShellContext {
+            auth: None,
+            config: Some(ConfigShellContext {
+                provider: "OpenAI".to_string(),
+                model: "gpt-5.5".to_string(),
+                base_url: "https://api.openai.com/v1/chat/completions".to_string(),
+                database_path: ":memory:".to_string(),
+                approval: "AllowListed".to_string(),
+                strategy: "Auto".to_string(),
+                sandbox: "Disabled".to_string(),
+                tools: tools.effective_tools(),
+                tool_filters: tools.describe_filters(),
+            }),
+        };
+        let output = execute_native_shell_command_with_context(
+            &conn,
+            "session-a",
+            NativeShellCommand::Config(ConfigShellCommand::Show),
+            &context,
+        )
+        .expect("config show");
+
+        let NativeShellOutcome::Handled(text) = output else {
+            panic!("config show should be handled");
+        };
+        assert!(text.contains("tools: read_file, git_status, git_diff, git_add"));
+        assert!(text.contains("tool filters: enabled [read_file, git_*], disabled [git_commit]"));
+    }
+
     #[test]
     fn plan_commands_update_persisted_state() {
         let conn = setup_conn();
@@ -0,0 +1,176 @@
+// Copyright 2026 harpertoken
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Token usage and cost accounting
+//!
+//! Providers report prompt and completion token counts with every reply.
+//! They are recorded per call and priced from the per-model
+//! `input_cost_per_mtok` / `output_cost_per_mtok` parameters.
+
+use crate::core::{ApiProvider, ModelParameters};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Tokens a provider billed for a single call
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenUsage {
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+}
+
+impl TokenUsage {
+    /// Read the usage block of a provider response or stream event
+    ///
+    /// Anthropic stream events carry the prompt count in `message_start` and
+    /// the completion count in `message_delta`, so either half may be 0.
+    pub fn from_response(provider: &ApiProvider, resp_json: &Value) -> Option<Self> {
+        let count = |value: &Value, key: &str| value.get(key).and_then(Value::as_u64);
+        let (prompt, completion) = match provider {
+            ApiProvider::OpenAI | ApiProvider::Sambanova | ApiProvider::OpenAiCompatible => {
+                let usage = resp_json.get("usage").filter(|usage| usage.is_object())?;
+                (
+                    count(usage, "prompt_tokens"),
+                    count(usage, "completion_tokens"),
+                )
+            }
+            ApiProvider::Gemini => {
+                let usage = resp_json.get("usageMetadata")?;
+                (
+                    count(usage, "promptTokenCount"),
+                    count(usage, "candidatesTokenCount"),
+                )
+            }
+            ApiProvider::Anthropic => {
+                let usage = resp_json
+                    .get("usage")
+                    .or_else(|| resp_json.pointer("/message/usage"))?;
+                (count(usage, "input_tokens"), count(usage, "output_tokens"))
+            }
+            ApiProvider::Ollama => (
+                count(resp_json, "prompt_eval_count"),
+                count(resp_json, "eval_count"),
+            ),
+        };
+        if prompt.is_none() && completion.is_none() {
+            return None;
+        }
+        Some(Self {
+            prompt_tokens: prompt.unwrap_or(0),
+            completion_tokens: completion.unwrap_or(0),
+        })
+    }
+
+    /// Take every non-zero count from `other`
+    pub fn merge(&mut self, other: TokenUsage) {
+        if other.prompt_tokens > 0 {
+            self.prompt_tokens = other.prompt_tokens;
+        }
+        if other.completion_tokens > 0 {
+            self.completion_tokens = other.completion_tokens;
+        }
+    }
+
+    pub fn total_tokens(&self) -> u64 {
+        self.prompt_tokens + self.completion_tokens
+    }
+
+    /// Estimated cost in USD, or `None` when the model has no prices configured
+    pub fn estimate_cost(&self, parameters: &ModelParameters) -> Option<f64> {
+        if parameters.input_cost_per_mtok.is_none() && parameters.output_cost_per_mtok.is_none() {
+            return None;
+        }
+        let input = parameters.input_cost_per_mtok.unwrap_or(0.0);
+        let output = parameters.output_cost_per_mtok.unwrap_or(0.0);
+        Some(
+            (self.prompt_tokens as f64 * input + self.completion_tokens as f64 * output)
+                / 1_000_000.0,
+        )
+    }
+}
+
+/// Usage summed over the calls of a session or a user
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct UsageTotals {
+    pub calls: usize,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    /// Sum of the estimated costs of priced calls, in USD
+    pub estimated_cost: f64,
+}
+
+impl UsageTotals {
+    pub fn total_tokens(&self) -> u64 {
+        self.prompt_tokens + self.completion_tokens
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn usage_is_read_from_each_provider_shape() {
+        let cases = [
+            (
+                ApiProvider::OpenAI,
+                json!({"usage": {"prompt_tokens": 12, "completion_tokens": 3}}),
+            ),
+            (
+                ApiProvider::Gemini,
+                json!({"usageMetadata": {"promptTokenCount": 12, "candidatesTokenCount": 3}}),
+            ),
+            (
+                ApiProvider::Anthropic,
+                json!({"usage": {"input_tokens": 12, "output_tokens": 3}}),
+            ),
+            (
+                ApiProvider::Ollama,
+                json!({"prompt_eval_count": 12, "eval_count": 3, "done": true}),
+            ),
+        ];
+        for (provider, body) in cases {
+            assert_eq!(
+                TokenUsage::from_response(&provider, &body),
+                Some(TokenUsage {
+                    prompt_tokens: 12,
+                    completion_tokens: 3,
+                }),
+                "{}",
+                provider
+            );
+        }
+        assert_eq!(
+            TokenUsage::from_response(&ApiProvider::OpenAI, &json!({"usage": null})),
+            None
+        );
+    }
+
+    #[test]
+    fn cost_uses_per_million_token_prices() {
+        let usage = TokenUsage {
+            prompt_tokens: 200_000,
+            completion_tokens: 50_000,
+        };
+        assert_eq!(usage.estimate_cost(&ModelParameters::default()), None);
+
+        let parameters = ModelParameters {
+            input_cost_per_mtok: Some(2.5),
+            output_cost_per_mtok: Some(10.0),
+            ..ModelParameters::default()
+        };
+        let cost = usage.estimate_cost(&parameters).expect("priced model");
+        assert!((cost - 1.0).abs() < 1e-9);
+    }
+}
@@ -27,39 +27,47 @@ pub use crate::core::agents::{AgentsSource, ResolvedAgents};
 pub use crate::core::auth::{AuthSession, AuthenticatedUser, UserAuthClaims, UserAuthProvider};
 pub use crate::core::constants::VERSION;
 pub use crate::core::error::{HarperError, HarperResult};
-pub use crate::core::llm_client::call_llm;
+pub use crate::core::llm_client::{call_llm, call_llm_stream, LlmReply};
+pub use crate::core::mcp::{McpPromptEntry, McpRegistry, McpResourceEntry, McpToolEntry};
 pub use crate::core::models::ProviderModels;
 pub use crate::core::native_shell::{
-    execute_native_shell_command, execute_native_shell_command_with_context,
-    parse_native_shell_command, resolve_session_target, AuthShellContext, ConfigShellContext,
-    NativeShellCommand, NativeShellContext, NativeShellOutcome, PlanShellCommand,
+    execute_mcp_shell_command, execute_native_shell_command,
+    execute_native_shell_command_with_context, parse_native_shell_command, resolve_session_target,
+    AuthShellContext, ConfigShellContext, McpShellCommand, McpShellReply, NativeShellCommand,
+    NativeShellContext, NativeShellOutcome, PlanShellCommand,
 };
 pub use crate::core::plan::{PlanItem, PlanRuntime, PlanState, PlanStepStatus};
-pub use crate::core::{ApiConfig, ApiProvider, Message};
+pub use crate::core::usage::{TokenUsage, UsageTotals};
+pub use crate::core::{
+    ApiConfig, ApiProvider, FallbackChain, ImageAttachment, Message, ModelParameters, RetryPolicy,
+};
 
 // Re-export agent types
 pub use crate::agent::chat::ChatService;
 
 // Re-export tools
+pub use crate::tools::registry::ToolRegistry;
 pub use crate::tools::{
-    api, code_analysis, db, filesystem, firmware, git, github, image, parsing, plan, screenpipe,
-    shell, todo, web, ToolService,
+    api, code_analysis, db, filesystem, firmware, git, github, image, parsing, plan, registry,
+    screenpipe, shell, todo, web, ToolService,
 };
 
 // Re-export memory utilities
 pub use crate::memory::cache::{CacheAligned, CacheAlignedBuffer, CACHE_LINE_BYTES};
 pub use crate::memory::session_service::SessionStateView;
 pub use crate::memory::storage::{
-    clear_todos, create_connection, delete_messages, delete_session, delete_todo, init_db,
-    insert_command_log, list_sessions, load_active_agents, load_command_logs_for_session,
-    load_history, load_latest_command_log, load_plan_state, load_todos, save_active_agents,
-    save_message, save_plan_state, save_session, save_todo, CommandLogEntry,
+    clear_todos, compact_messages, create_connection, delete_messages, delete_session, delete_todo,
+    init_db, insert_command_log, insert_token_usage, list_sessions, load_active_agents,
+    load_command_logs_for_session, load_context_history, load_history, load_latest_command_log,
+    load_plan_state, load_session_usage, load_todos, save_active_agents, save_message,
+    save_plan_state, save_session, save_todo, CommandLogEntry, SUMMARY_ROLE,
 };
 pub use crate::runtime::utils;
 
 // Re-export runtime
 pub use crate::runtime::config::{
-    ApprovalProfile, ExecPolicyConfig, ExecutionStrategy, SandboxProfile, SupabaseAuthConfig,
+    ApprovalProfile, ExecPolicyConfig, ExecutionStrategy, McpConfig, McpServerConfig,
+    McpTransportKind, SandboxProfile, SupabaseAuthConfig,
 };
 pub use crate::runtime::scheduler::TaskScheduler;
 pub use crate::runtime::update::{
@@ -89,6 +97,9 @@ mod tests {
             api_key: "test-key".to_string(),
             base_url: "https://api.openai.com/v1/chat/completions".to_string(),
             model_name: "gpt-5.5".to_string(),
+            headers: Default::default(),
+            parameters: Default::default(),
+            fallback: Default::default(),
         }
     }
 
@@ -99,6 +110,8 @@ mod tests {
             ApiProvider::Sambanova,
             ApiProvider::Gemini,
             ApiProvider::Ollama,
+            ApiProvider::Anthropic,
+            ApiProvider::OpenAiCompatible,
         ];
 
         for provider in providers {
@@ -113,6 +126,9 @@ mod tests {
             api_key: "test-key".to_string(),
             base_url: "https://api.openai.com/v1/chat/completions".to_string(),
             model_name: "gpt-5.5".to_string(),
+            headers: Default::default(),
+            parameters: Default::default(),
+            fallback: Default::default(),
         };
 
         assert!(matches!(config.provider, ApiProvider::OpenAI));
@@ -123,10 +139,7 @@ mod tests {
 
     #[test]
     fn test_message_creation() {
-        let message = Message {
-            role: "user".to_string(),
-            content: "Hello, world!".to_string(),
-        };
+        let message = Message::text("user", "Hello, world!");
 
         assert_eq!(message.role, "user");
         assert_eq!(message.content, "Hello, world!");
@@ -139,10 +152,7 @@ mod tests {
         let roles = ["user", "assistant", "system"];
 
         for role in roles {
-            let message = Message {
-                role: role.to_string(),
-                content: "Test content".to_string(),
-            };
+            let message = Message::text(role, "Test content");
             assert_eq!(message.role, role);
         }
     }
@@ -21,6 +21,7 @@ use crate::core::agents::ResolvedAgents;
 use crate::core::error::{HarperError, HarperResult};
 use crate::core::io_traits::{Input, Output};
 use crate::core::plan::PlanState;
+use crate::core::usage::UsageTotals;
 use crate::core::Message;
 use crate::memory::cache::CacheAlignedBuffer;
 use crate::memory::storage::{
@@ -50,6 +51,11 @@ pub struct GlobalStats {
     pub total_commands: usize,
     pub approved_commands: usize,
     pub avg_command_duration_ms: f64,
+    /// Response cache hits; the cache is shared, so user-scoped stats leave this at 0
+    pub cache_hits: usize,
+    pub cache_misses: usize,
+    /// LLM token usage and estimated cost
+    pub usage: UsageTotals,
 }
 
 #[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
@@ -61,6 +67,8 @@ pub struct SessionStateView {
     pub agents: Option<ResolvedAgents>,
     pub agents_rendered: Option<String>,
     pub agents_effective_rendered: Option<String>,
+    #[serde(default)]
+    pub usage: UsageTotals,
 }
 
 /// Service for managing chat sessions
@@ -119,6 +127,8 @@ impl<'a> SessionService<'a> {
                 |r| r.get::<_, Option<f64>>(0),
             )?
             .unwrap_or(0.0);
+        let (cache_hits, cache_misses) = crate::memory::storage::load_cache_stats(self.conn)?;
+        let usage = crate::memory::storage::load_usage_totals(self.conn, None)?;
 
         Ok(GlobalStats {
             total_sessions,
@@ -126,6 +136,9 @@ impl<'a> SessionService<'a> {
             total_commands,
             approved_commands,
             avg_command_duration_ms: avg_duration,
+            cache_hits,
+            cache_misses,
+            usage,
         })
     }
 
@@ -170,6 +183,7 @@ impl<'a> SessionService<'a> {
                 |r| r.get::<_, Option<f64>>(0),
             )?
             .unwrap_or(0.0);
+        let usage = crate::memory::storage::load_usage_totals(self.conn, Some(user_id))?;
 
         Ok(GlobalStats {
             total_sessions,
@@ -177,6 +191,8 @@ impl<'a> SessionService<'a> {
             total_commands,
             approved_commands,
             avg_command_duration_ms: avg_duration,
+            usage,
+            ..GlobalStats::default()
         })
     }
 
@@ -335,6 +351,7 @@ impl<'a> SessionService<'a> {
         let agents_effective_rendered = agents
             .as_ref()
             .and_then(|resolved| resolved.render_effective_for_display());
+        let usage = crate::memory::storage::load_session_usage(self.conn, session_id)?;
 
         Ok(SessionStateView {
             session_id: session_id.to_string(),
@@ -344,6 +361,7 @@ impl<'a> SessionService<'a> {
             agents,
             agents_rendered,
             agents_effective_rendered,
+            usage,
         })
     }
 
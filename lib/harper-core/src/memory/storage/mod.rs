@@ -19,9 +19,11 @@
 
 use crate::core::agents::ResolvedAgents;
 use crate::core::error::HarperResult;
+use crate::core::llm_client::LlmReply;
 use crate::core::plan::{PlanRuntime, PlanState};
+use crate::core::usage::UsageTotals;
 use crate::core::Message;
-use rusqlite::{params, Connection};
+use rusqlite::{params, Connection, OptionalExtension};
 use serde::{Deserialize, Serialize};
 
 /// Create a new database connection
@@ -98,6 +100,12 @@ pub fn init_db(conn: &Connection) -> HarperResult<()> {
          )",
         [],
     )?;
+    if !column_exists(conn, "messages", "compacted")? {
+        conn.execute(
+            "ALTER TABLE messages ADD COLUMN compacted INTEGER NOT NULL DEFAULT 0",
+            [],
+        )?;
+    }
     conn.execute(
         "CREATE TABLE IF NOT EXISTS todos (
              id INTEGER PRIMARY KEY AUTOINCREMENT,
@@ -168,6 +176,40 @@ pub fn init_db(conn: &Connection) -> HarperResult<()> {
          ON session_plan_events(session_id, id)",
         [],
     )?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS response_cache (
+             cache_key TEXT PRIMARY KEY,
+             response TEXT NOT NULL,
+             expires_at INTEGER NOT NULL,
+             created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+         )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS response_cache_stats (
+             id INTEGER PRIMARY KEY CHECK (id = 1),
+             hits INTEGER NOT NULL DEFAULT 0,
+             misses INTEGER NOT NULL DEFAULT 0
+         )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS token_usage (
+             id INTEGER PRIMARY KEY AUTOINCREMENT,
+             session_id TEXT NOT NULL,
+             provider TEXT NOT NULL,
+             model TEXT NOT NULL,
+             prompt_tokens INTEGER NOT NULL,
+             completion_tokens INTEGER NOT NULL,
+             estimated_cost REAL,
+             created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+         )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_token_usage_session_id ON token_usage(session_id)",
+        [],
+    )?;
     conn.execute(
         "CREATE TABLE IF NOT EXISTS session_agents (
              session_id TEXT PRIMARY KEY,
@@ -294,10 +336,10 @@ pub fn load_history(conn: &Connection, session_id: &str) -> HarperResult<Vec<Mes
     let mut stmt =
         conn.prepare("SELECT role, content FROM messages WHERE session_id = ?1 ORDER BY id ASC")?;
     let rows = stmt.query_map(params![session_id], |row| {
-        Ok(Message {
-            role: row.get(0)?,
-            content: row.get(1)?,
-        })
+        Ok(Message::text(
+            row.get::<_, String>(0)?,
+            row.get::<_, String>(1)?,
+        ))
     })?;
 
     let mut messages = Vec::new();
@@ -307,6 +349,191 @@ pub fn load_history(conn: &Connection, session_id: &str) -> HarperResult<Vec<Mes
     Ok(messages)
 }
 
+/// Role marking a compaction summary in the messages table
+pub const SUMMARY_ROLE: &str = "summary";
+
+/// Load the history a model should see for a session
+///
+/// Messages folded into a compaction summary are skipped, and the active
+/// summary (if any) leads the result. Use [`load_history`] for the full
+/// transcript including the compacted originals.
+///
+/// # Errors
+/// Returns `HarperError::Database` if the query fails
+pub fn load_context_history(conn: &Connection, session_id: &str) -> HarperResult<Vec<Message>> {
+    let mut stmt = conn.prepare(
+        "SELECT role, content FROM messages
+         WHERE session_id = ?1 AND compacted = 0
+         ORDER BY role = ?2 DESC, id ASC",
+    )?;
+    let rows = stmt.query_map(params![session_id, SUMMARY_ROLE], |row| {
+        Ok(Message::text(
+            row.get::<_, String>(0)?,
+            row.get::<_, String>(1)?,
+        ))
+    })?;
+    rows.collect::<Result<Vec<_>, _>>().map_err(Into::into)
+}
+
+/// Fold the oldest `count` context messages of a session into a summary
+///
+/// The folded messages are kept but marked compacted, and `summary` is
+/// stored as a new message with [`SUMMARY_ROLE`]. A previous summary is
+/// folded like any other message when it falls inside the span.
+///
+/// # Errors
+/// Returns `HarperError::Database` if the update or insert fails
+pub fn compact_messages(
+    conn: &Connection,
+    session_id: &str,
+    count: usize,
+    summary: &str,
+) -> HarperResult<()> {
+    let tx = conn.unchecked_transaction()?;
+    tx.execute(
+        "UPDATE messages SET compacted = 1
+         WHERE id IN (
+             SELECT id FROM messages
+             WHERE session_id = ?1 AND compacted = 0
+             ORDER BY role = ?2 DESC, id ASC
+             LIMIT ?3
+         )",
+        params![session_id, SUMMARY_ROLE, count as i64],
+    )?;
+    tx.execute(
+        "INSERT INTO messages (session_id, role, content) VALUES (?1, ?2, ?3)",
+        params![session_id, SUMMARY_ROLE, summary],
+    )?;
+    tx.commit()?;
+    Ok(())
+}
+
+/// Load a cached LLM response that has not expired yet
+pub fn load_cached_response(conn: &Connection, cache_key: &str) -> HarperResult<Option<String>> {
+    conn.query_row(
+        "SELECT response FROM response_cache
+         WHERE cache_key = ?1 AND expires_at > CAST(strftime('%s', 'now') AS INTEGER)",
+        params![cache_key],
+        |row| row.get(0),
+    )
+    .optional()
+    .map_err(Into::into)
+}
+
+/// Store an LLM response for `ttl`, dropping entries that already expired
+pub fn save_cached_response(
+    conn: &Connection,
+    cache_key: &str,
+    response: &str,
+    ttl: std::time::Duration,
+) -> HarperResult<()> {
+    conn.execute(
+        "DELETE FROM response_cache WHERE expires_at <= CAST(strftime('%s', 'now') AS INTEGER)",
+        [],
+    )?;
+    conn.execute(
+        "INSERT INTO response_cache (cache_key, response, expires_at)
+         VALUES (?1, ?2, CAST(strftime('%s', 'now') AS INTEGER) + ?3)
+         ON CONFLICT(cache_key) DO UPDATE SET
+             response = excluded.response,
+             expires_at = excluded.expires_at,
+             created_at = CURRENT_TIMESTAMP",
+        params![cache_key, response, ttl.as_secs() as i64],
+    )?;
+    Ok(())
+}
+
+/// Count a response cache lookup as a hit or a miss
+pub fn record_cache_lookup(conn: &Connection, hit: bool) -> HarperResult<()> {
+    conn.execute(
+        "INSERT INTO response_cache_stats (id, hits, misses) VALUES (1, ?1, ?2)
+         ON CONFLICT(id) DO UPDATE SET
+             hits = hits + excluded.hits,
+             misses = misses + excluded.misses",
+        params![hit as i64, (!hit) as i64],
+    )?;
+    Ok(())
+}
+
+/// Load response cache `(hits, misses)`
+pub fn load_cache_stats(conn: &Connection) -> HarperResult<(usize, usize)> {
+    conn.query_row(
+        "SELECT hits, misses FROM response_cache_stats WHERE id = 1",
+        [],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )
+    .optional()
+    .map(Option::unwrap_or_default)
+    .map_err(Into::into)
+}
+
+/// Record the token usage of an LLM call made for `session_id`
+///
+/// Replies without a usage block are skipped.
+pub fn insert_token_usage(
+    conn: &Connection,
+    session_id: &str,
+    reply: &LlmReply,
+) -> HarperResult<()> {
+    let Some(usage) = reply.usage else {
+        return Ok(());
+    };
+    conn.execute(
+        "INSERT INTO token_usage (
+             session_id, provider, model, prompt_tokens, completion_tokens, estimated_cost
+         ) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            session_id,
+            reply.provider.to_string(),
+            reply.model,
+            usage.prompt_tokens as i64,
+            usage.completion_tokens as i64,
+            reply.estimated_cost,
+        ],
+    )?;
+    Ok(())
+}
+
+/// Sum the token usage recorded for one session
+pub fn load_session_usage(conn: &Connection, session_id: &str) -> HarperResult<UsageTotals> {
+    conn.query_row(
+        "SELECT COUNT(*),
+                COALESCE(SUM(prompt_tokens), 0),
+                COALESCE(SUM(completion_tokens), 0),
+                COALESCE(SUM(estimated_cost), 0.0)
+         FROM token_usage
+         WHERE session_id = ?1",
+        params![session_id],
+        map_usage_totals,
+    )
+    .map_err(Into::into)
+}
+
+/// Sum the token usage across all sessions, or only those owned by `user_id`
+pub fn load_usage_totals(conn: &Connection, user_id: Option<&str>) -> HarperResult<UsageTotals> {
+    conn.query_row(
+        "SELECT COUNT(*),
+                COALESCE(SUM(u.prompt_tokens), 0),
+                COALESCE(SUM(u.completion_tokens), 0),
+                COALESCE(SUM(u.estimated_cost), 0.0)
+         FROM token_usage u
+         LEFT JOIN sessions s ON s.id = u.session_id
+         WHERE ?1 IS NULL OR s.user_id = ?1",
+        params![user_id],
+        map_usage_totals,
+    )
+    .map_err(Into::into)
+}
+
+fn map_usage_totals(row: &rusqlite::Row<'_>) -> rusqlite::Result<UsageTotals> {
+    Ok(UsageTotals {
+        calls: row.get(0)?,
+        prompt_tokens: row.get::<_, i64>(1)? as u64,
+        completion_tokens: row.get::<_, i64>(2)? as u64,
+        estimated_cost: row.get(3)?,
+    })
+}
+
 pub fn save_plan_state(conn: &Connection, session_id: &str, plan: &PlanState) -> HarperResult<()> {
     let items_json = serde_json::to_string(&plan.items)
         .map_err(|e| crate::core::error::HarperError::Database(e.to_string()))?;
@@ -762,6 +989,90 @@ mod tests {
         assert!(column_exists(&conn, "sessions", "user_id").expect("column lookup"));
     }
 
+    #[test]
+    fn compaction_hides_originals_from_context_but_keeps_them_in_history() {
+        let conn = Connection::open_in_memory().expect("in-memory sqlite");
+        init_db(&conn).expect("db init");
+        for (role, content) in [
+            ("user", "first"),
+            ("assistant", "one"),
+            ("user", "second"),
+            ("assistant", "two"),
+        ] {
+            save_message(&conn, "s1", role, content).expect("save message");
+        }
+
+        compact_messages(&conn, "s1", 2, "asked first, got one").expect("compact");
+        save_message(&conn, "s1", "user", "third").expect("save message");
+        compact_messages(&conn, "s1", 2, "first and second answered").expect("compact again");
+
+        let context: Vec<(String, String)> = load_context_history(&conn, "s1")
+            .expect("context")
+            .into_iter()
+            .map(|m| (m.role, m.content))
+            .collect();
+        assert_eq!(
+            context,
+            vec![
+                (
+                    SUMMARY_ROLE.to_string(),
+                    "first and second answered".to_string()
+                ),
+                ("assistant".to_string(), "two".to_string()),
+                ("user".to_string(), "third".to_string()),
+            ]
+        );
+
+        let history = load_history(&conn, "s1").expect("history");
+        assert_eq!(history.len(), 7);
+        assert_eq!(history[0].content, "first");
+        assert_eq!(history[4].role, SUMMARY_ROLE);
+    }
+
+    #[test]
+    fn token_usage_sums_per_session_and_per_user() {
+        let conn = Connection::open_in_memory().expect("in-memory sqlite");
+        init_db(&conn).expect("db init");
+        save_session_for_user(&conn, "s1", "user-a").expect("claim s1");
+        save_session(&conn, "s2").expect("save s2");
+        let reply = |prompt_tokens, completion_tokens, estimated_cost| LlmReply {
+            content: "ok".to_string(),
+            provider: crate::core::ApiProvider::OpenAI,
+            model: "gpt-4o".to_string(),
+            usage: Some(crate::core::usage::TokenUsage {
+                prompt_tokens,
+                completion_tokens,
+            }),
+            estimated_cost,
+        };
+
+        insert_token_usage(&conn, "s1", &reply(100, 20, Some(0.5))).expect("usage");
+        insert_token_usage(&conn, "s1", &reply(50, 5, None)).expect("usage");
+        insert_token_usage(&conn, "s2", &reply(10, 1, Some(0.25))).expect("usage");
+        let mut unreported = reply(0, 0, None);
+        unreported.usage = None;
+        insert_token_usage(&conn, "s2", &unreported).expect("skipped");
+
+        assert_eq!(
+            load_session_usage(&conn, "s1").expect("session usage"),
+            UsageTotals {
+                calls: 2,
+                prompt_tokens: 150,
+                completion_tokens: 25,
+                estimated_cost: 0.5,
+            }
+        );
+        let user = load_usage_totals(&conn, Some("user-a")).expect("user usage");
+        assert_eq!((user.calls, user.total_tokens()), (2, 175));
+        let all = load_usage_totals(&conn, None).expect("all usage");
+        assert_eq!((all.calls, all.total_tokens()), (3, 186));
+        assert!((all.estimated_cost - 0.75).abs() < 1e-9);
+        assert_eq!(
+            load_session_usage(&conn, "missing").expect("empty usage"),
+            UsageTotals::default()
+        );
+    }
+
     #[test]
     fn save_session_for_user_claims_once_and_blocks_reassignment() {
         let conn = Connection::open_in_memory().expect("in-memory sqlite");
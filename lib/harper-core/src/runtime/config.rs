@@ -12,11 +12,14 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use crate::core::cache::ResponseCacheConfig;
 use crate::core::error::{HarperError, HarperResult};
 use crate::core::models::ProviderModels;
-use crate::core::ApiProvider;
+use crate::core::{ApiProvider, FallbackChain, ModelParameters, RetryPolicy};
+use crate::tools::registry::ToolRegistry;
 use config::{ConfigBuilder, File};
 use serde::Deserialize;
+use std::collections::HashMap;
 use std::env;
 use std::path::Path;
 
@@ -41,6 +44,38 @@ pub struct ApiConfig {
     pub api_key: String,
     pub base_url: String,
     pub model_name: String,
+    /// Extra HTTP headers sent with every request
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    /// Sampling parameters applied to every model
+    #[serde(default)]
+    pub parameters: ModelParameters,
+    /// Per-model parameter overrides, keyed by model name
+    #[serde(default)]
+    pub models: HashMap<String, ModelParameters>,
+    /// Backoff for rate-limited and server-error responses
+    #[serde(default)]
+    pub retry: RetryPolicy,
+    /// Providers tried in order when the primary one keeps failing
+    #[serde(default)]
+    pub fallback: Vec<FallbackProviderConfig>,
+    /// Answer simple shell requests offline once every provider has failed
+    #[serde(default)]
+    pub offline_fallback: bool,
+    /// Opt-in cache for LLM responses
+    #[serde(default)]
+    pub cache: ResponseCacheConfig,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct FallbackProviderConfig {
+    pub provider: String,
+    #[serde(default)]
+    pub api_key: String,
+    pub base_url: String,
+    pub model_name: String,
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
 }
 
 #[derive(Debug, Clone, Deserialize, Default)]
@@ -63,10 +98,40 @@ pub struct DatabaseConfig {
     pub path: String,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 pub struct McpConfig {
     pub enabled: bool,
+    /// Single HTTP server used when no `[[mcp.servers]]` are configured
+    #[serde(default)]
     pub server_url: String,
+    #[serde(default)]
+    pub servers: Vec<McpServerConfig>,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum McpTransportKind {
+    #[default]
+    Http,
+    Stdio,
+}
+
+/// One entry of `[[mcp.servers]]`
+#[derive(Debug, Clone, Deserialize)]
+pub struct McpServerConfig {
+    /// Namespace of the server's tools, exposed as `mcp__<name>__<tool>`
+    pub name: String,
+    pub enabled: Option<bool>,
+    #[serde(default)]
+    pub transport: McpTransportKind,
+    /// Endpoint of an `http` server
+    pub url: Option<String>,
+    /// Program launched for a `stdio` server
+    pub command: Option<String>,
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default)]
+    pub env: HashMap<String, String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -94,9 +159,9 @@ pub struct KeyConfig {
 
 #[derive(Debug, Deserialize)]
 pub struct ToolsConfig {
-    #[allow(dead_code)]
+    /// Names or glob patterns of the only tools offered; all when unset
     pub enabled_tools: Option<Vec<String>>,
-    #[allow(dead_code)]
+    /// Names or glob patterns of tools never offered, applied after `enabled_tools`
     pub disabled_tools: Option<Vec<String>>,
 }
 
@@ -104,10 +169,15 @@ pub struct ToolsConfig {
 pub struct ExecPolicyConfig {
     pub approval_profile: Option<ApprovalProfile>,
     pub execution_strategy: Option<ExecutionStrategy>,
-    #[allow(dead_code)]
+    /// Command patterns that run without a prompt under `allow_listed`
     pub allowed_commands: Option<Vec<String>>,
-    #[allow(dead_code)]
+    /// Command patterns that are always refused, whatever the profile
     pub blocked_commands: Option<Vec<String>>,
+    /// Whether pipelines and `;`, `&&` or `||` chains may run; refused when unset
+    pub allow_command_chains: Option<bool>,
+    /// Whether file redirections, `$VAR`, `$(...)` or backtick expansions and
+    /// `( ... )` subshells may run; refused when unset
+    pub allow_redirects_and_substitutions: Option<bool>,
     pub sandbox_profile: Option<SandboxProfile>,
     pub sandbox: Option<SandboxConfig>,
     pub retry_max_attempts: Option<u32>,
@@ -122,6 +192,8 @@ impl Default for ExecPolicyConfig {
             execution_strategy: Some(ExecutionStrategy::Auto),
             allowed_commands: None,
             blocked_commands: None,
+            allow_command_chains: Some(false),
+            allow_redirects_and_substitutions: Some(false),
             sandbox_profile: Some(SandboxProfile::Disabled),
             sandbox: Some(SandboxConfig {
                 enabled: Some(false),
@@ -130,6 +202,12 @@ impl Default for ExecPolicyConfig {
                 network_access: Some(true),
                 readonly_home: Some(false),
                 max_execution_time_secs: None,
+                max_memory_mb: None,
+                max_cpu_time_secs: None,
+                max_processes: None,
+                max_open_files: None,
+                max_output_bytes: None,
+                backend: None,
             }),
             retry_max_attempts: Some(1),
             retry_network_commands: Some(vec!["curl".to_string(), "wget".to_string()]),
@@ -171,6 +249,13 @@ pub struct SandboxConfig {
     pub network_access: Option<bool>,
     pub readonly_home: Option<bool>,
     pub max_execution_time_secs: Option<u64>,
+    pub max_memory_mb: Option<u64>,
+    pub max_cpu_time_secs: Option<u64>,
+    pub max_processes: Option<u64>,
+    pub max_open_files: Option<u64>,
+    pub max_output_bytes: Option<u64>,
+    /// Isolation backend to require instead of the detected one
+    pub backend: Option<harper_sandbox::SandboxBackend>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -190,6 +275,16 @@ pub struct FirmwareDeviceConfig {
     pub platform: String,
     pub port: Option<String>,
     pub address: Option<String>,
+    /// TOML or JSON file describing a `virtual` device's peripherals
+    pub peripherals: Option<String>,
+    /// Firmware project directory that `firmware_flash` builds
+    pub project: Option<String>,
+    /// `cargo`, `platformio` or `idf`; detected from the project when unset
+    pub toolchain: Option<String>,
+    /// probe-rs chip name, for Cargo projects on boards espflash cannot flash
+    pub chip: Option<String>,
+    /// Serial monitor speed, 115200 when unset
+    pub baud_rate: Option<u32>,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -260,6 +355,15 @@ impl HarperConfig {
                 temp_builder = temp_builder
                     .set_override("api.model_name", ProviderModels::GEMINI.default_model)?;
             }
+        } else if let Ok(key) = env::var("ANTHROPIC_API_KEY") {
+            if !key.trim().is_empty() {
+                temp_builder = temp_builder.set_override("api.api_key", key)?;
+                temp_builder = temp_builder.set_override("api.provider", "Anthropic")?;
+                temp_builder = temp_builder
+                    .set_override("api.base_url", ProviderModels::ANTHROPIC.base_url)?;
+                temp_builder = temp_builder
+                    .set_override("api.model_name", ProviderModels::ANTHROPIC.default_model)?;
+            }
         } else if let Ok(host) = env::var("OLLAMA_HOST").or_else(|_| env::var("OLLAMA_BASE_URL")) {
             if !host.trim().is_empty() {
                 let mut normalized = host.trim().trim_end_matches('/').to_string();
@@ -363,57 +467,198 @@ pub fn should_enable_server(config_enabled: bool, args: &[String]) -> bool {
 impl ApiConfig {
     /// Validate API configuration
     fn validate(&self) -> HarperResult<()> {
-        // Validate provider
-        let requires_api_key = match self.provider.as_str() {
-            "OpenAI" | "Sambanova" | "Gemini" => true,
-            "Ollama" => false,
-            _ => {
-                return Err(HarperError::Config(format!(
-                "Invalid API provider: {}. Supported providers: OpenAI, Sambanova, Gemini, Ollama",
-                self.provider
-            )))
-            }
-        };
-
-        // Validate API key
-        if requires_api_key && self.api_key.trim().is_empty() {
-            return Err(HarperError::Config("API key cannot be empty".to_string()));
+        validate_endpoint(
+            &self.provider,
+            &self.api_key,
+            &self.base_url,
+            &self.model_name,
+        )?;
+        validate_headers(&self.headers)?;
+
+        validate_model_parameters("api.parameters", &self.parameters)?;
+        for (model, parameters) in &self.models {
+            validate_model_parameters(&format!("api.models.{}", model), parameters)?;
         }
 
-        // Validate base URL
-        if self.base_url.trim().is_empty() {
-            return Err(HarperError::Config("Base URL cannot be empty".to_string()));
+        for entry in &self.fallback {
+            validate_endpoint(
+                &entry.provider,
+                &entry.api_key,
+                &entry.base_url,
+                &entry.model_name,
+            )?;
+            validate_headers(&entry.headers)?;
         }
 
-        if !self.base_url.starts_with("http://") && !self.base_url.starts_with("https://") {
+        if self.cache.enabled && self.cache.ttl_secs == 0 {
             return Err(HarperError::Config(
-                "Base URL must start with http:// or https://".to_string(),
-            ));
-        }
-
-        // Validate model name
-        if self.model_name.trim().is_empty() {
-            return Err(HarperError::Config(
-                "Model name cannot be empty".to_string(),
+                "api.cache.ttl_secs must be greater than 0".to_string(),
             ));
         }
 
         Ok(())
     }
 
+    /// Sampling parameters for `model`, with per-model overrides taking
+    /// precedence over `[api.parameters]`
+    pub fn model_parameters(&self, model: &str) -> ModelParameters {
+        self.models
+            .get(model)
+            .copied()
+            .unwrap_or_default()
+            .or(self.parameters)
+    }
+
     /// Convert string provider to ApiProvider enum
     pub fn get_provider(&self) -> HarperResult<ApiProvider> {
-        match self.provider.as_str() {
-            "OpenAI" => Ok(ApiProvider::OpenAI),
-            "Sambanova" => Ok(ApiProvider::Sambanova),
-            "Gemini" => Ok(ApiProvider::Gemini),
-            "Ollama" => Ok(ApiProvider::Ollama),
-            _ => Err(HarperError::Config(format!(
-                "Unsupported provider: {}",
-                self.provider
-            ))),
+        parse_provider(&self.provider)
+    }
+
+    /// Build the fallback chain that follows the primary provider
+    pub fn fallback_chain(&self) -> HarperResult<FallbackChain> {
+        let providers = self
+            .fallback
+            .iter()
+            .map(|entry| {
+                Ok(crate::core::ApiConfig {
+                    provider: parse_provider(&entry.provider)?,
+                    api_key: entry.api_key.clone(),
+                    base_url: entry.base_url.clone(),
+                    model_name: entry.model_name.clone(),
+                    headers: entry.headers.clone(),
+                    parameters: self.model_parameters(&entry.model_name),
+                    fallback: FallbackChain::default(),
+                })
+            })
+            .collect::<HarperResult<Vec<_>>>()?;
+        Ok(FallbackChain {
+            providers,
+            retry: self.retry,
+            offline_shell: self.offline_fallback,
+        })
+    }
+}
+
+/// Validate the provider, key, URL and model of one chain entry
+fn validate_endpoint(
+    provider: &str,
+    api_key: &str,
+    base_url: &str,
+    model_name: &str,
+) -> HarperResult<()> {
+    // Validate provider
+    let requires_api_key = match provider {
+        "OpenAI" | "Sambanova" | "Gemini" | "Anthropic" => true,
+        "Ollama" | "OpenAiCompatible" => false,
+        _ => {
+            return Err(HarperError::Config(format!(
+            "Invalid API provider: {}. Supported providers: OpenAI, Sambanova, Gemini, Anthropic, Ollama, OpenAiCompatible",
+            provider
+        )))
         }
+    };
+
+    // Validate API key
+    if requires_api_key && api_key.trim().is_empty() {
+        return Err(HarperError::Config("API key cannot be empty".to_string()));
+    }
+
+    // Validate base URL
+    if base_url.trim().is_empty() {
+        return Err(HarperError::Config("Base URL cannot be empty".to_string()));
+    }
+
+    if !base_url.starts_with("http://") && !base_url.starts_with("https://") {
+        return Err(HarperError::Config(
+            "Base URL must start with http:// or https://".to_string(),
+        ));
+    }
+
+    // Validate model name
+    if model_name.trim().is_empty() {
+        return Err(HarperError::Config(
+            "Model name cannot be empty".to_string(),
+        ));
     }
+
+    Ok(())
+}
+
+fn validate_headers(headers: &HashMap<String, String>) -> HarperResult<()> {
+    for (name, value) in headers {
+        if reqwest::header::HeaderName::from_bytes(name.as_bytes()).is_err()
+            || reqwest::header::HeaderValue::from_str(value).is_err()
+        {
+            return Err(HarperError::Config(format!("Invalid API header: {}", name)));
+        }
+    }
+    Ok(())
+}
+
+fn parse_provider(provider: &str) -> HarperResult<ApiProvider> {
+    match provider {
+        "OpenAI" => Ok(ApiProvider::OpenAI),
+        "Sambanova" => Ok(ApiProvider::Sambanova),
+        "Gemini" => Ok(ApiProvider::Gemini),
+        "Ollama" => Ok(ApiProvider::Ollama),
+        "Anthropic" => Ok(ApiProvider::Anthropic),
+        "OpenAiCompatible" => Ok(ApiProvider::OpenAiCompatible),
+        _ => Err(HarperError::Config(format!(
+            "Unsupported provider: {}",
+            provider
+        ))),
+    }
+}
+
+const MIN_CONTEXT_WINDOW: u32 = 1024;
+const MAX_CONTEXT_WINDOW: u32 = 1_000_000;
+
+fn validate_model_parameters(section: &str, parameters: &ModelParameters) -> HarperResult<()> {
+    if parameters
+        .temperature
+        .is_some_and(|value| !(0.0..=2.0).contains(&value))
+    {
+        return Err(HarperError::Config(format!(
+            "{}.temperature must be between 0 and 2",
+            section
+        )));
+    }
+    if parameters
+        .top_p
+        .is_some_and(|value| !(0.0..=1.0).contains(&value))
+    {
+        return Err(HarperError::Config(format!(
+            "{}.top_p must be between 0 and 1",
+            section
+        )));
+    }
+    if parameters.max_tokens == Some(0) {
+        return Err(HarperError::Config(format!(
+            "{}.max_tokens must be greater than 0",
+            section
+        )));
+    }
+    if parameters
+        .context_window
+        .is_some_and(|value| !(MIN_CONTEXT_WINDOW..=MAX_CONTEXT_WINDOW).contains(&value))
+    {
+        return Err(HarperError::Config(format!(
+            "{}.context_window must be between {} and {}",
+            section, MIN_CONTEXT_WINDOW, MAX_CONTEXT_WINDOW
+        )));
+    }
+    for (field, price) in [
+        ("input_cost_per_mtok", parameters.input_cost_per_mtok),
+        ("output_cost_per_mtok", parameters.output_cost_per_mtok),
+    ] {
+        if price.is_some_and(|value| !value.is_finite() || value < 0.0) {
+            return Err(HarperError::Config(format!(
+                "{}.{} must not be negative",
+                section, field
+            )));
+        }
+    }
+    Ok(())
 }
 
 impl DatabaseConfig {
@@ -446,20 +691,106 @@ impl DatabaseConfig {
 impl McpConfig {
     /// Validate MCP configuration
     fn validate(&self) -> HarperResult<()> {
-        if self.enabled {
-            if self.server_url.trim().is_empty() {
-                return Err(HarperError::Config(
-                    "MCP server URL cannot be empty when MCP is enabled".to_string(),
-                ));
+        if !self.enabled {
+            return Ok(());
+        }
+        if !self.servers.is_empty() {
+            let mut names = std::collections::HashSet::new();
+            for server in &self.servers {
+                server.validate()?;
+                if !names.insert(server.name.as_str()) {
+                    return Err(HarperError::Config(format!(
+                        "Duplicate MCP server name: {}",
+                        server.name
+                    )));
+                }
             }
+            return Ok(());
+        }
 
-            if !self.server_url.starts_with("http://") && !self.server_url.starts_with("https://") {
-                return Err(HarperError::Config(
-                    "MCP server URL must start with http:// or https://".to_string(),
-                ));
+        if self.server_url.trim().is_empty() {
+            return Err(HarperError::Config(
+                "MCP server URL cannot be empty when MCP is enabled".to_string(),
+            ));
+        }
+
+        if !self.server_url.starts_with("http://") && !self.server_url.starts_with("https://") {
+            return Err(HarperError::Config(
+                "MCP server URL must start with http:// or https://".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Servers to connect to, with the legacy `server_url` as a server named `default`
+    pub fn active_servers(&self) -> Vec<McpServerConfig> {
+        if !self.enabled {
+            return Vec::new();
+        }
+        if self.servers.is_empty() {
+            if self.server_url.trim().is_empty() {
+                return Vec::new();
             }
+            return vec![McpServerConfig {
+                name: "default".to_string(),
+                enabled: Some(true),
+                transport: McpTransportKind::Http,
+                url: Some(self.server_url.clone()),
+                command: None,
+                args: Vec::new(),
+                env: HashMap::new(),
+            }];
         }
+        self.servers
+            .iter()
+            .filter(|server| server.is_enabled())
+            .cloned()
+            .collect()
+    }
+}
 
+impl McpServerConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.unwrap_or(true)
+    }
+
+    fn validate(&self) -> HarperResult<()> {
+        let name = self.name.trim();
+        if name.is_empty() {
+            return Err(HarperError::Config(
+                "MCP server name cannot be empty".to_string(),
+            ));
+        }
+        if name.contains("__")
+            || !name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            return Err(HarperError::Config(format!(
+                "MCP server name '{}' may only contain letters, digits, '-' and single '_'",
+                self.name
+            )));
+        }
+        match self.transport {
+            McpTransportKind::Http => {
+                let url = self.url.as_deref().unwrap_or("").trim();
+                if !url.starts_with("http://") && !url.starts_with("https://") {
+                    return Err(HarperError::Config(format!(
+                        "MCP server '{}' needs a url starting with http:// or https://",
+                        self.name
+                    )));
+                }
+            }
+            McpTransportKind::Stdio => {
+                if self.command.as_deref().unwrap_or("").trim().is_empty() {
+                    return Err(HarperError::Config(format!(
+                        "MCP server '{}' needs a command for the stdio transport",
+                        self.name
+                    )));
+                }
+            }
+        }
         Ok(())
     }
 }
@@ -489,9 +820,28 @@ impl UiConfig {
 impl ToolsConfig {
     /// Validate tools configuration
     fn validate(&self) -> HarperResult<()> {
-        // Basic validation - could add more specific tool name checks
+        let patterns = self
+            .enabled_tools
+            .iter()
+            .chain(self.disabled_tools.iter())
+            .flatten();
+        for pattern in patterns {
+            if pattern.trim().is_empty() {
+                return Err(HarperError::Config(
+                    "tools entries must not be empty".to_string(),
+                ));
+            }
+        }
         Ok(())
     }
+
+    /// Registry applying `enabled_tools` and `disabled_tools`
+    pub fn registry(&self) -> ToolRegistry {
+        ToolRegistry::new(
+            self.enabled_tools.clone(),
+            self.disabled_tools.clone().unwrap_or_default(),
+        )
+    }
 }
 
 impl ExecPolicyConfig {
@@ -500,6 +850,23 @@ impl ExecPolicyConfig {
         Ok(())
     }
 
+    /// The first blocked pattern matching a single command segment
+    pub fn blocked_command_pattern(&self, command: &str) -> Option<&str> {
+        self.blocked_commands
+            .iter()
+            .flatten()
+            .find(|pattern| harper_sandbox::command_blocked_by(pattern, command))
+            .map(String::as_str)
+    }
+
+    /// Whether a single command segment matches an allowed pattern
+    pub fn allows_command(&self, command: &str) -> bool {
+        self.allowed_commands
+            .iter()
+            .flatten()
+            .any(|pattern| harper_sandbox::command_matches(pattern, command))
+    }
+
     pub fn effective_execution_strategy(&self) -> ExecutionStrategy {
         self.execution_strategy.unwrap_or(ExecutionStrategy::Auto)
     }
@@ -522,6 +889,12 @@ impl ExecPolicyConfig {
                 network_access: Some(true),
                 readonly_home: Some(false),
                 max_execution_time_secs: None,
+                max_memory_mb: None,
+                max_cpu_time_secs: None,
+                max_processes: None,
+                max_open_files: None,
+                max_output_bytes: None,
+                backend: None,
             },
             SandboxProfile::Workspace => SandboxConfig {
                 enabled: Some(true),
@@ -530,6 +903,12 @@ impl ExecPolicyConfig {
                 network_access: Some(false),
                 readonly_home: Some(true),
                 max_execution_time_secs: Some(30),
+                max_memory_mb: None,
+                max_cpu_time_secs: None,
+                max_processes: None,
+                max_open_files: None,
+                max_output_bytes: None,
+                backend: None,
             },
             SandboxProfile::NetworkedWorkspace => SandboxConfig {
                 enabled: Some(true),
@@ -538,6 +917,12 @@ impl ExecPolicyConfig {
                 network_access: Some(true),
                 readonly_home: Some(true),
                 max_execution_time_secs: Some(30),
+                max_memory_mb: None,
+                max_cpu_time_secs: None,
+                max_processes: None,
+                max_open_files: None,
+                max_output_bytes: None,
+                backend: None,
             },
         };
 
@@ -560,11 +945,37 @@ impl ExecPolicyConfig {
             if raw.max_execution_time_secs.is_some() {
                 effective.max_execution_time_secs = raw.max_execution_time_secs;
             }
+            if raw.backend.is_some() {
+                effective.backend = raw.backend;
+            }
+            if raw.max_memory_mb.is_some() {
+                effective.max_memory_mb = raw.max_memory_mb;
+            }
+            if raw.max_cpu_time_secs.is_some() {
+                effective.max_cpu_time_secs = raw.max_cpu_time_secs;
+            }
+            if raw.max_processes.is_some() {
+                effective.max_processes = raw.max_processes;
+            }
+            if raw.max_open_files.is_some() {
+                effective.max_open_files = raw.max_open_files;
+            }
+            if raw.max_output_bytes.is_some() {
+                effective.max_output_bytes = raw.max_output_bytes;
+            }
         }
 
         effective
     }
 
+    pub fn allows_command_chains(&self) -> bool {
+        self.allow_command_chains.unwrap_or(false)
+    }
+
+    pub fn allows_redirects_and_substitutions(&self) -> bool {
+        self.allow_redirects_and_substitutions.unwrap_or(false)
+    }
+
     pub fn effective_retry_max_attempts(&self) -> u32 {
         self.retry_max_attempts.unwrap_or(1)
     }
@@ -595,9 +1006,10 @@ impl CustomCommandsConfig {
 #[cfg(test)]
 mod tests {
     use super::{
-        should_enable_server, ApprovalProfile, ExecPolicyConfig, HarperConfig, SandboxConfig,
-        SandboxProfile, ServerConfig,
+        should_enable_server, ApiConfig, ApprovalProfile, ExecPolicyConfig, HarperConfig,
+        McpConfig, McpTransportKind, SandboxConfig, SandboxProfile, ServerConfig,
     };
+    use crate::core::{ApiProvider, ModelParameters};
     use config::{ConfigBuilder, File};
     use std::env;
     use std::path::Path;
@@ -789,6 +1201,8 @@ mod tests {
             execution_strategy: None,
             allowed_commands: None,
             blocked_commands: None,
+            allow_command_chains: None,
+            allow_redirects_and_substitutions: None,
             sandbox_profile: Some(SandboxProfile::Workspace),
             sandbox: None,
             retry_max_attempts: None,
@@ -846,6 +1260,8 @@ mod tests {
             execution_strategy: None,
             allowed_commands: None,
             blocked_commands: None,
+            allow_command_chains: None,
+            allow_redirects_and_substitutions: None,
             sandbox_profile: Some(SandboxProfile::Workspace),
             sandbox: Some(SandboxConfig {
                 enabled: None,
@@ -854,6 +1270,12 @@ mod tests {
                 network_access: Some(true),
                 readonly_home: None,
                 max_execution_time_secs: Some(5),
+                max_memory_mb: Some(512),
+                max_cpu_time_secs: None,
+                max_processes: None,
+                max_open_files: None,
+                max_output_bytes: None,
+                backend: Some(harper_sandbox::SandboxBackend::Landlock),
             }),
             retry_max_attempts: None,
             retry_network_commands: None,
@@ -867,5 +1289,301 @@ mod tests {
         assert_eq!(sandbox.network_access, Some(true));
         assert_eq!(sandbox.readonly_home, Some(true));
         assert_eq!(sandbox.max_execution_time_secs, Some(5));
+        assert_eq!(
+            sandbox.backend,
+            Some(harper_sandbox::SandboxBackend::Landlock)
+        );
+        assert_eq!(sandbox.max_memory_mb, Some(512));
+        assert_eq!(sandbox.max_processes, None);
+    }
+
+    fn openai_compatible_config(toml: &str) -> ApiConfig {
+        config::Config::builder()
+            .add_source(File::from_str(toml, config::FileFormat::Toml))
+            .build()
+            .expect("api config builds")
+            .try_deserialize()
+            .expect("api config deserializes")
+    }
+
+    #[test]
+    fn openai_compatible_provider_accepts_headers_without_api_key() {
+        let config = openai_compatible_config(
+            r#"
+            provider = "OpenAiCompatible"
+            api_key = ""
+            base_url = "http://localhost:8000/v1/chat/completions"
+            model_name = "qwen2.5-coder"
+
+            [headers]
+            X-Team = "infra"
+            "#,
+        );
+
+        assert!(config.validate().is_ok());
+        assert!(matches!(
+            config.get_provider().expect("provider"),
+            ApiProvider::OpenAiCompatible
+        ));
+        assert_eq!(
+            config.headers.get("X-Team").map(String::as_str),
+            Some("infra")
+        );
+    }
+
+    #[test]
+    fn per_model_parameters_override_api_defaults() {
+        let config = openai_compatible_config(
+            r#"
+            provider = "OpenAiCompatible"
+            api_key = ""
+            base_url = "http://localhost:1234/v1/chat/completions"
+            model_name = "Qwen2.5-Coder"
+
+            [parameters]
+            temperature = 0.2
+            max_tokens = 1024
+
+            [models."Qwen2.5-Coder"]
+            temperature = 0.7
+            top_p = 0.9
+            "#,
+        );
+
+        assert!(config.validate().is_ok());
+        assert_eq!(
+            config.model_parameters("Qwen2.5-Coder"),
+            ModelParameters {
+                temperature: Some(0.7),
+                top_p: Some(0.9),
+                max_tokens: Some(1024),
+                context_window: None,
+                ..ModelParameters::default()
+            }
+        );
+        assert_eq!(
+            config.model_parameters("other-model"),
+            ModelParameters {
+                temperature: Some(0.2),
+                top_p: None,
+                max_tokens: Some(1024),
+                context_window: None,
+                ..ModelParameters::default()
+            }
+        );
+    }
+
+    #[test]
+    fn api_config_rejects_out_of_range_parameters() {
+        let config = openai_compatible_config(
+            r#"
+            provider = "OpenAiCompatible"
+            api_key = ""
+            base_url = "http://localhost:8080/v1/chat/completions"
+            model_name = "local"
+
+            [models.local]
+            top_p = 1.5
+            "#,
+        );
+
+        let err = config.validate().expect_err("top_p above 1 is rejected");
+        assert!(err.to_string().contains("api.models.local.top_p"));
+    }
+
+    #[test]
+    fn api_config_enforces_context_window_range() {
+        for (window, valid) in [
+            (512, false),
+            (1024, true),
+            (1_000_000, true),
+            (1_000_001, false),
+        ] {
+            let config = openai_compatible_config(&format!(
+                r#"
+                provider = "OpenAiCompatible"
+                api_key = ""
+                base_url = "http://localhost:8080/v1/chat/completions"
+                model_name = "local"
+
+                [models.local]
+                context_window = {}
+                "#,
+                window
+            ));
+
+            match config.validate() {
+                Ok(()) => assert!(valid, "context_window {} should be rejected", window),
+                Err(err) => {
+                    assert!(!valid, "context_window {} should be accepted", window);
+                    assert!(err.to_string().contains("api.models.local.context_window"));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn fallback_chain_builds_providers_in_order() {
+        let config = openai_compatible_config(
+            r#"
+            provider = "OpenAI"
+            api_key = "primary-key"
+            base_url = "https://api.openai.com/v1/chat/completions"
+            model_name = "gpt-5.5"
+            offline_fallback = true
+
+            [retry]
+            max_retries = 4
+            initial_backoff_ms = 250
+
+            [models.llama3]
+            temperature = 0.3
+
+            [[fallback]]
+            provider = "Anthropic"
+            api_key = "anthropic-key"
+            base_url = "https://api.anthropic.com/v1/messages"
+            model_name = "claude-sonnet-4-5"
+
+            [[fallback]]
+            provider = "Ollama"
+            base_url = "http://localhost:11434/api/chat"
+            model_name = "llama3"
+            "#,
+        );
+
+        assert!(config.validate().is_ok());
+        let chain = config.fallback_chain().expect("fallback chain");
+        assert_eq!(chain.providers.len(), 2);
+        assert!(matches!(
+            chain.providers[0].provider,
+            ApiProvider::Anthropic
+        ));
+        assert!(matches!(chain.providers[1].provider, ApiProvider::Ollama));
+        assert_eq!(chain.providers[1].parameters.temperature, Some(0.3));
+        assert_eq!(chain.retry.max_retries, 4);
+        assert_eq!(chain.retry.initial_backoff_ms, 250);
+        assert_eq!(chain.retry.max_backoff_ms, 8_000);
+        assert!(chain.offline_shell);
+    }
+
+    #[test]
+    fn fallback_entries_are_validated() {
+        let config = openai_compatible_config(
+            r#"
+            provider = "Ollama"
+            api_key = ""
+            base_url = "http://localhost:11434/api/chat"
+            model_name = "llama3"
+
+            [[fallback]]
+            provider = "Gemini"
+            base_url = "https://generativelanguage.googleapis.com/v1beta/models/gemini-2.5-flash:generateContent"
+            model_name = "gemini-2.5-flash"
+            "#,
+        );
+
+        let err = config.validate().expect_err("fallback without key");
+        assert!(err.to_string().contains("API key cannot be empty"));
+    }
+
+    fn mcp_config(toml: &str) -> McpConfig {
+        config::Config::builder()
+            .add_source(File::from_str(toml, config::FileFormat::Toml))
+            .build()
+            .expect("mcp config builds")
+            .try_deserialize()
+            .expect("mcp config deserializes")
+    }
+
+    #[test]
+    fn mcp_servers_list_skips_disabled_entries() {
+        let config = mcp_config(
+            r#"
+            enabled = true
+
+            [[servers]]
+            name = "docs"
+            url = "http://127.0.0.1:5001"
+
+            [[servers]]
+            name = "fs"
+            transport = "stdio"
+            command = "mcp-filesystem"
+            args = ["--root", "."]
+            env = { RUST_LOG = "warn" }
+
+            [[servers]]
+            name = "tracker"
+            enabled = false
+            url = "https://tracker.example.com/mcp"
+            "#,
+        );
+
+        assert!(config.validate().is_ok());
+        let servers = config.active_servers();
+        let names: Vec<&str> = servers.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, vec!["docs", "fs"]);
+        assert_eq!(servers[1].transport, McpTransportKind::Stdio);
+        assert_eq!(servers[1].args, vec!["--root", "."]);
+        assert_eq!(
+            servers[1].env.get("RUST_LOG").map(String::as_str),
+            Some("warn")
+        );
+    }
+
+    #[test]
+    fn mcp_legacy_server_url_becomes_default_server() {
+        let config = mcp_config(
+            r#"
+            enabled = true
+            server_url = "http://localhost:5001"
+            "#,
+        );
+
+        let servers = config.active_servers();
+        assert_eq!(servers.len(), 1);
+        assert_eq!(servers[0].name, "default");
+        assert_eq!(servers[0].url.as_deref(), Some("http://localhost:5001"));
+    }
+
+    #[test]
+    fn mcp_server_entries_are_validated() {
+        let cases = [
+            (
+                r#"
+                enabled = true
+                [[servers]]
+                name = "fs"
+                transport = "stdio"
+                "#,
+                "needs a command",
+            ),
+            (
+                r#"
+                enabled = true
+                [[servers]]
+                name = "my__fs"
+                url = "http://localhost:5001"
+                "#,
+                "may only contain",
+            ),
+            (
+                r#"
+                enabled = true
+                [[servers]]
+                name = "docs"
+                url = "http://localhost:5001"
+                [[servers]]
+                name = "docs"
+                url = "http://localhost:5002"
+                "#,
+                "Duplicate MCP server name",
+            ),
+        ];
+        for (toml, expected) in cases {
+            let err = mcp_config(toml).validate().expect_err(expected);
+            assert!(err.to_string().contains(expected), "{}", err);
+        }
     }
 }
@@ -36,24 +36,31 @@ use std::time::{Duration, Instant};
 
 use crate::agent::intent::route_intent;
 use crate::core::auth::{AuthSession, AuthenticatedUser, UserAuthProvider};
+use crate::core::cache::{ApiCacheKey, ResponseCache, ResponseCacheConfig};
 use crate::core::error::{HarperError, HarperResult};
-use crate::core::llm_client::call_llm;
+use crate::core::llm_client::{call_llm, call_llm_reply, LlmReply};
 use crate::core::plan_events;
 use crate::core::{ApiConfig, Message};
-use crate::memory::storage::{save_message, save_session, save_session_for_user, CommandLogRecord};
+use crate::memory::storage::{
+    insert_token_usage, save_message, save_session, save_session_for_user, CommandLogRecord,
+};
 use crate::runtime::config::ExecPolicyConfig;
 use crate::runtime::config::SupabaseAuthConfig;
+use crate::tools::registry::ToolRegistry;
 use rusqlite::params;
 
 #[derive(Clone)]
 pub struct ServerState {
     pub conn: Arc<Mutex<Connection>>,
     pub api_config: ApiConfig,
+    /// Tools declared to the model, from `[tools]`
+    pub tools: ToolRegistry,
     pub client: Client,
     pub exec_policy: ExecPolicyConfig,
     pub supabase_auth: Option<SupabaseAuthConfig>,
     pub oauth_states: Arc<Mutex<HashMap<String, PendingOauthState>>>,
     pub tui_auth_flows: Arc<Mutex<HashMap<String, TuiAuthFlowState>>>,
+    pub response_cache: Option<Arc<Mutex<ResponseCache>>>,
 }
 
 #[derive(Clone)]
@@ -89,6 +96,9 @@ pub struct SessionListItem {
 pub struct ChatRequest {
     pub message: String,
     pub session_id: Option<String>,
+    /// Skip the response cache for this request
+    #[serde(default)]
+    pub bypass_cache: bool,
 }
 
 #[derive(Serialize)]
@@ -900,6 +910,137 @@ pub async fn chat_endpoint(
         .unwrap_or_else(|| "api".to_string());
     let auth_user = optional_authenticated_user_from_headers(&state, &headers).await?;
 
+    let history = chat_endpoint_history(&message);
+    let cache_key = ApiCacheKey::new(&state.api_config, &state.tools.effective_tools(), &history);
+
+    let cached = if payload.bypass_cache {
+        None
+    } else {
+        cached_chat_response(&state, &cache_key)
+    };
+    let (response, reply) = match cached {
+        Some(response) => (response, None),
+        None => {
+            let reply = call_llm_reply(&state.client, &state.api_config, &state.tools, &history)
+                .await
+                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+            if !payload.bypass_cache {
+                store_chat_response(&state, cache_key, &reply.content);
+            }
+            (reply.content.clone(), Some(reply))
+        }
+    };
+
+    let chat_response =
+        complete_chat_turn(&state, session_id, auth_user.as_ref(), &message, &response)?;
+    if let Some(reply) = reply {
+        record_chat_usage(&state, &chat_response.session_id, &reply);
+    }
+    Ok(Json(chat_response))
+}
+
+/// Streams the assistant reply as `delta` events, then a final `done` event
+/// carrying the same payload `/api/chat` returns
+pub async fn chat_stream_endpoint(
+    State(state): State<Arc<ServerState>>,
+    headers: axum::http::HeaderMap,
+    Json(payload): Json<ChatRequest>,
+) -> Result<Sse<impl futures_util::Stream<Item = Result<Event, Infallible>>>, (StatusCode, String)>
+{
+    let message = payload.message.clone();
+    let session_id = payload
+        .session_id
+        .clone()
+        .unwrap_or_else(|| "api".to_string());
+    let auth_user = optional_authenticated_user_from_headers(&state, &headers).await?;
+    let history = chat_endpoint_history(&message);
+
+    let (event_tx, event_rx) = tokio::sync::mpsc::unbounded_channel::<Event>();
+    tokio::spawn(async move {
+        let delta_tx = event_tx.clone();
+        let result = crate::core::llm_client::call_llm_stream_reply(
+            &state.client,
+            &state.api_config,
+            &state.tools,
+            &history,
+            |delta| {
+                let data = serde_json::json!({ "delta": delta }).to_string();
+                let _ = delta_tx.send(Event::default().event("delta").data(data));
+            },
+        )
+        .await;
+
+        let event = match result.map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string())) {
+            Ok(reply) => {
+                match complete_chat_turn(
+                    &state,
+                    session_id,
+                    auth_user.as_ref(),
+                    &message,
+                    &reply.content,
+                ) {
+                    Ok(chat_response) => {
+                        record_chat_usage(&state, &chat_response.session_id, &reply);
+                        match serde_json::to_string(&chat_response) {
+                            Ok(json) => Event::default().event("done").data(json),
+                            Err(e) => Event::default().event("error").data(e.to_string()),
+                        }
+                    }
+                    Err((_, error)) => Event::default().event("error").data(error),
+                }
+            }
+            Err((_, error)) => Event::default().event("error").data(error),
+        };
+        let _ = event_tx.send(event);
+    });
+
+    let stream = stream::unfold(event_rx, |mut receiver| async move {
+        receiver
+            .recv()
+            .await
+            .map(|event| (Ok::<Event, Infallible>(event), receiver))
+    });
+
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
+fn record_chat_usage(state: &ServerState, session_id: &str, reply: &LlmReply) {
+    let conn = state
+        .conn
+        .lock()
+        .expect("Failed to lock database connection");
+    let _ = insert_token_usage(&conn, session_id, reply);
+}
+
+fn cached_chat_response(state: &ServerState, key: &ApiCacheKey) -> Option<String> {
+    let cache = state.response_cache.as_ref()?;
+    let conn = state
+        .conn
+        .lock()
+        .expect("Failed to lock database connection");
+    cache
+        .lock()
+        .expect("Failed to lock response cache")
+        .get(&conn, key)
+        .ok()
+        .flatten()
+}
+
+fn store_chat_response(state: &ServerState, key: ApiCacheKey, response: &str) {
+    let Some(cache) = state.response_cache.as_ref() else {
+        return;
+    };
+    let conn = state
+        .conn
+        .lock()
+        .expect("Failed to lock database connection");
+    let _ = cache
+        .lock()
+        .expect("Failed to lock response cache")
+        .insert(&conn, key, response);
+}
+
+fn chat_endpoint_history(message: &str) -> Vec<Message> {
     let system_prompt = r#"You are Harper, a CLI assistant. Use JSON for commands:
 {"tool": "run_command", "args": {"command": "ls -la"}}
 {"tool": "read_file", "args": {"filePath": "/path/to/file"}}
@@ -907,23 +1048,21 @@ pub async fn chat_endpoint(
 {"tool": "grep", "args": {"pattern": "search", "path": "."}}
 {"tool": "update_plan", "args": {"explanation": "optional note", "items": [{"step": "Inspect files", "status": "in_progress"}]}}"#;
 
-    let history = vec![
-        Message {
-            role: "system".to_string(),
-            content: system_prompt.to_string(),
-        },
-        Message {
-            role: "user".to_string(),
-            content: message.clone(),
-        },
-    ];
-
-    let response = call_llm(&state.client, &state.api_config, &history)
-        .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    vec![
+        Message::text("system", system_prompt),
+        Message::text("user", message),
+    ]
+}
 
+fn complete_chat_turn(
+    state: &ServerState,
+    session_id: String,
+    auth_user: Option<&AuthenticatedUser>,
+    message: &str,
+    response: &str,
+) -> Result<ChatResponse, (StatusCode, String)> {
     if let Some(crate::agent::intent::DeterministicIntent::ListChangedFiles(intent_args)) =
-        route_intent(&message)
+        route_intent(message)
     {
         let mut git_cmd = std::process::Command::new("git");
         git_cmd.arg("diff");
@@ -955,19 +1094,19 @@ pub async fn chat_endpoint(
                 format!("Lock error: {:?}", e),
             )
         })?;
-        claim_or_verify_session_access(&conn, &session_id, auth_user.as_ref())
+        claim_or_verify_session_access(&conn, &session_id, auth_user)
             .map_err(|status| (status, "Session access denied".to_string()))?;
-        let _ = save_message(&conn, &session_id, "user", &message);
+        let _ = save_message(&conn, &session_id, "user", message);
         let _ = save_message(&conn, &session_id, "assistant", &result);
         drop(conn);
 
-        return Ok(Json(ChatResponse {
+        return Ok(ChatResponse {
             message: result,
             session_id,
             status: "completed".to_string(),
             pending_id: None,
             pending_tools: None,
-        }));
+        });
     }
 
     let conn = state.conn.lock().map_err(|e| {
@@ -976,12 +1115,12 @@ pub async fn chat_endpoint(
             format!("Lock error: {:?}", e),
         )
     })?;
-    claim_or_verify_session_access(&conn, &session_id, auth_user.as_ref())
+    claim_or_verify_session_access(&conn, &session_id, auth_user)
         .map_err(|status| (status, "Session access denied".to_string()))?;
-    let _ = save_message(&conn, &session_id, "user", &message);
+    let _ = save_message(&conn, &session_id, "user", message);
     drop(conn);
 
-    let final_response = if let Ok(json) = serde_json::from_str::<serde_json::Value>(&response) {
+    let final_response = if let Ok(json) = serde_json::from_str::<serde_json::Value>(response) {
         if let Some(tool) = json.get("tool").and_then(|t| t.as_str()) {
             let args = json.get("args").and_then(|a| a.as_object());
 
@@ -1034,13 +1173,13 @@ pub async fn chat_endpoint(
                     let _ = crate::memory::storage::insert_command_log(&conn, &record);
                     drop(conn);
 
-                    return Ok(Json(ChatResponse {
+                    return Ok(ChatResponse {
                         message: output_str,
                         session_id,
                         status: "completed".to_string(),
                         pending_id: None,
                         pending_tools: None,
-                    }));
+                    });
                 }
             }
 
@@ -1090,13 +1229,13 @@ pub async fn chat_endpoint(
                     let _ = crate::memory::storage::insert_command_log(&conn, &record);
                     drop(conn);
 
-                    return Ok(Json(ChatResponse {
+                    return Ok(ChatResponse {
                         message: truncated,
                         session_id,
                         status: "completed".to_string(),
                         pending_id: None,
                         pending_tools: None,
-                    }));
+                    });
                 }
             }
 
@@ -1144,13 +1283,13 @@ pub async fn chat_endpoint(
                         let _ = crate::memory::storage::insert_command_log(&conn, &record);
                         drop(conn);
 
-                        return Ok(Json(ChatResponse {
+                        return Ok(ChatResponse {
                             message: output_str,
                             session_id,
                             status: "completed".to_string(),
                             pending_id: None,
                             pending_tools: None,
-                        }));
+                        });
                     }
                 }
             }
@@ -1208,13 +1347,13 @@ pub async fn chat_endpoint(
                         let _ = crate::memory::storage::insert_command_log(&conn, &record);
                         drop(conn);
 
-                        return Ok(Json(ChatResponse {
+                        return Ok(ChatResponse {
                             message: output_str,
                             session_id,
                             status: "completed".to_string(),
                             pending_id: None,
                             pending_tools: None,
-                        }));
+                        });
                     }
                 }
             }
@@ -1235,13 +1374,13 @@ pub async fn chat_endpoint(
                 let _ = save_message(&conn, &session_id, "assistant", &output_str);
                 drop(conn);
 
-                return Ok(Json(ChatResponse {
+                return Ok(ChatResponse {
                     message: output_str,
                     session_id,
                     status: "completed".to_string(),
                     pending_id: None,
                     pending_tools: None,
-                }));
+                });
             }
         }
         response.trim().to_string()
@@ -1258,13 +1397,13 @@ pub async fn chat_endpoint(
     let _ = save_message(&conn, &session_id, "assistant", &final_response);
     drop(conn);
 
-    Ok(Json(ChatResponse {
+    Ok(ChatResponse {
         message: final_response,
         session_id,
         status: "completed".to_string(),
         pending_id: None,
         pending_tools: None,
-    }))
+    })
 }
 
 pub async fn review_code(
@@ -1273,7 +1412,7 @@ pub async fn review_code(
 ) -> Result<Json<ReviewResponse>, (StatusCode, String)> {
     validate_review_request(&payload).map_err(into_http_error)?;
 
-    let review = generate_review(&state.client, &state.api_config, &payload)
+    let review = generate_review(&state.client, &state.api_config, &state.tools, &payload)
         .await
         .map_err(into_http_error)?;
 
@@ -1307,6 +1446,7 @@ fn validate_review_request(request: &ReviewRequest) -> HarperResult<()> {
 async fn generate_review(
     client: &Client,
     api_config: &ApiConfig,
+    tools: &ToolRegistry,
     request: &ReviewRequest,
 ) -> HarperResult<ReviewResponse> {
     let max_findings = request.max_findings.unwrap_or(8).clamp(1, 20);
@@ -1370,17 +1510,11 @@ Code with line numbers:
     );
 
     let messages = vec![
-        Message {
-            role: "system".to_string(),
-            content: system_prompt,
-        },
-        Message {
-            role: "user".to_string(),
-            content: user_prompt,
-        },
+        Message::text("system", system_prompt),
+        Message::text("user", user_prompt),
     ];
 
-    let raw = call_llm(client, api_config, &messages).await?;
+    let raw = call_llm(client, api_config, tools, &messages).await?;
     let cleaned = extract_json_payload(&raw);
     let review_payload: ModelReviewResponse = serde_json::from_str(&cleaned)
         .map_err(|e| HarperError::Api(format!("Failed to parse review response: {}", e)))?;
@@ -1451,6 +1585,7 @@ fn into_http_error(error: HarperError) -> (StatusCode, String) {
     match error {
         HarperError::Validation(message) => (StatusCode::BAD_REQUEST, message),
         HarperError::Api(message) => (StatusCode::BAD_GATEWAY, message),
+        HarperError::Unavailable(message) => (StatusCode::SERVICE_UNAVAILABLE, message),
         HarperError::Config(message) => (StatusCode::INTERNAL_SERVER_ERROR, message),
         HarperError::Database(message) => (StatusCode::INTERNAL_SERVER_ERROR, message),
         HarperError::Command(message) => (StatusCode::INTERNAL_SERVER_ERROR, message),
@@ -1468,17 +1603,23 @@ fn into_http_error(error: HarperError) -> (StatusCode, String) {
 pub fn create_router(
     conn: Arc<Mutex<Connection>>,
     api_config: ApiConfig,
+    tools: ToolRegistry,
     exec_policy: ExecPolicyConfig,
     supabase_auth: Option<SupabaseAuthConfig>,
+    response_cache: ResponseCacheConfig,
 ) -> Router {
     let state = Arc::new(ServerState {
         conn,
         api_config,
+        tools,
         client: Client::new(),
         exec_policy,
         supabase_auth,
         oauth_states: Arc::new(Mutex::new(HashMap::new())),
         tui_auth_flows: Arc::new(Mutex::new(HashMap::new())),
+        response_cache: response_cache
+            .enabled
+            .then(|| Arc::new(Mutex::new(ResponseCache::new(&response_cache)))),
     });
 
     Router::new()
@@ -1500,6 +1641,7 @@ pub fn create_router(
         )
         .route("/api/sessions/{id}", delete(delete_session))
         .route("/api/chat", post(chat_endpoint))
+        .route("/api/chat/stream", post(chat_stream_endpoint))
         .route("/api/approvals/{session_id}", get(list_pending_approvals))
         .route("/api/approvals/{session_id}", post(approve_command))
         .route("/api/chat/approve/{pending_id}", post(approve_pending_tool))
@@ -1837,10 +1979,19 @@ pub async fn run_server(
     addr: &str,
     conn: Arc<Mutex<Connection>>,
     api_config: ApiConfig,
+    tools: ToolRegistry,
     exec_policy: ExecPolicyConfig,
     supabase_auth: Option<SupabaseAuthConfig>,
+    response_cache: ResponseCacheConfig,
 ) -> HarperResult<()> {
-    let router = create_router(conn, api_config, exec_policy, supabase_auth);
+    let router = create_router(
+        conn,
+        api_config,
+        tools,
+        exec_policy,
+        supabase_auth,
+        response_cache,
+    );
 
     let listener = tokio::net::TcpListener::bind(addr).await?;
     log::info!("Harper API server running on {}", addr);
@@ -2065,13 +2216,15 @@ pub async fn approve_pending_tool(
 #[cfg(test)]
 mod tests {
     use super::{
-        auth_me, auth_tui_poll, auth_tui_refresh, build_authorize_url, delete_session,
-        extract_json_payload, get_session, get_session_plan, get_session_plan_stream,
-        list_sessions, normalize_finding_range, render_auth_status_page, render_auth_success,
-        review_code, CodeReviewFinding, CodeSuggestion, ReviewRange, ReviewRequest, ServerState,
-        SupabaseAuthConfig, TuiAuthFlowState, TuiRefreshRequest,
+        auth_me, auth_tui_poll, auth_tui_refresh, build_authorize_url, chat_endpoint,
+        chat_stream_endpoint, delete_session, extract_json_payload, get_session, get_session_plan,
+        get_session_plan_stream, list_sessions, normalize_finding_range, render_auth_status_page,
+        render_auth_success, review_code, ChatRequest, CodeReviewFinding, CodeSuggestion,
+        ReviewRange, ReviewRequest, ServerState, SupabaseAuthConfig, TuiAuthFlowState,
+        TuiRefreshRequest,
     };
     use crate::core::auth::{AuthSession, AuthenticatedUser, UserAuthProvider};
+    use crate::core::cache::{ResponseCache, ResponseCacheConfig};
     use crate::core::{ApiConfig, ApiProvider};
     use crate::memory::storage::{save_message, save_session, save_session_for_user};
     use crate::runtime::config::ExecPolicyConfig;
@@ -2181,12 +2334,17 @@ mod tests {
                 api_key: "test-key".to_string(),
                 base_url: "https://api.openai.com/v1/chat/completions".to_string(),
                 model_name: "gpt-5.5".to_string(),
+                headers: Default::default(),
+                parameters: Default::default(),
+                fallback: Default::default(),
             },
+            tools: Default::default(),
             client: Client::new(),
             exec_policy: crate::runtime::config::ExecPolicyConfig::default(),
             supabase_auth: None,
             oauth_states: Arc::new(Mutex::new(HashMap::new())),
             tui_auth_flows: Arc::new(Mutex::new(HashMap::new())),
+            response_cache: None,
         });
 
         let request = ReviewRequest {
@@ -2216,12 +2374,17 @@ mod tests {
                 api_key: "test-key".to_string(),
                 base_url: "https://api.openai.com/v1/chat/completions".to_string(),
                 model_name: "gpt-5.5".to_string(),
+                headers: Default::default(),
+                parameters: Default::default(),
+                fallback: Default::default(),
             },
+            tools: Default::default(),
             client: Client::new(),
             exec_policy: ExecPolicyConfig::default(),
             supabase_auth,
             oauth_states: Arc::new(Mutex::new(HashMap::new())),
             tui_auth_flows: Arc::new(Mutex::new(HashMap::new())),
+            response_cache: None,
         })
     }
 
@@ -2462,6 +2625,159 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn chat_endpoint_serves_repeats_from_cache_unless_bypassed() {
+        let hits = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let hits_clone = hits.clone();
+        let app = axum::Router::new().fallback(move || {
+            let hits = hits_clone.clone();
+            async move {
+                hits.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                Json(serde_json::json!({
+                    "choices": [{"message": {"content": "cached reply"}}]
+                }))
+            }
+        });
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("bind mock provider");
+        let addr = listener.local_addr().expect("mock provider addr");
+        tokio::spawn(async move {
+            let _ = axum::serve(listener, app).await;
+        });
+
+        let mut state = (*test_server_state(None)).clone();
+        state.api_config.base_url = format!("http://{}/v1/chat/completions", addr);
+        state.response_cache = Some(Arc::new(Mutex::new(ResponseCache::new(
+            &ResponseCacheConfig {
+                enabled: true,
+                ..ResponseCacheConfig::default()
+            },
+        ))));
+        let state = Arc::new(state);
+        let request = |bypass_cache| {
+            Json(ChatRequest {
+                message: "hello".to_string(),
+                session_id: Some("cache-session".to_string()),
+                bypass_cache,
+            })
+        };
+
+        for bypass_cache in [false, false, true] {
+            let response = chat_endpoint(
+                State(state.clone()),
+                HeaderMap::new(),
+                request(bypass_cache),
+            )
+            .await
+            .expect("chat response");
+            assert_eq!(response.0.message, "cached reply");
+        }
+
+        assert_eq!(hits.load(std::sync::atomic::Ordering::SeqCst), 2);
+        let conn = state.conn.lock().expect("db lock");
+        let stats = crate::memory::session_service::SessionService::new(&conn)
+            .get_global_stats()
+            .expect("stats");
+        assert_eq!((stats.cache_hits, stats.cache_misses), (1, 1));
+    }
+
+    #[tokio::test]
+    async fn get_session_returns_token_usage_recorded_by_chat() {
+        let app = axum::Router::new().fallback(|| async {
+            Json(serde_json::json!({
+                "choices": [{"message": {"content": "counted reply"}}],
+                "usage": {"prompt_tokens": 30, "completion_tokens": 12}
+            }))
+        });
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("bind mock provider");
+        let addr = listener.local_addr().expect("mock provider addr");
+        tokio::spawn(async move {
+            let _ = axum::serve(listener, app).await;
+        });
+
+        let mut state = (*test_server_state(None)).clone();
+        state.api_config.base_url = format!("http://{}/v1/chat/completions", addr);
+        state.api_config.parameters.input_cost_per_mtok = Some(1_000.0);
+        let state = Arc::new(state);
+
+        let response = chat_endpoint(
+            State(state.clone()),
+            HeaderMap::new(),
+            Json(ChatRequest {
+                message: "hello".to_string(),
+                session_id: Some("usage-session".to_string()),
+                bypass_cache: false,
+            }),
+        )
+        .await
+        .expect("chat response");
+        assert_eq!(response.0.message, "counted reply");
+
+        let Json(view) = get_session(
+            State(state),
+            HeaderMap::new(),
+            Path("usage-session".to_string()),
+        )
+        .await
+        .expect("session view");
+        assert_eq!(view["usage"]["calls"], 1);
+        assert_eq!(view["usage"]["prompt_tokens"], 30);
+        assert_eq!(view["usage"]["completion_tokens"], 12);
+        assert_eq!(view["usage"]["estimated_cost"], 0.03);
+    }
+
+    #[tokio::test]
+    async fn chat_stream_endpoint_emits_deltas_then_done() {
+        let app = axum::Router::new().fallback(|| async {
+            axum::body::Body::from_stream(futures_util::stream::iter(
+                [
+                    "data: {\"choices\":[{\"delta\":{\"content\":\"Hi\"}}]}\n\n",
+                    "data: {\"choices\":[{\"delta\":{\"content\":\" there\"}}]}\n\n",
+                    "data: [DONE]\n\n",
+                ]
+                .map(|chunk| Ok::<_, std::convert::Infallible>(chunk.to_string())),
+            ))
+        });
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("bind mock provider");
+        let addr = listener.local_addr().expect("mock provider addr");
+        tokio::spawn(async move {
+            let _ = axum::serve(listener, app).await;
+        });
+
+        let mut state = (*test_server_state(None)).clone();
+        state.api_config.base_url = format!("http://{}/v1/chat/completions", addr);
+
+        let response = chat_stream_endpoint(
+            State(Arc::new(state)),
+            HeaderMap::new(),
+            Json(ChatRequest {
+                message: "hello".to_string(),
+                session_id: Some("stream-session".to_string()),
+                bypass_cache: false,
+            }),
+        )
+        .await
+        .expect("stream should start")
+        .into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = to_bytes(response.into_body(), usize::MAX)
+            .await
+            .expect("stream body");
+        let body = String::from_utf8_lossy(&body);
+        let first_delta = body.find("\"delta\":\"Hi\"").expect("first delta");
+        let second_delta = body.find("\"delta\":\" there\"").expect("second delta");
+        let done = body.find("event: done").expect("done event");
+        assert!(first_delta < second_delta && second_delta < done);
+        assert!(body.contains("\"message\":\"Hi there\""));
+        assert!(body.contains("\"session_id\":\"stream-session\""));
+    }
+
     #[tokio::test]
     async fn authenticated_list_sessions_returns_only_owned_sessions() {
         let secret = "test-secret";
@@ -14,9 +14,12 @@
 
 //! Azure Data Explorer query tool.
 
+use crate::core::constants::tools;
 use crate::core::error::{HarperError, HarperResult};
 use crate::core::io_traits::UserApproval;
 use crate::tools::parsing;
+use crate::tools::registry::{Tool, ToolContext, ToolRisk};
+use async_trait::async_trait;
 use chrono::Utc;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
@@ -587,6 +590,68 @@ fn format_value(value: &Value) -> String {
     }
 }
 
+/// `adx_query`: run a read-only KQL query against Azure Data Explorer
+pub struct AdxQueryTool;
+
+#[async_trait(?Send)]
+impl Tool for AdxQueryTool {
+    fn name(&self) -> &str {
+        "adx_query"
+    }
+
+    fn description(&self) -> &str {
+        "Run a read-only KQL query against Azure Data Explorer. Credentials come from HARPER_ADX_* environment variables unless explicitly provided."
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "cluster_url": {
+                    "type": "string",
+                    "description": "Azure Data Explorer cluster URL, for example https://help.kusto.windows.net"
+                },
+                "database": {
+                    "type": "string",
+                    "description": "ADX database name"
+                },
+                "query": {
+                    "type": "string",
+                    "description": "Read-only KQL query. Management commands that start with a dot are not allowed."
+                }
+            },
+            "required": ["query"]
+        })
+    }
+
+    fn risk(&self) -> ToolRisk {
+        ToolRisk::Execute
+    }
+
+    fn aliases(&self) -> &[&str] {
+        &["azure_data_explorer"]
+    }
+
+    fn bracket_prefix(&self) -> Option<&str> {
+        Some(tools::ADX_QUERY)
+    }
+
+    async fn execute(&self, args: &Value, ctx: &ToolContext<'_>) -> HarperResult<Option<String>> {
+        query_from_json(ctx.client, args, ctx.approver.clone())
+            .await
+            .map(Some)
+    }
+
+    async fn execute_bracket(
+        &self,
+        call: &str,
+        ctx: &ToolContext<'_>,
+    ) -> HarperResult<Option<String>> {
+        let args = args_from_bracket_call(call)?;
+        self.execute(&args, ctx).await
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::{format_query_response, normalize_cluster_url, query_from_json, AdxQueryRequest};
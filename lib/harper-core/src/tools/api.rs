@@ -16,30 +16,57 @@
 //!
 //! This module provides functionality for testing APIs with HTTP requests.
 
-use crate::core::error::HarperError;
+use crate::core::constants::tools;
+use crate::core::error::{HarperError, HarperResult};
+use crate::core::io_traits::UserApproval;
 use crate::tools::parsing;
+use crate::tools::registry::{str_arg, Tool, ToolContext, ToolRisk};
+use async_trait::async_trait;
 use colored::*;
 use reqwest::Client;
+use serde_json::{json, Value};
+use std::sync::Arc;
 
 /// Test an API endpoint
-pub async fn test_api(response: &str) -> crate::core::error::HarperResult<String> {
+pub async fn test_api(
+    response: &str,
+    approver: Option<Arc<dyn UserApproval>>,
+) -> HarperResult<String> {
     let args = parsing::extract_tool_args(response, "[API_TEST", 4)?;
-    let method = &args[0];
-    let url = &args[1];
-    let headers = &args[2]; // JSON string like {"Content-Type": "application/json"}
-    let body = &args[3]; // Optional body
+    test_api_direct(&args[0], &args[1], &args[2], &args[3], approver).await
+}
 
-    println!(
-        "{} Test API {} {} with headers '{}' and body '{}' ? (y/n): ",
-        "System:".bold().magenta(),
-        method.magenta(),
-        url.magenta(),
-        headers.magenta(),
-        body.magenta()
-    );
-    let mut approval = String::new();
-    std::io::stdin().read_line(&mut approval)?;
-    if !approval.trim().eq_ignore_ascii_case("y") {
+/// Test an API endpoint, with the arguments already split
+///
+/// `headers` is a JSON object such as `{"Content-Type": "application/json"}`;
+/// it and `body` may be empty.
+pub async fn test_api_direct(
+    method: &str,
+    url: &str,
+    headers: &str,
+    body: &str,
+    approver: Option<Arc<dyn UserApproval>>,
+) -> HarperResult<String> {
+    let is_approved = if let Some(appr) = approver {
+        appr.approve(
+            &format!("Test API {} {}?", method, url),
+            &format!("headers: {}\nbody: {}", headers, body),
+        )
+        .await?
+    } else {
+        println!(
+            "{} Test API {} {} with headers '{}' and body '{}' ? (y/n): ",
+            "System:".bold().magenta(),
+            method.magenta(),
+            url.magenta(),
+            headers.magenta(),
+            body.magenta()
+        );
+        let mut approval = String::new();
+        std::io::stdin().read_line(&mut approval)?;
+        approval.trim().eq_ignore_ascii_case("y")
+    };
+    if !is_approved {
         return Ok("API test cancelled by user".to_string());
     }
 
@@ -96,3 +123,75 @@ pub async fn test_api(response: &str) -> crate::core::error::HarperResult<String
 
     Ok(result)
 }
+
+/// `api_test`: send an HTTP request and report the response
+pub struct ApiTestTool;
+
+#[async_trait(?Send)]
+impl Tool for ApiTestTool {
+    fn name(&self) -> &str {
+        "api_test"
+    }
+
+    fn description(&self) -> &str {
+        "Send an HTTP request to an API endpoint and show the status, headers and body"
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "method": {
+                    "type": "string",
+                    "enum": ["GET", "POST", "PUT", "DELETE", "PATCH"],
+                    "description": "HTTP method"
+                },
+                "url": {
+                    "type": "string",
+                    "description": "URL to request"
+                },
+                "headers": {
+                    "type": "object",
+                    "additionalProperties": {"type": "string"},
+                    "description": "Optional request headers"
+                },
+                "body": {
+                    "type": "string",
+                    "description": "Optional request body"
+                }
+            },
+            "required": ["method", "url"]
+        })
+    }
+
+    fn risk(&self) -> ToolRisk {
+        ToolRisk::Execute
+    }
+
+    fn bracket_prefix(&self) -> Option<&str> {
+        Some(tools::API_TEST)
+    }
+
+    async fn execute(&self, args: &Value, ctx: &ToolContext<'_>) -> HarperResult<Option<String>> {
+        let (Some(method), Some(url)) = (str_arg(args, "method"), str_arg(args, "url")) else {
+            return Ok(None);
+        };
+        let headers = match args.get("headers") {
+            Some(Value::String(headers)) => headers.clone(),
+            Some(headers @ Value::Object(_)) => headers.to_string(),
+            _ => String::new(),
+        };
+        let body = str_arg(args, "body").unwrap_or_default();
+        test_api_direct(method, url, &headers, body, ctx.approver.clone())
+            .await
+            .map(Some)
+    }
+
+    async fn execute_bracket(
+        &self,
+        call: &str,
+        ctx: &ToolContext<'_>,
+    ) -> HarperResult<Option<String>> {
+        test_api(call, ctx.approver.clone()).await.map(Some)
+    }
+}
@@ -16,9 +16,13 @@
 //!
 //! This module provides functionality for analyzing code metrics like complexity.
 
-use crate::core::error::HarperError;
+use crate::core::constants::tools;
+use crate::core::error::{HarperError, HarperResult};
 use crate::tools::parsing;
+use crate::tools::registry::{str_arg, Tool, ToolContext, ToolRisk};
+use async_trait::async_trait;
 use colored::*;
+use serde_json::{json, Value};
 // No regex needed
 
 /// Analyze code metrics
@@ -61,3 +65,53 @@ pub fn analyze_code(response: &str) -> crate::core::error::HarperResult<String>
 
     Ok(result)
 }
+
+/// `code_analyze`: rough size and structure metrics for a source file
+pub struct CodeAnalyzeTool;
+
+#[async_trait(?Send)]
+impl Tool for CodeAnalyzeTool {
+    fn name(&self) -> &str {
+        "code_analyze"
+    }
+
+    fn description(&self) -> &str {
+        "Report line counts and rough structure metrics for a source file"
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "path": {
+                    "type": "string",
+                    "description": "The path to the file to analyze"
+                }
+            },
+            "required": ["path"]
+        })
+    }
+
+    fn risk(&self) -> ToolRisk {
+        ToolRisk::ReadOnly
+    }
+
+    fn bracket_prefix(&self) -> Option<&str> {
+        Some(tools::CODE_ANALYZE)
+    }
+
+    async fn execute(&self, args: &Value, _ctx: &ToolContext<'_>) -> HarperResult<Option<String>> {
+        let Some(path) = str_arg(args, "path") else {
+            return Ok(None);
+        };
+        analyze_code(&format!("[CODE_ANALYZE {}]", path)).map(Some)
+    }
+
+    async fn execute_bracket(
+        &self,
+        call: &str,
+        _ctx: &ToolContext<'_>,
+    ) -> HarperResult<Option<String>> {
+        analyze_code(call).map(Some)
+    }
+}
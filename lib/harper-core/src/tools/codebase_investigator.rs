@@ -14,11 +14,15 @@
 
 //! Codebase investigation tool for deep structural analysis
 
+use crate::core::constants::tools;
 use crate::core::error::{HarperError, HarperResult};
 use crate::core::io_traits::UserApproval;
 use crate::tools::parsing;
+use crate::tools::registry::{str_arg, Tool, ToolContext, ToolRisk};
+use async_trait::async_trait;
 use colored::*;
 use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
 use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::ffi::OsStr;
 use std::path::Path;
@@ -2434,6 +2438,90 @@ fn infer_query_focus(query: &str, terms: &[String]) -> QueryFocus {
     QueryFocus::General
 }
 
+/// `codebase_investigator`: structural queries over the workspace
+pub struct CodebaseInvestigatorTool;
+
+#[async_trait(?Send)]
+impl Tool for CodebaseInvestigatorTool {
+    fn name(&self) -> &str {
+        "codebase_investigator"
+    }
+
+    fn description(&self) -> &str {
+        "Investigate the codebase: find calls to a symbol, trace how two symbols relate, search text, or clone another repository for context"
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "action": {
+                    "type": "string",
+                    "enum": ["find_calls", "trace_relationship", "search_text", "clone_context"],
+                    "description": "The investigation to run"
+                },
+                "symbol": {
+                    "type": "string",
+                    "description": "Symbol for 'find_calls'"
+                },
+                "x": {
+                    "type": "string",
+                    "description": "First symbol for 'trace_relationship'"
+                },
+                "y": {
+                    "type": "string",
+                    "description": "Second symbol for 'trace_relationship'"
+                },
+                "query": {
+                    "type": "string",
+                    "description": "Text for 'search_text'"
+                },
+                "repo_url": {
+                    "type": "string",
+                    "description": "Repository URL for 'clone_context'"
+                }
+            },
+            "required": ["action"]
+        })
+    }
+
+    fn risk(&self) -> ToolRisk {
+        ToolRisk::Execute
+    }
+
+    fn bracket_prefix(&self) -> Option<&str> {
+        Some(tools::CODEBASE_INVESTIGATE)
+    }
+
+    async fn execute(&self, args: &Value, ctx: &ToolContext<'_>) -> HarperResult<Option<String>> {
+        let arg = |key| str_arg(args, key);
+        let result = match (arg("action"), arg("symbol"), arg("x"), arg("y")) {
+            (Some("find_calls"), Some(symbol), _, _) => find_symbol_calls(symbol).await,
+            (Some("trace_relationship"), _, Some(x), Some(y)) => trace_relationship(x, y).await,
+            (Some("search_text"), ..) => match arg("query") {
+                Some(query) => search_text(query).await,
+                None => return Ok(None),
+            },
+            (Some("clone_context"), ..) => match arg("repo_url") {
+                Some(repo_url) => clone_temp_context(repo_url, ctx.approver.clone()).await,
+                None => return Ok(None),
+            },
+            _ => return Ok(None),
+        };
+        result.map(Some)
+    }
+
+    async fn execute_bracket(
+        &self,
+        call: &str,
+        ctx: &ToolContext<'_>,
+    ) -> HarperResult<Option<String>> {
+        investigate_codebase(call, ctx.approver.clone())
+            .await
+            .map(Some)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::collections::BTreeMap;
@@ -16,10 +16,14 @@
 //!
 //! This module provides functionality for running read-only SQL queries on SQLite databases.
 
+use crate::core::constants::tools;
 use crate::core::error::{HarperError, HarperResult};
 use crate::tools::parsing;
+use crate::tools::registry::{str_arg, Tool, ToolContext, ToolRisk};
+use async_trait::async_trait;
 use colored::*;
 use rusqlite::{Connection, Result as SqlResult};
+use serde_json::{json, Value};
 use std::io::{self, Write};
 
 use crate::core::io_traits::UserApproval;
@@ -31,15 +35,21 @@ pub async fn run_query(
     approver: Option<Arc<dyn UserApproval>>,
 ) -> HarperResult<String> {
     let args = parsing::extract_tool_args(response, "[DB_QUERY", 2)?;
-    let db_path = &args[0];
-    let query = &args[1];
+    run_query_direct(&args[0], &args[1], approver).await
+}
 
+/// Run a query, with the arguments already split
+pub async fn run_query_direct(
+    db_path: &str,
+    query: &str,
+    approver: Option<Arc<dyn UserApproval>>,
+) -> HarperResult<String> {
     let is_approved = if let Some(appr) = approver {
         appr.approve(&format!("Run query on DB {}?", db_path), query)
             .await?
     } else {
-        let db = db_path.clone();
-        let q = query.clone();
+        let db = db_path.to_string();
+        let q = query.to_string();
         tokio::task::spawn_blocking(move || {
             println!(
                 "{} Run query on DB {} ? (y/n): {}",
@@ -122,3 +132,60 @@ pub async fn run_query(
         count, result
     ))
 }
+
+/// `db_query`: run a SELECT against a SQLite database file
+pub struct DbQueryTool;
+
+#[async_trait(?Send)]
+impl Tool for DbQueryTool {
+    fn name(&self) -> &str {
+        "db_query"
+    }
+
+    fn description(&self) -> &str {
+        "Run a read-only SELECT query against a SQLite database file"
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "db_path": {
+                    "type": "string",
+                    "description": "Path to the SQLite database"
+                },
+                "query": {
+                    "type": "string",
+                    "description": "SELECT query to run"
+                }
+            },
+            "required": ["db_path", "query"]
+        })
+    }
+
+    fn risk(&self) -> ToolRisk {
+        ToolRisk::Execute
+    }
+
+    fn bracket_prefix(&self) -> Option<&str> {
+        Some(tools::DB_QUERY)
+    }
+
+    async fn execute(&self, args: &Value, ctx: &ToolContext<'_>) -> HarperResult<Option<String>> {
+        let (Some(db_path), Some(query)) = (str_arg(args, "db_path"), str_arg(args, "query"))
+        else {
+            return Ok(None);
+        };
+        run_query_direct(db_path, query, ctx.approver.clone())
+            .await
+            .map(Some)
+    }
+
+    async fn execute_bracket(
+        &self,
+        call: &str,
+        ctx: &ToolContext<'_>,
+    ) -> HarperResult<Option<String>> {
+        run_query(call, ctx.approver.clone()).await.map(Some)
+    }
+}
@@ -17,10 +17,14 @@
 //! This module provides functionality for reading, writing, and
 //! searching files with user approval.
 
+use crate::core::constants::tools;
 use crate::core::error::{HarperError, HarperResult};
 use crate::memory::cache::CacheAlignedBuffer;
 use crate::tools::parsing;
+use crate::tools::registry::{str_arg, Tool, ToolContext, ToolRisk};
+use async_trait::async_trait;
 use colored::*;
+use serde_json::{json, Value};
 use std::io::{self, Write};
 use std::path::Path;
 use walkdir::WalkDir;
@@ -220,7 +224,18 @@ pub async fn read_file(
     response: &str,
     approver: Option<Arc<dyn UserApproval>>,
 ) -> HarperResult<String> {
-    let path = resolve_read_target(&parsing::extract_tool_arg(response, "[READ_FILE")?)?;
+    read_file_direct(
+        &parsing::extract_tool_arg(response, "[READ_FILE")?,
+        approver,
+    )
+    .await
+}
+
+pub async fn read_file_direct(
+    raw_path: &str,
+    approver: Option<Arc<dyn UserApproval>>,
+) -> HarperResult<String> {
+    let path = resolve_read_target(raw_path)?;
     validate_read_target(&path)?;
 
     if let Some(appr) = approver {
@@ -300,10 +315,20 @@ pub async fn search_replace(
     approver: Option<Arc<dyn UserApproval>>,
 ) -> HarperResult<String> {
     let args = parsing::extract_tool_args(response, "[SEARCH_REPLACE", 3)?;
-    let path = resolve_read_target(&args[0])?;
+    search_replace_direct(&args[0], &args[1], &args[2], approver).await
+}
+
+/// Search and replace in a file, with the arguments already split
+pub async fn search_replace_direct(
+    raw_path: &str,
+    old_string: &str,
+    new_string: &str,
+    approver: Option<Arc<dyn UserApproval>>,
+) -> HarperResult<String> {
+    let path = resolve_read_target(raw_path)?;
     validate_read_target(&path)?;
-    let old_string = &args[1];
-    let new_string = &args[2];
+    // Approvers run without a console (TUI, MCP stdio), so only the prompt path reports here
+    let interactive = approver.is_none();
 
     let is_approved = if let Some(appr) = approver {
         appr.approve("Search and replace in file?", &path).await?
@@ -332,11 +357,13 @@ pub async fn search_replace(
         return Ok("Search and replace cancelled by user".to_string());
     }
 
-    println!(
-        "{} Searching and replacing in file: {}",
-        "System:".bold().magenta(),
-        path.magenta()
-    );
+    if interactive {
+        println!(
+            "{} Searching and replacing in file: {}",
+            "System:".bold().magenta(),
+            path.magenta()
+        );
+    }
 
     let content = std::fs::read_to_string(&path)
         .map_err(|e| HarperError::Command(format!("Failed to read file {}: {}", path, e)))?;
@@ -356,6 +383,179 @@ fn write_cache_aligned(path: &str, bytes: &[u8]) -> std::io::Result<()> {
     std::fs::write(path, buffer.as_slice())
 }
 
+/// `read_file`: read a file in the workspace
+pub struct ReadFileTool;
+
+#[async_trait(?Send)]
+impl Tool for ReadFileTool {
+    fn name(&self) -> &str {
+        "read_file"
+    }
+
+    fn description(&self) -> &str {
+        "Read the contents of a file"
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "path": {
+                    "type": "string",
+                    "description": "The path to the file to read"
+                }
+            },
+            "required": ["path"]
+        })
+    }
+
+    fn risk(&self) -> ToolRisk {
+        ToolRisk::ReadOnly
+    }
+
+    fn bracket_prefix(&self) -> Option<&str> {
+        Some(tools::READ_FILE)
+    }
+
+    async fn execute(&self, args: &Value, ctx: &ToolContext<'_>) -> HarperResult<Option<String>> {
+        let Some(path) = str_arg(args, "path") else {
+            return Ok(None);
+        };
+        let bracket_command = format!("[READ_FILE {}]", path);
+        read_file(&bracket_command, ctx.approver.clone())
+            .await
+            .map(Some)
+    }
+
+    async fn execute_bracket(
+        &self,
+        call: &str,
+        ctx: &ToolContext<'_>,
+    ) -> HarperResult<Option<String>> {
+        read_file(call, ctx.approver.clone()).await.map(Some)
+    }
+}
+
+/// `write_file`: create or overwrite a file
+pub struct WriteFileTool;
+
+#[async_trait(?Send)]
+impl Tool for WriteFileTool {
+    fn name(&self) -> &str {
+        "write_file"
+    }
+
+    fn description(&self) -> &str {
+        "Write content to a file"
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "path": {
+                    "type": "string",
+                    "description": "The path to the file to write"
+                },
+                "content": {
+                    "type": "string",
+                    "description": "The content to write to the file"
+                }
+            },
+            "required": ["path", "content"]
+        })
+    }
+
+    fn risk(&self) -> ToolRisk {
+        ToolRisk::Write
+    }
+
+    fn bracket_prefix(&self) -> Option<&str> {
+        Some(tools::WRITE_FILE)
+    }
+
+    async fn execute(&self, args: &Value, ctx: &ToolContext<'_>) -> HarperResult<Option<String>> {
+        let (Some(path), Some(content)) = (str_arg(args, "path"), str_arg(args, "content")) else {
+            return Ok(None);
+        };
+        write_file_direct(path, content, ctx.approver.clone())
+            .await
+            .map(Some)
+    }
+
+    async fn execute_bracket(
+        &self,
+        call: &str,
+        ctx: &ToolContext<'_>,
+    ) -> HarperResult<Option<String>> {
+        write_file(call, ctx.approver.clone()).await.map(Some)
+    }
+}
+
+/// `search_replace`: replace every occurrence of a string in a file
+pub struct SearchReplaceTool;
+
+#[async_trait(?Send)]
+impl Tool for SearchReplaceTool {
+    fn name(&self) -> &str {
+        "search_replace"
+    }
+
+    fn description(&self) -> &str {
+        "Search and replace text in a file"
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "path": {
+                    "type": "string",
+                    "description": "The path to the file"
+                },
+                "old_string": {
+                    "type": "string",
+                    "description": "The text to replace"
+                },
+                "new_string": {
+                    "type": "string",
+                    "description": "The replacement text"
+                }
+            },
+            "required": ["path", "old_string", "new_string"]
+        })
+    }
+
+    fn risk(&self) -> ToolRisk {
+        ToolRisk::Write
+    }
+
+    fn bracket_prefix(&self) -> Option<&str> {
+        Some(tools::SEARCH_REPLACE)
+    }
+
+    async fn execute(&self, args: &Value, ctx: &ToolContext<'_>) -> HarperResult<Option<String>> {
+        let (Some(path), Some(old_string), Some(new_string)) = (
+            str_arg(args, "path"),
+            str_arg(args, "old_string"),
+            str_arg(args, "new_string"),
+        ) else {
+            return Ok(None);
+        };
+        search_replace_direct(path, old_string, new_string, ctx.approver.clone())
+            .await
+            .map(Some)
+    }
+
+    async fn execute_bracket(
+        &self,
+        call: &str,
+        ctx: &ToolContext<'_>,
+    ) -> HarperResult<Option<String>> {
+        search_replace(call, ctx.approver.clone()).await.map(Some)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::core::error::HarperResult;
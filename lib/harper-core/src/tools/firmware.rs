@@ -12,109 +12,2423 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+//! Firmware devices from `[[firmware.devices]]` and the tools that drive them
+//!
+//! [`FirmwareDevices`] is built once at startup and shared by every session.
+//! Connections are tracked per session: a device is opened when the first
+//! session connects and closed when the last one disconnects, and controller
+//! commands only run for sessions that connected the device. A session that
+//! ends or is deleted releases its connections the same way.
+//!
+//! `firmware_flash` builds a device's `project` with Cargo, PlatformIO or
+//! ESP-IDF and writes it to the board, and `firmware_monitor` tails the
+//! board's serial console. Both stream their output like `run_command` and
+//! record it on the active plan job. Each flash step is also held to the
+//! same exec policy, sandbox and command log as `run_command`.
+
 use crate::core::constants::tools;
-use crate::core::error::HarperResult;
+use crate::core::error::{HarperError, HarperResult};
+use crate::core::io_traits::{RuntimeEventSink, StdinApproval, UserApproval};
+use crate::core::plan::PlanJobStatus;
+use crate::runtime::config::{FirmwareConfig, FirmwareDeviceConfig};
+use crate::tools::registry::{str_arg, Tool, ToolContext, ToolRisk};
+use crate::tools::{plan, shell};
+use async_trait::async_trait;
+use harper_firmware::serial::DEFAULT_BAUD_RATE;
+use harper_firmware::{
+    Esp32Device, FirmwareDevice, FirmwareRegistry, I2cMessage, PinState, Platform,
+    RaspberryPiDevice, SerialMonitor, Stm32Device, VirtualDevice,
+};
+use harper_sandbox::{Sandbox, SandboxRequest};
+use rusqlite::Connection;
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::sync::{mpsc, Mutex};
+
+/// Connection key for calls made outside a chat session
+const NO_SESSION: &str = "";
+
+const DEFAULT_MONITOR_SECS: u64 = 10;
+const MAX_MONITOR_SECS: u64 = 300;
+
+/// Characters of build or monitor output returned to the model
+const OUTPUT_TAIL_LIMIT: usize = 4000;
+
+/// Characters of output kept as the plan job preview
+const OUTPUT_PREVIEW_LIMIT: usize = 512;
+
+/// Longest a build or flash step may run; a cold ESP-IDF build takes minutes
+const FLASH_STEP_TIMEOUT_SECS: u64 = 900;
+
+/// Live firmware devices and the sessions connected to each
+pub struct FirmwareDevices {
+    registry: FirmwareRegistry,
+    ports: BTreeMap<String, String>,
+    /// Every configured device, including skipped ones, which may still be
+    /// flashed and monitored
+    configs: BTreeMap<String, FirmwareDeviceConfig>,
+    /// Configured devices that could not be registered, with the reason
+    skipped: Vec<(String, String)>,
+    connections: Mutex<BTreeMap<String, BTreeSet<String>>>,
+}
+
+impl FirmwareDevices {
+    /// Registers every configured device whose platform Harper can drive
+    pub fn from_config(config: &FirmwareConfig) -> Self {
+        let mut devices = Self::new(FirmwareRegistry::new());
+        if !config.enabled.unwrap_or(true) {
+            return devices;
+        }
+        for device_config in config.devices.iter().flatten() {
+            devices
+                .configs
+                .insert(device_config.name.clone(), device_config.clone());
+            match build_device(device_config) {
+                Ok(device) => {
+                    if let Some(port) = device_config
+                        .port
+                        .as_ref()
+                        .or(device_config.address.as_ref())
+                    {
+                        devices
+                            .ports
+                            .insert(device_config.name.clone(), port.clone());
+                    }
+                    devices
+                        .registry
+                        .register(device_config.name.clone(), device);
+                }
+                Err(reason) => {
+                    log::warn!(
+                        "Skipping firmware device {}: {}",
+                        device_config.name,
+                        reason
+                    );
+                    devices.skipped.push((device_config.name.clone(), reason));
+                }
+            }
+        }
+        devices
+    }
+
+    /// Wraps a registry populated in code
+    pub fn new(registry: FirmwareRegistry) -> Self {
+        Self {
+            registry,
+            ports: BTreeMap::new(),
+            configs: BTreeMap::new(),
+            skipped: Vec::new(),
+            connections: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    fn device(&self, name: &str) -> HarperResult<&dyn FirmwareDevice> {
+        self.registry.get(name).ok_or_else(|| {
+            HarperError::Validation(format!(
+                "Unknown firmware device '{}'. Use [FIRMWARE list] to see configured devices.",
+                name
+            ))
+        })
+    }
+
+    /// Whether `session_id` connected `name` and has not disconnected it
+    pub async fn is_connected(&self, name: &str, session_id: Option<&str>) -> bool {
+        self.connections
+            .lock()
+            .await
+            .get(name)
+            .is_some_and(|sessions| sessions.contains(session_id.unwrap_or(NO_SESSION)))
+    }
+
+    /// Devices `session_id` is connected to, by name
+    pub async fn connected_devices(&self, session_id: Option<&str>) -> Vec<String> {
+        let session = session_id.unwrap_or(NO_SESSION);
+        self.connections
+            .lock()
+            .await
+            .iter()
+            .filter(|(_, sessions)| sessions.contains(session))
+            .map(|(name, _)| name.clone())
+            .collect()
+    }
+
+    pub async fn connect(&self, name: &str, session_id: Option<&str>) -> HarperResult<String> {
+        let device = self.device(name)?;
+        // Held across the connect so two sessions never open the port at once
+        let mut connections = self.connections.lock().await;
+        let sessions = connections.entry(name.to_string()).or_default();
+        if sessions.contains(session_id.unwrap_or(NO_SESSION)) {
+            return Ok(format!("{} is already connected.", name));
+        }
+        if sessions.is_empty() {
+            if let Err(err) = device.connect().await {
+                connections.remove(name);
+                return Err(err.into());
+            }
+        }
+        sessions.insert(session_id.unwrap_or(NO_SESSION).to_string());
+        Ok(format!("Connected to {}.", name))
+    }
+
+    pub async fn disconnect(&self, name: &str, session_id: Option<&str>) -> HarperResult<String> {
+        let device = self.device(name)?;
+        let mut connections = self.connections.lock().await;
+        let Some(sessions) = connections.get_mut(name) else {
+            return Ok(format!("{} is not connected.", name));
+        };
+        if !sessions.remove(session_id.unwrap_or(NO_SESSION)) {
+            return Ok(format!("{} is not connected.", name));
+        }
+        if sessions.is_empty() {
+            connections.remove(name);
+            device.disconnect().await?;
+        }
+        Ok(format!("Disconnected from {}.", name))
+    }
+
+    /// Drops every connection `session_id` holds, closing devices no other
+    /// session still uses. Returns the devices it released.
+    pub async fn release_session(&self, session_id: Option<&str>) -> Vec<String> {
+        let session = session_id.unwrap_or(NO_SESSION);
+        let mut connections = self.connections.lock().await;
+        let mut released = Vec::new();
+        for (name, sessions) in connections.iter_mut() {
+            if sessions.remove(session) {
+                released.push(name.clone());
+            }
+        }
+        for name in &released {
+            if !connections.get(name).is_some_and(BTreeSet::is_empty) {
+                continue;
+            }
+            connections.remove(name);
+            let Some(device) = self.registry.get(name) else {
+                continue;
+            };
+            if let Err(err) = device.disconnect().await {
+                log::warn!("Failed to disconnect firmware device {}: {}", name, err);
+            }
+        }
+        released
+    }
+
+    /// Closes every connected device, whichever sessions hold it
+    pub async fn release_all(&self) {
+        let mut connections = self.connections.lock().await;
+        for name in std::mem::take(&mut *connections).into_keys() {
+            if let Some(device) = self.registry.get(&name) {
+                if let Err(err) = device.disconnect().await {
+                    log::warn!("Failed to disconnect firmware device {}: {}", name, err);
+                }
+            }
+        }
+    }
+
+    /// The device a controller command targets, which the session must have connected
+    async fn connected_device(
+        &self,
+        name: &str,
+        session_id: Option<&str>,
+    ) -> HarperResult<&dyn FirmwareDevice> {
+        let device = self.device(name)?;
+        if !self.is_connected(name, session_id).await {
+            return Err(HarperError::Validation(format!(
+                "{} is not connected. Use [FIRMWARE connect {}] first.",
+                name, name
+            )));
+        }
+        Ok(device)
+    }
+
+    async fn list(&self, session_id: Option<&str>) -> String {
+        let mut names = self.registry.list_devices();
+        names.sort();
+        if names.is_empty() && self.skipped.is_empty() {
+            return "No firmware devices configured. Add [[firmware.devices]] entries with a \
+                name, platform and port to your config."
+                .to_string();
+        }
+
+        let connected = self.connected_devices(session_id).await;
+        let mut lines = vec!["Firmware devices:".to_string()];
+        for name in names {
+            let Some(device) = self.registry.get(&name) else {
+                continue;
+            };
+            let info = device.device_info();
+            let port = self
+                .ports
+                .get(&name)
+                .map(|port| format!(", {}", port))
+                .unwrap_or_default();
+            let state = if connected.contains(&name) {
+                "connected"
+            } else {
+                "disconnected"
+            };
+            lines.push(format!(
+                "- {} ({:?}{}) {}",
+                name, info.platform, port, state
+            ));
+        }
+        for (name, reason) in &self.skipped {
+            lines.push(format!("- {} unavailable: {}", name, reason));
+        }
+        lines.join("\n")
+    }
+
+    async fn info(&self, name: &str, session_id: Option<&str>) -> HarperResult<String> {
+        let device = self.device(name)?;
+        let info = device.device_info();
+        let controllers = [
+            ("gpio", device.gpio().is_some()),
+            ("i2c", device.i2c().is_some()),
+            ("spi", device.spi().is_some()),
+            ("uart", device.uart().is_some()),
+            ("pwm", device.pwm().is_some()),
+            ("adc", device.adc().is_some()),
+        ]
+        .into_iter()
+        .filter(|(_, available)| *available)
+        .map(|(name, _)| name)
+        .collect::<Vec<_>>();
+
+        let mut lines = vec![
+            format!("Device: {}", info.name),
+            format!("Platform: {:?}", info.platform),
+        ];
+        if let Some(port) = self.ports.get(name) {
+            lines.push(format!("Port: {}", port));
+        }
+        if let Some(version) = &info.firmware_version {
+            lines.push(format!("Firmware version: {}", version));
+        }
+        lines.push(format!("Capabilities: {}", info.capabilities.join(", ")));
+        lines.push(format!(
+            "Controllers: {}",
+            if controllers.is_empty() {
+                "none".to_string()
+            } else {
+                controllers.join(", ")
+            }
+        ));
+        lines.push(format!(
+            "Connected: {}",
+            if self.is_connected(name, session_id).await {
+                "yes"
+            } else {
+                "no"
+            }
+        ));
+        Ok(lines.join("\n"))
+    }
+
+    /// `gpio [device] <pin> <high|low|toggle|read>`; the device may be left
+    /// out while the session has exactly one connected
+    async fn gpio(&self, args: &str, session_id: Option<&str>) -> HarperResult<String> {
+        let mut parts: Vec<&str> = args.split_whitespace().collect();
+        let name = match parts.first() {
+            Some(first) if first.parse::<u32>().is_err() => parts.remove(0).to_string(),
+            _ => self.only_connected_device(session_id).await?,
+        };
+        let (Some(pin), Some(action)) = (parts.first(), parts.get(1)) else {
+            return Err(usage("gpio [device] <pin> <high|low|toggle|read>"));
+        };
+        let pin = pin
+            .parse::<u32>()
+            .map_err(|_| HarperError::Validation(format!("Invalid GPIO pin: {}", pin)))?;
+        let device = self.connected_device(&name, session_id).await?;
+        let gpio = device
+            .gpio()
+            .ok_or_else(|| missing_controller(&name, "GPIO"))?;
+
+        match action.to_ascii_lowercase().as_str() {
+            "high" | "on" | "1" => {
+                gpio.write_pin(pin, PinState::High).await?;
+                Ok(format!("{} pin {} set high.", name, pin))
+            }
+            "low" | "off" | "0" => {
+                gpio.write_pin(pin, PinState::Low).await?;
+                Ok(format!("{} pin {} set low.", name, pin))
+            }
+            "toggle" => {
+                gpio.toggle_pin(pin).await?;
+                Ok(format!("{} pin {} toggled.", name, pin))
+            }
+            "read" => {
+                let state = gpio.read_pin(pin).await?;
+                Ok(format!("{} pin {} is {:?}.", name, pin, state))
+            }
+            other => Err(HarperError::Validation(format!(
+                "Unknown GPIO action '{}'. Use high, low, toggle or read.",
+                other
+            ))),
+        }
+    }
+
+    /// `i2c <device> scan`, `i2c <device> read <address> <length>` or
+    /// `i2c <device> write <address> <bytes...>`
+    async fn i2c(&self, args: &str, session_id: Option<&str>) -> HarperResult<String> {
+        let parts: Vec<&str> = args.split_whitespace().collect();
+        let (Some(name), Some(action)) = (parts.first(), parts.get(1)) else {
+            return Err(usage(
+                "i2c <device> <scan|read|write> [address] [length|bytes]",
+            ));
+        };
+        let device = self.connected_device(name, session_id).await?;
+        let i2c = device
+            .i2c()
+            .ok_or_else(|| missing_controller(name, "I2C"))?;
+
+        match *action {
+            "scan" => {
+                let found = i2c.scan_devices().await?;
+                if found.is_empty() {
+                    Ok(format!("No I2C devices found on {}.", name))
+                } else {
+                    Ok(format!("I2C devices on {}: {}", name, format_bytes(&found)))
+                }
+            }
+            "read" => {
+                let (Some(address), Some(length)) = (parts.get(2), parts.get(3)) else {
+                    return Err(usage("i2c <device> read <address> <length>"));
+                };
+                let address = parse_byte(address)?;
+                let length = length.parse::<usize>().map_err(|_| {
+                    HarperError::Validation(format!("Invalid read length: {}", length))
+                })?;
+                let data = i2c.read(address, length).await?;
+                Ok(format!(
+                    "Read from 0x{:02x}: {}",
+                    address,
+                    format_bytes(&data)
+                ))
+            }
+            "write" => {
+                let Some(address) = parts.get(2) else {
+                    return Err(usage("i2c <device> write <address> <bytes...>"));
+                };
+                let address = parse_byte(address)?;
+                let data = parse_bytes(&parts[3..])?;
+                let count = data.len();
+                i2c.write(I2cMessage { address, data }).await?;
+                Ok(format!("Wrote {} bytes to 0x{:02x}.", count, address))
+            }
+            other => Err(HarperError::Validation(format!(
+                "Unknown I2C action '{}'. Use scan, read or write.",
+                other
+            ))),
+        }
+    }
+
+    /// `spi <device> transfer <bytes...>`, `spi <device> write <bytes...>` or
+    /// `spi <device> read <length>`
+    async fn spi(&self, args: &str, session_id: Option<&str>) -> HarperResult<String> {
+        let parts: Vec<&str> = args.split_whitespace().collect();
+        let (Some(name), Some(action)) = (parts.first(), parts.get(1)) else {
+            return Err(usage(
+                "spi <device> <transfer|write|read> <bytes...|length>",
+            ));
+        };
+        let device = self.connected_device(name, session_id).await?;
+        let spi = device
+            .spi()
+            .ok_or_else(|| missing_controller(name, "SPI"))?;
+
+        match *action {
+            "transfer" => {
+                let received = spi.transfer(&parse_bytes(&parts[2..])?).await?;
+                Ok(format!("Received: {}", format_bytes(&received)))
+            }
+            "write" => {
+                let data = parse_bytes(&parts[2..])?;
+                spi.write(&data).await?;
+                Ok(format!("Wrote {} bytes.", data.len()))
+            }
+            "read" => {
+                let length = parts
+                    .get(2)
+                    .and_then(|length| length.parse::<usize>().ok())
+                    .ok_or_else(|| usage("spi <device> read <length>"))?;
+                let data = spi.read(length).await?;
+                Ok(format!("Read: {}", format_bytes(&data)))
+            }
+            other => Err(HarperError::Validation(format!(
+                "Unknown SPI action '{}'. Use transfer, write or read.",
+                other
+            ))),
+        }
+    }
+
+    /// `uart <device> send <text>` or `uart <device> read <length>`
+    async fn uart(&self, args: &str, session_id: Option<&str>) -> HarperResult<String> {
+        let mut parts = args.trim().splitn(3, char::is_whitespace);
+        let (Some(name), Some(action)) = (parts.next(), parts.next()) else {
+            return Err(usage("uart <device> <send|read> <text|length>"));
+        };
+        let rest = parts.next().unwrap_or("").trim();
+        let device = self.connected_device(name, session_id).await?;
+        let uart = device
+            .uart()
+            .ok_or_else(|| missing_controller(name, "UART"))?;
+
+        match action {
+            "send" => {
+                let written = uart.write_string(rest).await?;
+                uart.flush().await?;
+                Ok(format!("Sent {} bytes to {}.", written, name))
+            }
+            "read" => {
+                let length = match rest {
+                    "" => uart.available().await?,
+                    length => length.parse::<usize>().map_err(|_| {
+                        HarperError::Validation(format!("Invalid read length: {}", length))
+                    })?,
+                };
+                let data = uart.read(length).await?;
+                Ok(format!(
+                    "Received {} bytes: {}",
+                    data.len(),
+                    String::from_utf8_lossy(&data)
+                ))
+            }
+            other => Err(HarperError::Validation(format!(
+                "Unknown UART action '{}'. Use send or read.",
+                other
+            ))),
+        }
+    }
+
+    async fn only_connected_device(&self, session_id: Option<&str>) -> HarperResult<String> {
+        let mut connected = self.connected_devices(session_id).await;
+        match connected.len() {
+            1 => Ok(connected.remove(0)),
+            0 => Err(HarperError::Validation(
+                "No firmware device is connected. Use [FIRMWARE connect <device>] first."
+                    .to_string(),
+            )),
+            _ => Err(HarperError::Validation(format!(
+                "Several devices are connected ({}). Name the device to use.",
+                connected.join(", ")
+            ))),
+        }
+    }
+
+    /// Config and port of a device to flash or monitor; no session may have
+    /// it connected, since the tools need the port to themselves. A Pico's
+    /// `port` is its SPI bus, not a serial console, so it is refused
+    async fn serial_target(&self, name: &str) -> HarperResult<(&FirmwareDeviceConfig, String)> {
+        let config = self.configs.get(name).ok_or_else(|| {
+            HarperError::Validation(format!(
+                "Unknown firmware device '{}'. Use [FIRMWARE list] to see configured devices.",
+                name
+            ))
+        })?;
+        if Platform::parse(&config.platform) == Platform::RaspberryPiPico {
+            return Err(HarperError::Validation(format!(
+                "{} is a {} device whose port is an SPI bus, not a serial console; it cannot be flashed or monitored.",
+                name, config.platform
+            )));
+        }
+        let port = config
+            .port
+            .clone()
+            .ok_or_else(|| HarperError::Validation(format!("{} has no port configured.", name)))?;
+        if self.connections.lock().await.contains_key(name) {
+            return Err(HarperError::Validation(format!(
+                "{} is connected. Use [FIRMWARE disconnect {}] so its port is free.",
+                name, name
+            )));
+        }
+        Ok((config, port))
+    }
+
+    /// Builds the device's project and writes it to the board, after approval
+    async fn flash(&self, name: &str, ctx: &ToolContext<'_>) -> HarperResult<String> {
+        let (config, port) = self.serial_target(name).await?;
+        let project = config
+            .project
+            .as_deref()
+            .map(PathBuf::from)
+            .ok_or_else(|| {
+                HarperError::Validation(format!(
+                    "{} has no project configured. Set `project` to the firmware directory.",
+                    name
+                ))
+            })?;
+        if !project.is_dir() {
+            return Err(HarperError::Validation(format!(
+                "Project directory {} does not exist.",
+                project.display()
+            )));
+        }
+        let toolchain = match config.toolchain.as_deref() {
+            Some(toolchain) => Toolchain::parse(toolchain).ok_or_else(|| {
+                HarperError::Validation(format!(
+                    "Unknown toolchain '{}'. Use cargo, platformio or idf.",
+                    toolchain
+                ))
+            })?,
+            None => Toolchain::detect(&project).ok_or_else(|| {
+                HarperError::Validation(format!(
+                    "Could not tell how to build {}. Set `toolchain` to cargo, platformio or idf.",
+                    project.display()
+                ))
+            })?,
+        };
+        let steps = toolchain.flash_steps(
+            Platform::parse(&config.platform),
+            &port,
+            config.chip.as_deref(),
+        )?;
+        let command_line = steps
+            .iter()
+            .map(|step| step.join(" "))
+            .collect::<Vec<_>>()
+            .join(" && ");
+
+        let audit = ctx.audit("firmware_flash");
+        for step in &steps {
+            let step_line = step.join(" ");
+            if let Some(err) = shell::command_refusal(ctx.exec_policy, &step_line) {
+                shell::maybe_log_command(
+                    Some(&audit),
+                    &step_line,
+                    "blocked",
+                    true,
+                    false,
+                    None,
+                    None,
+                    None,
+                    None,
+                    Some(err.clone()),
+                );
+                return Err(HarperError::Command(err));
+            }
+        }
+
+        let mut output = JobOutput::new(ctx, "firmware_flash", command_line.clone());
+        let requires_approval =
+            shell::approval_required_for_command(ctx.exec_policy, &command_line, None);
+        if requires_approval {
+            output
+                .start(
+                    PlanJobStatus::WaitingApproval,
+                    format!("waiting approval: {}", command_line),
+                )
+                .await;
+            let approver: Arc<dyn UserApproval> = ctx
+                .approver
+                .clone()
+                .unwrap_or_else(|| Arc::new(StdinApproval));
+            let detail = format!("{} (in {})", command_line, project.display());
+            if !approver.approve("Flash firmware?", &detail).await? {
+                output.cancel().await;
+                shell::maybe_log_command(
+                    Some(&audit),
+                    &command_line,
+                    "cancelled",
+                    true,
+                    false,
+                    None,
+                    None,
+                    None,
+                    None,
+                    Some("User rejected command".to_string()),
+                );
+                return Ok(format!("Flashing {} cancelled by user", name));
+            }
+        }
+        output
+            .start(
+                PlanJobStatus::Running,
+                format!("flashing {}: {}", name, command_line),
+            )
+            .await;
+
+        let sandbox = shell::enabled_sandbox(ctx.exec_policy);
+        for step in &steps {
+            output.begin_step(step.join(" "));
+            let start = Instant::now();
+            let result = run_step(step, &project, &port, sandbox.as_ref(), &mut output).await;
+            let status = result.as_ref().ok().filter(|status| status.success());
+            shell::maybe_log_command(
+                Some(&audit),
+                &output.command,
+                if status.is_some() {
+                    "succeeded"
+                } else {
+                    "failed"
+                },
+                requires_approval,
+                true,
+                result.as_ref().ok().and_then(|status| status.code()),
+                Some(start.elapsed().as_millis() as i64),
+                shell::preview_text(&output.step_stdout),
+                shell::preview_text(&output.step_stderr),
+                match &result {
+                    Ok(status) if status.success() => None,
+                    Ok(_) => Some("Command exited with non-zero status".to_string()),
+                    Err(err) => Some(err.to_string()),
+                },
+            );
+            let status = match result {
+                Ok(status) => status,
+                Err(err) => {
+                    output.line(&err.to_string(), true).await;
+                    output.finish(PlanJobStatus::Failed).await;
+                    return Err(err);
+                }
+            };
+            if !status.success() {
+                output.finish(PlanJobStatus::Failed).await;
+                return Err(HarperError::Command(format!(
+                    "`{}` failed ({}). Last output:\n{}",
+                    output.command,
+                    status,
+                    output.tail(OUTPUT_TAIL_LIMIT)
+                )));
+            }
+        }
+        output.finish(PlanJobStatus::Succeeded).await;
+        Ok(format!(
+            "Flashed {} on {} with {}.\n{}",
+            name,
+            port,
+            toolchain.name(),
+            output.tail(OUTPUT_TAIL_LIMIT)
+        ))
+    }
+
+    /// Tails the device's serial console for `seconds`
+    async fn monitor(
+        &self,
+        name: &str,
+        seconds: u64,
+        ctx: &ToolContext<'_>,
+    ) -> HarperResult<String> {
+        let (config, port) = self.serial_target(name).await?;
+        let baud_rate = config.baud_rate.unwrap_or(DEFAULT_BAUD_RATE);
+        let seconds = seconds.clamp(1, MAX_MONITOR_SECS);
+        let command = format!("monitor {} at {} baud", port, baud_rate);
+
+        let mut output = JobOutput::new(ctx, "firmware_monitor", command.clone());
+        output
+            .start(PlanJobStatus::Running, format!("{}: {}", name, command))
+            .await;
+
+        // The port is read on a blocking thread and lines are passed back here,
+        // where the session's connection lives
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        let path = port.clone();
+        let reader = tokio::task::spawn_blocking(move || {
+            let deadline = Instant::now() + Duration::from_secs(seconds);
+            let mut monitor = SerialMonitor::open(&path, baud_rate)?;
+            while let Some(line) = monitor.next_line(deadline)? {
+                if sender.send(line).is_err() {
+                    return Ok(());
+                }
+            }
+            if let Some(line) = monitor.take_partial() {
+                let _ = sender.send(line);
+            }
+            Ok::<(), harper_firmware::FirmwareError>(())
+        });
+
+        let mut lines = 0;
+        while let Some(line) = receiver.recv().await {
+            output.line(&line, false).await;
+            lines += 1;
+        }
+        let result = reader
+            .await
+            .map_err(|e| HarperError::Command(format!("Serial monitor failed: {}", e)))?;
+        if let Err(err) = result {
+            output.line(&err.to_string(), true).await;
+            output.finish(PlanJobStatus::Failed).await;
+            return Err(err.into());
+        }
+        output.finish(PlanJobStatus::Succeeded).await;
+
+        if lines == 0 {
+            return Ok(format!(
+                "No output from {} on {} in {}s.",
+                name, port, seconds
+            ));
+        }
+        Ok(format!(
+            "Monitored {} on {} for {}s, {} lines:\n{}",
+            name,
+            port,
+            seconds,
+            lines,
+            output.tail(OUTPUT_TAIL_LIMIT)
+        ))
+    }
+}
+
+/// Builds the driver for one configured device
+fn build_device(config: &FirmwareDeviceConfig) -> Result<Box<dyn FirmwareDevice>, String> {
+    let port = || {
+        config
+            .port
+            .clone()
+            .ok_or_else(|| format!("platform '{}' needs a port", config.platform))
+    };
+    match Platform::parse(&config.platform) {
+        Platform::Esp32 | Platform::Esp8266 => {
+            Ok(Box::new(Esp32Device::new(port()?, config.name.clone())))
+        }
+        Platform::Stm32 => Ok(Box::new(Stm32Device::new(port()?, config.name.clone()))),
+        Platform::RaspberryPiPico => {
+            let device = RaspberryPiDevice::new(config.name.clone());
+            Ok(Box::new(match &config.port {
+                Some(port) => device.with_spi_port(port.clone()),
+                None => device,
+            }))
+        }
+        Platform::Virtual => match &config.peripherals {
+            Some(path) => VirtualDevice::from_spec_file(config.name.clone(), path)
+                .map(|device| Box::new(device) as Box<dyn FirmwareDevice>)
+                .map_err(|e| e.to_string()),
+            None => Ok(Box::new(VirtualDevice::new(config.name.clone()))),
+        },
+        Platform::Arduino | Platform::Custom => {
+            Err(format!("no driver for platform '{}'", config.platform))
+        }
+    }
+}
+
+/// Build system of a firmware project
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Toolchain {
+    Cargo,
+    PlatformIo,
+    EspIdf,
+}
+
+impl Toolchain {
+    fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "cargo" => Some(Self::Cargo),
+            "platformio" | "pio" => Some(Self::PlatformIo),
+            "idf" | "esp-idf" | "idf.py" => Some(Self::EspIdf),
+            _ => None,
+        }
+    }
+
+    /// Guesses the toolchain from the files at the project root
+    fn detect(project: &Path) -> Option<Self> {
+        if project.join("platformio.ini").is_file() {
+            Some(Self::PlatformIo)
+        } else if project.join("Cargo.toml").is_file() {
+            Some(Self::Cargo)
+        } else if project.join("CMakeLists.txt").is_file()
+            && (project.join("sdkconfig").is_file()
+                || project.join("sdkconfig.defaults").is_file()
+                || project.join("main").is_dir())
+        {
+            Some(Self::EspIdf)
+        } else {
+            None
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Cargo => "cargo",
+            Self::PlatformIo => "PlatformIO",
+            Self::EspIdf => "ESP-IDF",
+        }
+    }
+
+    /// Commands that build the project, then write it to the board on `port`
+    fn flash_steps(
+        self,
+        platform: Platform,
+        port: &str,
+        chip: Option<&str>,
+    ) -> HarperResult<Vec<Vec<String>>> {
+        let command = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect();
+        let steps = match self {
+            Self::Cargo => {
+                let flash = match (platform, chip) {
+                    (Platform::Esp32 | Platform::Esp8266, _) => {
+                        command(&["cargo", "espflash", "flash", "--release", "--port", port])
+                    }
+                    (_, Some(chip)) => command(&["cargo", "flash", "--release", "--chip", chip]),
+                    (_, None) => {
+                        return Err(HarperError::Validation(
+                            "Cargo projects for boards other than ESP32 are flashed with \
+                             cargo-flash, which needs `chip` set to the probe-rs chip name."
+                                .to_string(),
+                        ))
+                    }
+                };
+                vec![command(&["cargo", "build", "--release"]), flash]
+            }
+            Self::PlatformIo => vec![
+                command(&["pio", "run"]),
+                command(&["pio", "run", "--target", "upload", "--upload-port", port]),
+            ],
+            Self::EspIdf => vec![
+                command(&["idf.py", "build"]),
+                command(&["idf.py", "-p", port, "flash"]),
+            ],
+        };
+        Ok(steps)
+    }
+}
+
+/// Output of a flash or monitor job, streamed to the UI as it arrives and
+/// recorded on the session's active plan job
+struct JobOutput<'a> {
+    conn: &'a Connection,
+    session_id: Option<&'a str>,
+    runtime_events: Option<Arc<dyn RuntimeEventSink>>,
+    tool: &'static str,
+    /// Label the UI shows the output under
+    command: String,
+    transcript: String,
+    has_error_output: bool,
+    /// Output of the current step alone, for its command log entry
+    step_stdout: String,
+    step_stderr: String,
+}
+
+impl<'a> JobOutput<'a> {
+    fn new(ctx: &ToolContext<'a>, tool: &'static str, command: String) -> Self {
+        Self {
+            conn: ctx.conn,
+            session_id: ctx.session_id,
+            runtime_events: ctx.runtime_events.clone(),
+            tool,
+            command,
+            transcript: String::new(),
+            has_error_output: false,
+            step_stdout: String::new(),
+            step_stderr: String::new(),
+        }
+    }
+
+    /// Shows the following output under `command`
+    fn begin_step(&mut self, command: String) {
+        self.command = command;
+        self.step_stdout.clear();
+        self.step_stderr.clear();
+    }
+
+    /// Starts the plan job, or moves the one awaiting approval to `status`
+    async fn start(&self, status: PlanJobStatus, activity: String) {
+        let Some(session_id) = self.session_id else {
+            return;
+        };
+        if let Some(sink) = &self.runtime_events {
+            let _ = sink.activity_updated(session_id, Some(activity)).await;
+        }
+        let has_active_job = crate::memory::storage::load_plan_state(self.conn, session_id)
+            .ok()
+            .flatten()
+            .and_then(|plan| plan.runtime)
+            .and_then(|runtime| runtime.active_job_id)
+            .is_some();
+        let _ = if has_active_job {
+            plan::update_active_plan_job(self.conn, session_id, status)
+        } else {
+            plan::start_plan_job(
+                self.conn,
+                session_id,
+                self.tool,
+                Some(self.command.clone()),
+                status,
+            )
+        };
+        self.plan_updated().await;
+    }
+
+    async fn line(&mut self, line: &str, is_error: bool) {
+        let chunk = format!("{}\n", line);
+        self.transcript.push_str(&chunk);
+        self.has_error_output |= is_error;
+        if is_error {
+            self.step_stderr.push_str(&chunk);
+        } else {
+            self.step_stdout.push_str(&chunk);
+        }
+        if let Some(session_id) = self.session_id {
+            let _ = plan::append_active_plan_job_output(self.conn, session_id, &chunk, is_error);
+            self.plan_updated().await;
+            if let Some(sink) = &self.runtime_events {
+                let _ = sink
+                    .command_output_updated(
+                        session_id,
+                        self.command.clone(),
+                        chunk,
+                        is_error,
+                        false,
+                    )
+                    .await;
+            }
+        }
+    }
+
+    async fn finish(&self, status: PlanJobStatus) {
+        let Some(session_id) = self.session_id else {
+            return;
+        };
+        let is_error = self.has_error_output || matches!(status, PlanJobStatus::Failed);
+        if let Some(sink) = &self.runtime_events {
+            let _ = sink
+                .command_output_updated(
+                    session_id,
+                    self.command.clone(),
+                    String::new(),
+                    is_error,
+                    true,
+                )
+                .await;
+        }
+        let preview = self.tail(OUTPUT_PREVIEW_LIMIT);
+        let _ = plan::finish_active_plan_job_with_output(
+            self.conn,
+            session_id,
+            status,
+            (!preview.is_empty()).then_some(preview),
+            is_error,
+        );
+        self.plan_updated().await;
+    }
+
+    /// Marks the job blocked after the user declined it
+    async fn cancel(&self) {
+        let Some(session_id) = self.session_id else {
+            return;
+        };
+        if let Some(sink) = &self.runtime_events {
+            let _ = sink
+                .activity_updated(session_id, Some("approval rejected".to_string()))
+                .await;
+        }
+        let _ = plan::finish_active_plan_job(self.conn, session_id, PlanJobStatus::Blocked);
+        self.plan_updated().await;
+    }
+
+    async fn plan_updated(&self) {
+        if let (Some(sink), Some(session_id)) = (&self.runtime_events, self.session_id) {
+            let plan = crate::memory::storage::load_plan_state(self.conn, session_id)
+                .ok()
+                .flatten();
+            let _ = sink.plan_updated(session_id, plan).await;
+        }
+    }
 
-pub fn handle_firmware_command(response: &str) -> HarperResult<String> {
-    let command = response
+    /// The last `limit` characters of output
+    fn tail(&self, limit: usize) -> String {
+        let text = self.transcript.trim();
+        let count = text.chars().count();
+        if count <= limit {
+            return text.to_string();
+        }
+        let tail: String = text.chars().skip(count - limit).collect();
+        format!("…{}", tail)
+    }
+}
+
+/// Runs one build or flash command in `dir`, inside `sandbox` when one is
+/// enabled, streaming stdout and stderr. The step is stopped after
+/// [`FLASH_STEP_TIMEOUT_SECS`] if the sandbox has not stopped it sooner.
+async fn run_step(
+    command: &[String],
+    dir: &Path,
+    port: &str,
+    sandbox: Option<&Sandbox>,
+    output: &mut JobOutput<'_>,
+) -> HarperResult<std::process::ExitStatus> {
+    let (program, args) = command
+        .split_first()
+        .ok_or_else(|| HarperError::Command("No command provided".to_string()))?;
+    let run = async {
+        match sandbox {
+            Some(sandbox) => run_sandboxed_step(sandbox, program, args, dir, port, output).await,
+            None => run_direct_step(program, args, dir, output).await,
+        }
+    };
+    tokio::time::timeout(Duration::from_secs(FLASH_STEP_TIMEOUT_SECS), run)
+        .await
+        .map_err(|_| {
+            HarperError::Command(format!(
+                "{} did not finish within {} seconds and was stopped.",
+                program, FLASH_STEP_TIMEOUT_SECS
+            ))
+        })?
+}
+
+async fn run_direct_step(
+    program: &str,
+    args: &[String],
+    dir: &Path,
+    output: &mut JobOutput<'_>,
+) -> HarperResult<std::process::ExitStatus> {
+    let mut child = tokio::process::Command::new(program)
+        .args(args)
+        .current_dir(dir)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| HarperError::Command(format!("Failed to run {}: {}", program, e)))?;
+
+    let mut stdout = child.stdout.take().map(|out| BufReader::new(out).lines());
+    let mut stderr = child.stderr.take().map(|err| BufReader::new(err).lines());
+    while stdout.is_some() || stderr.is_some() {
+        tokio::select! {
+            line = async { stdout.as_mut().unwrap().next_line().await }, if stdout.is_some() => {
+                match line {
+                    Ok(Some(line)) => output.line(&line, false).await,
+                    _ => stdout = None,
+                }
+            }
+            line = async { stderr.as_mut().unwrap().next_line().await }, if stderr.is_some() => {
+                match line {
+                    Ok(Some(line)) => output.line(&line, true).await,
+                    _ => stderr = None,
+                }
+            }
+        }
+    }
+    child
+        .wait()
+        .await
+        .map_err(|e| HarperError::Command(format!("Failed to wait for {}: {}", program, e)))
+}
+
+/// Runs a step through the sandbox. The step that writes to the board
+/// declares its port, so the sandbox refuses it unless the port is writable.
+async fn run_sandboxed_step(
+    sandbox: &Sandbox,
+    program: &str,
+    args: &[String],
+    dir: &Path,
+    port: &str,
+    output: &mut JobOutput<'_>,
+) -> HarperResult<std::process::ExitStatus> {
+    let declared_write_paths = if args.iter().any(|arg| arg == port) {
+        vec![PathBuf::from(port)]
+    } else {
+        vec![]
+    };
+    let request = SandboxRequest {
+        command: program.to_string(),
+        args: args.to_vec(),
+        working_dir: dir.to_path_buf(),
+        env: vec![],
+        declared_read_paths: vec![],
+        declared_write_paths,
+        requires_network: false,
+    };
+    let (chunk_tx, mut chunk_rx) = mpsc::unbounded_channel::<(String, bool)>();
+    let run = sandbox.execute_request_streaming(request, move |chunk, is_error| {
+        let _ = chunk_tx.send((chunk, is_error));
+    });
+    tokio::pin!(run);
+    let result = loop {
+        tokio::select! {
+            result = &mut run => break result,
+            Some((chunk, is_error)) = chunk_rx.recv() => {
+                output.line(chunk.trim_end_matches('\n'), is_error).await;
+            }
+        }
+    };
+    while let Ok((chunk, is_error)) = chunk_rx.try_recv() {
+        output.line(chunk.trim_end_matches('\n'), is_error).await;
+    }
+    let result = result?;
+    if let Some(error) = result.limit_error() {
+        return Err(error.into());
+    }
+    Ok(result.output.status)
+}
+
+fn usage(syntax: &str) -> HarperError {
+    HarperError::Validation(format!("Usage: [FIRMWARE {}]", syntax))
+}
+
+fn missing_controller(device: &str, controller: &str) -> HarperError {
+    HarperError::Validation(format!("{} does not provide {}.", device, controller))
+}
+
+/// A byte written as `0x40` or `64`
+fn parse_byte(value: &str) -> HarperResult<u8> {
+    let parsed = match value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+    {
+        Some(hex) => u8::from_str_radix(hex, 16),
+        None => value.parse::<u8>(),
+    };
+    parsed.map_err(|_| HarperError::Validation(format!("Invalid byte: {}", value)))
+}
+
+fn parse_bytes(values: &[&str]) -> HarperResult<Vec<u8>> {
+    values.iter().map(|value| parse_byte(value)).collect()
+}
+
+fn format_bytes(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|byte| format!("0x{:02x}", byte))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// The command of a `[FIRMWARE ...]` call, such as `gpio 4 high`
+fn firmware_command(response: &str) -> &str {
+    response
         .strip_prefix(tools::FIRMWARE)
         .unwrap_or(response)
         .trim()
-        .trim_start_matches(']')
-        .trim();
+        .trim_end_matches(']')
+        .trim()
+}
+
+pub async fn handle_firmware_command(
+    response: &str,
+    devices: Option<&FirmwareDevices>,
+    session_id: Option<&str>,
+) -> HarperResult<String> {
+    let command = firmware_command(response);
 
     let parts: Vec<&str> = command.splitn(2, ' ').collect();
     let action = parts.first().unwrap_or(&"");
-    let args = parts.get(1).unwrap_or(&"");
+    let args = parts.get(1).unwrap_or(&"").trim();
+
+    let Some(devices) = devices else {
+        return Ok("Firmware devices are not available in this session.".to_string());
+    };
 
     match *action {
-        "list" => list_devices(),
-        "info" => device_info(args),
-        "connect" => connect_device(args),
-        "disconnect" => disconnect_device(args),
-        "gpio" => gpio_operation(args),
-        "i2c" => i2c_operation(args),
-        "spi" => spi_operation(args),
-        "uart" => uart_operation(args),
+        "list" => Ok(devices.list(session_id).await),
+        "info" => devices.info(args, session_id).await,
+        "connect" => devices.connect(args, session_id).await,
+        "disconnect" => devices.disconnect(args, session_id).await,
+        "gpio" => devices.gpio(args, session_id).await,
+        "i2c" => devices.i2c(args, session_id).await,
+        "spi" => devices.spi(args, session_id).await,
+        "uart" => devices.uart(args, session_id).await,
         _ => Ok(format!(
             "Unknown firmware command: {}\n\nAvailable commands:\n\
-            - [FIRMWARE list] - List registered devices\n\
+            - [FIRMWARE list] - List configured devices\n\
             - [FIRMWARE info <device>] - Show device info\n\
             - [FIRMWARE connect <device>] - Connect to device\n\
             - [FIRMWARE disconnect <device>] - Disconnect from device\n\
-            - [FIRMWARE gpio <pin> <high|low>] - Set GPIO pin state\n\
-            - [FIRMWARE i2c <device> <read|write> <data>] - I2C operations\n\
-            - [FIRMWARE spi <device> <transfer> <data>] - SPI transfer\n\
-            - [FIRMWARE uart <device> <send> <data>] - Send via UART",
+            - [FIRMWARE gpio [device] <pin> <high|low|toggle|read>] - Drive or read a GPIO pin\n\
+            - [FIRMWARE i2c <device> <scan|read|write> ...] - I2C operations\n\
+            - [FIRMWARE spi <device> <transfer|write|read> ...] - SPI operations\n\
+            - [FIRMWARE uart <device> <send|read> ...] - UART operations",
             action
         )),
     }
 }
 
-fn list_devices() -> HarperResult<String> {
-    Ok("No firmware devices configured.\n\
-        To add devices programmatically:\n\
-        ```rust\n\
-        use harper_firmware::{FirmwareRegistry, Esp32Device};\n\
-        \n\
-        let mut registry = FirmwareRegistry::new();\n\
-        registry.register(\"my-esp32\".to_string(), Box::new(Esp32Device::new(\"/dev/ttyUSB0\", \"my-esp32\")));\n\
-        ```\n\
-        See lib/harper-firmware/examples/basic.rs for more."
-        .to_string())
+/// `firmware`: any `[FIRMWARE ...]` command, such as `i2c` or `connect`
+pub struct FirmwareTool;
+
+impl FirmwareTool {
+    /// Refuse `command` when `[tools]` disables the dedicated tool it
+    /// corresponds to, such as `firmware_gpio` for `gpio 4 high`
+    fn check_routed(command: &str, ctx: &ToolContext<'_>) -> HarperResult<()> {
+        let action = command.split_whitespace().next().unwrap_or_default();
+        ctx.tools
+            .check_routed("firmware", &format!("firmware_{}", action))
+    }
 }
 
-fn device_info(_args: &str) -> HarperResult<String> {
-    Ok("Device info not available - no devices connected.\n\
-        Use [FIRMWARE list] to see available devices."
-        .to_string())
+#[async_trait(?Send)]
+impl Tool for FirmwareTool {
+    fn name(&self) -> &str {
+        "firmware"
+    }
+
+    fn description(&self) -> &str {
+        "Run a firmware command: list, info, connect, disconnect, gpio, i2c, spi or uart"
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "command": {
+                    "type": "string",
+                    "description": "The command and its arguments, for example 'i2c sensor read 0x40'"
+                }
+            },
+            "required": ["command"]
+        })
+    }
+
+    fn risk(&self) -> ToolRisk {
+        ToolRisk::Execute
+    }
+
+    fn bracket_prefix(&self) -> Option<&str> {
+        Some(tools::FIRMWARE)
+    }
+
+    async fn execute(&self, args: &Value, ctx: &ToolContext<'_>) -> HarperResult<Option<String>> {
+        let Some(command) = str_arg(args, "command") else {
+            return Ok(None);
+        };
+        Self::check_routed(command, ctx)?;
+        handle_firmware_command(
+            &format!("[FIRMWARE {}]", command),
+            ctx.firmware,
+            ctx.session_id,
+        )
+        .await
+        .map(Some)
+    }
+
+    async fn execute_bracket(
+        &self,
+        call: &str,
+        ctx: &ToolContext<'_>,
+    ) -> HarperResult<Option<String>> {
+        Self::check_routed(firmware_command(call), ctx)?;
+        handle_firmware_command(call, ctx.firmware, ctx.session_id)
+            .await
+            .map(Some)
+    }
 }
 
-fn connect_device(_args: &str) -> HarperResult<String> {
-    Ok("Connect not implemented - harper-firmware integration is ready but needs runtime device setup.\n\
-        Devices can be registered programmatically through the FirmwareRegistry API.".to_string())
+/// `firmware_list`: list registered devices
+pub struct FirmwareListTool;
+
+#[async_trait(?Send)]
+impl Tool for FirmwareListTool {
+    fn name(&self) -> &str {
+        "firmware_list"
+    }
+
+    fn description(&self) -> &str {
+        "List registered firmware devices"
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {},
+            "required": []
+        })
+    }
+
+    fn risk(&self) -> ToolRisk {
+        ToolRisk::ReadOnly
+    }
+
+    async fn execute(&self, _args: &Value, ctx: &ToolContext<'_>) -> HarperResult<Option<String>> {
+        handle_firmware_command("[FIRMWARE list]", ctx.firmware, ctx.session_id)
+            .await
+            .map(Some)
+    }
 }
 
-fn disconnect_device(_args: &str) -> HarperResult<String> {
-    Ok("Disconnect not implemented - harper-firmware integration is ready but needs runtime device setup.".to_string())
+/// `firmware_info`: describe one device
+pub struct FirmwareInfoTool;
+
+#[async_trait(?Send)]
+impl Tool for FirmwareInfoTool {
+    fn name(&self) -> &str {
+        "firmware_info"
+    }
+
+    fn description(&self) -> &str {
+        "Show information about a firmware device"
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "device": {
+                    "type": "string",
+                    "description": "Name of the device"
+                }
+            },
+            "required": ["device"]
+        })
+    }
+
+    fn risk(&self) -> ToolRisk {
+        ToolRisk::ReadOnly
+    }
+
+    async fn execute(&self, args: &Value, ctx: &ToolContext<'_>) -> HarperResult<Option<String>> {
+        let Some(device) = str_arg(args, "device") else {
+            return Ok(None);
+        };
+        handle_firmware_command(
+            &format!("[FIRMWARE info {}]", device),
+            ctx.firmware,
+            ctx.session_id,
+        )
+        .await
+        .map(Some)
+    }
 }
 
-fn gpio_operation(_args: &str) -> HarperResult<String> {
-    Ok(
-        "GPIO operations ready - harper-firmware traits available for:\n\
-        - Pin configuration (Input, Output, PullUp, PullDown, Analog)\n\
-        - Pin read/write/toggle\n\
-        Implement GpioController trait for your platform."
-            .to_string(),
-    )
+/// `firmware_connect`: open a device for this session
+pub struct FirmwareConnectTool;
+
+#[async_trait(?Send)]
+impl Tool for FirmwareConnectTool {
+    fn name(&self) -> &str {
+        "firmware_connect"
+    }
+
+    fn description(&self) -> &str {
+        "Connect to a firmware device so GPIO, I2C, SPI and UART commands can use it"
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "device": {
+                    "type": "string",
+                    "description": "Name of the device"
+                }
+            },
+            "required": ["device"]
+        })
+    }
+
+    fn risk(&self) -> ToolRisk {
+        ToolRisk::Execute
+    }
+
+    async fn execute(&self, args: &Value, ctx: &ToolContext<'_>) -> HarperResult<Option<String>> {
+        let Some(device) = str_arg(args, "device") else {
+            return Ok(None);
+        };
+        handle_firmware_command(
+            &format!("[FIRMWARE connect {}]", device),
+            ctx.firmware,
+            ctx.session_id,
+        )
+        .await
+        .map(Some)
+    }
 }
 
-fn i2c_operation(_args: &str) -> HarperResult<String> {
-    Ok(
-        "I2C operations ready - harper-firmware traits available for:\n\
-        - I2C read/write/write_read\n\
-        - Device scanning\n\
-        Implement I2cController trait for your platform."
-            .to_string(),
-    )
+/// `firmware_disconnect`: release a device this session connected
+pub struct FirmwareDisconnectTool;
+
+#[async_trait(?Send)]
+impl Tool for FirmwareDisconnectTool {
+    fn name(&self) -> &str {
+        "firmware_disconnect"
+    }
+
+    fn description(&self) -> &str {
+        "Disconnect from a firmware device"
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "device": {
+                    "type": "string",
+                    "description": "Name of the device"
+                }
+            },
+            "required": ["device"]
+        })
+    }
+
+    fn risk(&self) -> ToolRisk {
+        ToolRisk::Execute
+    }
+
+    async fn execute(&self, args: &Value, ctx: &ToolContext<'_>) -> HarperResult<Option<String>> {
+        let Some(device) = str_arg(args, "device") else {
+            return Ok(None);
+        };
+        handle_firmware_command(
+            &format!("[FIRMWARE disconnect {}]", device),
+            ctx.firmware,
+            ctx.session_id,
+        )
+        .await
+        .map(Some)
+    }
 }
 
-fn spi_operation(_args: &str) -> HarperResult<String> {
-    Ok(
-        "SPI operations ready - harper-firmware traits available for:\n\
-        - SPI transfer/write/read\n\
-        - Configurable modes (0-3)\n\
-        Implement SpiController trait for your platform."
-            .to_string(),
-    )
+/// `firmware_gpio`: drive a GPIO pin high or low
+pub struct FirmwareGpioTool;
+
+#[async_trait(?Send)]
+impl Tool for FirmwareGpioTool {
+    fn name(&self) -> &str {
+        "firmware_gpio"
+    }
+
+    fn description(&self) -> &str {
+        "Set the state of a GPIO pin"
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "device": {
+                    "type": "string",
+                    "description": "Name of the device; optional when only one is connected"
+                },
+                "pin": {
+                    "type": "integer",
+                    "description": "GPIO pin number"
+                },
+                "state": {
+                    "type": "boolean",
+                    "description": "true to drive the pin high, false for low"
+                }
+            },
+            "required": ["pin"]
+        })
+    }
+
+    fn risk(&self) -> ToolRisk {
+        ToolRisk::Execute
+    }
+
+    async fn execute(&self, args: &Value, ctx: &ToolContext<'_>) -> HarperResult<Option<String>> {
+        let Some(pin) = args.get("pin").and_then(|v| v.as_i64()) else {
+            return Ok(None);
+        };
+        let device = str_arg(args, "device").unwrap_or_default();
+        let state = match args.get("state") {
+            Some(Value::Bool(false)) => "low",
+            Some(Value::String(state)) if state.eq_ignore_ascii_case("low") => "low",
+            _ => "high",
+        };
+        handle_firmware_command(
+            &format!("[FIRMWARE gpio {} {} {}]", device, pin, state),
+            ctx.firmware,
+            ctx.session_id,
+        )
+        .await
+        .map(Some)
+    }
+}
+
+/// `firmware_flash`: build a device's firmware project and write it to the board
+pub struct FirmwareFlashTool;
+
+#[async_trait(?Send)]
+impl Tool for FirmwareFlashTool {
+    fn name(&self) -> &str {
+        "firmware_flash"
+    }
+
+    fn description(&self) -> &str {
+        "Build the firmware project configured for a device with cargo, PlatformIO or \
+         ESP-IDF and flash it over the device's port"
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "device": {
+                    "type": "string",
+                    "description": "Name of the device"
+                }
+            },
+            "required": ["device"]
+        })
+    }
+
+    fn risk(&self) -> ToolRisk {
+        ToolRisk::Execute
+    }
+
+    async fn execute(&self, args: &Value, ctx: &ToolContext<'_>) -> HarperResult<Option<String>> {
+        let Some(device) = str_arg(args, "device") else {
+            return Ok(None);
+        };
+        let Some(devices) = ctx.firmware else {
+            return Ok(Some(
+                "Firmware devices are not available in this session.".to_string(),
+            ));
+        };
+        devices.flash(device, ctx).await.map(Some)
+    }
 }
 
-fn uart_operation(_args: &str) -> HarperResult<String> {
-    Ok(
-        "UART operations ready - harper-firmware traits available for:\n\
-        - UART read/write/flush\n\
-        - Configurable baud rate, parity, stop bits\n\
-        Implement UartController trait for your platform."
-            .to_string(),
-    )
+/// `firmware_monitor`: tail a device's serial console
+pub struct FirmwareMonitorTool;
+
+#[async_trait(?Send)]
+impl Tool for FirmwareMonitorTool {
+    fn name(&self) -> &str {
+        "firmware_monitor"
+    }
+
+    fn description(&self) -> &str {
+        "Read a device's serial console for a few seconds, e.g. after flashing"
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "device": {
+                    "type": "string",
+                    "description": "Name of the device"
+                },
+                "seconds": {
+                    "type": "integer",
+                    "description": "How long to listen, 10 by default and at most 300"
+                }
+            },
+            "required": ["device"]
+        })
+    }
+
+    fn risk(&self) -> ToolRisk {
+        ToolRisk::Execute
+    }
+
+    async fn execute(&self, args: &Value, ctx: &ToolContext<'_>) -> HarperResult<Option<String>> {
+        let Some(device) = str_arg(args, "device") else {
+            return Ok(None);
+        };
+        let Some(devices) = ctx.firmware else {
+            return Ok(Some(
+                "Firmware devices are not available in this session.".to_string(),
+            ));
+        };
+        let seconds = args
+            .get("seconds")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(DEFAULT_MONITOR_SECS);
+        devices.monitor(device, seconds, ctx).await.map(Some)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use harper_firmware::{
+        AdcController, DelayController, DeviceInfo, GpioController, I2cController, PinConfig,
+        PwmController, SpiController, UartController,
+    };
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    #[derive(Default)]
+    struct MockGpio {
+        pins: std::sync::Mutex<HashMap<u32, PinState>>,
+    }
+
+    #[async_trait]
+    impl GpioController for MockGpio {
+        async fn configure_pin(&self, _config: PinConfig) -> harper_firmware::Result<()> {
+            Ok(())
+        }
+
+        async fn write_pin(&self, pin: u32, state: PinState) -> harper_firmware::Result<()> {
+            self.pins.lock().unwrap().insert(pin, state);
+            Ok(())
+        }
+
+        async fn read_pin(&self, pin: u32) -> harper_firmware::Result<PinState> {
+            Ok(*self
+                .pins
+                .lock()
+                .unwrap()
+                .get(&pin)
+                .unwrap_or(&PinState::Low))
+        }
+
+        async fn toggle_pin(&self, pin: u32) -> harper_firmware::Result<()> {
+            let mut pins = self.pins.lock().unwrap();
+            let next = match pins.get(&pin) {
+                Some(PinState::High) => PinState::Low,
+                _ => PinState::High,
+            };
+            pins.insert(pin, next);
+            Ok(())
+        }
+    }
+
+    #[derive(Clone, Default)]
+    struct Counters {
+        connects: Arc<AtomicUsize>,
+        disconnects: Arc<AtomicUsize>,
+    }
+
+    struct MockDevice {
+        name: String,
+        gpio: Option<MockGpio>,
+        counters: Counters,
+    }
+
+    #[async_trait]
+    impl FirmwareDevice for MockDevice {
+        fn device_info(&self) -> DeviceInfo {
+            DeviceInfo {
+                name: self.name.clone(),
+                platform: Platform::Esp32,
+                firmware_version: Some("1.0.0".to_string()),
+                capabilities: vec!["gpio".to_string()],
+            }
+        }
+
+        fn gpio(&self) -> Option<&dyn GpioController> {
+            self.gpio.as_ref().map(|gpio| gpio as &dyn GpioController)
+        }
+
+        fn i2c(&self) -> Option<&dyn I2cController> {
+            None
+        }
+
+        fn spi(&self) -> Option<&dyn SpiController> {
+            None
+        }
+
+        fn uart(&self) -> Option<&dyn UartController> {
+            None
+        }
+
+        fn pwm(&self) -> Option<&dyn PwmController> {
+            None
+        }
+
+        fn adc(&self) -> Option<&dyn AdcController> {
+            None
+        }
+
+        fn delay(&self) -> Option<&dyn DelayController> {
+            None
+        }
+
+        async fn connect(&self) -> harper_firmware::Result<()> {
+            self.counters.connects.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+
+        async fn disconnect(&self) -> harper_firmware::Result<()> {
+            self.counters.disconnects.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+
+        async fn is_connected(&self) -> harper_firmware::Result<bool> {
+            Ok(true)
+        }
+
+        async fn reset(&self) -> harper_firmware::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn mock_devices(with_gpio: bool) -> (FirmwareDevices, Counters) {
+        let counters = Counters::default();
+        let mut registry = FirmwareRegistry::new();
+        registry.register(
+            "board".to_string(),
+            Box::new(MockDevice {
+                name: "board".to_string(),
+                gpio: with_gpio.then(MockGpio::default),
+                counters: counters.clone(),
+            }),
+        );
+        (FirmwareDevices::new(registry), counters)
+    }
+
+    async fn run(devices: &FirmwareDevices, command: &str, session: &str) -> HarperResult<String> {
+        handle_firmware_command(command, Some(devices), Some(session)).await
+    }
+
+    #[test]
+    fn from_config_registers_supported_platforms_and_skips_the_rest() {
+        let config = FirmwareConfig {
+            enabled: Some(true),
+            devices: Some(vec![
+                FirmwareDeviceConfig {
+                    name: "esp".to_string(),
+                    platform: "esp32".to_string(),
+                    port: Some("/dev/ttyUSB0".to_string()),
+                    address: None,
+                    peripherals: None,
+                    project: None,
+                    toolchain: None,
+                    chip: None,
+                    baud_rate: None,
+                },
+                FirmwareDeviceConfig {
+                    name: "stm".to_string(),
+                    platform: "stm32".to_string(),
+                    port: None,
+                    address: None,
+                    peripherals: None,
+                    project: None,
+                    toolchain: None,
+                    chip: None,
+                    baud_rate: None,
+                },
+                FirmwareDeviceConfig {
+                    name: "uno".to_string(),
+                    platform: "arduino".to_string(),
+                    port: Some("/dev/ttyACM0".to_string()),
+                    address: None,
+                    peripherals: None,
+                    project: None,
+                    toolchain: None,
+                    chip: None,
+                    baud_rate: None,
+                },
+            ]),
+        };
+
+        let devices = FirmwareDevices::from_config(&config);
+
+        assert_eq!(devices.registry.list_devices(), vec!["esp".to_string()]);
+        assert_eq!(devices.skipped.len(), 2);
+        assert!(devices.skipped[0].1.contains("needs a port"));
+        assert!(devices.skipped[1].1.contains("no driver"));
+    }
+
+    #[test]
+    fn disabled_config_registers_no_devices() {
+        let config = FirmwareConfig {
+            enabled: Some(false),
+            devices: Some(vec![FirmwareDeviceConfig {
+                name: "esp".to_string(),
+                platform: "esp32".to_string(),
+                port: Some("/dev/ttyUSB0".to_string()),
+                address: None,
+                peripherals: None,
+                project: None,
+                toolchain: None,
+                chip: None,
+                baud_rate: None,
+            }]),
+        };
+
+        let devices = FirmwareDevices::from_config(&config);
+
+        assert!(devices.registry.list_devices().is_empty());
+        assert!(devices.skipped.is_empty());
+    }
+
+    #[tokio::test]
+    async fn connections_are_shared_across_sessions() {
+        let (devices, counters) = mock_devices(true);
+
+        run(&devices, "[FIRMWARE connect board]", "a")
+            .await
+            .unwrap();
+        run(&devices, "[FIRMWARE connect board]", "b")
+            .await
+            .unwrap();
+        assert_eq!(counters.connects.load(Ordering::SeqCst), 1);
+        assert!(devices.is_connected("board", Some("a")).await);
+
+        run(&devices, "[FIRMWARE disconnect board]", "a")
+            .await
+            .unwrap();
+        assert_eq!(counters.disconnects.load(Ordering::SeqCst), 0);
+        assert!(!devices.is_connected("board", Some("a")).await);
+        assert!(devices.is_connected("board", Some("b")).await);
+
+        run(&devices, "[FIRMWARE disconnect board]", "b")
+            .await
+            .unwrap();
+        assert_eq!(counters.disconnects.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn ended_sessions_release_their_connections() {
+        let (devices, counters) = mock_devices(true);
+        run(&devices, "[FIRMWARE connect board]", "a")
+            .await
+            .unwrap();
+        run(&devices, "[FIRMWARE connect board]", "b")
+            .await
+            .unwrap();
+
+        assert_eq!(devices.release_session(Some("a")).await, vec!["board"]);
+        assert_eq!(counters.disconnects.load(Ordering::SeqCst), 0);
+        assert!(devices.is_connected("board", Some("b")).await);
+        assert!(devices.release_session(Some("a")).await.is_empty());
+
+        assert_eq!(devices.release_session(Some("b")).await, vec!["board"]);
+        assert_eq!(counters.disconnects.load(Ordering::SeqCst), 1);
+        assert!(devices.connected_devices(Some("b")).await.is_empty());
+
+        run(&devices, "[FIRMWARE connect board]", "c")
+            .await
+            .unwrap();
+        devices.release_all().await;
+        assert_eq!(counters.disconnects.load(Ordering::SeqCst), 2);
+        assert!(!devices.is_connected("board", Some("c")).await);
+    }
+
+    #[tokio::test]
+    async fn gpio_requires_the_session_to_connect_first() {
+        let (devices, _) = mock_devices(true);
+        run(&devices, "[FIRMWARE connect board]", "a")
+            .await
+            .unwrap();
+
+        let err = run(&devices, "[FIRMWARE gpio board 2 high]", "b")
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("[FIRMWARE connect board]"));
+
+        let set = run(&devices, "[FIRMWARE gpio 2 high]", "a").await.unwrap();
+        assert_eq!(set, "board pin 2 set high.");
+        let read = run(&devices, "[FIRMWARE gpio board 2 read]", "a")
+            .await
+            .unwrap();
+        assert_eq!(read, "board pin 2 is High.");
+    }
+
+    #[tokio::test]
+    async fn missing_controller_is_reported() {
+        let (devices, _) = mock_devices(false);
+        run(&devices, "[FIRMWARE connect board]", "a")
+            .await
+            .unwrap();
+
+        let err = run(&devices, "[FIRMWARE gpio board 2 high]", "a")
+            .await
+            .unwrap_err();
+
+        assert!(err.to_string().contains("board does not provide GPIO"));
+    }
+
+    #[tokio::test]
+    async fn list_and_info_describe_the_device() {
+        let (devices, _) = mock_devices(true);
+        run(&devices, "[FIRMWARE connect board]", "a")
+            .await
+            .unwrap();
+
+        let list = run(&devices, "[FIRMWARE list]", "a").await.unwrap();
+        assert!(list.contains("- board (Esp32) connected"));
+        let other = run(&devices, "[FIRMWARE list]", "b").await.unwrap();
+        assert!(other.contains("- board (Esp32) disconnected"));
+
+        let info = run(&devices, "[FIRMWARE info board]", "a").await.unwrap();
+        assert!(info.contains("Firmware version: 1.0.0"));
+        assert!(info.contains("Controllers: gpio"));
+        assert!(info.contains("Connected: yes"));
+    }
+
+    #[tokio::test]
+    async fn virtual_device_from_config_runs_every_command() {
+        let dir = tempfile::tempdir().unwrap();
+        let peripherals = dir.path().join("bench.toml");
+        std::fs::write(
+            &peripherals,
+            "[[i2c]]\naddress = 0x48\nregisters = { \"0x00\" = [0x19, 0x60] }\n",
+        )
+        .unwrap();
+        let devices = FirmwareDevices::from_config(&FirmwareConfig {
+            enabled: Some(true),
+            devices: Some(vec![FirmwareDeviceConfig {
+                name: "bench".to_string(),
+                platform: "virtual".to_string(),
+                port: None,
+                address: None,
+                peripherals: Some(peripherals.display().to_string()),
+                project: None,
+                toolchain: None,
+                chip: None,
+                baud_rate: None,
+            }]),
+        });
+
+        run(&devices, "[FIRMWARE connect bench]", "a")
+            .await
+            .unwrap();
+        let gpio = run(&devices, "[FIRMWARE gpio 5 high]", "a").await.unwrap();
+        let scan = run(&devices, "[FIRMWARE i2c bench scan]", "a")
+            .await
+            .unwrap();
+        let read = run(&devices, "[FIRMWARE i2c bench read 0x48 2]", "a")
+            .await
+            .unwrap();
+        let spi = run(&devices, "[FIRMWARE spi bench transfer 1 2]", "a")
+            .await
+            .unwrap();
+        run(&devices, "[FIRMWARE uart bench send hi there]", "a")
+            .await
+            .unwrap();
+        let uart = run(&devices, "[FIRMWARE uart bench read]", "a")
+            .await
+            .unwrap();
+
+        assert_eq!(gpio, "bench pin 5 set high.");
+        assert_eq!(scan, "I2C devices on bench: 0x48");
+        assert_eq!(read, "Read from 0x48: 0x19 0x60");
+        assert_eq!(spi, "Received: 0x01 0x02");
+        assert_eq!(uart, "Received 8 bytes: hi there");
+    }
+
+    #[test]
+    fn bytes_parse_as_hex_or_decimal() {
+        assert_eq!(
+            parse_bytes(&["0x40", "64", "0XFF"]).unwrap(),
+            vec![64, 64, 255]
+        );
+        assert!(parse_byte("256").is_err());
+        assert_eq!(format_bytes(&[0x0a, 0xff]), "0x0a 0xff");
+    }
+
+    /// Records command output chunks as `(chunk, is_error, done)`
+    #[derive(Default)]
+    struct RecordingEvents {
+        output: std::sync::Mutex<Vec<(String, bool, bool)>>,
+    }
+
+    #[async_trait]
+    impl RuntimeEventSink for RecordingEvents {
+        async fn plan_updated(
+            &self,
+            _session_id: &str,
+            _plan: Option<crate::core::plan::PlanState>,
+        ) -> HarperResult<()> {
+            Ok(())
+        }
+
+        async fn agents_updated(
+            &self,
+            _session_id: &str,
+            _agents: Option<crate::core::agents::ResolvedAgents>,
+        ) -> HarperResult<()> {
+            Ok(())
+        }
+
+        async fn command_output_updated(
+            &self,
+            _session_id: &str,
+            _command: String,
+            chunk: String,
+            is_error: bool,
+            done: bool,
+        ) -> HarperResult<()> {
+            self.output.lock().unwrap().push((chunk, is_error, done));
+            Ok(())
+        }
+
+        async fn activity_updated(
+            &self,
+            _session_id: &str,
+            _status: Option<String>,
+        ) -> HarperResult<()> {
+            Ok(())
+        }
+
+        async fn assistant_output_updated(
+            &self,
+            _session_id: &str,
+            _chunk: String,
+            _done: bool,
+        ) -> HarperResult<()> {
+            Ok(())
+        }
+    }
+
+    /// What a `ToolContext` borrows, with a plan step in progress for `session`
+    struct Session {
+        client: reqwest::Client,
+        conn: Connection,
+        config: crate::core::ApiConfig,
+        tools: crate::tools::registry::ToolRegistry,
+        exec_policy: crate::runtime::config::ExecPolicyConfig,
+    }
+
+    const SESSION: &str = "firmware-session";
+
+    impl Session {
+        fn new() -> Self {
+            use crate::core::plan::{PlanItem, PlanState, PlanStepStatus};
+
+            let conn = Connection::open_in_memory().unwrap();
+            crate::memory::storage::init_db(&conn).unwrap();
+            crate::memory::storage::save_plan_state(
+                &conn,
+                SESSION,
+                &PlanState {
+                    explanation: None,
+                    items: vec![PlanItem {
+                        step: "Flash the board".to_string(),
+                        status: PlanStepStatus::InProgress,
+                        job_id: None,
+                    }],
+                    runtime: None,
+                    updated_at: None,
+                },
+            )
+            .unwrap();
+            Self {
+                client: reqwest::Client::new(),
+                conn,
+                config: crate::core::ApiConfig {
+                    provider: crate::core::ApiProvider::OpenAI,
+                    api_key: "test-key".to_string(),
+                    base_url: "https://api.openai.com/v1/chat/completions".to_string(),
+                    model_name: "gpt-5.5".to_string(),
+                    headers: Default::default(),
+                    parameters: Default::default(),
+                    fallback: Default::default(),
+                },
+                tools: Default::default(),
+                exec_policy: Default::default(),
+            }
+        }
+
+        fn ctx<'a>(
+            &'a self,
+            devices: &'a FirmwareDevices,
+            approver: Option<Arc<dyn UserApproval>>,
+            runtime_events: Option<Arc<dyn RuntimeEventSink>>,
+        ) -> ToolContext<'a> {
+            ToolContext {
+                client: &self.client,
+                conn: &self.conn,
+                config: &self.config,
+                tools: &self.tools,
+                exec_policy: &self.exec_policy,
+                session_id: Some(SESSION),
+                approver,
+                runtime_events,
+                web_search_enabled: false,
+                firmware: Some(devices),
+            }
+        }
+
+        fn jobs(&self) -> Vec<crate::core::plan::PlanJobRecord> {
+            crate::memory::storage::load_plan_state(&self.conn, SESSION)
+                .unwrap()
+                .and_then(|plan| plan.runtime)
+                .map(|runtime| runtime.jobs)
+                .unwrap_or_default()
+        }
+    }
+
+    fn serial_devices(port: Option<&str>, project: Option<&Path>) -> FirmwareDevices {
+        FirmwareDevices::from_config(&FirmwareConfig {
+            enabled: Some(true),
+            devices: Some(vec![FirmwareDeviceConfig {
+                name: "board".to_string(),
+                platform: "virtual".to_string(),
+                port: port.map(str::to_string),
+                address: None,
+                peripherals: None,
+                project: project.map(|path| path.display().to_string()),
+                toolchain: None,
+                chip: None,
+                baud_rate: None,
+            }]),
+        })
+    }
+
+    #[test]
+    fn toolchains_are_detected_and_turned_into_flash_commands() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(Toolchain::detect(dir.path()), None);
+        std::fs::write(dir.path().join("CMakeLists.txt"), "").unwrap();
+        assert_eq!(Toolchain::detect(dir.path()), None);
+        std::fs::write(dir.path().join("sdkconfig"), "").unwrap();
+        assert_eq!(Toolchain::detect(dir.path()), Some(Toolchain::EspIdf));
+        std::fs::write(dir.path().join("Cargo.toml"), "").unwrap();
+        assert_eq!(Toolchain::detect(dir.path()), Some(Toolchain::Cargo));
+        std::fs::write(dir.path().join("platformio.ini"), "").unwrap();
+        assert_eq!(Toolchain::detect(dir.path()), Some(Toolchain::PlatformIo));
+        assert_eq!(Toolchain::parse("idf.py"), Some(Toolchain::EspIdf));
+        assert_eq!(Toolchain::parse("make"), None);
+
+        let join =
+            |steps: Vec<Vec<String>>| steps.iter().map(|step| step.join(" ")).collect::<Vec<_>>();
+        assert_eq!(
+            join(
+                Toolchain::Cargo
+                    .flash_steps(Platform::Esp32, "/dev/ttyUSB0", None)
+                    .unwrap()
+            ),
+            [
+                "cargo build --release",
+                "cargo espflash flash --release --port /dev/ttyUSB0"
+            ]
+        );
+        assert_eq!(
+            join(
+                Toolchain::Cargo
+                    .flash_steps(Platform::Stm32, "/dev/ttyACM0", Some("STM32F411CEUx"))
+                    .unwrap()
+            )[1],
+            "cargo flash --release --chip STM32F411CEUx"
+        );
+        assert!(Toolchain::Cargo
+            .flash_steps(Platform::Stm32, "/dev/ttyACM0", None)
+            .is_err());
+        assert_eq!(
+            join(
+                Toolchain::EspIdf
+                    .flash_steps(Platform::Esp32, "/dev/ttyUSB0", None)
+                    .unwrap()
+            ),
+            ["idf.py build", "idf.py -p /dev/ttyUSB0 flash"]
+        );
+    }
+
+    #[tokio::test]
+    async fn firmware_commands_follow_their_dedicated_tools_filters() {
+        let mut session = Session::new();
+        session.tools =
+            crate::tools::registry::ToolRegistry::new(None, vec!["firmware_gpio".to_string()]);
+        let devices = FirmwareDevices::new(FirmwareRegistry::new());
+        let ctx = session.ctx(&devices, None, None);
+
+        let err = FirmwareTool
+            .execute_bracket("[FIRMWARE gpio 4 high]", &ctx)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("Tool 'firmware_gpio' is disabled"));
+        assert!(FirmwareTool
+            .execute(&json!({"command": "gpio 4 high"}), &ctx)
+            .await
+            .is_err());
+        assert!(FirmwareTool
+            .execute_bracket("[FIRMWARE list]", &ctx)
+            .await
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn flash_needs_a_free_port_and_a_project() {
+        let session = Session::new();
+        let dir = tempfile::tempdir().unwrap();
+
+        let devices = serial_devices(None, Some(dir.path()));
+        let err = devices
+            .flash("board", &session.ctx(&devices, None, None))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("no port configured"));
+        assert!(devices
+            .flash("other", &session.ctx(&devices, None, None))
+            .await
+            .is_err());
+
+        let devices = serial_devices(Some("/dev/ttyUSB0"), None);
+        let err = devices
+            .flash("board", &session.ctx(&devices, None, None))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("no project configured"));
+
+        let devices = serial_devices(Some("/dev/ttyUSB0"), Some(dir.path()));
+        let err = devices
+            .flash("board", &session.ctx(&devices, None, None))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("Could not tell how to build"));
+
+        run(&devices, "[FIRMWARE connect board]", "a")
+            .await
+            .unwrap();
+        let err = devices
+            .monitor("board", 1, &session.ctx(&devices, None, None))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("[FIRMWARE disconnect board]"));
+
+        let devices = FirmwareDevices::from_config(&FirmwareConfig {
+            enabled: Some(true),
+            devices: Some(vec![FirmwareDeviceConfig {
+                name: "pico".to_string(),
+                platform: "pico".to_string(),
+                port: Some("/dev/spidev0.0".to_string()),
+                address: None,
+                peripherals: None,
+                project: Some(dir.path().display().to_string()),
+                toolchain: None,
+                chip: None,
+                baud_rate: None,
+            }]),
+        });
+        let ctx = session.ctx(&devices, None, None);
+        let err = devices.flash("pico", &ctx).await.unwrap_err();
+        assert!(err.to_string().contains("not a serial console"));
+        let err = devices.monitor("pico", 1, &ctx).await.unwrap_err();
+        assert!(err.to_string().contains("not a serial console"));
+        assert!(session.jobs().is_empty());
+    }
+
+    #[tokio::test]
+    async fn declined_flash_blocks_the_plan_job() {
+        let session = Session::new();
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("platformio.ini"), "[env:esp32dev]\n").unwrap();
+        let devices = serial_devices(Some("/dev/ttyUSB0"), Some(dir.path()));
+
+        let result = FirmwareFlashTool
+            .execute(
+                &json!({"device": "board"}),
+                &session.ctx(
+                    &devices,
+                    Some(Arc::new(crate::core::io_traits::DenyApproval)),
+                    None,
+                ),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(result.as_deref(), Some("Flashing board cancelled by user"));
+        let jobs = session.jobs();
+        assert_eq!(jobs.len(), 1);
+        assert_eq!(jobs[0].tool, "firmware_flash");
+        assert_eq!(jobs[0].status, PlanJobStatus::Blocked);
+        assert_eq!(
+            jobs[0].command.as_deref(),
+            Some("pio run && pio run --target upload --upload-port /dev/ttyUSB0")
+        );
+    }
+
+    #[tokio::test]
+    async fn flash_steps_follow_the_exec_policy_and_the_command_log() {
+        let mut session = Session::new();
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("platformio.ini"), "[env:esp32dev]\n").unwrap();
+        let devices = serial_devices(Some("/dev/ttyUSB0"), Some(dir.path()));
+        let logs = |session: &Session| {
+            crate::memory::storage::load_command_logs_for_session(&session.conn, SESSION, 10)
+                .unwrap()
+        };
+
+        session.exec_policy.blocked_commands = Some(vec!["pio run --target upload".to_string()]);
+        let err = devices
+            .flash("board", &session.ctx(&devices, None, None))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("blocked by exec policy"));
+        assert!(session.jobs().is_empty());
+        let entries = logs(&session);
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].source, "firmware_flash");
+        assert_eq!(entries[0].status, "blocked");
+        assert_eq!(
+            entries[0].command,
+            "pio run --target upload --upload-port /dev/ttyUSB0"
+        );
+
+        // Without PlatformIO installed the build step fails to start
+        session.exec_policy.blocked_commands = None;
+        session.exec_policy.approval_profile =
+            Some(crate::runtime::config::ApprovalProfile::AllowAll);
+        assert!(devices
+            .flash("board", &session.ctx(&devices, None, None))
+            .await
+            .is_err());
+        let entries = logs(&session);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].command, "pio run");
+        assert_eq!(entries[0].status, "failed");
+        assert!(!entries[0].requires_approval);
+    }
+
+    #[tokio::test]
+    async fn sandboxed_flash_steps_declare_the_port() {
+        let dir = tempfile::tempdir().unwrap();
+        let session = Session::new();
+        let devices = FirmwareDevices::new(FirmwareRegistry::new());
+        let ctx = session.ctx(&devices, None, None);
+        let sandbox = Sandbox::new(harper_sandbox::SandboxConfig {
+            enabled: true,
+            allowed_dirs: vec![dir.path().to_path_buf()],
+            writable_dirs: vec![dir.path().to_path_buf()],
+            ..Default::default()
+        });
+
+        let mut output = JobOutput::new(&ctx, "firmware_flash", "flash".to_string());
+        let step = ["pio", "run", "--upload-port", "/dev/ttyUSB0"]
+            .map(str::to_string)
+            .to_vec();
+        let err = run_step(
+            &step,
+            dir.path(),
+            "/dev/ttyUSB0",
+            Some(&sandbox),
+            &mut output,
+        )
+        .await
+        .unwrap_err();
+        assert!(err.to_string().contains("/dev/ttyUSB0"));
+    }
+
+    #[tokio::test]
+    async fn build_output_streams_to_the_sink_and_the_plan_job() {
+        let session = Session::new();
+        let devices = FirmwareDevices::new(FirmwareRegistry::new());
+        let events = Arc::new(RecordingEvents::default());
+        let ctx = session.ctx(&devices, None, Some(events.clone()));
+        let dir = tempfile::tempdir().unwrap();
+
+        let mut output = JobOutput::new(&ctx, "firmware_flash", "build".to_string());
+        output
+            .start(PlanJobStatus::Running, "flashing".to_string())
+            .await;
+        let step = ["sh", "-c", "echo compiling; echo warning >&2; exit 3"]
+            .map(str::to_string)
+            .to_vec();
+        let status = run_step(&step, dir.path(), "/dev/ttyUSB0", None, &mut output)
+            .await
+            .unwrap();
+        output.finish(PlanJobStatus::Failed).await;
+
+        assert_eq!(status.code(), Some(3));
+        let chunks = events.output.lock().unwrap().clone();
+        assert!(chunks.contains(&("compiling\n".to_string(), false, false)));
+        assert!(chunks.contains(&("warning\n".to_string(), true, false)));
+        assert_eq!(chunks.last(), Some(&(String::new(), true, true)));
+
+        let jobs = session.jobs();
+        assert_eq!(jobs[0].status, PlanJobStatus::Failed);
+        assert!(jobs[0].output_transcript.contains("compiling\n"));
+        assert!(jobs[0].output_transcript.contains("warning\n"));
+        assert!(jobs[0].has_error_output);
+    }
+
+    #[tokio::test]
+    async fn monitor_of_a_missing_port_fails_its_plan_job() {
+        let session = Session::new();
+        let devices = serial_devices(Some("/nonexistent/ttyUSB9"), None);
+
+        let err = FirmwareMonitorTool
+            .execute(
+                &json!({"device": "board", "seconds": 1}),
+                &session.ctx(&devices, None, None),
+            )
+            .await
+            .unwrap_err();
+
+        assert!(err.to_string().contains("ttyUSB9"));
+        let jobs = session.jobs();
+        assert_eq!(jobs[0].tool, "firmware_monitor");
+        assert_eq!(jobs[0].status, PlanJobStatus::Failed);
+        assert!(jobs[0].output_transcript.contains("ttyUSB9"));
+    }
 }
@@ -17,12 +17,15 @@
 //! This module provides functionality for git operations
 //! with safety checks and user approval.
 
-use crate::core::error::HarperError;
+use crate::core::error::{HarperError, HarperResult};
 use crate::core::ApiConfig;
 use crate::runtime::config::ExecPolicyConfig;
 use crate::tools::parsing;
+use crate::tools::registry::{str_arg, Tool, ToolContext, ToolRisk};
 use crate::tools::shell::{self, CommandAuditContext};
+use async_trait::async_trait;
 use colored::*;
+use serde_json::{json, Value};
 use std::collections::HashSet;
 use std::io;
 use std::path::Path;
@@ -489,3 +492,247 @@ fn format_changed_files_response(
     }
     response.trim_end().to_string()
 }
+
+/// `git_status`: show the working tree status
+pub struct GitStatusTool;
+
+#[async_trait(?Send)]
+impl Tool for GitStatusTool {
+    fn name(&self) -> &str {
+        "git_status"
+    }
+
+    fn description(&self) -> &str {
+        "Get the current git status"
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {},
+            "required": []
+        })
+    }
+
+    fn risk(&self) -> ToolRisk {
+        ToolRisk::ReadOnly
+    }
+
+    fn bracket_prefix(&self) -> Option<&str> {
+        Some("[GIT_STATUS]")
+    }
+
+    async fn execute(&self, _args: &Value, _ctx: &ToolContext<'_>) -> HarperResult<Option<String>> {
+        git_status().map(Some)
+    }
+
+    async fn execute_bracket(
+        &self,
+        _call: &str,
+        _ctx: &ToolContext<'_>,
+    ) -> HarperResult<Option<String>> {
+        git_status().map(Some)
+    }
+}
+
+/// `git_diff`: show unstaged changes
+pub struct GitDiffTool;
+
+#[async_trait(?Send)]
+impl Tool for GitDiffTool {
+    fn name(&self) -> &str {
+        "git_diff"
+    }
+
+    fn description(&self) -> &str {
+        "Get the git diff"
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {},
+            "required": []
+        })
+    }
+
+    fn risk(&self) -> ToolRisk {
+        ToolRisk::ReadOnly
+    }
+
+    fn bracket_prefix(&self) -> Option<&str> {
+        Some("[GIT_DIFF]")
+    }
+
+    async fn execute(&self, _args: &Value, _ctx: &ToolContext<'_>) -> HarperResult<Option<String>> {
+        git_diff().map(Some)
+    }
+
+    async fn execute_bracket(
+        &self,
+        _call: &str,
+        _ctx: &ToolContext<'_>,
+    ) -> HarperResult<Option<String>> {
+        git_diff().map(Some)
+    }
+}
+
+/// `git_commit`: commit the staged changes
+pub struct GitCommitTool;
+
+#[async_trait(?Send)]
+impl Tool for GitCommitTool {
+    fn name(&self) -> &str {
+        "git_commit"
+    }
+
+    fn description(&self) -> &str {
+        "Commit changes with a message"
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "message": {
+                    "type": "string",
+                    "description": "The commit message"
+                }
+            },
+            "required": ["message"]
+        })
+    }
+
+    fn risk(&self) -> ToolRisk {
+        ToolRisk::Write
+    }
+
+    fn bracket_prefix(&self) -> Option<&str> {
+        Some("[GIT_COMMIT")
+    }
+
+    async fn execute(&self, args: &Value, ctx: &ToolContext<'_>) -> HarperResult<Option<String>> {
+        let Some(message) = str_arg(args, "message") else {
+            return Ok(None);
+        };
+        let bracket_command = format!("[GIT_COMMIT {}]", message);
+        git_commit(&bracket_command, ctx.approver.clone())
+            .await
+            .map(Some)
+    }
+
+    async fn execute_bracket(
+        &self,
+        call: &str,
+        ctx: &ToolContext<'_>,
+    ) -> HarperResult<Option<String>> {
+        git_commit(call, ctx.approver.clone()).await.map(Some)
+    }
+}
+
+/// `git_add`: stage files, or everything when none are named
+pub struct GitAddTool;
+
+#[async_trait(?Send)]
+impl Tool for GitAddTool {
+    fn name(&self) -> &str {
+        "git_add"
+    }
+
+    fn description(&self) -> &str {
+        "Add files to git staging"
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "files": {
+                    "type": "string",
+                    "description": "Files to add (space-separated)"
+                }
+            },
+            "required": ["files"]
+        })
+    }
+
+    fn risk(&self) -> ToolRisk {
+        ToolRisk::Write
+    }
+
+    fn bracket_prefix(&self) -> Option<&str> {
+        Some("[GIT_ADD")
+    }
+
+    async fn execute(&self, args: &Value, ctx: &ToolContext<'_>) -> HarperResult<Option<String>> {
+        let bracket_command = format!("[GIT_ADD {}]", str_arg(args, "files").unwrap_or("."));
+        git_add(&bracket_command, ctx.approver.clone())
+            .await
+            .map(Some)
+    }
+
+    async fn execute_bracket(
+        &self,
+        call: &str,
+        ctx: &ToolContext<'_>,
+    ) -> HarperResult<Option<String>> {
+        git_add(call, ctx.approver.clone()).await.map(Some)
+    }
+}
+
+/// `list_changed_files`: list changed files, run through the exec policy
+pub struct ListChangedFilesTool;
+
+#[async_trait(?Send)]
+impl Tool for ListChangedFilesTool {
+    fn name(&self) -> &str {
+        "list_changed_files"
+    }
+
+    fn description(&self) -> &str {
+        "List changed files with optional filters"
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "ext": {
+                    "type": "string",
+                    "description": "Optional file extension filter, for example 'rs'"
+                },
+                "tracked_only": {
+                    "type": "boolean",
+                    "description": "When true, exclude untracked files"
+                },
+                "since": {
+                    "type": "string",
+                    "description": "Optional git --since expression, for example 'today'"
+                }
+            },
+            "required": []
+        })
+    }
+
+    fn risk(&self) -> ToolRisk {
+        ToolRisk::ReadOnly
+    }
+
+    async fn execute(&self, args: &Value, ctx: &ToolContext<'_>) -> HarperResult<Option<String>> {
+        let tracked_only = args
+            .get("tracked_only")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+        list_changed_files_with_policy(
+            ctx.config,
+            ctx.exec_policy,
+            Some(&ctx.audit("tool_list_changed_files")),
+            ctx.approver.clone(),
+            str_arg(args, "ext"),
+            tracked_only,
+            str_arg(args, "since"),
+        )
+        .await
+        .map(Some)
+    }
+}
@@ -16,26 +16,54 @@
 //!
 //! This module provides functionality for GitHub operations like creating issues and PRs.
 
-use crate::core::error::HarperError;
+use crate::core::constants::tools;
+use crate::core::error::{HarperError, HarperResult};
+use crate::core::io_traits::UserApproval;
 use crate::tools::parsing;
+use crate::tools::registry::{str_arg, Tool, ToolContext, ToolRisk};
+use async_trait::async_trait;
 use colored::*;
+use serde_json::{json, Value};
 use std::process::Command;
+use std::sync::Arc;
 
-/// Create a GitHub issue
-pub fn create_issue(response: &str) -> crate::core::error::HarperResult<String> {
-    let args = parsing::extract_tool_args(response, "[GITHUB_ISSUE", 2)?;
-    let title = &args[0];
-    let body = &args[1];
-
+/// Ask before running `gh`, through the approver or on stdin
+async fn confirm(
+    approver: Option<Arc<dyn UserApproval>>,
+    prompt: &str,
+    detail: &str,
+) -> HarperResult<bool> {
+    if let Some(appr) = approver {
+        return appr.approve(prompt, detail).await;
+    }
     println!(
-        "{} Create GitHub issue '{}' with body '{}' ? (y/n): ",
+        "{} {} {} ? (y/n): ",
         "System:".bold().magenta(),
-        title.magenta(),
-        body.magenta()
+        prompt,
+        detail.magenta()
     );
     let mut approval = String::new();
     std::io::stdin().read_line(&mut approval)?;
-    if !approval.trim().eq_ignore_ascii_case("y") {
+    Ok(approval.trim().eq_ignore_ascii_case("y"))
+}
+
+/// Create a GitHub issue
+pub async fn create_issue(
+    response: &str,
+    approver: Option<Arc<dyn UserApproval>>,
+) -> HarperResult<String> {
+    let args = parsing::extract_tool_args(response, "[GITHUB_ISSUE", 2)?;
+    create_issue_direct(&args[0], &args[1], approver).await
+}
+
+/// Create a GitHub issue, with the arguments already split
+pub async fn create_issue_direct(
+    title: &str,
+    body: &str,
+    approver: Option<Arc<dyn UserApproval>>,
+) -> HarperResult<String> {
+    let detail = format!("'{}' with body '{}'", title, body);
+    if !confirm(approver, "Create GitHub issue", &detail).await? {
         return Ok("Issue creation cancelled by user".to_string());
     }
 
@@ -63,21 +91,23 @@ pub fn create_issue(response: &str) -> crate::core::error::HarperResult<String>
 }
 
 /// Create a GitHub pull request
-pub fn create_pr(response: &str) -> crate::core::error::HarperResult<String> {
+pub async fn create_pr(
+    response: &str,
+    approver: Option<Arc<dyn UserApproval>>,
+) -> HarperResult<String> {
     let args = parsing::extract_tool_args(response, "[GITHUB_PR", 3)?;
-    let title = &args[0];
-    let body = &args[1];
-    let branch = &args[2];
+    create_pr_direct(&args[0], &args[1], &args[2], approver).await
+}
 
-    println!(
-        "{} Create PR '{}' from branch '{}' ? (y/n): ",
-        "System:".bold().magenta(),
-        title.magenta(),
-        branch.magenta()
-    );
-    let mut approval = String::new();
-    std::io::stdin().read_line(&mut approval)?;
-    if !approval.trim().eq_ignore_ascii_case("y") {
+/// Create a GitHub pull request, with the arguments already split
+pub async fn create_pr_direct(
+    title: &str,
+    body: &str,
+    branch: &str,
+    approver: Option<Arc<dyn UserApproval>>,
+) -> HarperResult<String> {
+    let detail = format!("'{}' from branch '{}'", title, branch);
+    if !confirm(approver, "Create PR", &detail).await? {
         return Ok("PR creation cancelled by user".to_string());
     }
 
@@ -105,3 +135,123 @@ pub fn create_pr(response: &str) -> crate::core::error::HarperResult<String> {
         )))
     }
 }
+
+/// `github_issue`: open an issue with the `gh` CLI
+pub struct GithubIssueTool;
+
+#[async_trait(?Send)]
+impl Tool for GithubIssueTool {
+    fn name(&self) -> &str {
+        "github_issue"
+    }
+
+    fn description(&self) -> &str {
+        "Create a GitHub issue in the current repository"
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "title": {
+                    "type": "string",
+                    "description": "Issue title"
+                },
+                "body": {
+                    "type": "string",
+                    "description": "Issue body"
+                }
+            },
+            "required": ["title", "body"]
+        })
+    }
+
+    fn risk(&self) -> ToolRisk {
+        ToolRisk::Execute
+    }
+
+    fn bracket_prefix(&self) -> Option<&str> {
+        Some(tools::GITHUB_ISSUE)
+    }
+
+    async fn execute(&self, args: &Value, ctx: &ToolContext<'_>) -> HarperResult<Option<String>> {
+        let (Some(title), Some(body)) = (str_arg(args, "title"), str_arg(args, "body")) else {
+            return Ok(None);
+        };
+        create_issue_direct(title, body, ctx.approver.clone())
+            .await
+            .map(Some)
+    }
+
+    async fn execute_bracket(
+        &self,
+        call: &str,
+        ctx: &ToolContext<'_>,
+    ) -> HarperResult<Option<String>> {
+        create_issue(call, ctx.approver.clone()).await.map(Some)
+    }
+}
+
+/// `github_pr`: open a pull request with the `gh` CLI
+pub struct GithubPrTool;
+
+#[async_trait(?Send)]
+impl Tool for GithubPrTool {
+    fn name(&self) -> &str {
+        "github_pr"
+    }
+
+    fn description(&self) -> &str {
+        "Create a GitHub pull request from a branch"
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "title": {
+                    "type": "string",
+                    "description": "Pull request title"
+                },
+                "body": {
+                    "type": "string",
+                    "description": "Pull request body"
+                },
+                "branch": {
+                    "type": "string",
+                    "description": "Branch to open the pull request from"
+                }
+            },
+            "required": ["title", "body", "branch"]
+        })
+    }
+
+    fn risk(&self) -> ToolRisk {
+        ToolRisk::Execute
+    }
+
+    fn bracket_prefix(&self) -> Option<&str> {
+        Some(tools::GITHUB_PR)
+    }
+
+    async fn execute(&self, args: &Value, ctx: &ToolContext<'_>) -> HarperResult<Option<String>> {
+        let (Some(title), Some(body), Some(branch)) = (
+            str_arg(args, "title"),
+            str_arg(args, "body"),
+            str_arg(args, "branch"),
+        ) else {
+            return Ok(None);
+        };
+        create_pr_direct(title, body, branch, ctx.approver.clone())
+            .await
+            .map(Some)
+    }
+
+    async fn execute_bracket(
+        &self,
+        call: &str,
+        ctx: &ToolContext<'_>,
+    ) -> HarperResult<Option<String>> {
+        create_pr(call, ctx.approver.clone()).await.map(Some)
+    }
+}
@@ -16,10 +16,16 @@
 //!
 //! This module provides functionality for image information and processing.
 
-use crate::core::error::HarperError;
+use crate::core::constants::tools;
+use crate::core::error::{HarperError, HarperResult};
+use crate::core::io_traits::UserApproval;
 use crate::tools::parsing;
+use crate::tools::registry::{str_arg, Tool, ToolContext, ToolRisk};
+use async_trait::async_trait;
 use colored::*;
 use image::GenericImageView;
+use serde_json::{json, Value};
+use std::sync::Arc;
 
 /// Get image information
 pub fn get_image_info(response: &str) -> crate::core::error::HarperResult<String> {
@@ -44,28 +50,48 @@ pub fn get_image_info(response: &str) -> crate::core::error::HarperResult<String
 }
 
 /// Resize image
-pub fn resize_image(response: &str) -> crate::core::error::HarperResult<String> {
+pub async fn resize_image(
+    response: &str,
+    approver: Option<Arc<dyn UserApproval>>,
+) -> HarperResult<String> {
     let args = parsing::extract_tool_args(response, "[IMAGE_RESIZE", 4)?;
-    let input_path = &args[0];
-    let output_path = &args[1];
     let width: u32 = args[2]
         .parse()
         .map_err(|_| HarperError::Command("Invalid width".to_string()))?;
     let height: u32 = args[3]
         .parse()
         .map_err(|_| HarperError::Command("Invalid height".to_string()))?;
+    resize_image_direct(&args[0], &args[1], width, height, approver).await
+}
 
-    println!(
-        "{} Resize image {} to {}x{} and save to {} ? (y/n): ",
-        "System:".bold().magenta(),
-        input_path.magenta(),
-        width,
-        height,
-        output_path.magenta()
-    );
-    let mut approval = String::new();
-    std::io::stdin().read_line(&mut approval)?;
-    if !approval.trim().eq_ignore_ascii_case("y") {
+/// Resize an image, with the arguments already parsed
+pub async fn resize_image_direct(
+    input_path: &str,
+    output_path: &str,
+    width: u32,
+    height: u32,
+    approver: Option<Arc<dyn UserApproval>>,
+) -> HarperResult<String> {
+    let is_approved = if let Some(appr) = approver {
+        appr.approve(
+            &format!("Resize image to {}x{}?", width, height),
+            &format!("{} -> {}", input_path, output_path),
+        )
+        .await?
+    } else {
+        println!(
+            "{} Resize image {} to {}x{} and save to {} ? (y/n): ",
+            "System:".bold().magenta(),
+            input_path.magenta(),
+            width,
+            height,
+            output_path.magenta()
+        );
+        let mut approval = String::new();
+        std::io::stdin().read_line(&mut approval)?;
+        approval.trim().eq_ignore_ascii_case("y")
+    };
+    if !is_approved {
         return Ok("Image resize cancelled by user".to_string());
     }
 
@@ -89,3 +115,127 @@ pub fn resize_image(response: &str) -> crate::core::error::HarperResult<String>
         width, height, output_path
     ))
 }
+
+/// `image_info`: dimensions and colour type of an image
+pub struct ImageInfoTool;
+
+#[async_trait(?Send)]
+impl Tool for ImageInfoTool {
+    fn name(&self) -> &str {
+        "image_info"
+    }
+
+    fn description(&self) -> &str {
+        "Show the dimensions and color type of an image file"
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "path": {
+                    "type": "string",
+                    "description": "The path to the image"
+                }
+            },
+            "required": ["path"]
+        })
+    }
+
+    fn risk(&self) -> ToolRisk {
+        ToolRisk::ReadOnly
+    }
+
+    fn bracket_prefix(&self) -> Option<&str> {
+        Some(tools::IMAGE_INFO)
+    }
+
+    async fn execute(&self, args: &Value, _ctx: &ToolContext<'_>) -> HarperResult<Option<String>> {
+        let Some(path) = str_arg(args, "path") else {
+            return Ok(None);
+        };
+        get_image_info(&format!("[IMAGE_INFO {}]", path)).map(Some)
+    }
+
+    async fn execute_bracket(
+        &self,
+        call: &str,
+        _ctx: &ToolContext<'_>,
+    ) -> HarperResult<Option<String>> {
+        get_image_info(call).map(Some)
+    }
+}
+
+/// `image_resize`: resize an image and save the result
+pub struct ImageResizeTool;
+
+#[async_trait(?Send)]
+impl Tool for ImageResizeTool {
+    fn name(&self) -> &str {
+        "image_resize"
+    }
+
+    fn description(&self) -> &str {
+        "Resize an image and save it to a new path"
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "input_path": {
+                    "type": "string",
+                    "description": "The image to resize"
+                },
+                "output_path": {
+                    "type": "string",
+                    "description": "Where to save the resized image"
+                },
+                "width": {
+                    "type": "integer",
+                    "description": "Target width in pixels"
+                },
+                "height": {
+                    "type": "integer",
+                    "description": "Target height in pixels"
+                }
+            },
+            "required": ["input_path", "output_path", "width", "height"]
+        })
+    }
+
+    fn risk(&self) -> ToolRisk {
+        ToolRisk::Write
+    }
+
+    fn bracket_prefix(&self) -> Option<&str> {
+        Some(tools::IMAGE_RESIZE)
+    }
+
+    async fn execute(&self, args: &Value, ctx: &ToolContext<'_>) -> HarperResult<Option<String>> {
+        let dimension = |key| {
+            args.get(key)
+                .and_then(|v: &Value| v.as_u64())
+                .and_then(|v| u32::try_from(v).ok())
+        };
+        let (Some(input_path), Some(output_path), Some(width), Some(height)) = (
+            str_arg(args, "input_path"),
+            str_arg(args, "output_path"),
+            dimension("width"),
+            dimension("height"),
+        ) else {
+            return Ok(None);
+        };
+        resize_image_direct(input_path, output_path, width, height, ctx.approver.clone())
+            .await
+            .map(Some)
+    }
+
+    async fn execute_bracket(
+        &self,
+        call: &str,
+        ctx: &ToolContext<'_>,
+    ) -> HarperResult<Option<String>> {
+        resize_image(call, ctx.approver.clone()).await.map(Some)
+    }
+}
@@ -28,6 +28,7 @@ pub mod git;
 pub mod github;
 pub mod image;
 pub mod plan;
+pub mod registry;
 pub mod screenpipe;
 pub mod shell;
 pub mod todo;
@@ -38,14 +39,14 @@ pub mod parsing;
 
 use crate::core::constants::tools;
 use crate::core::error::{HarperError, HarperResult};
-use crate::core::{ApiConfig, Message};
+use crate::core::mcp::McpRegistry;
+use crate::core::{ApiConfig, ImageAttachment, Message};
 use crate::runtime::config::ExecPolicyConfig;
-use crate::tools::shell::CommandAuditContext;
+use crate::tools::registry::{ToolContext, ToolRegistry};
 use reqwest::Client;
 use rusqlite::Connection;
 use serde_json::json;
 use std::path::PathBuf;
-use turul_mcp_client::{ContentBlock, McpClient};
 
 // Git command constants
 mod git_tools {
@@ -56,17 +57,20 @@ mod git_tools {
 }
 
 use crate::core::io_traits::{RuntimeEventSink, UserApproval};
+use async_trait::async_trait;
 use std::sync::Arc;
 
 /// Tool execution service
 pub struct ToolService<'a> {
     conn: &'a Connection,
     config: &'a ApiConfig,
+    tools: &'a ToolRegistry,
     exec_policy: &'a ExecPolicyConfig,
-    mcp_client: Option<&'a turul_mcp_client::McpClient>,
+    mcp: Option<&'a McpRegistry>,
     session_id: Option<&'a str>,
     approver: Option<Arc<dyn UserApproval>>,
     runtime_events: Option<Arc<dyn RuntimeEventSink>>,
+    firmware: Option<Arc<firmware::FirmwareDevices>>,
 }
 
 #[derive(Debug, Default, Clone, PartialEq, Eq)]
@@ -75,57 +79,56 @@ struct PlanSyncOutcome {
     next_step: Option<String>,
 }
 
-impl<'a> ToolService<'a> {
-    fn parse_run_command_sandbox_intent(args: &serde_json::Value) -> shell::CommandSandboxIntent {
-        shell::CommandSandboxIntent {
-            declared_read_paths: args
-                .get("declared_read_paths")
-                .and_then(|v| v.as_array())
-                .into_iter()
-                .flatten()
-                .filter_map(|v| v.as_str())
-                .map(std::path::PathBuf::from)
-                .collect(),
-            declared_write_paths: args
-                .get("declared_write_paths")
-                .and_then(|v| v.as_array())
-                .into_iter()
-                .flatten()
-                .filter_map(|v| v.as_str())
-                .map(std::path::PathBuf::from)
-                .collect(),
-            requires_network: args
-                .get("requires_network")
-                .and_then(|v| v.as_bool())
-                .unwrap_or(false),
-            retry_policy: args
-                .get("retry_policy")
-                .and_then(|v| v.as_str())
-                .and_then(|value| {
-                    serde_json::from_value::<shell::CommandRetryPolicy>(serde_json::Value::String(
-                        value.to_string(),
-                    ))
-                    .ok()
-                }),
-        }
+/// One entry from a model turn that requested several tool calls
+#[derive(Debug, Clone, PartialEq)]
+struct BatchedToolCall {
+    id: String,
+    name: String,
+    args: serde_json::Value,
+}
+
+impl BatchedToolCall {
+    /// Single-call JSON used for plan bookkeeping
+    fn call_json(&self) -> String {
+        json!({"tool": self.name, "args": self.args}).to_string()
     }
+}
 
+/// Queues approval prompts from concurrently running tool calls so the user
+/// is only ever asked one question at a time
+struct SerializedApproval {
+    inner: Arc<dyn UserApproval>,
+    gate: tokio::sync::Mutex<()>,
+}
+
+#[async_trait]
+impl UserApproval for SerializedApproval {
+    async fn approve(&self, prompt: &str, command: &str) -> HarperResult<bool> {
+        let _guard = self.gate.lock().await;
+        self.inner.approve(prompt, command).await
+    }
+}
+
+impl<'a> ToolService<'a> {
     /// Create a new tool service
     pub fn new(
         conn: &'a Connection,
         config: &'a ApiConfig,
+        tools: &'a ToolRegistry,
         exec_policy: &'a ExecPolicyConfig,
-        mcp_client: Option<&'a McpClient>,
+        mcp: Option<&'a McpRegistry>,
         session_id: Option<&'a str>,
     ) -> Self {
         Self {
             conn,
             config,
+            tools,
             exec_policy,
-            mcp_client,
+            mcp,
             session_id,
             approver: None,
             runtime_events: None,
+            firmware: None,
         }
     }
 
@@ -140,6 +143,12 @@ impl<'a> ToolService<'a> {
         self
     }
 
+    /// Set the firmware devices built from `[firmware]`
+    pub fn with_firmware(mut self, firmware: Arc<firmware::FirmwareDevices>) -> Self {
+        self.firmware = Some(firmware);
+        self
+    }
+
     fn emit_activity_update(&self, status: Option<String>) {
         let (Some(runtime_events), Some(session_id)) = (&self.runtime_events, self.session_id)
         else {
@@ -166,6 +175,17 @@ impl<'a> ToolService<'a> {
         if let Ok(json_value) = serde_json::from_str::<serde_json::Value>(response) {
             // Case 1: OpenAI tool_calls format (array of objects)
             if let Some(tool_calls) = json_value.as_array() {
+                if tool_calls.len() > 1 {
+                    return self
+                        .handle_tool_call_batch(
+                            client,
+                            history,
+                            tool_calls,
+                            response,
+                            web_search_enabled,
+                        )
+                        .await;
+                }
                 if let Some(first_call) = tool_calls.first() {
                     let function = first_call.get("function");
                     let tool_name = function
@@ -243,167 +263,22 @@ impl<'a> ToolService<'a> {
             }
         }
 
-        // Fallback to old bracket format
-        if response.to_uppercase().starts_with(tools::RUN_COMMAND) {
-            self.sync_plan_before_tool("run_command")?;
-            let audit_ctx = CommandAuditContext {
-                conn: self.conn,
-                session_id: self.session_id,
-                source: "tool_run_command",
-            };
-            let command_result = shell::execute_command(
-                response,
-                self.config,
-                self.exec_policy,
-                None,
-                Some(&audit_ctx),
-                self.approver.clone(),
-                self.runtime_events.clone(),
-            )
-            .await?;
-            let final_response = self
-                .call_llm_after_tool(client, history, response, &command_result)
-                .await?;
-            Ok(Some((final_response, command_result)))
-        } else if response.to_uppercase().starts_with(tools::READ_FILE) {
-            self.sync_plan_before_tool("read_file")?;
-            let tool_result = filesystem::read_file(response, self.approver.clone()).await?;
-            let final_response = self
-                .call_llm_after_tool(client, history, response, &tool_result)
-                .await?;
-            Ok(Some((final_response, tool_result)))
-        } else if response.to_uppercase().starts_with(tools::WRITE_FILE) {
-            self.sync_plan_before_tool("write_file")?;
-            let tool_result = filesystem::write_file(response, self.approver.clone()).await?;
-            let final_response = self
-                .call_llm_after_tool(client, history, response, &tool_result)
-                .await?;
-            Ok(Some((final_response, tool_result)))
-        } else if response.to_uppercase().starts_with(tools::SEARCH_REPLACE) {
-            self.sync_plan_before_tool("search_replace")?;
-            let tool_result = filesystem::search_replace(response, self.approver.clone()).await?;
-            let final_response = self
-                .call_llm_after_tool(client, history, response, &tool_result)
-                .await?;
-            Ok(Some((final_response, tool_result)))
-        } else if response.to_uppercase().starts_with(tools::TODO) {
-            self.sync_plan_before_tool("todo")?;
-            self.execute_sync_tool(client, history, response, |conn, response| {
-                let conn = conn.ok_or_else(|| {
-                    HarperError::Database("Connection required for todo management".to_string())
-                })?;
-                todo::manage_todo(conn, response)
-            })
-            .await
-        } else if web_search_enabled && response.to_uppercase().starts_with(tools::SEARCH) {
-            self.sync_plan_before_tool("search")?;
-            let search_result = web::perform_web_search(response).await?;
-            let final_response = self
-                .call_llm_after_tool(client, history, response, &search_result)
-                .await?;
-            Ok(Some((final_response, search_result)))
-        } else if response.to_uppercase().starts_with(git_tools::GIT_STATUS) {
-            self.sync_plan_before_tool("git_status")?;
-            self.execute_sync_tool(client, history, response, |_, _| git::git_status())
-                .await
-        } else if response.to_uppercase().starts_with(git_tools::GIT_DIFF) {
-            self.sync_plan_before_tool("git_diff")?;
-            self.execute_sync_tool(client, history, response, |_, _| git::git_diff())
-                .await
-        } else if response.to_uppercase().starts_with(git_tools::GIT_COMMIT) {
-            self.sync_plan_before_tool("git_commit")?;
-            let commit_result = git::git_commit(response, self.approver.clone()).await?;
-            let final_response = self
-                .call_llm_after_tool(client, history, response, &commit_result)
-                .await?;
-            Ok(Some((final_response, commit_result)))
-        } else if response.to_uppercase().starts_with(git_tools::GIT_ADD) {
-            self.sync_plan_before_tool("git_add")?;
-            let add_result = git::git_add(response, self.approver.clone()).await?;
-            let final_response = self
-                .call_llm_after_tool(client, history, response, &add_result)
-                .await?;
-            Ok(Some((final_response, add_result)))
-        } else if response.to_uppercase().starts_with(tools::GITHUB_ISSUE) {
-            self.sync_plan_before_tool("github_issue")?;
-            self.execute_sync_tool(client, history, response, |_, response| {
-                github::create_issue(response)
-            })
-            .await
-        } else if response.to_uppercase().starts_with(tools::GITHUB_PR) {
-            self.sync_plan_before_tool("github_pr")?;
-            self.execute_sync_tool(client, history, response, |_, response| {
-                github::create_pr(response)
-            })
-            .await
-        } else if response.to_uppercase().starts_with(tools::API_TEST) {
-            self.sync_plan_before_tool("api_test")?;
-            let api_result = api::test_api(response).await?;
-            let final_response = self
-                .call_llm_after_tool(client, history, response, &api_result)
-                .await?;
-            Ok(Some((final_response, api_result)))
-        } else if response.to_uppercase().starts_with(tools::CODE_ANALYZE) {
-            self.sync_plan_before_tool("code_analyze")?;
-            self.execute_sync_tool(client, history, response, |_, response| {
-                code_analysis::analyze_code(response)
-            })
-            .await
-        } else if response
-            .to_uppercase()
-            .starts_with(tools::CODEBASE_INVESTIGATE)
-        {
-            self.sync_plan_before_tool("codebase_investigator")?;
-            let tool_result =
-                codebase_investigator::investigate_codebase(response, self.approver.clone())
-                    .await?;
-            let final_response = self
-                .call_llm_after_tool(client, history, response, &tool_result)
-                .await?;
-            Ok(Some((final_response, tool_result)))
-        } else if response.to_uppercase().starts_with(tools::DB_QUERY) {
-            self.sync_plan_before_tool("db_query")?;
-            let tool_result = db::run_query(response, self.approver.clone()).await?;
-            let final_response = self
-                .call_llm_after_tool(client, history, response, &tool_result)
-                .await?;
-            Ok(Some((final_response, tool_result)))
-        } else if response.to_uppercase().starts_with(tools::ADX_QUERY) {
-            self.sync_plan_before_tool("adx_query")?;
-            let args = adx::args_from_bracket_call(response)?;
-            let tool_result = adx::query_from_json(client, &args, self.approver.clone()).await?;
-            let final_response = self
-                .call_llm_after_tool(client, history, response, &tool_result)
-                .await?;
-            Ok(Some((final_response, tool_result)))
-        } else if response.to_uppercase().starts_with(tools::IMAGE_INFO) {
-            self.sync_plan_before_tool("image_info")?;
-            self.execute_sync_tool(client, history, response, |_, response| {
-                image::get_image_info(response)
-            })
-            .await
-        } else if response.to_uppercase().starts_with(tools::IMAGE_RESIZE) {
-            self.sync_plan_before_tool("image_resize")?;
-            self.execute_sync_tool(client, history, response, |_, response| {
-                image::resize_image(response)
-            })
-            .await
-        } else if response.to_uppercase().starts_with(tools::SCREENPIPE) {
-            self.sync_plan_before_tool("screenpipe")?;
-            let search_result = screenpipe::search_screenpipe(response).await?;
-            let final_response = self
-                .call_llm_after_tool(client, history, response, &search_result)
-                .await?;
-            Ok(Some((final_response, search_result)))
-        } else if response.to_uppercase().starts_with(tools::FIRMWARE) {
-            self.sync_plan_before_tool("firmware")?;
-            self.execute_sync_tool(client, history, response, |_, response| {
-                firmware::handle_firmware_command(response)
-            })
-            .await
-        } else {
-            Ok(None)
+        // Legacy bracket format for models without native function calling
+        let Some(tool) = self.tools.find_bracket(response).cloned() else {
+            return Ok(None);
+        };
+        if tool.name() == "search" && !web_search_enabled {
+            return Ok(None);
         }
+        self.begin_tool(tool.name())?;
+        let ctx = self.tool_context(client, web_search_enabled);
+        let Some(tool_result) = tool.execute_bracket(response, &ctx).await? else {
+            return Ok(None);
+        };
+        let final_response = self
+            .call_llm_after_tool(client, history, response, &tool_result)
+            .await?;
+        Ok(Some((final_response, tool_result)))
     }
 
     #[allow(dead_code)]
@@ -416,290 +291,218 @@ impl<'a> ToolService<'a> {
         raw_response: &str,
         web_search_enabled: bool,
     ) -> Result<Option<(String, String)>, HarperError> {
-        self.sync_plan_before_tool(tool_name)?;
-        match tool_name {
-            "run_command" => {
-                if let Some(command) = args.get("command").and_then(|v| v.as_str()) {
-                    let bracket_command = format!("[RUN_COMMAND {}]", command);
-                    let sandbox_intent = Self::parse_run_command_sandbox_intent(args);
-                    let audit_ctx = CommandAuditContext {
-                        conn: self.conn,
-                        session_id: self.session_id,
-                        source: "tool_run_command",
-                    };
-                    let command_result = shell::execute_command(
-                        &bracket_command,
-                        self.config,
-                        self.exec_policy,
-                        Some(&sandbox_intent),
-                        Some(&audit_ctx),
-                        self.approver.clone(),
-                        self.runtime_events.clone(),
-                    )
-                    .await?;
-                    let final_response = self
-                        .call_llm_after_tool(client, history, raw_response, &command_result)
-                        .await?;
-                    Ok(Some((final_response, command_result)))
-                } else {
-                    Ok(None)
-                }
-            }
+        self.begin_tool(tool_name)?;
+        if tool_name == "search" && !web_search_enabled {
+            return Ok(Some((
+                "Web search is off. Enable web mode and try again.".to_string(),
+                "Web search is disabled for this session.".to_string(),
+            )));
+        }
+        let Some(tool_result) = self
+            .execute_json_tool(client, tool_name, args, web_search_enabled)
+            .await?
+        else {
+            return Ok(None);
+        };
+        if matches!(tool_name, "adx_query" | "azure_data_explorer") {
+            return Ok(Some((tool_result.clone(), tool_result)));
+        }
+        let final_response = self
+            .call_llm_after_tool(client, history, raw_response, &tool_result)
+            .await?;
+        Ok(Some((final_response, tool_result)))
+    }
 
-            "search" => {
-                if !web_search_enabled {
-                    return Ok(Some((
-                        "Web search is off. Enable web mode and try again.".to_string(),
-                        "Web search is disabled for this session.".to_string(),
-                    )));
-                }
-                if let Some(query) = args.get("query").and_then(|v| v.as_str()) {
-                    let bracket_command = format!("[SEARCH: {}]", query);
-                    let search_result = web::perform_web_search(&bracket_command).await?;
-                    let final_response = self
-                        .call_llm_after_tool(client, history, raw_response, &search_result)
-                        .await?;
-                    Ok(Some((final_response, search_result)))
-                } else {
-                    Ok(None)
-                }
-            }
+    /// Run a JSON tool call and return its raw output, without a follow-up model call
+    async fn execute_json_tool(
+        &self,
+        client: &Client,
+        tool_name: &str,
+        args: &serde_json::Value,
+        web_search_enabled: bool,
+    ) -> Result<Option<String>, HarperError> {
+        let Some(tool) = self.tools.get(tool_name).cloned() else {
+            return Ok(None);
+        };
+        self.tools.check(tool.name())?;
+        let ctx = self.tool_context(client, web_search_enabled);
+        tool.execute(args, &ctx).await
+    }
 
-            "read_file" => {
-                if let Some(path) = args.get("path").and_then(|v| v.as_str()) {
-                    let bracket_command = format!("[READ_FILE {}]", path);
-                    let read_result =
-                        filesystem::read_file(&bracket_command, self.approver.clone()).await?;
-                    let final_response = self
-                        .call_llm_after_tool(client, history, raw_response, &read_result)
-                        .await?;
-                    Ok(Some((final_response, read_result)))
-                } else {
-                    Ok(None)
-                }
-            }
+    /// What registered tools can use while this service runs them
+    fn tool_context<'c>(&'c self, client: &'c Client, web_search_enabled: bool) -> ToolContext<'c> {
+        ToolContext {
+            client,
+            conn: self.conn,
+            config: self.config,
+            tools: self.tools,
+            exec_policy: self.exec_policy,
+            session_id: self.session_id,
+            approver: self.approver.clone(),
+            runtime_events: self.runtime_events.clone(),
+            web_search_enabled,
+            firmware: self.firmware.as_deref(),
+        }
+    }
 
-            "write_file" => {
-                let path = args.get("path").and_then(|v| v.as_str());
-                let content = args.get("content").and_then(|v| v.as_str());
-                if let (Some(path), Some(content)) = (path, content) {
-                    let write_result =
-                        filesystem::write_file_direct(path, content, self.approver.clone()).await?;
-                    let final_response = self
-                        .call_llm_after_tool(client, history, raw_response, &write_result)
-                        .await?;
-                    Ok(Some((final_response, write_result)))
-                } else {
-                    Ok(None)
-                }
-            }
-            "search_replace" => {
-                let path = args.get("path").and_then(|v| v.as_str());
-                let old_string = args.get("old_string").and_then(|v| v.as_str());
-                let new_string = args.get("new_string").and_then(|v| v.as_str());
-                if let (Some(path), Some(old_string), Some(new_string)) =
-                    (path, old_string, new_string)
-                {
-                    let bracket_command =
-                        format!("[SEARCH_REPLACE {} {} {}]", path, old_string, new_string);
-                    let replace_result =
-                        filesystem::search_replace(&bracket_command, self.approver.clone()).await?;
-                    let final_response = self
-                        .call_llm_after_tool(client, history, raw_response, &replace_result)
-                        .await?;
-                    Ok(Some((final_response, replace_result)))
-                } else {
-                    Ok(None)
-                }
-            }
-            "todo" => {
-                if let Some(action) = args.get("action").and_then(|v| v.as_str()) {
-                    let bracket_command = match action {
-                        "add" => {
-                            if let Some(description) =
-                                args.get("description").and_then(|v| v.as_str())
-                            {
-                                format!("[TODO add {}]", description)
-                            } else {
-                                "".to_string()
-                            }
-                        }
-                        "list" => "[TODO list]".to_string(),
-                        "remove" => {
-                            if let Some(index) = args.get("index").and_then(|v| v.as_i64()) {
-                                format!("[TODO remove {}]", index)
-                            } else {
-                                "".to_string()
-                            }
-                        }
-                        "clear" => "[TODO clear]".to_string(),
-                        _ => "".to_string(),
-                    };
-                    if bracket_command.is_empty() {
-                        return Ok(None);
-                    }
-                    let todo_result = todo::manage_todo(self.conn, &bracket_command)?;
-                    let final_response = self
-                        .call_llm_after_tool(client, history, raw_response, &todo_result)
-                        .await?;
-                    Ok(Some((final_response, todo_result)))
-                } else {
-                    Ok(None)
-                }
-            }
-            "adx_query" | "azure_data_explorer" => {
-                let query_result =
-                    adx::query_from_json(client, args, self.approver.clone()).await?;
-                Ok(Some((query_result.clone(), query_result)))
+    /// Execute every call from a model turn that requested several tools
+    ///
+    /// Read-only calls run concurrently, anything that can change state runs
+    /// afterwards in order so each approval is asked on its own. Every result is
+    /// fed back under its `tool_call_id` before a single follow-up model call.
+    async fn handle_tool_call_batch(
+        &mut self,
+        client: &Client,
+        history: &[Message],
+        tool_calls: &[serde_json::Value],
+        raw_response: &str,
+        web_search_enabled: bool,
+    ) -> Result<Option<(String, String)>, HarperError> {
+        let calls = Self::parse_tool_call_batch(tool_calls);
+        if calls.is_empty() {
+            return Ok(None);
+        }
+
+        let (read_only, mutating): (Vec<usize>, Vec<usize>) =
+            (0..calls.len()).partition(|&index| self.tools.is_read_only(&calls[index].name));
+        let mut outputs = vec![String::new(); calls.len()];
+        let mut images = vec![Vec::new(); calls.len()];
+        let mut plan_outcomes = vec![PlanSyncOutcome::default(); calls.len()];
+
+        if !read_only.is_empty() {
+            let original_approver = self.approver.clone();
+            self.approver = original_approver.clone().map(|inner| {
+                Arc::new(SerializedApproval {
+                    inner,
+                    gate: tokio::sync::Mutex::new(()),
+                }) as Arc<dyn UserApproval>
+            });
+            let service = &*self;
+            let results = futures_util::future::join_all(read_only.iter().map(|&index| {
+                service.run_batched_tool_call(client, &calls[index], web_search_enabled)
+            }))
+            .await;
+            self.approver = original_approver;
+            for (&index, (output, call_images, outcome)) in read_only.iter().zip(results) {
+                outputs[index] = output;
+                images[index] = call_images;
+                plan_outcomes[index] = outcome;
             }
-            "update_plan" => {
-                let Some(session_id) = self.session_id else {
-                    return Err(HarperError::Validation(
-                        "update_plan requires an active session".to_string(),
-                    ));
-                };
-                let plan_result = plan::update_plan(self.conn, session_id, args)?;
-                let final_response = self
-                    .call_llm_after_tool(client, history, raw_response, &plan_result)
-                    .await?;
-                Ok(Some((final_response, plan_result)))
+        }
+
+        for &index in &mutating {
+            let (output, call_images, outcome) = self
+                .run_batched_tool_call(client, &calls[index], web_search_enabled)
+                .await;
+            outputs[index] = output;
+            images[index] = call_images;
+            plan_outcomes[index] = outcome;
+        }
+
+        self.emit_activity_update(Some("thinking".to_string()));
+        let mut new_history = history.to_vec();
+        new_history.push(Message::text("assistant", raw_response));
+        for ((call, output), call_images) in calls.iter().zip(&outputs).zip(images) {
+            new_history.push(
+                Message::tool_result(call.id.clone(), output.clone()).with_images(call_images),
+            );
+        }
+
+        let mut system_message = format!(
+            "SYSTEM INSTRUCTION: All {} tool calls have completed. Their outputs are above, one message per tool_call_id.
+1. DO NOT output the tool call JSON again.
+2. DO NOT repeat the raw output.
+3. Analyze the results together and provide a human-readable answer to the user's request.",
+            calls.len()
+        );
+        if !calls.iter().any(|call| call.name == "update_plan") {
+            let plan_sync_outcome = plan_outcomes
+                .into_iter()
+                .rev()
+                .find(|outcome| outcome.completed_step.is_some())
+                .unwrap_or_default();
+            if let Some(plan_instruction) = self.plan_followup_instruction(&plan_sync_outcome)? {
+                system_message.push_str("\n4. ");
+                system_message.push_str(&plan_instruction);
             }
-            "codebase_investigator" => {
-                let action = args.get("action").and_then(|v| v.as_str());
-                if let Some(act) = action {
-                    let mut bracket_command = format!("[CODEBASE_INVESTIGATE {}", act);
-                    match act {
-                        "find_calls" => {
-                            if let Some(symbol) = args.get("symbol").and_then(|v| v.as_str()) {
-                                bracket_command.push_str(&format!(" {}]", symbol));
-                            }
-                        }
-                        "trace_relationship" => {
-                            let x = args.get("x").and_then(|v| v.as_str());
-                            let y = args.get("y").and_then(|v| v.as_str());
-                            if let (Some(x), Some(y)) = (x, y) {
-                                bracket_command.push_str(&format!(" {} {}]", x, y));
-                            }
-                        }
-                        "clone_context" => {
-                            if let Some(repo_url) = args.get("repo_url").and_then(|v| v.as_str()) {
-                                bracket_command.push_str(&format!(" {}]", repo_url));
-                            }
-                        }
-                        _ => {}
-                    }
-                    if bracket_command.ends_with(']') {
-                        let tool_result = codebase_investigator::investigate_codebase(
-                            &bracket_command,
-                            self.approver.clone(),
-                        )
-                        .await?;
-                        let final_response = self
-                            .call_llm_after_tool(client, history, raw_response, &tool_result)
-                            .await?;
-                        return Ok(Some((final_response, tool_result)));
+        }
+        new_history.push(Message::text("system", system_message));
+
+        let combined_output = Self::combine_batched_outputs(&calls, &outputs);
+        let final_response = self
+            .request_tool_followup(client, new_history, None, &combined_output)
+            .await?;
+        Ok(Some((final_response, combined_output)))
+    }
+
+    /// Run one call of a batch, keeping the plan runtime in step with it
+    ///
+    /// Returns the output, any images it came with, and the plan outcome.
+    async fn run_batched_tool_call(
+        &self,
+        client: &Client,
+        call: &BatchedToolCall,
+        web_search_enabled: bool,
+    ) -> (String, Vec<ImageAttachment>, PlanSyncOutcome) {
+        if let Err(err) = self.begin_tool(&call.name) {
+            return (
+                format!("Error: {}", err),
+                Vec::new(),
+                PlanSyncOutcome::default(),
+            );
+        }
+
+        let mut images = Vec::new();
+        let result = if call.name.starts_with("mcp__") {
+            let (output, call_images) = self.execute_mcp_tool(&call.name, &call.args).await;
+            images = call_images;
+            Ok(Some(output))
+        } else {
+            self.execute_json_tool(client, &call.name, &call.args, web_search_enabled)
+                .await
+        };
+        let output = match result {
+            Ok(Some(output)) => output,
+            Ok(None) => format!(
+                "Error: tool `{}` is unknown or is missing required arguments",
+                call.name
+            ),
+            Err(err) => format!("Error: {}", err),
+        };
+
+        let outcome = self
+            .sync_plan_after_tool(&call.call_json())
+            .unwrap_or_default();
+        (output, images, outcome)
+    }
+
+    fn parse_tool_call_batch(tool_calls: &[serde_json::Value]) -> Vec<BatchedToolCall> {
+        tool_calls
+            .iter()
+            .enumerate()
+            .filter_map(|(index, call)| {
+                let function = call.get("function")?;
+                let name = function.get("name")?.as_str()?.to_string();
+                let args = match function.get("arguments") {
+                    Some(serde_json::Value::String(raw)) => {
+                        serde_json::from_str::<serde_json::Value>(raw).unwrap_or(json!({}))
                     }
-                }
-                Ok(None)
-            }
-            "git_status" => {
-                let status_result = git::git_status()?;
-                let final_response = self
-                    .call_llm_after_tool(client, history, raw_response, &status_result)
-                    .await?;
-                Ok(Some((final_response, status_result)))
-            }
-            "git_diff" => {
-                let diff_result = git::git_diff()?;
-                let final_response = self
-                    .call_llm_after_tool(client, history, raw_response, &diff_result)
-                    .await?;
-                Ok(Some((final_response, diff_result)))
-            }
-            "git_add" => {
-                let files = args.get("files").and_then(|v| v.as_str());
-                let bracket_command = format!("[GIT_ADD {}]", files.unwrap_or("."));
-                let add_result = git::git_add(&bracket_command, self.approver.clone()).await?;
-                let final_response = self
-                    .call_llm_after_tool(client, history, raw_response, &add_result)
-                    .await?;
-                Ok(Some((final_response, add_result)))
-            }
-            "git_commit" => {
-                if let Some(message) = args.get("message").and_then(|v| v.as_str()) {
-                    let bracket_command = format!("[GIT_COMMIT {}]", message);
-                    let commit_result =
-                        git::git_commit(&bracket_command, self.approver.clone()).await?;
-                    let final_response = self
-                        .call_llm_after_tool(client, history, raw_response, &commit_result)
-                        .await?;
-                    Ok(Some((final_response, commit_result)))
-                } else {
-                    Ok(None)
-                }
-            }
-            "list_changed_files" => {
-                let ext = args.get("ext").and_then(|v| v.as_str());
-                let tracked_only = args
-                    .get("tracked_only")
-                    .and_then(|v| v.as_bool())
-                    .unwrap_or(false);
-                let since = args.get("since").and_then(|v| v.as_str());
-
-                let audit_ctx = CommandAuditContext {
-                    conn: self.conn,
-                    session_id: self.session_id,
-                    source: "tool_list_changed_files",
+                    Some(value) => value.clone(),
+                    None => json!({}),
                 };
-                let files_result = git::list_changed_files_with_policy(
-                    self.config,
-                    self.exec_policy,
-                    Some(&audit_ctx),
-                    self.approver.clone(),
-                    ext,
-                    tracked_only,
-                    since,
-                )
-                .await?;
-                let final_response = self
-                    .call_llm_after_tool(client, history, raw_response, &files_result)
-                    .await?;
-                Ok(Some((final_response, files_result)))
-            }
-            "firmware_list" => {
-                let result = firmware::handle_firmware_command("[FIRMWARE list]")?;
-                let final_response = self
-                    .call_llm_after_tool(client, history, raw_response, &result)
-                    .await?;
-                Ok(Some((final_response, result)))
-            }
-            "firmware_info" => {
-                if let Some(device) = args.get("device").and_then(|v| v.as_str()) {
-                    let command = format!("[FIRMWARE info {}]", device);
-                    let result = firmware::handle_firmware_command(&command)?;
-                    let final_response = self
-                        .call_llm_after_tool(client, history, raw_response, &result)
-                        .await?;
-                    Ok(Some((final_response, result)))
-                } else {
-                    Ok(None)
-                }
-            }
-            "firmware_gpio" => {
-                if let Some(pin) = args.get("pin").and_then(|v| v.as_i64()) {
-                    let state = args.get("state").and_then(|v| v.as_str()).unwrap_or("high");
-                    let command = format!("[FIRMWARE gpio {} {}]", pin, state);
-                    let result = firmware::handle_firmware_command(&command)?;
-                    let final_response = self
-                        .call_llm_after_tool(client, history, raw_response, &result)
-                        .await?;
-                    Ok(Some((final_response, result)))
-                } else {
-                    Ok(None)
-                }
-            }
-            _ => Ok(None),
-        }
+                let id = crate::core::llm_client::tool_call_id(call, index);
+                Some(BatchedToolCall { id, name, args })
+            })
+            .collect()
+    }
+
+    fn combine_batched_outputs(calls: &[BatchedToolCall], outputs: &[String]) -> String {
+        calls
+            .iter()
+            .zip(outputs)
+            .map(|(call, output)| format!("[{} {}]\n{}", call.id, call.name, output))
+            .collect::<Vec<_>>()
+            .join("\n\n")
     }
 
     /// Handle MCP tool calls
@@ -711,97 +514,65 @@ impl<'a> ToolService<'a> {
         args: &serde_json::Value,
         raw_response: &str,
     ) -> Result<Option<(String, String)>, HarperError> {
-        let Some(mcp_client) = self.mcp_client else {
-            let error_msg = "Error: MCP client not configured".to_string();
-            let final_response = self
-                .call_llm_after_tool(client, history, raw_response, &error_msg)
-                .await?;
-            return Ok(Some((final_response, error_msg)));
+        self.tools.check(tool_name)?;
+        let (tool_result, images) = self.execute_mcp_tool(tool_name, args).await;
+        let final_response = self
+            .call_llm_after_tool_with_images(client, history, raw_response, &tool_result, images)
+            .await?;
+        Ok(Some((final_response, tool_result)))
+    }
+
+    /// Call an MCP tool and render its content blocks as text for the model
+    ///
+    /// Images are also returned as attachments for models that accept them.
+    async fn execute_mcp_tool(
+        &self,
+        tool_name: &str,
+        args: &serde_json::Value,
+    ) -> (String, Vec<ImageAttachment>) {
+        let Some(registry) = self.mcp else {
+            return ("Error: MCP client not configured".to_string(), Vec::new());
         };
 
-        match mcp_client.call_tool(tool_name, args.clone()).await {
+        match registry.call_tool(tool_name, args.clone()).await {
             Ok(result) => {
-                // Format the response for the LLM
-                let mut result_parts = Vec::new();
-
-                for content in result.content {
-                    match content {
-                        ContentBlock::Text { text, .. } => {
-                            result_parts.push(text.clone());
-                        }
-                        ContentBlock::Image {
-                            data, mime_type, ..
-                        } => {
-                            result_parts.push(format!(
-                                "[Image: {} bytes, type: {}]",
-                                data.len(),
-                                mime_type
-                            ));
-                        }
-                        ContentBlock::Audio { .. } => {
-                            result_parts.push("[Audio content]".to_string());
-                        }
-                        ContentBlock::ResourceLink { .. } => {
-                            result_parts.push("[Resource link]".to_string());
-                        }
-                        ContentBlock::Resource { .. } => {
-                            result_parts.push("[Resource content]".to_string());
-                        }
-                        ContentBlock::ToolUse { .. } => {
-                            result_parts.push("[Tool use]".to_string());
-                        }
-                        ContentBlock::ToolResult { .. } => {
-                            result_parts.push("[Tool result]".to_string());
-                        }
-                    }
-                }
-
-                let tool_result = if result_parts.is_empty() {
-                    "Tool executed successfully (no output)".to_string()
+                let (text, images) = crate::core::mcp::render_content(result.content);
+                if text.is_empty() && images.is_empty() {
+                    ("Tool executed successfully (no output)".to_string(), images)
                 } else {
-                    result_parts.join("\n")
-                };
-
-                let final_response = self
-                    .call_llm_after_tool(client, history, raw_response, &tool_result)
-                    .await?;
-                Ok(Some((final_response, tool_result)))
-            }
-            Err(e) => {
-                let error_msg = format!("MCP tool call failed: {}", e);
-                let final_response = self
-                    .call_llm_after_tool(client, history, raw_response, &error_msg)
-                    .await?;
-                Ok::<Option<(String, String)>, HarperError>(Some((final_response, error_msg)))
+                    (text, images)
+                }
             }
+            Err(e) => (format!("MCP tool call failed: {}", e), Vec::new()),
         }
     }
 
-    /// Execute a synchronous tool
-    async fn execute_sync_tool<F>(
-        &mut self,
+    /// Call LLM after tool usage
+    async fn call_llm_after_tool(
+        &self,
         client: &Client,
         history: &[Message],
-        response: &str,
-        tool_fn: F,
-    ) -> Result<Option<(String, String)>, HarperError>
-    where
-        F: FnOnce(Option<&Connection>, &str) -> HarperResult<String>,
-    {
-        let tool_result = tool_fn(Some(self.conn), response)?;
-        let final_response = self
-            .call_llm_after_tool(client, history, response, &tool_result)
-            .await?;
-        Ok(Some((final_response, tool_result)))
+        tool_call_json: &str,
+        tool_output: &str,
+    ) -> Result<String, HarperError> {
+        self.call_llm_after_tool_with_images(
+            client,
+            history,
+            tool_call_json,
+            tool_output,
+            Vec::new(),
+        )
+        .await
     }
 
-    /// Call LLM after tool usage
-    async fn call_llm_after_tool(
+    /// Call LLM after a tool whose output came with images
+    async fn call_llm_after_tool_with_images(
         &self,
         client: &Client,
         history: &[Message],
         tool_call_json: &str,
         tool_output: &str,
+        images: Vec<ImageAttachment>,
     ) -> Result<String, HarperError> {
         self.emit_activity_update(Some("thinking".to_string()));
         let completed_tool_name = Self::tool_name_from_call(tool_call_json);
@@ -811,45 +582,96 @@ impl<'a> ToolService<'a> {
 
         // Add the assistant's tool call message
         // This ensures the model knows it just asked for this tool
-        new_history.push(Message {
-            role: "assistant".to_string(),
-            content: tool_call_json.to_string(),
-        });
-
-        // Add a new system message containing the tool's output and an instruction
-        let mut system_message = format!(
-            "Tool execution result:
-{}
+        new_history.push(Message::text("assistant", tool_call_json));
 
----
-SYSTEM INSTRUCTION: The tool has completed successfully. The output above is the result.
+        let instruction = "SYSTEM INSTRUCTION: The tool has completed successfully. The output above is the result.
 1. DO NOT output the tool call JSON again.
 2. DO NOT repeat the raw output.
 3. Analyze the result and provide a human-readable answer to the user's request.
-4. If the user asked to see a file, summarize it or show a relevant snippet, but do not dump the entire content if it is large.",
-            tool_output
-        );
+4. If the user asked to see a file, summarize it or show a relevant snippet, but do not dump the entire content if it is large.";
+        // Native tool calls get their output back as a `tool` message; legacy
+        // JSON and bracket calls get it inlined into the instruction
+        let mut system_message = match Self::parse_tool_call_batch(
+            serde_json::from_str::<serde_json::Value>(tool_call_json.trim())
+                .ok()
+                .as_ref()
+                .and_then(|value| value.as_array())
+                .map(Vec::as_slice)
+                .unwrap_or_default(),
+        )
+        .first()
+        {
+            Some(call) => {
+                new_history
+                    .push(Message::tool_result(call.id.clone(), tool_output).with_images(images));
+                instruction.to_string()
+            }
+            None => {
+                if !images.is_empty() {
+                    new_history.push(
+                        Message::text("user", "Images returned by the tool:").with_images(images),
+                    );
+                }
+                format!(
+                    "Tool execution result:\n{}\n\n---\n{}",
+                    tool_output, instruction
+                )
+            }
+        };
         if !Self::is_update_plan_call(tool_call_json) {
             if let Some(plan_instruction) = self.plan_followup_instruction(&plan_sync_outcome)? {
                 system_message.push_str("\n5. ");
                 system_message.push_str(&plan_instruction);
             }
         }
-        new_history.push(Message {
-            role: "system".to_string(),
-            content: system_message,
-        });
+        new_history.push(Message::text("system", system_message));
 
-        match crate::core::llm_client::call_llm(client, self.config, &new_history).await {
+        self.request_tool_followup(
+            client,
+            new_history,
+            completed_tool_name.as_deref(),
+            tool_output,
+        )
+        .await
+    }
+
+    /// Call the LLM for a tool follow-up, recording its token usage
+    async fn call_followup_llm(
+        &self,
+        client: &Client,
+        history: &[Message],
+    ) -> Result<String, HarperError> {
+        let reply = crate::core::llm_client::call_llm_with_events(
+            client,
+            self.config,
+            self.tools,
+            history,
+            self.runtime_events.clone(),
+            self.session_id,
+        )
+        .await?;
+        if let Some(session_id) = self.session_id {
+            let _ = crate::memory::storage::insert_token_usage(self.conn, session_id, &reply);
+        }
+        Ok(reply.content)
+    }
+
+    /// Ask the model to answer from completed tool output, retrying once if it
+    /// tries to call another tool instead
+    async fn request_tool_followup(
+        &self,
+        client: &Client,
+        mut new_history: Vec<Message>,
+        completed_tool_name: Option<&str>,
+        tool_output: &str,
+    ) -> Result<String, HarperError> {
+        match self.call_followup_llm(client, &new_history).await {
             Ok(response)
-                if completed_tool_name.as_deref() == Some("read_file")
+                if completed_tool_name == Some("read_file")
                     && Self::response_looks_like_file_tool_call(&response) =>
             {
-                new_history.push(Message {
-                    role: "system".to_string(),
-                    content: "You already have the completed file contents. Do not call read_file, write_file, or search_replace again. Answer the user now in plain language only from the file result you already have.".to_string(),
-                });
-                match crate::core::llm_client::call_llm(client, self.config, &new_history).await {
+                new_history.push(Message::text("system", "You already have the completed file contents. Do not call read_file, write_file, or search_replace again. Answer the user now in plain language only from the file result you already have."));
+                match self.call_followup_llm(client, &new_history).await {
                     Ok(retry_response) => Ok(Self::finalize_read_file_followup_response(
                         &retry_response,
                         tool_output,
@@ -858,13 +680,10 @@ SYSTEM INSTRUCTION: The tool has completed successfully. The output above is the
                 }
             }
             Ok(response) if Self::response_looks_like_tool_call(&response) => {
-                new_history.push(Message {
-                    role: "system".to_string(),
-                    content: "You already have the completed tool result. Do not call any tool again. Respond now in plain language only.".to_string(),
-                });
-                match crate::core::llm_client::call_llm(client, self.config, &new_history).await {
+                new_history.push(Message::text("system", "You already have the completed tool result. Do not call any tool again. Respond now in plain language only."));
+                match self.call_followup_llm(client, &new_history).await {
                     Ok(retry_response) => Ok(Self::finalize_tool_followup_response(
-                        completed_tool_name.as_deref(),
+                        completed_tool_name,
                         &retry_response,
                         tool_output,
                     )),
@@ -872,7 +691,7 @@ SYSTEM INSTRUCTION: The tool has completed successfully. The output above is the
                 }
             }
             Ok(response) => Ok(Self::finalize_tool_followup_response(
-                completed_tool_name.as_deref(),
+                completed_tool_name,
                 &response,
                 tool_output,
             )),
@@ -1097,6 +916,12 @@ SYSTEM INSTRUCTION: The tool has completed successfully. The output above is the
         }
     }
 
+    /// Refuse a tool that `[tools]` rules out, otherwise record it as running
+    fn begin_tool(&self, tool_name: &str) -> HarperResult<()> {
+        self.tools.check(tool_name)?;
+        self.sync_plan_before_tool(tool_name)
+    }
+
     fn sync_plan_before_tool(&self, tool_name: &str) -> HarperResult<()> {
         if tool_name == "update_plan" {
             return Ok(());
@@ -1455,13 +1280,19 @@ fn extract_target_paths_from_json(
 #[cfg(test)]
 mod tests {
     use super::{PlanSyncOutcome, ToolService};
+    use crate::core::error::HarperResult;
+    use crate::core::io_traits::UserApproval;
     use crate::core::plan::{PlanItem, PlanState, PlanStepStatus};
     use crate::core::{ApiConfig, ApiProvider};
     use crate::runtime::config::ExecPolicyConfig;
+    use crate::tools::registry::{Tool, ToolContext, ToolRegistry, ToolRisk};
+    use async_trait::async_trait;
     use reqwest::Client;
     use rusqlite::Connection;
     use serde_json::json;
     use std::path::PathBuf;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
 
     fn test_config() -> ApiConfig {
         ApiConfig {
@@ -1469,6 +1300,9 @@ mod tests {
             api_key: "test-key".to_string(),
             base_url: "https://api.openai.com/v1/chat/completions".to_string(),
             model_name: "gpt-5.5".to_string(),
+            headers: Default::default(),
+            parameters: Default::default(),
+            fallback: Default::default(),
         }
     }
 
@@ -1505,9 +1339,10 @@ mod tests {
     async fn disabled_web_search_json_tool_returns_user_message() {
         let conn = Connection::open_in_memory().expect("in-memory db");
         let config = test_config();
+        let tools = ToolRegistry::default();
         let exec_policy = ExecPolicyConfig::default();
         let client = Client::new();
-        let mut service = ToolService::new(&conn, &config, &exec_policy, None, None);
+        let mut service = ToolService::new(&conn, &config, &tools, &exec_policy, None, None);
         let result = service
             .handle_regular_json_tool(
                 &client,
@@ -1528,13 +1363,285 @@ mod tests {
         assert_eq!(result.1, "Web search is disabled for this session.");
     }
 
+    #[tokio::test]
+    async fn disabled_tools_are_refused_at_dispatch() {
+        let conn = Connection::open_in_memory().expect("in-memory db");
+        crate::memory::storage::init_db(&conn).expect("init db");
+        let config = unreachable_llm_config();
+        let tools = ToolRegistry::new(None, vec!["git_*".to_string(), "mcp__*".to_string()]);
+        let exec_policy = ExecPolicyConfig::default();
+        let client = Client::new();
+        let mut service = ToolService::new(&conn, &config, &tools, &exec_policy, None, None);
+
+        for response in [
+            r#"{"tool":"git_status","args":{}}"#,
+            "[GIT_STATUS]",
+            r#"{"mcp_tool":"mcp__docs__search","arguments":{}}"#,
+        ] {
+            let err = service
+                .handle_tool_use(&client, &[], response, false)
+                .await
+                .expect_err("disabled tool");
+            assert!(err
+                .to_string()
+                .contains("is disabled by the [tools] configuration"));
+        }
+
+        let response = json!([
+            {"id": "call_a", "type": "function", "function": {"name": "git_diff", "arguments": "{}"}},
+            {"id": "call_b", "type": "function", "function": {"name": "todo", "arguments": "{\"action\":\"list\"}"}}
+        ])
+        .to_string();
+        let (_, tool_output) = service
+            .handle_tool_use(&client, &[], &response, false)
+            .await
+            .expect("batch handling")
+            .expect("batch result");
+        assert!(tool_output
+            .contains("[call_a git_diff]\nError: Validation error: Tool 'git_diff' is disabled"));
+        assert!(tool_output.contains("[call_b todo]"));
+    }
+
+    #[derive(Default)]
+    struct CountingApproval {
+        active: AtomicUsize,
+        max_active: AtomicUsize,
+        approved: Mutex<Vec<String>>,
+    }
+
+    #[async_trait]
+    impl UserApproval for CountingApproval {
+        async fn approve(&self, _prompt: &str, command: &str) -> HarperResult<bool> {
+            let active = self.active.fetch_add(1, Ordering::SeqCst) + 1;
+            self.max_active.fetch_max(active, Ordering::SeqCst);
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+            self.active.fetch_sub(1, Ordering::SeqCst);
+            self.approved
+                .lock()
+                .expect("approval log lock")
+                .push(command.to_string());
+            Ok(true)
+        }
+    }
+
+    struct EchoTool;
+
+    #[async_trait(?Send)]
+    impl Tool for EchoTool {
+        fn name(&self) -> &str {
+            "echo"
+        }
+
+        fn description(&self) -> &str {
+            "Echo the given text"
+        }
+
+        fn parameters(&self) -> serde_json::Value {
+            json!({"type": "object", "properties": {"text": {"type": "string"}}})
+        }
+
+        fn risk(&self) -> ToolRisk {
+            ToolRisk::ReadOnly
+        }
+
+        fn bracket_prefix(&self) -> Option<&str> {
+            Some("[ECHO")
+        }
+
+        async fn execute(
+            &self,
+            args: &serde_json::Value,
+            _ctx: &ToolContext<'_>,
+        ) -> HarperResult<Option<String>> {
+            Ok(args
+                .get("text")
+                .and_then(|v| v.as_str())
+                .map(|text| format!("echo: {}", text)))
+        }
+
+        async fn execute_bracket(
+            &self,
+            call: &str,
+            _ctx: &ToolContext<'_>,
+        ) -> HarperResult<Option<String>> {
+            Ok(Some(format!("echo: {}", call)))
+        }
+    }
+
+    #[tokio::test]
+    async fn registered_tools_are_dispatched() {
+        let conn = Connection::open_in_memory().expect("in-memory db");
+        crate::memory::storage::init_db(&conn).expect("init db");
+        let config = unreachable_llm_config();
+        let tools = ToolRegistry::default().with_tool(EchoTool);
+        let exec_policy = ExecPolicyConfig::default();
+        let client = Client::new();
+        let mut service = ToolService::new(&conn, &config, &tools, &exec_policy, None, None);
+
+        let (_, tool_output) = service
+            .handle_tool_use(
+                &client,
+                &[],
+                r#"{"tool":"echo","args":{"text":"hi"}}"#,
+                false,
+            )
+            .await
+            .expect("json call")
+            .expect("json result");
+        assert_eq!(tool_output, "echo: hi");
+
+        let (_, tool_output) = service
+            .handle_tool_use(&client, &[], "[ECHO there]", false)
+            .await
+            .expect("bracket call")
+            .expect("bracket result");
+        assert_eq!(tool_output, "echo: [ECHO there]");
+
+        let batch = json!([
+            {"id": "call_a", "type": "function", "function": {"name": "echo", "arguments": "{\"text\":\"a\"}"}},
+            {"id": "call_b", "type": "function", "function": {"name": "echo", "arguments": "{}"}}
+        ])
+        .to_string();
+        let (_, tool_output) = service
+            .handle_tool_use(&client, &[], &batch, false)
+            .await
+            .expect("batch call")
+            .expect("batch result");
+        assert!(tool_output.contains("[call_a echo]\necho: a"));
+        assert!(tool_output.contains("[call_b echo]\nError: tool `echo` is unknown"));
+    }
+
+    fn unreachable_llm_config() -> ApiConfig {
+        ApiConfig {
+            base_url: "http://127.0.0.1:9/v1/chat/completions".to_string(),
+            ..test_config()
+        }
+    }
+
+    #[test]
+    fn parse_tool_call_batch_reads_ids_and_arguments() {
+        let calls = ToolService::parse_tool_call_batch(&[
+            json!({"id": "call_a", "function": {"name": "read_file", "arguments": "{\"path\":\"Cargo.toml\"}"}}),
+            json!({"function": {"name": "git_status", "arguments": {}}}),
+            json!({"type": "function"}),
+        ]);
+
+        assert_eq!(calls.len(), 2);
+        assert_eq!(calls[0].id, "call_a");
+        assert_eq!(calls[0].args, json!({"path": "Cargo.toml"}));
+        assert_eq!(calls[1].id, "call_1");
+        assert_eq!(calls[1].name, "git_status");
+    }
+
+    #[tokio::test]
+    async fn tool_call_batch_runs_every_call_in_order() {
+        let conn = Connection::open_in_memory().expect("in-memory db");
+        crate::memory::storage::init_db(&conn).expect("init db");
+        let config = unreachable_llm_config();
+        let tools = ToolRegistry::default();
+        let exec_policy = ExecPolicyConfig::default();
+        let client = Client::new();
+        let approval = Arc::new(CountingApproval::default());
+        let mut service = ToolService::new(&conn, &config, &tools, &exec_policy, None, None)
+            .with_approver(approval.clone());
+        let response = json!([
+            {"id": "call_a", "type": "function", "function": {"name": "read_file", "arguments": "{\"path\":\"Cargo.toml\"}"}},
+            {"id": "call_b", "type": "function", "function": {"name": "read_file", "arguments": "{\"path\":\"src/lib.rs\"}"}},
+            {"id": "call_c", "type": "function", "function": {"name": "todo", "arguments": "{\"action\":\"list\"}"}}
+        ])
+        .to_string();
+
+        let (final_response, tool_output) = service
+            .handle_tool_use(&client, &[], &response, false)
+            .await
+            .expect("batch handling")
+            .expect("batch result");
+
+        let first = tool_output
+            .find("[call_a read_file]")
+            .expect("first result");
+        let second = tool_output
+            .find("[call_b read_file]")
+            .expect("second result");
+        let third = tool_output.find("[call_c todo]").expect("third result");
+        assert!(first < second && second < third);
+        assert!(tool_output.contains("name = \"harper-core\""));
+        assert!(final_response.starts_with("Tool result:"));
+        assert_eq!(approval.approved.lock().unwrap().len(), 2);
+        assert_eq!(approval.max_active.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn tool_call_batch_advances_plan_per_call() {
+        let conn = Connection::open_in_memory().expect("in-memory db");
+        crate::memory::storage::init_db(&conn).expect("init db");
+        crate::memory::storage::save_plan_state(
+            &conn,
+            "batch-plan-session",
+            &PlanState {
+                explanation: None,
+                items: vec![
+                    PlanItem {
+                        step: "Inspect manifest file".to_string(),
+                        status: PlanStepStatus::InProgress,
+                        job_id: None,
+                    },
+                    PlanItem {
+                        step: "Read library file".to_string(),
+                        status: PlanStepStatus::Pending,
+                        job_id: None,
+                    },
+                    PlanItem {
+                        step: "Patch handler".to_string(),
+                        status: PlanStepStatus::Pending,
+                        job_id: None,
+                    },
+                ],
+                runtime: None,
+                updated_at: None,
+            },
+        )
+        .expect("save plan");
+        let config = unreachable_llm_config();
+        let tools = ToolRegistry::default();
+        let exec_policy = ExecPolicyConfig::default();
+        let client = Client::new();
+        let mut service = ToolService::new(
+            &conn,
+            &config,
+            &tools,
+            &exec_policy,
+            None,
+            Some("batch-plan-session"),
+        );
+        let response = json!([
+            {"id": "call_a", "function": {"name": "read_file", "arguments": {"path": "Cargo.toml"}}},
+            {"id": "call_b", "function": {"name": "read_file", "arguments": {"path": "src/lib.rs"}}}
+        ])
+        .to_string();
+
+        service
+            .handle_tool_use(&client, &[], &response, false)
+            .await
+            .expect("batch handling")
+            .expect("batch result");
+
+        let plan = crate::memory::storage::load_plan_state(&conn, "batch-plan-session")
+            .expect("load plan")
+            .expect("plan present");
+        assert_eq!(plan.items[0].status, PlanStepStatus::Completed);
+        assert_eq!(plan.items[1].status, PlanStepStatus::Completed);
+        assert_eq!(plan.items[2].status, PlanStepStatus::InProgress);
+    }
+
     #[tokio::test]
     async fn adx_json_tool_missing_query_returns_terminal_guidance() {
         let conn = Connection::open_in_memory().expect("in-memory db");
         let config = test_config();
+        let tools = ToolRegistry::default();
         let exec_policy = ExecPolicyConfig::default();
         let client = Client::new();
-        let mut service = ToolService::new(&conn, &config, &exec_policy, None, None);
+        let mut service = ToolService::new(&conn, &config, &tools, &exec_policy, None, None);
         let result = service
             .handle_regular_json_tool(
                 &client,
@@ -1638,10 +1745,12 @@ mod tests {
         .expect("save plan");
 
         let config = test_config();
+        let tools = ToolRegistry::default();
         let exec_policy = ExecPolicyConfig::default();
         let service = ToolService::new(
             &conn,
             &config,
+            &tools,
             &exec_policy,
             None,
             Some("sync-plan-session"),
@@ -1686,10 +1795,12 @@ mod tests {
         .expect("save plan");
 
         let config = test_config();
+        let tools = ToolRegistry::default();
         let exec_policy = ExecPolicyConfig::default();
         let service = ToolService::new(
             &conn,
             &config,
+            &tools,
             &exec_policy,
             None,
             Some("after-tool-session"),
@@ -1734,10 +1845,12 @@ mod tests {
         .expect("save plan");
 
         let config = test_config();
+        let tools = ToolRegistry::default();
         let exec_policy = ExecPolicyConfig::default();
         let service = ToolService::new(
             &conn,
             &config,
+            &tools,
             &exec_policy,
             None,
             Some("checkpoint-session"),
@@ -1775,10 +1888,12 @@ mod tests {
         .expect("save plan");
 
         let config = test_config();
+        let tools = ToolRegistry::default();
         let exec_policy = ExecPolicyConfig::default();
         let service = ToolService::new(
             &conn,
             &config,
+            &tools,
             &exec_policy,
             None,
             Some("no-auto-complete-session"),
@@ -1822,10 +1937,12 @@ mod tests {
         .expect("save plan");
 
         let config = test_config();
+        let tools = ToolRegistry::default();
         let exec_policy = ExecPolicyConfig::default();
         let service = ToolService::new(
             &conn,
             &config,
+            &tools,
             &exec_policy,
             None,
             Some("blocked-followup-session"),
@@ -1868,10 +1985,12 @@ mod tests {
         .expect("save plan");
 
         let config = test_config();
+        let tools = ToolRegistry::default();
         let exec_policy = ExecPolicyConfig::default();
         let service = ToolService::new(
             &conn,
             &config,
+            &tools,
             &exec_policy,
             None,
             Some("checkpoint-followup-session"),
@@ -1917,10 +2036,12 @@ mod tests {
         .expect("save plan");
 
         let config = test_config();
+        let tools = ToolRegistry::default();
         let exec_policy = ExecPolicyConfig::default();
         let service = ToolService::new(
             &conn,
             &config,
+            &tools,
             &exec_policy,
             None,
             Some("retry-followup-session"),
@@ -1963,10 +2084,12 @@ mod tests {
         .expect("save plan");
 
         let config = test_config();
+        let tools = ToolRegistry::default();
         let exec_policy = ExecPolicyConfig::default();
         let service = ToolService::new(
             &conn,
             &config,
+            &tools,
             &exec_policy,
             None,
             Some("retry-limit-session"),
@@ -1994,26 +2117,4 @@ mod tests {
         let paths = ToolService::target_paths_for_tool_call(r#"[READ_FILE src/main.rs]"#);
         assert_eq!(paths, vec![PathBuf::from("src/main.rs")]);
     }
-
-    #[test]
-    fn parse_run_command_sandbox_intent_reads_explicit_fields() {
-        let intent = ToolService::parse_run_command_sandbox_intent(&json!({
-            "command": "cp ./src.txt ./build/out.txt",
-            "declared_read_paths": ["./src.txt"],
-            "declared_write_paths": ["./build/out.txt"],
-            "requires_network": true,
-            "retry_policy": "safe"
-        }));
-
-        assert_eq!(intent.declared_read_paths, vec![PathBuf::from("./src.txt")]);
-        assert_eq!(
-            intent.declared_write_paths,
-            vec![PathBuf::from("./build/out.txt")]
-        );
-        assert!(intent.requires_network);
-        assert_eq!(
-            intent.retry_policy,
-            Some(crate::tools::shell::CommandRetryPolicy::Safe)
-        );
-    }
 }
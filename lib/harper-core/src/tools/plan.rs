@@ -17,7 +17,10 @@ use crate::core::plan::{
     AuthoringPlannedEdit, AuthoringValidationStep, PlanItem, PlanJobStatus, PlanLoopOutcome,
     PlanLoopStage, PlanRuntime, PlanState, PlanStepStatus, StructuredAuthoringPlan,
 };
+use crate::tools::registry::{Tool, ToolContext, ToolRisk};
+use async_trait::async_trait;
 use rusqlite::Connection;
+use serde_json::{json, Value};
 
 pub fn update_plan(
     conn: &Connection,
@@ -618,6 +621,97 @@ fn parse_authoring_plan(value: &serde_json::Value) -> HarperResult<StructuredAut
     Ok(plan)
 }
 
+/// `update_plan`: replace the active session's execution plan
+pub struct UpdatePlanTool;
+
+#[async_trait(?Send)]
+impl Tool for UpdatePlanTool {
+    fn name(&self) -> &str {
+        "update_plan"
+    }
+
+    fn description(&self) -> &str {
+        "Update the current execution plan for the active session"
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "explanation": {
+                    "type": "string",
+                    "description": "Optional note explaining the current plan or why it changed"
+                },
+                "authoring_plan": {
+                    "type": "object",
+                    "description": "Optional structured authoring plan for open-ended code changes",
+                    "properties": {
+                        "primary_files": {"type": "array", "items": {"type": "string"}},
+                        "supporting_files": {"type": "array", "items": {"type": "string"}},
+                        "validation_files": {"type": "array", "items": {"type": "string"}},
+                        "planned_edits": {
+                            "type": "array",
+                            "items": {
+                                "type": "object",
+                                "properties": {
+                                    "path": {"type": "string"},
+                                    "change": {"type": "string"},
+                                    "why": {"type": "string"}
+                                },
+                                "required": ["path", "change"]
+                            }
+                        },
+                        "validation_plan": {
+                            "type": "array",
+                            "items": {
+                                "type": "object",
+                                "properties": {
+                                    "command": {"type": "string"},
+                                    "scope": {"type": "string"}
+                                },
+                                "required": ["command"]
+                            }
+                        }
+                    }
+                },
+                "items": {
+                    "type": "array",
+                    "description": "Ordered plan steps",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "step": {
+                                "type": "string",
+                                "description": "Short step description"
+                            },
+                            "status": {
+                                "type": "string",
+                                "enum": ["pending", "in_progress", "completed", "blocked"],
+                                "description": "Current status of the step"
+                            }
+                        },
+                        "required": ["step", "status"]
+                    }
+                }
+            },
+            "required": ["items"]
+        })
+    }
+
+    fn risk(&self) -> ToolRisk {
+        ToolRisk::Write
+    }
+
+    async fn execute(&self, args: &Value, ctx: &ToolContext<'_>) -> HarperResult<Option<String>> {
+        let Some(session_id) = ctx.session_id else {
+            return Err(HarperError::Validation(
+                "update_plan requires an active session".to_string(),
+            ));
+        };
+        update_plan(ctx.conn, session_id, args).map(Some)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::{
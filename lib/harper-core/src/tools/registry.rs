@@ -0,0 +1,500 @@
+// Copyright 2026 harpertoken
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Central registry of the tools Harper offers the model
+//!
+//! Every tool implements [`Tool`], which supplies the schema declared to the
+//! provider and the code run when the model calls it. Downstream crates add
+//! their own with [`ToolRegistry::register`].
+//!
+//! `[tools]` narrows the set with `enabled_tools` and `disabled_tools`, whose
+//! entries are names or glob patterns such as `git_*` and `mcp__*`. Tools left
+//! out are neither declared to the model nor accepted at dispatch.
+
+use crate::core::error::{HarperError, HarperResult};
+use crate::core::io_traits::{RuntimeEventSink, UserApproval};
+use crate::core::ApiConfig;
+use crate::runtime::config::ExecPolicyConfig;
+use crate::tools::firmware::FirmwareDevices;
+use crate::tools::shell::CommandAuditContext;
+use crate::tools::{
+    adx, api, code_analysis, codebase_investigator, db, filesystem, firmware, git, github, image,
+    plan, screenpipe, shell, todo, web,
+};
+use async_trait::async_trait;
+use harper_sandbox::wildcard_match;
+use reqwest::Client;
+use rusqlite::Connection;
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+/// How far a tool reaches beyond reading state
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ToolRisk {
+    /// Only inspects files, the repository or remote data; safe to run
+    /// alongside other calls
+    ReadOnly,
+    /// Changes files, the repository or Harper's own records
+    Write,
+    /// Runs commands or acts on systems outside the workspace
+    Execute,
+}
+
+/// What a tool can use while it runs
+pub struct ToolContext<'a> {
+    pub client: &'a Client,
+    pub conn: &'a Connection,
+    pub config: &'a ApiConfig,
+    /// Tools offered this session, from `[tools]`
+    pub tools: &'a ToolRegistry,
+    pub exec_policy: &'a ExecPolicyConfig,
+    pub session_id: Option<&'a str>,
+    pub approver: Option<Arc<dyn UserApproval>>,
+    pub runtime_events: Option<Arc<dyn RuntimeEventSink>>,
+    pub web_search_enabled: bool,
+    pub firmware: Option<&'a FirmwareDevices>,
+}
+
+impl ToolContext<'_> {
+    /// Command log entry context, recorded under `source`
+    pub fn audit<'s>(&'s self, source: &'s str) -> CommandAuditContext<'s> {
+        CommandAuditContext {
+            conn: self.conn,
+            session_id: self.session_id,
+            source,
+        }
+    }
+}
+
+/// A tool the model can call
+///
+/// Tools run on the chat task next to the session's SQLite connection, so
+/// their futures need not be `Send`.
+#[async_trait(?Send)]
+pub trait Tool: Send + Sync {
+    /// Name the model calls the tool by
+    fn name(&self) -> &str;
+
+    /// One-line description declared to the model
+    fn description(&self) -> &str;
+
+    /// JSON schema of the arguments
+    fn parameters(&self) -> Value;
+
+    fn risk(&self) -> ToolRisk;
+
+    /// Other names the model may use for the tool
+    fn aliases(&self) -> &[&str] {
+        &[]
+    }
+
+    /// Prefix of the legacy bracket form, such as `[READ_FILE`
+    fn bracket_prefix(&self) -> Option<&str> {
+        None
+    }
+
+    /// Run a JSON call, returning `None` when the arguments do not make one
+    async fn execute(&self, args: &Value, ctx: &ToolContext<'_>) -> HarperResult<Option<String>>;
+
+    /// Run a legacy bracket call such as `[READ_FILE src/main.rs]`
+    async fn execute_bracket(
+        &self,
+        call: &str,
+        ctx: &ToolContext<'_>,
+    ) -> HarperResult<Option<String>> {
+        let _ = (call, ctx);
+        Ok(None)
+    }
+}
+
+/// The tools on offer, after applying `[tools]`
+#[derive(Clone)]
+pub struct ToolRegistry {
+    tools: Vec<Arc<dyn Tool>>,
+    /// When set, only tools matching one of these patterns are available
+    enabled: Option<Vec<String>>,
+    /// Tools matching any of these patterns are never available
+    disabled: Vec<String>,
+}
+
+impl Default for ToolRegistry {
+    fn default() -> Self {
+        Self::new(None, Vec::new())
+    }
+}
+
+impl std::fmt::Debug for ToolRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ToolRegistry")
+            .field(
+                "tools",
+                &self
+                    .tools
+                    .iter()
+                    .map(|tool| tool.name())
+                    .collect::<Vec<_>>(),
+            )
+            .field("enabled", &self.enabled)
+            .field("disabled", &self.disabled)
+            .finish()
+    }
+}
+
+impl ToolRegistry {
+    /// The built-in tools, narrowed by `enabled` and `disabled` patterns
+    pub fn new(enabled: Option<Vec<String>>, disabled: Vec<String>) -> Self {
+        Self {
+            tools: built_in_tools(),
+            enabled,
+            disabled,
+        }
+    }
+
+    /// Add a tool, replacing any registered under the same name
+    pub fn register(&mut self, tool: Arc<dyn Tool>) {
+        match self
+            .tools
+            .iter_mut()
+            .find(|existing| existing.name() == tool.name())
+        {
+            Some(existing) => *existing = tool,
+            None => self.tools.push(tool),
+        }
+    }
+
+    pub fn with_tool(mut self, tool: impl Tool + 'static) -> Self {
+        self.register(Arc::new(tool));
+        self
+    }
+
+    /// Registered tool called `name` or known by it as an alias, enabled or not
+    pub fn get(&self, name: &str) -> Option<&Arc<dyn Tool>> {
+        self.tools
+            .iter()
+            .find(|tool| tool.name() == name || tool.aliases().contains(&name))
+    }
+
+    /// Tool whose legacy bracket prefix starts `call`, preferring the longest
+    pub fn find_bracket(&self, call: &str) -> Option<&Arc<dyn Tool>> {
+        let upper = call.to_uppercase();
+        self.tools
+            .iter()
+            .filter_map(|tool| {
+                let prefix = tool.bracket_prefix()?;
+                upper.starts_with(prefix).then_some((prefix.len(), tool))
+            })
+            .max_by_key(|(len, _)| *len)
+            .map(|(_, tool)| tool)
+    }
+
+    /// Enabled tools, in registration order
+    pub fn tools(&self) -> impl Iterator<Item = &Arc<dyn Tool>> {
+        self.tools
+            .iter()
+            .filter(|tool| self.is_enabled(tool.name()))
+    }
+
+    /// Whether a call to `name` only reads state and may run alongside others
+    pub fn is_read_only(&self, name: &str) -> bool {
+        self.get(name)
+            .is_some_and(|tool| tool.risk() == ToolRisk::ReadOnly)
+    }
+
+    /// Whether a built-in or `mcp__<server>__<tool>` name may be used
+    pub fn is_enabled(&self, name: &str) -> bool {
+        let allowed = self
+            .enabled
+            .as_ref()
+            .is_none_or(|patterns| patterns.iter().any(|pattern| wildcard_match(pattern, name)));
+        allowed
+            && !self
+                .disabled
+                .iter()
+                .any(|pattern| wildcard_match(pattern, name))
+    }
+
+    /// Refuse a tool call that `[tools]` rules out
+    pub fn check(&self, name: &str) -> HarperResult<()> {
+        if self.is_enabled(name) {
+            Ok(())
+        } else {
+            Err(HarperError::Validation(format!(
+                "Tool '{}' is disabled by the [tools] configuration",
+                name
+            )))
+        }
+    }
+
+    /// Refuse a call `name` routes to `routed`, such as `[FIRMWARE gpio ...]`
+    /// to `firmware_gpio`, when either is ruled out
+    ///
+    /// Only disabled patterns apply to `routed`, so enabling the routing tool
+    /// is enough to use it.
+    pub fn check_routed(&self, name: &str, routed: &str) -> HarperResult<()> {
+        self.check(name)?;
+        if self
+            .disabled
+            .iter()
+            .any(|pattern| wildcard_match(pattern, routed))
+        {
+            return Err(HarperError::Validation(format!(
+                "Tool '{}' is disabled by the [tools] configuration",
+                routed
+            )));
+        }
+        Ok(())
+    }
+
+    /// Function definitions of the enabled tools, in the OpenAI format
+    pub fn tool_functions(&self) -> Vec<Value> {
+        self.tools()
+            .map(|tool| {
+                json!({
+                    "name": tool.name(),
+                    "description": tool.description(),
+                    "parameters": tool.parameters(),
+                })
+            })
+            .collect()
+    }
+
+    /// Names of the enabled tools, in registration order
+    pub fn effective_tools(&self) -> Vec<String> {
+        self.tools().map(|tool| tool.name().to_string()).collect()
+    }
+
+    /// Whether `[tools]` narrows the default set at all
+    pub fn is_filtered(&self) -> bool {
+        self.enabled.is_some() || !self.disabled.is_empty()
+    }
+
+    /// The configured patterns, for status displays
+    pub fn describe_filters(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(enabled) = &self.enabled {
+            parts.push(format!("enabled [{}]", enabled.join(", ")));
+        }
+        if !self.disabled.is_empty() {
+            parts.push(format!("disabled [{}]", self.disabled.join(", ")));
+        }
+        if parts.is_empty() {
+            "none".to_string()
+        } else {
+            parts.join(", ")
+        }
+    }
+}
+
+/// Every tool Harper ships with, in the order they are declared
+fn built_in_tools() -> Vec<Arc<dyn Tool>> {
+    vec![
+        Arc::new(filesystem::ReadFileTool),
+        Arc::new(filesystem::WriteFileTool),
+        Arc::new(filesystem::SearchReplaceTool),
+        Arc::new(shell::RunCommandTool),
+        Arc::new(web::WebSearchTool),
+        Arc::new(adx::AdxQueryTool),
+        Arc::new(todo::TodoTool),
+        Arc::new(plan::UpdatePlanTool),
+        Arc::new(git::GitStatusTool),
+        Arc::new(git::GitDiffTool),
+        Arc::new(git::GitCommitTool),
+        Arc::new(git::GitAddTool),
+        Arc::new(git::ListChangedFilesTool),
+        Arc::new(codebase_investigator::CodebaseInvestigatorTool),
+        Arc::new(firmware::FirmwareListTool),
+        Arc::new(firmware::FirmwareInfoTool),
+        Arc::new(firmware::FirmwareConnectTool),
+        Arc::new(firmware::FirmwareDisconnectTool),
+        Arc::new(firmware::FirmwareGpioTool),
+        Arc::new(firmware::FirmwareFlashTool),
+        Arc::new(firmware::FirmwareMonitorTool),
+        Arc::new(firmware::FirmwareTool),
+        Arc::new(db::DbQueryTool),
+        Arc::new(api::ApiTestTool),
+        Arc::new(code_analysis::CodeAnalyzeTool),
+        Arc::new(image::ImageInfoTool),
+        Arc::new(image::ImageResizeTool),
+        Arc::new(screenpipe::ScreenpipeTool),
+        Arc::new(github::GithubIssueTool),
+        Arc::new(github::GithubPrTool),
+    ]
+}
+
+/// String argument `key`, if present
+pub fn str_arg<'v>(args: &'v Value, key: &str) -> Option<&'v str> {
+    args.get(key).and_then(|v| v.as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn registry(enabled: Option<&[&str]>, disabled: &[&str]) -> ToolRegistry {
+        let owned = |patterns: &[&str]| patterns.iter().map(|p| p.to_string()).collect();
+        ToolRegistry::new(enabled.map(owned), owned(disabled))
+    }
+
+    #[test]
+    fn default_registry_offers_every_built_in_tool() {
+        let tools = ToolRegistry::default();
+        assert!(!tools.is_filtered());
+        assert_eq!(tools.tool_functions().len(), built_in_tools().len());
+        assert!(tools.check("mcp__docs__search").is_ok());
+    }
+
+    #[test]
+    fn disabled_patterns_win_over_enabled_ones() {
+        let tools = registry(Some(&["read_file", "git_*", "mcp__*"]), &["git_commit"]);
+        assert_eq!(
+            tools.effective_tools(),
+            vec!["read_file", "git_status", "git_diff", "git_add"]
+        );
+        assert!(tools.is_enabled("mcp__docs__search"));
+        assert!(!tools.is_enabled("run_command"));
+
+        let err = tools.check("git_commit").unwrap_err();
+        assert!(err.to_string().contains("Tool 'git_commit' is disabled"));
+        assert_eq!(
+            tools.describe_filters(),
+            "enabled [read_file, git_*, mcp__*], disabled [git_commit]"
+        );
+    }
+
+    #[test]
+    fn routed_calls_follow_disabled_patterns_of_their_target() {
+        let tools = registry(None, &["firmware_*"]);
+        assert!(tools.check("firmware").is_ok());
+        let err = tools.check_routed("firmware", "firmware_gpio").unwrap_err();
+        assert!(err.to_string().contains("Tool 'firmware_gpio' is disabled"));
+
+        let tools = registry(Some(&["firmware"]), &[]);
+        assert!(tools.check_routed("firmware", "firmware_gpio").is_ok());
+        assert!(registry(None, &["firmware"])
+            .check_routed("firmware", "firmware_list")
+            .is_err());
+    }
+
+    struct Probe;
+
+    #[async_trait(?Send)]
+    impl Tool for Probe {
+        fn name(&self) -> &str {
+            "probe"
+        }
+
+        fn description(&self) -> &str {
+            "Probe a sensor"
+        }
+
+        fn parameters(&self) -> Value {
+            json!({"type": "object", "properties": {}})
+        }
+
+        fn risk(&self) -> ToolRisk {
+            ToolRisk::ReadOnly
+        }
+
+        fn aliases(&self) -> &[&str] {
+            &["sensor_probe"]
+        }
+
+        fn bracket_prefix(&self) -> Option<&str> {
+            Some("[PROBE")
+        }
+
+        async fn execute(
+            &self,
+            _args: &Value,
+            _ctx: &ToolContext<'_>,
+        ) -> HarperResult<Option<String>> {
+            Ok(Some("probed".to_string()))
+        }
+    }
+
+    #[test]
+    fn registered_tools_are_declared_and_found() {
+        let tools = ToolRegistry::default().with_tool(Probe);
+        let functions = tools.tool_functions();
+        let probe = functions.last().expect("probe declared");
+        assert_eq!(probe["name"], "probe");
+        assert_eq!(probe["description"], "Probe a sensor");
+
+        assert_eq!(tools.get("sensor_probe").map(|t| t.name()), Some("probe"));
+        assert_eq!(
+            tools.get("azure_data_explorer").map(|t| t.name()),
+            Some("adx_query")
+        );
+        assert_eq!(
+            tools.find_bracket("[probe now]").map(|t| t.name()),
+            Some("probe")
+        );
+        assert_eq!(
+            tools
+                .find_bracket("[SEARCH_REPLACE a b c]")
+                .map(|t| t.name()),
+            Some("search_replace")
+        );
+        assert!(tools.is_read_only("probe"));
+        assert!(!tools.is_read_only("run_command"));
+
+        let filtered = registry(None, &["probe"]).with_tool(Probe);
+        assert!(!filtered.effective_tools().contains(&"probe".to_string()));
+        assert!(filtered.get("probe").is_some());
+    }
+
+    #[test]
+    fn registering_a_tool_replaces_one_with_the_same_name() {
+        struct Quiet;
+
+        #[async_trait(?Send)]
+        impl Tool for Quiet {
+            fn name(&self) -> &str {
+                "read_file"
+            }
+
+            fn description(&self) -> &str {
+                "Read nothing"
+            }
+
+            fn parameters(&self) -> Value {
+                json!({"type": "object", "properties": {}})
+            }
+
+            fn risk(&self) -> ToolRisk {
+                ToolRisk::ReadOnly
+            }
+
+            async fn execute(
+                &self,
+                _args: &Value,
+                _ctx: &ToolContext<'_>,
+            ) -> HarperResult<Option<String>> {
+                Ok(None)
+            }
+        }
+
+        let tools = ToolRegistry::default().with_tool(Quiet);
+        assert_eq!(tools.tool_functions().len(), built_in_tools().len());
+        assert_eq!(tools.tool_functions()[0]["description"], "Read nothing");
+    }
+
+    #[test]
+    fn disabled_mcp_tools_are_refused() {
+        let tools = registry(None, &["mcp__*", "write_file"]);
+        assert!(tools.check("mcp__docs__search").is_err());
+        assert!(tools.check("read_file").is_ok());
+        assert!(!tools.effective_tools().contains(&"write_file".to_string()));
+    }
+}
@@ -16,11 +16,15 @@
 //!
 //! This module provides functionality for searching screenpipe's screen/audio history.
 
-use crate::core::error::HarperError;
+use crate::core::constants::tools;
+use crate::core::error::{HarperError, HarperResult};
 use crate::tools::parsing;
+use crate::tools::registry::{str_arg, Tool, ToolContext, ToolRisk};
+use async_trait::async_trait;
 use colored::*;
 use reqwest::Client;
 use serde::Deserialize;
+use serde_json::{json, Value};
 
 const DEFAULT_SCREENPIPE_URL: &str = "http://localhost:3030";
 const DEFAULT_LIMIT: usize = 10;
@@ -76,7 +80,15 @@ pub async fn search_screenpipe(response: &str) -> crate::core::error::HarperResu
     } else {
         DEFAULT_LIMIT
     };
+    search_screenpipe_direct(query, content_type, limit).await
+}
 
+/// Search screenpipe, with the arguments already parsed
+pub async fn search_screenpipe_direct(
+    query: &str,
+    content_type: &str,
+    limit: usize,
+) -> HarperResult<String> {
     let screenpipe_url =
         std::env::var("SCREENPIPE_URL").unwrap_or_else(|_| DEFAULT_SCREENPIPE_URL.to_string());
 
@@ -175,3 +187,68 @@ pub async fn search_screenpipe(response: &str) -> crate::core::error::HarperResu
 
     Ok(output)
 }
+
+/// `screenpipe`: search recorded screen text and audio transcripts
+pub struct ScreenpipeTool;
+
+#[async_trait(?Send)]
+impl Tool for ScreenpipeTool {
+    fn name(&self) -> &str {
+        "screenpipe"
+    }
+
+    fn description(&self) -> &str {
+        "Search screen text and audio transcripts recorded by screenpipe"
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "query": {
+                    "type": "string",
+                    "description": "Text to search for"
+                },
+                "content_type": {
+                    "type": "string",
+                    "description": "What to search, such as 'ocr' or 'audio'; defaults to 'ocr'"
+                },
+                "limit": {
+                    "type": "integer",
+                    "description": "Maximum number of results, defaults to 10"
+                }
+            },
+            "required": ["query"]
+        })
+    }
+
+    fn risk(&self) -> ToolRisk {
+        ToolRisk::ReadOnly
+    }
+
+    fn bracket_prefix(&self) -> Option<&str> {
+        Some(tools::SCREENPIPE)
+    }
+
+    async fn execute(&self, args: &Value, _ctx: &ToolContext<'_>) -> HarperResult<Option<String>> {
+        let Some(query) = str_arg(args, "query") else {
+            return Ok(None);
+        };
+        let content_type = str_arg(args, "content_type").unwrap_or("ocr");
+        let limit = args
+            .get("limit")
+            .and_then(|v| v.as_u64())
+            .map_or(DEFAULT_LIMIT, |limit| limit as usize);
+        search_screenpipe_direct(query, content_type, limit)
+            .await
+            .map(Some)
+    }
+
+    async fn execute_bracket(
+        &self,
+        call: &str,
+        _ctx: &ToolContext<'_>,
+    ) -> HarperResult<Option<String>> {
+        search_screenpipe(call).await.map(Some)
+    }
+}
@@ -17,15 +17,21 @@
 //! This module provides functionality for executing shell commands
 //! with safety checks and user approval.
 
+use crate::core::constants::tools;
+use crate::core::error::HarperResult;
 use crate::core::plan::PlanJobStatus;
 use crate::core::{error::HarperError, ApiConfig};
 use crate::memory::storage::{self, CommandLogRecord};
 use crate::runtime::config::{ApprovalProfile, ExecPolicyConfig, SandboxProfile};
 use crate::tools::parsing;
+use crate::tools::registry::{str_arg, Tool, ToolContext, ToolRisk};
+use async_trait::async_trait;
 use colored::*;
+use harper_sandbox::shell::{self as shell_parser, Script};
 use harper_sandbox::{Sandbox, SandboxRequest};
 use rusqlite::Connection;
 use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
 use std::io::{self, Write};
 use std::path::{Path, PathBuf};
 use std::time::Instant;
@@ -35,6 +41,12 @@ use tokio::sync::mpsc;
 
 const OUTPUT_PREVIEW_LIMIT: usize = 512;
 
+const COMMAND_CHAINS_REFUSED: &str = "Pipelines and command chains (|, ;, && or ||) are not allowed; run one command at a time, or set allow_command_chains under [exec_policy].";
+
+const REDIRECTS_AND_SUBSTITUTIONS_REFUSED: &str = "File redirections (<, >, >>), $VAR, $(...) or backtick expansions and ( ... ) subshells are not allowed; run the command on its own with literal arguments, or set allow_redirects_and_substitutions under [exec_policy].";
+
+const EXPANDED_PROGRAM_REFUSED: &str = "Program names built from $VAR, $(...) or backtick expansions are not allowed; name the program directly.";
+
 /// Context for persisting command audit logs
 pub struct CommandAuditContext<'a> {
     pub conn: &'a Connection,
@@ -50,6 +62,36 @@ pub struct CommandSandboxIntent {
     pub retry_policy: Option<CommandRetryPolicy>,
 }
 
+impl CommandSandboxIntent {
+    /// Intent declared alongside a `run_command` call
+    pub fn from_tool_args(args: &Value) -> Self {
+        let paths = |key: &str| {
+            args.get(key)
+                .and_then(|v| v.as_array())
+                .into_iter()
+                .flatten()
+                .filter_map(|v| v.as_str())
+                .map(PathBuf::from)
+                .collect()
+        };
+        Self {
+            declared_read_paths: paths("declared_read_paths"),
+            declared_write_paths: paths("declared_write_paths"),
+            requires_network: args
+                .get("requires_network")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false),
+            retry_policy: args
+                .get("retry_policy")
+                .and_then(|v| v.as_str())
+                .and_then(|value| {
+                    serde_json::from_value::<CommandRetryPolicy>(Value::String(value.to_string()))
+                        .ok()
+                }),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "snake_case")]
 pub enum CommandRetryPolicy {
@@ -66,6 +108,8 @@ struct CommandAttemptResult {
     stderr_preview: Option<String>,
     output_preview: Option<String>,
     has_error_output: bool,
+    /// Set when a sandbox resource limit stopped the command; never retried
+    limit_message: Option<String>,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -157,13 +201,23 @@ fn build_sandbox_request(
     command_str: &str,
     intent: Option<&CommandSandboxIntent>,
 ) -> crate::core::error::HarperResult<SandboxRequest> {
-    let args = parsing::parse_quoted_args(command_str)?;
-    let (command, rest) = args
+    let script =
+        shell_parser::parse(command_str).map_err(|e| HarperError::Command(e.to_string()))?;
+    let words = script
+        .single_command()
+        .map(|command| command.words.iter().map(|word| word.text.clone()))
+        .ok_or_else(|| {
+            HarperError::Command(
+                "The sandbox runs a single command without pipes, redirects or substitutions"
+                    .to_string(),
+            )
+        })?
+        .collect::<Vec<_>>();
+    let (command, rest) = words
         .split_first()
         .ok_or_else(|| HarperError::Command("No command provided".to_string()))?;
 
     let working_dir = std::env::current_dir().map_err(|e| HarperError::Io(e.to_string()))?;
-    let rest_args: Vec<&str> = rest.iter().map(String::as_str).collect();
     let (declared_read_paths, declared_write_paths, requires_network, _retry_policy) =
         if let Some(intent) = intent {
             (
@@ -173,8 +227,13 @@ fn build_sandbox_request(
                 intent.retry_policy.clone(),
             )
         } else {
-            let (reads, writes) = infer_path_intent(command, &rest_args);
-            (reads, writes, looks_like_network_command(command), None)
+            let inferred = infer_script_intent(&script);
+            (
+                inferred.declared_read_paths,
+                inferred.declared_write_paths,
+                inferred.requires_network,
+                None,
+            )
         };
 
     Ok(SandboxRequest {
@@ -370,6 +429,44 @@ fn infer_tar_path_intent(args: &[&str]) -> (Vec<PathBuf>, Vec<PathBuf>) {
     }
 }
 
+/// The program and arguments of every command a script runs
+fn command_lines(script: &Script) -> Vec<String> {
+    script
+        .simple_commands()
+        .into_iter()
+        .filter_map(|command| command.command_line())
+        .collect()
+}
+
+/// Read/write paths and network use of every command and redirection in a script
+fn infer_script_intent(script: &Script) -> CommandSandboxIntent {
+    let mut intent = CommandSandboxIntent::default();
+    for command in script.simple_commands() {
+        let Some(program) = command.program() else {
+            continue;
+        };
+        let (reads, writes) = infer_path_intent(program, &command.args());
+        intent.declared_read_paths.extend(reads);
+        intent.declared_write_paths.extend(writes);
+        intent.requires_network |= looks_like_network_command(program);
+    }
+    for redirect in script.redirects() {
+        let Some(path) = redirect.path() else {
+            continue;
+        };
+        if matches!(path, "/dev/null" | "/dev/stdout" | "/dev/stderr") {
+            continue;
+        }
+        if redirect.op.reads() {
+            intent.declared_read_paths.push(PathBuf::from(path));
+        }
+        if redirect.op.writes() {
+            intent.declared_write_paths.push(PathBuf::from(path));
+        }
+    }
+    intent
+}
+
 fn looks_like_network_command(command: &str) -> bool {
     matches!(
         command_basename(command),
@@ -400,9 +497,22 @@ fn configured_sandbox(exec_policy: &ExecPolicyConfig) -> Option<harper_sandbox::
         readonly_home: sandbox.readonly_home.unwrap_or(false),
         network_access: sandbox.network_access.unwrap_or(true),
         max_execution_time_secs: sandbox.max_execution_time_secs,
+        max_memory_mb: sandbox.max_memory_mb,
+        max_cpu_time_secs: sandbox.max_cpu_time_secs,
+        max_processes: sandbox.max_processes,
+        max_open_files: sandbox.max_open_files,
+        max_output_bytes: sandbox.max_output_bytes,
+        backend: sandbox.backend,
     })
 }
 
+/// The sandbox commands run in, when `[exec_policy.sandbox]` enables it
+pub(crate) fn enabled_sandbox(exec_policy: &ExecPolicyConfig) -> Option<Sandbox> {
+    configured_sandbox(exec_policy)
+        .filter(|config| config.enabled)
+        .map(Sandbox::new)
+}
+
 fn sandbox_profile_name(profile: SandboxProfile) -> &'static str {
     match profile {
         SandboxProfile::Disabled => "disabled",
@@ -472,7 +582,7 @@ fn writes_within_configured_writable_dirs(
     })
 }
 
-fn approval_required_for_command(
+pub(crate) fn approval_required_for_command(
     exec_policy: &ExecPolicyConfig,
     command_str: &str,
     intent: Option<&CommandSandboxIntent>,
@@ -480,16 +590,25 @@ fn approval_required_for_command(
     match exec_policy.effective_approval_profile() {
         ApprovalProfile::Strict => true,
         ApprovalProfile::AllowListed => {
-            let allowlisted = exec_policy
-                .allowed_commands
-                .as_ref()
-                .map(|allowed| allowed.iter().any(|cmd| command_str.starts_with(cmd)))
-                .unwrap_or(false);
-            let intent_requires_approval = intent.is_some_and(|intent| {
-                intent.requires_network
-                    || (!intent.declared_write_paths.is_empty()
-                        && !writes_within_configured_writable_dirs(exec_policy, intent))
-            });
+            let Ok(script) = shell_parser::parse(command_str) else {
+                return true;
+            };
+            let command_lines = command_lines(&script);
+            let allowlisted = !command_lines.is_empty()
+                && command_lines
+                    .iter()
+                    .all(|line| exec_policy.allows_command(line));
+            let inferred;
+            let intent = match intent {
+                Some(intent) => intent,
+                None => {
+                    inferred = infer_script_intent(&script);
+                    &inferred
+                }
+            };
+            let intent_requires_approval = intent.requires_network
+                || (!intent.declared_write_paths.is_empty()
+                    && !writes_within_configured_writable_dirs(exec_policy, intent));
             !allowlisted || intent_requires_approval
         }
         ApprovalProfile::AllowAll => false,
@@ -576,8 +695,35 @@ async fn execute_sandboxed_once(
     } else {
         preview_text(&stdout_text)
     };
-    let has_error_output = !result.output.status.success() || !stderr_text.trim().is_empty();
-
+    let limit_message = result
+        .limit_error()
+        .map(|error| sandbox_limit_message(&error));
+    let unenforced_note = unenforced_limits_message(&result.unenforced_limits);
+    let success = result.output.status.success() && limit_message.is_none();
+    let has_error_output = !success || !stderr_text.trim().is_empty();
+
+    if let Some(note) = &unenforced_note {
+        emit_command_output(
+            runtime_events,
+            audit_ctx.and_then(|ctx| ctx.session_id),
+            command_str,
+            format!("{}\n", note),
+            false,
+            false,
+        )
+        .await;
+    }
+    if let Some(message) = &limit_message {
+        emit_command_output(
+            runtime_events,
+            audit_ctx.and_then(|ctx| ctx.session_id),
+            command_str,
+            format!("{}\n", message),
+            true,
+            false,
+        )
+        .await;
+    }
     emit_command_output(
         runtime_events,
         audit_ctx.and_then(|ctx| ctx.session_id),
@@ -588,19 +734,29 @@ async fn execute_sandboxed_once(
     )
     .await;
 
+    let mut output_text = match &limit_message {
+        // Whatever was captured before the kill helps explain it
+        Some(message) => format!("{}{}{}", stdout_text, stderr_text, message),
+        None if success => stdout_text,
+        None => stderr_text,
+    };
+    if let Some(note) = unenforced_note {
+        if !output_text.is_empty() && !output_text.ends_with('\n') {
+            output_text.push('\n');
+        }
+        output_text.push_str(&note);
+    }
+
     Ok(CommandAttemptResult {
-        output_text: if result.output.status.success() {
-            stdout_text
-        } else {
-            stderr_text
-        },
-        success: result.output.status.success(),
+        output_text,
+        success,
         exit_code,
         duration_ms,
         stdout_preview,
         stderr_preview,
         output_preview,
         has_error_output,
+        limit_message,
     })
 }
 
@@ -751,9 +907,135 @@ async fn execute_direct_once(
         stderr_preview,
         output_preview,
         has_error_output,
+        limit_message: None,
+    })
+}
+
+/// Tells the model which limit stopped a command and what it can do about it
+fn sandbox_limit_message(error: &harper_sandbox::SandboxError) -> String {
+    let advice = match error {
+        harper_sandbox::SandboxError::OutputLimitExceeded { .. } => {
+            "Filter or page the output, for example with head, tail or grep"
+        }
+        harper_sandbox::SandboxError::CpuTimeLimitExceeded { .. } => {
+            "Run a smaller piece of the work, such as a single test or package"
+        }
+        _ => "Reduce parallelism or run a smaller piece of the work",
+    };
+    format!(
+        "[sandbox] {} and was stopped. {}, or raise the limit under [exec_policy.sandbox].",
+        error, advice
+    )
+}
+
+/// Tells the model which configured caps the host could not apply
+fn unenforced_limits_message(limits: &[harper_sandbox::ResourceLimit]) -> Option<String> {
+    let names: Vec<String> = limits
+        .iter()
+        .map(|limit| match limit {
+            harper_sandbox::ResourceLimit::Memory { limit_mb } => {
+                format!("max_memory_mb ({} MB)", limit_mb)
+            }
+            harper_sandbox::ResourceLimit::Processes { limit } => {
+                format!("max_processes ({})", limit)
+            }
+            other => format!("{:?}", other),
+        })
+        .collect();
+    (!names.is_empty()).then(|| {
+        format!(
+            "[sandbox] {} not applied: this host does not delegate the cgroup v2 memory and pids controllers.",
+            names.join(" and ")
+        )
     })
 }
 
+/// Why the exec policy refuses `command_str` outright, before anyone is asked
+/// to approve it
+pub(crate) fn command_refusal(exec_policy: &ExecPolicyConfig, command_str: &str) -> Option<String> {
+    // Security check to prevent shell injection and dangerous commands
+    // Note: This is a defense-in-depth measure. The primary security comes from user approval.
+    // The command is parsed as POSIX shell so every program, redirection and substitution it
+    // runs goes through the policy checks below. Background jobs are refused, and so are
+    // pipelines and command chains unless `allow_command_chains` is set, and output
+    // file redirections, expansions and subshells unless `allow_redirects_and_substitutions`
+    // is set. A program name built from an expansion cannot be matched against the
+    // policy, so it is refused too.
+    let script = match shell_parser::parse(command_str) {
+        Ok(script) => script,
+        Err(err) => return Some(format!("Command could not be parsed: {}", err)),
+    };
+    if script.has_background_jobs() {
+        return Some("Background jobs (&) are not allowed.".to_string());
+    }
+    if script.has_command_chains() && !exec_policy.allows_command_chains() {
+        return Some(COMMAND_CHAINS_REFUSED.to_string());
+    }
+    if script.has_expanded_programs() {
+        return Some(EXPANDED_PROGRAM_REFUSED.to_string());
+    }
+    let shell_features = script.has_output_redirects()
+        || script.has_input_redirects()
+        || script.has_expansions()
+        || script.has_subshells();
+    if shell_features && !exec_policy.allows_redirects_and_substitutions() {
+        return Some(REDIRECTS_AND_SUBSTITUTIONS_REFUSED.to_string());
+    }
+
+    // Additional check for common dangerous patterns
+    let dangerous_patterns = [
+        "rm -rf",
+        "rmdir",
+        "del ",
+        "fdisk",
+        "mkfs",
+        "dd if=",
+        "shutdown",
+        "reboot",
+        "halt",
+        "poweroff",
+        "sudo",
+        "su ",
+        "chmod 777",
+        "chown root",
+        "passwd",
+        "/etc/",
+        "/bin/",
+        "/sbin/",
+        "/usr/bin/",
+        "/usr/sbin/",
+    ];
+    if let Some(pattern) = dangerous_patterns
+        .iter()
+        .find(|pattern| command_str.contains(*pattern))
+    {
+        return Some(format!(
+            "Command contains potentially dangerous pattern: '{}'. \
+                    This command is not allowed for security reasons.",
+            pattern
+        ));
+    }
+
+    // Check exec policy
+    for line in &command_lines(&script) {
+        if let Some(pattern) = exec_policy.blocked_command_pattern(line) {
+            return Some(format!(
+                "Command '{}' is blocked by exec policy (matches '{}').",
+                line, pattern
+            ));
+        }
+    }
+
+    if enabled_sandbox(exec_policy).is_some() && script.single_command().is_none() {
+        return Some(
+            "Only a single command without pipes, redirects or substitutions can run \
+             inside the sandbox; run each command separately."
+                .to_string(),
+        );
+    }
+    None
+}
+
 /// Execute a shell command with safety checks
 pub async fn execute_command(
     response: &str,
@@ -783,13 +1065,7 @@ pub async fn execute_command(
         return Err(HarperError::Command("No command provided".to_string()));
     }
 
-    // Security check to prevent shell injection and dangerous commands
-    // Note: This is a defense-in-depth measure. The primary security comes from user approval.
-    // We allow wildcards (*, ?) and git revision syntax (~, ^) but block chaining and subshells.
-    let dangerous_chars = [';', '|', '&', '`', '$', '(', ')', '<', '>', '\n', '\r'];
-    if command_str.chars().any(|c| dangerous_chars.contains(&c)) {
-        let message = "Command contains potentially dangerous shell metacharacters (like ;, |, &) or newlines. \
-             Command chaining and redirection are not allowed for security.";
+    if let Some(err) = command_refusal(exec_policy, command_str) {
         maybe_log_command(
             audit_ctx,
             command_str,
@@ -800,81 +1076,12 @@ pub async fn execute_command(
             None,
             None,
             None,
-            Some(message.to_string()),
+            Some(err.clone()),
         );
-        return Err(HarperError::Command(message.to_string()));
+        return Err(HarperError::Command(err));
     }
 
-    // Additional check for common dangerous patterns
-    let dangerous_patterns = [
-        "rm -rf",
-        "rmdir",
-        "del ",
-        "fdisk",
-        "mkfs",
-        "dd if=",
-        "shutdown",
-        "reboot",
-        "halt",
-        "poweroff",
-        "sudo",
-        "su ",
-        "chmod 777",
-        "chown root",
-        "passwd",
-        "/etc/",
-        "/bin/",
-        "/sbin/",
-        "/usr/bin/",
-        "/usr/sbin/",
-    ];
-
-    for pattern in &dangerous_patterns {
-        if command_str.contains(pattern) {
-            let err = format!(
-                "Command contains potentially dangerous pattern: '{}'. \
-                        This command is not allowed for security reasons.",
-                pattern
-            );
-            maybe_log_command(
-                audit_ctx,
-                command_str,
-                "blocked",
-                true,
-                false,
-                None,
-                None,
-                None,
-                None,
-                Some(err.clone()),
-            );
-            return Err(HarperError::Command(err));
-        }
-    }
-
-    // Check exec policy
-    let mut requires_approval = true;
-
-    if let Some(blocked) = &exec_policy.blocked_commands {
-        if blocked.iter().any(|cmd| command_str.starts_with(cmd)) {
-            let err = format!("Command '{}' is blocked by exec policy.", command_str);
-            maybe_log_command(
-                audit_ctx,
-                command_str,
-                "blocked",
-                requires_approval,
-                false,
-                None,
-                None,
-                None,
-                None,
-                Some(err.clone()),
-            );
-            return Err(HarperError::Command(err));
-        }
-    }
-
-    requires_approval =
+    let requires_approval =
         approval_required_for_command(exec_policy, command_str, resolved_intent.as_ref());
     let mut approved = !requires_approval;
 
@@ -959,9 +1166,7 @@ pub async fn execute_command(
         approved = true;
     }
 
-    let sandbox = configured_sandbox(exec_policy)
-        .filter(|config| config.enabled)
-        .map(Sandbox::new);
+    let sandbox = enabled_sandbox(exec_policy);
     let sandbox_status = sandbox_status_line(exec_policy, sandbox.as_ref());
 
     if let Some(ctx) =
@@ -1028,6 +1233,7 @@ pub async fn execute_command(
         };
 
         if attempt_result.success
+            || attempt_result.limit_message.is_some()
             || !retry_safe
             || attempt >= exec_policy.effective_retry_max_attempts()
         {
@@ -1045,8 +1251,10 @@ pub async fn execute_command(
                 Some(attempt_result.duration_ms),
                 attempt_result.stdout_preview,
                 attempt_result.stderr_preview,
-                (!attempt_result.success)
-                    .then_some("Command exited with non-zero status".to_string()),
+                attempt_result.limit_message.clone().or_else(|| {
+                    (!attempt_result.success)
+                        .then_some("Command exited with non-zero status".to_string())
+                }),
             );
 
             if let Some(ctx) =
@@ -1117,7 +1325,7 @@ async fn emit_plan_update(
 }
 
 #[allow(clippy::too_many_arguments)]
-fn maybe_log_command(
+pub(crate) fn maybe_log_command(
     audit_ctx: Option<&CommandAuditContext>,
     command: &str,
     status: &str,
@@ -1149,7 +1357,7 @@ fn maybe_log_command(
     }
 }
 
-fn preview_text(text: &str) -> Option<String> {
+pub(crate) fn preview_text(text: &str) -> Option<String> {
     let trimmed = text.trim();
     if trimmed.is_empty() {
         return None;
@@ -1175,13 +1383,104 @@ fn bytes_to_preview(bytes: &[u8]) -> Option<String> {
     Some(preview)
 }
 
+/// `run_command`: run a shell command under the exec policy and sandbox
+pub struct RunCommandTool;
+
+#[async_trait(?Send)]
+impl Tool for RunCommandTool {
+    fn name(&self) -> &str {
+        "run_command"
+    }
+
+    fn description(&self) -> &str {
+        "Run a shell command"
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "command": {
+                    "type": "string",
+                    "description": "The command to run"
+                },
+                "declared_read_paths": {
+                    "type": "array",
+                    "items": {"type": "string"},
+                    "description": "Optional explicit paths the command will read"
+                },
+                "declared_write_paths": {
+                    "type": "array",
+                    "items": {"type": "string"},
+                    "description": "Optional explicit paths the command will write"
+                },
+                "requires_network": {
+                    "type": "boolean",
+                    "description": "Set true if the command needs network access"
+                },
+                "retry_policy": {
+                    "type": "string",
+                    "enum": ["never", "safe"],
+                    "description": "Optional explicit retry policy for transient-safe commands"
+                }
+            },
+            "required": ["command"]
+        })
+    }
+
+    fn risk(&self) -> ToolRisk {
+        ToolRisk::Execute
+    }
+
+    fn bracket_prefix(&self) -> Option<&str> {
+        Some(tools::RUN_COMMAND)
+    }
+
+    async fn execute(&self, args: &Value, ctx: &ToolContext<'_>) -> HarperResult<Option<String>> {
+        let Some(command) = str_arg(args, "command") else {
+            return Ok(None);
+        };
+        let bracket_command = format!("[RUN_COMMAND {}]", command);
+        let sandbox_intent = CommandSandboxIntent::from_tool_args(args);
+        execute_command(
+            &bracket_command,
+            ctx.config,
+            ctx.exec_policy,
+            Some(&sandbox_intent),
+            Some(&ctx.audit("tool_run_command")),
+            ctx.approver.clone(),
+            ctx.runtime_events.clone(),
+        )
+        .await
+        .map(Some)
+    }
+
+    async fn execute_bracket(
+        &self,
+        call: &str,
+        ctx: &ToolContext<'_>,
+    ) -> HarperResult<Option<String>> {
+        execute_command(
+            call,
+            ctx.config,
+            ctx.exec_policy,
+            None,
+            Some(&ctx.audit("tool_run_command")),
+            ctx.approver.clone(),
+            ctx.runtime_events.clone(),
+        )
+        .await
+        .map(Some)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::{
         approval_required_for_command, autonomous_retry_safe, build_sandbox_request,
-        configured_sandbox, execute_command, infer_path_intent, looks_like_network_command,
-        parse_run_command_response, sandbox_status_line, CommandAuditContext, CommandRetryPolicy,
-        CommandSandboxIntent,
+        configured_sandbox, execute_command, infer_path_intent, infer_script_intent,
+        looks_like_network_command, parse_run_command_response, sandbox_status_line,
+        unenforced_limits_message, CommandAuditContext, CommandRetryPolicy, CommandSandboxIntent,
     };
     use crate::core::plan::{PlanFollowup, PlanItem, PlanState, PlanStepStatus};
     use crate::core::{ApiConfig, ApiProvider};
@@ -1196,6 +1495,9 @@ mod tests {
             api_key: "test-key".to_string(),
             base_url: "https://api.openai.com/v1/chat/completions".to_string(),
             model_name: "gpt-5.5".to_string(),
+            headers: Default::default(),
+            parameters: Default::default(),
+            fallback: Default::default(),
         }
     }
 
@@ -1366,6 +1668,8 @@ mod tests {
             execution_strategy: None,
             allowed_commands: Some(vec!["git".to_string()]),
             blocked_commands: Some(vec!["rm".to_string()]),
+            allow_command_chains: None,
+            allow_redirects_and_substitutions: None,
             sandbox_profile: Some(SandboxProfile::Disabled),
             sandbox: Some(RuntimeSandboxConfig {
                 enabled: Some(true),
@@ -1374,6 +1678,12 @@ mod tests {
                 network_access: Some(false),
                 readonly_home: Some(true),
                 max_execution_time_secs: Some(15),
+                max_memory_mb: None,
+                max_cpu_time_secs: None,
+                max_processes: None,
+                max_open_files: None,
+                max_output_bytes: None,
+                backend: None,
             }),
             retry_max_attempts: None,
             retry_network_commands: None,
@@ -1401,6 +1711,8 @@ mod tests {
             execution_strategy: None,
             allowed_commands: None,
             blocked_commands: None,
+            allow_command_chains: None,
+            allow_redirects_and_substitutions: None,
             sandbox_profile: Some(SandboxProfile::Disabled),
             sandbox: None,
             retry_max_attempts: None,
@@ -1418,6 +1730,8 @@ mod tests {
             execution_strategy: None,
             allowed_commands: None,
             blocked_commands: None,
+            allow_command_chains: None,
+            allow_redirects_and_substitutions: None,
             sandbox_profile: Some(SandboxProfile::Workspace),
             sandbox: None,
             retry_max_attempts: None,
@@ -1438,6 +1752,8 @@ mod tests {
             execution_strategy: None,
             allowed_commands: None,
             blocked_commands: None,
+            allow_command_chains: None,
+            allow_redirects_and_substitutions: None,
             sandbox_profile: Some(SandboxProfile::Disabled),
             sandbox: None,
             retry_max_attempts: None,
@@ -1458,6 +1774,8 @@ mod tests {
             execution_strategy: None,
             allowed_commands: Some(vec!["git".to_string()]),
             blocked_commands: None,
+            allow_command_chains: None,
+            allow_redirects_and_substitutions: None,
             sandbox_profile: Some(SandboxProfile::Disabled),
             sandbox: None,
             retry_max_attempts: None,
@@ -1478,6 +1796,8 @@ mod tests {
             execution_strategy: None,
             allowed_commands: Some(vec!["git".to_string()]),
             blocked_commands: None,
+            allow_command_chains: None,
+            allow_redirects_and_substitutions: None,
             sandbox_profile: Some(SandboxProfile::Disabled),
             sandbox: None,
             retry_max_attempts: None,
@@ -1490,6 +1810,86 @@ mod tests {
             None
         ));
         assert!(approval_required_for_command(&exec_policy, "ls -la", None));
+        assert!(approval_required_for_command(
+            &exec_policy,
+            "./git status",
+            None
+        ));
+        assert!(approval_required_for_command(
+            &exec_policy,
+            "/tmp/x/git",
+            None
+        ));
+    }
+
+    #[test]
+    fn infer_script_intent_covers_redirects_pipelines_and_substitutions() {
+        let intent = |line: &str| {
+            infer_script_intent(&harper_sandbox::shell::parse(line).expect("parse command"))
+        };
+
+        let redirect = intent("echo x > ~/.ssh/config");
+        assert_eq!(
+            redirect.declared_write_paths,
+            vec![std::path::PathBuf::from("~/.ssh/config")]
+        );
+
+        let pipeline = intent("curl -s https://example.com | tee ./out.log");
+        assert!(pipeline.requires_network);
+        assert_eq!(
+            pipeline.declared_write_paths,
+            vec![std::path::PathBuf::from("./out.log")]
+        );
+
+        let substitution = intent("echo \"$(wget -qO- example.com)\" < ./in.txt 2>/dev/null");
+        assert!(substitution.requires_network);
+        assert_eq!(
+            substitution.declared_read_paths,
+            vec![std::path::PathBuf::from("./in.txt")]
+        );
+        assert!(substitution.declared_write_paths.is_empty());
+    }
+
+    #[test]
+    fn approval_profile_allow_listed_checks_every_segment() {
+        let exec_policy = ExecPolicyConfig {
+            approval_profile: Some(ApprovalProfile::AllowListed),
+            execution_strategy: None,
+            allowed_commands: Some(vec!["git log *".to_string(), "grep".to_string()]),
+            blocked_commands: None,
+            allow_command_chains: None,
+            allow_redirects_and_substitutions: None,
+            sandbox_profile: Some(SandboxProfile::Disabled),
+            sandbox: None,
+            retry_max_attempts: None,
+            retry_network_commands: None,
+            retry_write_commands: None,
+        };
+        assert!(!approval_required_for_command(
+            &exec_policy,
+            "git log --oneline | grep fix",
+            None
+        ));
+        assert!(approval_required_for_command(
+            &exec_policy,
+            "git log --oneline | sh",
+            None
+        ));
+        assert!(approval_required_for_command(
+            &exec_policy,
+            "git push && git log",
+            None
+        ));
+        assert!(approval_required_for_command(
+            &exec_policy,
+            "grep $(sh -c id) notes.txt",
+            None
+        ));
+        assert!(approval_required_for_command(
+            &exec_policy,
+            "git log > ./history.txt",
+            None
+        ));
     }
 
     #[test]
@@ -1499,6 +1899,8 @@ mod tests {
             execution_strategy: None,
             allowed_commands: Some(vec!["cp".to_string()]),
             blocked_commands: None,
+            allow_command_chains: None,
+            allow_redirects_and_substitutions: None,
             sandbox_profile: Some(SandboxProfile::Workspace),
             sandbox: Some(crate::runtime::config::SandboxConfig {
                 enabled: Some(true),
@@ -1507,6 +1909,12 @@ mod tests {
                 network_access: Some(false),
                 readonly_home: Some(true),
                 max_execution_time_secs: Some(30),
+                max_memory_mb: None,
+                max_cpu_time_secs: None,
+                max_processes: None,
+                max_open_files: None,
+                max_output_bytes: None,
+                backend: None,
             }),
             retry_max_attempts: None,
             retry_network_commands: None,
@@ -1532,6 +1940,8 @@ mod tests {
             execution_strategy: None,
             allowed_commands: Some(vec!["cp".to_string()]),
             blocked_commands: None,
+            allow_command_chains: None,
+            allow_redirects_and_substitutions: None,
             sandbox_profile: Some(SandboxProfile::Workspace),
             sandbox: Some(crate::runtime::config::SandboxConfig {
                 enabled: Some(true),
@@ -1540,6 +1950,12 @@ mod tests {
                 network_access: Some(false),
                 readonly_home: Some(true),
                 max_execution_time_secs: Some(30),
+                max_memory_mb: None,
+                max_cpu_time_secs: None,
+                max_processes: None,
+                max_open_files: None,
+                max_output_bytes: None,
+                backend: None,
             }),
             retry_max_attempts: None,
             retry_network_commands: None,
@@ -1565,6 +1981,8 @@ mod tests {
             execution_strategy: None,
             allowed_commands: Some(vec!["curl".to_string()]),
             blocked_commands: None,
+            allow_command_chains: None,
+            allow_redirects_and_substitutions: None,
             sandbox_profile: Some(SandboxProfile::Disabled),
             sandbox: None,
             retry_max_attempts: None,
@@ -1620,6 +2038,12 @@ mod tests {
                 network_access: None,
                 readonly_home: None,
                 max_execution_time_secs: None,
+                max_memory_mb: None,
+                max_cpu_time_secs: None,
+                max_processes: None,
+                max_open_files: None,
+                max_output_bytes: None,
+                backend: None,
             }),
             ..Default::default()
         };
@@ -1705,6 +2129,8 @@ mod tests {
             execution_strategy: None,
             allowed_commands: None,
             blocked_commands: None,
+            allow_command_chains: None,
+            allow_redirects_and_substitutions: None,
             sandbox_profile: Some(SandboxProfile::Disabled),
             sandbox: None,
             retry_max_attempts: None,
@@ -1740,4 +2166,347 @@ mod tests {
             Some(PlanFollowup::RetryOrReplan { retry_count: 2, .. })
         ));
     }
+
+    #[tokio::test]
+    async fn execute_command_refuses_blocked_segment_under_any_profile() {
+        let exec_policy = ExecPolicyConfig {
+            approval_profile: Some(ApprovalProfile::AllowAll),
+            execution_strategy: None,
+            allowed_commands: None,
+            blocked_commands: Some(vec!["git push --force*".to_string()]),
+            allow_command_chains: Some(true),
+            allow_redirects_and_substitutions: None,
+            sandbox_profile: Some(SandboxProfile::Disabled),
+            sandbox: None,
+            retry_max_attempts: None,
+            retry_network_commands: None,
+            retry_write_commands: None,
+        };
+
+        let err = execute_command(
+            "[RUN_COMMAND git status && git push --force-with-lease]",
+            &test_config(),
+            &exec_policy,
+            None,
+            None,
+            None,
+            None,
+        )
+        .await
+        .expect_err("blocked segment should be refused");
+        let message = err.to_string();
+        assert!(message.contains("git push --force-with-lease"));
+        assert!(message.contains("git push --force*"));
+    }
+
+    #[tokio::test]
+    async fn execute_command_refuses_chains_unless_allowed() {
+        let exec_policy = ExecPolicyConfig {
+            approval_profile: Some(ApprovalProfile::AllowAll),
+            execution_strategy: None,
+            allowed_commands: None,
+            blocked_commands: None,
+            allow_command_chains: None,
+            allow_redirects_and_substitutions: None,
+            sandbox_profile: Some(SandboxProfile::Disabled),
+            sandbox: None,
+            retry_max_attempts: None,
+            retry_network_commands: None,
+            retry_write_commands: None,
+        };
+
+        for line in [
+            "echo harper | tr a-z A-Z",
+            "true && echo harper",
+            "false || echo harper",
+            "echo a; echo b",
+            "echo $(echo a || echo b)",
+        ] {
+            let err = execute_command(
+                &format!("[RUN_COMMAND {}]", line),
+                &test_config(),
+                &exec_policy,
+                None,
+                None,
+                None,
+                None,
+            )
+            .await
+            .expect_err("chains are refused by default");
+            assert!(
+                err.to_string().contains("allow_command_chains"),
+                "{line:?}: {err}"
+            );
+        }
+
+        let exec_policy = ExecPolicyConfig {
+            allow_command_chains: Some(true),
+            ..exec_policy
+        };
+        let output = execute_command(
+            "[RUN_COMMAND false || echo fallback]",
+            &test_config(),
+            &exec_policy,
+            None,
+            None,
+            None,
+            None,
+        )
+        .await
+        .expect("|| chain runs once allowed");
+        assert!(output.contains("fallback"));
+    }
+
+    #[tokio::test]
+    async fn execute_command_refuses_redirects_and_substitutions_unless_allowed() {
+        let exec_policy = ExecPolicyConfig {
+            approval_profile: Some(ApprovalProfile::AllowAll),
+            execution_strategy: None,
+            allowed_commands: None,
+            blocked_commands: None,
+            allow_command_chains: None,
+            allow_redirects_and_substitutions: None,
+            sandbox_profile: Some(SandboxProfile::Disabled),
+            sandbox: None,
+            retry_max_attempts: None,
+            retry_network_commands: None,
+            retry_write_commands: None,
+        };
+
+        for line in [
+            "printf x > ~/.bashrc",
+            "echo $(cat ~/.ssh/id_rsa)",
+            "echo `id`",
+            "echo x >> notes.txt",
+            "diff <(ls a) <(ls b)",
+            "echo ${x:-$(rm -rf /)}",
+            "echo $(( $(id) ))",
+            "cat $HOME/.ssh/id_rsa",
+            "cat < ~/.ssh/id_rsa",
+            "(curl example.com)",
+        ] {
+            let err = execute_command(
+                &format!("[RUN_COMMAND {}]", line),
+                &test_config(),
+                &exec_policy,
+                None,
+                None,
+                None,
+                None,
+            )
+            .await
+            .expect_err("redirects and substitutions are refused by default");
+            let message = err.to_string();
+            assert!(
+                message.contains("allow_redirects_and_substitutions")
+                    || message.contains("could not be parsed"),
+                "{line:?}: {message}"
+            );
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join("out.txt");
+        let exec_policy = ExecPolicyConfig {
+            allow_redirects_and_substitutions: Some(true),
+            ..exec_policy
+        };
+        execute_command(
+            &format!("[RUN_COMMAND echo $(echo harper) > {}]", target.display()),
+            &test_config(),
+            &exec_policy,
+            None,
+            None,
+            None,
+            None,
+        )
+        .await
+        .expect("redirect runs once allowed");
+        assert_eq!(std::fs::read_to_string(&target).unwrap().trim(), "harper");
+    }
+
+    #[tokio::test]
+    async fn execute_command_refuses_expanded_programs_and_nested_background_jobs() {
+        let exec_policy = ExecPolicyConfig {
+            approval_profile: Some(ApprovalProfile::AllowAll),
+            execution_strategy: None,
+            allowed_commands: None,
+            blocked_commands: Some(vec!["rm".to_string()]),
+            allow_command_chains: Some(true),
+            allow_redirects_and_substitutions: None,
+            sandbox_profile: Some(SandboxProfile::Disabled),
+            sandbox: None,
+            retry_max_attempts: None,
+            retry_network_commands: None,
+            retry_write_commands: None,
+        };
+
+        let cases = [
+            ("x=rm; $x -rf d", "Program names"),
+            ("$(printf rm) -rf d", "Program names"),
+            ("`printf rm` -rf d", "Program names"),
+            ("(sleep 100 &)", "Background jobs"),
+            ("echo $(sleep 100 &)", "Background jobs"),
+        ];
+        for (line, expected) in cases {
+            let err = execute_command(
+                &format!("[RUN_COMMAND {}]", line),
+                &test_config(),
+                &exec_policy,
+                None,
+                None,
+                None,
+                None,
+            )
+            .await
+            .expect_err("bypass should be refused");
+            assert!(err.to_string().contains(expected), "{line:?}: {err}");
+        }
+    }
+
+    #[tokio::test]
+    async fn execute_command_runs_pipelines_outside_the_sandbox() {
+        let exec_policy = ExecPolicyConfig {
+            approval_profile: Some(ApprovalProfile::AllowListed),
+            execution_strategy: None,
+            allowed_commands: Some(vec!["echo".to_string(), "tr a-z *".to_string()]),
+            blocked_commands: None,
+            allow_command_chains: Some(true),
+            allow_redirects_and_substitutions: None,
+            sandbox_profile: Some(SandboxProfile::Disabled),
+            sandbox: None,
+            retry_max_attempts: None,
+            retry_network_commands: None,
+            retry_write_commands: None,
+        };
+
+        let output = execute_command(
+            "[RUN_COMMAND echo harper | tr a-z A-Z]",
+            &test_config(),
+            &exec_policy,
+            None,
+            None,
+            None,
+            None,
+        )
+        .await
+        .expect("allow-listed pipeline should run");
+        assert!(output.contains("HARPER"));
+
+        let err = execute_command(
+            "[RUN_COMMAND echo harper &]",
+            &test_config(),
+            &exec_policy,
+            None,
+            None,
+            None,
+            None,
+        )
+        .await
+        .expect_err("background jobs are refused");
+        assert!(err.to_string().contains("Background jobs"));
+
+        let sandboxed = ExecPolicyConfig {
+            sandbox_profile: Some(SandboxProfile::Workspace),
+            ..exec_policy
+        };
+        let err = execute_command(
+            "[RUN_COMMAND echo harper | tr a-z A-Z]",
+            &test_config(),
+            &sandboxed,
+            None,
+            None,
+            None,
+            None,
+        )
+        .await
+        .expect_err("pipelines cannot run in the sandbox");
+        assert!(err.to_string().contains("inside the sandbox"));
+    }
+
+    #[test]
+    fn unenforced_limits_are_named_in_the_output_note() {
+        use harper_sandbox::ResourceLimit;
+
+        assert_eq!(unenforced_limits_message(&[]), None);
+        let note = unenforced_limits_message(&[
+            ResourceLimit::Memory { limit_mb: 256 },
+            ResourceLimit::Processes { limit: 64 },
+        ])
+        .expect("note");
+        assert!(note.contains("max_memory_mb (256 MB) and max_processes (64) not applied"));
+    }
+
+    #[tokio::test]
+    async fn execute_command_explains_sandbox_limit_kills() {
+        let exec_policy = ExecPolicyConfig {
+            approval_profile: Some(ApprovalProfile::AllowAll),
+            execution_strategy: None,
+            allowed_commands: None,
+            blocked_commands: None,
+            allow_command_chains: None,
+            allow_redirects_and_substitutions: None,
+            sandbox_profile: Some(SandboxProfile::Workspace),
+            sandbox: Some(crate::runtime::config::SandboxConfig {
+                enabled: None,
+                allowed_dirs: None,
+                writable_dirs: None,
+                network_access: None,
+                readonly_home: None,
+                max_execution_time_secs: None,
+                max_memory_mb: None,
+                max_cpu_time_secs: None,
+                max_processes: None,
+                max_open_files: None,
+                max_output_bytes: Some(100),
+                backend: None,
+            }),
+            retry_max_attempts: Some(3),
+            retry_network_commands: None,
+            retry_write_commands: None,
+        };
+        let sandbox = configured_sandbox(&exec_policy).map(harper_sandbox::Sandbox::new);
+        if !sandbox.is_some_and(|sandbox| sandbox.is_available()) {
+            return;
+        }
+
+        let output = execute_command(
+            "[RUN_COMMAND yes]",
+            &test_config(),
+            &exec_policy,
+            None,
+            None,
+            None,
+            None,
+        )
+        .await
+        .expect("limit kills are reported as output");
+
+        assert!(output.starts_with("y\ny\n"));
+        assert!(output.contains(
+            "[sandbox] Command output exceeded the 100 byte capture limit and was stopped."
+        ));
+        assert!(output.contains("[exec_policy.sandbox]"));
+    }
+
+    #[test]
+    fn sandbox_intent_reads_explicit_tool_args() {
+        let intent = CommandSandboxIntent::from_tool_args(&serde_json::json!({
+            "command": "cp ./src.txt ./build/out.txt",
+            "declared_read_paths": ["./src.txt"],
+            "declared_write_paths": ["./build/out.txt"],
+            "requires_network": true,
+            "retry_policy": "safe"
+        }));
+
+        assert_eq!(
+            intent.declared_read_paths,
+            vec![std::path::PathBuf::from("./src.txt")]
+        );
+        assert_eq!(
+            intent.declared_write_paths,
+            vec![std::path::PathBuf::from("./build/out.txt")]
+        );
+        assert!(intent.requires_network);
+        assert_eq!(intent.retry_policy, Some(CommandRetryPolicy::Safe));
+    }
 }
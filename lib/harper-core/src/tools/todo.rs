@@ -17,9 +17,13 @@
 //! This module provides functionality for managing todo lists.
 //! Uses persistent SQLite storage.
 
-use crate::core::error::HarperError;
+use crate::core::constants::tools;
+use crate::core::error::{HarperError, HarperResult};
 use crate::memory::storage;
 use crate::tools::parsing;
+use crate::tools::registry::{str_arg, Tool, ToolContext, ToolRisk};
+use async_trait::async_trait;
+use serde_json::{json, Value};
 
 /// Manage todo list operations for task tracking
 ///
@@ -125,3 +129,72 @@ pub fn manage_todo(
         ))),
     }
 }
+
+/// `todo`: manage the persistent todo list
+pub struct TodoTool;
+
+#[async_trait(?Send)]
+impl Tool for TodoTool {
+    fn name(&self) -> &str {
+        "todo"
+    }
+
+    fn description(&self) -> &str {
+        "Manage todo list. Supported actions: add, list, remove, clear"
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "action": {
+                    "type": "string",
+                    "enum": ["add", "list", "remove", "clear"],
+                    "description": "The action to perform"
+                },
+                "description": {
+                    "type": "string",
+                    "description": "Description for 'add' action"
+                },
+                "index": {
+                    "type": "integer",
+                    "description": "1-based index for 'remove' action"
+                }
+            },
+            "required": ["action"]
+        })
+    }
+
+    fn risk(&self) -> ToolRisk {
+        ToolRisk::Write
+    }
+
+    fn bracket_prefix(&self) -> Option<&str> {
+        Some(tools::TODO)
+    }
+
+    async fn execute(&self, args: &Value, ctx: &ToolContext<'_>) -> HarperResult<Option<String>> {
+        let bracket_command = match str_arg(args, "action") {
+            Some("add") => match str_arg(args, "description") {
+                Some(description) => format!("[TODO add {}]", description),
+                None => return Ok(None),
+            },
+            Some("list") => "[TODO list]".to_string(),
+            Some("remove") => match args.get("index").and_then(|v| v.as_i64()) {
+                Some(index) => format!("[TODO remove {}]", index),
+                None => return Ok(None),
+            },
+            Some("clear") => "[TODO clear]".to_string(),
+            _ => return Ok(None),
+        };
+        manage_todo(ctx.conn, &bracket_command).map(Some)
+    }
+
+    async fn execute_bracket(
+        &self,
+        call: &str,
+        ctx: &ToolContext<'_>,
+    ) -> HarperResult<Option<String>> {
+        manage_todo(ctx.conn, call).map(Some)
+    }
+}
@@ -16,8 +16,13 @@
 //!
 //! This module provides functionality for performing web searches.
 
+use crate::core::constants::tools;
+use crate::core::error::HarperResult;
 use crate::runtime::utils::web_search;
+use crate::tools::registry::{str_arg, Tool, ToolContext, ToolRisk};
+use async_trait::async_trait;
 use colored::*;
+use serde_json::{json, Value};
 
 /// Perform web search
 pub async fn perform_web_search(response: &str) -> crate::core::error::HarperResult<String> {
@@ -36,3 +41,57 @@ pub async fn perform_web_search(response: &str) -> crate::core::error::HarperRes
 
     web_search(query_part).await
 }
+
+/// `search`: search the web, when the session has it turned on
+pub struct WebSearchTool;
+
+#[async_trait(?Send)]
+impl Tool for WebSearchTool {
+    fn name(&self) -> &str {
+        "search"
+    }
+
+    fn description(&self) -> &str {
+        "Search the web for current external information"
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "query": {
+                    "type": "string",
+                    "description": "The web search query"
+                }
+            },
+            "required": ["query"]
+        })
+    }
+
+    fn risk(&self) -> ToolRisk {
+        ToolRisk::ReadOnly
+    }
+
+    fn bracket_prefix(&self) -> Option<&str> {
+        Some(tools::SEARCH)
+    }
+
+    async fn execute(&self, args: &Value, ctx: &ToolContext<'_>) -> HarperResult<Option<String>> {
+        if !ctx.web_search_enabled {
+            return Ok(Some("Web search is disabled for this session.".to_string()));
+        }
+        let Some(query) = str_arg(args, "query") else {
+            return Ok(None);
+        };
+        let bracket_command = format!("[SEARCH: {}]", query);
+        perform_web_search(&bracket_command).await.map(Some)
+    }
+
+    async fn execute_bracket(
+        &self,
+        call: &str,
+        _ctx: &ToolContext<'_>,
+    ) -> HarperResult<Option<String>> {
+        perform_web_search(call).await.map(Some)
+    }
+}
@@ -35,6 +35,9 @@ pub enum FirmwareError {
     /// Input/output operation failed
     #[error("IO error: {0}")]
     IoError(String),
+    /// A device description could not be loaded
+    #[error("Invalid device configuration: {0}")]
+    ConfigError(String),
 }
 
 pub type Result<T> = std::result::Result<T, FirmwareError>;
@@ -192,6 +195,8 @@ pub enum Platform {
     Stm32,
     RaspberryPiPico,
     Arduino,
+    /// Simulated board, see [`VirtualDevice`]
+    Virtual,
     Custom,
 }
 
@@ -203,6 +208,7 @@ impl Platform {
             "stm32" => Platform::Stm32,
             "raspberrypipico" | "pico" => Platform::RaspberryPiPico,
             "arduino" => Platform::Arduino,
+            "virtual" => Platform::Virtual,
             _ => Platform::Custom,
         }
     }
@@ -259,12 +265,19 @@ impl Default for FirmwareRegistry {
 }
 
 pub mod esp32;
+#[cfg(target_os = "linux")]
+pub mod linux;
+pub mod protocol;
 pub mod raspberry_pi;
+pub mod serial;
 pub mod stm32;
+pub mod virtual_device;
 
 pub use esp32::Esp32Device;
-pub use raspberry_pi::RaspberryPiDevice;
+pub use raspberry_pi::{RaspberryPiDevice, RaspberryPiPaths};
+pub use serial::{SerialBoard, SerialMonitor};
 pub use stm32::Stm32Device;
+pub use virtual_device::{VirtualDevice, VirtualDeviceSpec, VirtualEvent};
 
 #[cfg(test)]
 mod tests {
@@ -311,6 +324,7 @@ mod tests {
         assert!(matches!(Platform::parse("stm32"), Platform::Stm32));
         assert!(matches!(Platform::parse("pico"), Platform::RaspberryPiPico));
         assert!(matches!(Platform::parse("arduino"), Platform::Arduino));
+        assert!(matches!(Platform::parse("Virtual"), Platform::Virtual));
         assert!(matches!(Platform::parse("unknown"), Platform::Custom));
     }
 
@@ -0,0 +1,334 @@
+// Copyright 2026 harpertoken
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! GPIO through the v2 character device ABI (Linux 5.10 and later)
+//!
+//! Each pin is requested as its own line the first time it is used, as an
+//! output for writes and an input for reads, and released on close.
+
+use super::{ioctl, ioctl_error, iowr, lock_poisoned, not_open, open_read_write, pin_error};
+use crate::{GpioController, PinConfig, PinMode, PinState, Result};
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::fs::File;
+use std::os::fd::FromRawFd;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+const LINES_MAX: usize = 64;
+const NAME_SIZE: usize = 32;
+const LINE_NUM_ATTRS_MAX: usize = 10;
+
+const LINE_FLAG_INPUT: u64 = 1 << 2;
+const LINE_FLAG_OUTPUT: u64 = 1 << 3;
+const LINE_FLAG_BIAS_PULL_UP: u64 = 1 << 8;
+const LINE_FLAG_BIAS_PULL_DOWN: u64 = 1 << 9;
+
+const LINE_ATTR_ID_OUTPUT_VALUES: u32 = 2;
+
+const CONSUMER: &[u8] = b"harper";
+
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+struct LineAttribute {
+    id: u32,
+    padding: u32,
+    /// Flags, output values or debounce period, depending on `id`
+    value: u64,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+struct LineConfigAttribute {
+    attr: LineAttribute,
+    mask: u64,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+struct LineConfig {
+    flags: u64,
+    num_attrs: u32,
+    padding: [u32; 5],
+    attrs: [LineConfigAttribute; LINE_NUM_ATTRS_MAX],
+}
+
+#[repr(C)]
+struct LineRequest {
+    offsets: [u32; LINES_MAX],
+    consumer: [u8; NAME_SIZE],
+    config: LineConfig,
+    num_lines: u32,
+    event_buffer_size: u32,
+    padding: [u32; 5],
+    fd: i32,
+}
+
+#[repr(C)]
+#[derive(Default)]
+struct LineValues {
+    bits: u64,
+    mask: u64,
+}
+
+const GET_LINE_IOCTL: u32 = iowr(0xb4, 0x07, std::mem::size_of::<LineRequest>());
+const LINE_SET_CONFIG_IOCTL: u32 = iowr(0xb4, 0x0d, std::mem::size_of::<LineConfig>());
+const LINE_GET_VALUES_IOCTL: u32 = iowr(0xb4, 0x0e, std::mem::size_of::<LineValues>());
+const LINE_SET_VALUES_IOCTL: u32 = iowr(0xb4, 0x0f, std::mem::size_of::<LineValues>());
+
+/// Line settings for a pin mode, with the level to drive outputs to
+fn line_config(mode: PinMode, level: Option<PinState>) -> Result<LineConfig> {
+    let flags = match mode {
+        PinMode::Input => LINE_FLAG_INPUT,
+        PinMode::InputPullUp => LINE_FLAG_INPUT | LINE_FLAG_BIAS_PULL_UP,
+        PinMode::InputPullDown => LINE_FLAG_INPUT | LINE_FLAG_BIAS_PULL_DOWN,
+        PinMode::Output => LINE_FLAG_OUTPUT,
+        PinMode::Analog => {
+            return Err(crate::FirmwareError::UnsupportedPlatform(
+                "Raspberry Pi GPIO has no analog mode".to_string(),
+            ))
+        }
+    };
+    let mut config = LineConfig {
+        flags,
+        ..LineConfig::default()
+    };
+    if mode == PinMode::Output {
+        if let Some(level) = level {
+            config.num_attrs = 1;
+            config.attrs[0] = LineConfigAttribute {
+                attr: LineAttribute {
+                    id: LINE_ATTR_ID_OUTPUT_VALUES,
+                    padding: 0,
+                    value: u64::from(level == PinState::High),
+                },
+                mask: 1,
+            };
+        }
+    }
+    Ok(config)
+}
+
+struct Line {
+    file: File,
+    mode: PinMode,
+}
+
+/// GPIO lines of one `/dev/gpiochipN`
+pub struct CdevGpio {
+    path: PathBuf,
+    state: Mutex<Option<Chip>>,
+}
+
+struct Chip {
+    file: File,
+    lines: HashMap<u32, Line>,
+}
+
+impl CdevGpio {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            state: Mutex::new(None),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn open(&self) -> Result<()> {
+        let file = open_read_write(&self.path)?;
+        *self.state.lock().map_err(|_| lock_poisoned())? = Some(Chip {
+            file,
+            lines: HashMap::new(),
+        });
+        Ok(())
+    }
+
+    /// Releases every requested line
+    pub fn close(&self) {
+        if let Ok(mut state) = self.state.lock() {
+            *state = None;
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.state.lock().is_ok_and(|state| state.is_some())
+    }
+
+    /// Runs `action` on the line for `pin`, requesting it in `mode` first
+    /// unless it is already held in a compatible mode
+    fn with_line<T>(
+        &self,
+        pin: u32,
+        mode: PinMode,
+        level: Option<PinState>,
+        action: impl FnOnce(&Line) -> Result<T>,
+    ) -> Result<T> {
+        let mut state = self.state.lock().map_err(|_| lock_poisoned())?;
+        let chip = state.as_mut().ok_or_else(|| not_open(&self.path))?;
+
+        let reusable = chip.lines.get(&pin).is_some_and(|line| {
+            line.mode == mode || (mode == PinMode::Input && line.mode != PinMode::Analog)
+        });
+        if !reusable {
+            match chip.lines.get_mut(&pin) {
+                Some(line) => {
+                    let mut config = line_config(mode, level)?;
+                    ioctl(&line.file, LINE_SET_CONFIG_IOCTL, &mut config)
+                        .map_err(|e| ioctl_error(&self.path, "a GPIO line", e))?;
+                    line.mode = mode;
+                }
+                None => {
+                    let line = self.request_line(&chip.file, pin, mode, level)?;
+                    chip.lines.insert(pin, line);
+                }
+            }
+        }
+        action(&chip.lines[&pin])
+    }
+
+    fn request_line(
+        &self,
+        chip: &File,
+        pin: u32,
+        mode: PinMode,
+        level: Option<PinState>,
+    ) -> Result<Line> {
+        let mut request = LineRequest {
+            offsets: [0; LINES_MAX],
+            consumer: [0; NAME_SIZE],
+            config: line_config(mode, level)?,
+            num_lines: 1,
+            event_buffer_size: 0,
+            padding: [0; 5],
+            fd: -1,
+        };
+        request.offsets[0] = pin;
+        request.consumer[..CONSUMER.len()].copy_from_slice(CONSUMER);
+
+        ioctl(chip, GET_LINE_IOCTL, &mut request).map_err(|e| match e.raw_os_error() {
+            Some(libc::EINVAL) => pin_error(pin, "no such line on this chip"),
+            Some(libc::EBUSY) => pin_error(pin, "line is in use by another consumer"),
+            _ => ioctl_error(&self.path, "a GPIO character device", e),
+        })?;
+        // SAFETY: the kernel returned a new file descriptor owned by us
+        let file = unsafe { File::from_raw_fd(request.fd) };
+        Ok(Line { file, mode })
+    }
+
+    fn set_level(&self, line: &Line, level: PinState) -> Result<()> {
+        let mut values = LineValues {
+            bits: u64::from(level == PinState::High),
+            mask: 1,
+        };
+        ioctl(&line.file, LINE_SET_VALUES_IOCTL, &mut values)
+            .map_err(|e| ioctl_error(&self.path, "a GPIO line", e))
+    }
+
+    fn get_level(&self, line: &Line) -> Result<PinState> {
+        let mut values = LineValues { bits: 0, mask: 1 };
+        ioctl(&line.file, LINE_GET_VALUES_IOCTL, &mut values)
+            .map_err(|e| ioctl_error(&self.path, "a GPIO line", e))?;
+        Ok(if values.bits & 1 == 1 {
+            PinState::High
+        } else {
+            PinState::Low
+        })
+    }
+}
+
+#[async_trait]
+impl GpioController for CdevGpio {
+    async fn configure_pin(&self, config: PinConfig) -> Result<()> {
+        let mut state = self.state.lock().map_err(|_| lock_poisoned())?;
+        let chip = state.as_mut().ok_or_else(|| not_open(&self.path))?;
+        // Re-request so the bias and initial level apply from the start
+        chip.lines.remove(&config.pin_number);
+        let line = self.request_line(
+            &chip.file,
+            config.pin_number,
+            config.mode,
+            config.initial_state,
+        )?;
+        chip.lines.insert(config.pin_number, line);
+        Ok(())
+    }
+
+    async fn write_pin(&self, pin: u32, state: PinState) -> Result<()> {
+        if state == PinState::Unknown {
+            return Err(pin_error(pin, "cannot drive a pin to an unknown state"));
+        }
+        self.with_line(pin, PinMode::Output, Some(state), |line| {
+            self.set_level(line, state)
+        })
+    }
+
+    async fn read_pin(&self, pin: u32) -> Result<PinState> {
+        self.with_line(pin, PinMode::Input, None, |line| self.get_level(line))
+    }
+
+    async fn toggle_pin(&self, pin: u32) -> Result<()> {
+        self.with_line(pin, PinMode::Output, None, |line| {
+            let level = match self.get_level(line)? {
+                PinState::High => PinState::Low,
+                _ => PinState::High,
+            };
+            self.set_level(line, level)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FirmwareError;
+
+    #[test]
+    fn abi_structs_match_the_kernel_layout() {
+        assert_eq!(std::mem::size_of::<LineRequest>(), 592);
+        assert_eq!(std::mem::size_of::<LineConfig>(), 272);
+        assert_eq!(GET_LINE_IOCTL, 0xc250_b407);
+        assert_eq!(LINE_SET_VALUES_IOCTL, 0xc010_b40f);
+    }
+
+    #[tokio::test]
+    async fn a_plain_file_is_not_a_gpio_chip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("gpiochip0");
+        std::fs::write(&path, b"").unwrap();
+        let gpio = CdevGpio::new(&path);
+
+        assert!(matches!(
+            gpio.read_pin(4).await,
+            Err(FirmwareError::DeviceNotConnected(_))
+        ));
+        gpio.open().unwrap();
+        assert!(matches!(
+            gpio.write_pin(4, PinState::High).await,
+            Err(FirmwareError::UnsupportedPlatform(message)) if message.contains("not a GPIO")
+        ));
+    }
+
+    #[test]
+    fn missing_chip_is_not_connected() {
+        let gpio = CdevGpio::new("/nonexistent/gpiochip9");
+
+        assert!(matches!(
+            gpio.open(),
+            Err(FirmwareError::DeviceNotConnected(_))
+        ));
+    }
+}
@@ -0,0 +1,286 @@
+// Copyright 2026 harpertoken
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! I2C through the `i2c-dev` interface (`/dev/i2c-N`)
+
+use super::{io_error, ioctl, ioctl_error, ioctl_value, lock_poisoned, not_open, open_read_write};
+use crate::{FirmwareError, I2cController, I2cMessage, Result};
+use async_trait::async_trait;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+const I2C_SLAVE: u32 = 0x0703;
+const I2C_RDWR: u32 = 0x0707;
+const I2C_SMBUS: u32 = 0x0720;
+
+const I2C_M_RD: u16 = 0x0001;
+
+const I2C_SMBUS_READ: u8 = 1;
+const I2C_SMBUS_WRITE: u8 = 0;
+const I2C_SMBUS_QUICK: u32 = 0;
+const I2C_SMBUS_BYTE: u32 = 1;
+
+/// Addresses outside this range are reserved by the I2C specification
+const SCAN_RANGE: std::ops::RangeInclusive<u8> = 0x03..=0x77;
+
+#[repr(C)]
+struct Msg {
+    addr: u16,
+    flags: u16,
+    len: u16,
+    buf: *mut u8,
+}
+
+#[repr(C)]
+struct RdwrData {
+    msgs: *mut Msg,
+    nmsgs: u32,
+}
+
+#[repr(C)]
+struct SmbusData {
+    read_write: u8,
+    command: u8,
+    size: u32,
+    data: *mut [u8; 34],
+}
+
+/// One I2C bus adapter
+pub struct I2cDev {
+    path: PathBuf,
+    file: Mutex<Option<File>>,
+}
+
+impl I2cDev {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            file: Mutex::new(None),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn open(&self) -> Result<()> {
+        let file = open_read_write(&self.path)?;
+        *self.file.lock().map_err(|_| lock_poisoned())? = Some(file);
+        Ok(())
+    }
+
+    pub fn close(&self) {
+        if let Ok(mut file) = self.file.lock() {
+            *file = None;
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.file.lock().is_ok_and(|file| file.is_some())
+    }
+
+    fn with_file<T>(&self, action: impl FnOnce(&mut File) -> Result<T>) -> Result<T> {
+        let mut file = self.file.lock().map_err(|_| lock_poisoned())?;
+        let file = file.as_mut().ok_or_else(|| not_open(&self.path))?;
+        action(file)
+    }
+
+    /// Points plain reads and writes on `file` at `address`
+    fn select(&self, file: &File, address: u8) -> Result<()> {
+        check_address(address)?;
+        ioctl_value(file, I2C_SLAVE, address.into()).map_err(|e| self.bus_error(address, e))
+    }
+
+    fn bus_error(&self, address: u8, error: std::io::Error) -> FirmwareError {
+        match error.raw_os_error() {
+            Some(libc::ENOTTY) => ioctl_error(&self.path, "an I2C adapter", error),
+            Some(libc::EBUSY) => FirmwareError::CommunicationError(format!(
+                "0x{:02x} on {} is claimed by a kernel driver",
+                address,
+                self.path.display()
+            )),
+            Some(libc::ENXIO) | Some(libc::EREMOTEIO) => FirmwareError::CommunicationError(
+                format!("no ACK from 0x{:02x} on {}", address, self.path.display()),
+            ),
+            _ => io_error(&self.path, error),
+        }
+    }
+
+    /// Whether something answers at `address`, probing the way `i2cdetect`
+    /// does: EEPROM ranges with a read, so a quick write cannot corrupt them
+    fn probe(&self, file: &File, address: u8) -> Result<bool> {
+        match ioctl_value(file, I2C_SLAVE, address.into()) {
+            Ok(()) => {}
+            // A kernel driver owns it, so it is there
+            Err(e) if e.raw_os_error() == Some(libc::EBUSY) => return Ok(true),
+            Err(e) => return Err(self.bus_error(address, e)),
+        }
+
+        let mut block = [0u8; 34];
+        let mut request = if (0x30..=0x37).contains(&address) || (0x50..=0x5f).contains(&address) {
+            SmbusData {
+                read_write: I2C_SMBUS_READ,
+                command: 0,
+                size: I2C_SMBUS_BYTE,
+                data: &mut block,
+            }
+        } else {
+            SmbusData {
+                read_write: I2C_SMBUS_WRITE,
+                command: 0,
+                size: I2C_SMBUS_QUICK,
+                data: std::ptr::null_mut(),
+            }
+        };
+        match ioctl(file, I2C_SMBUS, &mut request) {
+            Ok(()) => Ok(true),
+            Err(e) if e.raw_os_error() == Some(libc::ENOTTY) => {
+                Err(ioctl_error(&self.path, "an I2C adapter", e))
+            }
+            Err(_) => Ok(false),
+        }
+    }
+}
+
+fn check_address(address: u8) -> Result<()> {
+    if address > 0x7f {
+        return Err(FirmwareError::CommunicationError(format!(
+            "0x{:02x} is not a 7-bit I2C address",
+            address
+        )));
+    }
+    Ok(())
+}
+
+fn message_len(len: usize) -> Result<u16> {
+    u16::try_from(len).map_err(|_| {
+        FirmwareError::CommunicationError(format!("{} bytes is too long for one I2C message", len))
+    })
+}
+
+#[async_trait]
+impl I2cController for I2cDev {
+    async fn write(&self, message: I2cMessage) -> Result<()> {
+        self.with_file(|file| {
+            self.select(file, message.address)?;
+            let written = file
+                .write(&message.data)
+                .map_err(|e| self.bus_error(message.address, e))?;
+            if written != message.data.len() {
+                return Err(FirmwareError::CommunicationError(format!(
+                    "wrote {} of {} bytes to 0x{:02x}",
+                    written,
+                    message.data.len(),
+                    message.address
+                )));
+            }
+            Ok(())
+        })
+    }
+
+    async fn read(&self, address: u8, length: usize) -> Result<Vec<u8>> {
+        self.with_file(|file| {
+            self.select(file, address)?;
+            let mut data = vec![0; length];
+            let read = file
+                .read(&mut data)
+                .map_err(|e| self.bus_error(address, e))?;
+            data.truncate(read);
+            Ok(data)
+        })
+    }
+
+    async fn write_read(&self, write_data: Vec<u8>, read_len: usize) -> Result<Vec<u8>> {
+        let Some((&address, data)) = write_data.split_first() else {
+            return Err(FirmwareError::CommunicationError(
+                "write_read needs the device address as its first byte".to_string(),
+            ));
+        };
+        check_address(address)?;
+        let mut outgoing = data.to_vec();
+        let mut incoming = vec![0u8; read_len];
+
+        // One combined transaction, with a repeated start between the halves
+        let mut messages = [
+            Msg {
+                addr: address.into(),
+                flags: 0,
+                len: message_len(outgoing.len())?,
+                buf: outgoing.as_mut_ptr(),
+            },
+            Msg {
+                addr: address.into(),
+                flags: I2C_M_RD,
+                len: message_len(incoming.len())?,
+                buf: incoming.as_mut_ptr(),
+            },
+        ];
+        let mut request = RdwrData {
+            msgs: messages.as_mut_ptr(),
+            nmsgs: messages.len() as u32,
+        };
+        self.with_file(|file| {
+            ioctl(file, I2C_RDWR, &mut request).map_err(|e| self.bus_error(address, e))
+        })?;
+        Ok(incoming)
+    }
+
+    async fn scan_devices(&self) -> Result<Vec<u8>> {
+        self.with_file(|file| {
+            let mut found = Vec::new();
+            for address in SCAN_RANGE {
+                if self.probe(file, address)? {
+                    found.push(address);
+                }
+            }
+            Ok(found)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn abi_structs_match_the_kernel_layout() {
+        let pointer = std::mem::size_of::<*mut u8>();
+        assert_eq!(std::mem::size_of::<Msg>(), 8 + pointer);
+        assert_eq!(std::mem::size_of::<SmbusData>(), 8 + pointer);
+    }
+
+    #[tokio::test]
+    async fn a_plain_file_is_not_an_i2c_adapter() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("i2c-1");
+        std::fs::write(&path, b"").unwrap();
+        let bus = I2cDev::new(&path);
+        bus.open().unwrap();
+
+        assert!(matches!(
+            bus.scan_devices().await,
+            Err(FirmwareError::UnsupportedPlatform(message)) if message.contains("not an I2C")
+        ));
+        assert!(matches!(
+            bus.write_read(vec![], 1).await,
+            Err(FirmwareError::CommunicationError(_))
+        ));
+        assert!(matches!(
+            bus.read(0x80, 1).await,
+            Err(FirmwareError::CommunicationError(message)) if message.contains("7-bit")
+        ));
+    }
+}
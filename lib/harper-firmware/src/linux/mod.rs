@@ -0,0 +1,120 @@
+// Copyright 2026 harpertoken
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Controllers backed by Linux kernel interfaces.
+//!
+//! GPIO uses the character device (`/dev/gpiochipN`), I2C and SPI use the
+//! `i2c-dev` and `spidev` ioctls, PWM uses `/sys/class/pwm` and UART uses a
+//! termios serial port. Each controller is given its path, so tests can point
+//! it at files in a temporary directory. Every controller opens its device on
+//! [`open`](gpio::CdevGpio::open) and reports
+//! [`DeviceNotConnected`](crate::FirmwareError::DeviceNotConnected) until then.
+
+pub mod gpio;
+pub mod i2c;
+pub mod pwm;
+pub mod spi;
+pub mod uart;
+
+pub use gpio::CdevGpio;
+pub use i2c::I2cDev;
+pub use pwm::SysfsPwm;
+pub use spi::SpiDev;
+pub use uart::TermiosUart;
+
+use crate::FirmwareError;
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::os::fd::AsRawFd;
+use std::path::Path;
+
+/// `_IOC` request number for the generic ioctl layout used on ARM and x86
+const fn ioc(direction: u32, kind: u8, number: u8, size: usize) -> u32 {
+    (direction << 30) | ((size as u32) << 16) | ((kind as u32) << 8) | number as u32
+}
+
+const IOC_WRITE: u32 = 1;
+const IOC_READ: u32 = 2;
+
+const fn iow(kind: u8, number: u8, size: usize) -> u32 {
+    ioc(IOC_WRITE, kind, number, size)
+}
+
+const fn iowr(kind: u8, number: u8, size: usize) -> u32 {
+    ioc(IOC_READ | IOC_WRITE, kind, number, size)
+}
+
+/// Runs `ioctl(fd, request, arg)`
+fn ioctl<T>(file: &File, request: u32, arg: *mut T) -> io::Result<()> {
+    // SAFETY: callers pass a request whose argument layout matches `T`
+    let result = unsafe { libc::ioctl(file.as_raw_fd(), request as _, arg) };
+    if result < 0 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(())
+    }
+}
+
+/// Runs an ioctl that takes a plain integer argument
+fn ioctl_value(file: &File, request: u32, value: libc::c_ulong) -> io::Result<()> {
+    // SAFETY: the request takes its argument by value
+    let result = unsafe { libc::ioctl(file.as_raw_fd(), request as _, value) };
+    if result < 0 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(())
+    }
+}
+
+fn open_read_write(path: &Path) -> crate::Result<File> {
+    OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(path)
+        .map_err(|e| open_error(path, e))
+}
+
+/// A missing device means the interface is not enabled; anything else is I/O
+fn open_error(path: &Path, error: io::Error) -> FirmwareError {
+    if error.kind() == io::ErrorKind::NotFound {
+        FirmwareError::DeviceNotConnected(format!("{} does not exist", path.display()))
+    } else {
+        io_error(path, error)
+    }
+}
+
+fn io_error(path: &Path, error: io::Error) -> FirmwareError {
+    FirmwareError::IoError(format!("{}: {}", path.display(), error))
+}
+
+/// `ENOTTY` from an ioctl means the path is not the expected kind of device
+fn ioctl_error(path: &Path, kind: &str, error: io::Error) -> FirmwareError {
+    if error.raw_os_error() == Some(libc::ENOTTY) {
+        FirmwareError::UnsupportedPlatform(format!("{} is not {}", path.display(), kind))
+    } else {
+        io_error(path, error)
+    }
+}
+
+fn not_open(path: &Path) -> FirmwareError {
+    FirmwareError::DeviceNotConnected(format!("{} is not open", path.display()))
+}
+
+fn lock_poisoned() -> FirmwareError {
+    FirmwareError::CommunicationError("device lock poisoned".to_string())
+}
+
+fn pin_error(pin: u32, message: &str) -> FirmwareError {
+    FirmwareError::PinError(format!("pin {}: {}", pin, message))
+}
@@ -0,0 +1,261 @@
+// Copyright 2026 harpertoken
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! PWM through sysfs (`/sys/class/pwm/pwmchipN`)
+//!
+//! The `pin` of each call is the channel number on the chip. Channels are
+//! exported on first use and left exported, as other tools expect.
+
+use super::{io_error, not_open, open_error, pin_error};
+use crate::{FirmwareError, PwmConfig, PwmController, Result};
+use async_trait::async_trait;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+const NANOS_PER_SECOND: u64 = 1_000_000_000;
+
+/// How long udev gets to create and chown a freshly exported channel
+const EXPORT_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// One PWM chip
+pub struct SysfsPwm {
+    path: PathBuf,
+    open: AtomicBool,
+}
+
+impl SysfsPwm {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            open: AtomicBool::new(false),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn open(&self) -> Result<()> {
+        std::fs::metadata(self.path.join("export")).map_err(|e| open_error(&self.path, e))?;
+        self.open.store(true, Ordering::SeqCst);
+        Ok(())
+    }
+
+    pub fn close(&self) {
+        self.open.store(false, Ordering::SeqCst);
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open.load(Ordering::SeqCst)
+    }
+
+    /// Directory of `channel`, exporting it if needed
+    fn channel(&self, channel: u32) -> Result<PathBuf> {
+        if !self.is_open() {
+            return Err(not_open(&self.path));
+        }
+        let dir = self.path.join(format!("pwm{}", channel));
+        if dir.join("period").exists() {
+            return Ok(dir);
+        }
+        if let Ok(count) = self.read(&self.path.join("npwm")) {
+            if u64::from(channel) >= count {
+                return Err(pin_error(
+                    channel,
+                    &format!("{} has {} PWM channels", self.path.display(), count),
+                ));
+            }
+        }
+        self.write(&self.path.join("export"), channel)?;
+
+        let deadline = Instant::now() + EXPORT_TIMEOUT;
+        while !dir.join("period").exists() {
+            if Instant::now() >= deadline {
+                return Err(pin_error(
+                    channel,
+                    "PWM channel did not appear after export",
+                ));
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        Ok(dir)
+    }
+
+    fn read(&self, path: &Path) -> Result<u64> {
+        let text = std::fs::read_to_string(path).map_err(|e| io_error(path, e))?;
+        text.trim().parse().map_err(|_| {
+            FirmwareError::IoError(format!("{}: unexpected value {:?}", path.display(), text))
+        })
+    }
+
+    fn write(&self, path: &Path, value: impl ToString) -> Result<()> {
+        std::fs::write(path, value.to_string()).map_err(|e| io_error(path, e))
+    }
+
+    /// Current duty cycle of a channel as a fraction of its period
+    fn duty_ratio(&self, dir: &Path) -> Result<f32> {
+        let period = self.read(&dir.join("period"))?;
+        if period == 0 {
+            return Ok(0.0);
+        }
+        Ok(self.read(&dir.join("duty_cycle"))? as f32 / period as f32)
+    }
+
+    /// Sets the period, lowering the duty cycle first so the kernel never
+    /// sees a duty cycle longer than the period
+    fn set_period(&self, dir: &Path, pin: u32, frequency: u32, duty: f32) -> Result<()> {
+        let period = period_ns(pin, frequency)?;
+        self.write(&dir.join("duty_cycle"), 0)?;
+        self.write(&dir.join("period"), period)?;
+        self.write(&dir.join("duty_cycle"), duty_ns(period, duty))
+    }
+}
+
+fn check_duty(duty: f32) -> Result<()> {
+    if (0.0..=1.0).contains(&duty) {
+        Ok(())
+    } else {
+        Err(FirmwareError::PinError(format!(
+            "duty cycle {} is outside 0.0..=1.0",
+            duty
+        )))
+    }
+}
+
+fn period_ns(pin: u32, frequency: u32) -> Result<u64> {
+    if frequency == 0 {
+        return Err(pin_error(pin, "PWM frequency must be above 0 Hz"));
+    }
+    Ok(NANOS_PER_SECOND / u64::from(frequency))
+}
+
+fn duty_ns(period: u64, duty: f32) -> u64 {
+    (period as f64 * f64::from(duty)).round() as u64
+}
+
+#[async_trait]
+impl PwmController for SysfsPwm {
+    async fn configure(&self, config: PwmConfig) -> Result<()> {
+        check_duty(config.duty_cycle)?;
+        let dir = self.channel(config.pin)?;
+        self.set_period(&dir, config.pin, config.frequency, config.duty_cycle)?;
+        self.write(&dir.join("enable"), 1)
+    }
+
+    async fn set_duty_cycle(&self, pin: u32, duty: f32) -> Result<()> {
+        check_duty(duty)?;
+        let dir = self.channel(pin)?;
+        let period = self.read(&dir.join("period"))?;
+        if period == 0 {
+            return Err(pin_error(pin, "PWM channel has no frequency configured"));
+        }
+        self.write(&dir.join("duty_cycle"), duty_ns(period, duty))
+    }
+
+    async fn set_frequency(&self, pin: u32, frequency: u32) -> Result<()> {
+        let dir = self.channel(pin)?;
+        let duty = self.duty_ratio(&dir)?;
+        self.set_period(&dir, pin, frequency, duty)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    /// A chip directory with `npwm` channels, of which `exported` exist
+    fn fake_chip(root: &Path, npwm: u32, exported: &[u32]) -> PathBuf {
+        let chip = root.join("pwmchip0");
+        fs::create_dir_all(&chip).unwrap();
+        fs::write(chip.join("export"), "").unwrap();
+        fs::write(chip.join("npwm"), format!("{}\n", npwm)).unwrap();
+        for channel in exported {
+            let dir = chip.join(format!("pwm{}", channel));
+            fs::create_dir_all(&dir).unwrap();
+            for file in ["period", "duty_cycle", "enable"] {
+                fs::write(dir.join(file), "0\n").unwrap();
+            }
+        }
+        chip
+    }
+
+    fn value(path: PathBuf) -> String {
+        fs::read_to_string(path).unwrap()
+    }
+
+    #[tokio::test]
+    async fn configure_writes_period_duty_and_enable() {
+        let dir = tempfile::tempdir().unwrap();
+        let chip = fake_chip(dir.path(), 2, &[1]);
+        let pwm = SysfsPwm::new(&chip);
+        pwm.open().unwrap();
+
+        pwm.configure(PwmConfig {
+            pin: 1,
+            frequency: 1000,
+            duty_cycle: 0.25,
+        })
+        .await
+        .unwrap();
+        assert_eq!(value(chip.join("pwm1/period")), "1000000");
+        assert_eq!(value(chip.join("pwm1/duty_cycle")), "250000");
+        assert_eq!(value(chip.join("pwm1/enable")), "1");
+
+        pwm.set_frequency(1, 50).await.unwrap();
+        assert_eq!(value(chip.join("pwm1/period")), "20000000");
+        assert_eq!(value(chip.join("pwm1/duty_cycle")), "5000000");
+
+        pwm.set_duty_cycle(1, 0.5).await.unwrap();
+        assert_eq!(value(chip.join("pwm1/duty_cycle")), "10000000");
+    }
+
+    #[tokio::test]
+    async fn unexported_channels_are_exported_or_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let chip = fake_chip(dir.path(), 2, &[]);
+        let pwm = SysfsPwm::new(&chip);
+
+        assert!(matches!(
+            pwm.set_duty_cycle(0, 0.5).await,
+            Err(FirmwareError::DeviceNotConnected(_))
+        ));
+        pwm.open().unwrap();
+
+        assert!(matches!(
+            pwm.set_frequency(2, 100).await,
+            Err(FirmwareError::PinError(message)) if message.contains("2 PWM channels")
+        ));
+        assert!(matches!(
+            pwm.set_frequency(0, 100).await,
+            Err(FirmwareError::PinError(message)) if message.contains("did not appear")
+        ));
+        assert_eq!(value(chip.join("export")), "0");
+        assert!(matches!(
+            pwm.set_duty_cycle(0, 1.5).await,
+            Err(FirmwareError::PinError(_))
+        ));
+    }
+
+    #[test]
+    fn missing_chip_is_not_connected() {
+        let pwm = SysfsPwm::new("/nonexistent/pwmchip0");
+
+        assert!(matches!(
+            pwm.open(),
+            Err(FirmwareError::DeviceNotConnected(_))
+        ));
+    }
+}
@@ -0,0 +1,187 @@
+// Copyright 2026 harpertoken
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! SPI through `spidev` (`/dev/spidevB.C`)
+
+use super::{ioctl, ioctl_error, iow, lock_poisoned, not_open, open_read_write};
+use crate::{FirmwareError, Result, SpiConfig, SpiController, SpiMode};
+use async_trait::async_trait;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+const SPI_IOC_MAGIC: u8 = b'k';
+
+#[repr(C)]
+#[derive(Default)]
+struct Transfer {
+    tx_buf: u64,
+    rx_buf: u64,
+    len: u32,
+    speed_hz: u32,
+    delay_usecs: u16,
+    bits_per_word: u8,
+    cs_change: u8,
+    tx_nbits: u8,
+    rx_nbits: u8,
+    word_delay_usecs: u8,
+    pad: u8,
+}
+
+const SPI_IOC_MESSAGE_1: u32 = iow(SPI_IOC_MAGIC, 0, std::mem::size_of::<Transfer>());
+const SPI_IOC_WR_MODE: u32 = iow(SPI_IOC_MAGIC, 1, 1);
+const SPI_IOC_WR_MAX_SPEED_HZ: u32 = iow(SPI_IOC_MAGIC, 4, 4);
+
+/// One chip select on an SPI bus
+pub struct SpiDev {
+    path: PathBuf,
+    config: Option<SpiConfig>,
+    file: Mutex<Option<File>>,
+}
+
+impl SpiDev {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            config: None,
+            file: Mutex::new(None),
+        }
+    }
+
+    /// Mode and clock applied on open; the driver defaults are kept otherwise
+    pub fn with_config(mut self, config: SpiConfig) -> Self {
+        self.config = Some(config);
+        self
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn open(&self) -> Result<()> {
+        let file = open_read_write(&self.path)?;
+        if let Some(config) = &self.config {
+            let mut mode: u8 = match config.mode {
+                SpiMode::Mode0 => 0,
+                SpiMode::Mode1 => 1,
+                SpiMode::Mode2 => 2,
+                SpiMode::Mode3 => 3,
+            };
+            ioctl(&file, SPI_IOC_WR_MODE, &mut mode).map_err(|e| self.error(e))?;
+            let mut speed = config.frequency;
+            ioctl(&file, SPI_IOC_WR_MAX_SPEED_HZ, &mut speed).map_err(|e| self.error(e))?;
+        }
+        *self.file.lock().map_err(|_| lock_poisoned())? = Some(file);
+        Ok(())
+    }
+
+    pub fn close(&self) {
+        if let Ok(mut file) = self.file.lock() {
+            *file = None;
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.file.lock().is_ok_and(|file| file.is_some())
+    }
+
+    fn error(&self, error: std::io::Error) -> FirmwareError {
+        ioctl_error(&self.path, "an SPI device", error)
+    }
+
+    /// Clocks `tx` out while filling `rx`; either may be empty but not both
+    fn exchange(&self, tx: Option<&[u8]>, rx: Option<&mut [u8]>) -> Result<()> {
+        let len = tx.map(<[u8]>::len).or(rx.as_ref().map(|rx| rx.len()));
+        let len = u32::try_from(len.unwrap_or(0)).map_err(|_| {
+            FirmwareError::CommunicationError("SPI transfer is too long".to_string())
+        })?;
+        let mut transfer = Transfer {
+            tx_buf: tx.map_or(0, |tx| tx.as_ptr() as u64),
+            rx_buf: rx.map_or(0, |rx| rx.as_mut_ptr() as u64),
+            len,
+            ..Transfer::default()
+        };
+        if len == 0 {
+            return Ok(());
+        }
+
+        let file = self.file.lock().map_err(|_| lock_poisoned())?;
+        let file = file.as_ref().ok_or_else(|| not_open(&self.path))?;
+        ioctl(file, SPI_IOC_MESSAGE_1, &mut transfer).map_err(|e| self.error(e))
+    }
+}
+
+#[async_trait]
+impl SpiController for SpiDev {
+    async fn transfer(&self, data: &[u8]) -> Result<Vec<u8>> {
+        let mut received = vec![0; data.len()];
+        self.exchange(Some(data), Some(&mut received))?;
+        Ok(received)
+    }
+
+    async fn write(&self, data: &[u8]) -> Result<()> {
+        self.exchange(Some(data), None)
+    }
+
+    async fn read(&self, length: usize) -> Result<Vec<u8>> {
+        let mut received = vec![0; length];
+        self.exchange(None, Some(&mut received))?;
+        Ok(received)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn abi_structs_match_the_kernel_layout() {
+        assert_eq!(std::mem::size_of::<Transfer>(), 32);
+        assert_eq!(SPI_IOC_MESSAGE_1, 0x4020_6b00);
+        assert_eq!(SPI_IOC_WR_MAX_SPEED_HZ, 0x4004_6b04);
+    }
+
+    #[tokio::test]
+    async fn a_plain_file_is_not_an_spi_device() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("spidev0.0");
+        std::fs::write(&path, b"").unwrap();
+        let spi = SpiDev::new(&path);
+
+        assert!(matches!(
+            spi.transfer(&[1]).await,
+            Err(FirmwareError::DeviceNotConnected(_))
+        ));
+        spi.open().unwrap();
+        assert_eq!(spi.transfer(&[]).await.unwrap(), Vec::<u8>::new());
+        assert!(matches!(
+            spi.transfer(&[0x9f, 0, 0]).await,
+            Err(FirmwareError::UnsupportedPlatform(message)) if message.contains("not an SPI")
+        ));
+
+        let configured = SpiDev::new(&path).with_config(SpiConfig {
+            mode: SpiMode::Mode3,
+            frequency: 1_000_000,
+            mosi_pin: None,
+            miso_pin: None,
+            clock_pin: None,
+            chip_select: None,
+        });
+        assert!(matches!(
+            configured.open(),
+            Err(FirmwareError::UnsupportedPlatform(_))
+        ));
+        assert!(!configured.is_open());
+    }
+}
@@ -0,0 +1,215 @@
+// Copyright 2026 harpertoken
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! UART through a termios serial port (`/dev/serial0`, `/dev/ttyAMA0`)
+
+use super::{io_error, lock_poisoned, not_open, open_error};
+use crate::{FirmwareError, Parity, Result, UartConfig, UartController};
+use async_trait::async_trait;
+use serialport::{DataBits, SerialPort, StopBits, TTYPort};
+use std::io::{ErrorKind, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// How long a read waits for the first byte when nothing is buffered
+const READ_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// One serial port, 115200 8N1 unless configured otherwise
+pub struct TermiosUart {
+    path: PathBuf,
+    config: UartConfig,
+    port: Mutex<Option<TTYPort>>,
+}
+
+impl TermiosUart {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            config: UartConfig {
+                baud_rate: 115_200,
+                data_bits: 8,
+                stop_bits: 1,
+                parity: Parity::None,
+                rx_pin: None,
+                tx_pin: None,
+            },
+            port: Mutex::new(None),
+        }
+    }
+
+    pub fn with_config(mut self, config: UartConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn open(&self) -> Result<()> {
+        let data_bits = match self.config.data_bits {
+            5 => DataBits::Five,
+            6 => DataBits::Six,
+            7 => DataBits::Seven,
+            8 => DataBits::Eight,
+            other => {
+                return Err(FirmwareError::ConfigError(format!(
+                    "{} data bits is not supported",
+                    other
+                )))
+            }
+        };
+        let stop_bits = match self.config.stop_bits {
+            1 => StopBits::One,
+            2 => StopBits::Two,
+            other => {
+                return Err(FirmwareError::ConfigError(format!(
+                    "{} stop bits is not supported",
+                    other
+                )))
+            }
+        };
+        let parity = match self.config.parity {
+            Parity::None => serialport::Parity::None,
+            Parity::Even => serialport::Parity::Even,
+            Parity::Odd => serialport::Parity::Odd,
+        };
+
+        let builder = serialport::new(self.path.to_string_lossy(), self.config.baud_rate)
+            .data_bits(data_bits)
+            .stop_bits(stop_bits)
+            .parity(parity)
+            .timeout(READ_TIMEOUT);
+        let port = TTYPort::open(&builder).map_err(|e| match e.kind() {
+            serialport::ErrorKind::Io(kind) => open_error(&self.path, kind.into()),
+            _ => FirmwareError::IoError(format!("{}: {}", self.path.display(), e)),
+        })?;
+        *self.port.lock().map_err(|_| lock_poisoned())? = Some(port);
+        Ok(())
+    }
+
+    pub fn close(&self) {
+        if let Ok(mut port) = self.port.lock() {
+            *port = None;
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.port.lock().is_ok_and(|port| port.is_some())
+    }
+
+    fn with_port<T>(&self, action: impl FnOnce(&mut TTYPort) -> Result<T>) -> Result<T> {
+        let mut port = self.port.lock().map_err(|_| lock_poisoned())?;
+        let port = port.as_mut().ok_or_else(|| not_open(&self.path))?;
+        action(port)
+    }
+}
+
+#[async_trait]
+impl UartController for TermiosUart {
+    async fn write(&self, data: &[u8]) -> Result<usize> {
+        self.with_port(|port| {
+            port.write_all(data).map_err(|e| io_error(&self.path, e))?;
+            Ok(data.len())
+        })
+    }
+
+    /// Returns what is buffered, up to `length`, waiting briefly if nothing is
+    async fn read(&self, length: usize) -> Result<Vec<u8>> {
+        self.with_port(|port| {
+            let mut data = vec![0; length];
+            if length == 0 {
+                return Ok(data);
+            }
+            let read = match port.read(&mut data) {
+                Ok(read) => read,
+                Err(e) if e.kind() == ErrorKind::TimedOut => 0,
+                Err(e) => return Err(io_error(&self.path, e)),
+            };
+            data.truncate(read);
+            Ok(data)
+        })
+    }
+
+    async fn write_string(&self, data: &str) -> Result<usize> {
+        UartController::write(self, data.as_bytes()).await
+    }
+
+    async fn flush(&self) -> Result<()> {
+        self.with_port(|port| port.flush().map_err(|e| io_error(&self.path, e)))
+    }
+
+    async fn available(&self) -> Result<usize> {
+        self.with_port(|port| {
+            port.bytes_to_read()
+                .map(|count| count as usize)
+                .map_err(|e| FirmwareError::IoError(format!("{}: {}", self.path.display(), e)))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn reads_and_writes_through_a_pty() {
+        let (mut master, slave) = TTYPort::pair().unwrap();
+        let uart = TermiosUart::new(slave.name().unwrap()).with_config(UartConfig {
+            baud_rate: 9600,
+            data_bits: 8,
+            stop_bits: 1,
+            parity: Parity::None,
+            rx_pin: None,
+            tx_pin: None,
+        });
+        uart.open().unwrap();
+
+        assert_eq!(uart.write_string("ping").await.unwrap(), 4);
+        uart.flush().await.unwrap();
+        let mut received = [0u8; 4];
+        master.read_exact(&mut received).unwrap();
+        assert_eq!(&received, b"ping");
+
+        master.write_all(b"pong").unwrap();
+        master.flush().unwrap();
+        let deadline = std::time::Instant::now() + Duration::from_secs(2);
+        while uart.available().await.unwrap() < 4 && std::time::Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(uart.read(2).await.unwrap(), b"po");
+        assert_eq!(uart.read(16).await.unwrap(), b"ng");
+        assert!(uart.read(16).await.unwrap().is_empty());
+    }
+
+    #[test]
+    fn missing_port_is_not_connected_and_bad_framing_is_rejected() {
+        let uart = TermiosUart::new("/nonexistent/ttyAMA9");
+        assert!(matches!(
+            uart.open(),
+            Err(FirmwareError::DeviceNotConnected(_))
+        ));
+
+        let uart = TermiosUart::new("/nonexistent/ttyAMA9").with_config(UartConfig {
+            baud_rate: 9600,
+            data_bits: 9,
+            stop_bits: 1,
+            parity: Parity::None,
+            rx_pin: None,
+            tx_pin: None,
+        });
+        assert!(matches!(uart.open(), Err(FirmwareError::ConfigError(_))));
+    }
+}
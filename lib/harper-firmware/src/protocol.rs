@@ -0,0 +1,273 @@
+// Copyright 2026 harpertoken
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Framed command protocol spoken by Harper's companion firmware.
+//!
+//! Every message is COBS-encoded and terminated by a zero byte, so a reader
+//! can always resynchronise on the next delimiter. A request is
+//! `[sequence, opcode, arguments...]` and its reply is
+//! `[sequence, status, data...]`. Multi-byte integers and floats are
+//! little-endian. Replies whose sequence does not match the request in
+//! flight are stale and dropped by the host.
+
+use crate::FirmwareError;
+
+/// Version reported in the `HELLO` reply by compatible firmware
+pub const PROTOCOL_VERSION: u8 = 1;
+
+/// Largest decoded message either side accepts
+pub const MAX_MESSAGE_LEN: usize = 1024;
+
+/// Largest data block carried by one request or reply
+pub const MAX_DATA_LEN: usize = MAX_MESSAGE_LEN - 8;
+
+/// Request opcodes
+pub mod opcode {
+    /// `[]` -> `[protocol_version, firmware_version...]`
+    pub const HELLO: u8 = 0x01;
+    /// `[]` -> `[]`
+    pub const RESET: u8 = 0x02;
+
+    /// `[pin, mode, initial_state]`; see [`super::pin_mode`] and [`super::pin_state`]
+    pub const GPIO_CONFIGURE: u8 = 0x10;
+    /// `[pin, state]`
+    pub const GPIO_WRITE: u8 = 0x11;
+    /// `[pin]` -> `[state]`
+    pub const GPIO_READ: u8 = 0x12;
+    /// `[pin]`
+    pub const GPIO_TOGGLE: u8 = 0x13;
+
+    /// `[address, data...]`
+    pub const I2C_WRITE: u8 = 0x20;
+    /// `[address, length: u16]` -> `data`
+    pub const I2C_READ: u8 = 0x21;
+    /// `[address, read_length: u16, data...]` -> `data`
+    pub const I2C_WRITE_READ: u8 = 0x22;
+    /// `[]` -> `addresses`
+    pub const I2C_SCAN: u8 = 0x23;
+
+    /// `data` -> `data`
+    pub const SPI_TRANSFER: u8 = 0x30;
+    /// `data`
+    pub const SPI_WRITE: u8 = 0x31;
+    /// `[length: u16]` -> `data`
+    pub const SPI_READ: u8 = 0x32;
+
+    /// `data` -> `[written: u16]`
+    pub const UART_WRITE: u8 = 0x40;
+    /// `[length: u16]` -> `data`
+    pub const UART_READ: u8 = 0x41;
+    /// `[]`
+    pub const UART_FLUSH: u8 = 0x42;
+    /// `[]` -> `[available: u16]`
+    pub const UART_AVAILABLE: u8 = 0x43;
+
+    /// `[pin, frequency: u32, duty: f32]`
+    pub const PWM_CONFIGURE: u8 = 0x50;
+    /// `[pin, duty: f32]`
+    pub const PWM_DUTY: u8 = 0x51;
+    /// `[pin, frequency: u32]`
+    pub const PWM_FREQUENCY: u8 = 0x52;
+
+    /// `[pin, resolution_bits, vref: f32]`
+    pub const ADC_CONFIGURE: u8 = 0x60;
+    /// `[pin]` -> `[raw: u32]`
+    pub const ADC_READ: u8 = 0x61;
+}
+
+/// Reply status codes; every status but `OK` carries a UTF-8 message
+pub mod status {
+    pub const OK: u8 = 0x00;
+    pub const INVALID_REQUEST: u8 = 0x01;
+    pub const UNSUPPORTED: u8 = 0x02;
+    pub const PIN_ERROR: u8 = 0x03;
+    pub const BUS_ERROR: u8 = 0x04;
+}
+
+/// Wire values for [`crate::PinMode`]
+pub mod pin_mode {
+    pub const INPUT: u8 = 0;
+    pub const OUTPUT: u8 = 1;
+    pub const INPUT_PULL_UP: u8 = 2;
+    pub const INPUT_PULL_DOWN: u8 = 3;
+    pub const ANALOG: u8 = 4;
+}
+
+/// Wire values for [`crate::PinState`]
+pub mod pin_state {
+    pub const LOW: u8 = 0;
+    pub const HIGH: u8 = 1;
+    /// Leave the pin as it is when configuring
+    pub const UNCHANGED: u8 = 0xff;
+}
+
+/// COBS-encodes `data`, without the trailing delimiter
+pub fn cobs_encode(data: &[u8]) -> Vec<u8> {
+    let mut encoded = Vec::with_capacity(data.len() + data.len() / 254 + 2);
+    let mut code_index = 0;
+    encoded.push(0);
+    let mut code = 1u8;
+
+    for &byte in data {
+        if byte == 0 {
+            encoded[code_index] = code;
+            code_index = encoded.len();
+            encoded.push(0);
+            code = 1;
+            continue;
+        }
+        encoded.push(byte);
+        code += 1;
+        if code == 0xff {
+            encoded[code_index] = code;
+            code_index = encoded.len();
+            encoded.push(0);
+            code = 1;
+        }
+    }
+    encoded[code_index] = code;
+    encoded
+}
+
+/// Decodes one COBS frame without its delimiter, or `None` if it is malformed
+pub fn cobs_decode(frame: &[u8]) -> Option<Vec<u8>> {
+    let mut decoded = Vec::with_capacity(frame.len());
+    let mut index = 0;
+
+    while index < frame.len() {
+        let code = frame[index] as usize;
+        if code == 0 || index + code > frame.len() {
+            return None;
+        }
+        decoded.extend_from_slice(&frame[index + 1..index + code]);
+        index += code;
+        if code < 0xff && index < frame.len() {
+            decoded.push(0);
+        }
+    }
+    Some(decoded)
+}
+
+/// Encodes `message` as a complete frame, delimiter included
+pub fn encode_frame(message: &[u8]) -> Vec<u8> {
+    let mut frame = cobs_encode(message);
+    frame.push(0);
+    frame
+}
+
+/// Splits a byte stream into decoded messages
+#[derive(Debug, Default)]
+pub struct FrameReader {
+    pending: Vec<u8>,
+}
+
+impl FrameReader {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds bytes received from the port
+    pub fn push(&mut self, bytes: &[u8]) {
+        self.pending.extend_from_slice(bytes);
+    }
+
+    /// The next complete message; malformed and oversized frames are skipped
+    pub fn next_message(&mut self) -> Option<Vec<u8>> {
+        loop {
+            let Some(end) = self.pending.iter().position(|byte| *byte == 0) else {
+                // No delimiter within a full frame means the stream is garbage
+                if self.pending.len() > MAX_MESSAGE_LEN + MAX_MESSAGE_LEN / 254 + 2 {
+                    self.pending.clear();
+                }
+                return None;
+            };
+            let frame: Vec<u8> = self.pending.drain(..=end).collect();
+            let frame = &frame[..frame.len() - 1];
+            if frame.is_empty() {
+                continue;
+            }
+            match cobs_decode(frame) {
+                Some(message) if message.len() <= MAX_MESSAGE_LEN => return Some(message),
+                _ => continue,
+            }
+        }
+    }
+}
+
+/// Turns a reply status into a result, using the reply data as the message
+pub fn check_status(status: u8, data: &[u8]) -> crate::Result<()> {
+    let message = || {
+        let text = String::from_utf8_lossy(data).trim().to_string();
+        if text.is_empty() {
+            format!("device returned status 0x{:02x}", status)
+        } else {
+            text
+        }
+    };
+    match status {
+        status::OK => Ok(()),
+        status::PIN_ERROR => Err(FirmwareError::PinError(message())),
+        status::UNSUPPORTED => Err(FirmwareError::UnsupportedPlatform(message())),
+        _ => Err(FirmwareError::CommunicationError(message())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cobs_round_trips_zeros_and_long_runs() {
+        let cases: Vec<Vec<u8>> = vec![
+            vec![],
+            vec![0],
+            vec![0, 0],
+            vec![1, 0, 2, 0],
+            (1..=254).collect(),
+            (0..600).map(|i| (i % 256) as u8).collect(),
+        ];
+        for data in cases {
+            let encoded = cobs_encode(&data);
+            assert!(!encoded.contains(&0), "zero in frame for {:?}", data);
+            assert_eq!(cobs_decode(&encoded), Some(data));
+        }
+    }
+
+    #[test]
+    fn frame_reader_skips_corrupt_frames_and_resynchronises() {
+        let mut reader = FrameReader::new();
+        reader.push(&[0x05, 0x01, 0x00]);
+        reader.push(&encode_frame(&[7, 0, 1]));
+        reader.push(&encode_frame(&[8])[..2]);
+
+        assert_eq!(reader.next_message(), Some(vec![7, 0, 1]));
+        assert_eq!(reader.next_message(), None);
+
+        reader.push(&[0]);
+        assert_eq!(reader.next_message(), Some(vec![8]));
+    }
+
+    #[test]
+    fn error_statuses_map_to_firmware_errors() {
+        assert!(check_status(status::OK, &[]).is_ok());
+        assert!(matches!(
+            check_status(status::PIN_ERROR, b"pin 40 is not an output"),
+            Err(FirmwareError::PinError(message)) if message == "pin 40 is not an output"
+        ));
+        assert!(matches!(
+            check_status(status::BUS_ERROR, &[]),
+            Err(FirmwareError::CommunicationError(message)) if message.contains("0x04")
+        ));
+    }
+}
@@ -12,28 +12,193 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+//! Raspberry Pi running Linux, driven through the kernel's own interfaces.
+//!
+//! Interfaces that are not enabled on the board (no `/dev/i2c-1` because
+//! `dtparam=i2c_arm=on` is missing, say) are skipped on connect and report
+//! [`DeviceNotConnected`](crate::FirmwareError::DeviceNotConnected) when used.
+
 use crate::{
-    AdcController, DeviceInfo, FirmwareDevice, GpioController, I2cController, Platform,
-    PwmController, Result, SpiController, UartController,
+    AdcController, DeviceInfo, FirmwareDevice, FirmwareError, GpioController, I2cController,
+    Platform, PwmController, Result, SpiConfig, SpiController, UartConfig, UartController,
 };
 use async_trait::async_trait;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Where each interface lives
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RaspberryPiPaths {
+    pub gpio_chip: PathBuf,
+    pub i2c_bus: PathBuf,
+    pub spi_device: PathBuf,
+    pub pwm_chip: PathBuf,
+    pub uart: PathBuf,
+}
+
+impl Default for RaspberryPiPaths {
+    fn default() -> Self {
+        Self::under("/")
+    }
+}
+
+impl RaspberryPiPaths {
+    /// The standard layout below `root` instead of `/`, for fake device trees
+    pub fn under(root: impl AsRef<Path>) -> Self {
+        let root = root.as_ref();
+        Self {
+            gpio_chip: root.join("dev/gpiochip0"),
+            i2c_bus: root.join("dev/i2c-1"),
+            spi_device: root.join("dev/spidev0.0"),
+            pwm_chip: root.join("sys/class/pwm/pwmchip0"),
+            uart: root.join("dev/serial0"),
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+struct Interfaces {
+    gpio: crate::linux::CdevGpio,
+    i2c: crate::linux::I2cDev,
+    spi: crate::linux::SpiDev,
+    pwm: crate::linux::SysfsPwm,
+    uart: crate::linux::TermiosUart,
+}
+
+#[cfg(target_os = "linux")]
+impl Interfaces {
+    fn new(paths: &RaspberryPiPaths, spi: Option<&SpiConfig>, uart: Option<&UartConfig>) -> Self {
+        use crate::linux::{CdevGpio, I2cDev, SpiDev, SysfsPwm, TermiosUart};
+
+        let mut spi_dev = SpiDev::new(&paths.spi_device);
+        if let Some(config) = spi {
+            spi_dev = spi_dev.with_config(config.clone());
+        }
+        let mut uart_port = TermiosUart::new(&paths.uart);
+        if let Some(config) = uart {
+            uart_port = uart_port.with_config(config.clone());
+        }
+        Self {
+            gpio: CdevGpio::new(&paths.gpio_chip),
+            i2c: I2cDev::new(&paths.i2c_bus),
+            spi: spi_dev,
+            pwm: SysfsPwm::new(&paths.pwm_chip),
+            uart: uart_port,
+        }
+    }
+
+    /// Opens every interface that exists; returns how many did
+    fn open(&self) -> Result<usize> {
+        let attempts = [
+            ("gpio", self.gpio.open()),
+            ("i2c", self.i2c.open()),
+            ("spi", self.spi.open()),
+            ("pwm", self.pwm.open()),
+            ("uart", self.uart.open()),
+        ];
+        let mut opened = 0;
+        for (name, result) in attempts {
+            match result {
+                Ok(()) => opened += 1,
+                Err(FirmwareError::DeviceNotConnected(reason)) => {
+                    log::debug!("Raspberry Pi {} unavailable: {}", name, reason)
+                }
+                Err(e) => {
+                    self.close();
+                    return Err(e);
+                }
+            }
+        }
+        Ok(opened)
+    }
+
+    fn close(&self) {
+        self.gpio.close();
+        self.i2c.close();
+        self.spi.close();
+        self.pwm.close();
+        self.uart.close();
+    }
+}
 
 pub struct RaspberryPiDevice {
-    port: String,
     name: String,
+    paths: RaspberryPiPaths,
+    spi_config: Option<SpiConfig>,
+    uart_config: Option<UartConfig>,
+    #[cfg(target_os = "linux")]
+    interfaces: Interfaces,
+    connected: AtomicBool,
 }
 
 impl RaspberryPiDevice {
     pub fn new(name: impl Into<String>) -> Self {
+        Self::build(name.into(), RaspberryPiPaths::default(), None, None)
+    }
+
+    fn build(
+        name: String,
+        paths: RaspberryPiPaths,
+        spi_config: Option<SpiConfig>,
+        uart_config: Option<UartConfig>,
+    ) -> Self {
         Self {
-            port: "/dev/spidev0.0".to_string(),
-            name: name.into(),
+            #[cfg(target_os = "linux")]
+            interfaces: Interfaces::new(&paths, spi_config.as_ref(), uart_config.as_ref()),
+            name,
+            paths,
+            spi_config,
+            uart_config,
+            connected: AtomicBool::new(false),
         }
     }
 
-    pub fn with_spi_port(mut self, port: impl Into<String>) -> Self {
-        self.port = port.into();
-        self
+    fn rebuild(self) -> Self {
+        Self::build(self.name, self.paths, self.spi_config, self.uart_config)
+    }
+
+    pub fn with_paths(mut self, paths: RaspberryPiPaths) -> Self {
+        self.paths = paths;
+        self.rebuild()
+    }
+
+    pub fn with_gpio_chip(mut self, path: impl Into<PathBuf>) -> Self {
+        self.paths.gpio_chip = path.into();
+        self.rebuild()
+    }
+
+    pub fn with_i2c_bus(mut self, path: impl Into<PathBuf>) -> Self {
+        self.paths.i2c_bus = path.into();
+        self.rebuild()
+    }
+
+    pub fn with_spi_port(mut self, port: impl Into<PathBuf>) -> Self {
+        self.paths.spi_device = port.into();
+        self.rebuild()
+    }
+
+    pub fn with_pwm_chip(mut self, path: impl Into<PathBuf>) -> Self {
+        self.paths.pwm_chip = path.into();
+        self.rebuild()
+    }
+
+    pub fn with_uart_port(mut self, port: impl Into<PathBuf>) -> Self {
+        self.paths.uart = port.into();
+        self.rebuild()
+    }
+
+    pub fn with_spi_config(mut self, config: SpiConfig) -> Self {
+        self.spi_config = Some(config);
+        self.rebuild()
+    }
+
+    pub fn with_uart_config(mut self, config: UartConfig) -> Self {
+        self.uart_config = Some(config);
+        self.rebuild()
+    }
+
+    pub fn paths(&self) -> &RaspberryPiPaths {
+        &self.paths
     }
 }
 
@@ -54,21 +219,48 @@ impl FirmwareDevice for RaspberryPiDevice {
         }
     }
 
+    #[cfg(target_os = "linux")]
+    fn gpio(&self) -> Option<&dyn GpioController> {
+        Some(&self.interfaces.gpio)
+    }
+    #[cfg(target_os = "linux")]
+    fn i2c(&self) -> Option<&dyn I2cController> {
+        Some(&self.interfaces.i2c)
+    }
+    #[cfg(target_os = "linux")]
+    fn spi(&self) -> Option<&dyn SpiController> {
+        Some(&self.interfaces.spi)
+    }
+    #[cfg(target_os = "linux")]
+    fn uart(&self) -> Option<&dyn UartController> {
+        Some(&self.interfaces.uart)
+    }
+    #[cfg(target_os = "linux")]
+    fn pwm(&self) -> Option<&dyn PwmController> {
+        Some(&self.interfaces.pwm)
+    }
+
+    #[cfg(not(target_os = "linux"))]
     fn gpio(&self) -> Option<&dyn GpioController> {
         None
     }
+    #[cfg(not(target_os = "linux"))]
     fn i2c(&self) -> Option<&dyn I2cController> {
         None
     }
+    #[cfg(not(target_os = "linux"))]
     fn spi(&self) -> Option<&dyn SpiController> {
         None
     }
+    #[cfg(not(target_os = "linux"))]
     fn uart(&self) -> Option<&dyn UartController> {
         None
     }
+    #[cfg(not(target_os = "linux"))]
     fn pwm(&self) -> Option<&dyn PwmController> {
         None
     }
+
     fn adc(&self) -> Option<&dyn AdcController> {
         None
     }
@@ -76,21 +268,137 @@ impl FirmwareDevice for RaspberryPiDevice {
         None
     }
 
+    #[cfg(target_os = "linux")]
     async fn connect(&self) -> Result<()> {
         log::info!("Connecting to Raspberry Pi device");
+        if self.interfaces.open()? == 0 {
+            return Err(FirmwareError::DeviceNotConnected(format!(
+                "none of {}, {}, {}, {} or {} exist",
+                self.paths.gpio_chip.display(),
+                self.paths.i2c_bus.display(),
+                self.paths.spi_device.display(),
+                self.paths.pwm_chip.display(),
+                self.paths.uart.display()
+            )));
+        }
+        self.connected.store(true, Ordering::SeqCst);
         Ok(())
     }
 
+    #[cfg(not(target_os = "linux"))]
+    async fn connect(&self) -> Result<()> {
+        Err(FirmwareError::UnsupportedPlatform(
+            "Raspberry Pi interfaces are only available on Linux".to_string(),
+        ))
+    }
+
     async fn disconnect(&self) -> Result<()> {
         log::info!("Disconnecting from Raspberry Pi device");
+        #[cfg(target_os = "linux")]
+        self.interfaces.close();
+        self.connected.store(false, Ordering::SeqCst);
         Ok(())
     }
 
     async fn is_connected(&self) -> Result<bool> {
-        Ok(true)
+        Ok(self.connected.load(Ordering::SeqCst))
     }
+
+    /// Releases and reopens every interface, which returns GPIO lines to
+    /// the kernel
     async fn reset(&self) -> Result<()> {
         log::info!("Resetting Raspberry Pi device");
-        Ok(())
+        self.disconnect().await?;
+        self.connect().await
+    }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+    use crate::{PinState, PwmConfig};
+    use std::fs;
+
+    /// A board with the character devices as plain files and one PWM channel
+    fn fake_board(root: &Path) -> RaspberryPiPaths {
+        let paths = RaspberryPiPaths::under(root);
+        fs::create_dir_all(root.join("dev")).unwrap();
+        fs::write(&paths.gpio_chip, "").unwrap();
+        fs::write(&paths.i2c_bus, "").unwrap();
+        fs::write(&paths.spi_device, "").unwrap();
+        let channel = paths.pwm_chip.join("pwm0");
+        fs::create_dir_all(&channel).unwrap();
+        fs::write(paths.pwm_chip.join("export"), "").unwrap();
+        fs::write(paths.pwm_chip.join("npwm"), "2\n").unwrap();
+        for file in ["period", "duty_cycle", "enable"] {
+            fs::write(channel.join(file), "0\n").unwrap();
+        }
+        paths
+    }
+
+    #[test]
+    fn paths_default_to_the_standard_layout() {
+        let paths = RaspberryPiPaths::default();
+        assert_eq!(paths.gpio_chip, PathBuf::from("/dev/gpiochip0"));
+        assert_eq!(paths.pwm_chip, PathBuf::from("/sys/class/pwm/pwmchip0"));
+
+        let device = RaspberryPiDevice::new("pi").with_spi_port("/dev/spidev0.1");
+        assert_eq!(device.paths().spi_device, PathBuf::from("/dev/spidev0.1"));
+    }
+
+    #[tokio::test]
+    async fn connects_to_the_interfaces_that_exist() {
+        let dir = tempfile::tempdir().unwrap();
+        let device = RaspberryPiDevice::new("pi").with_paths(fake_board(dir.path()));
+
+        assert!(matches!(
+            device.gpio().unwrap().read_pin(17).await,
+            Err(FirmwareError::DeviceNotConnected(_))
+        ));
+        device.connect().await.unwrap();
+        assert!(device.is_connected().await.unwrap());
+
+        device
+            .pwm()
+            .unwrap()
+            .configure(PwmConfig {
+                pin: 0,
+                frequency: 50,
+                duty_cycle: 0.075,
+            })
+            .await
+            .unwrap();
+        let duty = fs::read_to_string(device.paths().pwm_chip.join("pwm0/duty_cycle")).unwrap();
+        assert_eq!(duty, "1500000");
+
+        // Plain files stand in for the character devices, so ioctls fail
+        assert!(matches!(
+            device.gpio().unwrap().write_pin(17, PinState::High).await,
+            Err(FirmwareError::UnsupportedPlatform(_))
+        ));
+        // No serial0 in the fake tree
+        assert!(matches!(
+            device.uart().unwrap().available().await,
+            Err(FirmwareError::DeviceNotConnected(_))
+        ));
+
+        device.disconnect().await.unwrap();
+        assert!(!device.is_connected().await.unwrap());
+        assert!(matches!(
+            device.i2c().unwrap().scan_devices().await,
+            Err(FirmwareError::DeviceNotConnected(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn connect_fails_without_any_interface() {
+        let dir = tempfile::tempdir().unwrap();
+        let device = RaspberryPiDevice::new("pi").with_paths(RaspberryPiPaths::under(dir.path()));
+
+        assert!(matches!(
+            device.connect().await,
+            Err(FirmwareError::DeviceNotConnected(_))
+        ));
+        assert!(!device.is_connected().await.unwrap());
     }
 }
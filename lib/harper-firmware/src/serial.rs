@@ -0,0 +1,859 @@
+// Copyright 2026 harpertoken
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Controllers for boards running the companion firmware over a serial port.
+//!
+//! [`SerialBoard`] owns the port and implements every controller trait by
+//! sending one [`protocol`](crate::protocol) request per call. Port I/O is
+//! blocking, so each request runs on Tokio's blocking pool.
+//!
+//! [`SerialMonitor`] reads the plain text a board logs to the same port, for
+//! tailing a console after flashing.
+
+use crate::protocol::{self, opcode, pin_mode, pin_state, FrameReader};
+use crate::{
+    AdcConfig, AdcController, FirmwareError, GpioController, I2cController, I2cMessage, PinConfig,
+    PinMode, PinState, PwmConfig, PwmController, Result, SpiController, UartController,
+};
+use async_trait::async_trait;
+use serialport::SerialPort;
+use std::collections::HashMap;
+use std::io::{ErrorKind, Read, Write};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+pub const DEFAULT_BAUD_RATE: u32 = 115_200;
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// How long one port read waits before the reply deadline is checked again
+const READ_POLL: Duration = Duration::from_millis(50);
+
+/// ADC settings assumed until a pin is configured
+const DEFAULT_ADC_RESOLUTION: u8 = 12;
+const DEFAULT_ADC_VREF: f32 = 3.3;
+
+/// An open port and the request sequence
+struct Link {
+    port: Box<dyn SerialPort>,
+    sequence: u8,
+    reader: FrameReader,
+}
+
+impl Link {
+    fn open(path: &str, baud_rate: u32) -> Result<Self> {
+        let port = serialport::new(path, baud_rate)
+            .timeout(READ_POLL)
+            .open()
+            .map_err(|e| FirmwareError::DeviceNotConnected(format!("{}: {}", path, e)))?;
+        Ok(Self {
+            port,
+            sequence: 0,
+            reader: FrameReader::new(),
+        })
+    }
+
+    /// Sends one request and waits for the reply with the same sequence
+    fn transact(&mut self, opcode: u8, args: &[u8], timeout: Duration) -> Result<Vec<u8>> {
+        self.sequence = self.sequence.wrapping_add(1);
+        let mut request = Vec::with_capacity(args.len() + 2);
+        request.push(self.sequence);
+        request.push(opcode);
+        request.extend_from_slice(args);
+
+        self.port
+            .write_all(&protocol::encode_frame(&request))
+            .and_then(|()| self.port.flush())
+            .map_err(|e| FirmwareError::IoError(e.to_string()))?;
+
+        let deadline = Instant::now() + timeout;
+        loop {
+            while let Some(reply) = self.reader.next_message() {
+                if reply.len() < 2 || reply[0] != self.sequence {
+                    log::debug!("Dropping stale firmware reply {:?}", reply.first());
+                    continue;
+                }
+                protocol::check_status(reply[1], &reply[2..])?;
+                return Ok(reply[2..].to_vec());
+            }
+            if Instant::now() >= deadline {
+                return Err(FirmwareError::CommunicationError(format!(
+                    "no reply to opcode 0x{:02x} within {} ms",
+                    opcode,
+                    timeout.as_millis()
+                )));
+            }
+
+            let mut buffer = [0u8; 256];
+            match self.port.read(&mut buffer) {
+                Ok(0) => {
+                    return Err(FirmwareError::DeviceNotConnected(
+                        "serial port closed".to_string(),
+                    ))
+                }
+                Ok(read) => self.reader.push(&buffer[..read]),
+                Err(e) if e.kind() == ErrorKind::TimedOut => {}
+                Err(e) => return Err(FirmwareError::IoError(e.to_string())),
+            }
+        }
+    }
+}
+
+/// A board running the companion firmware, reached over a serial port
+pub struct SerialBoard {
+    port: String,
+    baud_rate: u32,
+    timeout: Duration,
+    link: Arc<Mutex<Option<Link>>>,
+    firmware_version: Mutex<Option<String>>,
+    adc: Mutex<HashMap<u32, AdcConfig>>,
+}
+
+impl SerialBoard {
+    pub fn new(port: impl Into<String>) -> Self {
+        Self {
+            port: port.into(),
+            baud_rate: DEFAULT_BAUD_RATE,
+            timeout: DEFAULT_TIMEOUT,
+            link: Arc::new(Mutex::new(None)),
+            firmware_version: Mutex::new(None),
+            adc: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn with_baud_rate(mut self, baud_rate: u32) -> Self {
+        self.baud_rate = baud_rate;
+        self
+    }
+
+    /// How long to wait for each reply
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn port(&self) -> &str {
+        &self.port
+    }
+
+    /// Version the firmware reported when it was last connected
+    pub fn firmware_version(&self) -> Option<String> {
+        self.firmware_version.lock().ok()?.clone()
+    }
+
+    /// Opens the port and checks the firmware speaks this protocol version
+    pub async fn connect(&self) -> Result<()> {
+        if self.is_connected() {
+            return Ok(());
+        }
+        let path = self.port.clone();
+        let baud_rate = self.baud_rate;
+        let link = tokio::task::spawn_blocking(move || Link::open(&path, baud_rate))
+            .await
+            .map_err(|e| FirmwareError::CommunicationError(e.to_string()))??;
+        *self.lock_link()? = Some(link);
+
+        let hello = match self.request(opcode::HELLO, Vec::new()).await {
+            Ok(hello) => hello,
+            Err(e) => {
+                *self.lock_link()? = None;
+                return Err(e);
+            }
+        };
+        if hello.first() != Some(&protocol::PROTOCOL_VERSION) {
+            *self.lock_link()? = None;
+            return Err(FirmwareError::CommunicationError(format!(
+                "{} speaks protocol version {}, expected {}",
+                self.port,
+                hello.first().copied().unwrap_or(0),
+                protocol::PROTOCOL_VERSION
+            )));
+        }
+        let version = String::from_utf8_lossy(&hello[1..]).trim().to_string();
+        if let Ok(mut firmware_version) = self.firmware_version.lock() {
+            *firmware_version = (!version.is_empty()).then_some(version);
+        }
+        Ok(())
+    }
+
+    pub async fn disconnect(&self) -> Result<()> {
+        *self.lock_link()? = None;
+        Ok(())
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.link.lock().is_ok_and(|link| link.is_some())
+    }
+
+    pub async fn reset(&self) -> Result<()> {
+        self.request(opcode::RESET, Vec::new()).await.map(|_| ())
+    }
+
+    fn lock_link(&self) -> Result<std::sync::MutexGuard<'_, Option<Link>>> {
+        self.link
+            .lock()
+            .map_err(|_| FirmwareError::CommunicationError("serial link poisoned".to_string()))
+    }
+
+    async fn request(&self, opcode: u8, args: Vec<u8>) -> Result<Vec<u8>> {
+        if args.len() > protocol::MAX_DATA_LEN {
+            return Err(too_long(args.len()));
+        }
+        let link = self.link.clone();
+        let port = self.port.clone();
+        let timeout = self.timeout;
+        tokio::task::spawn_blocking(move || {
+            let mut link = link.lock().map_err(|_| {
+                FirmwareError::CommunicationError("serial link poisoned".to_string())
+            })?;
+            let Some(link) = link.as_mut() else {
+                return Err(FirmwareError::DeviceNotConnected(port));
+            };
+            link.transact(opcode, &args, timeout)
+        })
+        .await
+        .map_err(|e| FirmwareError::CommunicationError(e.to_string()))?
+    }
+}
+
+/// Line-oriented reader for the text a board logs to its serial port
+///
+/// Reads block, so run the monitor on a blocking thread.
+pub struct SerialMonitor {
+    port: Box<dyn SerialPort>,
+    pending: Vec<u8>,
+}
+
+impl SerialMonitor {
+    pub fn open(path: &str, baud_rate: u32) -> Result<Self> {
+        let port = serialport::new(path, baud_rate)
+            .timeout(READ_POLL)
+            .open()
+            .map_err(|e| FirmwareError::DeviceNotConnected(format!("{}: {}", path, e)))?;
+        Ok(Self {
+            port,
+            pending: Vec::new(),
+        })
+    }
+
+    /// The next line without its terminator, or `None` if no full line
+    /// arrives before `deadline`
+    pub fn next_line(&mut self, deadline: Instant) -> Result<Option<String>> {
+        loop {
+            if let Some(end) = self.pending.iter().position(|byte| *byte == b'\n') {
+                let line: Vec<u8> = self.pending.drain(..=end).collect();
+                return Ok(Some(decode_line(&line)));
+            }
+            if Instant::now() >= deadline {
+                return Ok(None);
+            }
+
+            let mut buffer = [0u8; 256];
+            match self.port.read(&mut buffer) {
+                Ok(0) => {
+                    return Err(FirmwareError::DeviceNotConnected(
+                        "serial port closed".to_string(),
+                    ))
+                }
+                Ok(read) => self.pending.extend_from_slice(&buffer[..read]),
+                Err(e) if e.kind() == ErrorKind::TimedOut => {}
+                Err(e) => return Err(FirmwareError::IoError(e.to_string())),
+            }
+        }
+    }
+
+    /// Text received after the last complete line
+    pub fn take_partial(&mut self) -> Option<String> {
+        if self.pending.is_empty() {
+            return None;
+        }
+        let line = std::mem::take(&mut self.pending);
+        Some(decode_line(&line))
+    }
+}
+
+/// Lossy UTF-8 with the line terminator removed
+fn decode_line(line: &[u8]) -> String {
+    String::from_utf8_lossy(line)
+        .trim_end_matches(['\r', '\n'])
+        .to_string()
+}
+
+fn too_long(length: usize) -> FirmwareError {
+    FirmwareError::CommunicationError(format!(
+        "{} bytes exceeds the {} byte frame limit",
+        length,
+        protocol::MAX_DATA_LEN
+    ))
+}
+
+fn pin_byte(pin: u32) -> Result<u8> {
+    u8::try_from(pin).map_err(|_| FirmwareError::PinError(format!("pin {} out of range", pin)))
+}
+
+fn length_bytes(length: usize) -> Result<[u8; 2]> {
+    if length > protocol::MAX_DATA_LEN {
+        return Err(too_long(length));
+    }
+    Ok((length as u16).to_le_bytes())
+}
+
+fn state_byte(state: PinState) -> Result<u8> {
+    match state {
+        PinState::Low => Ok(pin_state::LOW),
+        PinState::High => Ok(pin_state::HIGH),
+        PinState::Unknown => Err(FirmwareError::PinError(
+            "cannot drive a pin to an unknown state".to_string(),
+        )),
+    }
+}
+
+fn check_duty(duty: f32) -> Result<()> {
+    if (0.0..=1.0).contains(&duty) {
+        Ok(())
+    } else {
+        Err(FirmwareError::PinError(format!(
+            "duty cycle {} is outside 0.0..=1.0",
+            duty
+        )))
+    }
+}
+
+/// Reads a little-endian integer reply of `N` bytes
+fn reply_bytes<const N: usize>(reply: &[u8]) -> Result<[u8; N]> {
+    reply
+        .get(..N)
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| {
+            FirmwareError::CommunicationError(format!(
+                "expected a {} byte reply, got {}",
+                N,
+                reply.len()
+            ))
+        })
+}
+
+#[async_trait]
+impl GpioController for SerialBoard {
+    async fn configure_pin(&self, config: PinConfig) -> Result<()> {
+        let mode = match config.mode {
+            PinMode::Input => pin_mode::INPUT,
+            PinMode::Output => pin_mode::OUTPUT,
+            PinMode::InputPullUp => pin_mode::INPUT_PULL_UP,
+            PinMode::InputPullDown => pin_mode::INPUT_PULL_DOWN,
+            PinMode::Analog => pin_mode::ANALOG,
+        };
+        let initial = match config.initial_state {
+            Some(state) => state_byte(state)?,
+            None => pin_state::UNCHANGED,
+        };
+        let args = vec![pin_byte(config.pin_number)?, mode, initial];
+        self.request(opcode::GPIO_CONFIGURE, args).await.map(|_| ())
+    }
+
+    async fn write_pin(&self, pin: u32, state: PinState) -> Result<()> {
+        let args = vec![pin_byte(pin)?, state_byte(state)?];
+        self.request(opcode::GPIO_WRITE, args).await.map(|_| ())
+    }
+
+    async fn read_pin(&self, pin: u32) -> Result<PinState> {
+        let reply = self
+            .request(opcode::GPIO_READ, vec![pin_byte(pin)?])
+            .await?;
+        Ok(match reply.first() {
+            Some(&pin_state::LOW) => PinState::Low,
+            Some(&pin_state::HIGH) => PinState::High,
+            _ => PinState::Unknown,
+        })
+    }
+
+    async fn toggle_pin(&self, pin: u32) -> Result<()> {
+        self.request(opcode::GPIO_TOGGLE, vec![pin_byte(pin)?])
+            .await
+            .map(|_| ())
+    }
+}
+
+#[async_trait]
+impl I2cController for SerialBoard {
+    async fn write(&self, message: I2cMessage) -> Result<()> {
+        let mut args = vec![message.address];
+        args.extend(message.data);
+        self.request(opcode::I2C_WRITE, args).await.map(|_| ())
+    }
+
+    async fn read(&self, address: u8, length: usize) -> Result<Vec<u8>> {
+        let mut args = vec![address];
+        args.extend(length_bytes(length)?);
+        self.request(opcode::I2C_READ, args).await
+    }
+
+    /// The first byte of `write_data` is the device address, since the
+    /// trait does not carry one separately
+    async fn write_read(&self, write_data: Vec<u8>, read_len: usize) -> Result<Vec<u8>> {
+        let Some((&address, data)) = write_data.split_first() else {
+            return Err(FirmwareError::CommunicationError(
+                "write_read needs the device address as its first byte".to_string(),
+            ));
+        };
+        let mut args = vec![address];
+        args.extend(length_bytes(read_len)?);
+        args.extend_from_slice(data);
+        self.request(opcode::I2C_WRITE_READ, args).await
+    }
+
+    async fn scan_devices(&self) -> Result<Vec<u8>> {
+        self.request(opcode::I2C_SCAN, Vec::new()).await
+    }
+}
+
+#[async_trait]
+impl SpiController for SerialBoard {
+    async fn transfer(&self, data: &[u8]) -> Result<Vec<u8>> {
+        self.request(opcode::SPI_TRANSFER, data.to_vec()).await
+    }
+
+    async fn write(&self, data: &[u8]) -> Result<()> {
+        self.request(opcode::SPI_WRITE, data.to_vec())
+            .await
+            .map(|_| ())
+    }
+
+    async fn read(&self, length: usize) -> Result<Vec<u8>> {
+        self.request(opcode::SPI_READ, length_bytes(length)?.to_vec())
+            .await
+    }
+}
+
+#[async_trait]
+impl UartController for SerialBoard {
+    async fn write(&self, data: &[u8]) -> Result<usize> {
+        let reply = self.request(opcode::UART_WRITE, data.to_vec()).await?;
+        Ok(u16::from_le_bytes(reply_bytes(&reply)?) as usize)
+    }
+
+    async fn read(&self, length: usize) -> Result<Vec<u8>> {
+        self.request(opcode::UART_READ, length_bytes(length)?.to_vec())
+            .await
+    }
+
+    async fn write_string(&self, data: &str) -> Result<usize> {
+        UartController::write(self, data.as_bytes()).await
+    }
+
+    async fn flush(&self) -> Result<()> {
+        self.request(opcode::UART_FLUSH, Vec::new())
+            .await
+            .map(|_| ())
+    }
+
+    async fn available(&self) -> Result<usize> {
+        let reply = self.request(opcode::UART_AVAILABLE, Vec::new()).await?;
+        Ok(u16::from_le_bytes(reply_bytes(&reply)?) as usize)
+    }
+}
+
+#[async_trait]
+impl PwmController for SerialBoard {
+    async fn configure(&self, config: PwmConfig) -> Result<()> {
+        check_duty(config.duty_cycle)?;
+        let mut args = vec![pin_byte(config.pin)?];
+        args.extend(config.frequency.to_le_bytes());
+        args.extend(config.duty_cycle.to_le_bytes());
+        self.request(opcode::PWM_CONFIGURE, args).await.map(|_| ())
+    }
+
+    async fn set_duty_cycle(&self, pin: u32, duty: f32) -> Result<()> {
+        check_duty(duty)?;
+        let mut args = vec![pin_byte(pin)?];
+        args.extend(duty.to_le_bytes());
+        self.request(opcode::PWM_DUTY, args).await.map(|_| ())
+    }
+
+    async fn set_frequency(&self, pin: u32, frequency: u32) -> Result<()> {
+        let mut args = vec![pin_byte(pin)?];
+        args.extend(frequency.to_le_bytes());
+        self.request(opcode::PWM_FREQUENCY, args).await.map(|_| ())
+    }
+}
+
+#[async_trait]
+impl AdcController for SerialBoard {
+    async fn read_voltage(&self, pin: u32) -> Result<f32> {
+        let raw = self.read_raw(pin).await?;
+        let (resolution, vref) = self
+            .adc
+            .lock()
+            .ok()
+            .and_then(|adc| adc.get(&pin).map(|config| (config.resolution, config.vref)))
+            .unwrap_or((DEFAULT_ADC_RESOLUTION, DEFAULT_ADC_VREF));
+        let full_scale = ((1u64 << resolution.min(32)) - 1) as f32;
+        Ok(raw as f32 / full_scale * vref)
+    }
+
+    async fn read_raw(&self, pin: u32) -> Result<u32> {
+        let reply = self.request(opcode::ADC_READ, vec![pin_byte(pin)?]).await?;
+        Ok(u32::from_le_bytes(reply_bytes(&reply)?))
+    }
+
+    async fn configure(&self, config: AdcConfig) -> Result<()> {
+        if config.resolution == 0 || config.resolution > 32 {
+            return Err(FirmwareError::PinError(format!(
+                "ADC resolution of {} bits is not supported",
+                config.resolution
+            )));
+        }
+        let mut args = vec![pin_byte(config.pin)?, config.resolution];
+        args.extend(config.vref.to_le_bytes());
+        self.request(opcode::ADC_CONFIGURE, args).await?;
+        if let Ok(mut adc) = self.adc.lock() {
+            adc.insert(config.pin, config);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::protocol::status;
+    use crate::{Esp32Device, FirmwareDevice, Stm32Device};
+    use serialport::TTYPort;
+    use std::collections::VecDeque;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::thread::JoinHandle;
+
+    const SENSOR_ADDRESS: u8 = 0x48;
+
+    /// Companion firmware stand-in on the master side of a pty pair
+    struct Simulator {
+        pins: HashMap<u8, u8>,
+        registers: [u8; 16],
+        register_pointer: usize,
+        uart: VecDeque<u8>,
+        pwm: HashMap<u8, f32>,
+    }
+
+    impl Simulator {
+        fn new() -> Self {
+            Self {
+                pins: HashMap::new(),
+                registers: [0; 16],
+                register_pointer: 0,
+                uart: VecDeque::new(),
+                pwm: HashMap::new(),
+            }
+        }
+
+        fn handle(&mut self, opcode: u8, args: &[u8]) -> (u8, Vec<u8>) {
+            let read_len = |at: usize| u16::from_le_bytes([args[at], args[at + 1]]) as usize;
+            match opcode {
+                opcode::HELLO => {
+                    let mut reply = vec![protocol::PROTOCOL_VERSION];
+                    reply.extend_from_slice(b"sim-0.1");
+                    (status::OK, reply)
+                }
+                opcode::RESET => {
+                    self.pins.clear();
+                    (status::OK, Vec::new())
+                }
+                opcode::GPIO_CONFIGURE => {
+                    if args[2] != pin_state::UNCHANGED {
+                        self.pins.insert(args[0], args[2]);
+                    }
+                    (status::OK, Vec::new())
+                }
+                opcode::GPIO_WRITE if args[0] >= 40 => (
+                    status::PIN_ERROR,
+                    format!("pin {} does not exist", args[0]).into_bytes(),
+                ),
+                opcode::GPIO_WRITE => {
+                    self.pins.insert(args[0], args[1]);
+                    (status::OK, Vec::new())
+                }
+                opcode::GPIO_READ => (
+                    status::OK,
+                    vec![*self.pins.get(&args[0]).unwrap_or(&pin_state::LOW)],
+                ),
+                opcode::GPIO_TOGGLE => {
+                    let pin = self.pins.entry(args[0]).or_insert(pin_state::LOW);
+                    *pin ^= 1;
+                    (status::OK, Vec::new())
+                }
+                opcode::I2C_WRITE | opcode::I2C_READ | opcode::I2C_WRITE_READ
+                    if args[0] != SENSOR_ADDRESS =>
+                {
+                    (status::BUS_ERROR, b"address not acknowledged".to_vec())
+                }
+                opcode::I2C_WRITE => {
+                    self.write_registers(&args[1..]);
+                    (status::OK, Vec::new())
+                }
+                opcode::I2C_READ => (status::OK, self.read_registers(read_len(1))),
+                opcode::I2C_WRITE_READ => {
+                    self.write_registers(&args[3..]);
+                    (status::OK, self.read_registers(read_len(1)))
+                }
+                opcode::I2C_SCAN => (status::OK, vec![SENSOR_ADDRESS]),
+                opcode::SPI_TRANSFER => (status::OK, args.to_vec()),
+                opcode::SPI_WRITE => (status::OK, Vec::new()),
+                opcode::SPI_READ => (status::OK, vec![0xff; read_len(0)]),
+                opcode::UART_WRITE => {
+                    self.uart.extend(args);
+                    (status::OK, (args.len() as u16).to_le_bytes().to_vec())
+                }
+                opcode::UART_READ => {
+                    let count = read_len(0).min(self.uart.len());
+                    (status::OK, self.uart.drain(..count).collect())
+                }
+                opcode::UART_FLUSH => (status::OK, Vec::new()),
+                opcode::UART_AVAILABLE => {
+                    (status::OK, (self.uart.len() as u16).to_le_bytes().to_vec())
+                }
+                opcode::PWM_CONFIGURE => {
+                    let duty = f32::from_le_bytes(args[5..9].try_into().unwrap());
+                    self.pwm.insert(args[0], duty);
+                    (status::OK, Vec::new())
+                }
+                opcode::PWM_DUTY => {
+                    let duty = f32::from_le_bytes(args[1..5].try_into().unwrap());
+                    self.pwm.insert(args[0], duty);
+                    (status::OK, Vec::new())
+                }
+                opcode::PWM_FREQUENCY | opcode::ADC_CONFIGURE => (status::OK, Vec::new()),
+                opcode::ADC_READ => (status::OK, 2048u32.to_le_bytes().to_vec()),
+                _ => (status::UNSUPPORTED, b"unknown opcode".to_vec()),
+            }
+        }
+
+        /// First byte selects the register, the rest are written from there
+        fn write_registers(&mut self, data: &[u8]) {
+            let Some((&register, values)) = data.split_first() else {
+                return;
+            };
+            self.register_pointer = register as usize % self.registers.len();
+            for value in values {
+                self.registers[self.register_pointer] = *value;
+                self.register_pointer = (self.register_pointer + 1) % self.registers.len();
+            }
+        }
+
+        fn read_registers(&mut self, length: usize) -> Vec<u8> {
+            (0..length)
+                .map(|_| {
+                    let value = self.registers[self.register_pointer];
+                    self.register_pointer = (self.register_pointer + 1) % self.registers.len();
+                    value
+                })
+                .collect()
+        }
+    }
+
+    /// A simulated board and the pty path the host should open
+    struct SimulatedPort {
+        path: String,
+        stop: Arc<AtomicBool>,
+        thread: Option<JoinHandle<()>>,
+        // Holding the slave open keeps the master readable between host opens
+        _slave: TTYPort,
+    }
+
+    impl SimulatedPort {
+        /// `respond` is false for a board that never answers
+        fn start(respond: bool) -> Self {
+            let (mut master, slave) = TTYPort::pair().expect("pty pair");
+            let path = slave.name().expect("slave pty path");
+            let stop = Arc::new(AtomicBool::new(false));
+            let thread_stop = stop.clone();
+            let thread = std::thread::spawn(move || {
+                let mut simulator = Simulator::new();
+                let mut reader = FrameReader::new();
+                let mut buffer = [0u8; 256];
+                while !thread_stop.load(Ordering::SeqCst) {
+                    match master.read(&mut buffer) {
+                        Ok(read) => reader.push(&buffer[..read]),
+                        Err(e) if e.kind() == ErrorKind::TimedOut => continue,
+                        Err(_) => break,
+                    }
+                    while let Some(request) = reader.next_message() {
+                        if !respond {
+                            continue;
+                        }
+                        let (status, data) = simulator.handle(request[1], &request[2..]);
+                        let mut reply = vec![request[0], status];
+                        reply.extend(data);
+                        master
+                            .write_all(&protocol::encode_frame(&reply))
+                            .expect("simulator write");
+                    }
+                }
+            });
+            Self {
+                path,
+                stop,
+                thread: Some(thread),
+                _slave: slave,
+            }
+        }
+    }
+
+    impl Drop for SimulatedPort {
+        fn drop(&mut self) {
+            self.stop.store(true, Ordering::SeqCst);
+            if let Some(thread) = self.thread.take() {
+                let _ = thread.join();
+            }
+        }
+    }
+
+    #[test]
+    fn monitor_splits_the_console_into_lines() {
+        let (mut master, slave) = TTYPort::pair().expect("pty pair");
+        let mut monitor = SerialMonitor::open(&slave.name().unwrap(), 115_200).unwrap();
+        master.write_all(b"boot: ok\r\nheap 2").unwrap();
+        master.write_all(b"048\nready").unwrap();
+
+        let deadline = Instant::now() + Duration::from_secs(2);
+        assert_eq!(
+            monitor.next_line(deadline).unwrap().as_deref(),
+            Some("boot: ok")
+        );
+        assert_eq!(
+            monitor.next_line(deadline).unwrap().as_deref(),
+            Some("heap 2048")
+        );
+        let soon = Instant::now() + Duration::from_millis(200);
+        assert_eq!(monitor.next_line(soon).unwrap(), None);
+        assert_eq!(monitor.take_partial().as_deref(), Some("ready"));
+        assert_eq!(monitor.take_partial(), None);
+    }
+
+    #[tokio::test]
+    async fn esp32_drives_every_controller_over_the_serial_protocol() {
+        let board = SimulatedPort::start(true);
+        let device = Esp32Device::new(&board.path, "sim");
+        device.connect().await.expect("connect");
+        assert!(device.is_connected().await.unwrap());
+        assert_eq!(
+            device.device_info().firmware_version.as_deref(),
+            Some("sim-0.1")
+        );
+
+        let gpio = device.gpio().expect("gpio");
+        gpio.write_pin(2, PinState::High).await.unwrap();
+        assert_eq!(gpio.read_pin(2).await.unwrap(), PinState::High);
+        gpio.toggle_pin(2).await.unwrap();
+        assert_eq!(gpio.read_pin(2).await.unwrap(), PinState::Low);
+        assert!(matches!(
+            gpio.write_pin(41, PinState::High).await,
+            Err(FirmwareError::PinError(message)) if message == "pin 41 does not exist"
+        ));
+
+        let i2c = device.i2c().expect("i2c");
+        assert_eq!(i2c.scan_devices().await.unwrap(), vec![SENSOR_ADDRESS]);
+        i2c.write(I2cMessage {
+            address: SENSOR_ADDRESS,
+            data: vec![0x04, 0xde, 0xad],
+        })
+        .await
+        .unwrap();
+        assert_eq!(
+            i2c.write_read(vec![SENSOR_ADDRESS, 0x04], 2).await.unwrap(),
+            vec![0xde, 0xad]
+        );
+        assert!(matches!(
+            i2c.read(0x50, 1).await,
+            Err(FirmwareError::CommunicationError(message)) if message == "address not acknowledged"
+        ));
+
+        let spi = device.spi().expect("spi");
+        assert_eq!(spi.transfer(&[0, 1, 0]).await.unwrap(), vec![0, 1, 0]);
+        assert_eq!(spi.read(3).await.unwrap(), vec![0xff; 3]);
+
+        let uart = device.uart().expect("uart");
+        assert_eq!(uart.write_string("ping").await.unwrap(), 4);
+        assert_eq!(uart.available().await.unwrap(), 4);
+        assert_eq!(uart.read(4).await.unwrap(), b"ping".to_vec());
+
+        let pwm = device.pwm().expect("pwm");
+        pwm.set_duty_cycle(5, 0.25).await.unwrap();
+        assert!(matches!(
+            pwm.set_duty_cycle(5, 1.5).await,
+            Err(FirmwareError::PinError(_))
+        ));
+
+        let adc = device.adc().expect("adc");
+        assert_eq!(adc.read_raw(34).await.unwrap(), 2048);
+        adc.configure(AdcConfig {
+            pin: 34,
+            resolution: 12,
+            vref: 3.3,
+        })
+        .await
+        .unwrap();
+        let voltage = adc.read_voltage(34).await.unwrap();
+        assert!((voltage - 1.65).abs() < 0.01, "voltage {}", voltage);
+
+        device.disconnect().await.unwrap();
+        assert!(!device.is_connected().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn stm32_has_no_pwm_and_reconnects() {
+        let board = SimulatedPort::start(true);
+        let device = Stm32Device::new(&board.path, "sim");
+        assert!(device.pwm().is_none());
+
+        device.connect().await.expect("connect");
+        device.disconnect().await.unwrap();
+        device.connect().await.expect("reconnect");
+        device
+            .gpio()
+            .unwrap()
+            .write_pin(13, PinState::High)
+            .await
+            .unwrap();
+        device.reset().await.unwrap();
+        assert_eq!(
+            device.gpio().unwrap().read_pin(13).await.unwrap(),
+            PinState::Low
+        );
+    }
+
+    #[tokio::test]
+    async fn requests_fail_before_connect() {
+        let device = Esp32Device::new("/dev/does-not-exist", "missing");
+
+        assert!(matches!(
+            device.gpio().unwrap().read_pin(2).await,
+            Err(FirmwareError::DeviceNotConnected(_))
+        ));
+        assert!(matches!(
+            device.connect().await,
+            Err(FirmwareError::DeviceNotConnected(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn silent_board_times_out_and_stays_disconnected() {
+        let board = SimulatedPort::start(false);
+        let device = SerialBoard::new(&board.path).with_timeout(Duration::from_millis(200));
+
+        let err = device.connect().await.unwrap_err();
+
+        assert!(
+            matches!(err, FirmwareError::CommunicationError(message) if message.contains("no reply"))
+        );
+        assert!(!device.is_connected());
+    }
+}
@@ -12,24 +12,42 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use crate::serial::SerialBoard;
 use crate::{
     AdcController, DeviceInfo, FirmwareDevice, GpioController, I2cController, Platform,
     PwmController, Result, SpiController, UartController,
 };
 use async_trait::async_trait;
+use std::time::Duration;
 
+/// STM32 board running the companion firmware on a serial port
 pub struct Stm32Device {
-    port: String,
+    board: SerialBoard,
     name: String,
 }
 
 impl Stm32Device {
     pub fn new(port: impl Into<String>, name: impl Into<String>) -> Self {
         Self {
-            port: port.into(),
+            board: SerialBoard::new(port),
             name: name.into(),
         }
     }
+
+    pub fn with_baud_rate(mut self, baud_rate: u32) -> Self {
+        self.board = self.board.with_baud_rate(baud_rate);
+        self
+    }
+
+    /// How long to wait for each reply from the board
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.board = self.board.with_timeout(timeout);
+        self
+    }
+
+    pub fn port(&self) -> &str {
+        self.board.port()
+    }
 }
 
 #[async_trait]
@@ -38,7 +56,10 @@ impl FirmwareDevice for Stm32Device {
         DeviceInfo {
             name: self.name.clone(),
             platform: Platform::Stm32,
-            firmware_version: Some(env!("CARGO_PKG_VERSION").to_string()),
+            firmware_version: self
+                .board
+                .firmware_version()
+                .or_else(|| Some(env!("CARGO_PKG_VERSION").to_string())),
             capabilities: vec![
                 "gpio".to_string(),
                 "i2c".to_string(),
@@ -50,42 +71,43 @@ impl FirmwareDevice for Stm32Device {
     }
 
     fn gpio(&self) -> Option<&dyn GpioController> {
-        None
+        Some(&self.board)
     }
     fn i2c(&self) -> Option<&dyn I2cController> {
-        None
+        Some(&self.board)
     }
     fn spi(&self) -> Option<&dyn SpiController> {
-        None
+        Some(&self.board)
     }
     fn uart(&self) -> Option<&dyn UartController> {
-        None
+        Some(&self.board)
     }
     fn pwm(&self) -> Option<&dyn PwmController> {
         None
     }
     fn adc(&self) -> Option<&dyn AdcController> {
-        None
+        Some(&self.board)
     }
     fn delay(&self) -> Option<&dyn crate::DelayController> {
         None
     }
 
     async fn connect(&self) -> Result<()> {
-        log::info!("Connecting to STM32 device on {}", self.port);
-        Ok(())
+        log::info!("Connecting to STM32 device on {}", self.board.port());
+        self.board.connect().await
     }
 
     async fn disconnect(&self) -> Result<()> {
         log::info!("Disconnecting from STM32 device");
-        Ok(())
+        self.board.disconnect().await
     }
 
     async fn is_connected(&self) -> Result<bool> {
-        Ok(true)
+        Ok(self.board.is_connected())
     }
+
     async fn reset(&self) -> Result<()> {
         log::info!("Resetting STM32 device");
-        Ok(())
+        self.board.reset().await
     }
 }
@@ -0,0 +1,1078 @@
+// Copyright 2026 harpertoken
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Simulated board for tests and demos.
+//!
+//! [`VirtualDevice`] keeps every controller in memory: GPIO pins, I2C
+//! peripherals with register maps, an SPI bus wired in loopback, a UART that
+//! echoes what it is sent and ADC channels that follow a waveform. The
+//! peripherals come from a [`VirtualDeviceSpec`], usually loaded from a TOML
+//! or JSON file, and every operation is recorded as a [`VirtualEvent`].
+
+use crate::{
+    AdcConfig, AdcController, DelayController, DeviceInfo, FirmwareDevice, FirmwareError,
+    GpioController, I2cController, I2cMessage, PinConfig, PinMode, PinState, Platform, PwmConfig,
+    PwmController, Result, SpiController, UartController,
+};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::path::Path;
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+const DEFAULT_GPIO_PINS: u32 = 40;
+const DEFAULT_REGISTER_COUNT: usize = 256;
+
+/// Peripherals of a virtual board
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VirtualDeviceSpec {
+    /// Pins `0..gpio_pins` exist
+    #[serde(default = "default_gpio_pins")]
+    pub gpio_pins: u32,
+    #[serde(default)]
+    pub i2c: Vec<I2cPeripheralSpec>,
+    #[serde(default)]
+    pub adc: Vec<AdcChannelSpec>,
+}
+
+fn default_gpio_pins() -> u32 {
+    DEFAULT_GPIO_PINS
+}
+
+impl Default for VirtualDeviceSpec {
+    fn default() -> Self {
+        Self {
+            gpio_pins: DEFAULT_GPIO_PINS,
+            i2c: Vec::new(),
+            adc: Vec::new(),
+        }
+    }
+}
+
+/// An I2C peripheral and the initial contents of its registers
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct I2cPeripheralSpec {
+    pub address: u8,
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Number of registers; the register pointer wraps after the last one
+    #[serde(default = "default_register_count")]
+    pub size: usize,
+    /// Register (`"0x10"` or `"16"`) to the value or values stored from it
+    #[serde(default)]
+    pub registers: BTreeMap<String, RegisterValue>,
+}
+
+fn default_register_count() -> usize {
+    DEFAULT_REGISTER_COUNT
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum RegisterValue {
+    Byte(u8),
+    Bytes(Vec<u8>),
+}
+
+/// An ADC channel whose input voltage follows a waveform
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AdcChannelSpec {
+    pub pin: u32,
+    #[serde(default)]
+    pub waveform: Waveform,
+    /// Volts around which the waveform swings, or the constant level
+    #[serde(default)]
+    pub offset: f32,
+    /// Peak deviation from `offset` in volts
+    #[serde(default)]
+    pub amplitude: f32,
+    #[serde(default = "default_period_ms")]
+    pub period_ms: u64,
+    #[serde(default = "default_resolution")]
+    pub resolution: u8,
+    #[serde(default = "default_vref")]
+    pub vref: f32,
+}
+
+fn default_period_ms() -> u64 {
+    1000
+}
+
+fn default_resolution() -> u8 {
+    12
+}
+
+fn default_vref() -> f32 {
+    3.3
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Waveform {
+    #[default]
+    Constant,
+    Sine,
+    Square,
+    Triangle,
+    Sawtooth,
+}
+
+impl Waveform {
+    /// Value in `-1.0..=1.0` at `phase` in `0.0..1.0`
+    fn sample(self, phase: f32) -> f32 {
+        match self {
+            Waveform::Constant => 0.0,
+            Waveform::Sine => (phase * std::f32::consts::TAU).sin(),
+            Waveform::Square => {
+                if phase < 0.5 {
+                    1.0
+                } else {
+                    -1.0
+                }
+            }
+            Waveform::Triangle => 1.0 - 4.0 * (phase - 0.5).abs(),
+            Waveform::Sawtooth => 2.0 * phase - 1.0,
+        }
+    }
+}
+
+impl VirtualDeviceSpec {
+    pub fn from_toml(text: &str) -> Result<Self> {
+        toml::from_str(text).map_err(|e| FirmwareError::ConfigError(e.to_string()))
+    }
+
+    pub fn from_json(text: &str) -> Result<Self> {
+        serde_json::from_str(text).map_err(|e| FirmwareError::ConfigError(e.to_string()))
+    }
+
+    /// Loads a `.json` file as JSON and anything else as TOML
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| FirmwareError::IoError(format!("{}: {}", path.display(), e)))?;
+        let is_json = path
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("json"));
+        let spec = if is_json {
+            Self::from_json(&text)
+        } else {
+            Self::from_toml(&text)
+        };
+        spec.map_err(|e| FirmwareError::ConfigError(format!("{}: {}", path.display(), e)))
+    }
+}
+
+/// Something a virtual board did, in the order it happened
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum VirtualEvent {
+    Connected,
+    Disconnected,
+    Reset,
+    PinConfigured {
+        pin: u32,
+        mode: PinMode,
+    },
+    PinWritten {
+        pin: u32,
+        state: PinState,
+    },
+    PinRead {
+        pin: u32,
+        state: PinState,
+    },
+    I2cWrite {
+        address: u8,
+        data: Vec<u8>,
+    },
+    I2cRead {
+        address: u8,
+        data: Vec<u8>,
+    },
+    I2cScan {
+        found: Vec<u8>,
+    },
+    SpiTransfer {
+        sent: Vec<u8>,
+        received: Vec<u8>,
+    },
+    UartWrite {
+        data: Vec<u8>,
+    },
+    UartRead {
+        data: Vec<u8>,
+    },
+    PwmConfigured {
+        pin: u32,
+        frequency: u32,
+        duty_cycle: f32,
+    },
+    PwmDutyCycle {
+        pin: u32,
+        duty_cycle: f32,
+    },
+    PwmFrequency {
+        pin: u32,
+        frequency: u32,
+    },
+    AdcConfigured {
+        pin: u32,
+        resolution: u8,
+        vref: f32,
+    },
+    AdcRead {
+        pin: u32,
+        raw: u32,
+    },
+    Delay {
+        micros: u64,
+    },
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Pin {
+    mode: Option<PinMode>,
+    state: PinState,
+}
+
+struct I2cPeripheral {
+    registers: Vec<u8>,
+    pointer: usize,
+}
+
+impl I2cPeripheral {
+    /// First byte selects the register, the rest are stored from there
+    fn write(&mut self, data: &[u8]) {
+        let Some((&register, values)) = data.split_first() else {
+            return;
+        };
+        self.pointer = register as usize % self.registers.len();
+        for value in values {
+            self.registers[self.pointer] = *value;
+            self.pointer = (self.pointer + 1) % self.registers.len();
+        }
+    }
+
+    fn read(&mut self, length: usize) -> Vec<u8> {
+        (0..length)
+            .map(|_| {
+                let value = self.registers[self.pointer];
+                self.pointer = (self.pointer + 1) % self.registers.len();
+                value
+            })
+            .collect()
+    }
+}
+
+struct State {
+    connected: bool,
+    pins: HashMap<u32, Pin>,
+    i2c: BTreeMap<u8, I2cPeripheral>,
+    uart: VecDeque<u8>,
+    pwm: HashMap<u32, PwmConfig>,
+    adc: HashMap<u32, AdcChannelSpec>,
+    events: Vec<VirtualEvent>,
+}
+
+/// A board simulated in memory; see the module documentation
+pub struct VirtualDevice {
+    name: String,
+    spec: VirtualDeviceSpec,
+    state: Mutex<State>,
+    started: Instant,
+    clock: Mutex<Option<Duration>>,
+}
+
+impl VirtualDevice {
+    /// A board with the default pins and no peripherals
+    pub fn new(name: impl Into<String>) -> Self {
+        Self::from_spec(name, VirtualDeviceSpec::default())
+            .expect("the default virtual device spec is valid")
+    }
+
+    pub fn from_spec(name: impl Into<String>, spec: VirtualDeviceSpec) -> Result<Self> {
+        let state = State {
+            connected: false,
+            pins: HashMap::new(),
+            i2c: build_i2c(&spec)?,
+            uart: VecDeque::new(),
+            pwm: HashMap::new(),
+            adc: build_adc(&spec)?,
+            events: Vec::new(),
+        };
+        Ok(Self {
+            name: name.into(),
+            spec,
+            state: Mutex::new(state),
+            started: Instant::now(),
+            clock: Mutex::new(None),
+        })
+    }
+
+    /// Loads the peripherals from a TOML or JSON file
+    pub fn from_spec_file(name: impl Into<String>, path: impl AsRef<Path>) -> Result<Self> {
+        Self::from_spec(name, VirtualDeviceSpec::from_file(path)?)
+    }
+
+    /// Every recorded event, oldest first
+    pub fn events(&self) -> Vec<VirtualEvent> {
+        self.state
+            .lock()
+            .map(|state| state.events.clone())
+            .unwrap_or_default()
+    }
+
+    pub fn clear_events(&self) {
+        if let Ok(mut state) = self.state.lock() {
+            state.events.clear();
+        }
+    }
+
+    /// Drives a pin from outside the board, as a button or sensor would
+    pub fn set_input(&self, pin: u32, state: PinState) -> Result<()> {
+        self.check_pin(pin)?;
+        self.lock_state()?
+            .pins
+            .entry(pin)
+            .or_insert(Pin { mode: None, state })
+            .state = state;
+        Ok(())
+    }
+
+    /// Current register contents of the peripheral at `address`
+    pub fn i2c_registers(&self, address: u8) -> Option<Vec<u8>> {
+        let state = self.state.lock().ok()?;
+        state
+            .i2c
+            .get(&address)
+            .map(|peripheral| peripheral.registers.clone())
+    }
+
+    /// Current PWM output of `pin`, if it was ever set
+    pub fn pwm_output(&self, pin: u32) -> Option<PwmConfig> {
+        self.state.lock().ok()?.pwm.get(&pin).cloned()
+    }
+
+    /// Pins the waveform clock so ADC readings are reproducible
+    pub fn set_clock(&self, elapsed: Duration) {
+        if let Ok(mut clock) = self.clock.lock() {
+            *clock = Some(elapsed);
+        }
+    }
+
+    fn elapsed(&self) -> Duration {
+        self.clock
+            .lock()
+            .ok()
+            .and_then(|clock| *clock)
+            .unwrap_or_else(|| self.started.elapsed())
+    }
+
+    fn lock_state(&self) -> Result<MutexGuard<'_, State>> {
+        self.state
+            .lock()
+            .map_err(|_| FirmwareError::CommunicationError("virtual device poisoned".to_string()))
+    }
+
+    /// The board state, once connected
+    fn connected_state(&self) -> Result<MutexGuard<'_, State>> {
+        let state = self.lock_state()?;
+        if !state.connected {
+            return Err(FirmwareError::DeviceNotConnected(self.name.clone()));
+        }
+        Ok(state)
+    }
+
+    fn check_pin(&self, pin: u32) -> Result<()> {
+        if pin < self.spec.gpio_pins {
+            Ok(())
+        } else {
+            Err(FirmwareError::PinError(format!(
+                "pin {} does not exist on {}",
+                pin, self.name
+            )))
+        }
+    }
+}
+
+fn parse_register(key: &str) -> Result<usize> {
+    let parsed = match key.strip_prefix("0x").or_else(|| key.strip_prefix("0X")) {
+        Some(hex) => usize::from_str_radix(hex, 16),
+        None => key.parse::<usize>(),
+    };
+    parsed.map_err(|_| FirmwareError::ConfigError(format!("invalid register '{}'", key)))
+}
+
+fn build_i2c(spec: &VirtualDeviceSpec) -> Result<BTreeMap<u8, I2cPeripheral>> {
+    let mut peripherals = BTreeMap::new();
+    for peripheral in &spec.i2c {
+        if peripheral.size == 0 {
+            return Err(FirmwareError::ConfigError(format!(
+                "I2C peripheral 0x{:02x} has no registers",
+                peripheral.address
+            )));
+        }
+        let mut registers = vec![0u8; peripheral.size];
+        for (key, value) in &peripheral.registers {
+            let start = parse_register(key)?;
+            let values = match value {
+                RegisterValue::Byte(byte) => std::slice::from_ref(byte),
+                RegisterValue::Bytes(bytes) => bytes.as_slice(),
+            };
+            if start + values.len() > registers.len() {
+                return Err(FirmwareError::ConfigError(format!(
+                    "register {} of I2C peripheral 0x{:02x} is past its {} registers",
+                    key, peripheral.address, peripheral.size
+                )));
+            }
+            registers[start..start + values.len()].copy_from_slice(values);
+        }
+        let duplicate = peripherals
+            .insert(
+                peripheral.address,
+                I2cPeripheral {
+                    registers,
+                    pointer: 0,
+                },
+            )
+            .is_some();
+        if duplicate {
+            return Err(FirmwareError::ConfigError(format!(
+                "two I2C peripherals use address 0x{:02x}",
+                peripheral.address
+            )));
+        }
+    }
+    Ok(peripherals)
+}
+
+fn build_adc(spec: &VirtualDeviceSpec) -> Result<HashMap<u32, AdcChannelSpec>> {
+    let mut channels = HashMap::new();
+    for channel in &spec.adc {
+        if channel.resolution == 0 || channel.resolution > 32 {
+            return Err(FirmwareError::ConfigError(format!(
+                "ADC pin {} has an unsupported {} bit resolution",
+                channel.pin, channel.resolution
+            )));
+        }
+        channels.insert(channel.pin, channel.clone());
+    }
+    Ok(channels)
+}
+
+fn full_scale(resolution: u8) -> f32 {
+    ((1u64 << resolution) - 1) as f32
+}
+
+fn no_ack(address: u8) -> FirmwareError {
+    FirmwareError::CommunicationError(format!(
+        "no I2C device acknowledged address 0x{:02x}",
+        address
+    ))
+}
+
+fn idle_pwm(pin: u32) -> PwmConfig {
+    PwmConfig {
+        pin,
+        frequency: 0,
+        duty_cycle: 0.0,
+    }
+}
+
+fn check_duty(duty: f32) -> Result<()> {
+    if (0.0..=1.0).contains(&duty) {
+        Ok(())
+    } else {
+        Err(FirmwareError::PinError(format!(
+            "duty cycle {} is outside 0.0..=1.0",
+            duty
+        )))
+    }
+}
+
+#[async_trait]
+impl GpioController for VirtualDevice {
+    async fn configure_pin(&self, config: PinConfig) -> Result<()> {
+        self.check_pin(config.pin_number)?;
+        let mut state = self.connected_state()?;
+        let level = match (config.initial_state, config.mode) {
+            (Some(level), _) => level,
+            (None, PinMode::InputPullUp) => PinState::High,
+            (None, _) => state
+                .pins
+                .get(&config.pin_number)
+                .map_or(PinState::Low, |pin| pin.state),
+        };
+        state.pins.insert(
+            config.pin_number,
+            Pin {
+                mode: Some(config.mode),
+                state: level,
+            },
+        );
+        state.events.push(VirtualEvent::PinConfigured {
+            pin: config.pin_number,
+            mode: config.mode,
+        });
+        Ok(())
+    }
+
+    async fn write_pin(&self, pin: u32, level: PinState) -> Result<()> {
+        self.check_pin(pin)?;
+        if level == PinState::Unknown {
+            return Err(FirmwareError::PinError(
+                "cannot drive a pin to an unknown state".to_string(),
+            ));
+        }
+        let mut state = self.connected_state()?;
+        let slot = state.pins.entry(pin).or_insert(Pin {
+            mode: None,
+            state: PinState::Low,
+        });
+        if matches!(slot.mode, Some(mode) if mode != PinMode::Output) {
+            return Err(FirmwareError::PinError(format!(
+                "pin {} is not configured as an output",
+                pin
+            )));
+        }
+        slot.state = level;
+        state
+            .events
+            .push(VirtualEvent::PinWritten { pin, state: level });
+        Ok(())
+    }
+
+    async fn read_pin(&self, pin: u32) -> Result<PinState> {
+        self.check_pin(pin)?;
+        let mut state = self.connected_state()?;
+        let level = state.pins.get(&pin).map_or(PinState::Low, |pin| pin.state);
+        state
+            .events
+            .push(VirtualEvent::PinRead { pin, state: level });
+        Ok(level)
+    }
+
+    async fn toggle_pin(&self, pin: u32) -> Result<()> {
+        let level = match self.read_pin(pin).await? {
+            PinState::High => PinState::Low,
+            _ => PinState::High,
+        };
+        self.write_pin(pin, level).await
+    }
+}
+
+#[async_trait]
+impl I2cController for VirtualDevice {
+    async fn write(&self, message: I2cMessage) -> Result<()> {
+        let mut state = self.connected_state()?;
+        let peripheral = state
+            .i2c
+            .get_mut(&message.address)
+            .ok_or_else(|| no_ack(message.address))?;
+        peripheral.write(&message.data);
+        state.events.push(VirtualEvent::I2cWrite {
+            address: message.address,
+            data: message.data,
+        });
+        Ok(())
+    }
+
+    async fn read(&self, address: u8, length: usize) -> Result<Vec<u8>> {
+        let mut state = self.connected_state()?;
+        let data = state
+            .i2c
+            .get_mut(&address)
+            .ok_or_else(|| no_ack(address))?
+            .read(length);
+        state.events.push(VirtualEvent::I2cRead {
+            address,
+            data: data.clone(),
+        });
+        Ok(data)
+    }
+
+    /// The first byte of `write_data` is the device address, since the
+    /// trait does not carry one separately
+    async fn write_read(&self, write_data: Vec<u8>, read_len: usize) -> Result<Vec<u8>> {
+        let Some((&address, data)) = write_data.split_first() else {
+            return Err(FirmwareError::CommunicationError(
+                "write_read needs the device address as its first byte".to_string(),
+            ));
+        };
+        I2cController::write(
+            self,
+            I2cMessage {
+                address,
+                data: data.to_vec(),
+            },
+        )
+        .await?;
+        I2cController::read(self, address, read_len).await
+    }
+
+    async fn scan_devices(&self) -> Result<Vec<u8>> {
+        let mut state = self.connected_state()?;
+        let found: Vec<u8> = state.i2c.keys().copied().collect();
+        state.events.push(VirtualEvent::I2cScan {
+            found: found.clone(),
+        });
+        Ok(found)
+    }
+}
+
+/// MOSI is wired to MISO, so every byte sent comes straight back
+#[async_trait]
+impl SpiController for VirtualDevice {
+    async fn transfer(&self, data: &[u8]) -> Result<Vec<u8>> {
+        let mut state = self.connected_state()?;
+        state.events.push(VirtualEvent::SpiTransfer {
+            sent: data.to_vec(),
+            received: data.to_vec(),
+        });
+        Ok(data.to_vec())
+    }
+
+    async fn write(&self, data: &[u8]) -> Result<()> {
+        SpiController::transfer(self, data).await.map(|_| ())
+    }
+
+    async fn read(&self, length: usize) -> Result<Vec<u8>> {
+        SpiController::transfer(self, &vec![0; length]).await
+    }
+}
+
+/// TX is wired to RX, so everything written can be read back
+#[async_trait]
+impl UartController for VirtualDevice {
+    async fn write(&self, data: &[u8]) -> Result<usize> {
+        let mut state = self.connected_state()?;
+        state.uart.extend(data);
+        state.events.push(VirtualEvent::UartWrite {
+            data: data.to_vec(),
+        });
+        Ok(data.len())
+    }
+
+    async fn read(&self, length: usize) -> Result<Vec<u8>> {
+        let mut state = self.connected_state()?;
+        let count = length.min(state.uart.len());
+        let data: Vec<u8> = state.uart.drain(..count).collect();
+        state
+            .events
+            .push(VirtualEvent::UartRead { data: data.clone() });
+        Ok(data)
+    }
+
+    async fn write_string(&self, data: &str) -> Result<usize> {
+        UartController::write(self, data.as_bytes()).await
+    }
+
+    async fn flush(&self) -> Result<()> {
+        self.connected_state().map(|_| ())
+    }
+
+    async fn available(&self) -> Result<usize> {
+        Ok(self.connected_state()?.uart.len())
+    }
+}
+
+#[async_trait]
+impl PwmController for VirtualDevice {
+    async fn configure(&self, config: PwmConfig) -> Result<()> {
+        self.check_pin(config.pin)?;
+        check_duty(config.duty_cycle)?;
+        let mut state = self.connected_state()?;
+        state.events.push(VirtualEvent::PwmConfigured {
+            pin: config.pin,
+            frequency: config.frequency,
+            duty_cycle: config.duty_cycle,
+        });
+        state.pwm.insert(config.pin, config);
+        Ok(())
+    }
+
+    async fn set_duty_cycle(&self, pin: u32, duty: f32) -> Result<()> {
+        self.check_pin(pin)?;
+        check_duty(duty)?;
+        let mut state = self.connected_state()?;
+        state
+            .pwm
+            .entry(pin)
+            .or_insert_with(|| idle_pwm(pin))
+            .duty_cycle = duty;
+        state.events.push(VirtualEvent::PwmDutyCycle {
+            pin,
+            duty_cycle: duty,
+        });
+        Ok(())
+    }
+
+    async fn set_frequency(&self, pin: u32, frequency: u32) -> Result<()> {
+        self.check_pin(pin)?;
+        let mut state = self.connected_state()?;
+        state
+            .pwm
+            .entry(pin)
+            .or_insert_with(|| idle_pwm(pin))
+            .frequency = frequency;
+        state
+            .events
+            .push(VirtualEvent::PwmFrequency { pin, frequency });
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl AdcController for VirtualDevice {
+    async fn read_voltage(&self, pin: u32) -> Result<f32> {
+        let raw = self.read_raw(pin).await?;
+        let state = self.lock_state()?;
+        let channel = state
+            .adc
+            .get(&pin)
+            .ok_or_else(|| FirmwareError::PinError(format!("pin {} has no ADC channel", pin)))?;
+        Ok(raw as f32 / full_scale(channel.resolution) * channel.vref)
+    }
+
+    async fn read_raw(&self, pin: u32) -> Result<u32> {
+        let elapsed = self.elapsed();
+        let mut state = self.connected_state()?;
+        let channel = state
+            .adc
+            .get(&pin)
+            .ok_or_else(|| FirmwareError::PinError(format!("pin {} has no ADC channel", pin)))?;
+        let period = channel.period_ms.max(1) as f32 / 1000.0;
+        let phase = (elapsed.as_secs_f32() / period).fract();
+        let volts = channel.offset + channel.amplitude * channel.waveform.sample(phase);
+        let scale = full_scale(channel.resolution);
+        let raw = (volts.clamp(0.0, channel.vref) / channel.vref * scale).round() as u32;
+        state.events.push(VirtualEvent::AdcRead { pin, raw });
+        Ok(raw)
+    }
+
+    async fn configure(&self, config: AdcConfig) -> Result<()> {
+        if config.resolution == 0 || config.resolution > 32 {
+            return Err(FirmwareError::PinError(format!(
+                "ADC resolution of {} bits is not supported",
+                config.resolution
+            )));
+        }
+        let mut state = self.connected_state()?;
+        let channel = state.adc.entry(config.pin).or_insert(AdcChannelSpec {
+            pin: config.pin,
+            waveform: Waveform::Constant,
+            offset: 0.0,
+            amplitude: 0.0,
+            period_ms: default_period_ms(),
+            resolution: config.resolution,
+            vref: config.vref,
+        });
+        channel.resolution = config.resolution;
+        channel.vref = config.vref;
+        state.events.push(VirtualEvent::AdcConfigured {
+            pin: config.pin,
+            resolution: config.resolution,
+            vref: config.vref,
+        });
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl DelayController for VirtualDevice {
+    async fn delay_ms(&self, ms: u32) {
+        self.delay_us(ms.saturating_mul(1000)).await;
+    }
+
+    async fn delay_us(&self, us: u32) {
+        if let Ok(mut state) = self.state.lock() {
+            state.events.push(VirtualEvent::Delay {
+                micros: u64::from(us),
+            });
+        }
+        tokio::time::sleep(Duration::from_micros(u64::from(us))).await;
+    }
+}
+
+#[async_trait]
+impl FirmwareDevice for VirtualDevice {
+    fn device_info(&self) -> DeviceInfo {
+        DeviceInfo {
+            name: self.name.clone(),
+            platform: Platform::Virtual,
+            firmware_version: Some(env!("CARGO_PKG_VERSION").to_string()),
+            capabilities: vec![
+                "gpio".to_string(),
+                "i2c".to_string(),
+                "spi".to_string(),
+                "uart".to_string(),
+                "pwm".to_string(),
+                "adc".to_string(),
+            ],
+        }
+    }
+
+    fn gpio(&self) -> Option<&dyn GpioController> {
+        Some(self)
+    }
+
+    fn i2c(&self) -> Option<&dyn I2cController> {
+        Some(self)
+    }
+
+    fn spi(&self) -> Option<&dyn SpiController> {
+        Some(self)
+    }
+
+    fn uart(&self) -> Option<&dyn UartController> {
+        Some(self)
+    }
+
+    fn pwm(&self) -> Option<&dyn PwmController> {
+        Some(self)
+    }
+
+    fn adc(&self) -> Option<&dyn AdcController> {
+        Some(self)
+    }
+
+    fn delay(&self) -> Option<&dyn DelayController> {
+        Some(self)
+    }
+
+    async fn connect(&self) -> Result<()> {
+        log::info!("Connecting to virtual device {}", self.name);
+        let mut state = self.lock_state()?;
+        state.connected = true;
+        state.events.push(VirtualEvent::Connected);
+        Ok(())
+    }
+
+    async fn disconnect(&self) -> Result<()> {
+        log::info!("Disconnecting from virtual device {}", self.name);
+        let mut state = self.lock_state()?;
+        state.connected = false;
+        state.events.push(VirtualEvent::Disconnected);
+        Ok(())
+    }
+
+    async fn is_connected(&self) -> Result<bool> {
+        Ok(self.lock_state()?.connected)
+    }
+
+    /// Restores the pins, buffers and registers described by the spec
+    async fn reset(&self) -> Result<()> {
+        let i2c = build_i2c(&self.spec)?;
+        let adc = build_adc(&self.spec)?;
+        let mut state = self.connected_state()?;
+        state.pins.clear();
+        state.i2c = i2c;
+        state.uart.clear();
+        state.pwm.clear();
+        state.adc = adc;
+        state.events.push(VirtualEvent::Reset);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BENCH: &str = r#"
+gpio_pins = 8
+
+[[i2c]]
+name = "tmp102"
+address = 0x48
+size = 4
+registers = { "0x00" = [0x19, 0x60], "2" = 0x4b }
+
+[[adc]]
+pin = 3
+waveform = "sine"
+offset = 1.65
+amplitude = 1.0
+period_ms = 1000
+"#;
+
+    async fn bench() -> VirtualDevice {
+        let device = VirtualDevice::from_spec(
+            "bench",
+            VirtualDeviceSpec::from_toml(BENCH).expect("bench spec"),
+        )
+        .expect("bench device");
+        device.connect().await.unwrap();
+        device
+    }
+
+    #[tokio::test]
+    async fn gpio_pins_track_state_and_refuse_bad_writes() {
+        let device = bench().await;
+        let gpio = device.gpio().unwrap();
+
+        gpio.write_pin(2, PinState::High).await.unwrap();
+        gpio.toggle_pin(2).await.unwrap();
+        assert_eq!(gpio.read_pin(2).await.unwrap(), PinState::Low);
+        assert!(matches!(
+            gpio.write_pin(8, PinState::High).await,
+            Err(FirmwareError::PinError(_))
+        ));
+
+        gpio.configure_pin(PinConfig {
+            pin_number: 4,
+            mode: PinMode::InputPullUp,
+            initial_state: None,
+        })
+        .await
+        .unwrap();
+        assert_eq!(gpio.read_pin(4).await.unwrap(), PinState::High);
+        device.set_input(4, PinState::Low).unwrap();
+        assert_eq!(gpio.read_pin(4).await.unwrap(), PinState::Low);
+        assert!(gpio.write_pin(4, PinState::High).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn i2c_peripherals_serve_their_register_maps() {
+        let device = bench().await;
+        let i2c = device.i2c().unwrap();
+
+        assert_eq!(i2c.scan_devices().await.unwrap(), vec![0x48]);
+        assert_eq!(
+            i2c.write_read(vec![0x48, 0x00], 3).await.unwrap(),
+            vec![0x19, 0x60, 0x4b]
+        );
+        i2c.write(I2cMessage {
+            address: 0x48,
+            data: vec![0x03, 0xaa, 0xbb],
+        })
+        .await
+        .unwrap();
+        assert_eq!(
+            device.i2c_registers(0x48).unwrap(),
+            vec![0xbb, 0x60, 0x4b, 0xaa]
+        );
+        assert!(matches!(
+            i2c.read(0x50, 1).await,
+            Err(FirmwareError::CommunicationError(_))
+        ));
+
+        device.reset().await.unwrap();
+        assert_eq!(
+            device.i2c_registers(0x48).unwrap(),
+            vec![0x19, 0x60, 0x4b, 0x00]
+        );
+    }
+
+    #[tokio::test]
+    async fn spi_loops_back_and_uart_echoes() {
+        let device = bench().await;
+
+        let spi = device.spi().unwrap();
+        assert_eq!(spi.transfer(&[1, 2, 3]).await.unwrap(), vec![1, 2, 3]);
+
+        let uart = device.uart().unwrap();
+        uart.write_string("hello").await.unwrap();
+        assert_eq!(uart.available().await.unwrap(), 5);
+        assert_eq!(uart.read(3).await.unwrap(), b"hel".to_vec());
+        assert_eq!(uart.read(10).await.unwrap(), b"lo".to_vec());
+    }
+
+    #[tokio::test]
+    async fn adc_follows_the_waveform_clock() {
+        let device = bench().await;
+        let adc = device.adc().unwrap();
+
+        device.set_clock(Duration::from_millis(0));
+        let middle = adc.read_voltage(3).await.unwrap();
+        device.set_clock(Duration::from_millis(250));
+        let peak = adc.read_voltage(3).await.unwrap();
+        device.set_clock(Duration::from_millis(750));
+        let trough = adc.read_voltage(3).await.unwrap();
+
+        assert!((middle - 1.65).abs() < 0.01, "middle {}", middle);
+        assert!((peak - 2.65).abs() < 0.01, "peak {}", peak);
+        assert!((trough - 0.65).abs() < 0.01, "trough {}", trough);
+        assert!(matches!(
+            adc.read_raw(5).await,
+            Err(FirmwareError::PinError(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn events_record_operations_in_order() {
+        let device = bench().await;
+        device.clear_events();
+
+        device
+            .gpio()
+            .unwrap()
+            .write_pin(1, PinState::High)
+            .await
+            .unwrap();
+        device.pwm().unwrap().set_duty_cycle(1, 0.5).await.unwrap();
+        device.disconnect().await.unwrap();
+
+        assert_eq!(device.pwm_output(1).map(|pwm| pwm.duty_cycle), Some(0.5));
+
+        assert_eq!(
+            device.events(),
+            vec![
+                VirtualEvent::PinWritten {
+                    pin: 1,
+                    state: PinState::High
+                },
+                VirtualEvent::PwmDutyCycle {
+                    pin: 1,
+                    duty_cycle: 0.5
+                },
+                VirtualEvent::Disconnected,
+            ]
+        );
+        assert!(matches!(
+            device.gpio().unwrap().read_pin(1).await,
+            Err(FirmwareError::DeviceNotConnected(_))
+        ));
+    }
+
+    #[test]
+    fn example_bench_spec_loads() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/examples/virtual-bench.toml");
+        let device = VirtualDevice::from_spec_file("bench", path).unwrap();
+
+        assert_eq!(device.i2c_registers(0x48).unwrap()[..2], [0x19, 0x60]);
+        assert_eq!(device.i2c_registers(0x50).unwrap().len(), 256);
+    }
+
+    #[test]
+    fn json_specs_load_and_bad_registers_are_rejected() {
+        let spec = VirtualDeviceSpec::from_json(
+            r#"{"i2c": [{"address": 80, "size": 2, "registers": {"1": [7]}}]}"#,
+        )
+        .unwrap();
+        assert_eq!(spec.gpio_pins, DEFAULT_GPIO_PINS);
+        let device = VirtualDevice::from_spec("eeprom", spec).unwrap();
+        assert_eq!(device.i2c_registers(80).unwrap(), vec![0, 7]);
+
+        let spec = VirtualDeviceSpec::from_toml(
+            "[[i2c]]\naddress = 0x50\nsize = 2\nregisters = { \"0x02\" = 1 }\n",
+        )
+        .unwrap();
+        assert!(matches!(
+            VirtualDevice::from_spec("eeprom", spec),
+            Err(FirmwareError::ConfigError(_))
+        ));
+    }
+}
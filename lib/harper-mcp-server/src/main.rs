@@ -12,17 +12,39 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+mod policy;
+mod resources;
+mod stdio;
+mod tools;
+
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
 use axum::{routing::post, Router};
+use dashmap::DashMap;
+use harper_core::runtime::config::HarperConfig;
+use harper_core::{create_connection, init_db, ExecPolicyConfig, HarperError, HarperResult};
+use rusqlite::Connection;
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 use std::collections::HashMap;
-use std::sync::{LazyLock, Mutex};
+use std::future::Future;
+use std::path::PathBuf;
+use std::sync::{Arc, LazyLock, Mutex, MutexGuard, RwLock};
 use std::time::{Duration, Instant};
+use tokio::sync::{broadcast, mpsc};
+use tokio::task::AbortHandle;
 use tracing::info;
 
-const PROTOCOL_VERSION: &str = "2024-11-05";
+const PROTOCOL_VERSION: &str = "2025-11-25";
+/// Versions a client may ask for; the server answers with the one requested
+const SUPPORTED_PROTOCOL_VERSIONS: [&str; 4] =
+    [PROTOCOL_VERSION, "2025-06-18", "2025-03-26", "2024-11-05"];
+const DEFAULT_PORT: u16 = 5001;
 const RATE_LIMIT_REQUESTS: u64 = 100;
 const RATE_LIMIT_WINDOW_SECS: u64 = 60;
+/// How often a long tool call reports progress
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(1);
 
 struct RateLimiter {
     requests: Mutex<HashMap<String, Vec<Instant>>>,
@@ -61,6 +83,134 @@ impl RateLimiter {
 // Global rate limiter instance
 static RATE_LIMITER: LazyLock<RateLimiter> = LazyLock::new(RateLimiter::new);
 
+/// Harper state the MCP handlers work against
+pub struct ServerState {
+    conn: Mutex<Connection>,
+    exec_policy: RwLock<ExecPolicyConfig>,
+    /// Directory of custom prompts served through `prompts/list`
+    pub prompts_dir: Option<PathBuf>,
+    /// Running `tools/call` requests by JSON-RPC id, for `notifications/cancelled`
+    in_flight: DashMap<String, AbortHandle>,
+    tools_changed: broadcast::Sender<()>,
+}
+
+impl ServerState {
+    pub fn new(
+        conn: Connection,
+        exec_policy: ExecPolicyConfig,
+        prompts_dir: Option<PathBuf>,
+    ) -> Self {
+        Self {
+            conn: Mutex::new(conn),
+            exec_policy: RwLock::new(exec_policy),
+            prompts_dir,
+            in_flight: DashMap::new(),
+            tools_changed: broadcast::channel(8).0,
+        }
+    }
+
+    /// Session database and exec policy from Harper's configuration
+    ///
+    /// Without a usable configuration the server still starts, with an empty
+    /// in-memory session store and the default (prompting, hence refusing)
+    /// exec policy.
+    fn from_config() -> HarperResult<Self> {
+        let prompts_dir = dirs::home_dir().map(|home| home.join(".harper").join("prompts"));
+        let (conn, exec_policy) = match HarperConfig::new() {
+            Ok(config) => (
+                create_connection(&config.database.path)?,
+                config.exec_policy,
+            ),
+            Err(e) => {
+                eprintln!("Warning: Failed to load Harper config, serving without sessions: {e}");
+                (create_connection(":memory:")?, ExecPolicyConfig::default())
+            }
+        };
+        init_db(&conn)?;
+        Ok(Self::new(conn, exec_policy, prompts_dir))
+    }
+
+    pub fn connection(&self) -> HarperResult<MutexGuard<'_, Connection>> {
+        self.conn
+            .lock()
+            .map_err(|e| HarperError::Database(format!("Failed to lock database: {}", e)))
+    }
+
+    pub fn exec_policy(&self) -> ExecPolicyConfig {
+        match self.exec_policy.read() {
+            Ok(policy) => policy.clone(),
+            Err(poisoned) => poisoned.into_inner().clone(),
+        }
+    }
+
+    /// Swap in a new exec policy, announcing the change if it alters the tool list
+    pub fn set_exec_policy(&self, exec_policy: ExecPolicyConfig) {
+        let changed = tools::definitions(&self.exec_policy()) != tools::definitions(&exec_policy);
+        match self.exec_policy.write() {
+            Ok(mut policy) => *policy = exec_policy,
+            Err(poisoned) => *poisoned.into_inner() = exec_policy,
+        }
+        if changed {
+            let _ = self.tools_changed.send(());
+        }
+    }
+
+    /// Re-read `[exec_policy]`, e.g. after `SIGHUP`
+    fn reload(&self) {
+        match HarperConfig::new() {
+            Ok(config) => self.set_exec_policy(config.exec_policy),
+            Err(e) => eprintln!("Warning: Failed to reload Harper config: {e}"),
+        }
+    }
+
+    /// Fires whenever the `tools/list` result changes
+    pub fn subscribe_tools_changed(&self) -> broadcast::Receiver<()> {
+        self.tools_changed.subscribe()
+    }
+
+    /// Abort the running `tools/call` with `request_id`
+    fn cancel(&self, request_id: &Value) -> bool {
+        match self.in_flight.remove(&request_id.to_string()) {
+            Some((_, task)) => {
+                task.abort();
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// Messages the server sends on its own: responses, progress and list changes
+///
+/// Only the stdio transport has somewhere to send them. A plain HTTP POST gets
+/// its response as the reply body, and progress is dropped.
+#[derive(Clone, Default)]
+pub struct Outbox {
+    sender: Option<mpsc::UnboundedSender<Value>>,
+}
+
+impl Outbox {
+    pub fn new(sender: mpsc::UnboundedSender<Value>) -> Self {
+        Self {
+            sender: Some(sender),
+        }
+    }
+
+    fn send(&self, message: Value) {
+        if let Some(sender) = &self.sender {
+            let _ = sender.send(message);
+        }
+    }
+
+    fn notify(&self, method: &str, params: Value) {
+        self.send(json!({
+            "jsonrpc": "2.0",
+            "method": method,
+            "params": params
+        }));
+    }
+}
+
 fn error_response(
     id: Option<Value>,
     code: i32,
@@ -80,55 +230,68 @@ fn error_response(
 }
 
 #[derive(Serialize, Deserialize)]
-struct JsonRpcRequest {
+pub struct JsonRpcRequest {
     jsonrpc: String,
-    id: Value,
+    /// Absent for notifications
+    #[serde(default)]
+    id: Option<Value>,
     method: String,
     params: Option<Value>,
 }
 
 #[derive(Serialize, Deserialize)]
-struct JsonRpcResponse {
+pub struct JsonRpcResponse {
     jsonrpc: String,
     id: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     result: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     error: Option<JsonRpcError>,
 }
 
 #[derive(Serialize, Deserialize)]
-struct JsonRpcError {
+pub struct JsonRpcError {
     code: i32,
     message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
     data: Option<Value>,
 }
 
 fn handle_initialize(request_id: Value, params: Option<&Value>) -> JsonRpcResponse {
     // Check protocol version
-    if let Some(params) = params {
+    let version = if let Some(params) = params {
         if let Some(version) = params.get("protocolVersion") {
-            if version != PROTOCOL_VERSION {
-                return error_response(
-                    Some(request_id),
-                    -32602,
-                    "Unsupported protocol version",
-                    None,
-                );
+            match SUPPORTED_PROTOCOL_VERSIONS
+                .iter()
+                .find(|supported| version == **supported)
+            {
+                Some(supported) => *supported,
+                None => {
+                    return error_response(
+                        Some(request_id),
+                        -32602,
+                        "Unsupported protocol version",
+                        Some(json!({ "supported": SUPPORTED_PROTOCOL_VERSIONS })),
+                    );
+                }
             }
         } else {
             return error_response(Some(request_id), -32600, "Invalid Request", None);
         }
     } else {
         return error_response(Some(request_id), -32601, "Method not found", None);
-    }
+    };
     JsonRpcResponse {
         jsonrpc: "2.0".to_string(),
         id: Some(request_id),
         result: Some(json!({
-            "protocolVersion": PROTOCOL_VERSION,
+            "protocolVersion": version,
             "capabilities": {
                 "tools": {
                     "listChanged": true
-                }
+                },
+                "resources": {},
+                "prompts": {}
             },
             "serverInfo": {
                 "name": "harper-mcp-server",
@@ -139,8 +302,8 @@ fn handle_initialize(request_id: Value, params: Option<&Value>) -> JsonRpcRespon
     }
 }
 
-fn handle_tools_list(request_id: Value) -> JsonRpcResponse {
-    let tools = json!({
+fn handle_tools_list(state: &ServerState, request_id: Value) -> JsonRpcResponse {
+    let mut tools = json!({
         "tools": [
             {
                 "name": "echo",
@@ -167,6 +330,9 @@ fn handle_tools_list(request_id: Value) -> JsonRpcResponse {
             }
         ]
     });
+    if let Some(list) = tools["tools"].as_array_mut() {
+        list.extend(tools::definitions(&state.exec_policy()));
+    }
     JsonRpcResponse {
         jsonrpc: "2.0".to_string(),
         id: Some(request_id),
@@ -175,7 +341,66 @@ fn handle_tools_list(request_id: Value) -> JsonRpcResponse {
     }
 }
 
-fn handle_tools_call(request_id: Value, params: Option<&Value>) -> JsonRpcResponse {
+fn tool_result(request_id: Value, result: HarperResult<String>) -> JsonRpcResponse {
+    let (text, is_error) = match result {
+        Ok(text) => (text, false),
+        Err(e) => (e.to_string(), true),
+    };
+    JsonRpcResponse {
+        jsonrpc: "2.0".to_string(),
+        id: Some(request_id),
+        result: Some(json!({
+            "content": [
+                {
+                    "type": "text",
+                    "text": text
+                }
+            ],
+            "isError": is_error
+        })),
+        error: None,
+    }
+}
+
+/// Run `call`, reporting progress every [`PROGRESS_INTERVAL`] until it finishes
+async fn with_progress<F: Future>(outbox: &Outbox, token: Value, name: &str, call: F) -> F::Output {
+    tokio::pin!(call);
+    let mut ticker = tokio::time::interval(PROGRESS_INTERVAL);
+    let mut progress = 0u64;
+    let output = loop {
+        tokio::select! {
+            output = &mut call => break output,
+            _ = ticker.tick() => {
+                outbox.notify(
+                    "notifications/progress",
+                    json!({
+                        "progressToken": token,
+                        "progress": progress,
+                        "message": format!("Running {name}")
+                    }),
+                );
+                progress += 1;
+            }
+        }
+    };
+    outbox.notify(
+        "notifications/progress",
+        json!({
+            "progressToken": token,
+            "progress": progress,
+            "total": progress,
+            "message": format!("Finished {name}")
+        }),
+    );
+    output
+}
+
+async fn handle_tools_call(
+    state: &ServerState,
+    outbox: &Outbox,
+    request_id: Value,
+    params: Option<&Value>,
+) -> JsonRpcResponse {
     if let Some(params) = params {
         if let Some(name) = params.get("name") {
             if name == "echo" {
@@ -218,7 +443,24 @@ fn handle_tools_call(request_id: Value, params: Option<&Value>) -> JsonRpcRespon
                     error: None,
                 }
             } else {
-                error_response(Some(request_id), -32601, "Method not found", None)
+                let name = name.as_str().unwrap_or_default();
+                let args = params
+                    .get("arguments")
+                    .cloned()
+                    .unwrap_or_else(|| json!({}));
+                let call = tools::call(state, name, &args);
+                let token = params
+                    .get("_meta")
+                    .and_then(|meta| meta.get("progressToken"))
+                    .cloned();
+                let result = match token {
+                    Some(token) => with_progress(outbox, token, name, call).await,
+                    None => call.await,
+                };
+                match result {
+                    Some(result) => tool_result(request_id, result),
+                    None => error_response(Some(request_id), -32601, "Method not found", None),
+                }
             }
         } else {
             error_response(Some(request_id), -32602, "Invalid params", None)
@@ -228,46 +470,232 @@ fn handle_tools_call(request_id: Value, params: Option<&Value>) -> JsonRpcRespon
     }
 }
 
+fn handle_resources_list(state: &ServerState, request_id: Value) -> JsonRpcResponse {
+    match resources::list(state) {
+        Ok(resources) => JsonRpcResponse {
+            jsonrpc: "2.0".to_string(),
+            id: Some(request_id),
+            result: Some(json!({ "resources": resources })),
+            error: None,
+        },
+        Err(e) => error_response(Some(request_id), -32603, &e.to_string(), None),
+    }
+}
+
+fn handle_resources_read(
+    state: &ServerState,
+    request_id: Value,
+    params: Option<&Value>,
+) -> JsonRpcResponse {
+    let Some(uri) = params.and_then(|p| p.get("uri")).and_then(Value::as_str) else {
+        return error_response(Some(request_id), -32602, "Invalid params", None);
+    };
+    match resources::read(state, uri) {
+        Ok(Some(contents)) => JsonRpcResponse {
+            jsonrpc: "2.0".to_string(),
+            id: Some(request_id),
+            result: Some(json!({ "contents": [contents] })),
+            error: None,
+        },
+        Ok(None) => error_response(
+            Some(request_id),
+            -32002,
+            "Resource not found",
+            Some(json!({ "uri": uri })),
+        ),
+        Err(e) => error_response(Some(request_id), -32603, &e.to_string(), None),
+    }
+}
+
+fn handle_prompts_list(state: &ServerState, request_id: Value) -> JsonRpcResponse {
+    match resources::list_prompts(state) {
+        Ok(prompts) => JsonRpcResponse {
+            jsonrpc: "2.0".to_string(),
+            id: Some(request_id),
+            result: Some(json!({ "prompts": prompts })),
+            error: None,
+        },
+        Err(e) => error_response(Some(request_id), -32603, &e.to_string(), None),
+    }
+}
+
+fn handle_prompts_get(
+    state: &ServerState,
+    request_id: Value,
+    params: Option<&Value>,
+) -> JsonRpcResponse {
+    let Some(name) = params.and_then(|p| p.get("name")).and_then(Value::as_str) else {
+        return error_response(Some(request_id), -32602, "Invalid params", None);
+    };
+    match resources::get_prompt(state, name) {
+        Ok(Some(prompt)) => JsonRpcResponse {
+            jsonrpc: "2.0".to_string(),
+            id: Some(request_id),
+            result: Some(prompt),
+            error: None,
+        },
+        Ok(None) => error_response(Some(request_id), -32602, "Unknown prompt", None),
+        Err(e) => error_response(Some(request_id), -32603, &e.to_string(), None),
+    }
+}
+
+/// Handle one JSON-RPC message; `None` when nothing should be sent back
+///
+/// This is the core both transports share. Notifications never get a reply,
+/// and neither does a `tools/call` cancelled by `notifications/cancelled`.
+pub async fn dispatch(
+    state: &Arc<ServerState>,
+    outbox: &Outbox,
+    request: JsonRpcRequest,
+) -> Option<JsonRpcResponse> {
+    let Some(id) = request.id else {
+        info!("MCP notification: method={}", request.method);
+        if request.method == "notifications/cancelled" {
+            if let Some(request_id) = request.params.as_ref().and_then(|p| p.get("requestId")) {
+                state.cancel(request_id);
+            }
+        }
+        return None;
+    };
+
+    info!("MCP request: method={}, id={:?}", request.method, id);
+    let params = request.params.as_ref();
+    let response = match request.method.as_str() {
+        "initialize" => handle_initialize(id, params),
+        "ping" => JsonRpcResponse {
+            jsonrpc: "2.0".to_string(),
+            id: Some(id),
+            result: Some(json!({})),
+            error: None,
+        },
+        "tools/list" => handle_tools_list(state, id),
+        "tools/call" => {
+            let key = id.to_string();
+            let task = {
+                let state = Arc::clone(state);
+                let outbox = outbox.clone();
+                let id = id.clone();
+                let params = request.params.clone();
+                tokio::spawn(async move {
+                    handle_tools_call(&state, &outbox, id, params.as_ref()).await
+                })
+            };
+            state.in_flight.insert(key.clone(), task.abort_handle());
+            let outcome = task.await;
+            state.in_flight.remove(&key);
+            match outcome {
+                Ok(response) => response,
+                Err(e) if e.is_cancelled() => {
+                    info!("MCP request cancelled: id={}", key);
+                    return None;
+                }
+                Err(e) => error_response(Some(id), -32603, &e.to_string(), None),
+            }
+        }
+        "resources/list" => handle_resources_list(state, id),
+        "resources/read" => handle_resources_read(state, id, params),
+        "prompts/list" => handle_prompts_list(state, id),
+        "prompts/get" => handle_prompts_get(state, id, params),
+        _ => error_response(Some(id), -32601, "Method not found", None),
+    };
+    Some(response)
+}
+
 async fn handle_request(
+    State(state): State<Arc<ServerState>>,
     axum::Json(rpc_req): axum::Json<JsonRpcRequest>,
-) -> axum::Json<JsonRpcResponse> {
-    let client_ip = "default";
-    if !RATE_LIMITER.check(client_ip) {
-        return axum::Json(error_response(
-            Some(rpc_req.id.clone()),
-            -32029,
-            "Rate limit exceeded. Try again later.",
-            None,
-        ));
-    }
-
-    info!(
-        "MCP request: method={}, id={:?}",
-        rpc_req.method, rpc_req.id
-    );
-    let response = match rpc_req.method.as_str() {
-        "initialize" => handle_initialize(rpc_req.id.clone(), rpc_req.params.as_ref()),
-        "tools/list" => handle_tools_list(rpc_req.id.clone()),
-        "tools/call" => handle_tools_call(rpc_req.id.clone(), rpc_req.params.as_ref()),
-        _ => error_response(Some(rpc_req.id), -32601, "Method not found", None),
-    };
+) -> Response {
+    if let Some(id) = &rpc_req.id {
+        let client_ip = "default";
+        if !RATE_LIMITER.check(client_ip) {
+            return axum::Json(error_response(
+                Some(id.clone()),
+                -32029,
+                "Rate limit exceeded. Try again later.",
+                None,
+            ))
+            .into_response();
+        }
+    }
 
-    axum::Json(response)
+    match dispatch(&state, &Outbox::default(), rpc_req).await {
+        Some(response) => axum::Json(response).into_response(),
+        // Notifications such as notifications/initialized expect no reply
+        None => StatusCode::ACCEPTED.into_response(),
+    }
 }
 
 async fn health() -> &'static str {
     "OK"
 }
 
+#[derive(Debug, PartialEq, Eq)]
+enum Transport {
+    Http { port: u16 },
+    Stdio,
+}
+
+/// `--port <port>` (`0` picks a free one) or `--stdio`
+fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Transport, String> {
+    let mut args = args.into_iter();
+    let mut port = DEFAULT_PORT;
+    let mut stdio = false;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--port" => {
+                let value = args.next().ok_or("--port needs a value")?;
+                port = value
+                    .parse()
+                    .map_err(|_| format!("invalid port: {value}"))?;
+            }
+            "--stdio" => stdio = true,
+            other => return Err(format!("unknown argument: {other}")),
+        }
+    }
+    Ok(if stdio {
+        Transport::Stdio
+    } else {
+        Transport::Http { port }
+    })
+}
+
+/// Reload the exec policy on `SIGHUP`
+#[cfg(unix)]
+fn reload_on_hangup(state: Arc<ServerState>) {
+    use tokio::signal::unix::{signal, SignalKind};
+    tokio::spawn(async move {
+        let Ok(mut hangup) = signal(SignalKind::hangup()) else {
+            return;
+        };
+        while hangup.recv().await.is_some() {
+            state.reload();
+        }
+    });
+}
+
+#[cfg(not(unix))]
+fn reload_on_hangup(_state: Arc<ServerState>) {}
+
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let transport = parse_args(std::env::args().skip(1))?;
+    let state = Arc::new(ServerState::from_config()?);
+    reload_on_hangup(Arc::clone(&state));
+    let port = match transport {
+        Transport::Stdio => {
+            stdio::serve(state, tokio::io::stdin(), tokio::io::stdout()).await?;
+            return Ok(());
+        }
+        Transport::Http { port } => port,
+    };
     let app = Router::new()
         .route("/", post(handle_request))
-        .route("/health", axum::routing::get(health));
-    let addr = std::net::SocketAddr::from(([127, 0, 0, 1], 5001));
-    println!("MCP server listening on http://127.0.0.1:5001");
-    println!("Rate limit: {RATE_LIMIT_REQUESTS} requests per {RATE_LIMIT_WINDOW_SECS} seconds");
+        .route("/health", axum::routing::get(health))
+        .with_state(state);
+    let addr = std::net::SocketAddr::from(([127, 0, 0, 1], port));
     let listener = tokio::net::TcpListener::bind(addr).await?;
+    println!("MCP server listening on http://{}", listener.local_addr()?);
+    println!("Rate limit: {RATE_LIMIT_REQUESTS} requests per {RATE_LIMIT_WINDOW_SECS} seconds");
     axum::serve(listener, app).await?;
     Ok(())
 }
@@ -279,6 +707,12 @@ mod tests {
     use std::thread;
     use std::time::Duration;
 
+    fn test_state() -> ServerState {
+        let conn = create_connection(":memory:").unwrap();
+        init_db(&conn).unwrap();
+        ServerState::new(conn, ExecPolicyConfig::default(), None)
+    }
+
     #[test]
     fn test_rate_limiter_new() {
         let limiter = RateLimiter::new();
@@ -375,6 +809,45 @@ mod tests {
         assert!(result.get("serverInfo").is_some());
     }
 
+    #[test]
+    fn test_handle_initialize_negotiates_older_version() {
+        let params = json!({
+            "protocolVersion": "2024-11-05"
+        });
+        let response = handle_initialize(json!(1), Some(&params));
+
+        assert!(response.error.is_none());
+        assert_eq!(response.result.unwrap()["protocolVersion"], "2024-11-05");
+    }
+
+    #[test]
+    fn test_response_omits_absent_result_and_error() {
+        let response = handle_tools_list(&test_state(), json!(1));
+        let encoded = serde_json::to_value(&response).unwrap();
+        assert!(encoded.get("error").is_none());
+
+        let response = error_response(Some(json!(2)), -32601, "Method not found", None);
+        let encoded = serde_json::to_value(&response).unwrap();
+        assert!(encoded.get("result").is_none());
+        assert!(encoded["error"].get("data").is_none());
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let args = |list: &[&str]| list.iter().map(ToString::to_string).collect::<Vec<_>>();
+        assert_eq!(
+            parse_args(args(&[])),
+            Ok(Transport::Http { port: DEFAULT_PORT })
+        );
+        assert_eq!(
+            parse_args(args(&["--port", "0"])),
+            Ok(Transport::Http { port: 0 })
+        );
+        assert_eq!(parse_args(args(&["--stdio"])), Ok(Transport::Stdio));
+        assert!(parse_args(args(&["--port"])).is_err());
+        assert!(parse_args(args(&["--verbose"])).is_err());
+    }
+
     #[test]
     fn test_handle_initialize_invalid_protocol() {
         let params = json!({
@@ -396,7 +869,7 @@ mod tests {
 
     #[test]
     fn test_handle_tools_list() {
-        let response = handle_tools_list(json!(1));
+        let response = handle_tools_list(&test_state(), json!(1));
 
         assert_eq!(response.jsonrpc, "2.0");
         assert_eq!(response.id, Some(json!(1)));
@@ -405,20 +878,25 @@ mod tests {
 
         let result = response.result.unwrap();
         let tools = result["tools"].as_array().unwrap();
-        assert_eq!(tools.len(), 2);
+        let harper_tools = tools::definitions(&ExecPolicyConfig::default());
+        assert_eq!(tools.len(), 2 + harper_tools.len());
         assert_eq!(tools[0]["name"], "echo");
         assert_eq!(tools[1]["name"], "get_time");
+        assert!(tools.iter().any(|tool| tool["name"] == "read_file"));
+        // The default policy asks before writing, which the server cannot do
+        assert!(!tools.iter().any(|tool| tool["name"] == "search_replace"));
     }
 
-    #[test]
-    fn test_handle_tools_call_echo() {
+    #[tokio::test]
+    async fn test_handle_tools_call_echo() {
         let params = json!({
             "name": "echo",
             "arguments": {
                 "message": "Hello, World!"
             }
         });
-        let response = handle_tools_call(json!(1), Some(&params));
+        let response =
+            handle_tools_call(&test_state(), &Outbox::default(), json!(1), Some(&params)).await;
 
         assert!(response.error.is_none());
         assert!(response.result.is_some());
@@ -429,13 +907,14 @@ mod tests {
         assert_eq!(content["text"], "Hello, World!");
     }
 
-    #[test]
-    fn test_handle_tools_call_get_time() {
+    #[tokio::test]
+    async fn test_handle_tools_call_get_time() {
         let params = json!({
             "name": "get_time",
             "arguments": {}
         });
-        let response = handle_tools_call(json!(1), Some(&params));
+        let response =
+            handle_tools_call(&test_state(), &Outbox::default(), json!(1), Some(&params)).await;
 
         assert!(response.error.is_none());
         assert!(response.result.is_some());
@@ -447,26 +926,152 @@ mod tests {
         assert!(content["text"].as_str().unwrap().contains('T'));
     }
 
-    #[test]
-    fn test_handle_tools_call_invalid_tool() {
+    #[tokio::test]
+    async fn test_handle_tools_call_invalid_tool() {
         let params = json!({
             "name": "invalid_tool",
             "arguments": {}
         });
-        let response = handle_tools_call(json!(1), Some(&params));
+        let response =
+            handle_tools_call(&test_state(), &Outbox::default(), json!(1), Some(&params)).await;
 
         assert!(response.error.is_some());
         assert_eq!(response.error.unwrap().code, -32601);
     }
 
-    #[test]
-    fn test_handle_tools_call_missing_args() {
+    #[tokio::test]
+    async fn test_handle_tools_call_missing_args() {
         let params = json!({
             "name": "echo"
         });
-        let response = handle_tools_call(json!(1), Some(&params));
+        let response =
+            handle_tools_call(&test_state(), &Outbox::default(), json!(1), Some(&params)).await;
 
         assert!(response.error.is_some());
         assert_eq!(response.error.unwrap().code, -32602);
     }
+
+    #[tokio::test]
+    async fn test_harper_tool_errors_are_reported_in_the_result() {
+        let params = json!({
+            "name": "read_file",
+            "arguments": {}
+        });
+        let response =
+            handle_tools_call(&test_state(), &Outbox::default(), json!(1), Some(&params)).await;
+
+        assert!(response.error.is_none());
+        let result = response.result.unwrap();
+        assert_eq!(result["isError"], true);
+        assert!(result["content"][0]["text"]
+            .as_str()
+            .unwrap()
+            .contains("path"));
+    }
+
+    #[tokio::test]
+    async fn test_read_file_accepts_brackets_in_path() {
+        let file = tempfile::Builder::new()
+            .prefix("notes]")
+            .suffix(".txt")
+            .tempfile_in(".")
+            .unwrap();
+        std::fs::write(file.path(), "bracketed").unwrap();
+        let name = file.path().file_name().unwrap().to_string_lossy();
+        let params = json!({
+            "name": "read_file",
+            "arguments": { "path": name }
+        });
+        let response =
+            handle_tools_call(&test_state(), &Outbox::default(), json!(1), Some(&params)).await;
+
+        let result = response.result.unwrap();
+        assert_eq!(result["isError"], false);
+        assert_eq!(result["content"][0]["text"], "bracketed");
+    }
+
+    #[tokio::test]
+    async fn test_search_replace_refused_without_approval() {
+        let temp = tempfile::tempdir().unwrap();
+        let file = temp.path().join("notes.txt");
+        std::fs::write(&file, "old text").unwrap();
+        let params = json!({
+            "name": "search_replace",
+            "arguments": {
+                "path": file.display().to_string(),
+                "old_string": "old",
+                "new_string": "new"
+            }
+        });
+        let response =
+            handle_tools_call(&test_state(), &Outbox::default(), json!(1), Some(&params)).await;
+
+        assert_eq!(response.result.unwrap()["isError"], true);
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "old text");
+    }
+
+    #[tokio::test]
+    async fn test_session_tools_and_resources() {
+        let state = test_state();
+        {
+            let conn = state.connection().unwrap();
+            harper_core::save_session(&conn, "s1").unwrap();
+            harper_core::save_message(&conn, "s1", "user", "hello").unwrap();
+            harper_core::save_message(&conn, "s1", "assistant", "hi there").unwrap();
+        }
+
+        let params = json!({
+            "name": "session_history",
+            "arguments": { "session_id": "s1", "limit": 1 }
+        });
+        let response = handle_tools_call(&state, &Outbox::default(), json!(1), Some(&params)).await;
+        let result = response.result.unwrap();
+        assert_eq!(result["isError"], false);
+        assert_eq!(result["content"][0]["text"], "assistant: hi there");
+
+        let listed = handle_resources_list(&state, json!(2)).result.unwrap();
+        assert_eq!(listed["resources"][0]["uri"], "harper://sessions/s1");
+        assert_eq!(listed["resources"].as_array().unwrap().len(), 1);
+
+        let params = json!({ "uri": "harper://sessions/s1" });
+        let read = handle_resources_read(&state, json!(3), Some(&params))
+            .result
+            .unwrap();
+        let text = read["contents"][0]["text"].as_str().unwrap();
+        assert!(text.contains("hi there"));
+
+        let params = json!({ "uri": "harper://plans/s1" });
+        let missing = handle_resources_read(&state, json!(4), Some(&params));
+        assert_eq!(missing.error.unwrap().code, -32002);
+    }
+
+    #[test]
+    fn test_prompts_list_and_get() {
+        let temp = tempfile::tempdir().unwrap();
+        std::fs::write(temp.path().join("review.md"), "# Code review\nBe thorough.").unwrap();
+        std::fs::write(temp.path().join("notes.txt"), "ignored").unwrap();
+        let mut state = test_state();
+        state.prompts_dir = Some(temp.path().to_path_buf());
+
+        let listed = handle_prompts_list(&state, json!(1)).result.unwrap();
+        let prompts = listed["prompts"].as_array().unwrap();
+        assert_eq!(prompts.len(), 1);
+        assert_eq!(prompts[0]["name"], "review");
+        assert_eq!(prompts[0]["description"], "Code review");
+
+        let params = json!({ "name": "review" });
+        let prompt = handle_prompts_get(&state, json!(2), Some(&params))
+            .result
+            .unwrap();
+        assert_eq!(prompt["messages"][0]["role"], "user");
+        assert!(prompt["messages"][0]["content"]["text"]
+            .as_str()
+            .unwrap()
+            .contains("Be thorough."));
+
+        let params = json!({ "name": "../secrets" });
+        assert!(handle_prompts_get(&state, json!(3), Some(&params))
+            .error
+            .is_some());
+    }
 }
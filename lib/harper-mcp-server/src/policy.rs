@@ -0,0 +1,267 @@
+// Copyright 2026 harpertoken
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `[exec_policy]` applied to file access requested over MCP
+//!
+//! Nobody is around to answer an approval prompt, so anything the policy
+//! would ask the user about is refused instead.
+
+use async_trait::async_trait;
+use harper_core::core::io_traits::UserApproval;
+use harper_core::{ApprovalProfile, ExecPolicyConfig, HarperError, HarperResult};
+use std::path::{Path, PathBuf};
+
+/// How a tool touches the file it is approved for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    Read,
+    Write,
+}
+
+/// Approver handed to Harper's file tools on behalf of an MCP client
+pub struct PolicyApproval {
+    exec_policy: ExecPolicyConfig,
+    access: Access,
+}
+
+impl PolicyApproval {
+    pub fn new(exec_policy: &ExecPolicyConfig, access: Access) -> Self {
+        Self {
+            exec_policy: exec_policy.clone(),
+            access,
+        }
+    }
+
+    fn check(&self, target: &Path) -> HarperResult<()> {
+        let base_dir = std::env::current_dir()
+            .map_err(|e| HarperError::Command(format!("Failed to get current dir: {}", e)))?;
+        let sandbox = self.exec_policy.effective_sandbox_config();
+        let writable_dirs = sandbox.writable_dirs.unwrap_or_default();
+
+        if sandbox.enabled.unwrap_or(false) {
+            let mut roots = writable_dirs.clone();
+            if self.access == Access::Read {
+                roots.extend(sandbox.allowed_dirs.clone().unwrap_or_default());
+            }
+            if !within_any_root(&base_dir, target, &roots) {
+                return Err(HarperError::Command(format!(
+                    "Blocked by sandbox policy: {} is outside the allowed directories",
+                    target.display()
+                )));
+            }
+        }
+
+        if self.access == Access::Read {
+            // The workspace and configured roots are readable under every profile;
+            // anything else, such as `../` escapes or `~/.ssh`, only under allow_all
+            let mut roots = vec![".".to_string()];
+            roots.extend(sandbox.allowed_dirs.unwrap_or_default());
+            roots.extend(writable_dirs.iter().cloned());
+            let approved = match self.exec_policy.effective_approval_profile() {
+                ApprovalProfile::Strict | ApprovalProfile::AllowListed => {
+                    within_any_root(&base_dir, target, &roots)
+                }
+                ApprovalProfile::AllowAll => true,
+            };
+            if !approved {
+                return Err(HarperError::Command(format!(
+                    "Read of {} needs approval, which the MCP server cannot ask for. \
+                     Add its directory to exec_policy.sandbox.allowed_dirs or use approval_profile = \"allow_all\".",
+                    target.display()
+                )));
+            }
+        }
+
+        if self.access == Access::Write {
+            let approved = match self.exec_policy.effective_approval_profile() {
+                ApprovalProfile::Strict => false,
+                ApprovalProfile::AllowListed => within_any_root(&base_dir, target, &writable_dirs),
+                ApprovalProfile::AllowAll => true,
+            };
+            if !approved {
+                return Err(HarperError::Command(format!(
+                    "Write to {} needs approval, which the MCP server cannot ask for. \
+                     Add its directory to exec_policy.sandbox.writable_dirs or use approval_profile = \"allow_all\".",
+                    target.display()
+                )));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Whether `exec_policy` lets any write through without asking
+pub fn can_approve_writes(exec_policy: &ExecPolicyConfig) -> bool {
+    match exec_policy.effective_approval_profile() {
+        ApprovalProfile::Strict => false,
+        ApprovalProfile::AllowListed => !exec_policy
+            .effective_sandbox_config()
+            .writable_dirs
+            .unwrap_or_default()
+            .is_empty(),
+        ApprovalProfile::AllowAll => true,
+    }
+}
+
+#[async_trait]
+impl UserApproval for PolicyApproval {
+    async fn approve(&self, _prompt: &str, command: &str) -> HarperResult<bool> {
+        self.check(Path::new(command))?;
+        Ok(true)
+    }
+}
+
+fn absolute(base_dir: &Path, path: &Path) -> PathBuf {
+    let joined = base_dir.join(path);
+    joined.canonicalize().unwrap_or(joined)
+}
+
+fn within_any_root(base_dir: &Path, path: &Path, roots: &[String]) -> bool {
+    let path = absolute(base_dir, path);
+    roots
+        .iter()
+        .any(|root| path.starts_with(absolute(base_dir, Path::new(root))))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use harper_core::runtime::config::SandboxConfig;
+    use harper_core::SandboxProfile;
+
+    fn policy(profile: ApprovalProfile, sandbox: Option<SandboxConfig>) -> ExecPolicyConfig {
+        ExecPolicyConfig {
+            approval_profile: Some(profile),
+            sandbox_profile: Some(SandboxProfile::Disabled),
+            sandbox,
+            ..ExecPolicyConfig::default()
+        }
+    }
+
+    fn sandbox(allowed: &Path, writable: &Path) -> SandboxConfig {
+        SandboxConfig {
+            enabled: Some(true),
+            allowed_dirs: Some(vec![allowed.display().to_string()]),
+            writable_dirs: Some(vec![writable.display().to_string()]),
+            network_access: Some(false),
+            readonly_home: Some(true),
+            max_execution_time_secs: None,
+            max_memory_mb: None,
+            max_cpu_time_secs: None,
+            max_processes: None,
+            max_open_files: None,
+            max_output_bytes: None,
+            backend: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn sandbox_limits_reads_and_writes_to_configured_dirs() {
+        let temp = tempfile::tempdir().expect("tempdir");
+        let docs = temp.path().join("docs");
+        let src = temp.path().join("src");
+        std::fs::create_dir_all(&docs).expect("docs");
+        std::fs::create_dir_all(&src).expect("src");
+        let outside = tempfile::tempdir().expect("outside");
+        let config = policy(ApprovalProfile::AllowListed, Some(sandbox(&docs, &src)));
+
+        let read = PolicyApproval::new(&config, Access::Read);
+        assert!(read
+            .approve("", &docs.join("a.md").display().to_string())
+            .await
+            .unwrap());
+        assert!(read
+            .approve("", &src.join("a.rs").display().to_string())
+            .await
+            .unwrap());
+        assert!(read
+            .approve("", &outside.path().join("a").display().to_string())
+            .await
+            .is_err());
+
+        let write = PolicyApproval::new(&config, Access::Write);
+        assert!(write
+            .approve("", &src.join("a.rs").display().to_string())
+            .await
+            .unwrap());
+        assert!(write
+            .approve("", &docs.join("a.md").display().to_string())
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn approval_profile_decides_unattended_writes() {
+        let temp = tempfile::tempdir().expect("tempdir");
+        let target = temp.path().join("notes.txt").display().to_string();
+
+        let strict = policy(
+            ApprovalProfile::Strict,
+            Some(sandbox(temp.path(), temp.path())),
+        );
+        assert!(PolicyApproval::new(&strict, Access::Write)
+            .approve("", &target)
+            .await
+            .is_err());
+        assert!(PolicyApproval::new(&strict, Access::Read)
+            .approve("", &target)
+            .await
+            .unwrap());
+
+        let allow_listed = policy(ApprovalProfile::AllowListed, None);
+        assert!(PolicyApproval::new(&allow_listed, Access::Write)
+            .approve("", &target)
+            .await
+            .is_err());
+
+        let allow_all = policy(ApprovalProfile::AllowAll, None);
+        assert!(!can_approve_writes(&strict));
+        assert!(!can_approve_writes(&allow_listed));
+        assert!(can_approve_writes(&allow_all));
+        assert!(PolicyApproval::new(&allow_all, Access::Write)
+            .approve("", &target)
+            .await
+            .unwrap());
+    }
+
+    #[tokio::test]
+    async fn reads_outside_the_workspace_need_allow_all_without_a_sandbox() {
+        let outside = tempfile::tempdir().expect("outside");
+        let secret = outside.path().join("secret");
+        std::fs::write(&secret, "hunter2").expect("secret");
+        let cwd = std::env::current_dir().expect("cwd");
+        let escape = format!(
+            "{}{}",
+            "../".repeat(cwd.components().count()),
+            secret.strip_prefix("/").expect("absolute").display()
+        );
+
+        for profile in [ApprovalProfile::Strict, ApprovalProfile::AllowListed] {
+            let read = PolicyApproval::new(&policy(profile, None), Access::Read);
+            assert!(read.approve("", &escape).await.is_err(), "{profile:?}");
+            assert!(read
+                .approve("", &secret.display().to_string())
+                .await
+                .is_err());
+            assert!(read.approve("", "Cargo.toml").await.unwrap());
+        }
+
+        let allow_all = policy(ApprovalProfile::AllowAll, None);
+        assert!(PolicyApproval::new(&allow_all, Access::Read)
+            .approve("", &escape)
+            .await
+            .unwrap());
+    }
+}
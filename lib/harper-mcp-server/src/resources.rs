@@ -0,0 +1,132 @@
+// Copyright 2026 harpertoken
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Sessions and plans as MCP resources, custom prompts as MCP prompts
+//!
+//! Sessions are served as `harper://sessions/<id>` and plans as
+//! `harper://plans/<id>`. Prompts are the `<name>.md` files in
+//! `~/.harper/prompts`, the same ones `prompts.system_prompt_id` picks from.
+
+use crate::ServerState;
+use harper_core::{list_sessions, load_history, load_plan_state, HarperError, HarperResult};
+use serde_json::{json, Value};
+
+const SESSION_PREFIX: &str = "harper://sessions/";
+const PLAN_PREFIX: &str = "harper://plans/";
+
+/// `resources/list` entries: every session, plus a plan for sessions that have one
+pub fn list(state: &ServerState) -> HarperResult<Vec<Value>> {
+    let conn = state.connection()?;
+    let mut resources = Vec::new();
+    for session_id in list_sessions(&conn)? {
+        resources.push(json!({
+            "uri": format!("{}{}", SESSION_PREFIX, session_id),
+            "name": format!("Session {}", session_id),
+            "description": "Conversation history of a Harper session",
+            "mimeType": "application/json"
+        }));
+        if load_plan_state(&conn, &session_id)?.is_some() {
+            resources.push(json!({
+                "uri": format!("{}{}", PLAN_PREFIX, session_id),
+                "name": format!("Plan {}", session_id),
+                "description": "Current plan of a Harper session",
+                "mimeType": "application/json"
+            }));
+        }
+    }
+    Ok(resources)
+}
+
+/// `resources/read` contents for `uri`; `None` when no such resource exists
+pub fn read(state: &ServerState, uri: &str) -> HarperResult<Option<Value>> {
+    let conn = state.connection()?;
+    let body = if let Some(session_id) = uri.strip_prefix(SESSION_PREFIX) {
+        if !list_sessions(&conn)?.iter().any(|id| id == session_id) {
+            return Ok(None);
+        }
+        serde_json::to_string_pretty(&load_history(&conn, session_id)?)
+    } else if let Some(session_id) = uri.strip_prefix(PLAN_PREFIX) {
+        match load_plan_state(&conn, session_id)? {
+            Some(plan) => serde_json::to_string_pretty(&plan),
+            None => return Ok(None),
+        }
+    } else {
+        return Ok(None);
+    };
+    let text = body.map_err(|e| HarperError::Io(e.to_string()))?;
+    Ok(Some(json!({
+        "uri": uri,
+        "mimeType": "application/json",
+        "text": text
+    })))
+}
+
+/// `prompts/list` entries, described by the first line of each prompt file
+pub fn list_prompts(state: &ServerState) -> HarperResult<Vec<Value>> {
+    let Some(dir) = state.prompts_dir.as_deref() else {
+        return Ok(Vec::new());
+    };
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(HarperError::Io(e.to_string())),
+    };
+
+    let mut prompts = Vec::new();
+    for entry in entries {
+        let path = entry.map_err(|e| HarperError::Io(e.to_string()))?.path();
+        if path.extension().and_then(|ext| ext.to_str()) != Some("md") {
+            continue;
+        }
+        let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) else {
+            continue;
+        };
+        let content = std::fs::read_to_string(&path).unwrap_or_default();
+        let description = content
+            .lines()
+            .map(|line| line.trim_start_matches('#').trim())
+            .find(|line| !line.is_empty())
+            .unwrap_or_default();
+        prompts.push(json!({
+            "name": name,
+            "description": description,
+            "arguments": []
+        }));
+    }
+    prompts.sort_by(|a, b| a["name"].as_str().cmp(&b["name"].as_str()));
+    Ok(prompts)
+}
+
+/// `prompts/get` result for `name`; `None` when there is no such prompt
+pub fn get_prompt(state: &ServerState, name: &str) -> HarperResult<Option<Value>> {
+    let Some(dir) = state.prompts_dir.as_deref() else {
+        return Ok(None);
+    };
+    if name.is_empty() || name.contains(['/', '\\']) || name.starts_with('.') {
+        return Ok(None);
+    }
+    let content = match std::fs::read_to_string(dir.join(format!("{}.md", name))) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(HarperError::Io(e.to_string())),
+    };
+    Ok(Some(json!({
+        "messages": [
+            {
+                "role": "user",
+                "content": { "type": "text", "text": content }
+            }
+        ]
+    })))
+}
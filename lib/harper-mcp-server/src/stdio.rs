@@ -0,0 +1,268 @@
+// Copyright 2026 harpertoken
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Line-delimited JSON-RPC over stdin/stdout
+//!
+//! Each line is one message. Requests run concurrently so a long tool call
+//! can be cancelled, and everything written back (responses, progress,
+//! `notifications/tools/list_changed`) goes through a single writer.
+
+use crate::{dispatch, error_response, JsonRpcRequest, Outbox, ServerState};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::sync::{broadcast, mpsc};
+
+/// Serve one client until `reader` reaches end of file
+pub async fn serve<R, W>(state: Arc<ServerState>, reader: R, mut writer: W) -> std::io::Result<()>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin + Send + 'static,
+{
+    let (sender, mut outgoing) = mpsc::unbounded_channel::<Value>();
+    let outbox = Outbox::new(sender);
+    let writer_task = tokio::spawn(async move {
+        while let Some(message) = outgoing.recv().await {
+            let mut line = message.to_string();
+            line.push('\n');
+            writer.write_all(line.as_bytes()).await?;
+            writer.flush().await?;
+        }
+        Ok::<(), std::io::Error>(())
+    });
+
+    let mut tools_changed = state.subscribe_tools_changed();
+    let mut lines = BufReader::new(reader).lines();
+    loop {
+        tokio::select! {
+            line = lines.next_line() => {
+                let Some(line) = line? else {
+                    break;
+                };
+                handle_line(&state, &outbox, &line);
+            }
+            changed = tools_changed.recv() => {
+                if !matches!(changed, Err(broadcast::error::RecvError::Closed)) {
+                    outbox.notify("notifications/tools/list_changed", json!({}));
+                }
+            }
+        }
+    }
+
+    // The writer ends once the last in-flight request has answered
+    drop(outbox);
+    writer_task.await.map_err(std::io::Error::other)?
+}
+
+fn handle_line(state: &Arc<ServerState>, outbox: &Outbox, line: &str) {
+    if line.trim().is_empty() {
+        return;
+    }
+    let message: Value = match serde_json::from_str(line) {
+        Ok(message) => message,
+        Err(_) => return send_response(outbox, error_response(None, -32700, "Parse error", None)),
+    };
+    // Replies to requests the server never sends
+    if message.get("method").is_none() {
+        return;
+    }
+    let request: JsonRpcRequest = match serde_json::from_value(message) {
+        Ok(request) => request,
+        Err(_) => {
+            return send_response(
+                outbox,
+                error_response(None, -32600, "Invalid Request", None),
+            )
+        }
+    };
+
+    let state = Arc::clone(state);
+    let outbox = outbox.clone();
+    tokio::spawn(async move {
+        if let Some(response) = dispatch(&state, &outbox, request).await {
+            send_response(&outbox, response);
+        }
+    });
+}
+
+fn send_response(outbox: &Outbox, response: crate::JsonRpcResponse) {
+    if let Ok(message) = serde_json::to_value(response) {
+        outbox.send(message);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use harper_core::{create_connection, init_db, ApprovalProfile, ExecPolicyConfig};
+    use tokio::io::{duplex, DuplexStream, Lines};
+
+    /// In-process client on the other end of a duplex pipe
+    struct Client {
+        writer: DuplexStream,
+        lines: Lines<BufReader<DuplexStream>>,
+    }
+
+    impl Client {
+        fn start(state: Arc<ServerState>) -> Self {
+            let (client_writer, server_reader) = duplex(64 * 1024);
+            let (server_writer, client_reader) = duplex(64 * 1024);
+            tokio::spawn(serve(state, server_reader, server_writer));
+            Self {
+                writer: client_writer,
+                lines: BufReader::new(client_reader).lines(),
+            }
+        }
+
+        async fn send(&mut self, message: Value) {
+            let line = format!("{}\n", message);
+            self.writer.write_all(line.as_bytes()).await.unwrap();
+        }
+
+        async fn receive(&mut self) -> Value {
+            let line = self.lines.next_line().await.unwrap().expect("server line");
+            serde_json::from_str(&line).unwrap()
+        }
+    }
+
+    fn test_state() -> Arc<ServerState> {
+        let conn = create_connection(":memory:").unwrap();
+        init_db(&conn).unwrap();
+        Arc::new(ServerState::new(conn, ExecPolicyConfig::default(), None))
+    }
+
+    #[tokio::test]
+    async fn initialize_ping_and_tool_call_over_lines() {
+        let mut client = Client::start(test_state());
+
+        client
+            .send(json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": "initialize",
+                "params": { "protocolVersion": "2025-06-18" }
+            }))
+            .await;
+        let response = client.receive().await;
+        assert_eq!(response["result"]["protocolVersion"], "2025-06-18");
+
+        client
+            .send(json!({ "jsonrpc": "2.0", "method": "notifications/initialized" }))
+            .await;
+        client
+            .send(json!({ "jsonrpc": "2.0", "id": 2, "method": "ping" }))
+            .await;
+        let response = client.receive().await;
+        assert_eq!(response["id"], 2);
+        assert_eq!(response["result"], json!({}));
+
+        client
+            .send(json!({
+                "jsonrpc": "2.0",
+                "id": 3,
+                "method": "tools/call",
+                "params": { "name": "echo", "arguments": { "message": "over stdio" } }
+            }))
+            .await;
+        let response = client.receive().await;
+        assert_eq!(response["result"]["content"][0]["text"], "over stdio");
+
+        client.writer.write_all(b"{oops\n").await.unwrap();
+        let response = client.receive().await;
+        assert_eq!(response["error"]["code"], -32700);
+    }
+
+    #[tokio::test]
+    async fn long_tool_calls_report_progress() {
+        let mut client = Client::start(test_state());
+
+        client
+            .send(json!({
+                "jsonrpc": "2.0",
+                "id": 7,
+                "method": "tools/call",
+                "params": {
+                    "name": "list_sessions",
+                    "arguments": {},
+                    "_meta": { "progressToken": "tok" }
+                }
+            }))
+            .await;
+
+        let mut progress = Vec::new();
+        let response = loop {
+            let message = client.receive().await;
+            if message["method"] == "notifications/progress" {
+                progress.push(message["params"].clone());
+            } else {
+                break message;
+            }
+        };
+        assert_eq!(response["id"], 7);
+        assert_eq!(response["result"]["content"][0]["text"], "No sessions");
+        let last = progress.last().expect("final progress");
+        assert_eq!(last["progressToken"], "tok");
+        assert_eq!(last["progress"], last["total"]);
+    }
+
+    #[tokio::test]
+    async fn cancelled_requests_are_aborted_without_reply() {
+        let state = test_state();
+        let mut client = Client::start(Arc::clone(&state));
+        let task = tokio::spawn(std::future::pending::<()>());
+        state
+            .in_flight
+            .insert(json!(9).to_string(), task.abort_handle());
+
+        client
+            .send(json!({
+                "jsonrpc": "2.0",
+                "method": "notifications/cancelled",
+                "params": { "requestId": 9, "reason": "user gave up" }
+            }))
+            .await;
+        client
+            .send(json!({ "jsonrpc": "2.0", "id": 10, "method": "ping" }))
+            .await;
+
+        assert_eq!(client.receive().await["id"], 10);
+        assert!(task.await.unwrap_err().is_cancelled());
+        assert!(state.in_flight.is_empty());
+    }
+
+    #[tokio::test]
+    async fn policy_changes_announce_tool_list_changes() {
+        let state = test_state();
+        let mut client = Client::start(Arc::clone(&state));
+        client
+            .send(json!({ "jsonrpc": "2.0", "id": 1, "method": "ping" }))
+            .await;
+        client.receive().await;
+
+        state.set_exec_policy(ExecPolicyConfig::default());
+        state.set_exec_policy(ExecPolicyConfig {
+            approval_profile: Some(ApprovalProfile::AllowAll),
+            ..ExecPolicyConfig::default()
+        });
+        let message = client.receive().await;
+        assert_eq!(message["method"], "notifications/tools/list_changed");
+
+        client
+            .send(json!({ "jsonrpc": "2.0", "id": 2, "method": "tools/list" }))
+            .await;
+        let response = client.receive().await;
+        let tools = response["result"]["tools"].as_array().unwrap();
+        assert!(tools.iter().any(|tool| tool["name"] == "search_replace"));
+    }
+}
@@ -0,0 +1,186 @@
+// Copyright 2026 harpertoken
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Harper's own tools offered to MCP clients
+
+use crate::policy::{can_approve_writes, Access, PolicyApproval};
+use crate::ServerState;
+use harper_core::tools::{codebase_investigator, filesystem, git};
+use harper_core::{
+    list_sessions, load_history, load_plan_state, ExecPolicyConfig, HarperError, HarperResult,
+};
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+/// `tools/list` entries for the Harper tools
+///
+/// `search_replace` is left out while `exec_policy` would refuse every write.
+pub fn definitions(exec_policy: &ExecPolicyConfig) -> Vec<Value> {
+    let mut tools = vec![
+        json!({
+            "name": "read_file",
+            "description": "Read a file from the workspace",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "path": { "type": "string", "description": "Workspace-relative file path" }
+                },
+                "required": ["path"]
+            }
+        }),
+        json!({
+            "name": "search_replace",
+            "description": "Replace every occurrence of a string in a workspace file",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "path": { "type": "string", "description": "Workspace-relative file path" },
+                    "old_string": { "type": "string", "description": "Text to replace" },
+                    "new_string": { "type": "string", "description": "Replacement text" }
+                },
+                "required": ["path", "old_string", "new_string"]
+            }
+        }),
+        json!({
+            "name": "search_text",
+            "description": "Search the workspace source for text and symbols",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "query": { "type": "string", "description": "Text to search for" }
+                },
+                "required": ["query"]
+            }
+        }),
+        json!({
+            "name": "git_status",
+            "description": "Show the working tree status",
+            "inputSchema": { "type": "object", "properties": {}, "required": [] }
+        }),
+        json!({
+            "name": "git_diff",
+            "description": "Show unstaged changes",
+            "inputSchema": { "type": "object", "properties": {}, "required": [] }
+        }),
+        json!({
+            "name": "list_sessions",
+            "description": "List Harper session ids, newest first",
+            "inputSchema": { "type": "object", "properties": {}, "required": [] }
+        }),
+        json!({
+            "name": "session_history",
+            "description": "Show the messages of a Harper session",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "session_id": { "type": "string", "description": "Session to read" },
+                    "limit": { "type": "integer", "description": "Only the last N messages" }
+                },
+                "required": ["session_id"]
+            }
+        }),
+        json!({
+            "name": "plan_state",
+            "description": "Show the current plan of a Harper session",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "session_id": { "type": "string", "description": "Session to inspect" }
+                },
+                "required": ["session_id"]
+            }
+        }),
+    ];
+    if !can_approve_writes(exec_policy) {
+        tools.retain(|tool| tool["name"] != "search_replace");
+    }
+    tools
+}
+
+fn string_arg<'a>(args: &'a Value, key: &str) -> HarperResult<&'a str> {
+    args.get(key)
+        .and_then(Value::as_str)
+        .ok_or_else(|| HarperError::Validation(format!("Missing string argument: {}", key)))
+}
+
+/// Run a Harper tool; `None` when `name` is not one of them
+pub async fn call(state: &ServerState, name: &str, args: &Value) -> Option<HarperResult<String>> {
+    let result = match name {
+        "read_file" => read_file(state, args).await,
+        "search_replace" => search_replace(state, args).await,
+        "search_text" => match string_arg(args, "query") {
+            Ok(query) => codebase_investigator::search_text(query).await,
+            Err(e) => Err(e),
+        },
+        "git_status" => git::git_status_async().await,
+        "git_diff" => git::git_diff(),
+        "list_sessions" => session_list(state),
+        "session_history" => session_history(state, args),
+        "plan_state" => plan_state(state, args),
+        _ => return None,
+    };
+    Some(result)
+}
+
+async fn read_file(state: &ServerState, args: &Value) -> HarperResult<String> {
+    let path = string_arg(args, "path")?;
+    let approver = Arc::new(PolicyApproval::new(&state.exec_policy(), Access::Read));
+    filesystem::read_file_direct(path, Some(approver)).await
+}
+
+async fn search_replace(state: &ServerState, args: &Value) -> HarperResult<String> {
+    let path = string_arg(args, "path")?;
+    let old_string = string_arg(args, "old_string")?;
+    let new_string = string_arg(args, "new_string")?;
+    let approver = Arc::new(PolicyApproval::new(&state.exec_policy(), Access::Write));
+    filesystem::search_replace_direct(path, old_string, new_string, Some(approver)).await
+}
+
+fn session_list(state: &ServerState) -> HarperResult<String> {
+    let sessions = list_sessions(&*state.connection()?)?;
+    if sessions.is_empty() {
+        return Ok("No sessions".to_string());
+    }
+    Ok(sessions.join("\n"))
+}
+
+fn session_history(state: &ServerState, args: &Value) -> HarperResult<String> {
+    let session_id = string_arg(args, "session_id")?;
+    let history = load_history(&*state.connection()?, session_id)?;
+    if history.is_empty() {
+        return Ok(format!("No messages in session {}", session_id));
+    }
+    let limit = args
+        .get("limit")
+        .and_then(Value::as_u64)
+        .map_or(history.len(), |limit| {
+            usize::try_from(limit).unwrap_or(usize::MAX)
+        });
+    let skip = history.len().saturating_sub(limit);
+    Ok(history[skip..]
+        .iter()
+        .map(|message| format!("{}: {}", message.role, message.content))
+        .collect::<Vec<_>>()
+        .join("\n\n"))
+}
+
+fn plan_state(state: &ServerState, args: &Value) -> HarperResult<String> {
+    let session_id = string_arg(args, "session_id")?;
+    match load_plan_state(&*state.connection()?, session_id)? {
+        Some(plan) => {
+            serde_json::to_string_pretty(&plan).map_err(|e| HarperError::Io(e.to_string()))
+        }
+        None => Ok(format!("No plan for session {}", session_id)),
+    }
+}
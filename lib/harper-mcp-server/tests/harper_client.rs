@@ -0,0 +1,260 @@
+// Copyright 2026 harpertoken
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Harper's MCP client talking to this server as a local peer
+
+use harper_core::agent::prompt::PromptBuilder;
+use harper_core::{
+    execute_mcp_shell_command, ApiConfig, ApiProvider, FallbackChain, McpConfig, McpRegistry,
+    McpServerConfig, McpShellCommand, McpShellReply, McpTransportKind, ModelParameters,
+    ToolRegistry,
+};
+use serde_json::json;
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader};
+use std::process::{Child, ChildStdout, Command, Stdio};
+
+/// Server process that is killed when the test ends
+struct ServerProcess {
+    child: Child,
+    // Held open so the server can keep writing to its stdout
+    _stdout: BufReader<ChildStdout>,
+    url: String,
+}
+
+impl ServerProcess {
+    fn spawn_http() -> Self {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_harper-mcp-server"))
+            .args(["--port", "0"])
+            .stdout(Stdio::piped())
+            .spawn()
+            .expect("start harper-mcp-server");
+        let mut stdout = BufReader::new(child.stdout.take().expect("server stdout"));
+        let mut line = String::new();
+        stdout.read_line(&mut line).expect("read listen address");
+        let url = line
+            .trim()
+            .strip_prefix("MCP server listening on ")
+            .expect("listen banner")
+            .to_string();
+        Self {
+            child,
+            _stdout: stdout,
+            url,
+        }
+    }
+}
+
+impl Drop for ServerProcess {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+fn http_server(name: &str, url: &str, enabled: bool) -> McpServerConfig {
+    McpServerConfig {
+        name: name.to_string(),
+        enabled: Some(enabled),
+        transport: McpTransportKind::Http,
+        url: Some(url.to_string()),
+        command: None,
+        args: Vec::new(),
+        env: HashMap::new(),
+    }
+}
+
+#[tokio::test]
+async fn registry_namespaces_tools_across_servers() {
+    let alpha = ServerProcess::spawn_http();
+    let beta = ServerProcess::spawn_http();
+    let config = McpConfig {
+        enabled: true,
+        server_url: String::new(),
+        servers: vec![
+            http_server("alpha", &alpha.url, true),
+            http_server("beta", &beta.url, true),
+            http_server("offline", "http://127.0.0.1:9", false),
+        ],
+    };
+
+    let registry = McpRegistry::connect(&config).await;
+    let names: Vec<&str> = registry.servers().iter().map(|s| s.name.as_str()).collect();
+    assert_eq!(names, vec!["alpha", "beta"]);
+
+    let tools: Vec<String> = registry
+        .list_tools()
+        .await
+        .into_iter()
+        .filter(|entry| matches!(entry.tool.name.as_str(), "echo" | "get_time"))
+        .map(|entry| entry.name)
+        .collect();
+    assert_eq!(
+        tools,
+        vec![
+            "mcp__alpha__echo",
+            "mcp__alpha__get_time",
+            "mcp__beta__echo",
+            "mcp__beta__get_time",
+        ]
+    );
+
+    let result = registry
+        .call_tool("mcp__beta__echo", json!({"message": "from beta"}))
+        .await
+        .expect("namespaced call");
+    let text = serde_json::to_value(&result.content[0]).expect("content block");
+    assert_eq!(text["text"], "from beta");
+
+    assert!(registry
+        .call_tool("mcp__gamma__echo", json!({"message": "nowhere"}))
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn system_prompt_lists_tools_of_every_server() {
+    let alpha = ServerProcess::spawn_http();
+    let beta = ServerProcess::spawn_http();
+    let config = McpConfig {
+        enabled: true,
+        server_url: String::new(),
+        servers: vec![
+            http_server("alpha", &alpha.url, true),
+            http_server("beta", &beta.url, true),
+        ],
+    };
+    let registry = McpRegistry::connect(&config).await;
+    let api_config = ApiConfig {
+        provider: ApiProvider::OpenAI,
+        api_key: "test-key".to_string(),
+        base_url: "http://127.0.0.1:9/v1/chat/completions".to_string(),
+        model_name: "gpt-5.5".to_string(),
+        headers: HashMap::new(),
+        parameters: ModelParameters::default(),
+        fallback: FallbackChain::default(),
+    };
+
+    let prompt = PromptBuilder::new(&api_config, &ToolRegistry::default(), None, Some(&registry))
+        .build_system_prompt(false)
+        .await;
+    assert!(prompt.contains("- mcp__alpha__echo: Echo the input message"));
+    assert!(prompt.contains("- mcp__beta__get_time: Get the current UTC time"));
+
+    let tools = ToolRegistry::new(None, vec!["mcp__beta__*".to_string()]);
+    let prompt = PromptBuilder::new(&api_config, &tools, None, Some(&registry))
+        .build_system_prompt(false)
+        .await;
+    assert!(prompt.contains("- mcp__alpha__echo: Echo the input message"));
+    assert!(!prompt.contains("mcp__beta__"));
+}
+
+#[tokio::test]
+async fn registry_drives_server_over_stdio() {
+    let config = McpConfig {
+        enabled: true,
+        server_url: String::new(),
+        servers: vec![McpServerConfig {
+            name: "harper".to_string(),
+            enabled: None,
+            transport: McpTransportKind::Stdio,
+            url: None,
+            command: Some(env!("CARGO_BIN_EXE_harper-mcp-server").to_string()),
+            args: vec!["--stdio".to_string()],
+            env: HashMap::new(),
+        }],
+    };
+
+    let registry = McpRegistry::connect(&config).await;
+    assert_eq!(registry.servers().len(), 1);
+
+    let tools: Vec<String> = registry
+        .list_tools()
+        .await
+        .into_iter()
+        .map(|entry| entry.name)
+        .collect();
+    assert!(tools.contains(&"mcp__harper__read_file".to_string()));
+
+    let result = registry
+        .call_tool("mcp__harper__echo", json!({"message": "over stdio"}))
+        .await
+        .expect("stdio call");
+    let text = serde_json::to_value(&result.content[0]).expect("content block");
+    assert_eq!(text["text"], "over stdio");
+}
+
+#[tokio::test]
+async fn registry_lists_and_expands_server_prompts() {
+    let home = tempfile::tempdir().expect("home");
+    let prompts = home.path().join(".harper").join("prompts");
+    std::fs::create_dir_all(&prompts).expect("prompts dir");
+    std::fs::write(
+        prompts.join("review.md"),
+        "# Review\nLook for missing tests.",
+    )
+    .unwrap();
+    let config = McpConfig {
+        enabled: true,
+        server_url: String::new(),
+        servers: vec![McpServerConfig {
+            name: "harper".to_string(),
+            enabled: None,
+            transport: McpTransportKind::Stdio,
+            url: None,
+            command: Some(env!("CARGO_BIN_EXE_harper-mcp-server").to_string()),
+            args: vec!["--stdio".to_string()],
+            env: HashMap::from([("HOME".to_string(), home.path().display().to_string())]),
+        }],
+    };
+    let registry = McpRegistry::connect(&config).await;
+
+    let names: Vec<String> = registry
+        .list_prompts()
+        .await
+        .iter()
+        .map(|entry| entry.qualified_name())
+        .collect();
+    assert_eq!(names, vec!["harper/review"]);
+
+    let listing = execute_mcp_shell_command(Some(&registry), &McpShellCommand::Prompts)
+        .await
+        .expect("prompts listing");
+    assert!(
+        matches!(listing, McpShellReply::Listing(text) if text.contains("harper/review - Review"))
+    );
+
+    let prompt = execute_mcp_shell_command(
+        Some(&registry),
+        &McpShellCommand::Prompt {
+            name: "review".to_string(),
+            arguments: Vec::new(),
+        },
+    )
+    .await
+    .expect("expanded prompt");
+    assert_eq!(
+        prompt,
+        McpShellReply::Prompt("# Review\nLook for missing tests.".to_string())
+    );
+
+    assert!(registry.get_prompt("harper/missing", &[]).await.is_err());
+    let resources = execute_mcp_shell_command(Some(&registry), &McpShellCommand::Resources)
+        .await
+        .expect("resources listing");
+    assert_eq!(
+        resources,
+        McpShellReply::Listing("No MCP resources available.".to_string())
+    );
+}
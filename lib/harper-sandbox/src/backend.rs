@@ -13,45 +13,90 @@
 // limitations under the License.
 
 use crate::errors::{Result, SandboxError};
+use crate::limits::{OutputBudget, ProcessLimits, ResourceLimit};
 use crate::policy::SandboxConfig;
 use crate::request::SandboxRequest;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
 #[cfg(any(target_os = "linux", target_os = "macos", test))]
-use std::path::{Path, PathBuf};
+use std::path::PathBuf;
 use std::process::Stdio;
-use tokio::io::{AsyncBufReadExt, BufReader};
+use std::sync::Arc;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, BufReader};
 use tokio::process::Command;
 use tokio::sync::mpsc;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum SandboxBackend {
     Bubblewrap,
     SandboxExec,
+    /// Landlock filesystem rules plus a seccomp network filter, applied in-process
+    Landlock,
     None,
 }
 
+const MAX_CHUNK_BYTES: u64 = 64 * 1024;
+
+/// Captured output, the resource limit that stopped the command, if any, and
+/// the configured limits that could not be applied
+pub(crate) type Execution = (
+    std::process::Output,
+    Option<ResourceLimit>,
+    Vec<ResourceLimit>,
+);
+
 pub fn detect_backend() -> SandboxBackend {
-    #[cfg(target_os = "linux")]
-    {
-        if std::path::Path::new("/usr/bin/bwrap").exists()
-            || std::path::Path::new("/bin/bwrap").exists()
-        {
-            return SandboxBackend::Bubblewrap;
+    [
+        SandboxBackend::Bubblewrap,
+        SandboxBackend::SandboxExec,
+        SandboxBackend::Landlock,
+    ]
+    .into_iter()
+    .find(|backend| is_backend_available(*backend))
+    .unwrap_or(SandboxBackend::None)
+}
+
+/// Uses the preferred backend when this host supports it, otherwise detects one.
+///
+/// An unavailable preference resolves to `None` so an enabled sandbox fails
+/// closed instead of silently using a different isolation model.
+pub fn select_backend(preferred: Option<SandboxBackend>) -> SandboxBackend {
+    match preferred {
+        None => detect_backend(),
+        Some(backend) if is_backend_available(backend) => backend,
+        Some(backend) => {
+            log::warn!(
+                "Sandbox backend {} is not available on this host",
+                backend_name(backend)
+            );
+            SandboxBackend::None
         }
     }
-    #[cfg(target_os = "macos")]
-    {
-        if std::path::Path::new("/usr/bin/sandbox-exec").exists() {
-            return SandboxBackend::SandboxExec;
+}
+
+pub fn is_backend_available(backend: SandboxBackend) -> bool {
+    match backend {
+        SandboxBackend::Bubblewrap => {
+            cfg!(target_os = "linux")
+                && (Path::new("/usr/bin/bwrap").exists() || Path::new("/bin/bwrap").exists())
+        }
+        SandboxBackend::SandboxExec => {
+            cfg!(target_os = "macos") && Path::new("/usr/bin/sandbox-exec").exists()
         }
+        #[cfg(target_os = "linux")]
+        SandboxBackend::Landlock => crate::linux::landlock_supported(),
+        #[cfg(not(target_os = "linux"))]
+        SandboxBackend::Landlock => false,
+        SandboxBackend::None => true,
     }
-    #[allow(unreachable_code)]
-    SandboxBackend::None
 }
 
 pub fn backend_name(backend: SandboxBackend) -> &'static str {
     match backend {
         SandboxBackend::Bubblewrap => "bubblewrap (bwrap)",
         SandboxBackend::SandboxExec => "sandbox-exec (macOS)",
+        SandboxBackend::Landlock => "landlock + seccomp",
         SandboxBackend::None => "none",
     }
 }
@@ -61,7 +106,7 @@ pub async fn execute_with_backend_streaming<C>(
     config: &SandboxConfig,
     request: &SandboxRequest,
     on_output: C,
-) -> Result<std::process::Output>
+) -> Result<Execution>
 where
     C: FnMut(String, bool) + Send + 'static,
 {
@@ -69,6 +114,7 @@ where
         SandboxBackend::None => execute_direct(config, request, on_output).await,
         SandboxBackend::Bubblewrap => execute_bwrap(config, request, on_output).await,
         SandboxBackend::SandboxExec => execute_sandbox_exec(config, request, on_output).await,
+        SandboxBackend::Landlock => execute_landlock(config, request, on_output).await,
     }
 }
 
@@ -76,7 +122,7 @@ async fn execute_direct(
     config: &SandboxConfig,
     request: &SandboxRequest,
     on_output: impl FnMut(String, bool) + Send + 'static,
-) -> Result<std::process::Output> {
+) -> Result<Execution> {
     let mut cmd = Command::new(&request.command);
     cmd.args(&request.args)
         .current_dir(&request.working_dir)
@@ -95,7 +141,51 @@ async fn execute_bwrap(
     config: &SandboxConfig,
     request: &SandboxRequest,
     on_output: impl FnMut(String, bool) + Send + 'static,
-) -> Result<std::process::Output> {
+) -> Result<Execution> {
+    let mut cmd = Command::new("bwrap");
+    cmd.args(bwrap_args(config, request))
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    for (key, value) in &request.env {
+        cmd.env(key, value);
+    }
+    run_command_with_timeout_streaming(config, cmd, on_output).await
+}
+
+/// Readable and writable paths granted by the config, shared by the bwrap and
+/// Landlock backends so both treat home and the configured dirs the same way.
+/// Home is writable only when `readonly_home` is off.
+#[cfg(target_os = "linux")]
+pub(crate) fn filesystem_paths(
+    config: &SandboxConfig,
+    working_dir: &Path,
+) -> (Vec<PathBuf>, Vec<PathBuf>) {
+    let mut read_paths = Vec::new();
+    let mut write_paths = Vec::new();
+    if let Ok(home) = std::env::var("HOME") {
+        if config.readonly_home {
+            read_paths.push(PathBuf::from(home));
+        } else {
+            write_paths.push(PathBuf::from(home));
+        }
+    }
+    read_paths.extend(
+        config
+            .allowed_dirs
+            .iter()
+            .filter_map(|dir| existing_mount_path(dir, working_dir)),
+    );
+    write_paths.extend(
+        config
+            .writable_dirs
+            .iter()
+            .filter_map(|dir| existing_mount_path(dir, working_dir)),
+    );
+    (read_paths, write_paths)
+}
+
+#[cfg(target_os = "linux")]
+fn bwrap_args(config: &SandboxConfig, request: &SandboxRequest) -> Vec<String> {
     let mut bwrap_args = vec!["--unshare-pid".to_string()];
 
     if !config.network_access {
@@ -107,23 +197,12 @@ async fn execute_bwrap(
     bpush(&mut bwrap_args, "--ro-bind", "/lib".to_string());
     bpush(&mut bwrap_args, "--ro-bind", "/lib64".to_string());
 
-    if config.readonly_home {
-        if let Ok(home) = std::env::var("HOME") {
-            bpush(&mut bwrap_args, "--ro-bind", home);
-        }
-    } else if let Ok(home) = std::env::var("HOME") {
-        bpush(&mut bwrap_args, "--ro-bind", home);
+    let (read_paths, write_paths) = filesystem_paths(config, &request.working_dir);
+    for path in read_paths {
+        bpush(&mut bwrap_args, "--ro-bind", path.display().to_string());
     }
-
-    for dir in &config.allowed_dirs {
-        if let Some(dir) = existing_mount_path(dir, &request.working_dir) {
-            bpush(&mut bwrap_args, "--ro-bind", dir.display().to_string());
-        }
-    }
-    for dir in &config.writable_dirs {
-        if let Some(dir) = existing_mount_path(dir, &request.working_dir) {
-            bpush(&mut bwrap_args, "--bind", dir.display().to_string());
-        }
+    for path in write_paths {
+        bpush(&mut bwrap_args, "--bind", path.display().to_string());
     }
 
     bpush(
@@ -135,25 +214,51 @@ async fn execute_bwrap(
     bwrap_args.push("--".to_string());
     bwrap_args.push(request.command.clone());
     bwrap_args.extend(request.args.iter().cloned());
+    bwrap_args
+}
 
-    let mut cmd = Command::new("bwrap");
-    cmd.args(&bwrap_args)
+#[cfg(not(target_os = "linux"))]
+async fn execute_bwrap(
+    _config: &SandboxConfig,
+    _request: &SandboxRequest,
+    _on_output: impl FnMut(String, bool) + Send + 'static,
+) -> Result<Execution> {
+    Err(SandboxError::BackendUnavailable(
+        "Bubblewrap only available on Linux".to_string(),
+    ))
+}
+
+#[cfg(target_os = "linux")]
+async fn execute_landlock(
+    config: &SandboxConfig,
+    request: &SandboxRequest,
+    on_output: impl FnMut(String, bool) + Send + 'static,
+) -> Result<Execution> {
+    let mut restrictions = crate::linux::prepare(config, &request.working_dir)?;
+
+    let mut cmd = Command::new(&request.command);
+    cmd.args(&request.args)
+        .current_dir(&request.working_dir)
         .stdout(Stdio::piped())
         .stderr(Stdio::piped());
     for (key, value) in &request.env {
         cmd.env(key, value);
     }
+    // SAFETY: the hook only issues system calls on state prepared above
+    unsafe {
+        cmd.pre_exec(move || restrictions.apply());
+    }
     run_command_with_timeout_streaming(config, cmd, on_output).await
 }
 
 #[cfg(not(target_os = "linux"))]
-async fn execute_bwrap(
+async fn execute_landlock(
     _config: &SandboxConfig,
     _request: &SandboxRequest,
     _on_output: impl FnMut(String, bool) + Send + 'static,
-) -> Result<std::process::Output> {
+) -> Result<Execution> {
     Err(SandboxError::BackendUnavailable(
-        "Bubblewrap only available on Linux".to_string(),
+        "Landlock only available on Linux".to_string(),
     ))
 }
 
@@ -162,7 +267,7 @@ async fn execute_sandbox_exec(
     config: &SandboxConfig,
     request: &SandboxRequest,
     on_output: impl FnMut(String, bool) + Send + 'static,
-) -> Result<std::process::Output> {
+) -> Result<Execution> {
     let sandbox_profile = build_sandbox_exec_profile(config, &request.working_dir);
 
     let mut cmd = Command::new("sandbox-exec");
@@ -180,7 +285,7 @@ async fn execute_sandbox_exec(
 }
 
 #[cfg(any(target_os = "linux", target_os = "macos", test))]
-fn existing_mount_path(path: &Path, working_dir: &Path) -> Option<PathBuf> {
+pub(crate) fn existing_mount_path(path: &Path, working_dir: &Path) -> Option<PathBuf> {
     let candidate = if path.is_absolute() {
         path.to_path_buf()
     } else {
@@ -244,7 +349,7 @@ async fn execute_sandbox_exec(
     _config: &SandboxConfig,
     _request: &SandboxRequest,
     _on_output: impl FnMut(String, bool) + Send + 'static,
-) -> Result<std::process::Output> {
+) -> Result<Execution> {
     Err(SandboxError::BackendUnavailable(
         "sandbox-exec only available on macOS".to_string(),
     ))
@@ -254,7 +359,12 @@ async fn run_command_with_timeout_streaming(
     config: &SandboxConfig,
     mut cmd: Command,
     on_output: impl FnMut(String, bool) + Send + 'static,
-) -> Result<std::process::Output> {
+) -> Result<Execution> {
+    let limits = ProcessLimits::prepare(config);
+    limits.install(&mut cmd);
+    let budget = Arc::new(OutputBudget::new(config.max_output_bytes));
+    let unenforced = limits.unenforced();
+
     let operation = async move {
         let mut child = cmd
             .spawn()
@@ -263,8 +373,8 @@ async fn run_command_with_timeout_streaming(
         let stderr = child.stderr.take();
         let (tx, mut rx) = mpsc::unbounded_channel::<(String, bool)>();
 
-        let stdout_task = tokio::spawn(read_stream(stdout, false, tx.clone()));
-        let stderr_task = tokio::spawn(read_stream(stderr, true, tx));
+        let stdout_task = tokio::spawn(read_stream(stdout, false, tx.clone(), budget.clone()));
+        let stderr_task = tokio::spawn(read_stream(stderr, true, tx, budget.clone()));
         let consumer_task = tokio::spawn(async move {
             let mut on_output = on_output;
             while let Some((chunk, is_error)) = rx.recv().await {
@@ -272,10 +382,14 @@ async fn run_command_with_timeout_streaming(
             }
         });
 
-        let status = child
-            .wait()
-            .await
-            .map_err(|e| SandboxError::ExecutionFailed(e.to_string()))?;
+        let status = tokio::select! {
+            status = child.wait() => status,
+            _ = budget.exhausted() => {
+                let _ = child.start_kill();
+                child.wait().await
+            }
+        }
+        .map_err(|e| SandboxError::ExecutionFailed(e.to_string()))?;
         let stdout = stdout_task
             .await
             .map_err(|e| SandboxError::ExecutionFailed(e.to_string()))??;
@@ -286,11 +400,21 @@ async fn run_command_with_timeout_streaming(
             .await
             .map_err(|e| SandboxError::ExecutionFailed(e.to_string()))?;
 
-        Ok(std::process::Output {
-            status,
-            stdout,
-            stderr,
-        })
+        let limit_exceeded = if budget.is_exceeded() {
+            budget.limit()
+        } else {
+            limits.exceeded(config, &status)
+        };
+
+        Ok((
+            std::process::Output {
+                status,
+                stdout,
+                stderr,
+            },
+            limit_exceeded,
+            unenforced,
+        ))
     };
 
     if let Some(timeout) = config.max_execution_time_secs {
@@ -309,6 +433,7 @@ async fn read_stream(
     stream: Option<impl tokio::io::AsyncRead + Unpin>,
     is_error: bool,
     tx: mpsc::UnboundedSender<(String, bool)>,
+    budget: Arc<OutputBudget>,
 ) -> Result<Vec<u8>> {
     let Some(stream) = stream else {
         return Ok(Vec::new());
@@ -318,16 +443,25 @@ async fn read_stream(
     let mut all = Vec::new();
     loop {
         let mut buf = Vec::new();
-        let read = reader
+        // Long lines are split so an output limit can stop them early
+        let read = (&mut reader)
+            .take(MAX_CHUNK_BYTES)
             .read_until(b'\n', &mut buf)
             .await
             .map_err(|e| SandboxError::ExecutionFailed(e.to_string()))?;
         if read == 0 {
             break;
         }
+        let kept = budget.claim(buf.len());
+        buf.truncate(kept);
         all.extend_from_slice(&buf);
-        let chunk = String::from_utf8_lossy(&buf).into_owned();
-        let _ = tx.send((chunk, is_error));
+        if !buf.is_empty() {
+            let chunk = String::from_utf8_lossy(&buf).into_owned();
+            let _ = tx.send((chunk, is_error));
+        }
+        if budget.is_exceeded() {
+            break;
+        }
     }
     Ok(all)
 }
@@ -340,8 +474,7 @@ fn bpush(args: &mut Vec<String>, flag: &str, val: impl Into<String>) {
 
 #[cfg(test)]
 mod tests {
-    use super::{build_sandbox_exec_profile, escape_sandbox_string, existing_mount_path};
-    use crate::policy::SandboxConfig;
+    use super::*;
     use std::path::Path;
 
     #[test]
@@ -394,4 +527,165 @@ mod tests {
 
         assert!(profile.contains("(allow network*)"));
     }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn unavailable_backend_preference_fails_closed() {
+        assert_eq!(
+            select_backend(Some(SandboxBackend::SandboxExec)),
+            SandboxBackend::None
+        );
+        assert_eq!(select_backend(None), detect_backend());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn bwrap_and_landlock_follow_readonly_home_alike() {
+        let Ok(home) = std::env::var("HOME") else {
+            return;
+        };
+        let home = PathBuf::from(home);
+        let request = SandboxRequest::new("true", &[]).expect("request");
+
+        for readonly_home in [true, false] {
+            let config = SandboxConfig {
+                enabled: true,
+                readonly_home,
+                ..SandboxConfig::default()
+            };
+            let args = bwrap_args(&config, &request);
+            let binds = |flag: &str| {
+                args.windows(2)
+                    .filter(|pair| pair[0] == flag)
+                    .map(|pair| PathBuf::from(&pair[1]))
+                    .collect::<Vec<_>>()
+            };
+            let (landlock_reads, landlock_writes) =
+                crate::linux::ruleset_paths(&config, &request.working_dir);
+
+            assert_eq!(binds("--bind").contains(&home), !readonly_home);
+            assert_eq!(landlock_writes.contains(&home), !readonly_home);
+            assert_eq!(binds("--ro-bind").contains(&home), readonly_home);
+            assert_eq!(landlock_reads.contains(&home), readonly_home);
+            for path in binds("--bind") {
+                assert!(landlock_writes.contains(&path), "{}", path.display());
+            }
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    fn run_landlocked(
+        config: &SandboxConfig,
+        command: &str,
+        args: &[&str],
+    ) -> std::process::Output {
+        let request = SandboxRequest::new(command, args).expect("request");
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        runtime
+            .block_on(execute_with_backend_streaming(
+                SandboxBackend::Landlock,
+                config,
+                &request,
+                |_chunk, _is_error| {},
+            ))
+            .expect("landlocked command")
+            .0
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn landlock_backend_limits_writes_to_writable_dirs() {
+        if !is_backend_available(SandboxBackend::Landlock) {
+            return;
+        }
+        let temp = tempfile::tempdir().expect("tempdir");
+        let readonly = temp.path().join("readonly");
+        let writable = temp.path().join("writable");
+        std::fs::create_dir_all(&readonly).expect("readonly");
+        std::fs::create_dir_all(&writable).expect("writable");
+        std::fs::write(readonly.join("notes.txt"), "notes").expect("notes");
+        let config = SandboxConfig {
+            enabled: true,
+            allowed_dirs: vec![readonly.clone()],
+            writable_dirs: vec![writable.clone()],
+            ..SandboxConfig::default()
+        };
+
+        let read = run_landlocked(
+            &config,
+            "cat",
+            &[&readonly.join("notes.txt").display().to_string()],
+        );
+        let blocked = run_landlocked(
+            &config,
+            "touch",
+            &[&readonly.join("new.txt").display().to_string()],
+        );
+        let written = run_landlocked(
+            &config,
+            "touch",
+            &[&writable.join("new.txt").display().to_string()],
+        );
+
+        assert_eq!(String::from_utf8_lossy(&read.stdout), "notes");
+        assert!(!blocked.status.success());
+        assert!(!readonly.join("new.txt").exists());
+        assert!(written.status.success());
+        assert!(writable.join("new.txt").exists());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn landlock_backend_hides_dirs_outside_the_policy() {
+        if !is_backend_available(SandboxBackend::Landlock) {
+            return;
+        }
+        let outside = tempfile::tempdir().expect("outside");
+        std::fs::write(outside.path().join("secret.txt"), "secret").expect("secret");
+
+        let output = run_landlocked(
+            &SandboxConfig {
+                enabled: true,
+                ..SandboxConfig::default()
+            },
+            "cat",
+            &[&outside.path().join("secret.txt").display().to_string()],
+        );
+
+        assert!(!output.status.success());
+        assert!(output.stdout.is_empty());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn landlock_backend_refuses_sockets_without_network_access() {
+        if !is_backend_available(SandboxBackend::Landlock) || !Path::new("/bin/bash").exists() {
+            return;
+        }
+        let probe = ["-c", "exec 3<>/dev/tcp/127.0.0.1/9"];
+
+        let offline = run_landlocked(
+            &SandboxConfig {
+                enabled: true,
+                ..SandboxConfig::default()
+            },
+            "/bin/bash",
+            &probe,
+        );
+        let online = run_landlocked(
+            &SandboxConfig {
+                enabled: true,
+                network_access: true,
+                ..SandboxConfig::default()
+            },
+            "/bin/bash",
+            &probe,
+        );
+
+        assert!(String::from_utf8_lossy(&offline.stderr).contains("Permission denied"));
+        assert!(!String::from_utf8_lossy(&online.stderr).contains("Permission denied"));
+    }
 }
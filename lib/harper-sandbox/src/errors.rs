@@ -29,6 +29,16 @@ pub enum SandboxError {
     NetworkBlocked,
     #[error("Command timed out after {timeout_secs} seconds")]
     Timeout { timeout_secs: u64 },
+    #[error("Command exceeded the {limit_mb} MB memory limit")]
+    MemoryLimitExceeded { limit_mb: u64 },
+    #[error("Command exceeded the {limit_secs} second CPU time limit")]
+    CpuTimeLimitExceeded { limit_secs: u64 },
+    #[error("Command exceeded the limit of {limit} processes")]
+    ProcessLimitExceeded { limit: u64 },
+    #[error("Command output exceeded the {limit_bytes} byte capture limit")]
+    OutputLimitExceeded { limit_bytes: u64 },
+    #[error("Shell syntax error: {0}")]
+    ShellSyntax(String),
     #[error("Configuration error: {0}")]
     ConfigError(String),
     #[error("IO error: {0}")]
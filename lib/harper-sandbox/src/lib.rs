@@ -14,12 +14,17 @@
 
 mod backend;
 mod errors;
+mod limits;
+#[cfg(target_os = "linux")]
+mod linux;
 mod policy;
 mod request;
+pub mod shell;
 
 pub use backend::SandboxBackend;
 pub use errors::{Result, SandboxError};
-pub use policy::SandboxConfig;
+pub use limits::ResourceLimit;
+pub use policy::{command_blocked_by, command_matches, wildcard_match, SandboxConfig};
 pub use request::{SandboxExecutionResult, SandboxRequest};
 
 /// Sandbox execution environment for secure command running
@@ -35,7 +40,7 @@ impl Sandbox {
     /// Create a new sandbox with the given configuration
     #[must_use]
     pub fn new(config: SandboxConfig) -> Self {
-        let backend = backend::detect_backend();
+        let backend = backend::select_backend(config.backend);
         Self { config, backend }
     }
 
@@ -80,13 +85,13 @@ impl Sandbox {
             SandboxBackend::None
         } else if self.backend == SandboxBackend::None {
             return Err(SandboxError::BackendUnavailable(
-                "sandbox is enabled but no supported backend is available".to_string(),
+                "sandbox is enabled but no supported backend is available (install bubblewrap or use a kernel with Landlock)".to_string(),
             ));
         } else {
             self.backend
         };
 
-        let output =
+        let (output, limit_exceeded, unenforced_limits) =
             backend::execute_with_backend_streaming(backend, &self.config, &request, on_output)
                 .await?;
 
@@ -94,6 +99,8 @@ impl Sandbox {
             request,
             backend,
             output,
+            limit_exceeded,
+            unenforced_limits,
         })
     }
 
@@ -119,7 +126,12 @@ impl Sandbox {
     }
 
     pub fn validate_request(&self, request: &SandboxRequest) -> Result<()> {
-        self.validate_command(&request.command)?;
+        // Requests run without a shell, so the arguments are checked as given
+        let command_line = std::iter::once(request.command.as_str())
+            .chain(request.args.iter().map(String::as_str))
+            .collect::<Vec<_>>()
+            .join(" ");
+        policy::validate_program(&self.config, &command_line)?;
         self.validate_working_dir(&request.working_dir)?;
         policy::validate_network_request(&self.config, request.requires_network)?;
 
@@ -172,7 +184,10 @@ mod tests {
         let sandbox = Sandbox::new(config);
         assert!(matches!(
             sandbox.backend,
-            SandboxBackend::Bubblewrap | SandboxBackend::SandboxExec | SandboxBackend::None
+            SandboxBackend::Bubblewrap
+                | SandboxBackend::SandboxExec
+                | SandboxBackend::Landlock
+                | SandboxBackend::None
         ));
     }
 
@@ -185,6 +200,7 @@ mod tests {
         match sandbox.backend {
             SandboxBackend::Bubblewrap => assert_eq!(name, "bubblewrap (bwrap)"),
             SandboxBackend::SandboxExec => assert_eq!(name, "sandbox-exec (macOS)"),
+            SandboxBackend::Landlock => assert_eq!(name, "landlock + seccomp"),
             SandboxBackend::None => assert_eq!(name, "none"),
         }
     }
@@ -260,6 +276,74 @@ mod tests {
         assert!(!sandbox.is_command_allowed("rm -rf /"));
     }
 
+    #[test]
+    fn test_every_program_in_a_command_line_is_checked() {
+        let config = SandboxConfig {
+            allowed_commands: Some(vec!["cat".to_string(), "grep".to_string()]),
+            blocked_commands: Some(vec!["sh".to_string()]),
+            ..Default::default()
+        };
+        let sandbox = Sandbox::new(config);
+
+        assert!(sandbox.is_command_allowed("cat notes.txt | grep todo"));
+        assert!(!sandbox.is_command_allowed("cat a | sh"));
+        assert!(!sandbox.is_command_allowed("cat $(curl -s example.com)"));
+        assert!(!sandbox.is_command_allowed("cat 'unterminated"));
+        assert!(matches!(
+            sandbox.validate_command("grep x && sh -c ls"),
+            Err(SandboxError::CommandBlocked { command }) if command == "sh"
+        ));
+    }
+
+    #[test]
+    fn test_command_patterns_match_arguments() {
+        let config = SandboxConfig {
+            allowed_commands: Some(vec!["cargo test *".to_string(), "git status".to_string()]),
+            blocked_commands: Some(vec!["git push --force*".to_string()]),
+            ..Default::default()
+        };
+        let sandbox = Sandbox::new(config);
+
+        assert!(sandbox.is_command_allowed("cargo test"));
+        assert!(sandbox.is_command_allowed("cargo  test --workspace"));
+        assert!(!sandbox.is_command_allowed("cargo build"));
+        assert!(sandbox.is_command_allowed("git status --short"));
+        assert!(!sandbox.is_command_allowed("git statusx"));
+
+        assert!(command_matches("git push --force*", "git push --force"));
+        assert!(command_blocked_by(
+            "git push --force*",
+            "/usr/bin/git push --force-with-lease origin main"
+        ));
+        assert!(!command_matches(
+            "git push --force*",
+            "/usr/bin/git push --force-with-lease origin main"
+        ));
+        assert!(!command_matches("git", "./git status"));
+        assert!(!command_matches("git", "/tmp/x/git"));
+        assert!(command_blocked_by("rm", "./rm -rf target"));
+        assert!(!command_matches(
+            "git push --force*",
+            "git push origin main"
+        ));
+        assert!(command_matches("npm run ?est", "npm run test"));
+        assert!(!command_matches("", "ls"));
+    }
+
+    #[test]
+    fn test_wildcard_match() {
+        assert!(wildcard_match("git_*", "git_status"));
+        assert!(wildcard_match("mcp__*", "mcp__docs__search"));
+        assert!(wildcard_match("mcp__*__search", "mcp__docs__search"));
+        assert!(wildcard_match("git_?iff", "git_diff"));
+        assert!(wildcard_match("*", "read_file"));
+        assert!(wildcard_match("*a", "*ba"));
+        assert!(!wildcard_match("git_*", "list_changed_files"));
+        assert!(!wildcard_match("read_file", "read_files"));
+        assert!(!wildcard_match("read_file", "Read_File"));
+        assert!(!wildcard_match("mcp__*__search", "mcp__docs__list"));
+    }
+
     #[test]
     fn test_validate_requested_paths_blocks_traversal_outside_allowed_dir() {
         let temp_dir = tempfile::tempdir().unwrap();
@@ -496,6 +580,75 @@ mod tests {
         assert!(matches!(result, Err(SandboxError::BackendUnavailable(_))));
     }
 
+    #[test]
+    fn test_output_limit_kills_command_and_keeps_captured_bytes() {
+        let sandbox = Sandbox::new(SandboxConfig {
+            max_output_bytes: Some(1000),
+            ..Default::default()
+        });
+        let request = SandboxRequest::new("yes", &[]).unwrap();
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let result = runtime.block_on(sandbox.execute_request(request)).unwrap();
+
+        assert_eq!(
+            result.limit_exceeded,
+            Some(ResourceLimit::Output { limit_bytes: 1000 })
+        );
+        assert_eq!(result.output.stdout.len(), 1000);
+        assert!(matches!(
+            result.limit_error(),
+            Some(SandboxError::OutputLimitExceeded { limit_bytes: 1000 })
+        ));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_cpu_time_limit_is_reported() {
+        let sandbox = Sandbox::new(SandboxConfig {
+            max_cpu_time_secs: Some(1),
+            max_execution_time_secs: Some(20),
+            ..Default::default()
+        });
+        let request = SandboxRequest::new("sh", &["-c", "while :; do :; done"]).unwrap();
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let result = runtime.block_on(sandbox.execute_request(request)).unwrap();
+
+        assert!(!result.output.status.success());
+        assert_eq!(
+            result.limit_exceeded,
+            Some(ResourceLimit::CpuTime { limit_secs: 1 })
+        );
+    }
+
+    #[test]
+    fn test_commands_within_limits_report_none() {
+        let sandbox = Sandbox::new(SandboxConfig {
+            max_output_bytes: Some(1000),
+            max_open_files: Some(256),
+            max_cpu_time_secs: Some(5),
+            ..Default::default()
+        });
+        let request = SandboxRequest::new("echo", &["hello"]).unwrap();
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let result = runtime.block_on(sandbox.execute_request(request)).unwrap();
+
+        assert!(result.output.status.success());
+        assert_eq!(result.limit_exceeded, None);
+        assert!(result.limit_error().is_none());
+    }
+
     #[test]
     fn test_execute_request_streaming_emits_chunks() {
         let sandbox = Sandbox::new(SandboxConfig::default());
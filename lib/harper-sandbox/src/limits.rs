@@ -0,0 +1,555 @@
+// Copyright 2026 harpertoken
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Resource limits for sandboxed commands.
+//!
+//! Memory and process limits need a cgroup v2 child group, so they are only
+//! enforced when the current cgroup delegates the `memory` and `pids`
+//! controllers. The rlimit alternatives would cap address space, which breaks
+//! runtimes that reserve large ranges, and count every process the user owns.
+//! CPU time and open files use rlimits, and the cgroup, when there is one,
+//! measures the CPU time a killed command used. Captured output is counted by
+//! the executor, which kills the command once the budget is spent.
+//!
+//! The open file cap is never reported as the limit a command hit: the call
+//! that runs into it fails with `EMFILE` inside the command, and nothing
+//! outside the command counts it. Memory and process caps that could not be
+//! applied are returned with the result so callers can say so.
+
+use crate::errors::SandboxError;
+use crate::policy::SandboxConfig;
+use serde::{Deserialize, Serialize};
+use std::process::ExitStatus;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use tokio::sync::Notify;
+
+/// The configured limit a command ran into
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResourceLimit {
+    Memory { limit_mb: u64 },
+    CpuTime { limit_secs: u64 },
+    Processes { limit: u64 },
+    Output { limit_bytes: u64 },
+}
+
+impl From<ResourceLimit> for SandboxError {
+    fn from(limit: ResourceLimit) -> Self {
+        match limit {
+            ResourceLimit::Memory { limit_mb } => SandboxError::MemoryLimitExceeded { limit_mb },
+            ResourceLimit::CpuTime { limit_secs } => {
+                SandboxError::CpuTimeLimitExceeded { limit_secs }
+            }
+            ResourceLimit::Processes { limit } => SandboxError::ProcessLimitExceeded { limit },
+            ResourceLimit::Output { limit_bytes } => {
+                SandboxError::OutputLimitExceeded { limit_bytes }
+            }
+        }
+    }
+}
+
+/// Shared count of captured stdout and stderr bytes
+pub(crate) struct OutputBudget {
+    limit: Option<u64>,
+    used: AtomicU64,
+    exceeded: AtomicBool,
+    notify: Notify,
+}
+
+impl OutputBudget {
+    pub(crate) fn new(limit: Option<u64>) -> Self {
+        Self {
+            limit,
+            used: AtomicU64::new(0),
+            exceeded: AtomicBool::new(false),
+            notify: Notify::new(),
+        }
+    }
+
+    /// Claims room for `len` bytes and returns how many of them may be kept
+    pub(crate) fn claim(&self, len: usize) -> usize {
+        let Some(limit) = self.limit else {
+            return len;
+        };
+        let before = self.used.fetch_add(len as u64, Ordering::SeqCst);
+        let room = limit.saturating_sub(before);
+        if room < len as u64 {
+            self.exceeded.store(true, Ordering::SeqCst);
+            self.notify.notify_one();
+            room as usize
+        } else {
+            len
+        }
+    }
+
+    pub(crate) fn is_exceeded(&self) -> bool {
+        self.exceeded.load(Ordering::SeqCst)
+    }
+
+    /// Resolves once more output arrived than the budget allows
+    pub(crate) async fn exhausted(&self) {
+        if self.limit.is_none() {
+            std::future::pending::<()>().await;
+        }
+        self.notify.notified().await;
+    }
+
+    pub(crate) fn limit(&self) -> Option<ResourceLimit> {
+        self.limit
+            .map(|limit_bytes| ResourceLimit::Output { limit_bytes })
+    }
+}
+
+/// Limits prepared in the parent and applied to the child before `exec`
+pub(crate) struct ProcessLimits {
+    cpu_time_secs: Option<u64>,
+    open_files: Option<u64>,
+    /// Configured caps this host cannot apply
+    unenforced: Vec<ResourceLimit>,
+    #[cfg(target_os = "linux")]
+    cgroup: Option<cgroup::Cgroup>,
+}
+
+impl ProcessLimits {
+    pub(crate) fn prepare(config: &SandboxConfig) -> Self {
+        #[cfg(target_os = "linux")]
+        let cgroup = cgroup::Cgroup::create(config);
+        #[cfg(target_os = "linux")]
+        let cgroup_enforces = cgroup.is_some();
+        #[cfg(not(target_os = "linux"))]
+        let cgroup_enforces = false;
+        let mut unenforced = Vec::new();
+        if !cgroup_enforces {
+            if let Some(limit_mb) = config.max_memory_mb {
+                unenforced.push(ResourceLimit::Memory { limit_mb });
+            }
+            if let Some(limit) = config.max_processes {
+                unenforced.push(ResourceLimit::Processes { limit });
+            }
+        }
+        if !unenforced.is_empty() {
+            log::warn!(
+                "max_memory_mb and max_processes need a delegated cgroup v2 memory and pids \
+                 controller and are not enforced on this host"
+            );
+        }
+
+        Self {
+            cpu_time_secs: config.max_cpu_time_secs,
+            open_files: config.max_open_files,
+            unenforced,
+            #[cfg(target_os = "linux")]
+            cgroup,
+        }
+    }
+
+    /// Installs the limits on a command that has not been spawned yet
+    pub(crate) fn install(&self, cmd: &mut tokio::process::Command) {
+        #[cfg(unix)]
+        {
+            let Self {
+                cpu_time_secs,
+                open_files,
+                ..
+            } = *self;
+            #[cfg(target_os = "linux")]
+            let procs = self.cgroup.as_ref().map(cgroup::Cgroup::procs_fd);
+            // SAFETY: the hook only issues system calls on values copied above
+            unsafe {
+                cmd.pre_exec(move || {
+                    #[cfg(target_os = "linux")]
+                    if let Some(fd) = procs {
+                        cgroup::enter(fd)?;
+                    }
+                    let set = |resource, soft: u64, hard: u64| {
+                        let limit = libc::rlimit {
+                            rlim_cur: soft as libc::rlim_t,
+                            rlim_max: hard as libc::rlim_t,
+                        };
+                        if libc::setrlimit(resource, &limit) != 0 {
+                            return Err(std::io::Error::last_os_error());
+                        }
+                        Ok(())
+                    };
+                    if let Some(secs) = cpu_time_secs {
+                        // SIGXCPU at the soft limit, SIGKILL a second later
+                        set(libc::RLIMIT_CPU, secs, secs.saturating_add(1))?;
+                    }
+                    if let Some(files) = open_files {
+                        set(libc::RLIMIT_NOFILE, files, files)?;
+                    }
+                    Ok(())
+                });
+            }
+        }
+        #[cfg(not(unix))]
+        let _ = cmd;
+    }
+
+    pub(crate) fn unenforced(&self) -> Vec<ResourceLimit> {
+        self.unenforced.clone()
+    }
+
+    /// Works out which limit, if any, ended the command
+    pub(crate) fn exceeded(
+        &self,
+        config: &SandboxConfig,
+        status: &ExitStatus,
+    ) -> Option<ResourceLimit> {
+        #[cfg(target_os = "linux")]
+        if let Some(limit) = self
+            .cgroup
+            .as_ref()
+            .and_then(|cgroup| cgroup.exceeded(config))
+        {
+            return Some(limit);
+        }
+        #[cfg(target_os = "linux")]
+        let cpu_limit_reached = self
+            .cgroup
+            .as_ref()
+            .is_some_and(|cgroup| cgroup.cpu_limit_reached(config));
+        #[cfg(not(target_os = "linux"))]
+        let cpu_limit_reached = false;
+        classify_exit(config, status, cpu_limit_reached)
+    }
+}
+
+/// Matches an exit against the CPU time rlimit.
+///
+/// A CPU limit is signalled directly: SIGXCPU at the soft limit, then SIGKILL
+/// for commands that ignore it. Anything can send SIGKILL, so it only counts
+/// when `cpu_limit_reached` says the measured CPU time got to the limit. Other
+/// limits are only reported when the cgroup counted them, never guessed from
+/// what the command printed.
+fn classify_exit(
+    config: &SandboxConfig,
+    status: &ExitStatus,
+    cpu_limit_reached: bool,
+) -> Option<ResourceLimit> {
+    if status.success() {
+        return None;
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+        if let (Some(limit_secs), Some(signal)) = (config.max_cpu_time_secs, status.signal()) {
+            if signal == libc::SIGXCPU || (signal == libc::SIGKILL && cpu_limit_reached) {
+                return Some(ResourceLimit::CpuTime { limit_secs });
+            }
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = (config, cpu_limit_reached);
+    None
+}
+
+#[cfg(target_os = "linux")]
+mod cgroup {
+    use super::ResourceLimit;
+    use crate::policy::SandboxConfig;
+    use std::fs::{self, File, OpenOptions};
+    use std::os::fd::{AsRawFd, RawFd};
+    use std::path::{Path, PathBuf};
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    const CGROUP_ROOT: &str = "/sys/fs/cgroup";
+
+    static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+    /// A child of the current cgroup holding one command
+    pub(super) struct Cgroup {
+        path: PathBuf,
+        procs: File,
+    }
+
+    impl Cgroup {
+        /// Creates the group, or returns `None` when the controllers are not delegated.
+        ///
+        /// A CPU limit needs no controller: `cpu.stat` is kept for every group.
+        pub(super) fn create(config: &SandboxConfig) -> Option<Self> {
+            if config.max_memory_mb.is_none()
+                && config.max_processes.is_none()
+                && config.max_cpu_time_secs.is_none()
+            {
+                return None;
+            }
+            let membership = fs::read_to_string("/proc/self/cgroup").ok()?;
+            let current = membership
+                .lines()
+                .find_map(|line| line.strip_prefix("0::"))?;
+            let parent = Path::new(CGROUP_ROOT).join(current.trim_start_matches('/'));
+            let controllers = fs::read_to_string(parent.join("cgroup.subtree_control")).ok()?;
+            let delegated = |name: &str| controllers.split_whitespace().any(|c| c == name);
+            if (config.max_memory_mb.is_some() && !delegated("memory"))
+                || (config.max_processes.is_some() && !delegated("pids"))
+            {
+                return None;
+            }
+
+            let path = parent.join(format!(
+                "harper-sandbox-{}-{}",
+                std::process::id(),
+                NEXT_ID.fetch_add(1, Ordering::Relaxed)
+            ));
+            fs::create_dir(&path).ok()?;
+            let configured = (|| {
+                if let Some(mb) = config.max_memory_mb {
+                    fs::write(
+                        path.join("memory.max"),
+                        mb.saturating_mul(1024 * 1024).to_string(),
+                    )?;
+                    // Swap would let the command outgrow the memory limit
+                    let _ = fs::write(path.join("memory.swap.max"), "0");
+                }
+                if let Some(processes) = config.max_processes {
+                    fs::write(path.join("pids.max"), processes.to_string())?;
+                }
+                OpenOptions::new()
+                    .write(true)
+                    .open(path.join("cgroup.procs"))
+            })();
+            match configured {
+                Ok(procs) => Some(Self { path, procs }),
+                Err(e) => {
+                    log::debug!("cgroup limits unavailable, using rlimits: {}", e);
+                    let _ = fs::remove_dir(&path);
+                    None
+                }
+            }
+        }
+
+        pub(super) fn procs_fd(&self) -> RawFd {
+            self.procs.as_raw_fd()
+        }
+
+        pub(super) fn exceeded(&self, config: &SandboxConfig) -> Option<ResourceLimit> {
+            if let Some(limit_mb) = config.max_memory_mb {
+                if keyed_value(&self.path.join("memory.events"), "oom_kill") > 0 {
+                    return Some(ResourceLimit::Memory { limit_mb });
+                }
+            }
+            if let Some(limit) = config.max_processes {
+                if keyed_value(&self.path.join("pids.events"), "max") > 0 {
+                    return Some(ResourceLimit::Processes { limit });
+                }
+            }
+            None
+        }
+
+        /// Whether the group's processes used up the configured CPU time
+        pub(super) fn cpu_limit_reached(&self, config: &SandboxConfig) -> bool {
+            config.max_cpu_time_secs.is_some_and(|secs| {
+                keyed_value(&self.path.join("cpu.stat"), "usage_usec")
+                    >= secs.saturating_mul(1_000_000)
+            })
+        }
+    }
+
+    impl Drop for Cgroup {
+        fn drop(&mut self) {
+            // Stray background processes would keep the group alive
+            let _ = fs::write(self.path.join("cgroup.kill"), "1");
+            if let Err(e) = fs::remove_dir(&self.path) {
+                log::debug!("failed to remove {}: {}", self.path.display(), e);
+            }
+        }
+    }
+
+    /// Moves the calling process into the group; runs in the pre-exec hook
+    pub(super) fn enter(procs: RawFd) -> std::io::Result<()> {
+        // SAFETY: writes a static buffer to a descriptor owned by the parent's Cgroup
+        if unsafe { libc::write(procs, b"0".as_ptr().cast(), 1) } < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    /// A value from a flat keyed file such as `memory.events` or `cpu.stat`
+    pub(super) fn keyed_value(path: &Path, key: &str) -> u64 {
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|entries| {
+                entries.lines().find_map(|line| {
+                    let (name, value) = line.split_once(' ')?;
+                    if name == key {
+                        value.trim().parse().ok()
+                    } else {
+                        None
+                    }
+                })
+            })
+            .unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    fn exit_status(raw: i32) -> ExitStatus {
+        use std::os::unix::process::ExitStatusExt;
+        ExitStatus::from_raw(raw)
+    }
+
+    #[test]
+    fn output_budget_keeps_bytes_up_to_the_limit() {
+        let budget = OutputBudget::new(Some(10));
+
+        assert_eq!(budget.claim(6), 6);
+        assert!(!budget.is_exceeded());
+        assert_eq!(budget.claim(6), 4);
+        assert!(budget.is_exceeded());
+        assert_eq!(budget.claim(6), 0);
+    }
+
+    #[test]
+    fn output_budget_without_limit_keeps_everything() {
+        let budget = OutputBudget::new(None);
+
+        assert_eq!(budget.claim(usize::MAX), usize::MAX);
+        assert!(!budget.is_exceeded());
+        assert_eq!(budget.limit(), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn exits_are_matched_to_configured_limits() {
+        let config = SandboxConfig {
+            max_memory_mb: Some(256),
+            max_cpu_time_secs: Some(5),
+            max_processes: Some(64),
+            max_open_files: Some(128),
+            ..SandboxConfig::default()
+        };
+
+        assert_eq!(
+            classify_exit(&config, &exit_status(libc::SIGXCPU), false),
+            Some(ResourceLimit::CpuTime { limit_secs: 5 })
+        );
+        assert_eq!(
+            classify_exit(&config, &exit_status(libc::SIGKILL), true),
+            Some(ResourceLimit::CpuTime { limit_secs: 5 })
+        );
+        assert_eq!(
+            classify_exit(&config, &exit_status(libc::SIGKILL), false),
+            None
+        );
+        // A failure is not blamed on a limit the cgroup did not count
+        assert_eq!(classify_exit(&config, &exit_status(1 << 8), false), None);
+        assert_eq!(classify_exit(&config, &exit_status(0), true), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn unset_limits_are_never_reported() {
+        assert_eq!(
+            classify_exit(&SandboxConfig::default(), &exit_status(libc::SIGKILL), true),
+            None
+        );
+        assert_eq!(
+            classify_exit(
+                &SandboxConfig::default(),
+                &exit_status(libc::SIGXCPU),
+                false
+            ),
+            None
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn limit_looking_errors_are_not_reported_as_limits() {
+        let config = SandboxConfig {
+            max_memory_mb: Some(256),
+            max_processes: Some(64),
+            max_open_files: Some(128),
+            ..SandboxConfig::default()
+        };
+        let limits = ProcessLimits::prepare(&config);
+        let mut cmd = tokio::process::Command::new("sh");
+        cmd.args([
+            "-c",
+            "echo 'fork: Resource temporarily unavailable; out of memory' >&2; exit 1",
+        ])
+        .stderr(std::process::Stdio::null());
+        limits.install(&mut cmd);
+        let status = cmd.status().await.expect("run sh");
+
+        assert!(!status.success());
+        assert_eq!(limits.exceeded(&config, &status), None);
+    }
+
+    #[test]
+    fn caps_without_a_cgroup_are_reported_as_unenforced() {
+        let config = SandboxConfig {
+            max_memory_mb: Some(256),
+            max_processes: Some(64),
+            max_open_files: Some(128),
+            ..SandboxConfig::default()
+        };
+        let limits = ProcessLimits::prepare(&config);
+        #[cfg(target_os = "linux")]
+        let enforced = limits.cgroup.is_some();
+        #[cfg(not(target_os = "linux"))]
+        let enforced = false;
+
+        if enforced {
+            assert!(limits.unenforced().is_empty());
+        } else {
+            assert_eq!(
+                limits.unenforced(),
+                vec![
+                    ResourceLimit::Memory { limit_mb: 256 },
+                    ResourceLimit::Processes { limit: 64 }
+                ]
+            );
+        }
+        assert!(ProcessLimits::prepare(&SandboxConfig::default())
+            .unenforced()
+            .is_empty());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn cpu_usage_is_read_from_cpu_stat() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let stat = dir.path().join("cpu.stat");
+        std::fs::write(&stat, "usage_usec 5000123\nuser_usec 4000000\n").expect("cpu.stat");
+
+        assert_eq!(cgroup::keyed_value(&stat, "usage_usec"), 5_000_123);
+        assert_eq!(cgroup::keyed_value(&stat, "nr_throttled"), 0);
+        assert_eq!(
+            cgroup::keyed_value(&dir.path().join("missing"), "usage_usec"),
+            0
+        );
+    }
+
+    #[test]
+    fn limits_map_to_distinct_errors() {
+        assert!(matches!(
+            SandboxError::from(ResourceLimit::Memory { limit_mb: 1 }),
+            SandboxError::MemoryLimitExceeded { limit_mb: 1 }
+        ));
+        assert!(matches!(
+            SandboxError::from(ResourceLimit::Output { limit_bytes: 2 }),
+            SandboxError::OutputLimitExceeded { limit_bytes: 2 }
+        ));
+        assert_eq!(
+            SandboxError::from(ResourceLimit::Processes { limit: 3 }).to_string(),
+            "Command exceeded the limit of 3 processes"
+        );
+    }
+}
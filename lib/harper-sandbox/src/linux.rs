@@ -0,0 +1,193 @@
+// Copyright 2026 harpertoken
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Landlock and seccomp restrictions for hosts without bubblewrap.
+//!
+//! Everything is built in the parent. The child only applies the prepared
+//! ruleset and filter between `fork` and `exec`, which needs no allocation.
+
+use crate::errors::{Result, SandboxError};
+use crate::policy::SandboxConfig;
+use landlock::{
+    path_beneath_rules, Access, AccessFs, CompatLevel, Compatible, Ruleset, RulesetAttr,
+    RulesetCreated, RulesetCreatedAttr, RulesetStatus, ABI,
+};
+use seccompiler::{
+    BpfProgram, SeccompAction, SeccompCmpArgLen, SeccompCmpOp, SeccompCondition, SeccompFilter,
+    SeccompRule,
+};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Newest ABI whose rights are requested; older kernels enforce what they know
+const TARGET_ABI: ABI = ABI::V5;
+
+const SYSTEM_READ_PATHS: &[&str] = &[
+    "/usr",
+    "/bin",
+    "/sbin",
+    "/lib",
+    "/lib64",
+    "/etc/ld.so.cache",
+    "/etc/alternatives",
+    "/dev/urandom",
+];
+
+const SYSTEM_WRITE_PATHS: &[&str] = &["/dev/null"];
+
+/// Socket families refused when network access is off
+const BLOCKED_SOCKET_FAMILIES: &[libc::c_int] = &[libc::AF_INET, libc::AF_INET6, libc::AF_PACKET];
+
+/// Whether the running kernel enforces Landlock at all
+pub(crate) fn landlock_supported() -> bool {
+    Ruleset::default()
+        .set_compatibility(CompatLevel::HardRequirement)
+        .handle_access(AccessFs::from_all(ABI::V1))
+        .and_then(|ruleset| ruleset.create())
+        .is_ok()
+}
+
+/// Restrictions for one command, ready to apply in the child
+pub(crate) struct Restrictions {
+    ruleset: Option<RulesetCreated>,
+    network_filter: Option<BpfProgram>,
+}
+
+impl Restrictions {
+    /// Applies the ruleset and filter to the calling process.
+    ///
+    /// Runs in the pre-exec hook, so it only makes system calls and reports
+    /// failures as raw OS errors.
+    pub(crate) fn apply(&mut self) -> std::io::Result<()> {
+        if let Some(ruleset) = self.ruleset.take() {
+            let status = ruleset
+                .restrict_self()
+                .map_err(|_| std::io::Error::from_raw_os_error(libc::EPERM))?;
+            if status.ruleset == RulesetStatus::NotEnforced {
+                return Err(std::io::Error::from_raw_os_error(libc::ENOSYS));
+            }
+        }
+        if let Some(filter) = &self.network_filter {
+            seccompiler::apply_filter(filter)
+                .map_err(|_| std::io::Error::from_raw_os_error(libc::EPERM))?;
+        }
+        Ok(())
+    }
+}
+
+/// Paths the Landlock ruleset lets a command read, and read and write
+pub(crate) fn ruleset_paths(
+    config: &SandboxConfig,
+    working_dir: &Path,
+) -> (Vec<PathBuf>, Vec<PathBuf>) {
+    let (granted_reads, granted_writes) = crate::backend::filesystem_paths(config, working_dir);
+    let mut read_paths: Vec<PathBuf> = SYSTEM_READ_PATHS.iter().map(PathBuf::from).collect();
+    let mut write_paths: Vec<PathBuf> = SYSTEM_WRITE_PATHS.iter().map(PathBuf::from).collect();
+    read_paths.extend(granted_reads);
+    write_paths.extend(granted_writes);
+    (read_paths, write_paths)
+}
+
+/// Builds the Landlock ruleset and network filter for a command
+pub(crate) fn prepare(config: &SandboxConfig, working_dir: &Path) -> Result<Restrictions> {
+    let (read_paths, write_paths) = ruleset_paths(config, working_dir);
+
+    let ruleset = Ruleset::default()
+        .handle_access(AccessFs::from_all(TARGET_ABI))
+        .and_then(|ruleset| ruleset.create())
+        .and_then(|ruleset| {
+            ruleset.add_rules(path_beneath_rules(
+                existing(&read_paths),
+                AccessFs::from_read(TARGET_ABI),
+            ))
+        })
+        .and_then(|ruleset| {
+            ruleset.add_rules(path_beneath_rules(
+                existing(&write_paths),
+                AccessFs::from_all(TARGET_ABI),
+            ))
+        })
+        .map_err(|e| SandboxError::BackendUnavailable(format!("landlock: {}", e)))?;
+
+    let network_filter = if config.network_access {
+        None
+    } else {
+        Some(network_filter()?)
+    };
+
+    Ok(Restrictions {
+        ruleset: Some(ruleset),
+        network_filter,
+    })
+}
+
+fn existing(paths: &[PathBuf]) -> Vec<&PathBuf> {
+    paths.iter().filter(|path| path.exists()).collect()
+}
+
+/// Seccomp filter that refuses IP and raw sockets with `EACCES`
+fn network_filter() -> Result<BpfProgram> {
+    let seccomp_error =
+        |e: seccompiler::BackendError| SandboxError::BackendUnavailable(format!("seccomp: {}", e));
+
+    let socket_rules = BLOCKED_SOCKET_FAMILIES
+        .iter()
+        .map(|family| {
+            SeccompCondition::new(0, SeccompCmpArgLen::Dword, SeccompCmpOp::Eq, *family as u64)
+                .and_then(|condition| SeccompRule::new(vec![condition]))
+        })
+        .collect::<std::result::Result<Vec<_>, _>>()
+        .map_err(seccomp_error)?;
+
+    let rules = BTreeMap::from([
+        (libc::SYS_socket, socket_rules),
+        // io_uring can open sockets without going through socket(2)
+        (libc::SYS_io_uring_setup, vec![]),
+    ]);
+    let arch = std::env::consts::ARCH.try_into().map_err(seccomp_error)?;
+    let filter = SeccompFilter::new(
+        rules,
+        SeccompAction::Allow,
+        SeccompAction::Errno(libc::EACCES as u32),
+        arch,
+    )
+    .map_err(seccomp_error)?;
+    BpfProgram::try_from(filter).map_err(seccomp_error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn network_filter_builds_for_host_arch() {
+        assert!(!network_filter().expect("network filter").is_empty());
+    }
+
+    #[test]
+    fn network_filter_is_skipped_when_network_is_allowed() {
+        if !landlock_supported() {
+            return;
+        }
+        let config = SandboxConfig {
+            network_access: true,
+            ..SandboxConfig::default()
+        };
+
+        let restrictions = prepare(&config, Path::new("/")).expect("restrictions");
+
+        assert!(restrictions.ruleset.is_some());
+        assert!(restrictions.network_filter.is_none());
+    }
+}
@@ -12,7 +12,9 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use crate::backend::SandboxBackend;
 use crate::errors::{Result, SandboxError};
+use crate::shell;
 use serde::{Deserialize, Serialize};
 use std::path::{Component, Path, PathBuf};
 
@@ -26,6 +28,23 @@ pub struct SandboxConfig {
     pub readonly_home: bool,
     pub network_access: bool,
     pub max_execution_time_secs: Option<u64>,
+    /// Memory cap for the command and its children
+    #[serde(default)]
+    pub max_memory_mb: Option<u64>,
+    /// CPU seconds before the command receives `SIGXCPU`
+    #[serde(default)]
+    pub max_cpu_time_secs: Option<u64>,
+    /// Process cap; without cgroup v2 this counts every process of the user
+    #[serde(default)]
+    pub max_processes: Option<u64>,
+    #[serde(default)]
+    pub max_open_files: Option<u64>,
+    /// Combined stdout and stderr bytes kept before the command is killed
+    #[serde(default)]
+    pub max_output_bytes: Option<u64>,
+    /// Backend to use instead of the detected one
+    #[serde(default)]
+    pub backend: Option<SandboxBackend>,
 }
 
 impl Default for SandboxConfig {
@@ -39,6 +58,12 @@ impl Default for SandboxConfig {
             readonly_home: true,
             network_access: false,
             max_execution_time_secs: Some(30),
+            max_memory_mb: None,
+            max_cpu_time_secs: None,
+            max_processes: None,
+            max_open_files: None,
+            max_output_bytes: None,
+            backend: None,
         }
     }
 }
@@ -71,6 +96,12 @@ pub fn from_env() -> SandboxConfig {
         .map(|v| v != "false")
         .unwrap_or(true);
 
+    let limit = |name: &str| std::env::var(name).ok().and_then(|v| v.parse().ok());
+
+    let backend = std::env::var("HARPER_SANDBOX_BACKEND")
+        .ok()
+        .and_then(|v| serde_json::from_value(serde_json::Value::String(v)).ok());
+
     SandboxConfig {
         enabled,
         allowed_dirs,
@@ -80,37 +111,135 @@ pub fn from_env() -> SandboxConfig {
         network_access,
         readonly_home,
         max_execution_time_secs: Some(30),
+        max_memory_mb: limit("HARPER_SANDBOX_MAX_MEMORY_MB"),
+        max_cpu_time_secs: limit("HARPER_SANDBOX_MAX_CPU_TIME_SECS"),
+        max_processes: limit("HARPER_SANDBOX_MAX_PROCESSES"),
+        max_open_files: limit("HARPER_SANDBOX_MAX_OPEN_FILES"),
+        max_output_bytes: limit("HARPER_SANDBOX_MAX_OUTPUT_BYTES"),
+        backend,
     }
 }
 
+/// Whether every program a shell command line runs is permitted
 pub fn is_command_allowed(config: &SandboxConfig, command: &str) -> bool {
-    if let Some(blocked) = &config.blocked_commands {
-        if blocked
-            .iter()
-            .any(|blocked| blocked == command_name(command))
-        {
-            return false;
-        }
+    validate_command(config, command).is_ok()
+}
+
+pub fn validate_command(config: &SandboxConfig, command: &str) -> Result<()> {
+    if config.allowed_commands.is_none() && config.blocked_commands.is_none() {
+        return Ok(());
     }
 
-    match &config.allowed_commands {
-        Some(allowed) => allowed
-            .iter()
-            .any(|allowed| allowed == command_name(command)),
-        None => true,
+    let script = shell::parse(command)?;
+    for line in script
+        .simple_commands()
+        .into_iter()
+        .filter_map(|command| command.command_line())
+    {
+        validate_program(config, &line)?;
     }
+    Ok(())
 }
 
-pub fn validate_command(config: &SandboxConfig, command: &str) -> Result<()> {
-    if is_command_allowed(config, command) {
+/// Check a single program and its arguments, without shell parsing
+pub fn validate_program(config: &SandboxConfig, command_line: &str) -> Result<()> {
+    let blocked = config
+        .blocked_commands
+        .iter()
+        .flatten()
+        .any(|pattern| command_blocked_by(pattern, command_line));
+    let allowed = config.allowed_commands.as_ref().is_none_or(|allowed| {
+        allowed
+            .iter()
+            .any(|pattern| command_matches(pattern, command_line))
+    });
+
+    if !blocked && allowed {
         Ok(())
     } else {
         Err(SandboxError::CommandBlocked {
-            command: command_name(command).to_string(),
+            command: command_name(command_line).to_string(),
         })
     }
 }
 
+/// Whether a command line matches an allow pattern.
+///
+/// A bare name such as `git` matches the executable and any arguments, while
+/// patterns with arguments match the whole line: `*` stands for any run of
+/// characters and `?` for one, so `cargo test *` matches `cargo test` and
+/// `git push --force*` matches `git push --force-with-lease`. The executable is
+/// compared as written, so an allowed `git` never matches `./git` or
+/// `/tmp/x/git`, and `rm` never matches `rmdir`.
+pub fn command_matches(pattern: &str, command: &str) -> bool {
+    pattern_matches(pattern, command.split_whitespace())
+}
+
+/// Whether a command line matches a block pattern.
+///
+/// Like [`command_matches`], but the executable is also compared by file name,
+/// so a blocked `rm` blocks `/bin/rm` and `./rm` as well.
+pub fn command_blocked_by(pattern: &str, command: &str) -> bool {
+    if command_matches(pattern, command) {
+        return true;
+    }
+    let mut words = command.split_whitespace();
+    let Some(first) = words.next() else {
+        return false;
+    };
+    let executable = Path::new(first)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or(first);
+    pattern_matches(pattern, std::iter::once(executable).chain(words))
+}
+
+fn pattern_matches<'a>(pattern: &str, words: impl Iterator<Item = &'a str>) -> bool {
+    let command = words.collect::<Vec<_>>().join(" ");
+    if command.is_empty() {
+        return false;
+    }
+
+    let pattern = pattern.split_whitespace().collect::<Vec<_>>().join(" ");
+    let pattern = pattern.strip_suffix(" *").unwrap_or(&pattern);
+    if pattern.is_empty() {
+        return false;
+    }
+
+    wildcard_match(pattern, &command) || wildcard_match(&format!("{pattern} *"), &command)
+}
+
+/// Whether `text` matches `pattern`, where `*` stands for any run of
+/// characters and `?` for exactly one. Every other character, including case,
+/// must match exactly.
+pub fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                Some((star, consumed)) => {
+                    p = star + 1;
+                    t = consumed + 1;
+                    backtrack = Some((star, consumed + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
 pub fn validate_working_dir(config: &SandboxConfig, path: &Path) -> Result<()> {
     validate_read_path_access(config, path, path.parent().unwrap_or(Path::new("/")))
 }
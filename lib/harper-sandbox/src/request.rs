@@ -13,7 +13,8 @@
 // limitations under the License.
 
 use crate::backend::SandboxBackend;
-use crate::errors::Result;
+use crate::errors::{Result, SandboxError};
+use crate::limits::ResourceLimit;
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 
@@ -47,4 +48,16 @@ pub struct SandboxExecutionResult {
     pub request: SandboxRequest,
     pub backend: SandboxBackend,
     pub output: std::process::Output,
+    /// Limit that stopped the command; the output is what was captured before
+    pub limit_exceeded: Option<ResourceLimit>,
+    /// Configured limits this host could not apply to the command
+    pub unenforced_limits: Vec<ResourceLimit>,
+}
+
+impl SandboxExecutionResult {
+    /// Error describing the limit that stopped the command, if one did
+    #[must_use]
+    pub fn limit_error(&self) -> Option<SandboxError> {
+        self.limit_exceeded.map(SandboxError::from)
+    }
 }
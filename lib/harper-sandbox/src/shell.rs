@@ -0,0 +1,1365 @@
+// Copyright 2026 harpertoken
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! POSIX shell parsing for policy checks.
+//!
+//! This is not an interpreter: parameter and arithmetic expansions are kept as
+//! written, and only quote removal is applied to words. It parses enough of the
+//! grammar to find every program a command line runs, including those inside
+//! `$(...)`, backticks and unquoted heredocs, and every redirection target.
+//!
+//! Compound commands are flattened. The reserved words of `if`, `while`,
+//! `until`, `for` and `{ ... }` are skipped, and the commands they contain
+//! appear as ordinary list items. `case` statements and function definitions
+//! are rejected rather than guessed at.
+
+use crate::errors::{Result, SandboxError};
+
+/// A parsed command line: a list of and-or lists separated by `;`, `&` or newlines
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Script {
+    pub items: Vec<ListItem>,
+}
+
+/// One and-or list, such as `make && make test || echo failed`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ListItem {
+    pub first: Pipeline,
+    pub rest: Vec<(AndOr, Pipeline)>,
+    /// Whether the list is terminated by `&`
+    pub background: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AndOr {
+    And,
+    Or,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Pipeline {
+    pub negated: bool,
+    pub commands: Vec<Command>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    Simple(SimpleCommand),
+    Subshell {
+        body: Script,
+        redirects: Vec<Redirect>,
+    },
+    /// The header of a `for` loop; the loop body follows as separate list items
+    ForHeader {
+        name: Word,
+        items: Vec<Word>,
+    },
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SimpleCommand {
+    /// Leading `NAME=value` words
+    pub assignments: Vec<Word>,
+    /// The program followed by its arguments
+    pub words: Vec<Word>,
+    pub redirects: Vec<Redirect>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Word {
+    /// The word after quote removal; expansions are left as written
+    pub text: String,
+    /// Whether any part of the word was quoted or escaped
+    pub quoted: bool,
+    /// Whether the word contains a parameter, arithmetic or command expansion
+    pub expanded: bool,
+    /// Command substitutions that run while the word is expanded
+    pub substitutions: Vec<Script>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Redirect {
+    pub fd: Option<u32>,
+    pub op: RedirectOp,
+    /// The file, descriptor or heredoc delimiter after the operator
+    pub target: Word,
+    /// The body of a heredoc
+    pub heredoc: Option<Word>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RedirectOp {
+    /// `<`
+    Input,
+    /// `>`
+    Output,
+    /// `>>`
+    Append,
+    /// `>|`
+    Clobber,
+    /// `<>`
+    ReadWrite,
+    /// `<&`
+    DupInput,
+    /// `>&`
+    DupOutput,
+    /// `<<`
+    Heredoc,
+    /// `<<-`
+    HeredocStripTabs,
+    /// `<<<`
+    HereString,
+}
+
+impl RedirectOp {
+    pub fn reads(self) -> bool {
+        matches!(self, Self::Input | Self::ReadWrite)
+    }
+
+    pub fn writes(self) -> bool {
+        matches!(
+            self,
+            Self::Output | Self::Append | Self::Clobber | Self::ReadWrite
+        )
+    }
+}
+
+impl Redirect {
+    /// The file this redirection opens, if it opens one
+    pub fn path(&self) -> Option<&str> {
+        (self.op.reads() || self.op.writes()).then_some(self.target.text.as_str())
+    }
+}
+
+impl SimpleCommand {
+    pub fn program(&self) -> Option<&str> {
+        self.words.first().map(|word| word.text.as_str())
+    }
+
+    /// Whether the program name comes from a parameter expansion or command
+    /// substitution, so which program runs is only known once the shell expands it
+    pub fn program_is_expanded(&self) -> bool {
+        self.program()
+            .is_some_and(|program| program.contains(['$', '`']))
+    }
+
+    pub fn args(&self) -> Vec<&str> {
+        self.words
+            .iter()
+            .skip(1)
+            .map(|word| word.text.as_str())
+            .collect()
+    }
+
+    /// The program and arguments joined by spaces, for pattern matching
+    pub fn command_line(&self) -> Option<String> {
+        self.program()?;
+        Some(
+            self.words
+                .iter()
+                .map(|word| word.text.as_str())
+                .collect::<Vec<_>>()
+                .join(" "),
+        )
+    }
+
+    fn has_substitutions(&self) -> bool {
+        self.assignments
+            .iter()
+            .chain(&self.words)
+            .any(|word| !word.substitutions.is_empty())
+    }
+}
+
+impl ListItem {
+    pub fn pipelines(&self) -> impl Iterator<Item = &Pipeline> {
+        std::iter::once(&self.first).chain(self.rest.iter().map(|(_, pipeline)| pipeline))
+    }
+}
+
+impl Script {
+    /// Every simple command the script runs, in source order, including those
+    /// in subshells and command substitutions
+    pub fn simple_commands(&self) -> Vec<&SimpleCommand> {
+        Walk::of(self).commands
+    }
+
+    /// Every redirection in the script, including nested ones
+    pub fn redirects(&self) -> Vec<&Redirect> {
+        Walk::of(self).redirects
+    }
+
+    /// The script followed by every subshell body and command substitution
+    /// nested in it
+    pub fn scripts(&self) -> Vec<&Script> {
+        Walk::of(self).scripts
+    }
+
+    /// Every program the script runs, as written
+    pub fn programs(&self) -> Vec<&str> {
+        self.simple_commands()
+            .into_iter()
+            .filter_map(SimpleCommand::program)
+            .collect()
+    }
+
+    /// Whether the script, or any subshell or substitution in it, starts a
+    /// background job
+    pub fn has_background_jobs(&self) -> bool {
+        self.scripts()
+            .into_iter()
+            .any(|script| script.items.iter().any(|item| item.background))
+    }
+
+    /// Whether any command's program name is only known after expansion
+    pub fn has_expanded_programs(&self) -> bool {
+        self.simple_commands()
+            .into_iter()
+            .any(SimpleCommand::program_is_expanded)
+    }
+
+    /// Whether the script, or any subshell or substitution in it, runs more
+    /// than one pipeline or pipes commands together
+    pub fn has_command_chains(&self) -> bool {
+        self.scripts().into_iter().any(|script| {
+            script.items.len() > 1
+                || script
+                    .items
+                    .iter()
+                    .any(|item| !item.rest.is_empty() || item.first.commands.len() > 1)
+        })
+    }
+
+    /// Whether any redirection, including nested ones, writes to a file. `>&`
+    /// counts when its target is not a descriptor, since bash then opens it as
+    /// a file for both stdout and stderr
+    pub fn has_output_redirects(&self) -> bool {
+        self.redirects().into_iter().any(|redirect| {
+            redirect.op.writes()
+                || (redirect.op == RedirectOp::DupOutput
+                    && redirect.target.text != "-"
+                    && !redirect.target.text.chars().all(|c| c.is_ascii_digit()))
+        })
+    }
+
+    /// Whether the script runs a command substitution anywhere, including in
+    /// redirection targets and heredoc bodies
+    pub fn has_substitutions(&self) -> bool {
+        !Walk::of(self).substitutions.is_empty()
+    }
+
+    /// Whether any redirection, including nested ones, reads from a file
+    pub fn has_input_redirects(&self) -> bool {
+        self.redirects()
+            .into_iter()
+            .any(|redirect| redirect.op.reads())
+    }
+
+    /// Whether any word, redirection target or heredoc body is expanded by
+    /// the shell, as `$HOME`, `${x}`, `$((1 + 2))` or a command substitution
+    pub fn has_expansions(&self) -> bool {
+        Walk::of(self).words.into_iter().any(|word| word.expanded)
+    }
+
+    /// Whether the script runs a `( ... )` subshell anywhere
+    pub fn has_subshells(&self) -> bool {
+        Walk::of(self).subshells > 0
+    }
+
+    /// The command when the script is a single program with arguments and
+    /// nothing a shell would need to interpret
+    pub fn single_command(&self) -> Option<&SimpleCommand> {
+        let [item] = self.items.as_slice() else {
+            return None;
+        };
+        if item.background || !item.rest.is_empty() || item.first.negated {
+            return None;
+        }
+        let [Command::Simple(command)] = item.first.commands.as_slice() else {
+            return None;
+        };
+        (command.program().is_some()
+            && command.assignments.is_empty()
+            && command.redirects.is_empty()
+            && !command.has_substitutions())
+        .then_some(command)
+    }
+}
+
+/// Everything reachable from a script, in source order
+#[derive(Default)]
+struct Walk<'a> {
+    scripts: Vec<&'a Script>,
+    commands: Vec<&'a SimpleCommand>,
+    redirects: Vec<&'a Redirect>,
+    substitutions: Vec<&'a Script>,
+    words: Vec<&'a Word>,
+    subshells: usize,
+}
+
+impl<'a> Walk<'a> {
+    fn of(script: &'a Script) -> Self {
+        let mut walk = Self::default();
+        walk.script(script);
+        walk
+    }
+
+    fn script(&mut self, script: &'a Script) {
+        self.scripts.push(script);
+        for pipeline in script.items.iter().flat_map(ListItem::pipelines) {
+            for command in &pipeline.commands {
+                match command {
+                    Command::Simple(simple) => {
+                        self.commands.push(simple);
+                        for word in simple.assignments.iter().chain(&simple.words) {
+                            self.word(word);
+                        }
+                        self.redirects(&simple.redirects);
+                    }
+                    Command::Subshell { body, redirects } => {
+                        self.subshells += 1;
+                        self.script(body);
+                        self.redirects(redirects);
+                    }
+                    Command::ForHeader { items, .. } => {
+                        for word in items {
+                            self.word(word);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn redirects(&mut self, own: &'a [Redirect]) {
+        for redirect in own {
+            self.redirects.push(redirect);
+            self.word(&redirect.target);
+            if let Some(body) = &redirect.heredoc {
+                self.word(body);
+            }
+        }
+    }
+
+    fn word(&mut self, word: &'a Word) {
+        self.words.push(word);
+        for substitution in &word.substitutions {
+            self.substitutions.push(substitution);
+            self.script(substitution);
+        }
+    }
+}
+
+/// Parse a shell command line
+pub fn parse(input: &str) -> Result<Script> {
+    let mut parser = Parser::new(input);
+    let script = parser.parse_list(false)?;
+    if parser.pos < parser.chars.len() {
+        return Err(parser.error("unexpected ')'"));
+    }
+    Ok(script)
+}
+
+const RESERVED_WORDS: &[&str] = &[
+    "!", "{", "}", "if", "then", "elif", "else", "fi", "while", "until", "do", "done",
+];
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    /// The newline that starts pending heredoc bodies and the offset just past them
+    heredoc_skip: Option<(usize, usize)>,
+}
+
+impl Parser {
+    fn new(input: &str) -> Self {
+        Self {
+            chars: input.chars().collect(),
+            pos: 0,
+            heredoc_skip: None,
+        }
+    }
+
+    fn error(&self, message: &str) -> SandboxError {
+        SandboxError::ShellSyntax(format!("{} at offset {}", message, self.pos))
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<char> {
+        self.chars.get(self.pos + offset).copied()
+    }
+
+    fn source(&self, start: usize) -> String {
+        self.chars[start..self.pos].iter().collect()
+    }
+
+    fn skip_blanks(&mut self) {
+        loop {
+            match self.peek() {
+                Some(' ' | '\t') => self.pos += 1,
+                Some('\\') if self.peek_at(1) == Some('\n') => self.pos += 2,
+                Some('#') => {
+                    while self.peek().is_some_and(|c| c != '\n') {
+                        self.pos += 1;
+                    }
+                }
+                _ => return,
+            }
+        }
+    }
+
+    fn consume_newline(&mut self) {
+        match self.heredoc_skip.take() {
+            Some((newline, resume)) if newline == self.pos => self.pos = resume,
+            other => {
+                self.heredoc_skip = other;
+                self.pos += 1;
+            }
+        }
+    }
+
+    fn skip_linebreaks(&mut self) {
+        loop {
+            self.skip_blanks();
+            if self.peek() != Some('\n') {
+                return;
+            }
+            self.consume_newline();
+        }
+    }
+
+    fn parse_list(&mut self, nested: bool) -> Result<Script> {
+        let mut script = Script::default();
+        loop {
+            self.skip_linebreaks();
+            match self.peek() {
+                None => return Ok(script),
+                Some(')') if nested => return Ok(script),
+                _ => {}
+            }
+
+            let item = self.parse_and_or()?;
+            self.skip_blanks();
+            let mut background = false;
+            match self.peek() {
+                Some(';') if self.peek_at(1) == Some(';') => {
+                    return Err(self.error("';;' outside a case statement"));
+                }
+                Some(';') => self.pos += 1,
+                Some('&') => {
+                    background = true;
+                    self.pos += 1;
+                }
+                Some('\n') => self.consume_newline(),
+                None => {}
+                Some(')') if nested => {}
+                Some(c) => return Err(self.error(&format!("unexpected '{}'", c))),
+            }
+
+            if let Some(mut item) = item {
+                item.background = background;
+                if item
+                    .pipelines()
+                    .any(|pipeline| !pipeline.commands.is_empty())
+                {
+                    script.items.push(item);
+                }
+            }
+        }
+    }
+
+    fn parse_and_or(&mut self) -> Result<Option<ListItem>> {
+        let first = self.parse_pipeline()?;
+        let mut rest = Vec::new();
+        loop {
+            self.skip_blanks();
+            let op = match (self.peek(), self.peek_at(1)) {
+                (Some('&'), Some('&')) => AndOr::And,
+                (Some('|'), Some('|')) => AndOr::Or,
+                _ => break,
+            };
+            if first.is_none() {
+                return Err(self.error("expected a command before an and-or operator"));
+            }
+            self.pos += 2;
+            self.skip_linebreaks();
+            let Some(pipeline) = self.parse_pipeline()? else {
+                return Err(self.error("expected a command after an and-or operator"));
+            };
+            rest.push((op, pipeline));
+        }
+
+        Ok(first.map(|first| ListItem {
+            first,
+            rest,
+            background: false,
+        }))
+    }
+
+    fn parse_pipeline(&mut self) -> Result<Option<Pipeline>> {
+        let mut pipeline = Pipeline::default();
+        let mut seen = false;
+        let mut needs_command = false;
+        loop {
+            let (command, keywords) = self.parse_command(&mut pipeline.negated)?;
+            seen |= keywords || command.is_some();
+            let had_command = command.is_some();
+            if needs_command && !had_command {
+                return Err(self.error("expected a command after '|'"));
+            }
+            pipeline.commands.extend(command);
+
+            self.skip_blanks();
+            if self.peek() == Some('|') && self.peek_at(1) != Some('|') {
+                if !had_command {
+                    return Err(self.error("expected a command before '|'"));
+                }
+                self.pos += 1;
+                self.skip_linebreaks();
+                needs_command = true;
+                continue;
+            }
+            return Ok(seen.then_some(pipeline));
+        }
+    }
+
+    /// Parse one command, skipping leading reserved words. Returns the command
+    /// and whether any reserved word was consumed.
+    fn parse_command(&mut self, negated: &mut bool) -> Result<(Option<Command>, bool)> {
+        let mut keywords = false;
+        let mut first_word = None;
+        loop {
+            self.skip_blanks();
+            if self.at_operator() || self.redirect_ahead() {
+                break;
+            }
+            let word = self.parse_word()?;
+            if word.quoted {
+                first_word = Some(word);
+                break;
+            }
+            match word.text.as_str() {
+                "case" => return Err(self.error("case statements are not supported")),
+                "for" => return Ok((Some(self.parse_for_header()?), true)),
+                text if RESERVED_WORDS.contains(&text) => {
+                    keywords = true;
+                    if text == "!" {
+                        *negated = !*negated;
+                    }
+                }
+                _ => {
+                    first_word = Some(word);
+                    break;
+                }
+            }
+        }
+
+        if first_word.is_none() && self.peek() == Some('(') {
+            self.pos += 1;
+            let body = self.parse_list(true)?;
+            if self.peek() != Some(')') {
+                return Err(self.error("unterminated subshell"));
+            }
+            self.pos += 1;
+            let mut redirects = Vec::new();
+            loop {
+                self.skip_blanks();
+                if !self.redirect_ahead() {
+                    break;
+                }
+                redirects.push(self.parse_redirect()?);
+            }
+            return Ok((Some(Command::Subshell { body, redirects }), keywords));
+        }
+
+        let mut command = SimpleCommand::default();
+        if let Some(word) = first_word {
+            push_word(&mut command, word);
+        }
+        loop {
+            self.skip_blanks();
+            if self.redirect_ahead() {
+                command.redirects.push(self.parse_redirect()?);
+                continue;
+            }
+            match self.peek() {
+                Some('(') if !command.words.is_empty() => {
+                    return Err(self.error("function definitions are not supported"));
+                }
+                _ if self.at_operator() => break,
+                _ => {
+                    let word = self.parse_word()?;
+                    push_word(&mut command, word);
+                }
+            }
+        }
+
+        let empty = command.words.is_empty()
+            && command.assignments.is_empty()
+            && command.redirects.is_empty();
+        Ok(((!empty).then_some(Command::Simple(command)), keywords))
+    }
+
+    fn parse_for_header(&mut self) -> Result<Command> {
+        self.skip_blanks();
+        if self.at_operator() {
+            return Err(self.error("expected a name after 'for'"));
+        }
+        let name = self.parse_word()?;
+        let mut items = Vec::new();
+
+        self.skip_linebreaks();
+        let start = self.pos;
+        if !self.at_operator() {
+            let word = self.parse_word()?;
+            if word.text == "in" && !word.quoted {
+                loop {
+                    self.skip_blanks();
+                    if self.at_operator() {
+                        break;
+                    }
+                    items.push(self.parse_word()?);
+                }
+            } else {
+                self.pos = start;
+            }
+        }
+        Ok(Command::ForHeader { name, items })
+    }
+
+    fn at_operator(&self) -> bool {
+        matches!(
+            self.peek(),
+            None | Some(';' | '&' | '|' | '(' | ')' | '<' | '>' | '\n')
+        )
+    }
+
+    fn redirect_ahead(&self) -> bool {
+        let digits = self.chars[self.pos..]
+            .iter()
+            .take_while(|c| c.is_ascii_digit())
+            .count();
+        matches!(self.peek_at(digits), Some('<' | '>'))
+    }
+
+    fn parse_redirect(&mut self) -> Result<Redirect> {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        let fd = if self.pos > start {
+            Some(
+                self.source(start)
+                    .parse()
+                    .map_err(|_| self.error("file descriptor out of range"))?,
+            )
+        } else {
+            None
+        };
+
+        let (op, len) = match (self.peek(), self.peek_at(1), self.peek_at(2)) {
+            (Some('<'), Some('<'), Some('<')) => (RedirectOp::HereString, 3),
+            (Some('<'), Some('<'), Some('-')) => (RedirectOp::HeredocStripTabs, 3),
+            (Some('<'), Some('<'), _) => (RedirectOp::Heredoc, 2),
+            (Some('<'), Some('&'), _) => (RedirectOp::DupInput, 2),
+            (Some('<'), Some('>'), _) => (RedirectOp::ReadWrite, 2),
+            (Some('<'), _, _) => (RedirectOp::Input, 1),
+            (Some('>'), Some('>'), _) => (RedirectOp::Append, 2),
+            (Some('>'), Some('|'), _) => (RedirectOp::Clobber, 2),
+            (Some('>'), Some('&'), _) => (RedirectOp::DupOutput, 2),
+            _ => (RedirectOp::Output, 1),
+        };
+        self.pos += len;
+
+        self.skip_blanks();
+        if self.at_operator() {
+            return Err(self.error("missing redirection target"));
+        }
+        let target = self.parse_word()?;
+        let heredoc = match op {
+            RedirectOp::Heredoc | RedirectOp::HeredocStripTabs => Some(self.read_heredoc(
+                &target.text,
+                op == RedirectOp::HeredocStripTabs,
+                !target.quoted,
+            )?),
+            _ => None,
+        };
+
+        Ok(Redirect {
+            fd,
+            op,
+            target,
+            heredoc,
+        })
+    }
+
+    /// Read a heredoc body, which starts on the line after the current one or
+    /// after the bodies already pending for it
+    fn read_heredoc(&mut self, delimiter: &str, strip_tabs: bool, expand: bool) -> Result<Word> {
+        let (newline, mut cursor) = match self.heredoc_skip {
+            Some(pending) => pending,
+            None => {
+                let Some(offset) = self.chars[self.pos..].iter().position(|c| *c == '\n') else {
+                    return Err(self.error("missing heredoc body"));
+                };
+                (self.pos + offset, self.pos + offset + 1)
+            }
+        };
+
+        let mut body = String::new();
+        while cursor < self.chars.len() {
+            let end = self.chars[cursor..]
+                .iter()
+                .position(|c| *c == '\n')
+                .map_or(self.chars.len(), |offset| cursor + offset);
+            let mut line: String = self.chars[cursor..end].iter().collect();
+            cursor = (end + 1).min(self.chars.len());
+            if strip_tabs {
+                line = line.trim_start_matches('\t').to_string();
+            }
+            if line == delimiter {
+                break;
+            }
+            body.push_str(&line);
+            body.push('\n');
+        }
+        self.heredoc_skip = Some((newline, cursor));
+
+        if !expand {
+            return Ok(Word {
+                text: body,
+                quoted: true,
+                expanded: false,
+                substitutions: Vec::new(),
+            });
+        }
+        let mut parser = Parser::new(&body);
+        let mut word = Word::default();
+        parser.scan_double_quoted(&mut word, None)?;
+        Ok(word)
+    }
+
+    fn parse_word(&mut self) -> Result<Word> {
+        let mut word = Word::default();
+        while let Some(c) = self.peek() {
+            match c {
+                ' ' | '\t' | '\n' | ';' | '&' | '|' | '<' | '>' | '(' | ')' => break,
+                '\\' => {
+                    self.pos += 1;
+                    match self.peek() {
+                        Some('\n') => self.pos += 1,
+                        Some(escaped) => {
+                            word.text.push(escaped);
+                            word.quoted = true;
+                            self.pos += 1;
+                        }
+                        None => word.text.push('\\'),
+                    }
+                }
+                '\'' => {
+                    self.pos += 1;
+                    word.quoted = true;
+                    loop {
+                        match self.peek() {
+                            Some('\'') => break,
+                            Some(quoted) => {
+                                word.text.push(quoted);
+                                self.pos += 1;
+                            }
+                            None => return Err(self.error("unterminated single quote")),
+                        }
+                    }
+                    self.pos += 1;
+                }
+                '"' => {
+                    self.pos += 1;
+                    word.quoted = true;
+                    self.scan_double_quoted(&mut word, Some('"'))?;
+                }
+                '$' => self.scan_dollar(&mut word)?,
+                '`' => self.scan_backtick(&mut word)?,
+                _ => {
+                    word.text.push(c);
+                    self.pos += 1;
+                }
+            }
+        }
+        Ok(word)
+    }
+
+    /// Scan double-quoted text up to `terminator`, or to the end of input for
+    /// heredoc bodies
+    fn scan_double_quoted(&mut self, word: &mut Word, terminator: Option<char>) -> Result<()> {
+        loop {
+            match self.peek() {
+                None if terminator.is_some() => {
+                    return Err(self.error("unterminated double quote"));
+                }
+                None => return Ok(()),
+                Some(c) if Some(c) == terminator => {
+                    self.pos += 1;
+                    return Ok(());
+                }
+                Some('\\') => {
+                    self.pos += 1;
+                    match self.peek() {
+                        Some('\n') => self.pos += 1,
+                        Some(escaped @ ('$' | '`' | '\\')) => {
+                            word.text.push(escaped);
+                            self.pos += 1;
+                        }
+                        Some('"') if terminator.is_some() => {
+                            word.text.push('"');
+                            self.pos += 1;
+                        }
+                        _ => word.text.push('\\'),
+                    }
+                }
+                Some('$') => self.scan_dollar(word)?,
+                Some('`') => self.scan_backtick(word)?,
+                Some(c) => {
+                    word.text.push(c);
+                    self.pos += 1;
+                }
+            }
+        }
+    }
+
+    fn scan_dollar(&mut self, word: &mut Word) -> Result<()> {
+        let start = self.pos;
+        word.expanded |= self.peek_at(1).is_some_and(|c| {
+            c.is_ascii_alphanumeric()
+                || matches!(c, '_' | '(' | '{' | '?' | '$' | '!' | '#' | '@' | '*' | '-')
+        });
+        match (self.peek_at(1), self.peek_at(2)) {
+            (Some('('), Some('(')) => {
+                self.pos += 1;
+                self.scan_expansion(word, '(', ')', "unterminated arithmetic expansion")?;
+            }
+            (Some('('), _) => {
+                self.pos += 2;
+                let substitution = self.parse_list(true)?;
+                if self.peek() != Some(')') {
+                    return Err(self.error("unterminated command substitution"));
+                }
+                self.pos += 1;
+                word.substitutions.push(substitution);
+            }
+            (Some('{'), _) => {
+                self.pos += 1;
+                self.scan_expansion(word, '{', '}', "unterminated parameter expansion")?;
+            }
+            _ => self.pos += 1,
+        }
+        word.text.push_str(&self.source(start));
+        Ok(())
+    }
+
+    /// Scan a `${...}` or `$((...))` expansion starting at `open`. Command
+    /// substitutions nested in it still run, so they are parsed into `word`;
+    /// process substitutions are refused.
+    fn scan_expansion(
+        &mut self,
+        word: &mut Word,
+        open: char,
+        close: char,
+        message: &str,
+    ) -> Result<()> {
+        let mut depth = 0usize;
+        let mut nested = Word::default();
+        while let Some(c) = self.peek() {
+            match c {
+                '\\' => {
+                    self.pos = (self.pos + 2).min(self.chars.len());
+                    continue;
+                }
+                '"' => {
+                    self.pos += 1;
+                    self.scan_double_quoted(&mut nested, Some('"'))?;
+                    continue;
+                }
+                '$' => {
+                    self.scan_dollar(&mut nested)?;
+                    continue;
+                }
+                '`' => {
+                    self.scan_backtick(&mut nested)?;
+                    continue;
+                }
+                '<' | '>' if self.peek_at(1) == Some('(') => {
+                    return Err(self.error("process substitution is not supported"));
+                }
+                _ => {}
+            }
+            self.pos += 1;
+            if c == open {
+                depth += 1;
+            } else if c == close {
+                depth -= 1;
+                if depth == 0 {
+                    word.substitutions.extend(nested.substitutions);
+                    return Ok(());
+                }
+            }
+        }
+        Err(self.error(message))
+    }
+
+    fn scan_backtick(&mut self, word: &mut Word) -> Result<()> {
+        let start = self.pos;
+        self.pos += 1;
+        let mut inner = String::new();
+        loop {
+            match self.peek() {
+                None => return Err(self.error("unterminated backquote")),
+                Some('`') => break,
+                Some('\\') if matches!(self.peek_at(1), Some('`' | '\\' | '$')) => {
+                    inner.push(self.chars[self.pos + 1]);
+                    self.pos += 2;
+                }
+                Some(c) => {
+                    inner.push(c);
+                    self.pos += 1;
+                }
+            }
+        }
+        self.pos += 1;
+        word.text.push_str(&self.source(start));
+        word.expanded = true;
+        word.substitutions.push(parse(&inner)?);
+        Ok(())
+    }
+}
+
+fn push_word(command: &mut SimpleCommand, word: Word) {
+    if command.words.is_empty() && is_assignment(&word) {
+        command.assignments.push(word);
+    } else {
+        command.words.push(word);
+    }
+}
+
+fn is_assignment(word: &Word) -> bool {
+    let Some((name, _)) = word.text.split_once('=') else {
+        return false;
+    };
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn writes(script: &Script) -> Vec<&str> {
+        script
+            .redirects()
+            .into_iter()
+            .filter(|redirect| redirect.op.writes())
+            .filter_map(Redirect::path)
+            .collect()
+    }
+
+    fn reads(script: &Script) -> Vec<&str> {
+        script
+            .redirects()
+            .into_iter()
+            .filter(|redirect| redirect.op.reads())
+            .filter_map(Redirect::path)
+            .collect()
+    }
+
+    #[test]
+    fn corpus_of_shell_lines() {
+        // (line, programs, written paths, read paths)
+        type Case<'a> = (&'a str, &'a [&'a str], &'a [&'a str], &'a [&'a str]);
+        let corpus: &[Case] = &[
+            ("ls -la", &["ls"], &[], &[]),
+            ("echo x > ~/.ssh/config", &["echo"], &["~/.ssh/config"], &[]),
+            ("cat a | sh", &["cat", "sh"], &[], &[]),
+            (
+                "sort < in.txt >> out.txt",
+                &["sort"],
+                &["out.txt"],
+                &["in.txt"],
+            ),
+            ("make 2>&1 | tee build.log", &["make", "tee"], &[], &[]),
+            ("cmd 2> errors.log", &["cmd"], &["errors.log"], &[]),
+            (
+                "git status && git push || echo failed",
+                &["git", "git", "echo"],
+                &[],
+                &[],
+            ),
+            ("echo $(curl -s example.com)", &["echo", "curl"], &[], &[]),
+            ("echo \"today is $(date +%F)\"", &["echo", "date"], &[], &[]),
+            ("echo `whoami`", &["echo", "whoami"], &[], &[]),
+            ("echo '$(rm -rf /)' \"a|b\"", &["echo"], &[], &[]),
+            ("FOO=1 BAR=\"x y\" env", &["env"], &[], &[]),
+            (
+                "(cd src && ls) > listing.txt",
+                &["cd", "ls"],
+                &["listing.txt"],
+                &[],
+            ),
+            ("if test -f x; then rm x; fi", &["test", "rm"], &[], &[]),
+            (
+                "for f in $(ls *.rs); do wc -l \"$f\"; done > counts",
+                &["ls", "wc"],
+                &["counts"],
+                &[],
+            ),
+            (
+                "while read line; do echo $line; done < input",
+                &["read", "echo"],
+                &[],
+                &["input"],
+            ),
+            (
+                "{ echo a; echo b; } >| both",
+                &["echo", "echo"],
+                &["both"],
+                &[],
+            ),
+            ("! grep -q x file", &["grep"], &[], &[]),
+            (
+                "cat <<EOF > notes.md\nhello $(id)\nEOF\nls",
+                &["cat", "id", "ls"],
+                &["notes.md"],
+                &[],
+            ),
+            ("cat <<'EOF'\n$(id)\nEOF", &["cat"], &[], &[]),
+            (
+                "cat <<-END\n\tbody\n\tEND\necho done",
+                &["cat", "echo"],
+                &[],
+                &[],
+            ),
+            ("grep x <<< \"$(pwd)\"", &["grep", "pwd"], &[], &[]),
+            ("echo $((1 + 2)) ${HOME}", &["echo"], &[], &[]),
+            ("echo ${x:-$(rm -rf /)}", &["echo", "rm"], &[], &[]),
+            ("echo $(( $(id -u) + 1 ))", &["echo", "id"], &[], &[]),
+            ("echo \"${x:-`whoami`}\"", &["echo", "whoami"], &[], &[]),
+            ("echo ${x:-\"$(date)\"}", &["echo", "date"], &[], &[]),
+            ("echo ${x:-${y:-$(pwd)}}", &["echo", "pwd"], &[], &[]),
+            ("ls # trailing | comment", &["ls"], &[], &[]),
+            ("echo a \\\n  b", &["echo"], &[], &[]),
+            ("sleep 5 &", &["sleep"], &[], &[]),
+            (
+                "exec 3<> /tmp/fifo",
+                &["exec"],
+                &["/tmp/fifo"],
+                &["/tmp/fifo"],
+            ),
+        ];
+
+        for (line, programs, expected_writes, expected_reads) in corpus {
+            let script = parse(line).unwrap_or_else(|err| panic!("{line:?}: {err}"));
+            assert_eq!(&script.programs(), programs, "programs of {line:?}");
+            assert_eq!(&writes(&script), expected_writes, "writes of {line:?}");
+            assert_eq!(&reads(&script), expected_reads, "reads of {line:?}");
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_and_unsupported_lines() {
+        for line in [
+            "echo 'open",
+            "echo \"open",
+            "echo $(date",
+            "echo `date",
+            "ls |",
+            "| ls",
+            "&& ls",
+            "ls >",
+            "(ls",
+            "ls )",
+            "case $x in a) ls;; esac",
+            "f() { ls; }",
+            "cat <<EOF",
+            "diff <(ls a) <(ls b)",
+            "echo ${x:-<(id)}",
+            "echo $(( $(id) ",
+            "tee >(wc -l)",
+        ] {
+            assert!(parse(line).is_err(), "{line:?} should not parse");
+        }
+    }
+
+    #[test]
+    fn words_have_quotes_removed() {
+        let script = parse(r#"grep -e "a b" 'c|d' e\ f --x="$HOME""#).unwrap();
+        let command = script.single_command().expect("single command");
+        assert_eq!(command.program(), Some("grep"));
+        assert_eq!(command.args(), vec!["-e", "a b", "c|d", "e f", "--x=$HOME"]);
+    }
+
+    #[test]
+    fn single_command_excludes_shell_features() {
+        assert!(parse("cargo test --workspace")
+            .unwrap()
+            .single_command()
+            .is_some());
+        for line in ["a | b", "a > f", "echo $(id)", "a &", "X=1 a", "! a", "(a)"] {
+            assert!(parse(line).unwrap().single_command().is_none(), "{line:?}");
+        }
+    }
+
+    #[test]
+    fn background_jobs_and_expanded_programs_are_found_at_any_depth() {
+        for line in [
+            "sleep 100 &",
+            "(sleep 100 &)",
+            "echo $(sleep 100 &)",
+            "echo `sleep 100 &`",
+        ] {
+            assert!(parse(line).unwrap().has_background_jobs(), "{line:?}");
+        }
+        assert!(!parse("echo '&' \\&").unwrap().has_background_jobs());
+
+        for line in [
+            "x=rm; $x -rf d",
+            "$(printf rm) -rf d",
+            "`printf rm` -rf d",
+            "\"${CMD}\" arg",
+            "echo $($cmd)",
+        ] {
+            assert!(parse(line).unwrap().has_expanded_programs(), "{line:?}");
+        }
+        for line in ["echo $HOME", "rm -rf $(pwd)/build", "X=$(id) env"] {
+            assert!(!parse(line).unwrap().has_expanded_programs(), "{line:?}");
+        }
+    }
+
+    #[test]
+    fn output_redirects_and_substitutions_are_found_at_any_depth() {
+        for line in [
+            "printf x > f",
+            "a >> f",
+            "a >| f",
+            "a <> f",
+            "a >&f",
+            "(a > f)",
+            "echo $(a > f)",
+        ] {
+            assert!(parse(line).unwrap().has_output_redirects(), "{line:?}");
+        }
+        for line in ["a < f", "a 2>&1", "a >&-", "echo '>'"] {
+            assert!(!parse(line).unwrap().has_output_redirects(), "{line:?}");
+        }
+
+        for line in [
+            "echo $(cat f)",
+            "echo `cat f`",
+            "echo \"$(id)\"",
+            "X=$(id) env",
+            "a > $(mktemp)",
+            "cat <<EOF\n$(id)\nEOF",
+            "echo ${x:-$(id)}",
+            "echo $(( $(id) ))",
+        ] {
+            assert!(parse(line).unwrap().has_substitutions(), "{line:?}");
+        }
+        for line in [
+            "echo $HOME",
+            "(a)",
+            "echo '$(id)'",
+            "cat <<'EOF'\n$(id)\nEOF",
+        ] {
+            assert!(!parse(line).unwrap().has_substitutions(), "{line:?}");
+        }
+    }
+
+    #[test]
+    fn input_redirects_expansions_and_subshells_are_found_at_any_depth() {
+        for line in ["cat < f", "a <> f", "(a < f)", "echo $(a < f)"] {
+            assert!(parse(line).unwrap().has_input_redirects(), "{line:?}");
+        }
+        for line in ["a > f", "a <<< x", "a 0<&3", "echo '<'"] {
+            assert!(!parse(line).unwrap().has_input_redirects(), "{line:?}");
+        }
+
+        for line in [
+            "cat $HOME/.ssh/id_rsa",
+            "echo \"${x}\"",
+            "echo $((1 + 2))",
+            "echo $1 $? $@",
+            "X=$y env",
+            "a > $f",
+            "cat <<EOF\n$HOME\nEOF",
+            "echo `id`",
+            "(echo $x)",
+        ] {
+            assert!(parse(line).unwrap().has_expansions(), "{line:?}");
+        }
+        for line in [
+            "echo '$HOME'",
+            "echo \\$HOME",
+            "grep foo$ f",
+            "echo $",
+            "cat <<'EOF'\n$HOME\nEOF",
+        ] {
+            assert!(!parse(line).unwrap().has_expansions(), "{line:?}");
+        }
+
+        for line in ["(curl example.com)", "echo $( (id) )", "a && (b)"] {
+            assert!(parse(line).unwrap().has_subshells(), "{line:?}");
+        }
+        for line in ["echo '(a)'", "{ a; }", "echo $(id)"] {
+            assert!(!parse(line).unwrap().has_subshells(), "{line:?}");
+        }
+    }
+
+    #[test]
+    fn command_chains_are_found_at_any_depth() {
+        for line in [
+            "a | b",
+            "a && b",
+            "a || b",
+            "a; b",
+            "a\nb",
+            "(a || b)",
+            "echo $(a | b)",
+            "echo `a; b`",
+            "cat <<EOF\n$(a && b)\nEOF",
+        ] {
+            assert!(parse(line).unwrap().has_command_chains(), "{line:?}");
+        }
+        for line in ["a", "a > f", "echo $(id)", "(a)", "! a", "X=1 a"] {
+            assert!(!parse(line).unwrap().has_command_chains(), "{line:?}");
+        }
+    }
+
+    fn program() -> impl Strategy<Value = &'static str> {
+        prop::sample::select(vec![
+            "ls", "cat", "grep", "git", "cargo", "sed", "curl", "echo", "wc", "sort",
+        ])
+    }
+
+    /// An argument and the text the shell would pass for it
+    fn argument() -> impl Strategy<Value = (String, String)> {
+        prop_oneof![
+            "[a-zA-Z0-9_./=:-]{1,12}".prop_map(|arg| (arg.clone(), arg)),
+            "[a-z |;&<>()$`#*]{0,10}".prop_map(|arg| (format!("'{arg}'"), arg)),
+            "[a-z |;&<>()#*']{0,10}".prop_map(|arg| (format!("\"{arg}\""), arg)),
+        ]
+    }
+
+    fn redirect() -> impl Strategy<Value = Option<(&'static str, String)>> {
+        prop::option::of((
+            prop::sample::select(vec![">", ">>", "<", "2>", ">|"]),
+            "[a-z0-9_./~-]{1,12}",
+        ))
+    }
+
+    fn separator() -> impl Strategy<Value = &'static str> {
+        prop::sample::select(vec![" | ", " && ", " || ", "; ", "\n", " |\n "])
+    }
+
+    type GeneratedCommand = (
+        &'static str,
+        Vec<(String, String)>,
+        Option<(&'static str, String)>,
+    );
+
+    fn command() -> impl Strategy<Value = GeneratedCommand> {
+        (
+            program(),
+            prop::collection::vec(argument(), 0..4),
+            redirect(),
+        )
+    }
+
+    fn render(command: &GeneratedCommand) -> String {
+        let (program, args, redirect) = command;
+        let mut line = program.to_string();
+        for (written, _) in args {
+            line.push(' ');
+            line.push_str(written);
+        }
+        if let Some((op, target)) = redirect {
+            line.push_str(&format!(" {op} {target}"));
+        }
+        line
+    }
+
+    proptest! {
+        #[test]
+        fn finds_every_program_and_redirect(
+            commands in prop::collection::vec(command(), 1..5),
+            separators in prop::collection::vec(separator(), 4),
+        ) {
+            let mut line = String::new();
+            for (index, command) in commands.iter().enumerate() {
+                if index > 0 {
+                    line.push_str(separators[index - 1]);
+                }
+                line.push_str(&render(command));
+            }
+
+            let script = parse(&line).unwrap();
+            let parsed = script.simple_commands();
+            prop_assert_eq!(parsed.len(), commands.len());
+            for (parsed, (program, args, redirect)) in parsed.iter().zip(&commands) {
+                prop_assert_eq!(parsed.program(), Some(*program));
+                let expected: Vec<&str> = args.iter().map(|(_, arg)| arg.as_str()).collect();
+                prop_assert_eq!(parsed.args(), expected);
+                let targets: Vec<(bool, &str)> = parsed
+                    .redirects
+                    .iter()
+                    .filter_map(|r| r.path().map(|path| (r.op.writes(), path)))
+                    .collect();
+                let expected: Vec<(bool, &str)> = redirect
+                    .iter()
+                    .map(|(op, target)| (*op != "<", target.as_str()))
+                    .collect();
+                prop_assert_eq!(targets, expected);
+            }
+        }
+
+        #[test]
+        fn finds_programs_inside_substitutions(inner in command(), outer in program()) {
+            let inner_line = render(&inner);
+            for line in [
+                format!("{outer} $({inner_line})"),
+                format!("{outer} \"$({inner_line})\""),
+                format!("{outer} <<EOF\n$({inner_line})\nEOF"),
+                format!("{outer} ${{x:-$({inner_line})}}"),
+                format!("{outer} \"${{x:-$({inner_line})}}\""),
+                format!("{outer} $(( $({inner_line}) + 1 ))"),
+            ] {
+                let script = parse(&line).unwrap();
+                prop_assert_eq!(script.programs(), vec![outer, inner.0]);
+                prop_assert!(script.single_command().is_none());
+            }
+        }
+
+        #[test]
+        fn never_panics(line in "[a-z0-9 $()`'\"|&;<>{}#=!\\\\\n-]{0,40}") {
+            let _ = parse(&line);
+        }
+
+        #[test]
+        fn never_panics_on_arbitrary_text(line in any::<String>()) {
+            let _ = parse(&line);
+        }
+    }
+}
@@ -23,6 +23,7 @@ pub enum Provider {
     OpenAI,
     Sambanova,
     Gemini,
+    Anthropic,
     Cerebras,
 }
 
@@ -32,6 +33,7 @@ impl Provider {
             "openai" | "open_ai" => Some(Self::OpenAI),
             "sambanova" | "samba" => Some(Self::Sambanova),
             "gemini" => Some(Self::Gemini),
+            "anthropic" => Some(Self::Anthropic),
             "cerebras" | "cerebras-ai" => Some(Self::Cerebras),
             _ => None,
         }
@@ -42,6 +44,7 @@ impl Provider {
             Self::OpenAI => "OpenAI",
             Self::Sambanova => "Sambanova",
             Self::Gemini => "Gemini",
+            Self::Anthropic => "Anthropic",
             Self::Cerebras => "Cerebras",
         }
     }
@@ -51,6 +54,7 @@ impl Provider {
             Self::OpenAI => "OpenAI",
             Self::Sambanova => "Sambanova",
             Self::Gemini => "Gemini",
+            Self::Anthropic => "Anthropic",
             Self::Cerebras => "Cerebras",
         }
     }
@@ -183,7 +187,7 @@ fn parse_provider(args: &[String]) -> HarperResult<Provider> {
 
     Provider::from_str(&provider_value).ok_or_else(|| {
         HarperError::Api(format!(
-            "Unknown provider '{}'. Use OpenAI, Sambanova, Gemini, or Cerebras.",
+            "Unknown provider '{}'. Use OpenAI, Sambanova, Gemini, Anthropic, or Cerebras.",
             provider_value
         ))
     })
@@ -191,8 +195,8 @@ fn parse_provider(args: &[String]) -> HarperResult<Provider> {
 
 fn print_usage() {
     eprintln!("Usage:");
-    eprintln!("  harper auth login --provider <openai|sambanova|gemini|cerebras>");
-    eprintln!("  harper auth logout --provider <openai|sambanova|gemini|cerebras>");
+    eprintln!("  harper auth login --provider <openai|sambanova|gemini|anthropic|cerebras>");
+    eprintln!("  harper auth logout --provider <openai|sambanova|gemini|anthropic|cerebras>");
 }
 
 #[cfg(test)]
@@ -13,20 +13,22 @@
 // limitations under the License.
 
 use async_trait::async_trait;
+use harper_core::core::cache::ResponseCache;
 use harper_core::core::io_traits::{DenyApproval, RuntimeEventSink, StdinApproval};
 use harper_core::runtime::config::HarperConfig;
+use harper_core::tools::firmware::FirmwareDevices;
 use harper_core::{
     agent::chat::{ChatService, ChatTurnDebugSummary},
-    create_connection, execute_native_shell_command_with_context, init_db,
-    parse_native_shell_command, resolve_session_target, ApiConfig, ConfigShellContext, HarperError,
-    Message, NativeShellContext, NativeShellOutcome, PlanState, ResolvedAgents,
+    create_connection, execute_mcp_shell_command, execute_native_shell_command_with_context,
+    init_db, parse_native_shell_command, resolve_session_target, ApiConfig, ConfigShellContext,
+    HarperError, McpRegistry, McpShellReply, Message, NativeShellContext, NativeShellOutcome,
+    PlanState, ResolvedAgents,
 };
 use serde::Serialize;
 use std::collections::HashMap;
 use std::env;
 use std::io::{self, IsTerminal};
 use std::sync::{Arc, Mutex};
-use turul_mcp_client::McpClient;
 
 #[derive(Debug, Default, Clone, Serialize)]
 struct TurnDebugOutput {
@@ -104,6 +106,15 @@ impl RuntimeEventSink for BatchRuntimeEvents {
         }
         Ok(())
     }
+
+    async fn assistant_output_updated(
+        &self,
+        _session_id: &str,
+        _chunk: String,
+        _done: bool,
+    ) -> Result<(), HarperError> {
+        Ok(())
+    }
 }
 
 #[derive(Default)]
@@ -112,6 +123,7 @@ struct BatchArgs {
     strategy: Option<String>,
     json: bool,
     web: bool,
+    no_cache: bool,
     help: bool,
 }
 
@@ -190,6 +202,7 @@ fn print_help() {
     println!("  --strategy <mode>       One of: auto, grounded, deterministic, model");
     println!("  --json                  Print JSON output");
     println!("  --web                   Enable web search for the session");
+    println!("  --no-cache              Bypass the response cache for every prompt");
     println!("  --help                  Show this help");
 }
 
@@ -218,6 +231,7 @@ fn parse_args(args: &[String]) -> Result<BatchArgs, HarperError> {
             }
             "--json" => parsed.json = true,
             "--web" => parsed.web = true,
+            "--no-cache" => parsed.no_cache = true,
             "--help" | "-h" => parsed.help = true,
             other => {
                 return Err(HarperError::Validation(format!(
@@ -245,6 +259,10 @@ fn get_api_key(config: &HarperConfig) -> String {
         if let Ok(env_key) = env::var("SAMBASTUDIO_API_KEY") {
             api_key = env_key;
         }
+    } else if config.api.provider == "Anthropic" {
+        if let Ok(env_key) = env::var("ANTHROPIC_API_KEY") {
+            api_key = env_key;
+        }
     }
     api_key
 }
@@ -269,10 +287,14 @@ fn build_api_config(config: &HarperConfig) -> Result<ApiConfig, HarperError> {
         api_key: get_api_key(config),
         base_url: config.api.base_url.clone(),
         model_name: config.api.model_name.clone(),
+        headers: config.api.headers.clone(),
+        parameters: config.api.model_parameters(&config.api.model_name),
+        fallback: config.api.fallback_chain()?,
     })
 }
 
 fn build_native_shell_context(config: &HarperConfig) -> NativeShellContext {
+    let tools = config.tools.registry();
     NativeShellContext {
         auth: None,
         config: Some(ConfigShellContext {
@@ -283,23 +305,12 @@ fn build_native_shell_context(config: &HarperConfig) -> NativeShellContext {
             approval: format!("{:?}", config.exec_policy.effective_approval_profile()),
             strategy: format!("{:?}", config.exec_policy.effective_execution_strategy()),
             sandbox: format!("{:?}", config.exec_policy.effective_sandbox_profile()),
+            tools: tools.effective_tools(),
+            tool_filters: tools.describe_filters(),
         }),
     }
 }
 
-async fn init_mcp_client(config: &HarperConfig) -> Option<McpClient> {
-    if !config.mcp.enabled {
-        return None;
-    }
-
-    let transport = turul_mcp_client::transport::HttpTransport::new(&config.mcp.server_url).ok()?;
-    let client = turul_mcp_client::McpClientBuilder::new()
-        .with_transport(Box::new(transport))
-        .build();
-    client.connect().await.ok()?;
-    Some(client)
-}
-
 fn latest_assistant_response(history: &[Message]) -> String {
     history
         .iter()
@@ -430,19 +441,27 @@ async fn main() -> Result<(), HarperError> {
     let api_config = build_api_config(&config)?;
     let conn = create_connection(&config.database.path)?;
     init_db(&conn)?;
-    let mcp_client = init_mcp_client(&config).await;
+    let mcp = McpRegistry::connect(&config.mcp).await;
     let runtime_events = Arc::new(BatchRuntimeEvents::default());
+    let mut api_cache = config
+        .api
+        .cache
+        .enabled
+        .then(|| ResponseCache::new(&config.api.cache));
 
     let mut chat_service = ChatService::new(
         &conn,
         &api_config,
-        mcp_client.as_ref(),
-        None,
+        (!mcp.is_empty()).then_some(&mcp),
+        api_cache.as_mut(),
         Some(uuid::Uuid::new_v4().to_string()),
         config.custom_commands.commands.clone().unwrap_or_default(),
         config.exec_policy.clone(),
     )
-    .with_runtime_events(runtime_events.clone());
+    .with_tools(config.tools.registry())
+    .with_runtime_events(runtime_events.clone())
+    .with_firmware(Arc::new(FirmwareDevices::from_config(&config.firmware)))
+    .with_cache_bypass(args.no_cache);
 
     let approver: Arc<dyn harper_core::core::io_traits::UserApproval> = if io::stdin().is_terminal()
     {
@@ -487,6 +506,25 @@ async fn main() -> Result<(), HarperError> {
                 NativeShellOutcome::Ask(message) => {
                     prompt = message;
                 }
+                NativeShellOutcome::Compact => {
+                    prompt = "/compact".to_string();
+                }
+                NativeShellOutcome::Mcp(command) => {
+                    match execute_mcp_shell_command((!mcp.is_empty()).then_some(&mcp), &command)
+                        .await?
+                    {
+                        McpShellReply::Prompt(message) => prompt = message,
+                        McpShellReply::Listing(response) => {
+                            results.push(TurnResult {
+                                prompt,
+                                response,
+                                routing,
+                                debug: TurnDebugOutput::default(),
+                            });
+                            continue;
+                        }
+                    }
+                }
                 NativeShellOutcome::Run(command) => {
                     let tool_response = execute_batch_run_command(
                         &command,
@@ -614,6 +652,7 @@ async fn main() -> Result<(), HarperError> {
             debug,
         });
     }
+    chat_service.end_session(&session_id).await;
 
     if args.json {
         println!(
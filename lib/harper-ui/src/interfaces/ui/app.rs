@@ -16,7 +16,9 @@ use harper_core::core::plan::{PlanLoopOutcome, PlanLoopStage};
 use harper_core::core::Message;
 use harper_core::memory::session_service::GlobalStats;
 use harper_core::ResolvedAgents;
-use harper_core::{ApprovalProfile, AuthSession, ExecutionStrategy, PlanState, SandboxProfile};
+use harper_core::{
+    ApprovalProfile, AuthSession, ExecutionStrategy, PlanState, SandboxProfile, UsageTotals,
+};
 use ratatui::layout::Rect;
 use ratatui::text::Line;
 use serde::Deserialize;
@@ -119,6 +121,8 @@ pub struct ChatState {
     pub messages_area: Cell<Option<Rect>>,
     pub command_output_area: Cell<Option<Rect>>,
     pub command_output_selection: Option<LineSelection>,
+    pub streaming_response: Option<StreamingResponseState>,
+    pub usage: UsageTotals,
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -137,6 +141,12 @@ impl LineSelection {
     }
 }
 
+#[derive(Clone, Default)]
+pub struct StreamingResponseState {
+    pub content: String,
+    pub done: bool,
+}
+
 #[derive(Clone)]
 pub struct CommandOutputState {
     pub command: String,
@@ -552,6 +562,7 @@ pub enum HeaderWidget {
     Auth,
     Focus,
     Model,
+    Usage,
     Cwd,
     Strategy,
     Approval,
@@ -587,6 +598,8 @@ pub struct TuiApp {
     pub header_widgets: Vec<HeaderWidget>,
     pub update_status: Option<String>,
     pub homebrew_path_fix: Option<PathBuf>,
+    /// URIs of the connected MCP servers' resources, for `@mcp:` completion
+    pub mcp_resources: Vec<String>,
     pub show_menu_logo: bool,
     pub mouse_capture: bool,
     pub drag_scroll: Option<DragScrollState>,
@@ -644,6 +657,7 @@ impl Default for TuiApp {
                 HeaderWidget::Auth,
                 HeaderWidget::Focus,
                 HeaderWidget::Model,
+                HeaderWidget::Usage,
                 HeaderWidget::Cwd,
                 HeaderWidget::Strategy,
                 HeaderWidget::Approval,
@@ -652,6 +666,7 @@ impl Default for TuiApp {
             ],
             update_status: None,
             homebrew_path_fix: None,
+            mcp_resources: Vec::new(),
             show_menu_logo: true,
             mouse_capture: false,
             drag_scroll: None,
@@ -1059,10 +1074,7 @@ mod tests {
         let mut app = TuiApp::new();
         app.state = AppState::Chat(Box::new(ChatState {
             session_id: "session".to_string(),
-            messages: vec![Message {
-                role: "user".to_string(),
-                content: "hello".to_string(),
-            }],
+            messages: vec![Message::text("user", "hello")],
             awaiting_response: false,
             active_plan: None,
             active_agents: None,
@@ -1095,6 +1107,8 @@ mod tests {
             messages_area: Cell::new(None),
             command_output_area: Cell::new(None),
             command_output_selection: None,
+            streaming_response: None,
+            usage: Default::default(),
         }));
 
         app.next();
@@ -137,6 +137,8 @@ pub(crate) fn create_chat_state(
         messages_area: Cell::new(None),
         command_output_area: Cell::new(None),
         command_output_selection: None,
+        streaming_response: None,
+        usage: Default::default(),
     };
     chat_state.refresh_review_state();
     chat_state.follow_latest_messages();
@@ -185,6 +187,14 @@ fn parse_command_list(input: &str) -> Vec<String> {
         .collect()
 }
 
+/// A command pattern that spans several commands, which the policy checks per segment
+fn chained_command_pattern(values: &[String]) -> Option<&str> {
+    values
+        .iter()
+        .find(|value| value.contains('|') || value.contains("&&") || value.contains(';'))
+        .map(String::as_str)
+}
+
 fn start_execution_policy_editor(app: &mut TuiApp, field: ExecutionPolicyListField) {
     let input = match field {
         ExecutionPolicyListField::HeaderWidgets => {
@@ -458,6 +468,16 @@ pub fn handle_event(
                     }
                     KeyCode::Enter => {
                         let values = parse_command_list(&editor.input);
+                        if editor.field != ExecutionPolicyListField::HeaderWidgets {
+                            if let Some(pattern) = chained_command_pattern(&values) {
+                                let message = format!(
+                                    "'{}' chains commands; add one pattern per command instead",
+                                    pattern
+                                );
+                                app.set_error_message(message);
+                                return EventResult::Continue;
+                            }
+                        }
                         match editor.field {
                             ExecutionPolicyListField::HeaderWidgets => {
                                 app.header_widgets = settings::parse_header_widgets(&values);
@@ -1250,13 +1270,15 @@ fn handle_enter(app: &mut TuiApp, session_service: &SessionService) -> EventResu
         AppState::ViewSession(session_id, _, _) => {
             match session_service.load_session_state_view(session_id) {
                 Ok(session_view) => {
-                    app.state = AppState::Chat(Box::new(create_chat_state(
+                    let mut chat_state = create_chat_state(
                         session_view.session_id,
                         session_view.messages,
                         session_view.plan,
                         session_view.agents,
                         app.agents_context_enabled,
-                    )));
+                    );
+                    chat_state.usage = session_view.usage;
+                    app.state = AppState::Chat(Box::new(chat_state));
                     return EventResult::GatherSidebarEntries;
                 }
                 Err(e) => app.set_error_message(format!("Error loading session: {}", e)),
@@ -1804,6 +1826,9 @@ fn slash_command_candidates(input: &str) -> Vec<String> {
         "/session open",
         "/history show",
         "/history list",
+        "/mcp resources",
+        "/mcp prompts",
+        "/mcp prompt",
         "/config show",
         "/config set approval",
         "/config set strategy",
@@ -1880,7 +1905,26 @@ fn handle_completion_up(app: &mut TuiApp) -> bool {
 
 fn handle_tab(app: &mut TuiApp) {
     if let AppState::Chat(chat_state) = &mut app.state {
-        if chat_state.input.starts_with('@') {
+        if chat_state.input.starts_with("@mcp:") {
+            let is_showing_candidate = chat_state.completion_candidates.contains(&chat_state.input);
+            if !is_showing_candidate {
+                let prefix = &chat_state.input["@mcp:".len()..];
+                chat_state.completion_candidates = app
+                    .mcp_resources
+                    .iter()
+                    .filter(|uri| uri.starts_with(prefix))
+                    .map(|uri| format!("@mcp:{}", uri))
+                    .collect();
+                chat_state.completion_candidates.sort();
+                chat_state.completion_index = 0;
+            }
+            if !chat_state.completion_candidates.is_empty() {
+                let next_index = current_completion_index(chat_state)
+                    .map(|index| (index + 1) % chat_state.completion_candidates.len())
+                    .unwrap_or(0);
+                select_completion_candidate(chat_state, next_index);
+            }
+        } else if chat_state.input.starts_with('@') {
             let prefix = &chat_state.input[1..];
             let path = Path::new(prefix);
 
@@ -2571,6 +2615,43 @@ mod tests {
         assert!(app.execution_policy_editor.is_none());
     }
 
+    #[test]
+    fn test_execution_policy_editor_keeps_argument_patterns_and_rejects_chains() {
+        let mut app = TuiApp::new();
+        app.state = AppState::ExecutionPolicy(6);
+        app.execution_policy_editor = Some(super::ExecutionPolicyEditorState {
+            field: super::ExecutionPolicyListField::BlockedCommands,
+            input: "git push --force*, rm -rf *".to_string(),
+            selected_index: 0,
+            text_input_focused: false,
+        });
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        harper_core::memory::storage::init_db(&conn).unwrap();
+        let session_service = SessionService::new(&conn);
+
+        handle_event(
+            Event::Key(KeyCode::Enter.into()),
+            &mut app,
+            &session_service,
+        );
+        assert_eq!(app.blocked_commands, vec!["git push --force*", "rm -rf *"]);
+        assert!(app.execution_policy_editor.is_none());
+
+        app.execution_policy_editor = Some(super::ExecutionPolicyEditorState {
+            field: super::ExecutionPolicyListField::BlockedCommands,
+            input: "curl * | sh".to_string(),
+            selected_index: 0,
+            text_input_focused: false,
+        });
+        handle_event(
+            Event::Key(KeyCode::Enter.into()),
+            &mut app,
+            &session_service,
+        );
+        assert_eq!(app.blocked_commands, vec!["git push --force*", "rm -rf *"]);
+        assert!(app.execution_policy_editor.is_some());
+    }
+
     #[test]
     fn test_enter_execution_policy_retry_attempts_cycles_value() {
         let mut app = TuiApp::new();
@@ -3124,6 +3205,39 @@ mod tests {
             .completion_candidates
             .iter()
             .any(|candidate| candidate == "/auth login"));
+
+        chat_state.input = "/mcp p".to_string();
+        refresh_chat_completions(&mut chat_state);
+        assert!(chat_state
+            .completion_candidates
+            .iter()
+            .any(|candidate| candidate == "/mcp prompts"));
+    }
+
+    #[test]
+    fn tab_completes_mcp_resource_uris() {
+        let mut app = TuiApp::new();
+        app.mcp_resources = vec![
+            "harper://sessions/b".to_string(),
+            "harper://sessions/a".to_string(),
+            "file:///notes.md".to_string(),
+        ];
+        let mut chat_state = create_chat_state("session".to_string(), vec![], None, None, true);
+        chat_state.input = "@mcp:harper://".to_string();
+        app.state = AppState::Chat(Box::new(chat_state));
+
+        handle_tab(&mut app);
+        let AppState::Chat(chat_state) = &app.state else {
+            panic!("expected chat state");
+        };
+        assert_eq!(chat_state.input, "@mcp:harper://sessions/a");
+
+        handle_tab(&mut app);
+        let AppState::Chat(chat_state) = &app.state else {
+            panic!("expected chat state");
+        };
+        assert_eq!(chat_state.input, "@mcp:harper://sessions/b");
+        assert_eq!(chat_state.completion_candidates.len(), 2);
     }
 
     #[test]
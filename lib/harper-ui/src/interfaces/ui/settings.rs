@@ -296,6 +296,7 @@ pub fn header_widget_name(widget: HeaderWidget) -> &'static str {
         HeaderWidget::Auth => "auth",
         HeaderWidget::Focus => "focus",
         HeaderWidget::Model => "model",
+        HeaderWidget::Usage => "usage",
         HeaderWidget::Cwd => "cwd",
         HeaderWidget::Strategy => "strategy",
         HeaderWidget::Approval => "approval",
@@ -317,6 +318,7 @@ pub fn available_header_widgets() -> &'static [HeaderWidget] {
         HeaderWidget::Auth,
         HeaderWidget::Focus,
         HeaderWidget::Model,
+        HeaderWidget::Usage,
         HeaderWidget::Cwd,
         HeaderWidget::Strategy,
         HeaderWidget::Approval,
@@ -336,6 +338,7 @@ pub fn parse_header_widgets(values: &[String]) -> Vec<HeaderWidget> {
             "auth" => Some(HeaderWidget::Auth),
             "focus" => Some(HeaderWidget::Focus),
             "model" => Some(HeaderWidget::Model),
+            "usage" => Some(HeaderWidget::Usage),
             "cwd" => Some(HeaderWidget::Cwd),
             "strategy" => Some(HeaderWidget::Strategy),
             "approval" => Some(HeaderWidget::Approval),
@@ -27,7 +27,9 @@ use crossterm::{cursor, execute};
 use ratatui::backend::CrosstermBackend;
 use ratatui::Terminal;
 
-use super::app::{AppState, ApprovalState, ChatState, CommandOutputState, TuiApp};
+use super::app::{
+    AppState, ApprovalState, ChatState, CommandOutputState, StreamingResponseState, TuiApp,
+};
 use super::auth;
 use super::events::{self, EventResult};
 use super::settings;
@@ -38,9 +40,13 @@ use harper_core::core::io_traits::{RuntimeEventSink, UserApproval};
 use harper_core::core::plan::{PlanLoopOutcome, PlanLoopStage};
 use harper_core::core::ApiConfig;
 use harper_core::memory::session_service::SessionService;
-use harper_core::runtime::config::{ExecPolicyConfig, UiConfig};
+use harper_core::runtime::config::{ExecPolicyConfig, FirmwareConfig, McpConfig, UiConfig};
+use harper_core::tools::firmware::FirmwareDevices;
+use harper_core::tools::registry::ToolRegistry;
 use harper_core::ExecutionStrategy;
-use harper_core::{PlanState, ResolvedAgents, SessionStateView};
+use harper_core::{
+    McpRegistry, McpShellCommand, McpShellReply, PlanState, ResolvedAgents, SessionStateView,
+};
 use rusqlite::Connection;
 
 use async_trait::async_trait;
@@ -62,6 +68,10 @@ pub struct TuiRuntimeEvents {
 pub struct TuiRunOptions {
     pub custom_commands: HashMap<String, String>,
     pub server_base_url: Option<String>,
+    pub response_cache: harper_core::core::cache::ResponseCacheConfig,
+    pub mcp: McpConfig,
+    pub firmware: FirmwareConfig,
+    pub tools: ToolRegistry,
 }
 
 #[async_trait]
@@ -164,6 +174,27 @@ impl RuntimeEventSink for TuiRuntimeEvents {
             })?;
         Ok(())
     }
+
+    async fn assistant_output_updated(
+        &self,
+        session_id: &str,
+        chunk: String,
+        done: bool,
+    ) -> HarperResult<()> {
+        self.ui_tx
+            .send(UiUpdate::AssistantOutputUpdated {
+                session_id: session_id.to_string(),
+                chunk,
+                done,
+            })
+            .await
+            .map_err(|_| {
+                harper_core::core::error::HarperError::Command(
+                    "Failed to send runtime assistant output update".to_string(),
+                )
+            })?;
+        Ok(())
+    }
 }
 
 /// Messages sent to the background chat worker
@@ -183,6 +214,16 @@ enum WorkerMsg {
         command: String,
         session_id: String,
     },
+    Mcp {
+        command: McpShellCommand,
+        session_id: String,
+    },
+    /// A connected MCP server reported its resources changed
+    McpResourcesChanged,
+    /// A local session was deleted and should release what it holds
+    SessionEnded {
+        session_id: String,
+    },
 }
 
 /// Messages sent from the background chat worker to the UI
@@ -199,6 +240,11 @@ enum UiUpdate {
         is_error: bool,
         done: bool,
     },
+    AssistantOutputUpdated {
+        session_id: String,
+        chunk: String,
+        done: bool,
+    },
     PlanUpdated {
         session_id: String,
         active_plan: Option<PlanState>,
@@ -218,9 +264,42 @@ enum UiUpdate {
     HomebrewPathFixApplied {
         result: Result<String, String>,
     },
+    McpResources(Vec<String>),
+    McpListing(String),
+    McpPrompt {
+        session_id: String,
+        prompt: String,
+    },
     Error(String),
 }
 
+/// Show a sent message right away and wait for the worker's reply
+fn push_pending_user_message(chat_state: &mut ChatState, content: &str) {
+    chat_state
+        .messages
+        .push(harper_core::core::Message::text("user", content));
+    chat_state.follow_latest_messages();
+    chat_state.awaiting_response = true;
+    chat_state.command_output = None;
+    chat_state.command_output_expanded = false;
+    chat_state.command_output_scroll = 0;
+    chat_state.command_output_selection = None;
+}
+
+/// Refresh the resource URIs offered for `@mcp:` completion
+async fn send_mcp_resources(mcp: &McpRegistry, ui_tx: &mpsc::Sender<UiUpdate>) {
+    if mcp.is_empty() {
+        return;
+    }
+    let uris = mcp
+        .list_resources()
+        .await
+        .into_iter()
+        .map(|entry| entry.resource.uri)
+        .collect();
+    let _ = ui_tx.send(UiUpdate::McpResources(uris)).await;
+}
+
 /// Helper function to spawn async sidebar gathering task
 fn spawn_sidebar_gathering(chat_state: &ChatState, ui_tx: &mpsc::Sender<UiUpdate>) {
     let messages = chat_state.messages.clone();
@@ -414,6 +493,7 @@ fn parse_update_command(input: &str) -> Option<UpdateCommand> {
 fn build_native_shell_context(
     app: &TuiApp,
     api_config: &ApiConfig,
+    tools: &ToolRegistry,
     exec_policy: &ExecPolicyConfig,
     db_path: Option<&str>,
 ) -> harper_core::NativeShellContext {
@@ -439,6 +519,8 @@ fn build_native_shell_context(
             approval: format!("{:?}", exec_policy.effective_approval_profile()),
             strategy: format!("{:?}", exec_policy.effective_execution_strategy()),
             sandbox: format!("{:?}", exec_policy.effective_sandbox_profile()),
+            tools: tools.effective_tools(),
+            tool_filters: tools.describe_filters(),
         }),
     }
 }
@@ -598,7 +680,9 @@ pub async fn run_tui(
 
     // Clone data for worker
     let worker_api_config = api_config.clone();
+    let worker_tools = options.tools.clone();
     let worker_custom_commands = options.custom_commands.clone();
+    let worker_response_cache = options.response_cache;
     let worker_exec_policy = Arc::new(Mutex::new(exec_policy.clone()));
     let ui_exec_policy = worker_exec_policy.clone();
     let db_path = conn
@@ -606,17 +690,16 @@ pub async fn run_tui(
         .and_then(|p| Path::new(p).to_str().map(|s| s.to_string()));
     let db_path_for_shell = db_path.clone();
 
-    // Wrap MCP client in Arc if present
-    // Note: This requires McpClient to be thread-safe (Send + Sync)
-    // We assume McpClient is Arc-cloneable or we wrap it.
-    // For now, if McpClient is not cloneable, we'd need a proxy.
-    // Let's assume the user can provide an Arc or we wrap it here if we had access to the type.
-    // Since we only have Option<&McpClient>, we can't easily Arc it for the worker thread.
-    // ARCHITECTURAL NOTE: To support MCP in TUI worker, McpClient should be passed as Arc<McpClient>.
+    // The worker connects to the MCP servers itself, so their transports live
+    // on the runtime that uses them
+    let worker_mcp_config = options.mcp.clone();
+    let worker_firmware_config = options.firmware.clone();
 
     // Spawn background worker in a separate thread to handle non-Send Connection
     let ui_tx_clone = ui_tx.clone();
     let worker_approval_tx = approval_tx.clone();
+    // Weak so the worker's own sender does not keep its loop alive
+    let worker_self_tx = worker_tx.downgrade();
     std::thread::spawn(move || {
         let rt = tokio::runtime::Builder::new_current_thread()
             .enable_all()
@@ -631,13 +714,30 @@ pub async fn run_tui(
                 Connection::open_in_memory().expect("Worker failed to open in-memory database")
             };
 
-            let mut api_cache = harper_core::core::cache::new_api_cache();
+            let mut api_cache = worker_response_cache
+                .enabled
+                .then(|| harper_core::core::cache::ResponseCache::new(&worker_response_cache));
             let approver = Arc::new(TuiApproval {
                 approval_tx: worker_approval_tx,
             });
             let runtime_events = Arc::new(TuiRuntimeEvents {
                 ui_tx: ui_tx_clone.clone(),
             });
+            let mcp = McpRegistry::connect(&worker_mcp_config).await;
+            send_mcp_resources(&mcp, &ui_tx_clone).await;
+            let mut resource_changes = mcp.resource_changes();
+            tokio::spawn(async move {
+                while !matches!(
+                    resource_changes.recv().await,
+                    Err(tokio::sync::broadcast::error::RecvError::Closed)
+                ) {
+                    let Some(tx) = worker_self_tx.upgrade() else {
+                        break;
+                    };
+                    let _ = tx.send(WorkerMsg::McpResourcesChanged).await;
+                }
+            });
+            let firmware = Arc::new(FirmwareDevices::from_config(&worker_firmware_config));
 
             while let Some(msg) = worker_rx.recv().await {
                 match msg {
@@ -650,8 +750,8 @@ pub async fn run_tui(
                         let mut chat_service = ChatService::new(
                             &worker_conn,
                             &worker_api_config,
-                            None, // TODO: Support MCP in worker thread
-                            Some(&mut api_cache),
+                            (!mcp.is_empty()).then_some(&mcp),
+                            api_cache.as_mut(),
                             None,
                             worker_custom_commands.clone(),
                             worker_exec_policy
@@ -659,13 +759,17 @@ pub async fn run_tui(
                                 .expect("worker exec policy lock")
                                 .clone(),
                         )
+                        .with_tools(worker_tools.clone())
                         .with_approver(approver.clone())
-                        .with_runtime_events(runtime_events.clone());
+                        .with_runtime_events(runtime_events.clone())
+                        .with_firmware(firmware.clone());
 
                         // Load existing history
-                        let mut history =
-                            harper_core::memory::storage::load_history(&worker_conn, &session_id)
-                                .unwrap_or_default();
+                        let mut history = harper_core::memory::storage::load_context_history(
+                            &worker_conn,
+                            &session_id,
+                        )
+                        .unwrap_or_default();
 
                         if let Some(user_id) = auth_user_id.as_deref() {
                             let _ = harper_core::memory::storage::save_session_for_user(
@@ -694,6 +798,7 @@ pub async fn run_tui(
                                             agents: None,
                                             agents_rendered: None,
                                             agents_effective_rendered: None,
+                                            usage: Default::default(),
                                         }),
                                     None => session_service
                                         .load_session_state_view(&session_id)
@@ -705,6 +810,7 @@ pub async fn run_tui(
                                             agents: None,
                                             agents_rendered: None,
                                             agents_effective_rendered: None,
+                                            usage: Default::default(),
                                         }),
                                 };
                                 let _ = ui_tx_clone
@@ -716,6 +822,34 @@ pub async fn run_tui(
                             }
                         }
                     }
+                    WorkerMsg::Mcp {
+                        command,
+                        session_id,
+                    } => {
+                        let update = match harper_core::execute_mcp_shell_command(
+                            (!mcp.is_empty()).then_some(&mcp),
+                            &command,
+                        )
+                        .await
+                        {
+                            Ok(McpShellReply::Listing(listing)) => UiUpdate::McpListing(listing),
+                            Ok(McpShellReply::Prompt(prompt)) => {
+                                UiUpdate::McpPrompt { session_id, prompt }
+                            }
+                            Err(err) => UiUpdate::Error(err.to_string()),
+                        };
+                        let _ = ui_tx_clone.send(update).await;
+                        if command == McpShellCommand::Resources {
+                            send_mcp_resources(&mcp, &ui_tx_clone).await;
+                        }
+                    }
+                    WorkerMsg::McpResourcesChanged => {
+                        mcp.subscribe_resources().await;
+                        send_mcp_resources(&mcp, &ui_tx_clone).await;
+                    }
+                    WorkerMsg::SessionEnded { session_id } => {
+                        firmware.release_session(Some(&session_id)).await;
+                    }
                     WorkerMsg::RetryPlanCommand {
                         command,
                         session_id,
@@ -797,6 +931,7 @@ pub async fn run_tui(
                                             agents: None,
                                             agents_rendered: None,
                                             agents_effective_rendered: None,
+                                            usage: Default::default(),
                                         }),
                                     None => session_service
                                         .load_session_state_view(&session_id)
@@ -812,6 +947,7 @@ pub async fn run_tui(
                                             agents: None,
                                             agents_rendered: None,
                                             agents_effective_rendered: None,
+                                            usage: Default::default(),
                                         }),
                                 };
                                 let _ = ui_tx_clone
@@ -825,6 +961,7 @@ pub async fn run_tui(
                     }
                 }
             }
+            firmware.release_all().await;
         });
     });
 
@@ -855,6 +992,7 @@ pub async fn run_tui(
                             let shell_context = build_native_shell_context(
                                 &app,
                                 api_config,
+                                &options.tools,
                                 exec_policy,
                                 db_path_for_shell.as_deref(),
                             );
@@ -876,6 +1014,17 @@ pub async fn run_tui(
                                             Ok(harper_core::NativeShellOutcome::Ask(prompt)) => {
                                                 msg = prompt;
                                             }
+                                            Ok(harper_core::NativeShellOutcome::Compact) => {
+                                                msg = "/compact".to_string();
+                                            }
+                                            Ok(harper_core::NativeShellOutcome::Mcp(command)) => {
+                                                app.set_activity_status(Some("mcp".to_string()));
+                                                let _ = worker_tx.send(WorkerMsg::Mcp {
+                                                    command,
+                                                    session_id,
+                                                }).await;
+                                                continue;
+                                            }
                                             Ok(harper_core::NativeShellOutcome::Run(command)) => {
                                                 chat_state.command_output = None;
                                                 chat_state.command_output_expanded = false;
@@ -1084,16 +1233,7 @@ pub async fn run_tui(
                                 let web_search = chat_state.web_search_enabled;
 
                                 // optimistic update
-                                chat_state.messages.push(harper_core::core::Message {
-                                    role: "user".to_string(),
-                                    content: msg.clone(),
-                                });
-                                chat_state.follow_latest_messages();
-                                chat_state.awaiting_response = true;
-                                chat_state.command_output = None;
-                                chat_state.command_output_expanded = false;
-                                chat_state.command_output_scroll = 0;
-                                chat_state.command_output_selection = None;
+                                push_pending_user_message(chat_state, &msg);
                                 app.set_activity_status(Some("thinking".to_string()));
 
                                 let _ = worker_tx.send(WorkerMsg::SendMessage {
@@ -1271,6 +1411,11 @@ pub async fn run_tui(
                                 match delete_result {
                                     Ok(true) => {
                                         app.set_status_message("Local session deleted".to_string());
+                                        let _ = worker_tx
+                                            .send(WorkerMsg::SessionEnded {
+                                                session_id: session_id.clone(),
+                                            })
+                                            .await;
                                         let sessions_result = if export_view {
                                             if let Some(auth_session) = app.auth_session.as_ref() {
                                                 session_service.list_sessions_data_for_user(
@@ -1583,8 +1728,10 @@ pub async fn run_tui(
                                     chat_state.messages = session_view.messages;
                                     chat_state.follow_latest_messages();
                                     chat_state.awaiting_response = false;
+                                    chat_state.streaming_response = None;
                                     chat_state.active_plan = session_view.plan;
                                     chat_state.active_agents = session_view.agents;
+                                    chat_state.usage = session_view.usage;
                                     chat_state.refresh_plan_state();
                                     chat_state.refresh_review_state();
                                     if chat_state.active_plan.is_none() {
@@ -1704,6 +1851,25 @@ pub async fn run_tui(
                                 }
                             }
                         }
+                        UiUpdate::AssistantOutputUpdated {
+                            session_id,
+                            chunk,
+                            done,
+                        } => {
+                            if let AppState::Chat(chat_state) = &mut app.state {
+                                if chat_state.session_id == session_id {
+                                    let state = chat_state
+                                        .streaming_response
+                                        .get_or_insert_with(StreamingResponseState::default);
+                                    if state.done && !chunk.is_empty() {
+                                        // A new model reply started after a tool round.
+                                        *state = StreamingResponseState::default();
+                                    }
+                                    state.content.push_str(&chunk);
+                                    state.done = done;
+                                }
+                            }
+                        }
                         UiUpdate::PlanUpdated { session_id, active_plan } => {
                             if let AppState::Chat(chat_state) = &mut app.state {
                                 if chat_state.session_id == session_id {
@@ -1727,6 +1893,32 @@ pub async fn run_tui(
                                 chat_state.sidebar_sections = sections;
                             }
                         }
+                        UiUpdate::McpResources(uris) => {
+                            app.mcp_resources = uris;
+                        }
+                        UiUpdate::McpListing(listing) => {
+                            app.set_activity_status(None);
+                            display_command_info(&mut app, listing);
+                        }
+                        UiUpdate::McpPrompt { session_id, prompt } => {
+                            let auth_user_id = app
+                                .auth_session
+                                .as_ref()
+                                .map(|session| session.user.user_id.clone());
+                            if let AppState::Chat(chat_state) = &mut app.state {
+                                if chat_state.session_id == session_id {
+                                    let web_search = chat_state.web_search_enabled;
+                                    push_pending_user_message(chat_state, &prompt);
+                                    app.set_activity_status(Some("thinking".to_string()));
+                                    let _ = worker_tx.send(WorkerMsg::SendMessage {
+                                        user_msg: prompt,
+                                        session_id,
+                                        web_search,
+                                        auth_user_id,
+                                    }).await;
+                                }
+                            }
+                        }
                         UiUpdate::UpdateStatus {
                             status,
                             homebrew_path_fix,
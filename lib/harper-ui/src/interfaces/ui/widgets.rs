@@ -27,7 +27,7 @@ use super::theme::Theme;
 use harper_core::core::plan::{
     PlanFollowup, PlanJobRecord, PlanJobStatus, PlanLoopOutcome, PlanLoopStage,
 };
-use harper_core::{PlanRuntime, PlanState, PlanStepStatus, ResolvedAgents};
+use harper_core::{PlanRuntime, PlanState, PlanStepStatus, ResolvedAgents, UsageTotals};
 
 const MAX_COMPLETION_POPUP_HEIGHT: u16 = 12;
 const MENU_BLOCK_VERTICAL_OVERHEAD: u16 = 3;
@@ -485,6 +485,18 @@ pub fn draw(frame: &mut Frame, app: &TuiApp, theme: &Theme) {
                         .fg(theme.title)
                         .add_modifier(Modifier::BOLD),
                 )]));
+                if let Some(streamed) = chat_state
+                    .streaming_response
+                    .as_ref()
+                    .filter(|streamed| !streamed.content.is_empty())
+                {
+                    for line in streamed.content.lines() {
+                        message_lines.push(Line::from(vec![Span::styled(
+                            line.to_string(),
+                            Style::default().fg(theme.output),
+                        )]));
+                    }
+                }
                 message_lines.push(Line::from(vec![
                     Span::styled(
                         activity_spinner_frame(app),
@@ -1230,6 +1242,7 @@ fn append_rendered_message_lines(
     let label = match msg.role.as_str() {
         "user" => "User ›",
         "assistant" => "Harper ›",
+        harper_core::SUMMARY_ROLE => "Summary ›",
         _ => "System ›",
     };
     let label_style = match msg.role.as_str() {
@@ -1348,6 +1361,7 @@ fn draw_chat_summary(
     } else {
         Some(format!("model: {}", app.model_label))
     };
+    let usage_status = usage_header_status(&chat_state.usage);
     let cwd_status = if app.current_working_dir.is_empty() {
         None
     } else {
@@ -1419,6 +1433,12 @@ fn draw_chat_summary(
             ));
         }
     }
+    if header_widget_enabled(app, super::app::HeaderWidget::Usage) {
+        if let Some(status) = usage_status {
+            push_header_separator(&mut spans, theme);
+            spans.push(Span::styled(status, theme.muted_style()));
+        }
+    }
     if header_widget_enabled(app, super::app::HeaderWidget::Cwd) {
         if let Some(status) = cwd_status {
             push_header_separator(&mut spans, theme);
@@ -1509,6 +1529,29 @@ fn draw_chat_summary(
     frame.render_widget(widget, area);
 }
 
+fn format_token_count(tokens: u64) -> String {
+    if tokens >= 1_000_000 {
+        format!("{:.1}M", tokens as f64 / 1_000_000.0)
+    } else if tokens >= 1_000 {
+        format!("{:.1}k", tokens as f64 / 1_000.0)
+    } else {
+        tokens.to_string()
+    }
+}
+
+/// Token count and estimated cost of the session, once a call has been recorded
+fn usage_header_status(usage: &UsageTotals) -> Option<String> {
+    if usage.calls == 0 {
+        return None;
+    }
+    let tokens = format_token_count(usage.total_tokens());
+    if usage.estimated_cost > 0.0 {
+        Some(format!("tokens: {} ${:.2}", tokens, usage.estimated_cost))
+    } else {
+        Some(format!("tokens: {}", tokens))
+    }
+}
+
 fn truncate_chat_summary(value: &str, max_len: usize) -> String {
     if value.len() <= max_len {
         value.to_string()
@@ -2663,6 +2706,41 @@ fn draw_stats(
                     .add_modifier(Modifier::BOLD),
             ),
         ]),
+        Line::from(vec![
+            Span::styled("Cache Hits       ", theme.muted_style()),
+            Span::styled(
+                format!(
+                    "{} / {}",
+                    stats.cache_hits,
+                    stats.cache_hits + stats.cache_misses
+                ),
+                Style::default()
+                    .fg(theme.accent)
+                    .add_modifier(Modifier::BOLD),
+            ),
+        ]),
+        Line::from(vec![
+            Span::styled("Tokens Used      ", theme.muted_style()),
+            Span::styled(
+                format!(
+                    "{} in / {} out",
+                    format_token_count(stats.usage.prompt_tokens),
+                    format_token_count(stats.usage.completion_tokens)
+                ),
+                Style::default()
+                    .fg(theme.accent)
+                    .add_modifier(Modifier::BOLD),
+            ),
+        ]),
+        Line::from(vec![
+            Span::styled("Estimated Cost   ", theme.muted_style()),
+            Span::styled(
+                format!("${:.2}", stats.usage.estimated_cost),
+                Style::default()
+                    .fg(theme.accent)
+                    .add_modifier(Modifier::BOLD),
+            ),
+        ]),
     ];
 
     let compact = compact_layout(area);
@@ -3439,7 +3517,7 @@ fn draw_execution_policy(
         .as_ref()
         .map(|editor| editor.field)
     {
-        Some(super::app::ExecutionPolicyListField::HeaderWidgets) => 18,
+        Some(super::app::ExecutionPolicyListField::HeaderWidgets) => 19,
         Some(_) => 4,
         None => 0,
     };
@@ -3561,7 +3639,14 @@ fn draw_execution_policy(
                 );
             }
             super::app::ExecutionPolicyListField::AllowedCommands => {
-                let editor_widget = Paragraph::new(editor.input.as_str()).block(
+                let editor_widget = Paragraph::new(vec![
+                    Line::from(editor.input.as_str()),
+                    Line::styled(
+                        "e.g. cargo test *, git status • each piped or && command is checked",
+                        theme.muted_style(),
+                    ),
+                ])
+                .block(
                     Block::default()
                         .title(" Allow ")
                         .padding(Padding::horizontal(1)),
@@ -3569,7 +3654,14 @@ fn draw_execution_policy(
                 frame.render_widget(editor_widget, chunks[2]);
             }
             super::app::ExecutionPolicyListField::BlockedCommands => {
-                let editor_widget = Paragraph::new(editor.input.as_str()).block(
+                let editor_widget = Paragraph::new(vec![
+                    Line::from(editor.input.as_str()),
+                    Line::styled(
+                        "e.g. git push --force*, rm -rf * • each piped or && command is checked",
+                        theme.muted_style(),
+                    ),
+                ])
+                .block(
                     Block::default()
                         .title(" Block ")
                         .padding(Padding::horizontal(1)),
@@ -3587,8 +3679,8 @@ fn execution_policy_context(selected: usize) -> &'static str {
         2 => "Cycles filesystem and network sandbox limits.",
         3 => "Cycles the retry limit for autonomous recovery.",
         4 => "Opens the header widget selector.",
-        5 => "Edits comma-separated commands allowed by policy.",
-        6 => "Edits comma-separated commands blocked by policy.",
+        5 => "Edits comma-separated command patterns that run without approval.",
+        6 => "Edits comma-separated command patterns that are always refused.",
         7 => "Writes the current policy settings to config/local.toml.",
         8 => "Checks for a newer release and refreshes the cached update status.",
         9 => "Moves the shadowing local binary aside and points it to Homebrew's Harper.",
@@ -4350,9 +4442,33 @@ mod tests {
             messages_area: Cell::new(None),
             command_output_area: Cell::new(None),
             command_output_selection: None,
+            streaming_response: None,
+            usage: UsageTotals::default(),
         }
     }
 
+    #[test]
+    fn usage_header_status_shows_tokens_and_priced_cost() {
+        assert_eq!(usage_header_status(&UsageTotals::default()), None);
+
+        let mut usage = UsageTotals {
+            calls: 2,
+            prompt_tokens: 11_800,
+            completion_tokens: 500,
+            estimated_cost: 0.0,
+        };
+        assert_eq!(
+            usage_header_status(&usage).as_deref(),
+            Some("tokens: 12.3k")
+        );
+
+        usage.estimated_cost = 0.041;
+        assert_eq!(
+            usage_header_status(&usage).as_deref(),
+            Some("tokens: 12.3k $0.04")
+        );
+    }
+
     #[test]
     fn draw_slash_completion_popup_fits_small_terminal() {
         let mut chat_state = empty_chat_state();
@@ -4572,10 +4688,9 @@ mod tests {
     #[test]
     fn draw_chat_compacts_crowded_terminal() {
         let mut chat_state = empty_chat_state();
-        chat_state.messages.push(Message {
-            role: "user".to_string(),
-            content: "Keep the main transcript visible.".to_string(),
-        });
+        chat_state
+            .messages
+            .push(Message::text("user", "Keep the main transcript visible."));
         chat_state.sidebar_visible = true;
         chat_state.sidebar_sections = vec![app::SidebarSection {
             title: "Commands".to_string(),
@@ -4633,10 +4748,9 @@ mod tests {
     #[test]
     fn chat_shows_only_agents_hint_until_opened() {
         let mut chat_state = empty_chat_state();
-        chat_state.messages.push(Message {
-            role: "user".to_string(),
-            content: "Keep chat space available.".to_string(),
-        });
+        chat_state
+            .messages
+            .push(Message::text("user", "Keep chat space available."));
         chat_state.active_agents = Some(ResolvedAgents {
             sources: vec![harper_core::core::agents::AgentsSource {
                 path: std::path::PathBuf::from("AGENTS.md"),
@@ -5261,14 +5375,11 @@ mod tests {
     #[test]
     fn latest_action_summary_does_not_echo_plain_assistant_reply() {
         let mut chat_state = empty_chat_state();
-        chat_state.messages.push(Message {
-            role: "user".to_string(),
-            content: "hi".to_string(),
-        });
-        chat_state.messages.push(Message {
-            role: "assistant".to_string(),
-            content: "Hello! How can I assist you today?".to_string(),
-        });
+        chat_state.messages.push(Message::text("user", "hi"));
+        chat_state.messages.push(Message::text(
+            "assistant",
+            "Hello! How can I assist you today?",
+        ));
 
         let app = app::TuiApp::default();
         assert!(latest_action_summary(&app, &chat_state).is_none());
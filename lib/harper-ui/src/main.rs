@@ -49,6 +49,10 @@ fn get_api_key(config: &HarperConfig) -> String {
         if let Ok(env_key) = std::env::var("SAMBASTUDIO_API_KEY") {
             api_key = env_key;
         }
+    } else if config.api.provider == "Anthropic" {
+        if let Ok(env_key) = std::env::var("ANTHROPIC_API_KEY") {
+            api_key = env_key;
+        }
     }
     if api_key == config.api.api_key && auth::is_placeholder_key(&api_key) {
         if let Some(keyring_key) = auth::load_keyring_key(&config.api.provider) {
@@ -92,6 +96,12 @@ async fn main() -> Result<(), HarperError> {
         api_key,
         base_url: config.api.base_url.clone(),
         model_name: config.api.model_name.clone(),
+        headers: config.api.headers.clone(),
+        parameters: config.api.model_parameters(&config.api.model_name),
+        fallback: config.api.fallback_chain().map_err(|e| {
+            eprintln!("Configuration error: {}", e);
+            e
+        })?,
     };
 
     // Ensure database directory exists
@@ -139,15 +149,19 @@ async fn main() -> Result<(), HarperError> {
 
         let conn_clone = conn.clone();
         let api_config_clone = api_config.clone();
+        let tools = config.tools.registry();
         let exec_policy_clone = exec_policy.clone();
         let supabase_auth_clone = config.auth.supabase.clone();
+        let response_cache = config.api.cache;
         server_task = Some(tokio::spawn(async move {
             if let Err(e) = harper_core::server::run_server(
                 &addr,
                 conn_clone,
                 api_config_clone,
+                tools,
                 exec_policy_clone,
                 supabase_auth_clone,
+                response_cache,
             )
             .await
             {
@@ -189,6 +203,10 @@ async fn main() -> Result<(), HarperError> {
         harper_ui::interfaces::ui::tui::TuiRunOptions {
             custom_commands,
             server_base_url,
+            response_cache: config.api.cache,
+            mcp: config.mcp.clone(),
+            firmware: config.firmware.clone(),
+            tools: config.tools.registry(),
         },
     )
     .await
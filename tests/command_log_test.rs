@@ -12,11 +12,12 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use harper_core::core::{ApiConfig, ApiProvider};
+use harper_core::core::{ApiConfig, ApiProvider, FallbackChain, ModelParameters};
 use harper_core::memory::storage::{self};
 use harper_core::runtime::config::ExecPolicyConfig;
 use harper_core::tools::shell::{self, CommandAuditContext};
 use rusqlite::Connection;
+use std::collections::HashMap;
 
 #[tokio::test]
 async fn test_command_logging() {
@@ -37,6 +38,9 @@ async fn test_command_logging() {
         api_key: "test-key".to_string(),
         base_url: "https://api.openai.com/v1".to_string(),
         model_name: "gpt-5.5".to_string(),
+        headers: HashMap::new(),
+        parameters: ModelParameters::default(),
+        fallback: FallbackChain::default(),
     };
 
     let exec_policy = ExecPolicyConfig {
@@ -44,6 +48,8 @@ async fn test_command_logging() {
         execution_strategy: None,
         allowed_commands: Some(vec!["echo".to_string()]),
         blocked_commands: None,
+        allow_command_chains: None,
+        allow_redirects_and_substitutions: None,
         sandbox_profile: None,
         sandbox: None,
         retry_max_attempts: None,
@@ -16,6 +16,7 @@ use harper_workspace::*;
 
 use regex::Regex;
 use rusqlite::Connection;
+use std::collections::HashMap;
 use std::thread;
 use tempfile::NamedTempFile;
 
@@ -341,6 +342,9 @@ fn test_api_config_creation() {
         api_key: "test-key".to_string(),
         base_url: "https://api.openai.com/v1/chat/completions".to_string(),
         model_name: "gpt-5.5".to_string(),
+        headers: HashMap::new(),
+        parameters: ModelParameters::default(),
+        fallback: FallbackChain::default(),
     };
 
     assert!(matches!(config.provider, ApiProvider::OpenAI));
@@ -350,10 +354,7 @@ fn test_api_config_creation() {
 
 #[test]
 fn test_message_creation() {
-    let message = Message {
-        role: "system".to_string(),
-        content: "You are a helpful assistant.".to_string(),
-    };
+    let message = Message::text("system", "You are a helpful assistant.");
 
     assert_eq!(message.role, "system");
     assert_eq!(message.content, "You are a helpful assistant.");
@@ -408,6 +409,9 @@ mod e2e_tests {
             api_key: "test-key".to_string(),
             base_url: "https://api.openai.com/v1/chat/completions".to_string(),
             model_name: "gpt-5.5".to_string(),
+            headers: HashMap::new(),
+            parameters: ModelParameters::default(),
+            fallback: FallbackChain::default(),
         };
 
         // Test session creation and message handling
@@ -863,6 +867,9 @@ Full stderr:
             api_key: "test-key".to_string(),
             base_url: "https://api.openai.com/v1/chat/completions".to_string(),
             model_name: "gpt-5.5".to_string(),
+            headers: HashMap::new(),
+            parameters: ModelParameters::default(),
+            fallback: FallbackChain::default(),
         };
 
         // Create exec policy
@@ -871,6 +878,8 @@ Full stderr:
             execution_strategy: None,
             allowed_commands: None,
             blocked_commands: None,
+            allow_command_chains: None,
+            allow_redirects_and_substitutions: None,
             sandbox_profile: None,
             sandbox: None,
             retry_max_attempts: None,